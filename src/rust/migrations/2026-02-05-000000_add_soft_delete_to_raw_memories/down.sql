@@ -0,0 +1,3 @@
+-- Remove soft-delete support from raw_memories
+DROP INDEX IF EXISTS idx_raw_memories_deleted_at;
+ALTER TABLE raw_memories DROP COLUMN IF EXISTS deleted_at;
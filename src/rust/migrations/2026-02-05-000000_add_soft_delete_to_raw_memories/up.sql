@@ -0,0 +1,12 @@
+-- DirSoul Migration: Soft-delete (tombstones) for raw_memories
+-- Soft-deleted memories are hidden from default queries and hard-deleted
+-- (cascading to event_memories) once the retention window has passed.
+
+ALTER TABLE raw_memories ADD COLUMN deleted_at TIMESTAMPTZ;
+
+-- Partial index for the purge job (only tombstoned rows)
+CREATE INDEX idx_raw_memories_deleted_at
+ON raw_memories (deleted_at)
+WHERE deleted_at IS NOT NULL;
+
+COMMENT ON COLUMN raw_memories.deleted_at IS 'Soft-delete tombstone; NULL for live memories';
@@ -0,0 +1,2 @@
+-- Remove event-entity links
+DROP TABLE IF EXISTS event_entity_links;
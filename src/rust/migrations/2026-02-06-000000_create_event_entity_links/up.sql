@@ -0,0 +1,17 @@
+-- DirSoul Migration: Event-entity links
+-- Records which entities an event mentions, so relation strength can count
+-- real co-occurrences instead of matching names inside event targets.
+-- Rows go away with either side (ON DELETE CASCADE).
+
+CREATE TABLE event_entity_links (
+    event_id UUID NOT NULL REFERENCES event_memories(event_id) ON DELETE CASCADE,
+    entity_id UUID NOT NULL REFERENCES entities(entity_id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (event_id, entity_id)
+);
+
+-- Co-occurrence queries look up links by user and entity
+CREATE INDEX idx_event_entity_links_user_entity ON event_entity_links (user_id, entity_id);
+
+COMMENT ON TABLE event_entity_links IS 'Entities mentioned by each event (Layer 1 → Layer 2 links)';
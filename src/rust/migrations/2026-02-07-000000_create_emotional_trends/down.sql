@@ -0,0 +1,2 @@
+-- Remove emotional trend history
+DROP TABLE IF EXISTS emotional_trends;
@@ -0,0 +1,15 @@
+-- DirSoul Migration: Emotional trend history
+-- One row per user per day holding the running mean of that day's
+-- emotional scores, so DeepTalk trends survive restarts and can be charted.
+-- Scores are normalized to [-1, 1]: -1 negative, 0 neutral, 1 positive.
+
+CREATE TABLE emotional_trends (
+    user_id TEXT NOT NULL,
+    day DATE NOT NULL,
+    score DOUBLE PRECISION NOT NULL CHECK (score BETWEEN -1 AND 1),
+    sample_count INTEGER NOT NULL DEFAULT 1 CHECK (sample_count > 0),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, day)
+);
+
+COMMENT ON TABLE emotional_trends IS 'Daily mean emotional score per user (DeepTalk emotional timeline)';
@@ -0,0 +1,2 @@
+-- Remove embedding model tracking from raw_memories
+ALTER TABLE raw_memories DROP COLUMN IF EXISTS embedding_model;
@@ -0,0 +1,7 @@
+-- DirSoul Migration: Track which embedding model produced each vector
+-- Vectors from different models live in different spaces; recording the
+-- model lets search detect drift after switching models.
+
+ALTER TABLE raw_memories ADD COLUMN embedding_model TEXT;
+
+COMMENT ON COLUMN raw_memories.embedding_model IS 'Embedding model that produced the vector; NULL for untracked vectors';
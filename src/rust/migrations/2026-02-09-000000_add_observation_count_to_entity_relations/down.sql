@@ -0,0 +1,2 @@
+-- Remove observation counts from entity_relations
+ALTER TABLE entity_relations DROP COLUMN IF EXISTS observation_count;
@@ -0,0 +1,10 @@
+-- DirSoul Migration: Separate observation count from relation strength
+-- `strength` is the graph weight (co-occurrence, 0-1); how often a relation
+-- was extracted is tracked on its own and drives confidence blending.
+
+ALTER TABLE entity_relations ADD COLUMN observation_count INTEGER NOT NULL DEFAULT 1;
+
+-- Until now save_relations counted observations in `strength`
+UPDATE entity_relations SET observation_count = GREATEST(ROUND(strength)::INTEGER, 1);
+
+COMMENT ON COLUMN entity_relations.observation_count IS 'Times the relation was extracted; strength is the graph weight';
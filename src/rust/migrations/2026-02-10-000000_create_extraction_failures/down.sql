@@ -0,0 +1,2 @@
+-- Remove the extraction failure queue
+DROP TABLE IF EXISTS extraction_failures;
@@ -0,0 +1,26 @@
+-- DirSoul Migration: Extraction failures (retry queue + dead letter)
+-- Raw memories whose event extraction failed wait here for a retry with
+-- backoff. After too many attempts they move to 'dead_letter' for manual
+-- review. Rows go away with the memory (ON DELETE CASCADE).
+
+CREATE TABLE extraction_failures (
+    failure_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    memory_id UUID NOT NULL UNIQUE REFERENCES raw_memories(memory_id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    last_error TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'dead_letter')),
+    next_retry_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- The retry job scans pending rows that are due
+CREATE INDEX idx_extraction_failures_due
+ON extraction_failures (next_retry_at)
+WHERE status = 'pending';
+
+CREATE INDEX idx_extraction_failures_user_status ON extraction_failures (user_id, status);
+
+COMMENT ON TABLE extraction_failures IS 'Failed event extractions awaiting retry or manual review';
+COMMENT ON COLUMN extraction_failures.next_retry_at IS 'When the next retry is due; NULL once dead-lettered';
@@ -0,0 +1,3 @@
+-- Remove metadata from event_memories
+DROP INDEX IF EXISTS idx_event_memories_metadata_gin;
+ALTER TABLE event_memories DROP COLUMN IF EXISTS metadata;
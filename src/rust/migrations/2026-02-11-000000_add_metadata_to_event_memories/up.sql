@@ -0,0 +1,10 @@
+-- DirSoul Migration: Free-form metadata on event memories
+-- Extractors attach signals the typed columns do not cover, such as the
+-- negation flag, sentiment and the source span of the event.
+
+ALTER TABLE event_memories ADD COLUMN metadata JSONB;
+
+-- Index for metadata queries (JSONB GIN index)
+CREATE INDEX idx_event_memories_metadata_gin ON event_memories USING GIN (metadata);
+
+COMMENT ON COLUMN event_memories.metadata IS 'Extractor signals (negated, sentiment, raw_span); NULL when none';
@@ -0,0 +1,3 @@
+-- Remove the co-occurrence column; strengths stay count-based
+ALTER TABLE entity_relations DROP COLUMN IF EXISTS co_occurrence;
+COMMENT ON COLUMN entity_relations.strength IS 'Relationship strength (based on co-occurrence frequency)';
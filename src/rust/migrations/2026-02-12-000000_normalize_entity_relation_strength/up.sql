@@ -0,0 +1,15 @@
+-- DirSoul Migration: Split count-based strength from co-occurrence
+-- `strength` becomes the count-based weight count / (count + k). k is
+-- `RelationExtractorConfig::strength_saturation`, so the application
+-- rewrites strengths from `observation_count` at startup
+-- (`sync_count_strengths`) instead of this migration fixing k.
+-- The co-occurrence coefficient of `recompute_all_strengths` moves to
+-- its own column so neither value overwrites the other.
+
+ALTER TABLE entity_relations ADD COLUMN co_occurrence FLOAT8 NOT NULL DEFAULT 0;
+
+-- Strengths above 1 are legacy observation counts, not coefficients
+UPDATE entity_relations SET co_occurrence = strength WHERE strength <= 1.0;
+
+COMMENT ON COLUMN entity_relations.strength IS 'Count-based weight in [0, 1): count / (count + k)';
+COMMENT ON COLUMN entity_relations.co_occurrence IS 'Co-occurrence coefficient in [0, 1] over the recompute window';
@@ -0,0 +1,2 @@
+-- Remove per-user settings
+DROP TABLE IF EXISTS user_settings;
@@ -0,0 +1,20 @@
+-- DirSoul Migration: Per-user settings
+-- One optional row per user. Every setting is nullable: NULL means the user
+-- has no override and the global configuration applies.
+
+CREATE TABLE user_settings (
+    user_id TEXT PRIMARY KEY,
+    timezone TEXT,
+    locale TEXT,
+    default_plugin TEXT,
+    promotion_min_confidence DOUBLE PRECISION
+        CHECK (promotion_min_confidence BETWEEN 0 AND 1),
+    promotion_min_validation_count INTEGER CHECK (promotion_min_validation_count >= 0),
+    promotion_min_time_span_days BIGINT CHECK (promotion_min_time_span_days >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+COMMENT ON TABLE user_settings IS 'Per-user overrides of the global configuration';
+COMMENT ON COLUMN user_settings.timezone IS 'IANA name or UTC offset used for timeline grouping';
+COMMENT ON COLUMN user_settings.locale IS 'Language of generated text, e.g. zh-CN or en';
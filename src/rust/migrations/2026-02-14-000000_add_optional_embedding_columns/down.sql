@@ -0,0 +1,3 @@
+-- The embedding columns belong to the create-table migrations; dropping them
+-- here would lose vectors stored before this migration ran
+SELECT 1;
@@ -0,0 +1,28 @@
+-- DirSoul Migration: Add the embedding columns where pgvector allows it
+-- pgvector is optional: on a database migrated without the vector columns
+-- they and their HNSW indexes are added once the extension is available.
+-- Without it nothing changes and the server falls back to keyword search
+-- (see check_vector_capability). Databases that already have the columns
+-- are left as they are.
+
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM pg_available_extensions WHERE name = 'vector') THEN
+        CREATE EXTENSION IF NOT EXISTS vector;
+
+        ALTER TABLE raw_memories ADD COLUMN IF NOT EXISTS embedding VECTOR(512);
+        CREATE INDEX IF NOT EXISTS idx_raw_memories_embedding_hnsw
+        ON raw_memories
+        USING hnsw (embedding vector_cosine_ops)
+        WITH (m = 16, ef_construction = 64);
+
+        ALTER TABLE entities ADD COLUMN IF NOT EXISTS embedding VECTOR(512);
+        CREATE INDEX IF NOT EXISTS idx_entities_embedding_hnsw
+        ON entities
+        USING hnsw (embedding vector_cosine_ops)
+        WITH (m = 16, ef_construction = 64);
+    ELSE
+        RAISE NOTICE 'pgvector is not available; embedding columns not added';
+    END IF;
+END
+$$;
@@ -19,8 +19,10 @@
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
 
 use crate::error::{DirSoulError, Result};
 use crate::schema::audit_logs;
@@ -163,15 +165,7 @@ impl AuditLogger {
         success: bool,
         result_count: i32,
     ) -> Result<AuditLog> {
-        let mut log = NewAuditLog::new(user_id.to_string(), "query".to_string(), target.to_string())
-            .with_success(success)
-            .with_result_count(result_count);
-
-        if !success {
-            log = log.with_error("Query failed".to_string());
-        }
-
-        self.insert_log(log)
+        self.insert_log(Self::query_entry(user_id, target, success, result_count))
     }
 
     /// Log an insert action
@@ -181,14 +175,7 @@ impl AuditLogger {
         target: &str,
         success: bool,
     ) -> Result<AuditLog> {
-        let mut log = NewAuditLog::new(user_id.to_string(), "insert".to_string(), target.to_string())
-            .with_success(success);
-
-        if !success {
-            log = log.with_error("Insert failed".to_string());
-        }
-
-        self.insert_log(log)
+        self.insert_log(Self::change_entry("insert", user_id, target, success))
     }
 
     /// Log an update action
@@ -198,14 +185,7 @@ impl AuditLogger {
         target: &str,
         success: bool,
     ) -> Result<AuditLog> {
-        let mut log = NewAuditLog::new(user_id.to_string(), "update".to_string(), target.to_string())
-            .with_success(success);
-
-        if !success {
-            log = log.with_error("Update failed".to_string());
-        }
-
-        self.insert_log(log)
+        self.insert_log(Self::change_entry("update", user_id, target, success))
     }
 
     /// Log a delete action
@@ -215,14 +195,7 @@ impl AuditLogger {
         target: &str,
         success: bool,
     ) -> Result<AuditLog> {
-        let mut log = NewAuditLog::new(user_id.to_string(), "delete".to_string(), target.to_string())
-            .with_success(success);
-
-        if !success {
-            log = log.with_error("Delete failed".to_string());
-        }
-
-        self.insert_log(log)
+        self.insert_log(Self::change_entry("delete", user_id, target, success))
     }
 
     /// Log an export action
@@ -233,6 +206,76 @@ impl AuditLogger {
         success: bool,
         result_count: i32,
     ) -> Result<AuditLog> {
+        self.insert_log(Self::export_entry(user_id, target, success, result_count))
+    }
+
+    /// Log custom action
+    pub fn log_custom(
+        &self,
+        user_id: &str,
+        action: &str,
+        target: &str,
+        success: bool,
+        metadata: Option<serde_json::Value>,
+    ) -> Result<AuditLog> {
+        self.insert_log(Self::custom_entry(user_id, action, target, success, metadata))
+    }
+
+    /// Insert several entries with one multi-row INSERT per chunk
+    ///
+    /// Returns the number of rows written.
+    pub fn log_batch(&self, logs: &[NewAuditLog]) -> Result<usize> {
+        if logs.is_empty() {
+            return Ok(0);
+        }
+
+        let mut conn = PgConnection::establish(&self.database_url)
+            .map_err(|e| DirSoulError::DatabaseConnection(e))?;
+
+        self.check_and_rotate(&mut conn)?;
+
+        let mut written = 0;
+        for chunk in logs.chunks(AUDIT_INSERT_CHUNK) {
+            written += diesel::insert_into(audit_logs::table)
+                .values(chunk)
+                .execute(&mut conn)?;
+        }
+
+        Ok(written)
+    }
+
+    /// Entry for a query action
+    fn query_entry(user_id: &str, target: &str, success: bool, result_count: i32) -> NewAuditLog {
+        let mut log = NewAuditLog::new(user_id.to_string(), "query".to_string(), target.to_string())
+            .with_success(success)
+            .with_result_count(result_count);
+
+        if !success {
+            log = log.with_error("Query failed".to_string());
+        }
+
+        log
+    }
+
+    /// Entry for an insert, update or delete action
+    fn change_entry(action: &str, user_id: &str, target: &str, success: bool) -> NewAuditLog {
+        let mut log = NewAuditLog::new(user_id.to_string(), action.to_string(), target.to_string())
+            .with_success(success);
+
+        if !success {
+            let error = match action {
+                "insert" => "Insert failed",
+                "update" => "Update failed",
+                _ => "Delete failed",
+            };
+            log = log.with_error(error.to_string());
+        }
+
+        log
+    }
+
+    /// Entry for an export action
+    fn export_entry(user_id: &str, target: &str, success: bool, result_count: i32) -> NewAuditLog {
         let mut log = NewAuditLog::new(user_id.to_string(), "export".to_string(), target.to_string())
             .with_success(success)
             .with_result_count(result_count);
@@ -241,18 +284,17 @@ impl AuditLogger {
             log = log.with_error("Export failed".to_string());
         }
 
-        self.insert_log(log)
+        log
     }
 
-    /// Log custom action
-    pub fn log_custom(
-        &self,
+    /// Entry for a custom action
+    fn custom_entry(
         user_id: &str,
         action: &str,
         target: &str,
         success: bool,
         metadata: Option<serde_json::Value>,
-    ) -> Result<AuditLog> {
+    ) -> NewAuditLog {
         let mut log = NewAuditLog::new(user_id.to_string(), action.to_string(), target.to_string())
             .with_success(success);
 
@@ -264,7 +306,7 @@ impl AuditLogger {
             log = log.with_metadata(meta);
         }
 
-        self.insert_log(log)
+        log
     }
 
     /// Insert log entry to database
@@ -297,12 +339,78 @@ impl AuditLogger {
     }
 }
 
+/// Rows per INSERT statement in `AuditLogger::log_batch`
+///
+/// Keeps each statement well under Postgres' 65535 bind parameter limit.
+const AUDIT_INSERT_CHUNK: usize = 1000;
+
+/// Writes a batch of buffered entries and returns how many were stored
+pub type AuditBatchWriter = Arc<dyn Fn(Vec<NewAuditLog>) -> Result<usize> + Send + Sync>;
+
+/// Buffered audit logging settings
+///
+/// # Durability
+/// Buffered entries live only in memory until they are flushed. A crash or
+/// kill loses up to `max_entries` entries (or `flush_interval` worth of
+/// traffic, whichever is smaller), and a batch whose insert fails is
+/// dropped rather than retried so a database outage cannot grow the buffer
+/// without bound. Call `ThreadSafeAuditLogger::shutdown` before exiting to
+/// write the remainder.
+#[derive(Debug, Clone)]
+pub struct AuditBufferConfig {
+    /// Flush as soon as this many entries are waiting
+    pub max_entries: usize,
+    /// Flush whatever is waiting at this interval
+    pub flush_interval: Duration,
+}
+
+impl Default for AuditBufferConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 100,
+            flush_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+/// In-memory queue of entries waiting for a batched insert
+struct AuditBuffer {
+    config: AuditBufferConfig,
+    entries: Mutex<Vec<NewAuditLog>>,
+    writer: AuditBatchWriter,
+    /// Interval flush task, started on the first buffered entry
+    flusher: StdMutex<Option<JoinHandle<()>>>,
+}
+
+impl AuditBuffer {
+    /// Write everything currently waiting as one batch
+    async fn flush(&self) -> Result<usize> {
+        let batch = std::mem::take(&mut *self.entries.lock().await);
+        self.write(batch).await
+    }
+
+    async fn write(&self, batch: Vec<NewAuditLog>) -> Result<usize> {
+        if batch.is_empty() {
+            return Ok(0);
+        }
+
+        let writer = self.writer.clone();
+        tokio::task::spawn_blocking(move || writer(batch))
+            .await
+            .map_err(|e| DirSoulError::ExternalError(format!("Audit flush task failed: {}", e)))?
+    }
+}
+
 /// Thread-safe audit logger wrapper
 ///
-/// Use this for concurrent access to audit logging.
+/// Use this for concurrent access to audit logging. By default every call
+/// inserts one row; `with_buffer` switches to batched inserts (see
+/// `AuditBufferConfig` for the durability trade-off). Log calls return the
+/// stored row when writing directly and `None` when the entry was buffered.
 #[derive(Clone)]
 pub struct ThreadSafeAuditLogger {
     inner: Arc<RwLock<AuditLogger>>,
+    buffer: Option<Arc<AuditBuffer>>,
 }
 
 impl ThreadSafeAuditLogger {
@@ -310,7 +418,113 @@ impl ThreadSafeAuditLogger {
     pub fn new(database_url: String) -> Self {
         Self {
             inner: Arc::new(RwLock::new(AuditLogger::new(database_url))),
+            buffer: None,
+        }
+    }
+
+    /// Buffer entries and write them with `AuditLogger::log_batch`
+    pub fn with_buffer(self, config: AuditBufferConfig) -> Self {
+        let inner = self.inner.clone();
+        let writer: AuditBatchWriter =
+            Arc::new(move |batch: Vec<NewAuditLog>| inner.blocking_read().log_batch(&batch));
+        self.with_buffer_writer(config, writer)
+    }
+
+    /// Buffer entries and hand each batch to `writer`
+    pub fn with_buffer_writer(mut self, config: AuditBufferConfig, writer: AuditBatchWriter) -> Self {
+        let config = AuditBufferConfig {
+            max_entries: config.max_entries.max(1),
+            ..config
+        };
+        self.buffer = Some(Arc::new(AuditBuffer {
+            config,
+            entries: Mutex::new(Vec::new()),
+            writer,
+            flusher: StdMutex::new(None),
+        }));
+        self
+    }
+
+    /// Whether entries are buffered instead of written per call
+    pub fn is_buffered(&self) -> bool {
+        self.buffer.is_some()
+    }
+
+    /// Record an entry, writing it now or queueing it for the next batch
+    pub async fn log(&self, entry: NewAuditLog) -> Result<Option<AuditLog>> {
+        let Some(buffer) = &self.buffer else {
+            let logger = self.inner.read().await;
+            return logger.insert_log(entry).map(Some);
+        };
+
+        self.ensure_flusher(buffer);
+
+        let full_batch = {
+            let mut entries = buffer.entries.lock().await;
+            entries.push(entry);
+            if entries.len() >= buffer.config.max_entries {
+                std::mem::take(&mut *entries)
+            } else {
+                Vec::new()
+            }
+        };
+        buffer.write(full_batch).await?;
+
+        Ok(None)
+    }
+
+    /// Write all buffered entries now
+    ///
+    /// Returns the number of rows written; always 0 when unbuffered.
+    pub async fn flush(&self) -> Result<usize> {
+        match &self.buffer {
+            Some(buffer) => buffer.flush().await,
+            None => Ok(0),
+        }
+    }
+
+    /// Stop the interval flush and write the remaining entries
+    ///
+    /// Entries logged after shutdown are still buffered and restart the
+    /// interval flush.
+    pub async fn shutdown(&self) -> Result<usize> {
+        let Some(buffer) = &self.buffer else {
+            return Ok(0);
+        };
+
+        let flusher = buffer.flusher.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(handle) = flusher {
+            handle.abort();
         }
+
+        buffer.flush().await
+    }
+
+    /// Start the interval flush task if it is not running
+    fn ensure_flusher(&self, buffer: &Arc<AuditBuffer>) {
+        let mut flusher = buffer.flusher.lock().unwrap_or_else(|e| e.into_inner());
+        if flusher.is_some() {
+            return;
+        }
+
+        // Hold only a weak reference so dropping the last logger ends the task
+        let weak = Arc::downgrade(buffer);
+        let interval = buffer.config.flush_interval;
+        *flusher = Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+                let Some(buffer) = weak.upgrade() else {
+                    break;
+                };
+                if let Err(e) = buffer.flush().await {
+                    tracing::warn!("Dropped buffered audit entries: {}", e);
+                }
+            }
+        }));
     }
 
     /// Log a query action (thread-safe)
@@ -320,9 +534,8 @@ impl ThreadSafeAuditLogger {
         target: &str,
         success: bool,
         result_count: i32,
-    ) -> Result<AuditLog> {
-        let logger = self.inner.read().await;
-        logger.log_query(user_id, target, success, result_count)
+    ) -> Result<Option<AuditLog>> {
+        self.log(AuditLogger::query_entry(user_id, target, success, result_count)).await
     }
 
     /// Log an insert action (thread-safe)
@@ -331,9 +544,8 @@ impl ThreadSafeAuditLogger {
         user_id: &str,
         target: &str,
         success: bool,
-    ) -> Result<AuditLog> {
-        let logger = self.inner.read().await;
-        logger.log_insert(user_id, target, success)
+    ) -> Result<Option<AuditLog>> {
+        self.log(AuditLogger::change_entry("insert", user_id, target, success)).await
     }
 
     /// Log an update action (thread-safe)
@@ -342,9 +554,8 @@ impl ThreadSafeAuditLogger {
         user_id: &str,
         target: &str,
         success: bool,
-    ) -> Result<AuditLog> {
-        let logger = self.inner.read().await;
-        logger.log_update(user_id, target, success)
+    ) -> Result<Option<AuditLog>> {
+        self.log(AuditLogger::change_entry("update", user_id, target, success)).await
     }
 
     /// Log a delete action (thread-safe)
@@ -353,9 +564,8 @@ impl ThreadSafeAuditLogger {
         user_id: &str,
         target: &str,
         success: bool,
-    ) -> Result<AuditLog> {
-        let logger = self.inner.read().await;
-        logger.log_delete(user_id, target, success)
+    ) -> Result<Option<AuditLog>> {
+        self.log(AuditLogger::change_entry("delete", user_id, target, success)).await
     }
 
     /// Log an export action (thread-safe)
@@ -365,9 +575,8 @@ impl ThreadSafeAuditLogger {
         target: &str,
         success: bool,
         result_count: i32,
-    ) -> Result<AuditLog> {
-        let logger = self.inner.read().await;
-        logger.log_export(user_id, target, success, result_count)
+    ) -> Result<Option<AuditLog>> {
+        self.log(AuditLogger::export_entry(user_id, target, success, result_count)).await
     }
 
     /// Log custom action (thread-safe)
@@ -378,9 +587,8 @@ impl ThreadSafeAuditLogger {
         target: &str,
         success: bool,
         metadata: Option<serde_json::Value>,
-    ) -> Result<AuditLog> {
-        let logger = self.inner.read().await;
-        logger.log_custom(user_id, action, target, success, metadata)
+    ) -> Result<Option<AuditLog>> {
+        self.log(AuditLogger::custom_entry(user_id, action, target, success, metadata)).await
     }
 }
 
@@ -505,4 +713,96 @@ mod tests {
         assert_eq!(logger.max_logs, 50_000);
         assert_eq!(logger.rotation_threshold, 40_000);
     }
+
+    /// Writer that records batch sizes instead of touching the database
+    fn recording_writer() -> (AuditBatchWriter, Arc<StdMutex<Vec<usize>>>) {
+        let batches = Arc::new(StdMutex::new(Vec::new()));
+        let recorded = batches.clone();
+        let writer: AuditBatchWriter = Arc::new(move |batch: Vec<NewAuditLog>| {
+            recorded.lock().unwrap().push(batch.len());
+            Ok(batch.len())
+        });
+        (writer, batches)
+    }
+
+    #[tokio::test]
+    async fn test_buffered_logger_batches_inserts() {
+        let (writer, batches) = recording_writer();
+        let logger = ThreadSafeAuditLogger::new("postgresql://localhost/test".to_string())
+            .with_buffer_writer(
+                AuditBufferConfig {
+                    max_entries: 100,
+                    flush_interval: Duration::from_secs(3600),
+                },
+                writer,
+            );
+        assert!(logger.is_buffered());
+
+        for i in 0..250 {
+            let logged = logger.log_query(&format!("user{}", i), "events", true, 1).await.unwrap();
+            assert!(logged.is_none());
+        }
+        assert_eq!(*batches.lock().unwrap(), vec![100, 100]);
+
+        // Shutdown writes the remainder in one more batch
+        assert_eq!(logger.shutdown().await.unwrap(), 50);
+        assert_eq!(*batches.lock().unwrap(), vec![100, 100, 50]);
+
+        // Nothing left to write
+        assert_eq!(logger.flush().await.unwrap(), 0);
+        assert_eq!(batches.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_buffered_logger_flushes_on_interval() {
+        let (writer, batches) = recording_writer();
+        let logger = ThreadSafeAuditLogger::new("postgresql://localhost/test".to_string())
+            .with_buffer_writer(
+                AuditBufferConfig {
+                    max_entries: 100,
+                    flush_interval: Duration::from_millis(20),
+                },
+                writer,
+            );
+
+        logger.log_insert("user123", "events", true).await.unwrap();
+        logger.log_update("user123", "views", false).await.unwrap();
+        logger.log_custom("user123", "detect", "patterns", true, None).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(*batches.lock().unwrap(), vec![3]);
+
+        assert_eq!(logger.shutdown().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_batch_is_dropped() {
+        let writer: AuditBatchWriter =
+            Arc::new(|_batch: Vec<NewAuditLog>| Err(DirSoulError::ExternalError("db down".to_string())));
+        let logger = ThreadSafeAuditLogger::new("postgresql://localhost/test".to_string())
+            .with_buffer_writer(
+                AuditBufferConfig {
+                    max_entries: 2,
+                    flush_interval: Duration::from_secs(3600),
+                },
+                writer,
+            );
+
+        assert!(logger.log_delete("user123", "events", true).await.is_ok());
+        // The second entry fills the buffer and the failing write surfaces
+        assert!(logger.log_delete("user123", "events", true).await.is_err());
+        assert_eq!(logger.shutdown().await.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_entry_builders() {
+        let log = AuditLogger::change_entry("update", "user123", "views", false);
+        assert_eq!(log.action, "update");
+        assert_eq!(log.error_message.as_deref(), Some("Update failed"));
+
+        let log = AuditLogger::export_entry("user123", "all", true, 7);
+        assert_eq!(log.action, "export");
+        assert_eq!(log.result_count, Some(7));
+        assert!(log.error_message.is_none());
+    }
 }
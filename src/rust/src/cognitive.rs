@@ -9,7 +9,13 @@
 //! - **Promotion Gate 把关**: 程序判定是否晋升为稳定概念
 //! - **避免 LLM 幻觉放大**: 隔离 AI 判断与系统结构
 
-use crate::schema::{cognitive_views, stable_concepts};
+use crate::audit::NewAuditLog;
+use crate::error::{DirSoulError, Result};
+use crate::lexicon::LexiconSet;
+use crate::models::EventMemory;
+use crate::schema::{audit_logs, cognitive_views, event_memories, raw_memories, stable_concepts};
+use crate::user_settings::UserSettings;
+use crate::webhook::{WebhookEvent, WebhookNotifier};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -68,6 +74,266 @@ impl From<&str> for ViewStatus {
     }
 }
 
+/// View type enum - what kind of hypothesis a cognitive view holds
+///
+/// Stored as text; known names are matched case-insensitively so that
+/// "Habit" and "habit" group together, anything else is kept as `Custom`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum ViewType {
+    /// Likes and dislikes ("用户喜欢吃水果")
+    Preference,
+    /// Repeated behavior
+    Habit,
+    /// Generic detected pattern
+    Pattern,
+    /// Increasing or decreasing behavior
+    Trend,
+    /// Deviation from the usual baseline
+    Anomaly,
+    /// Time-based routine
+    Routine,
+    /// Any other type, kept verbatim
+    Custom(String),
+}
+
+impl ViewType {
+    /// Stored name of the view type
+    pub fn as_str(&self) -> &str {
+        match self {
+            ViewType::Preference => "preference",
+            ViewType::Habit => "habit",
+            ViewType::Pattern => "pattern",
+            ViewType::Trend => "trend",
+            ViewType::Anomaly => "anomaly",
+            ViewType::Routine => "routine",
+            ViewType::Custom(name) => name,
+        }
+    }
+}
+
+impl From<String> for ViewType {
+    fn from(s: String) -> Self {
+        match s.trim().to_lowercase().as_str() {
+            "preference" => ViewType::Preference,
+            "habit" => ViewType::Habit,
+            "pattern" => ViewType::Pattern,
+            "trend" => ViewType::Trend,
+            "anomaly" => ViewType::Anomaly,
+            "routine" => ViewType::Routine,
+            _ => ViewType::Custom(s),
+        }
+    }
+}
+
+impl From<ViewType> for String {
+    fn from(view_type: ViewType) -> Self {
+        match view_type {
+            ViewType::Custom(name) => name,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+impl From<&str> for ViewType {
+    fn from(s: &str) -> Self {
+        s.to_string().into()
+    }
+}
+
+/// Promotion Gate thresholds
+///
+/// Keeps the promotion criteria and the counter-evidence auto-reject cutoff
+/// in one place so they cannot drift apart.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromotionGateConfig {
+    /// Confidence must be strictly above this value
+    pub min_confidence: f64,
+    /// Minimum number of validations
+    pub min_validation_count: i32,
+    /// Minimum lifetime of the view (days)
+    pub min_time_span_days: i64,
+    /// Counter-evidence ratio must be below this value to promote
+    pub gate_counter_ratio: f64,
+    /// Views with a counter-evidence ratio above this value are rejected
+    pub auto_reject_counter_ratio: f64,
+}
+
+impl Default for PromotionGateConfig {
+    fn default() -> Self {
+        Self {
+            min_confidence: 0.85,
+            min_validation_count: 3,
+            min_time_span_days: 30,
+            gate_counter_ratio: 0.15,
+            auto_reject_counter_ratio: 0.3,
+        }
+    }
+}
+
+impl PromotionGateConfig {
+    /// Validate threshold consistency
+    ///
+    /// The auto-reject ratio must not be below the gate ratio, otherwise a
+    /// view could be rejected while still passing the promotion gate.
+    pub fn validate(&self) -> Result<()> {
+        for (name, value) in [
+            ("gate_counter_ratio", self.gate_counter_ratio),
+            ("auto_reject_counter_ratio", self.auto_reject_counter_ratio),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(DirSoulError::Config(format!(
+                    "{} must be between 0 and 1, got {}",
+                    name, value
+                )));
+            }
+        }
+
+        if self.auto_reject_counter_ratio < self.gate_counter_ratio {
+            return Err(DirSoulError::Config(format!(
+                "auto_reject_counter_ratio ({}) must be >= gate_counter_ratio ({})",
+                self.auto_reject_counter_ratio, self.gate_counter_ratio
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// How a validation raises a view's confidence
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfidenceBump {
+    /// Add a fixed amount, capped at 1.0
+    Additive(f64),
+    /// Close this fraction of the remaining gap to 1.0: `c + rate * (1 - c)`
+    Proportional(f64),
+}
+
+impl Default for ConfidenceBump {
+    fn default() -> Self {
+        ConfidenceBump::Proportional(0.25)
+    }
+}
+
+impl ConfidenceBump {
+    /// Confidence after one more validation
+    pub fn apply(self, confidence: f64) -> f64 {
+        let bumped = match self {
+            ConfidenceBump::Additive(amount) => confidence + amount,
+            ConfidenceBump::Proportional(rate) => confidence + rate * (1.0 - confidence),
+        };
+        bumped.clamp(0.0, 1.0)
+    }
+
+    /// Reject negative or out-of-range bump amounts
+    pub fn validate(&self) -> Result<()> {
+        let (name, value) = match *self {
+            ConfidenceBump::Additive(amount) => ("Additive amount", amount),
+            ConfidenceBump::Proportional(rate) => ("Proportional rate", rate),
+        };
+        if !(0.0..=1.0).contains(&value) {
+            return Err(DirSoulError::Config(format!(
+                "{} must be between 0 and 1, got {}",
+                name, value
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of evaluating a view against the Promotion Gate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ViewDecision {
+    /// Passed every gate criterion
+    Promote,
+    /// Keep observing
+    Keep,
+    /// Too much counter-evidence
+    Reject,
+}
+
+/// A single Promotion Gate criterion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GateCriterion {
+    /// Confidence above `min_confidence`
+    Confidence,
+    /// At least `min_validation_count` validations
+    ValidationCount,
+    /// Lifetime of at least `min_time_span_days`
+    TimeSpan,
+    /// Counter-evidence ratio below `gate_counter_ratio`
+    CounterEvidenceRatio,
+}
+
+/// How a view measures up against one gate criterion
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CriterionCheck {
+    pub criterion: GateCriterion,
+    /// The view's value
+    pub value: f64,
+    /// The gate's threshold
+    pub threshold: f64,
+    /// The criterion is met
+    pub passed: bool,
+    /// Not met, but within the near-miss margin of the threshold
+    pub near_miss: bool,
+}
+
+impl CriterionCheck {
+    /// Check a criterion the value must reach (`strict`: exceed)
+    fn at_least(
+        criterion: GateCriterion,
+        value: f64,
+        threshold: f64,
+        strict: bool,
+        margin: f64,
+    ) -> Self {
+        let passed = if strict { value > threshold } else { value >= threshold };
+        Self {
+            criterion,
+            value,
+            threshold,
+            passed,
+            near_miss: !passed && value >= threshold * (1.0 - margin),
+        }
+    }
+
+    /// Check a criterion the value must stay below
+    fn below(criterion: GateCriterion, value: f64, threshold: f64, margin: f64) -> Self {
+        let passed = value < threshold;
+        Self {
+            criterion,
+            value,
+            threshold,
+            passed,
+            near_miss: !passed && value <= threshold * (1.0 + margin),
+        }
+    }
+}
+
+/// Where a promotion candidate stands against the gate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CandidateReadiness {
+    /// Passes every criterion
+    Ready,
+    /// Every failing criterion is within the near-miss margin
+    NearMiss,
+}
+
+/// How an event relates to a view's hypothesis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EvidenceKind {
+    /// Same topic and polarity
+    Supporting,
+    /// Same topic, opposite polarity
+    Contradicting,
+    /// Different topic
+    Unrelated,
+}
+
 /// Cognitive View - a temporary hypothesis about user behavior
 ///
 /// # Example (HEAD.md)
@@ -80,7 +346,7 @@ impl From<&str> for ViewStatus {
 ///     status: ViewStatus,        // active | expired | promoted
 /// }
 /// ```
-#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, Serialize, Deserialize)]
 pub struct CognitiveView {
     pub view_id: Uuid,
     pub user_id: String,
@@ -117,6 +383,35 @@ impl CognitiveView {
         self.status.as_str().into()
     }
 
+    /// Get the typed ViewType from the string
+    pub fn get_view_type(&self) -> ViewType {
+        self.view_type.as_str().into()
+    }
+
+    /// Check if re-validation flagged the view for manual promotion
+    pub fn is_flagged_for_promotion(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.get(READY_FOR_PROMOTION_KEY))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// Set or clear the `ready_for_promotion` metadata flag
+    fn set_flagged_for_promotion(&mut self, ready: bool) {
+        if !ready && self.metadata.is_none() {
+            return;
+        }
+        let metadata = self.metadata.get_or_insert_with(|| serde_json::json!({}));
+        if let Some(map) = metadata.as_object_mut() {
+            if ready {
+                map.insert(READY_FOR_PROMOTION_KEY.to_string(), serde_json::json!(true));
+            } else {
+                map.remove(READY_FOR_PROMOTION_KEY);
+            }
+        }
+    }
+
     /// Calculate default expiration time (30 days from now)
     pub fn default_expiration() -> chrono::DateTime<chrono::Utc> {
         chrono::Utc::now() + chrono::Duration::days(30)
@@ -135,14 +430,19 @@ impl CognitiveView {
     /// }
     /// ```
     pub fn is_ready_for_promotion(&self) -> bool {
+        self.is_ready_for_promotion_with(&PromotionGateConfig::default())
+    }
+
+    /// Promotion Gate check with explicit thresholds
+    pub fn is_ready_for_promotion_with(&self, config: &PromotionGateConfig) -> bool {
         // Basic criteria
-        if self.confidence <= 0.85 {
+        if self.confidence <= config.min_confidence {
             return false;
         }
-        if self.validation_count < 3 {
+        if self.validation_count < config.min_validation_count {
             return false;
         }
-        if (self.expires_at - self.created_at).num_days() < 30 {
+        if (self.expires_at - self.created_at).num_days() < config.min_time_span_days {
             return false;
         }
         if !self.get_status().can_be_promoted() {
@@ -151,7 +451,7 @@ impl CognitiveView {
 
         // Check counter-evidence ratio (< 15% per skill)
         let counter_ratio = self.counter_evidence_ratio();
-        if counter_ratio >= 0.15 {
+        if counter_ratio >= config.gate_counter_ratio {
             return false;
         }
 
@@ -159,6 +459,57 @@ impl CognitiveView {
         true
     }
 
+    /// Evaluate this view against the Promotion Gate in one call
+    ///
+    /// Rejection takes precedence over promotion; everything else is kept.
+    pub fn evaluate(&self, config: &PromotionGateConfig) -> ViewDecision {
+        if self.should_be_rejected_with(config) {
+            ViewDecision::Reject
+        } else if self.is_ready_for_promotion_with(config) {
+            ViewDecision::Promote
+        } else {
+            ViewDecision::Keep
+        }
+    }
+
+    /// Check each Promotion Gate criterion on its own
+    ///
+    /// A failing criterion is a near miss when it is within `margin` (a
+    /// fraction of the threshold) of passing. The checks pass exactly when
+    /// `is_ready_for_promotion_with` would, except that the status is not
+    /// considered.
+    pub fn gate_breakdown(&self, config: &PromotionGateConfig, margin: f64) -> Vec<CriterionCheck> {
+        vec![
+            CriterionCheck::at_least(
+                GateCriterion::Confidence,
+                self.confidence,
+                config.min_confidence,
+                true,
+                margin,
+            ),
+            CriterionCheck::at_least(
+                GateCriterion::ValidationCount,
+                self.validation_count as f64,
+                config.min_validation_count as f64,
+                false,
+                margin,
+            ),
+            CriterionCheck::at_least(
+                GateCriterion::TimeSpan,
+                (self.expires_at - self.created_at).num_days() as f64,
+                config.min_time_span_days as f64,
+                false,
+                margin,
+            ),
+            CriterionCheck::below(
+                GateCriterion::CounterEvidenceRatio,
+                self.counter_evidence_ratio(),
+                config.gate_counter_ratio,
+                margin,
+            ),
+        ]
+    }
+
     /// Calculate counter-evidence ratio
     ///
     /// Returns the ratio of counter-evidence to supporting evidence.
@@ -174,7 +525,12 @@ impl CognitiveView {
     ///
     /// Per skill: if counter_ratio > 0.3, automatically reject
     pub fn should_be_rejected(&self) -> bool {
-        self.counter_evidence_ratio() > 0.3
+        self.should_be_rejected_with(&PromotionGateConfig::default())
+    }
+
+    /// Auto-reject check with explicit thresholds
+    pub fn should_be_rejected_with(&self, config: &PromotionGateConfig) -> bool {
+        self.counter_evidence_ratio() > config.auto_reject_counter_ratio
     }
 
     /// Check for contradictions with another view (programmatic keyword matching)
@@ -184,84 +540,66 @@ impl CognitiveView {
     /// - "喜欢" vs "讨厌"
     /// - "经常" vs "很少"
     /// - "总是" vs "从不"
+    ///
+    /// Uses the built-in lexicon; see `has_conflict_with_lexicon`.
     pub fn has_conflict_with(&self, other: &CognitiveView) -> bool {
+        self.has_conflict_with_lexicon(other, &LexiconSet::default())
+    }
+
+    /// Conflict check with an explicit lexicon
+    ///
+    /// A positive keyword right after a negation marker ("不经常") counts as
+    /// its negative side.
+    pub fn has_conflict_with_lexicon(&self, other: &CognitiveView, lexicon: &LexiconSet) -> bool {
         // Skip if same view or same user
         if self.view_id == other.view_id || self.user_id != other.user_id {
             return false;
         }
 
-        // Get hypothesis texts
-        let self_hypothesis = &self.hypothesis;
-        let other_hypothesis = &other.hypothesis;
-
-        // Define contradiction pairs
-        let contradiction_pairs = vec![
-            ("喜欢", "讨厌"),
-            ("喜欢", "不喜欢"),
-            ("爱", "恨"),
-            ("经常", "很少"),
-            ("总是", "从不"),
-            ("每天", "从不"),
-            ("习惯", "讨厌"),
-        ];
-
-        // Check if any contradiction pair exists in the two hypotheses
-        for (positive, negative) in contradiction_pairs {
-            let self_has_positive = self_hypothesis.contains(positive);
-            let self_has_negative = self_hypothesis.contains(negative);
-            let other_has_positive = other_hypothesis.contains(positive);
-            let other_has_negative = other_hypothesis.contains(negative);
-
-            // Contradiction: one has positive, other has negative for same concept
-            if (self_has_positive && other_has_negative) || (self_has_negative && other_has_positive) {
-                // Additional check: must be about same target/action
-                if self.hypothesis_matches_target(other_hypothesis) {
-                    return true;
-                }
-            }
-        }
-
-        false
+        hypotheses_conflict(&self.hypothesis, &other.hypothesis, lexicon)
     }
 
-    /// Check if two hypotheses refer to the same target/action
+    /// Merge supporting event ids into `derived_from`
     ///
-    /// This is a simplified check for whether contradictions are relevant.
-    /// For example, "喜欢吃水果" and "讨厌吃水果" should conflict,
-    /// but "喜欢吃水果" and "讨厌吃蔬菜" should not.
-    fn hypothesis_matches_target(&self, other: &str) -> bool {
-        // Extract action/target from hypothesis (simplified)
-        // This is a basic implementation - can be enhanced with NLP
-
-        // Split hypothesis by common delimiters
-        let self_parts: Vec<&str> = self.hypothesis.split(|c| c == '的' || c == '是' || c == '吃').collect();
-        let other_parts: Vec<&str> = other.split(|c| c == '的' || c == '是' || c == '吃').collect();
-
-        // Check if any part matches (excluding the sentiment words)
-        for self_part in &self_parts {
-            for other_part in &other_parts {
-                let self_clean = self_part.trim();
-                let other_clean = other_part.trim();
-
-                // Skip empty strings and sentiment words
-                if self_clean.is_empty() || other_clean.is_empty() {
-                    continue;
-                }
-
-                // Skip common sentiment words
-                let sentiment_words = ["喜欢", "讨厌", "爱", "恨", "经常", "很少", "总是", "从不"];
-                if sentiment_words.contains(&self_clean) || sentiment_words.contains(&other_clean) {
-                    continue;
-                }
+    /// Ids already present are not added again, so `evidence_count` stays the
+    /// number of distinct supporting events. Returns the updated count.
+    pub fn merge_derived_from(&mut self, event_ids: &[Uuid]) -> i32 {
+        let mut ids: Vec<Uuid> = serde_json::from_value(self.derived_from.clone()).unwrap_or_default();
+        ids.extend_from_slice(event_ids);
+        let ids = unique_event_ids(ids);
+
+        self.evidence_count = ids.len() as i32;
+        self.derived_from = serde_json::to_value(&ids).unwrap_or_default();
+        self.evidence_count
+    }
 
-                // If any significant word matches, consider it a conflict
-                if self_clean == other_clean || self_clean.len() > 1 && other_clean.contains(self_clean) {
-                    return true;
-                }
-            }
+    /// Record a validation by a newly arrived supporting event
+    ///
+    /// Appends the event to `derived_from`, counts it as evidence and as a
+    /// validation, stamps `last_validated_at` and raises the confidence by
+    /// `bump`. An event that already supports the view changes nothing, so
+    /// replays cannot inflate `validation_count`. Returns whether the view
+    /// was updated; a `derived_from` that is not a list of event ids is an
+    /// error rather than being replaced, so no evidence is lost.
+    pub fn record_validation(
+        &mut self,
+        supporting_event_id: Uuid,
+        bump: ConfidenceBump,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<bool> {
+        let mut ids: Vec<Uuid> = serde_json::from_value(self.derived_from.clone())?;
+        if ids.contains(&supporting_event_id) {
+            return Ok(false);
         }
-
-        false
+        ids.push(supporting_event_id);
+        self.derived_from = serde_json::to_value(&ids).unwrap_or_default();
+        self.evidence_count += 1;
+
+        self.validation_count += 1;
+        self.last_validated_at = Some(now);
+        self.updated_at = now;
+        self.confidence = bump.apply(self.confidence);
+        Ok(true)
     }
 
     /// Add counter-evidence to this view
@@ -281,6 +619,142 @@ impl CognitiveView {
 
         self.counter_evidence_count
     }
+
+    /// Classify an event as evidence for this view
+    ///
+    /// The event is read as "action target". It is unrelated unless it shares
+    /// the hypothesis topic; it contradicts when the texts take opposite sides
+    /// of a contradiction pair or only one of them is negated ("不吃 水果"
+    /// against "喜欢吃水果"), and supports otherwise. An event the extractor
+    /// flagged as negated counts as negated text.
+    pub fn classify_event(&self, event: &EventMemory, lexicon: &LexiconSet) -> EvidenceKind {
+        let event_text = format!("{} {}", event.action, event.target);
+        if !lexicon.same_topic(&self.hypothesis, &event_text) {
+            return EvidenceKind::Unrelated;
+        }
+
+        if hypotheses_conflict(&self.hypothesis, &event_text, lexicon)
+            || lexicon.is_negated(&self.hypothesis)
+                != (event.is_negated() || lexicon.is_negated(&event_text))
+        {
+            EvidenceKind::Contradicting
+        } else {
+            EvidenceKind::Supporting
+        }
+    }
+
+    /// Apply newly observed events as evidence
+    ///
+    /// Supporting events go through `record_validation`; contradicting ones
+    /// are added as counter-evidence once. Events already recorded either way
+    /// are skipped. Returns how many events were counted as (supporting,
+    /// contradicting).
+    pub fn apply_evidence(
+        &mut self,
+        events: &[EventMemory],
+        lexicon: &LexiconSet,
+        bump: ConfidenceBump,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<(usize, usize)> {
+        let mut supporting = 0;
+        let mut contradicting = 0;
+
+        for event in events {
+            match self.classify_event(event, lexicon) {
+                EvidenceKind::Supporting => {
+                    if self.record_validation(event.event_id, bump, now)? {
+                        supporting += 1;
+                    }
+                }
+                EvidenceKind::Contradicting => {
+                    let recorded: Vec<Uuid> =
+                        serde_json::from_value(self.counter_evidence.clone())?;
+                    if !recorded.contains(&event.event_id) {
+                        self.add_counter_evidence(event.event_id);
+                        self.updated_at = now;
+                        contradicting += 1;
+                    }
+                }
+                EvidenceKind::Unrelated => {}
+            }
+        }
+
+        Ok((supporting, contradicting))
+    }
+}
+
+/// Whether two hypothesis texts take opposite sides of a contradiction pair
+/// about the same topic
+///
+/// For example, "喜欢吃水果" and "讨厌吃水果" share the topic 水果 and
+/// conflict, while "喜欢吃水果" and "讨厌吃蔬菜" do not (see
+/// `LexiconSet::topic_tokens`).
+fn hypotheses_conflict(a: &str, b: &str, lexicon: &LexiconSet) -> bool {
+    for (positive, negative) in &lexicon.contradiction_pairs {
+        let a_has_positive = lexicon.contains_affirmed(a, positive);
+        let a_has_negative = a.contains(negative.as_str()) || lexicon.contains_negated(a, positive);
+        let b_has_positive = lexicon.contains_affirmed(b, positive);
+        let b_has_negative = b.contains(negative.as_str()) || lexicon.contains_negated(b, positive);
+
+        // Contradiction: one has positive, other has negative for same concept
+        if ((a_has_positive && b_has_negative) || (a_has_negative && b_has_positive))
+            && lexicon.same_topic(a, b)
+        {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Drop repeated event ids, keeping the first occurrence of each
+fn unique_event_ids(ids: Vec<Uuid>) -> Vec<Uuid> {
+    let mut seen = std::collections::HashSet::new();
+    ids.into_iter().filter(|id| seen.insert(*id)).collect()
+}
+
+/// Initial confidence, lifetime and source for new cognitive views
+///
+/// Passed to `NewCognitiveView::new_with_defaults` so each view source can
+/// set its own starting values instead of overriding them per view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewDefaults {
+    /// Starting confidence (default: 0.5)
+    pub confidence: f64,
+    /// Days until the view expires (default: 30, per HEAD.md)
+    pub expiration_days: i64,
+    /// Source recorded on the view (default: "pattern_detector")
+    pub source: String,
+}
+
+impl Default for ViewDefaults {
+    fn default() -> Self {
+        Self {
+            confidence: 0.5,
+            expiration_days: 30,
+            source: "pattern_detector".to_string(),
+        }
+    }
+}
+
+impl ViewDefaults {
+    /// Set the starting confidence (clamped to 0-1)
+    pub fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the lifetime in days
+    pub fn with_expiration_days(mut self, days: i64) -> Self {
+        self.expiration_days = days;
+        self
+    }
+
+    /// Set the source
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = source.to_string();
+        self
+    }
 }
 
 /// New Cognitive View for insertion
@@ -311,37 +785,58 @@ pub struct NewCognitiveView {
 }
 
 impl NewCognitiveView {
-    /// Create a new cognitive view
+    /// Create a new cognitive view with `ViewDefaults::default()`
     ///
     /// # Arguments
     /// * `user_id` - Owner of the view
     /// * `hypothesis` - The hypothesis/pattern
-    /// * `view_type` - Type of view (pattern, preference, habit, trend)
-    /// * `derived_from` - Event IDs that support this hypothesis
+    /// * `view_type` - Type of view (a `ViewType`, or its name as a string)
+    /// * `derived_from` - Event IDs that support this hypothesis (duplicates are dropped)
     pub fn new(
         user_id: String,
         hypothesis: String,
-        view_type: String,
+        view_type: impl Into<ViewType>,
+        derived_from: Vec<Uuid>,
+    ) -> Self {
+        Self::new_with_defaults(
+            user_id,
+            hypothesis,
+            view_type,
+            derived_from,
+            &ViewDefaults::default(),
+        )
+    }
+
+    /// Create a new cognitive view starting from the given defaults
+    ///
+    /// `derived_from` is de-duplicated so `evidence_count` counts distinct events.
+    pub fn new_with_defaults(
+        user_id: String,
+        hypothesis: String,
+        view_type: impl Into<ViewType>,
         derived_from: Vec<Uuid>,
+        defaults: &ViewDefaults,
     ) -> Self {
         let now = chrono::Utc::now();
+        let view_type: ViewType = view_type.into();
+        let derived_from = unique_event_ids(derived_from);
         let evidence_count = derived_from.len() as i32;
         Self {
             user_id,
             hypothesis,
-            view_type,
+            view_type: view_type.into(),
             description: None,
             derived_from: serde_json::to_value(&derived_from).unwrap_or_default(),
             evidence_count,
-            confidence: 0.5,
+            confidence: defaults.confidence,
             validation_count: 0,
             last_validated_at: None,
             status: ViewStatus::Active.into(),
             created_at: now,
             updated_at: now,
-            expires_at: now + chrono::Duration::days(30),
+            expires_at: now + chrono::Duration::days(defaults.expiration_days),
             promoted_to: None,
-            source: "pattern_detector".to_string(),
+            source: defaults.source.clone(),
             tags: Some(serde_json::json!({})),
             metadata: Some(serde_json::json!({})),
             counter_evidence: serde_json::json!([]),
@@ -356,11 +851,17 @@ impl NewCognitiveView {
     }
 
     /// Set view type
-    pub fn with_view_type(mut self, view_type: &str) -> Self {
-        self.view_type = view_type.to_string();
+    pub fn with_view_type(mut self, view_type: impl Into<ViewType>) -> Self {
+        let view_type: ViewType = view_type.into();
+        self.view_type = view_type.into();
         self
     }
 
+    /// Get the typed ViewType from the string
+    pub fn get_view_type(&self) -> ViewType {
+        self.view_type.as_str().into()
+    }
+
     /// Set description
     pub fn with_description(mut self, description: &str) -> Self {
         self.description = Some(description.to_string());
@@ -383,7 +884,7 @@ impl NewCognitiveView {
 /// Stable Concept - a promoted view that has passed the promotion gate
 ///
 /// This represents stable, validated knowledge about the user.
-#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, Serialize, Deserialize)]
 pub struct StableConcept {
     pub concept_id: Uuid,
     pub user_id: String,
@@ -507,12 +1008,29 @@ impl StableConcept {
 
     /// Check if this is the latest version in the version chain
     ///
-    /// This is a simplified check - in production, you'd query the database
-    /// to see if there are any newer versions with this as parent_concept_id.
+    /// This is a simplified check based on the deprecated flag. Use
+    /// `is_latest_in_chain` or `has_newer_version` for an accurate answer.
     pub fn is_latest_version(&self) -> bool {
         !self.is_deprecated
     }
 
+    /// Check against a loaded chain: latest means no version names this one
+    /// as its `parent_concept_id`
+    pub fn is_latest_in_chain(&self, chain: &[StableConcept]) -> bool {
+        !chain
+            .iter()
+            .any(|other| other.parent_concept_id == Some(self.concept_id))
+    }
+
+    /// Check the database for any version derived from this one
+    pub fn has_newer_version(&self, conn: &mut PgConnection) -> Result<bool> {
+        let children: i64 = stable_concepts::table
+            .filter(stable_concepts::parent_concept_id.eq(self.concept_id))
+            .count()
+            .get_result(conn)?;
+        Ok(children > 0)
+    }
+
     /// Get version number as string
     pub fn version_string(&self) -> String {
         format!("v{}", self.version)
@@ -536,44 +1054,1093 @@ impl StableConcept {
         )
     }
 
-    /// Create a rollback concept (new version based on parent)
-    ///
-    /// # Arguments
-    /// * `parent_concept` - The parent concept to rollback to
-    pub fn create_rollback_version(&self, parent_concept: &StableConcept) -> NewStableConcept {
-        let now = chrono::Utc::now();
+    /// Create a rollback concept (new version based on parent)
+    ///
+    /// # Arguments
+    /// * `parent_concept` - The parent concept to rollback to
+    pub fn create_rollback_version(&self, parent_concept: &StableConcept) -> NewStableConcept {
+        let now = chrono::Utc::now();
+
+        // Create metadata documenting the rollback
+        let metadata = serde_json::json!({
+            "rollback_from_version": self.version,
+            "rollback_to_version": parent_concept.version,
+            "rollback_at": now.to_rfc3339(),
+            "original_created_at": self.created_at.to_rfc3339(),
+        });
+
+        NewStableConcept {
+            user_id: self.user_id.clone(),
+            canonical_name: self.canonical_name.clone(),
+            display_name: parent_concept.display_name.clone(),
+            concept_type: self.concept_type.clone(),
+            description: parent_concept.description.clone(),
+            definition: parent_concept.definition.clone(),
+            version: parent_concept.version + 1,  // New version number
+            parent_concept_id: Some(parent_concept.concept_id),
+            is_deprecated: false,
+            promoted_from: parent_concept.promoted_from,
+            promoted_at: parent_concept.promoted_at,
+            promotion_confidence: parent_concept.promotion_confidence,
+            created_at: now,
+            updated_at: now,
+            deprecated_at: None,
+            access_count: 0,
+            last_accessed_at: Some(now),
+            source: format!("rollback_from_v{}", self.version),
+            tags: parent_concept.tags.clone(),
+            metadata: Some(metadata),
+        }
+    }
+}
+
+/// Order a concept's versions chronologically
+///
+/// Rollbacks reuse version numbers (the rollback of v3 to v1 is a new v2),
+/// so creation time is the primary key and version only breaks ties.
+pub fn order_version_history(mut versions: Vec<StableConcept>) -> Vec<StableConcept> {
+    versions.sort_by(|a, b| {
+        a.created_at
+            .cmp(&b.created_at)
+            .then(a.version.cmp(&b.version))
+    });
+    versions
+}
+
+/// Pick the head of a version chain
+///
+/// The active (non-deprecated) version wins; if every version is deprecated
+/// the most recent one without a successor is returned.
+pub fn select_latest_version(versions: &[StableConcept]) -> Option<&StableConcept> {
+    if let Some(active) = versions.iter().find(|c| !c.is_deprecated) {
+        return Some(active);
+    }
+    versions
+        .iter()
+        .filter(|c| c.is_latest_in_chain(versions))
+        .max_by(|a, b| {
+            a.created_at
+                .cmp(&b.created_at)
+                .then(a.version.cmp(&b.version))
+        })
+}
+
+/// Load every version of a concept, oldest first
+pub fn get_version_history(
+    conn: &mut PgConnection,
+    canonical_name: &str,
+    user_id: &str,
+) -> Result<Vec<StableConcept>> {
+    let versions: Vec<StableConcept> = stable_concepts::table
+        .filter(stable_concepts::user_id.eq(user_id))
+        .filter(stable_concepts::canonical_name.eq(canonical_name))
+        .load(conn)?;
+    Ok(order_version_history(versions))
+}
+
+/// Load the head of a concept's version chain
+pub fn get_latest_version(
+    conn: &mut PgConnection,
+    canonical_name: &str,
+    user_id: &str,
+) -> Result<Option<StableConcept>> {
+    let versions = get_version_history(conn, canonical_name, user_id)?;
+    Ok(select_latest_version(&versions).cloned())
+}
+
+/// Head of the concept chain with this canonical name, if any
+///
+/// Promotion uses it to decide between starting a chain and adding a
+/// version; see `plan_promotion`.
+pub fn find_concept_by_canonical_name(
+    conn: &mut PgConnection,
+    user_id: &str,
+    canonical_name: &str,
+) -> Result<Option<StableConcept>> {
+    get_latest_version(conn, canonical_name, user_id)
+}
+
+/// What promotion does when the canonical name is already taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateConceptPolicy {
+    /// Add the concept as the next version of the existing chain
+    #[default]
+    NewVersion,
+    /// Refuse the promotion
+    Reject,
+}
+
+/// Validated insertion of a promoted concept
+#[derive(Debug, Clone)]
+pub struct PromotionPlan {
+    /// Active head of the existing chain, to be deprecated
+    pub replaces: Option<StableConcept>,
+    /// Concept to insert, with its version and parent set
+    pub new_concept: NewStableConcept,
+}
+
+/// Decide how a promoted concept enters its canonical name's chain
+///
+/// A brand-new name starts a chain at version 1. Otherwise the concept
+/// becomes the next version after `existing` (the chain head from
+/// `find_concept_by_canonical_name`), unless the head has a different
+/// `concept_type` or `policy` is `Reject`; both fail with
+/// `DirSoulError::Config` instead of creating a duplicate.
+pub fn plan_promotion(
+    existing: Option<&StableConcept>,
+    mut concept: NewStableConcept,
+    policy: DuplicateConceptPolicy,
+) -> Result<PromotionPlan> {
+    let Some(head) = existing else {
+        concept.version = 1;
+        concept.parent_concept_id = None;
+        return Ok(PromotionPlan {
+            replaces: None,
+            new_concept: concept,
+        });
+    };
+
+    if head.concept_type != concept.concept_type {
+        return Err(DirSoulError::Config(format!(
+            "Concept {} already exists as {}, cannot promote it as {}",
+            head.canonical_name, head.concept_type, concept.concept_type
+        )));
+    }
+    if policy == DuplicateConceptPolicy::Reject {
+        return Err(DirSoulError::Config(format!(
+            "Concept {} already exists (v{})",
+            head.canonical_name, head.version
+        )));
+    }
+
+    concept.version = head.version + 1;
+    concept.parent_concept_id = Some(head.concept_id);
+    Ok(PromotionPlan {
+        replaces: head.is_active().then(|| head.clone()),
+        new_concept: concept,
+    })
+}
+
+/// Insert a promoted concept with the default `DuplicateConceptPolicy`
+///
+/// See `promote_concept_with`.
+pub fn promote_concept(
+    conn: &mut PgConnection,
+    concept: NewStableConcept,
+) -> Result<StableConcept> {
+    promote_concept_with(conn, concept, DuplicateConceptPolicy::default())
+}
+
+/// Insert a promoted concept, transactionally
+///
+/// Follows `plan_promotion`: an existing active head is deprecated before
+/// the new version is inserted (only one active version is allowed per
+/// canonical name). Returns the inserted concept.
+pub fn promote_concept_with(
+    conn: &mut PgConnection,
+    concept: NewStableConcept,
+    policy: DuplicateConceptPolicy,
+) -> Result<StableConcept> {
+    conn.transaction::<_, DirSoulError, _>(|conn| {
+        let existing =
+            find_concept_by_canonical_name(conn, &concept.user_id, &concept.canonical_name)?;
+        let plan = plan_promotion(existing.as_ref(), concept, policy)?;
+
+        if let Some(head) = &plan.replaces {
+            let deprecated = head.deprecate(Some("superseded_by_promotion".to_string()));
+            diesel::update(
+                stable_concepts::table.filter(stable_concepts::concept_id.eq(head.concept_id)),
+            )
+            .set((
+                stable_concepts::is_deprecated.eq(true),
+                stable_concepts::deprecated_at.eq(deprecated.deprecated_at),
+                stable_concepts::metadata.eq(deprecated.metadata),
+            ))
+            .execute(conn)?;
+        }
+
+        let inserted = diesel::insert_into(stable_concepts::table)
+            .values(&plan.new_concept)
+            .get_result::<StableConcept>(conn)?;
+
+        Ok(inserted)
+    })
+}
+
+/// Validated rollback of a concept chain
+#[derive(Debug, Clone)]
+pub struct RollbackPlan {
+    /// Current head, to be deprecated
+    pub head: StableConcept,
+    /// Version whose content is restored
+    pub target: StableConcept,
+    /// New head to insert
+    pub new_version: NewStableConcept,
+}
+
+/// Validate a rollback against a loaded version chain
+///
+/// Fails with `DirSoulError::Config` when `to_version` is not part of the
+/// chain or is already the head. When several rows share `to_version`
+/// (earlier rollbacks reuse numbers) the most recent one is used.
+pub fn plan_rollback(versions: &[StableConcept], to_version: i32) -> Result<RollbackPlan> {
+    let head = select_latest_version(versions)
+        .ok_or_else(|| DirSoulError::Config("Concept has no versions".to_string()))?;
+
+    let target = versions
+        .iter()
+        .filter(|c| c.version == to_version)
+        .max_by_key(|c| c.created_at)
+        .ok_or_else(|| {
+            DirSoulError::Config(format!(
+                "Version {} does not exist for concept {}",
+                to_version, head.canonical_name
+            ))
+        })?;
+
+    if target.concept_id == head.concept_id {
+        return Err(DirSoulError::Config(format!(
+            "Version {} is already the current version of {}",
+            to_version, head.canonical_name
+        )));
+    }
+
+    let mut new_version = head.create_rollback_version(target);
+    // Keep version numbers increasing along the history
+    new_version.version = versions.iter().map(|c| c.version).max().unwrap_or(0) + 1;
+
+    Ok(RollbackPlan {
+        head: head.clone(),
+        target: target.clone(),
+        new_version,
+    })
+}
+
+/// Roll a concept back to a prior version, transactionally
+///
+/// `concept_id` may be any version of the chain; it must belong to
+/// `user_id`. The current head is deprecated before the rollback version
+/// is inserted (only one active version is allowed per canonical name).
+/// The chain's rows are locked first, so a concurrent rollback waits and then
+/// rolls back from the head this one created. Returns the new head.
+pub fn rollback_concept(
+    conn: &mut PgConnection,
+    user_id: &str,
+    concept_id: Uuid,
+    to_version: i32,
+) -> Result<StableConcept> {
+    conn.transaction::<_, DirSoulError, _>(|conn| {
+        let concept: StableConcept = stable_concepts::table
+            .filter(stable_concepts::concept_id.eq(concept_id))
+            .filter(stable_concepts::user_id.eq(user_id))
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| {
+                DirSoulError::NotFound(format!("Concept {} not found for user {}", concept_id, user_id))
+            })?;
+
+        // Read the history only after locking it; otherwise two rollbacks
+        // could both deprecate the same head
+        stable_concepts::table
+            .filter(stable_concepts::user_id.eq(user_id))
+            .filter(stable_concepts::canonical_name.eq(&concept.canonical_name))
+            .select(stable_concepts::concept_id)
+            .for_update()
+            .load::<Uuid>(conn)?;
+
+        let versions = get_version_history(conn, &concept.canonical_name, user_id)?;
+        let plan = plan_rollback(&versions, to_version)?;
+
+        let deprecated = plan
+            .head
+            .deprecate(Some(format!("rollback_to_v{}", plan.target.version)));
+        diesel::update(stable_concepts::table.filter(stable_concepts::concept_id.eq(plan.head.concept_id)))
+            .set((
+                stable_concepts::is_deprecated.eq(true),
+                stable_concepts::deprecated_at.eq(deprecated.deprecated_at),
+                stable_concepts::metadata.eq(deprecated.metadata),
+            ))
+            .execute(conn)?;
+
+        let new_head = diesel::insert_into(stable_concepts::table)
+            .values(&plan.new_version)
+            .get_result::<StableConcept>(conn)?;
+
+        Ok(new_head)
+    })
+}
+
+/// Record a validation of a view with the default `ConfidenceBump` and no
+/// event confidence floor
+///
+/// See `validate_view_with`.
+pub fn validate_view(
+    conn: &mut PgConnection,
+    user_id: &str,
+    view_id: Uuid,
+    supporting_event_id: Uuid,
+) -> Result<CognitiveView> {
+    let bump = ConfidenceBump::default();
+    validate_view_with(conn, user_id, view_id, supporting_event_id, bump, 0.0)
+}
+
+/// Check that an event confidence floor is a confidence in [0, 1]
+fn validate_event_floor(min_event_confidence: f64) -> Result<()> {
+    if !(0.0..=1.0).contains(&min_event_confidence) {
+        return Err(DirSoulError::Config(format!(
+            "min_event_confidence must be between 0 and 1, got {}",
+            min_event_confidence
+        )));
+    }
+    Ok(())
+}
+
+/// Record that `supporting_event_id` validates a view, transactionally
+///
+/// Both the view and the event must belong to `user_id`, and the event's
+/// source memory must not be soft-deleted (`DirSoulError::NotFound`
+/// otherwise). An event extracted with less than `min_event_confidence` is
+/// not evidence and is rejected with `DirSoulError::Config`. The view row is
+/// locked while `CognitiveView::record_validation` updates it, so concurrent
+/// validations are all counted. Only active views can be validated
+/// (`DirSoulError::Config` otherwise). Returns the updated view.
+pub fn validate_view_with(
+    conn: &mut PgConnection,
+    user_id: &str,
+    view_id: Uuid,
+    supporting_event_id: Uuid,
+    bump: ConfidenceBump,
+    min_event_confidence: f64,
+) -> Result<CognitiveView> {
+    bump.validate()?;
+    validate_event_floor(min_event_confidence)?;
+
+    conn.transaction::<_, DirSoulError, _>(|conn| {
+        let mut view: CognitiveView = cognitive_views::table
+            .filter(cognitive_views::view_id.eq(view_id))
+            .filter(cognitive_views::user_id.eq(user_id))
+            .for_update()
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| {
+                DirSoulError::NotFound(format!(
+                    "Cognitive view {} not found for user {}",
+                    view_id, user_id
+                ))
+            })?;
+
+        let event_confidence: Option<f64> = event_memories::table
+            .inner_join(raw_memories::table)
+            .filter(event_memories::event_id.eq(supporting_event_id))
+            .filter(event_memories::user_id.eq(user_id))
+            .filter(raw_memories::deleted_at.is_null())
+            .select(event_memories::confidence)
+            .first(conn)
+            .optional()?;
+        match event_confidence {
+            None => {
+                return Err(DirSoulError::NotFound(format!(
+                    "Event {} not found for user {}",
+                    supporting_event_id, user_id
+                )));
+            }
+            Some(confidence) if confidence < min_event_confidence => {
+                return Err(DirSoulError::Config(format!(
+                    "Event {} has confidence {:.2}, below the evidence floor {:.2}",
+                    supporting_event_id, confidence, min_event_confidence
+                )));
+            }
+            Some(_) => {}
+        }
+
+        if !view.get_status().is_active() {
+            return Err(DirSoulError::Config(format!(
+                "Cognitive view {} is {} and cannot be validated",
+                view_id, view.status
+            )));
+        }
+
+        if view.record_validation(supporting_event_id, bump, chrono::Utc::now())? {
+            diesel::update(cognitive_views::table.filter(cognitive_views::view_id.eq(view_id)))
+                .set((
+                    cognitive_views::derived_from.eq(&view.derived_from),
+                    cognitive_views::evidence_count.eq(view.evidence_count),
+                    cognitive_views::validation_count.eq(view.validation_count),
+                    cognitive_views::last_validated_at.eq(view.last_validated_at),
+                    cognitive_views::confidence.eq(view.confidence),
+                    cognitive_views::updated_at.eq(view.updated_at),
+                ))
+                .execute(conn)?;
+        }
+
+        Ok(view)
+    })
+}
+
+/// Metadata key holding the position of the last event scanned for a view
+///
+/// An object `{"ingested_at": rfc3339, "event_id": uuid}`: events are
+/// scanned in ingestion order, so backdated events ingested after the last
+/// run are still seen, and the event id breaks ties between events ingested
+/// in the same instant.
+const REVALIDATED_THROUGH_KEY: &str = "revalidated_through";
+
+/// Where the last re-validation of a view stopped
+///
+/// Views scanned before the cursor held an event id store a bare
+/// timestamp; it is read as that instant with the nil id.
+fn revalidation_cursor(view: &CognitiveView) -> (chrono::DateTime<chrono::Utc>, Uuid) {
+    let stored = view.metadata.as_ref().and_then(|m| m.get(REVALIDATED_THROUGH_KEY));
+    let parse_time = |v: &serde_json::Value| {
+        v.as_str()
+            .and_then(|v| chrono::DateTime::parse_from_rfc3339(v).ok())
+            .map(|v| v.with_timezone(&chrono::Utc))
+    };
+
+    match stored {
+        Some(serde_json::Value::String(_)) => stored
+            .and_then(parse_time)
+            .map(|at| (at, Uuid::nil()))
+            .unwrap_or((view.created_at, Uuid::nil())),
+        Some(cursor) => {
+            let at = cursor.get("ingested_at").and_then(parse_time);
+            let event_id = cursor
+                .get("event_id")
+                .and_then(|v| v.as_str())
+                .and_then(|v| Uuid::parse_str(v).ok())
+                .unwrap_or(Uuid::nil());
+            at.map(|at| (at, event_id)).unwrap_or((view.created_at, Uuid::nil()))
+        }
+        None => (view.created_at, Uuid::nil()),
+    }
+}
+
+/// Metadata key flagging a view that passed the gate and awaits approval
+const READY_FOR_PROMOTION_KEY: &str = "ready_for_promotion";
+
+/// Limits and thresholds for background view re-validation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RevalidationConfig {
+    /// Thresholds deciding rejection and promotion
+    pub gate: PromotionGateConfig,
+    /// Confidence raise per supporting event
+    pub bump: ConfidenceBump,
+    /// Lexicon used to match events to hypotheses
+    pub lexicon: LexiconSet,
+    /// Most views re-scored per run (least recently updated first)
+    pub max_views_per_run: i64,
+    /// Most new events scanned per view per run
+    pub max_events_per_view: i64,
+    /// Promote views that pass the gate into stable concepts; when false
+    /// they are only flagged for approval through `approve_view_promotion`
+    pub auto_promote: bool,
+    /// Events extracted with lower confidence are not scanned as evidence
+    /// (0 keeps every event)
+    #[serde(default)]
+    pub min_event_confidence: f64,
+}
+
+impl Default for RevalidationConfig {
+    fn default() -> Self {
+        Self {
+            gate: PromotionGateConfig::default(),
+            bump: ConfidenceBump::default(),
+            lexicon: LexiconSet::default(),
+            max_views_per_run: 100,
+            max_events_per_view: 200,
+            auto_promote: false,
+            min_event_confidence: 0.0,
+        }
+    }
+}
+
+impl RevalidationConfig {
+    /// Check the limits and the nested thresholds
+    pub fn validate(&self) -> Result<()> {
+        if self.max_views_per_run <= 0 || self.max_events_per_view <= 0 {
+            return Err(DirSoulError::Config(
+                "max_views_per_run and max_events_per_view must be positive".to_string(),
+            ));
+        }
+        validate_event_floor(self.min_event_confidence)?;
+        self.gate.validate()?;
+        self.bump.validate()?;
+        self.lexicon.validate()
+    }
+}
+
+/// Outcome of one `revalidate_active_views` run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RevalidationReport {
+    /// Views re-scored
+    pub views_checked: usize,
+    /// Events read across all views
+    pub events_scanned: usize,
+    /// Events recorded as validations
+    pub supporting_events: usize,
+    /// Events recorded as counter-evidence
+    pub contradicting_events: usize,
+    /// Views moved to `rejected`
+    pub rejected: Vec<Uuid>,
+    /// Views passing the Promotion Gate (including the promoted ones)
+    pub ready_for_promotion: Vec<Uuid>,
+    /// Views promoted to stable concepts (`auto_promote` only)
+    pub promoted: Vec<Uuid>,
+    /// Views whose re-validation failed, with the error
+    pub failures: Vec<(Uuid, String)>,
+}
+
+/// Re-score a user's active views against events seen since the last run
+///
+/// Meant to run on a schedule (see `spawn_revalidation_loop`). Each run checks at most
+/// `max_views_per_run` views and `max_events_per_view` events per view,
+/// remembering per view how far it has scanned, so later runs pick up where
+/// this one stopped. Views are rejected or flagged (and optionally promoted)
+/// per the Promotion Gate, with the user's overrides applied to
+/// `config.gate`. A failing view is logged and reported without stopping
+/// the others.
+pub fn revalidate_active_views(
+    conn: &mut PgConnection,
+    user_id: &str,
+    config: &RevalidationConfig,
+) -> Result<RevalidationReport> {
+    config.validate()?;
+    let config = &RevalidationConfig {
+        gate: UserSettings::get(conn, user_id)?.promotion_gate(&config.gate),
+        ..config.clone()
+    };
+
+    let view_ids: Vec<Uuid> = cognitive_views::table
+        .filter(cognitive_views::user_id.eq(user_id))
+        .filter(cognitive_views::status.eq(String::from(ViewStatus::Active)))
+        .filter(cognitive_views::expires_at.gt(chrono::Utc::now()))
+        .order(cognitive_views::updated_at.asc())
+        .limit(config.max_views_per_run)
+        .select(cognitive_views::view_id)
+        .load(conn)?;
+
+    let mut report = RevalidationReport::default();
+    for view_id in view_ids {
+        if let Err(e) = revalidate_view(conn, view_id, config, &mut report) {
+            tracing::warn!("Re-validation failed for view {}: {}", view_id, e);
+            report.failures.push((view_id, e.to_string()));
+        }
+    }
+
+    tracing::info!(
+        "Re-validated {} views for {}: {} supporting, {} contradicting, {} rejected, {} ready for promotion",
+        report.views_checked,
+        user_id,
+        report.supporting_events,
+        report.contradicting_events,
+        report.rejected.len(),
+        report.ready_for_promotion.len()
+    );
+
+    Ok(report)
+}
+
+/// Users owning at least one active, unexpired view
+pub fn users_with_active_views(conn: &mut PgConnection) -> Result<Vec<String>> {
+    Ok(cognitive_views::table
+        .filter(cognitive_views::status.eq(String::from(ViewStatus::Active)))
+        .filter(cognitive_views::expires_at.gt(chrono::Utc::now()))
+        .select(cognitive_views::user_id)
+        .distinct()
+        .load(conn)?)
+}
+
+/// Run `revalidate_active_views` for every user with active views on a
+/// Tokio interval
+///
+/// The first tick fires immediately. Each tick runs on the blocking pool
+/// with its own connection; a failing user is logged without stopping the
+/// others or later ticks. With `webhooks`, subscribers are told about each
+/// rejected view and promoted concept.
+pub fn spawn_revalidation_loop(
+    database_url: String,
+    interval: std::time::Duration,
+    config: RevalidationConfig,
+    webhooks: Option<WebhookNotifier>,
+) -> tokio::task::JoinHandle<()> {
+    let config = std::sync::Arc::new(config);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            ticker.tick().await;
+
+            let database_url = database_url.clone();
+            let config = std::sync::Arc::clone(&config);
+            let webhooks = webhooks.clone();
+            let run = tokio::task::spawn_blocking(move || -> Result<()> {
+                let mut conn = PgConnection::establish(&database_url)?;
+                for user_id in users_with_active_views(&mut conn)? {
+                    let report = match revalidate_active_views(&mut conn, &user_id, &config) {
+                        Ok(report) => report,
+                        Err(e) => {
+                            tracing::warn!("View re-validation failed for {}: {}", user_id, e);
+                            continue;
+                        }
+                    };
+                    if let Some(webhooks) = &webhooks {
+                        if let Err(e) = notify_revalidation(&mut conn, &report, webhooks) {
+                            tracing::warn!("Re-validation webhooks failed for {}: {}", user_id, e);
+                        }
+                    }
+                }
+                Ok(())
+            });
+
+            match run.await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::warn!("View re-validation tick failed: {}", e),
+                Err(e) => tracing::warn!("View re-validation tick aborted: {}", e),
+            }
+        }
+    })
+}
+
+/// Send the webhooks for the views a re-validation run rejected or promoted
+fn notify_revalidation(
+    conn: &mut PgConnection,
+    report: &RevalidationReport,
+    webhooks: &WebhookNotifier,
+) -> Result<()> {
+    if !report.rejected.is_empty() {
+        let rejected: Vec<CognitiveView> = cognitive_views::table
+            .filter(cognitive_views::view_id.eq_any(&report.rejected))
+            .load(conn)?;
+        for view in &rejected {
+            webhooks.notify(WebhookEvent::view_rejected(view));
+        }
+    }
+    if !report.promoted.is_empty() {
+        let promoted_to: Vec<Option<Uuid>> = cognitive_views::table
+            .filter(cognitive_views::view_id.eq_any(&report.promoted))
+            .select(cognitive_views::promoted_to)
+            .load(conn)?;
+        let concept_ids: Vec<Uuid> = promoted_to.into_iter().flatten().collect();
+        let concepts: Vec<StableConcept> = stable_concepts::table
+            .filter(stable_concepts::concept_id.eq_any(concept_ids))
+            .load(conn)?;
+        for concept in &concepts {
+            webhooks.notify(WebhookEvent::concept_promoted(concept));
+        }
+    }
+    Ok(())
+}
+
+/// Re-score one view inside a transaction holding its row lock
+fn revalidate_view(
+    conn: &mut PgConnection,
+    view_id: Uuid,
+    config: &RevalidationConfig,
+    report: &mut RevalidationReport,
+) -> Result<()> {
+    conn.transaction::<_, DirSoulError, _>(|conn| {
+        let mut view: CognitiveView = cognitive_views::table
+            .filter(cognitive_views::view_id.eq(view_id))
+            .for_update()
+            .first(conn)?;
+        // Changed by another writer since the ids were loaded
+        if !view.get_status().is_active() {
+            return Ok(());
+        }
+
+        // Keyset on (ingestion time, event id) of the live source memory
+        let (ingested_after, after_event) = revalidation_cursor(&view);
+        let page: Vec<(EventMemory, chrono::DateTime<chrono::Utc>)> = event_memories::table
+            .inner_join(raw_memories::table)
+            .filter(event_memories::user_id.eq(&view.user_id))
+            .filter(raw_memories::user_id.eq(&view.user_id))
+            .filter(raw_memories::deleted_at.is_null())
+            .filter(event_memories::confidence.ge(config.min_event_confidence))
+            .filter(
+                raw_memories::created_at.gt(ingested_after).or(raw_memories::created_at
+                    .eq(ingested_after)
+                    .and(event_memories::event_id.gt(after_event))),
+            )
+            .order((raw_memories::created_at.asc(), event_memories::event_id.asc()))
+            .limit(config.max_events_per_view)
+            .select((event_memories::all_columns, raw_memories::created_at))
+            .load(conn)?;
+        let cursor = page.last().map(|(event, ingested_at)| (*ingested_at, event.event_id));
+        let events: Vec<EventMemory> = page.into_iter().map(|(event, _)| event).collect();
+
+        let now = chrono::Utc::now();
+        let (supporting, contradicting) =
+            view.apply_evidence(&events, &config.lexicon, config.bump, now)?;
+
+        if let Some((ingested_at, event_id)) = cursor {
+            let mut metadata = view.metadata.take().unwrap_or_else(|| serde_json::json!({}));
+            if let Some(map) = metadata.as_object_mut() {
+                map.insert(
+                    REVALIDATED_THROUGH_KEY.to_string(),
+                    serde_json::json!({
+                        "ingested_at": ingested_at.to_rfc3339(),
+                        "event_id": event_id,
+                    }),
+                );
+            }
+            view.metadata = Some(metadata);
+        }
+        view.updated_at = now;
+
+        let decision = view.evaluate(&config.gate);
+        match decision {
+            ViewDecision::Reject => {
+                view.status = ViewStatus::Rejected.into();
+                view.set_flagged_for_promotion(false);
+            }
+            ViewDecision::Promote if config.auto_promote => {
+                finish_promotion(conn, &mut view, "auto")?;
+            }
+            ViewDecision::Promote => view.set_flagged_for_promotion(true),
+            ViewDecision::Keep => view.set_flagged_for_promotion(false),
+        }
+
+        diesel::update(cognitive_views::table.filter(cognitive_views::view_id.eq(view_id)))
+            .set((
+                cognitive_views::derived_from.eq(&view.derived_from),
+                cognitive_views::evidence_count.eq(view.evidence_count),
+                cognitive_views::validation_count.eq(view.validation_count),
+                cognitive_views::last_validated_at.eq(view.last_validated_at),
+                cognitive_views::confidence.eq(view.confidence),
+                cognitive_views::counter_evidence.eq(&view.counter_evidence),
+                cognitive_views::counter_evidence_count.eq(view.counter_evidence_count),
+                cognitive_views::status.eq(&view.status),
+                cognitive_views::promoted_to.eq(view.promoted_to),
+                cognitive_views::metadata.eq(&view.metadata),
+                cognitive_views::updated_at.eq(view.updated_at),
+            ))
+            .execute(conn)?;
+
+        match decision {
+            ViewDecision::Reject => report.rejected.push(view_id),
+            ViewDecision::Promote => {
+                report.ready_for_promotion.push(view_id);
+                if config.auto_promote {
+                    report.promoted.push(view_id);
+                }
+            }
+            ViewDecision::Keep => {}
+        }
+        report.views_checked += 1;
+        report.events_scanned += events.len();
+        report.supporting_events += supporting;
+        report.contradicting_events += contradicting;
+        Ok(())
+    })
+}
+
+/// A user's active views flagged `ready_for_promotion`, oldest update first
+///
+/// These passed the Promotion Gate during re-validation with
+/// `auto_promote` off and wait for `approve_view_promotion`.
+pub fn list_views_ready_for_promotion(
+    conn: &mut PgConnection,
+    user_id: &str,
+) -> Result<Vec<CognitiveView>> {
+    let views: Vec<CognitiveView> = cognitive_views::table
+        .filter(cognitive_views::user_id.eq(user_id))
+        .filter(cognitive_views::status.eq(String::from(ViewStatus::Active)))
+        .filter(cognitive_views::expires_at.gt(chrono::Utc::now()))
+        .order(cognitive_views::updated_at.asc())
+        .load(conn)?;
+
+    Ok(views.into_iter().filter(|v| v.is_flagged_for_promotion()).collect())
+}
+
+/// Gate and near-miss margin for the promotion review queue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromotionCandidateConfig {
+    /// Thresholds the views are checked against
+    pub gate: PromotionGateConfig,
+    /// How far below (or, for the counter-evidence ratio, above) a threshold
+    /// a failing criterion may be and still count as a near miss, as a
+    /// fraction of the threshold; 0 lists only views that pass
+    pub near_miss_margin: f64,
+}
+
+impl Default for PromotionCandidateConfig {
+    fn default() -> Self {
+        Self {
+            gate: PromotionGateConfig::default(),
+            near_miss_margin: 0.1,
+        }
+    }
+}
+
+impl PromotionCandidateConfig {
+    /// Check the margin and the nested thresholds
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.near_miss_margin) {
+            return Err(DirSoulError::Config(format!(
+                "near_miss_margin must be between 0 and 1, got {}",
+                self.near_miss_margin
+            )));
+        }
+        self.gate.validate()
+    }
+}
+
+/// A view in the promotion review queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromotionCandidate {
+    pub view: CognitiveView,
+    pub readiness: CandidateReadiness,
+    /// Every gate criterion, passing or not
+    pub criteria: Vec<CriterionCheck>,
+}
+
+impl PromotionCandidate {
+    /// Classify `view`, or `None` if it is neither ready nor a near miss
+    ///
+    /// Views that cannot be promoted or that the gate would reject are
+    /// never candidates.
+    pub fn classify(view: CognitiveView, config: &PromotionCandidateConfig) -> Option<Self> {
+        if !view.get_status().can_be_promoted() || view.should_be_rejected_with(&config.gate) {
+            return None;
+        }
+
+        let criteria = view.gate_breakdown(&config.gate, config.near_miss_margin);
+        let readiness = if criteria.iter().all(|c| c.passed) {
+            CandidateReadiness::Ready
+        } else if criteria.iter().all(|c| c.passed || c.near_miss) {
+            CandidateReadiness::NearMiss
+        } else {
+            return None;
+        };
+
+        Some(Self {
+            view,
+            readiness,
+            criteria,
+        })
+    }
+}
+
+/// One page of `list_promotion_candidates`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromotionCandidatePage {
+    /// Ready views first, then near misses, each by confidence descending
+    pub candidates: Vec<PromotionCandidate>,
+    /// Candidates across all pages
+    pub total: usize,
+    /// Offset of the next page, if any
+    pub next_offset: Option<usize>,
+}
+
+/// Lifetime of a view in whole days, as `(expires_at - created_at).num_days()`
+const CANDIDATE_SPAN_DAYS_SQL: &str =
+    "trunc((extract(epoch FROM expires_at - created_at) / 86400)::float8)";
+
+/// `CognitiveView::counter_evidence_ratio` in SQL
+const CANDIDATE_COUNTER_RATIO_SQL: &str = "(CASE WHEN evidence_count = 0 THEN 0
+      ELSE counter_evidence_count::float8 / evidence_count END)";
+
+#[derive(QueryableByName)]
+struct CandidateCountRow {
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    total: i64,
+}
+
+#[derive(QueryableByName)]
+struct CandidateIdRow {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    view_id: Uuid,
+}
+
+/// A page of the user's active views that pass, or nearly pass, the gate
+///
+/// Unlike `list_views_ready_for_promotion` this evaluates the views now
+/// instead of relying on the flag set by re-validation. Filtering, ordering
+/// and paging happen in SQL, mirroring `PromotionCandidate::classify`, so
+/// only the requested page is loaded.
+pub fn list_promotion_candidates(
+    conn: &mut PgConnection,
+    user_id: &str,
+    config: &PromotionCandidateConfig,
+    offset: usize,
+    limit: usize,
+) -> Result<PromotionCandidatePage> {
+    use diesel::sql_types::{BigInt, Double, Text};
+
+    config.validate()?;
+    if limit == 0 {
+        return Err(DirSoulError::Config("limit must be positive".to_string()));
+    }
 
-        // Create metadata documenting the rollback
-        let metadata = serde_json::json!({
-            "rollback_from_version": self.version,
-            "rollback_to_version": parent_concept.version,
-            "rollback_at": now.to_rfc3339(),
-            "original_created_at": self.created_at.to_rfc3339(),
-        });
+    // A criterion passes or is a near miss once it clears the margin-adjusted
+    // threshold; views over the auto-reject ratio are never candidates
+    let gate = &config.gate;
+    let margin = config.near_miss_margin;
+    let ratio_cap = (gate.gate_counter_ratio * (1.0 + margin)).min(gate.auto_reject_counter_ratio);
+    let filter = format!(
+        "FROM cognitive_views
+         WHERE user_id = $1 AND status = 'active' AND expires_at > NOW()
+           AND confidence >= $2
+           AND validation_count >= $3
+           AND {span} >= $4
+           AND {ratio} <= $5",
+        span = CANDIDATE_SPAN_DAYS_SQL,
+        ratio = CANDIDATE_COUNTER_RATIO_SQL,
+    );
+    let floors = [
+        gate.min_confidence * (1.0 - margin),
+        gate.min_validation_count as f64 * (1.0 - margin),
+        gate.min_time_span_days as f64 * (1.0 - margin),
+        ratio_cap,
+    ];
+
+    let total = diesel::sql_query(format!("SELECT COUNT(*) AS total {}", filter))
+        .bind::<Text, _>(user_id)
+        .bind::<Double, _>(floors[0])
+        .bind::<Double, _>(floors[1])
+        .bind::<Double, _>(floors[2])
+        .bind::<Double, _>(floors[3])
+        .get_result::<CandidateCountRow>(conn)?
+        .total as usize;
+
+    // Ready views (every criterion passes) first, each group by confidence
+    let ids: Vec<Uuid> = diesel::sql_query(format!(
+        "SELECT view_id {filter}
+         ORDER BY (confidence > $6 AND validation_count >= $7 AND {span} >= $8
+                   AND {ratio} < $9) DESC,
+                  confidence DESC, view_id ASC
+         OFFSET $10 LIMIT $11",
+        filter = filter,
+        span = CANDIDATE_SPAN_DAYS_SQL,
+        ratio = CANDIDATE_COUNTER_RATIO_SQL,
+    ))
+    .bind::<Text, _>(user_id)
+    .bind::<Double, _>(floors[0])
+    .bind::<Double, _>(floors[1])
+    .bind::<Double, _>(floors[2])
+    .bind::<Double, _>(floors[3])
+    .bind::<Double, _>(gate.min_confidence)
+    .bind::<Double, _>(gate.min_validation_count as f64)
+    .bind::<Double, _>(gate.min_time_span_days as f64)
+    .bind::<Double, _>(gate.gate_counter_ratio)
+    .bind::<BigInt, _>(offset as i64)
+    .bind::<BigInt, _>(limit as i64)
+    .load::<CandidateIdRow>(conn)?
+    .into_iter()
+    .map(|row| row.view_id)
+    .collect();
+
+    let mut views: Vec<CognitiveView> = cognitive_views::table
+        .filter(cognitive_views::view_id.eq_any(&ids))
+        .load(conn)?;
+    views.sort_by_key(|view| ids.iter().position(|id| *id == view.view_id));
+    let candidates: Vec<PromotionCandidate> = views
+        .into_iter()
+        .filter_map(|view| PromotionCandidate::classify(view, config))
+        .collect();
+
+    let next_offset = Some(offset + ids.len()).filter(|&next| next < total);
+    Ok(PromotionCandidatePage {
+        candidates,
+        total,
+        next_offset,
+    })
+}
 
-        NewStableConcept {
-            user_id: self.user_id.clone(),
-            canonical_name: self.canonical_name.clone(),
-            display_name: parent_concept.display_name.clone(),
-            concept_type: self.concept_type.clone(),
-            description: parent_concept.description.clone(),
-            definition: parent_concept.definition.clone(),
-            version: parent_concept.version + 1,  // New version number
-            parent_concept_id: Some(parent_concept.concept_id),
-            is_deprecated: false,
-            promoted_from: parent_concept.promoted_from,
-            promoted_at: parent_concept.promoted_at,
-            promotion_confidence: parent_concept.promotion_confidence,
-            created_at: now,
-            updated_at: now,
-            deprecated_at: None,
-            access_count: 0,
-            last_accessed_at: Some(now),
-            source: format!("rollback_from_v{}", self.version),
-            tags: parent_concept.tags.clone(),
-            metadata: Some(metadata),
+/// Promote a view flagged `ready_for_promotion`, transactionally
+///
+/// The manual counterpart of `auto_promote`: the view must belong to
+/// `user_id`, still be active, carry the flag and still pass `gate` with
+/// the user's overrides applied (they may have tightened since it was
+/// flagged). Returns the promoted view.
+pub fn approve_view_promotion(
+    conn: &mut PgConnection,
+    user_id: &str,
+    view_id: Uuid,
+    gate: &PromotionGateConfig,
+) -> Result<CognitiveView> {
+    let gate = UserSettings::get(conn, user_id)?.promotion_gate(gate);
+
+    conn.transaction::<_, DirSoulError, _>(|conn| {
+        let mut view: CognitiveView = cognitive_views::table
+            .filter(cognitive_views::view_id.eq(view_id))
+            .filter(cognitive_views::user_id.eq(user_id))
+            .for_update()
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| {
+                DirSoulError::NotFound(format!("View {} not found for user {}", view_id, user_id))
+            })?;
+
+        if !view.get_status().is_active() {
+            return Err(DirSoulError::Config(format!(
+                "View {} is {}, only active views can be promoted",
+                view_id, view.status
+            )));
         }
-    }
+        if !view.is_flagged_for_promotion() || view.evaluate(&gate) != ViewDecision::Promote {
+            return Err(DirSoulError::Config(format!(
+                "View {} has not passed the Promotion Gate",
+                view_id
+            )));
+        }
+
+        view.updated_at = chrono::Utc::now();
+        finish_promotion(conn, &mut view, "manual")?;
+
+        diesel::update(cognitive_views::table.filter(cognitive_views::view_id.eq(view_id)))
+            .set((
+                cognitive_views::status.eq(&view.status),
+                cognitive_views::promoted_to.eq(view.promoted_to),
+                cognitive_views::metadata.eq(&view.metadata),
+                cognitive_views::updated_at.eq(view.updated_at),
+            ))
+            .execute(conn)?;
+
+        Ok(view)
+    })
+}
+
+/// Promote `view` in memory and record it in the audit log
+///
+/// `mode` ("auto" or "manual") says who approved it. The caller writes the
+/// view row.
+fn finish_promotion(conn: &mut PgConnection, view: &mut CognitiveView, mode: &str) -> Result<()> {
+    let concept_id = promote_view(conn, view)?;
+    view.promoted_to = Some(concept_id);
+    view.status = ViewStatus::Promoted.into();
+    view.set_flagged_for_promotion(false);
+
+    diesel::insert_into(audit_logs::table)
+        .values(
+            &NewAuditLog::new(view.user_id.clone(), "promote".to_string(), "cognitive_view".to_string())
+                .with_metadata(serde_json::json!({
+                    "view_id": view.view_id,
+                    "concept_id": concept_id,
+                    "mode": mode,
+                })),
+        )
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Stable concept a promoted view graduates into
+///
+/// The hypothesis is the canonical name; if a concept of that name already
+/// exists the view becomes its next version (see `promote_concept`).
+fn promote_view(conn: &mut PgConnection, view: &CognitiveView) -> Result<Uuid> {
+    let concept = NewStableConcept::from_view(
+        view.user_id.clone(),
+        view.hypothesis.trim().to_string(),
+        view.hypothesis.clone(),
+        view.view_type.clone(),
+        view.view_id,
+        view.confidence,
+    );
+    Ok(promote_concept(conn, concept)?.concept_id)
 }
 
 /// New Stable Concept for insertion
@@ -660,6 +2227,35 @@ mod tests {
         assert_eq!(status2, ViewStatus::Active);
     }
 
+    #[test]
+    fn test_view_type_round_trip() {
+        for name in ["preference", "habit", "pattern", "trend", "anomaly", "routine"] {
+            let view_type: ViewType = name.into();
+            assert!(!matches!(view_type, ViewType::Custom(_)), "{}", name);
+            assert_eq!(String::from(view_type), name);
+        }
+
+        assert_eq!(ViewType::from(" Habit "), ViewType::Habit);
+        assert_eq!(ViewType::from("mood"), ViewType::Custom("mood".to_string()));
+        assert_eq!(String::from(ViewType::Custom("mood".to_string())), "mood");
+
+        let json = serde_json::to_string(&ViewType::Trend).unwrap();
+        assert_eq!(json, "\"trend\"");
+        assert_eq!(serde_json::from_str::<ViewType>(&json).unwrap(), ViewType::Trend);
+    }
+
+    #[test]
+    fn test_new_cognitive_view_accepts_typed_and_string_view_types() {
+        let typed = NewCognitiveView::new("u".to_string(), "h".to_string(), ViewType::Habit, vec![]);
+        let legacy =
+            NewCognitiveView::new("u".to_string(), "h".to_string(), "Habit".to_string(), vec![]);
+        assert_eq!(typed.view_type, "habit");
+        assert_eq!(legacy.get_view_type(), typed.get_view_type());
+
+        let custom = typed.with_view_type("mood");
+        assert_eq!(custom.get_view_type(), ViewType::Custom("mood".to_string()));
+    }
+
     #[test]
     fn test_view_status_active() {
         assert!(ViewStatus::Active.is_active());
@@ -683,6 +2279,49 @@ mod tests {
         assert!(view.expires_at > chrono::Utc::now());
     }
 
+    #[test]
+    fn test_new_cognitive_view_with_defaults() {
+        let defaults = ViewDefaults::default()
+            .with_confidence(0.8)
+            .with_expiration_days(7)
+            .with_source("conversation");
+        let view = NewCognitiveView::new_with_defaults(
+            "test_user".to_string(),
+            "用户每天早上跑步".to_string(),
+            "habit".to_string(),
+            vec![Uuid::new_v4()],
+            &defaults,
+        );
+
+        assert_eq!(view.confidence, 0.8);
+        assert_eq!(view.source, "conversation");
+        let lifetime = view.expires_at - view.created_at;
+        assert_eq!(lifetime, chrono::Duration::days(7));
+
+        // Builder calls still override the defaults
+        let view = view.with_confidence(0.6);
+        assert_eq!(view.confidence, 0.6);
+    }
+
+    #[test]
+    fn test_new_cognitive_view_matches_default_view_defaults() {
+        let view = NewCognitiveView::new(
+            "test_user".to_string(),
+            "用户喜欢吃水果".to_string(),
+            "preference".to_string(),
+            vec![],
+        );
+        let defaults = ViewDefaults::default();
+
+        assert_eq!(view.confidence, defaults.confidence);
+        assert_eq!(view.source, defaults.source);
+        assert_eq!(
+            view.expires_at - view.created_at,
+            chrono::Duration::days(defaults.expiration_days)
+        );
+        assert_eq!(ViewDefaults::default().with_confidence(1.5).confidence, 1.0);
+    }
+
     #[test]
     fn test_cognitive_view_ready_for_promotion() {
         let mut view = CognitiveView {
@@ -855,6 +2494,362 @@ mod tests {
         assert!(!view.is_ready_for_promotion()); // Should fail at >= 15%
     }
 
+    #[test]
+    fn test_gate_breakdown_matches_gate() {
+        let config = PromotionGateConfig::default();
+        let mut view = gate_test_view(20, 0);
+        let checks = view.gate_breakdown(&config, 0.1);
+        assert_eq!(checks.len(), 4);
+        assert!(checks.iter().all(|c| c.passed && !c.near_miss));
+        assert!(view.is_ready_for_promotion_with(&config));
+
+        view.validation_count = 2;
+        let checks = view.gate_breakdown(&config, 0.1);
+        let validations = checks[1];
+        assert_eq!(validations.criterion, GateCriterion::ValidationCount);
+        assert!(!validations.passed);
+        assert_eq!((validations.value, validations.threshold), (2.0, 3.0));
+        assert!(!view.is_ready_for_promotion_with(&config));
+    }
+
+    #[test]
+    fn test_gate_breakdown_near_miss_margin() {
+        let config = PromotionGateConfig::default();
+        let mut view = gate_test_view(20, 0);
+        view.confidence = 0.8;
+
+        let confidence = view.gate_breakdown(&config, 0.1)[0];
+        assert_eq!(confidence.criterion, GateCriterion::Confidence);
+        assert!(!confidence.passed);
+        assert!(confidence.near_miss); // 0.8 >= 0.85 * 0.9
+        assert!(!view.gate_breakdown(&config, 0.05)[0].near_miss); // 0.8 < 0.85 * 0.95
+
+        // Exactly at a strict threshold fails but is near
+        view.confidence = 0.85;
+        let confidence = view.gate_breakdown(&config, 0.0)[0];
+        assert!(!confidence.passed && confidence.near_miss);
+
+        // The counter-evidence ratio may be slightly above its threshold
+        let view = gate_test_view(100, 16);
+        let ratio = view.gate_breakdown(&config, 0.1)[3];
+        assert_eq!(ratio.criterion, GateCriterion::CounterEvidenceRatio);
+        assert!(!ratio.passed && ratio.near_miss); // 0.16 <= 0.15 * 1.1
+        assert!(!view.gate_breakdown(&config, 0.05)[3].near_miss);
+    }
+
+    #[test]
+    fn test_classify_promotion_candidate() {
+        let config = PromotionCandidateConfig::default();
+
+        let ready = PromotionCandidate::classify(gate_test_view(20, 0), &config).unwrap();
+        assert_eq!(ready.readiness, CandidateReadiness::Ready);
+        assert_eq!(ready.criteria.len(), 4);
+
+        let mut near = gate_test_view(20, 0);
+        near.confidence = 0.8;
+        let near = PromotionCandidate::classify(near, &config).unwrap();
+        assert_eq!(near.readiness, CandidateReadiness::NearMiss);
+
+        let mut far = gate_test_view(20, 0);
+        far.confidence = 0.5;
+        assert!(PromotionCandidate::classify(far, &config).is_none());
+
+        let mut promoted = gate_test_view(20, 0);
+        promoted.status = ViewStatus::Promoted.into();
+        assert!(PromotionCandidate::classify(promoted, &config).is_none());
+
+        // A zero margin lists only views that pass
+        let strict = PromotionCandidateConfig {
+            near_miss_margin: 0.0,
+            ..Default::default()
+        };
+        let mut near = gate_test_view(20, 0);
+        near.confidence = 0.8;
+        assert!(PromotionCandidate::classify(near, &strict).is_none());
+    }
+
+    #[test]
+    fn test_promotion_candidate_config_validation() {
+        assert!(PromotionCandidateConfig::default().validate().is_ok());
+        let config = PromotionCandidateConfig {
+            near_miss_margin: 1.5,
+            ..Default::default()
+        };
+        assert!(matches!(config.validate(), Err(DirSoulError::Config(_))));
+    }
+
+    fn gate_test_view(evidence_count: i32, counter_evidence_count: i32) -> CognitiveView {
+        CognitiveView {
+            view_id: Uuid::new_v4(),
+            user_id: "test_user".to_string(),
+            hypothesis: "用户喜欢吃水果".to_string(),
+            view_type: "preference".to_string(),
+            description: None,
+            derived_from: serde_json::json!([]),
+            evidence_count,
+            confidence: 0.9,
+            validation_count: 5,
+            last_validated_at: None,
+            status: ViewStatus::Active.into(),
+            created_at: chrono::Utc::now() - chrono::Duration::days(35),
+            updated_at: chrono::Utc::now(),
+            expires_at: chrono::Utc::now() + chrono::Duration::days(5),
+            promoted_to: None,
+            source: "test".to_string(),
+            tags: None,
+            metadata: None,
+            counter_evidence: serde_json::json!([]),
+            counter_evidence_count,
+        }
+    }
+
+    fn hypothesis_view(hypothesis: &str) -> CognitiveView {
+        CognitiveView {
+            hypothesis: hypothesis.to_string(),
+            ..gate_test_view(10, 0)
+        }
+    }
+
+    #[test]
+    fn test_conflict_detection_uses_custom_lexicon() {
+        let lexicon = LexiconSet::from_toml_str(
+            r#"
+            sentiment_words = ["偏爱", "排斥"]
+            contradiction_pairs = [["偏爱", "排斥"]]
+            "#,
+        )
+        .unwrap();
+
+        let prefer = hypothesis_view("偏爱吃水果");
+        let reject = hypothesis_view("排斥吃水果");
+
+        // Unknown to the built-in lexicon
+        assert!(!prefer.has_conflict_with(&reject));
+        assert!(prefer.has_conflict_with_lexicon(&reject, &lexicon));
+        assert!(reject.has_conflict_with_lexicon(&prefer, &lexicon));
+
+        // A negation marker flips the added positive term
+        assert!(prefer.has_conflict_with_lexicon(&hypothesis_view("不偏爱吃水果"), &lexicon));
+
+        // The added sentiment word is not mistaken for a shared target
+        assert!(!hypothesis_view("偏爱吃蔬菜")
+            .has_conflict_with_lexicon(&hypothesis_view("不偏爱吃水果"), &lexicon));
+
+        // Built-in pairs still apply
+        assert!(hypothesis_view("喜欢吃水果")
+            .has_conflict_with_lexicon(&hypothesis_view("讨厌吃水果"), &lexicon));
+    }
+
+    #[test]
+    fn test_negated_hypotheses_do_not_conflict_with_each_other() {
+        let first = hypothesis_view("不喜欢吃水果");
+        let second = hypothesis_view("不喜欢吃水果");
+        assert!(!first.has_conflict_with(&second));
+
+        assert!(hypothesis_view("经常跑步").has_conflict_with(&hypothesis_view("不经常跑步")));
+    }
+
+    fn test_event(action: &str, target: &str) -> EventMemory {
+        EventMemory {
+            event_id: Uuid::new_v4(),
+            memory_id: Uuid::new_v4(),
+            user_id: "test_user".to_string(),
+            timestamp: chrono::Utc::now(),
+            actor: None,
+            action: action.to_string(),
+            target: target.to_string(),
+            quantity: None,
+            unit: None,
+            confidence: 0.8,
+            extractor_version: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_classify_event() {
+        let lexicon = LexiconSet::default();
+        let view = hypothesis_view("用户喜欢吃水果");
+
+        assert_eq!(view.classify_event(&test_event("吃", "水果"), &lexicon), EvidenceKind::Supporting);
+        assert_eq!(view.classify_event(&test_event("讨厌", "水果"), &lexicon), EvidenceKind::Contradicting);
+        assert_eq!(view.classify_event(&test_event("不吃", "水果"), &lexicon), EvidenceKind::Contradicting);
+        assert_eq!(view.classify_event(&test_event("吃", "蔬菜"), &lexicon), EvidenceKind::Unrelated);
+
+        let negated = hypothesis_view("用户不吃水果");
+        assert_eq!(negated.classify_event(&test_event("吃", "水果"), &lexicon), EvidenceKind::Contradicting);
+        assert_eq!(negated.classify_event(&test_event("没吃", "水果"), &lexicon), EvidenceKind::Supporting);
+
+        // "不错" contains a marker but praises rather than negates
+        assert_eq!(view.classify_event(&test_event("吃", "不错的水果"), &lexicon), EvidenceKind::Supporting);
+    }
+
+    #[test]
+    fn test_classify_event_reads_negated_flag() {
+        let lexicon = LexiconSet::default();
+        let flagged = EventMemory {
+            metadata: Some(serde_json::json!({"negated": true})),
+            ..test_event("吃", "水果")
+        };
+
+        let view = hypothesis_view("用户喜欢吃水果");
+        assert_eq!(view.classify_event(&flagged, &lexicon), EvidenceKind::Contradicting);
+        let negated = hypothesis_view("用户不吃水果");
+        assert_eq!(negated.classify_event(&flagged, &lexicon), EvidenceKind::Supporting);
+    }
+
+    #[test]
+    fn test_supporting_events_advance_view() {
+        let lexicon = LexiconSet::default();
+        let config = PromotionGateConfig::default();
+        let mut view = CognitiveView {
+            confidence: 0.7,
+            validation_count: 0,
+            ..gate_test_view(5, 0)
+        };
+        assert_eq!(view.evaluate(&config), ViewDecision::Keep);
+
+        let events: Vec<EventMemory> = (0..3)
+            .map(|_| test_event("吃", "水果"))
+            .chain([test_event("吃", "蔬菜")])
+            .collect();
+        let now = chrono::Utc::now();
+        let counted = view.apply_evidence(&events, &lexicon, ConfidenceBump::default(), now);
+        assert_eq!(counted.unwrap(), (3, 0));
+        assert_eq!(view.validation_count, 3);
+        assert_eq!(view.evidence_count, 8);
+        assert_eq!(view.evaluate(&config), ViewDecision::Promote);
+
+        // Already recorded events are not counted again
+        let counted = view.apply_evidence(&events, &lexicon, ConfidenceBump::default(), now);
+        assert_eq!(counted.unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn test_ready_for_promotion_flag() {
+        let mut view = CognitiveView {
+            metadata: None,
+            ..hypothesis_view("用户喜欢吃水果")
+        };
+        assert!(!view.is_flagged_for_promotion());
+
+        // Clearing an absent flag leaves metadata untouched
+        view.set_flagged_for_promotion(false);
+        assert_eq!(view.metadata, None);
+
+        view.set_flagged_for_promotion(true);
+        assert!(view.is_flagged_for_promotion());
+        view.set_flagged_for_promotion(false);
+        assert!(!view.is_flagged_for_promotion());
+        assert_eq!(view.metadata, Some(serde_json::json!({})));
+    }
+
+    #[test]
+    fn test_contradicting_events_reject_view() {
+        let lexicon = LexiconSet::default();
+        let config = PromotionGateConfig::default();
+        let mut view = gate_test_view(5, 0);
+
+        let events = vec![test_event("讨厌", "水果"), test_event("不吃", "水果")];
+        let now = chrono::Utc::now();
+        let counted = view.apply_evidence(&events, &lexicon, ConfidenceBump::default(), now);
+        assert_eq!(counted.unwrap(), (0, 2));
+        assert_eq!(view.counter_evidence_count, 2);
+        assert_eq!(view.evaluate(&config), ViewDecision::Reject);
+
+        let counted = view.apply_evidence(&events, &lexicon, ConfidenceBump::default(), now);
+        assert_eq!(counted.unwrap(), (0, 0));
+        assert_eq!(view.counter_evidence_count, 2);
+    }
+
+    #[test]
+    fn test_revalidation_config_validation() {
+        assert!(RevalidationConfig::default().validate().is_ok());
+
+        let unbounded = RevalidationConfig {
+            max_views_per_run: 0,
+            ..Default::default()
+        };
+        assert!(unbounded.validate().is_err());
+
+        let bad_bump = RevalidationConfig {
+            bump: ConfidenceBump::Additive(-0.1),
+            ..Default::default()
+        };
+        assert!(bad_bump.validate().is_err());
+
+        let bad_floor = RevalidationConfig {
+            min_event_confidence: 1.5,
+            ..Default::default()
+        };
+        assert!(bad_floor.validate().is_err());
+    }
+
+    #[test]
+    fn test_conflict_requires_shared_topic() {
+        let like_fruit = hypothesis_view("用户喜欢吃水果");
+
+        // Opposite sentiment about a different food
+        assert!(!like_fruit.has_conflict_with(&hypothesis_view("讨厌吃蔬菜")));
+        // Same topic phrased without the shared verb
+        assert!(like_fruit.has_conflict_with(&hypothesis_view("讨厌水果")));
+        // Only sentiment words in common
+        assert!(!hypothesis_view("喜欢").has_conflict_with(&hypothesis_view("讨厌")));
+    }
+
+    #[test]
+    fn test_evaluate_decision_regions() {
+        let config = PromotionGateConfig::default();
+
+        // ratio 0.1 < gate 0.15 -> promote
+        assert_eq!(gate_test_view(20, 2).evaluate(&config), ViewDecision::Promote);
+
+        // gate 0.15 <= ratio 0.25 <= reject 0.3 -> keep
+        assert_eq!(gate_test_view(20, 5).evaluate(&config), ViewDecision::Keep);
+
+        // ratio 0.35 > reject 0.3 -> reject
+        assert_eq!(gate_test_view(20, 7).evaluate(&config), ViewDecision::Reject);
+
+        // Low evidence quality keeps the view even without counter-evidence
+        let mut weak = gate_test_view(20, 0);
+        weak.validation_count = 1;
+        assert_eq!(weak.evaluate(&config), ViewDecision::Keep);
+    }
+
+    #[test]
+    fn test_evaluate_with_custom_ratios() {
+        let config = PromotionGateConfig {
+            gate_counter_ratio: 0.3,
+            auto_reject_counter_ratio: 0.5,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        // ratio 0.25 now passes the gate
+        assert_eq!(gate_test_view(20, 5).evaluate(&config), ViewDecision::Promote);
+        // ratio 0.4 is kept rather than rejected
+        assert_eq!(gate_test_view(20, 8).evaluate(&config), ViewDecision::Keep);
+        assert_eq!(gate_test_view(20, 11).evaluate(&config), ViewDecision::Reject);
+    }
+
+    #[test]
+    fn test_promotion_gate_config_validation() {
+        assert!(PromotionGateConfig::default().validate().is_ok());
+
+        let inverted = PromotionGateConfig {
+            gate_counter_ratio: 0.3,
+            auto_reject_counter_ratio: 0.2,
+            ..Default::default()
+        };
+        assert!(inverted.validate().is_err());
+
+        let out_of_range = PromotionGateConfig {
+            auto_reject_counter_ratio: 1.5,
+            ..Default::default()
+        };
+        assert!(out_of_range.validate().is_err());
+    }
+
     #[test]
     fn test_has_conflict_with() {
         let view_like = CognitiveView {
@@ -1296,4 +3291,232 @@ mod tests {
         concept.is_deprecated = true;
         assert!(!concept.is_latest_version());
     }
+
+    /// v1 -> v2 -> v3, each created a day apart; only v3 is active
+    fn three_version_chain() -> Vec<StableConcept> {
+        let start = chrono::Utc::now() - chrono::Duration::days(3);
+        let mut chain: Vec<StableConcept> = Vec::new();
+        for version in 1..=3 {
+            let created_at = start + chrono::Duration::days(version as i64);
+            let parent_concept_id = chain.last().map(|c| c.concept_id);
+            chain.push(StableConcept {
+                concept_id: Uuid::new_v4(),
+                user_id: "test_user".to_string(),
+                canonical_name: "likes_fruit".to_string(),
+                display_name: format!("喜欢吃水果 v{}", version),
+                concept_type: "preference".to_string(),
+                description: None,
+                definition: serde_json::json!({}),
+                version,
+                parent_concept_id,
+                is_deprecated: version < 3,
+                promoted_from: None,
+                promoted_at: start,
+                promotion_confidence: 0.9,
+                created_at,
+                updated_at: created_at,
+                deprecated_at: None,
+                access_count: 0,
+                last_accessed_at: None,
+                source: "test".to_string(),
+                tags: None,
+                metadata: None,
+            });
+        }
+        chain
+    }
+
+    #[test]
+    fn test_plan_rollback_to_prior_version() {
+        let chain = three_version_chain();
+
+        let plan = plan_rollback(&chain, 1).unwrap();
+        assert_eq!(plan.head.concept_id, chain[2].concept_id);
+        assert_eq!(plan.target.concept_id, chain[0].concept_id);
+        assert_eq!(plan.new_version.version, 4);
+        assert_eq!(plan.new_version.parent_concept_id, Some(chain[0].concept_id));
+        assert_eq!(plan.new_version.display_name, chain[0].display_name);
+        assert!(!plan.new_version.is_deprecated);
+        assert_eq!(plan.new_version.canonical_name, "likes_fruit");
+    }
+
+    #[test]
+    fn test_plan_rollback_rejects_invalid_targets() {
+        let chain = three_version_chain();
+
+        // Nonexistent version
+        assert!(matches!(plan_rollback(&chain, 7), Err(DirSoulError::Config(_))));
+        // Current head
+        assert!(matches!(plan_rollback(&chain, 3), Err(DirSoulError::Config(_))));
+        // History is loaded per canonical_name/user, so another concept's
+        // v1 never appears here
+        let head_only: Vec<StableConcept> = chain[2..].to_vec();
+        assert!(matches!(plan_rollback(&head_only, 1), Err(DirSoulError::Config(_))));
+        // Empty chain
+        assert!(plan_rollback(&[], 1).is_err());
+    }
+
+    fn promoted_concept(concept_type: &str) -> NewStableConcept {
+        NewStableConcept::from_view(
+            "test_user".to_string(),
+            "likes_fruit".to_string(),
+            "喜欢吃水果".to_string(),
+            concept_type.to_string(),
+            Uuid::new_v4(),
+            0.9,
+        )
+    }
+
+    #[test]
+    fn test_plan_promotion_starts_or_extends_chain() {
+        let policy = DuplicateConceptPolicy::default();
+        let fresh = plan_promotion(None, promoted_concept("preference"), policy).unwrap();
+        assert!(fresh.replaces.is_none());
+        assert_eq!(fresh.new_concept.version, 1);
+        assert_eq!(fresh.new_concept.parent_concept_id, None);
+
+        let chain = three_version_chain();
+        let plan = plan_promotion(chain.last(), promoted_concept("preference"), policy).unwrap();
+        assert_eq!(plan.replaces.unwrap().concept_id, chain[2].concept_id);
+        assert_eq!(plan.new_concept.version, 4);
+        assert_eq!(plan.new_concept.parent_concept_id, Some(chain[2].concept_id));
+
+        // A fully deprecated chain is extended without deprecating anything
+        let mut retired = chain[2].clone();
+        retired.is_deprecated = true;
+        let plan = plan_promotion(Some(&retired), promoted_concept("preference"), policy).unwrap();
+        assert!(plan.replaces.is_none());
+        assert_eq!(plan.new_concept.parent_concept_id, Some(retired.concept_id));
+    }
+
+    #[test]
+    fn test_plan_promotion_rejects_duplicates() {
+        let chain = three_version_chain();
+
+        let conflicting = plan_promotion(
+            chain.last(),
+            promoted_concept("habit"),
+            DuplicateConceptPolicy::NewVersion,
+        );
+        assert!(matches!(conflicting, Err(DirSoulError::Config(_))));
+
+        let refused = plan_promotion(
+            chain.last(),
+            promoted_concept("preference"),
+            DuplicateConceptPolicy::Reject,
+        );
+        assert!(matches!(refused, Err(DirSoulError::Config(_))));
+    }
+
+    #[test]
+    fn test_version_history_ordering() {
+        let chain = three_version_chain();
+        let shuffled = vec![chain[2].clone(), chain[0].clone(), chain[1].clone()];
+
+        let ordered = order_version_history(shuffled);
+        let versions: Vec<i32> = ordered.iter().map(|c| c.version).collect();
+        assert_eq!(versions, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_latest_version_detection() {
+        let chain = three_version_chain();
+
+        assert!(!chain[0].is_latest_in_chain(&chain));
+        assert!(!chain[1].is_latest_in_chain(&chain));
+        assert!(chain[2].is_latest_in_chain(&chain));
+
+        let latest = select_latest_version(&chain).unwrap();
+        assert_eq!(latest.concept_id, chain[2].concept_id);
+
+        // With every version deprecated, the leaf of the chain is still the head
+        let mut all_deprecated = chain.clone();
+        for concept in &mut all_deprecated {
+            concept.is_deprecated = true;
+        }
+        let latest = select_latest_version(&all_deprecated).unwrap();
+        assert_eq!(latest.concept_id, chain[2].concept_id);
+        // The flag-based check can't tell
+        assert!(!all_deprecated[2].is_latest_version());
+    }
+
+    #[test]
+    fn test_new_view_deduplicates_derived_from() {
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        let view = NewCognitiveView::new(
+            "test_user".to_string(),
+            "用户喜欢喝咖啡".to_string(),
+            "preference".to_string(),
+            vec![a, b, a, a, b],
+        );
+
+        assert_eq!(view.evidence_count, 2);
+        assert_eq!(view.derived_from, serde_json::json!([a, b]));
+    }
+
+    #[test]
+    fn test_confidence_bump_rules() {
+        assert!((ConfidenceBump::Proportional(0.25).apply(0.6) - 0.7).abs() < 1e-12);
+        assert!((ConfidenceBump::Additive(0.05).apply(0.6) - 0.65).abs() < 1e-12);
+        assert_eq!(ConfidenceBump::Additive(0.5).apply(0.8), 1.0);
+        assert_eq!(ConfidenceBump::default(), ConfidenceBump::Proportional(0.25));
+
+        assert!(ConfidenceBump::Additive(-0.1).validate().is_err());
+        assert!(ConfidenceBump::Proportional(1.5).validate().is_err());
+        assert!(ConfidenceBump::Proportional(0.0).validate().is_ok());
+    }
+
+    #[test]
+    fn test_three_validations_make_view_ready_for_promotion() {
+        let mut view = gate_test_view(5, 0);
+        view.confidence = 0.7;
+        view.validation_count = 0;
+        view.derived_from = serde_json::json!([]);
+        let config = PromotionGateConfig::default();
+        assert_eq!(view.evaluate(&config), ViewDecision::Keep);
+
+        let now = chrono::Utc::now();
+        for expected in [0.775, 0.83125] {
+            let updated = view.record_validation(Uuid::new_v4(), ConfidenceBump::default(), now);
+            assert!(updated.unwrap());
+            assert!((view.confidence - expected).abs() < 1e-9);
+            assert!(!view.is_ready_for_promotion_with(&config));
+        }
+
+        let third = Uuid::new_v4();
+        assert!(view.record_validation(third, ConfidenceBump::default(), now).unwrap());
+        assert_eq!(view.validation_count, 3);
+        assert_eq!(view.evidence_count, 8);
+        assert_eq!(view.last_validated_at, Some(now));
+        assert!(view.confidence > config.min_confidence);
+        assert_eq!(view.evaluate(&config), ViewDecision::Promote);
+
+        // Replaying a supporting event is not another validation
+        assert!(!view.record_validation(third, ConfidenceBump::default(), now).unwrap());
+        assert_eq!(view.validation_count, 3);
+        assert_eq!(view.evidence_count, 8);
+
+        // Unreadable evidence is an error, not silently replaced
+        view.derived_from = serde_json::json!({"not": "a list"});
+        assert!(view.record_validation(Uuid::new_v4(), ConfidenceBump::default(), now).is_err());
+        assert_eq!(view.derived_from, serde_json::json!({"not": "a list"}));
+        assert_eq!(view.validation_count, 3);
+    }
+
+    #[test]
+    fn test_merge_derived_from_unions_without_duplicates() {
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        let c = Uuid::new_v4();
+        let mut view = gate_test_view(2, 0);
+        view.derived_from = serde_json::json!([a, b]);
+
+        assert_eq!(view.merge_derived_from(&[b, c, c]), 3);
+        assert_eq!(view.evidence_count, 3);
+        assert_eq!(view.derived_from, serde_json::json!([a, b, c]));
+
+        // Merging the same ids again changes nothing
+        assert_eq!(view.merge_derived_from(&[a, c]), 3);
+    }
 }
@@ -0,0 +1,515 @@
+//! Content Filter Module - PII redaction before storage and outbound calls
+//!
+//! Some deployments must not persist personal identifiers, or must not send
+//! them to a remote LLM. A `ContentFilter` rewrites text and reports what it
+//! replaced; filters compose with `FilterChain`.
+//!
+//! # Built-in Filters
+//! - `NoopFilter`: passes text through unchanged (the default)
+//! - `RegexPiiFilter`: emails, phone numbers, ID numbers
+//!
+//! # Restoring Originals
+//! Redacted values are replaced with placeholders such as `[PHONE_1]`, which
+//! keep the prompt readable for the LLM. `seal_redactions` records them in
+//! memory metadata, encrypting each original when an `EncryptionManager` is
+//! available, so `restore_redactions` can put them back locally with the key.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::EncryptionManager;
+use crate::error::{DirSoulError, Result};
+use crate::llm_provider::{ChatMessage, ChatResponse, GenerateOptions, LLMProvider, StreamChunk};
+
+/// Metadata key under which redactions are stored
+pub const REDACTIONS_METADATA_KEY: &str = "redactions";
+
+/// One replaced span of text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Redaction {
+    /// What was redacted (e.g. "email", "phone", "id_number")
+    pub kind: String,
+
+    /// Placeholder that replaced the value in the text
+    pub placeholder: String,
+
+    /// Original value; in memory only, never serialized
+    #[serde(skip)]
+    pub original: String,
+
+    /// Encrypted original, present when sealed with a key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sealed: Option<String>,
+}
+
+/// Filtered text plus the redactions applied to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilteredText {
+    /// Text safe to store or send
+    pub text: String,
+
+    /// Redactions in the order they were applied
+    pub redactions: Vec<Redaction>,
+}
+
+impl FilteredText {
+    /// Text that needed no changes
+    pub fn unchanged(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            redactions: Vec::new(),
+        }
+    }
+}
+
+/// Rewrites content before it is persisted or sent to a provider
+pub trait ContentFilter: Send + Sync {
+    /// Filter name for logging
+    fn name(&self) -> &str;
+
+    /// Return the filtered text and what was replaced
+    fn filter(&self, text: &str) -> FilteredText;
+}
+
+/// Filter that leaves content untouched
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopFilter;
+
+impl ContentFilter for NoopFilter {
+    fn name(&self) -> &str {
+        "noop"
+    }
+
+    fn filter(&self, text: &str) -> FilteredText {
+        FilteredText::unchanged(text)
+    }
+}
+
+/// Applies several filters in order, each seeing the previous output
+#[derive(Clone, Default)]
+pub struct FilterChain {
+    filters: Vec<Arc<dyn ContentFilter>>,
+}
+
+impl FilterChain {
+    /// Create an empty chain (behaves like `NoopFilter`)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a filter to the chain
+    pub fn then(mut self, filter: Arc<dyn ContentFilter>) -> Self {
+        self.filters.push(filter);
+        self
+    }
+}
+
+impl ContentFilter for FilterChain {
+    fn name(&self) -> &str {
+        "chain"
+    }
+
+    fn filter(&self, text: &str) -> FilteredText {
+        let mut result = FilteredText::unchanged(text);
+        for filter in &self.filters {
+            let next = filter.filter(&result.text);
+            result.text = next.text;
+            result.redactions.extend(next.redactions);
+        }
+        result
+    }
+}
+
+/// Which PII categories the built-in filter redacts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentFilterConfig {
+    /// Redact email addresses
+    #[serde(default = "default_true")]
+    pub redact_emails: bool,
+
+    /// Redact phone numbers (mainland mobile, +86 prefix, landlines)
+    #[serde(default = "default_true")]
+    pub redact_phones: bool,
+
+    /// Redact 18-digit resident ID numbers
+    #[serde(default = "default_true")]
+    pub redact_id_numbers: bool,
+
+    /// Also filter chat/embedding requests sent to the LLM provider
+    #[serde(default)]
+    pub filter_outbound: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for ContentFilterConfig {
+    fn default() -> Self {
+        Self {
+            redact_emails: true,
+            redact_phones: true,
+            redact_id_numbers: true,
+            filter_outbound: false,
+        }
+    }
+}
+
+impl ContentFilterConfig {
+    /// Wrap `provider` in a `FilteringProvider` when `filter_outbound` is set
+    pub fn apply_to_provider(
+        &self,
+        provider: Arc<dyn LLMProvider>,
+        filter: Arc<dyn ContentFilter>,
+    ) -> Arc<dyn LLMProvider> {
+        if self.filter_outbound {
+            Arc::new(FilteringProvider::new(provider, filter))
+        } else {
+            provider
+        }
+    }
+}
+
+/// One regex rule of `RegexPiiFilter`
+#[derive(Debug, Clone)]
+struct PiiRule {
+    kind: String,
+    tag: String,
+    pattern: Regex,
+}
+
+/// Regex-based PII filter
+///
+/// Rules run in order, so more specific patterns (ID numbers) go before the
+/// shorter ones they contain (phone numbers).
+#[derive(Debug, Clone)]
+pub struct RegexPiiFilter {
+    rules: Vec<PiiRule>,
+}
+
+impl RegexPiiFilter {
+    /// Filter with every built-in rule enabled
+    pub fn new() -> Self {
+        Self::from_config(&ContentFilterConfig::default())
+    }
+
+    /// Filter with the built-in rules selected by `config`
+    pub fn from_config(config: &ContentFilterConfig) -> Self {
+        let mut filter = Self { rules: Vec::new() };
+        if config.redact_id_numbers {
+            filter = filter
+                .with_rule("id_number", r"(?-u:\b)[1-9][0-9]{16}[0-9Xx](?-u:\b)")
+                .expect("built-in id pattern");
+        }
+        if config.redact_emails {
+            filter = filter
+                .with_rule("email", r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}")
+                .expect("built-in email pattern");
+        }
+        if config.redact_phones {
+            filter = filter
+                .with_rule(
+                    "phone",
+                    r"(?:\+86[- ]?|(?-u:\b)(?:86[- ]?)?)1[3-9][0-9]{9}(?-u:\b)|(?-u:\b)0[0-9]{2,3}-[0-9]{7,8}(?-u:\b)",
+                )
+                .expect("built-in phone pattern");
+        }
+        filter
+    }
+
+    /// Add a custom rule; matches are replaced with `[KIND_N]`
+    pub fn with_rule(mut self, kind: &str, pattern: &str) -> Result<Self> {
+        let pattern = Regex::new(pattern)
+            .map_err(|e| DirSoulError::Config(format!("Invalid {} pattern: {}", kind, e)))?;
+        self.rules.push(PiiRule {
+            kind: kind.to_string(),
+            tag: kind.to_uppercase(),
+            pattern,
+        });
+        Ok(self)
+    }
+}
+
+impl Default for RegexPiiFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentFilter for RegexPiiFilter {
+    fn name(&self) -> &str {
+        "regex_pii"
+    }
+
+    fn filter(&self, text: &str) -> FilteredText {
+        let mut result = FilteredText::unchanged(text);
+
+        for rule in &self.rules {
+            // Continue numbering after placeholders left by earlier filters
+            let mut next_index = highest_placeholder_index(&result.text, &rule.tag) + 1;
+            let mut rule_redactions: Vec<Redaction> = Vec::new();
+
+            let replaced = rule.pattern.replace_all(&result.text, |caps: &regex::Captures| {
+                let original = caps[0].to_string();
+                // The same value keeps the same placeholder
+                if let Some(existing) = rule_redactions.iter().find(|r| r.original == original) {
+                    return existing.placeholder.clone();
+                }
+                let placeholder = format!("[{}_{}]", rule.tag, next_index);
+                next_index += 1;
+                rule_redactions.push(Redaction {
+                    kind: rule.kind.clone(),
+                    placeholder: placeholder.clone(),
+                    original,
+                    sealed: None,
+                });
+                placeholder
+            });
+
+            result.text = replaced.into_owned();
+            result.redactions.extend(rule_redactions);
+        }
+
+        result
+    }
+}
+
+/// Largest N among `[TAG_N]` placeholders already in `text` (0 if none)
+fn highest_placeholder_index(text: &str, tag: &str) -> usize {
+    let prefix = format!("[{}_", tag);
+    text.match_indices(&prefix)
+        .filter_map(|(start, _)| {
+            let rest = &text[start + prefix.len()..];
+            let end = rest.find(']')?;
+            rest[..end].parse::<usize>().ok()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Serialize redactions for memory metadata
+///
+/// With an encryption manager each original is encrypted into `sealed` and
+/// can later be restored; without one only the placeholders are kept.
+pub fn seal_redactions(
+    redactions: &[Redaction],
+    encryption: Option<&EncryptionManager>,
+) -> Result<serde_json::Value> {
+    let sealed = redactions
+        .iter()
+        .map(|redaction| {
+            let sealed = encryption
+                .map(|enc| enc.encrypt_string(&redaction.original))
+                .transpose()?;
+            Ok(Redaction {
+                sealed,
+                ..redaction.clone()
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(serde_json::to_value(sealed)?)
+}
+
+/// Put sealed originals back into `text`
+///
+/// `redactions` is the value stored under `REDACTIONS_METADATA_KEY`.
+/// Placeholders without a sealed original are left as they are.
+pub fn restore_redactions(
+    text: &str,
+    redactions: &serde_json::Value,
+    encryption: &EncryptionManager,
+) -> Result<String> {
+    let redactions: Vec<Redaction> = serde_json::from_value(redactions.clone())?;
+
+    let mut restored = text.to_string();
+    for redaction in &redactions {
+        if let Some(sealed) = &redaction.sealed {
+            let original = encryption.decrypt_string(sealed)?;
+            restored = restored.replace(&redaction.placeholder, &original);
+        }
+    }
+    Ok(restored)
+}
+
+/// LLM provider wrapper that filters everything sent to the inner provider
+///
+/// Responses are returned unchanged; placeholders the model echoes back stay
+/// as placeholders.
+pub struct FilteringProvider {
+    inner: Arc<dyn LLMProvider>,
+    filter: Arc<dyn ContentFilter>,
+}
+
+impl FilteringProvider {
+    /// Wrap a provider with a content filter
+    pub fn new(inner: Arc<dyn LLMProvider>, filter: Arc<dyn ContentFilter>) -> Self {
+        Self { inner, filter }
+    }
+
+    fn filter_messages(&self, messages: Vec<ChatMessage>) -> Vec<ChatMessage> {
+        messages
+            .into_iter()
+            .map(|message| ChatMessage {
+                content: self.filter.filter(&message.content).text,
+                role: message.role,
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl LLMProvider for FilteringProvider {
+    async fn chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<ChatResponse> {
+        self.inner
+            .chat(self.filter_messages(messages), temperature, max_tokens)
+            .await
+    }
+
+    async fn stream_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<tokio::sync::mpsc::Receiver<StreamChunk>> {
+        self.inner
+            .stream_chat(self.filter_messages(messages), temperature, max_tokens)
+            .await
+    }
+
+    async fn generate(&self, prompt: &str, options: GenerateOptions) -> Result<String> {
+        self.inner.generate(&self.filter.filter(prompt).text, options).await
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.inner.embed(&self.filter.filter(text).text).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let filtered: Vec<String> = texts.iter().map(|t| self.filter.filter(t).text).collect();
+        self.inner.embed_batch(&filtered).await
+    }
+
+    fn embed_batch_fan_out(&self) -> usize {
+        self.inner.embed_batch_fan_out()
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{InputProcessor, RawInput};
+    use crate::llm_provider::extract_response_text;
+    use crate::llm_provider::mock::MockProvider;
+
+    #[test]
+    fn test_regex_filter_redacts_builtin_kinds() {
+        let filter = RegexPiiFilter::new();
+        let filtered = filter.filter(
+            "邮箱 alice@example.com，电话13812345678，身份证号110101199003071234，再打13812345678",
+        );
+
+        assert_eq!(
+            filtered.text,
+            "邮箱 [EMAIL_1]，电话[PHONE_1]，身份证号[ID_NUMBER_1]，再打[PHONE_1]"
+        );
+        let kinds: Vec<&str> = filtered.redactions.iter().map(|r| r.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["id_number", "email", "phone"]);
+    }
+
+    #[test]
+    fn test_config_disables_rules() {
+        let config = ContentFilterConfig {
+            redact_phones: false,
+            ..ContentFilterConfig::default()
+        };
+        let filtered = RegexPiiFilter::from_config(&config).filter("call 13812345678");
+        assert_eq!(filtered.text, "call 13812345678");
+        assert!(filtered.redactions.is_empty());
+    }
+
+    #[test]
+    fn test_chain_continues_placeholder_numbering() {
+        let chain = FilterChain::new()
+            .then(Arc::new(RegexPiiFilter::new()))
+            .then(Arc::new(
+                RegexPiiFilter { rules: Vec::new() }
+                    .with_rule("phone", r"[0-9]{3}-[0-9]{4}")
+                    .unwrap(),
+            ));
+
+        let filtered = chain.filter("13812345678 or 555-0100");
+        assert_eq!(filtered.text, "[PHONE_1] or [PHONE_2]");
+        assert_eq!(filtered.redactions.len(), 2);
+        assert_eq!(NoopFilter.filter("13812345678").text, "13812345678");
+    }
+
+    #[test]
+    fn test_sealed_redactions_restore_with_key() {
+        let key_file = "/tmp/test_content_filter_key";
+        let _ = std::fs::remove_file(key_file);
+        let encryption = EncryptionManager::initialize(key_file).unwrap();
+
+        let filtered = RegexPiiFilter::new().filter("我的手机是13812345678");
+        let sealed = seal_redactions(&filtered.redactions, Some(&encryption)).unwrap();
+
+        // Plaintext never reaches the metadata
+        assert!(!sealed.to_string().contains("13812345678"));
+        let restored = restore_redactions(&filtered.text, &sealed, &encryption).unwrap();
+        assert_eq!(restored, "我的手机是13812345678");
+
+        // Without a key only placeholders are kept
+        let unsealed = seal_redactions(&filtered.redactions, None).unwrap();
+        assert_eq!(
+            unsealed,
+            serde_json::json!([{"kind": "phone", "placeholder": "[PHONE_1]"}])
+        );
+
+        std::fs::remove_file(key_file).ok();
+    }
+
+    #[tokio::test]
+    async fn test_phone_redacted_in_storage_and_prompt_stays_usable() {
+        let filter: Arc<dyn ContentFilter> = Arc::new(RegexPiiFilter::new());
+        let input = "明天下午三点给张医生打电话，号码是13812345678";
+
+        let processor = InputProcessor::new("user123").with_content_filter(filter.clone());
+        let memory = processor.process_input(RawInput::text(input)).unwrap();
+        let stored = memory.content.unwrap();
+        assert_eq!(stored, "明天下午三点给张医生打电话，号码是[PHONE_1]");
+        assert_eq!(
+            memory.metadata.unwrap()[REDACTIONS_METADATA_KEY][0]["kind"],
+            "phone"
+        );
+
+        let recording = Arc::new(MockProvider::new().with_reply("ok"));
+        let config = ContentFilterConfig {
+            filter_outbound: true,
+            ..ContentFilterConfig::default()
+        };
+        let provider = config.apply_to_provider(recording.clone(), filter);
+        let response = provider
+            .chat(vec![ChatMessage::user(input)], None, None)
+            .await
+            .unwrap();
+        assert_eq!(extract_response_text(&response), "ok");
+
+        assert_eq!(recording.calls()[0].prompt(), "明天下午三点给张医生打电话，号码是[PHONE_1]");
+    }
+}
@@ -7,6 +7,7 @@
 //! - Keys are stored in restricted permission files (0400)
 //! - Sensitive data is zeroed from memory after use
 //! - No hardcoded keys or secrets
+//! - Every token gets a fresh random IV; reuse is detected (see `NonceCheck`)
 //!
 //! # Example
 //! ```no_run
@@ -20,8 +21,11 @@
 //! # Ok::<(), dirsoul::DirSoulError>(())
 //! ```
 
+use base64::Engine;
 use fernet::Fernet;
+use std::collections::{HashSet, VecDeque};
 use std::path::Path;
+use std::sync::Mutex;
 use zeroize::Zeroize;
 
 use crate::{DirSoulError, Result};
@@ -32,6 +36,84 @@ pub const DEFAULT_KEY_FILE: &str = ".encryption_key";
 /// Minimum Fernet token size (in bytes)
 const FERNET_MIN_SIZE: usize = 32;
 
+/// Offset of the IV in a decoded Fernet token (after version and timestamp)
+const FERNET_IV_OFFSET: usize = 9;
+
+/// Length of the IV (nonce) in a Fernet token
+pub const FERNET_IV_LEN: usize = 16;
+
+/// Length of the HMAC closing a decoded Fernet token
+const FERNET_HMAC_LEN: usize = 32;
+
+/// Most recent IVs remembered by the nonce check
+const MAX_TRACKED_NONCES: usize = 100_000;
+
+/// When `EncryptionManager` checks that every token got a fresh IV
+///
+/// Fernet draws a random 128-bit IV per token, so a repeat means a broken
+/// random source. The check remembers the last `MAX_TRACKED_NONCES` IVs and
+/// fails the encryption that would reuse one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonceCheck {
+    /// Don't track IVs
+    Disabled,
+    /// Track IVs in debug builds only (default)
+    #[default]
+    DebugBuilds,
+    /// Track IVs in every build
+    Always,
+}
+
+impl NonceCheck {
+    fn enabled(self) -> bool {
+        match self {
+            NonceCheck::Disabled => false,
+            NonceCheck::DebugBuilds => cfg!(debug_assertions),
+            NonceCheck::Always => true,
+        }
+    }
+}
+
+/// Recently issued IVs, oldest first out
+#[derive(Debug, Default)]
+struct NonceTracker {
+    seen: HashSet<[u8; FERNET_IV_LEN]>,
+    order: VecDeque<[u8; FERNET_IV_LEN]>,
+}
+
+impl NonceTracker {
+    /// Remember `iv`; false if it was already issued
+    fn record(&mut self, iv: [u8; FERNET_IV_LEN]) -> bool {
+        if !self.seen.insert(iv) {
+            return false;
+        }
+        self.order.push_back(iv);
+        if self.order.len() > MAX_TRACKED_NONCES {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Read the IV out of a Fernet token
+///
+/// Fernet prepends the IV to the ciphertext: a decoded token is version (1
+/// byte), timestamp (8), IV (16), ciphertext, HMAC (32).
+pub fn token_nonce(token: &[u8]) -> Result<[u8; FERNET_IV_LEN]> {
+    let decoded = base64::engine::general_purpose::URL_SAFE
+        .decode(token)
+        .map_err(|e| DirSoulError::Encryption(format!("Invalid token encoding: {}", e)))?;
+    if decoded.len() < FERNET_IV_OFFSET + FERNET_IV_LEN + FERNET_HMAC_LEN {
+        return Err(DirSoulError::Encryption("Token too short".to_string()));
+    }
+
+    let mut iv = [0u8; FERNET_IV_LEN];
+    iv.copy_from_slice(&decoded[FERNET_IV_OFFSET..FERNET_IV_OFFSET + FERNET_IV_LEN]);
+    Ok(iv)
+}
+
 /// Encryption manager for DirSoul
 ///
 /// Handles encryption/decryption operations using Fernet symmetric encryption.
@@ -44,6 +126,8 @@ const FERNET_MIN_SIZE: usize = 32;
 pub struct EncryptionManager {
     fernet: Fernet,
     key_file: std::path::PathBuf,
+    /// Issued IVs, when the nonce check is enabled
+    nonces: Option<Mutex<NonceTracker>>,
 }
 
 impl EncryptionManager {
@@ -110,7 +194,9 @@ impl EncryptionManager {
         Ok(Self {
             fernet,
             key_file: key_file.to_path_buf(),
-        })
+            nonces: None,
+        }
+        .with_nonce_check(NonceCheck::default()))
     }
 
     /// Load existing encryption key from file
@@ -131,7 +217,9 @@ impl EncryptionManager {
         Ok(Self {
             fernet,
             key_file: key_file.to_path_buf(),
-        })
+            nonces: None,
+        }
+        .with_nonce_check(NonceCheck::default()))
     }
 
     /// Encrypt data
@@ -149,7 +237,7 @@ impl EncryptionManager {
     /// Returns error if encryption fails
     pub fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
         // Fernet::encrypt returns String (base64-encoded token)
-        let token = self.fernet.encrypt(data);
+        let token = self.seal(data)?;
         Ok(token.into_bytes())
     }
 
@@ -192,7 +280,7 @@ impl EncryptionManager {
     /// * `text` - String to encrypt
     pub fn encrypt_string(&self, text: &str) -> Result<String> {
         // Fernet::encrypt accepts &[u8], convert &str to &[u8]
-        Ok(self.fernet.encrypt(text.as_bytes()))
+        self.seal(text.as_bytes())
     }
 
     /// Decrypt string
@@ -214,6 +302,30 @@ impl EncryptionManager {
     pub fn key_file(&self) -> &Path {
         &self.key_file
     }
+
+    /// Set when IVs are checked for reuse; changing it forgets tracked IVs
+    pub fn with_nonce_check(mut self, check: NonceCheck) -> Self {
+        self.nonces = check.enabled().then(|| Mutex::new(NonceTracker::default()));
+        self
+    }
+
+    /// Encrypt into a Fernet token, failing if its IV was issued before
+    fn seal(&self, data: &[u8]) -> Result<String> {
+        let token = self.fernet.encrypt(data);
+
+        if let Some(nonces) = &self.nonces {
+            let iv = token_nonce(token.as_bytes())?;
+            let fresh = nonces
+                .lock()
+                .map_err(|_| DirSoulError::Encryption("Nonce tracker poisoned".to_string()))?
+                .record(iv);
+            if !fresh {
+                tracing::error!("Fernet IV reused; the random source is broken");
+                return Err(DirSoulError::Encryption("Nonce reuse detected".to_string()));
+            }
+        }
+        Ok(token)
+    }
 }
 
 /// Secure buffer that zeroes memory on drop
@@ -391,6 +503,76 @@ mod tests {
         std::fs::remove_file(key_file2).ok();
     }
 
+    #[test]
+    fn test_nonces_are_unique_across_thousands_of_messages() {
+        let key_file = "/tmp/test_encryption_nonce_key";
+        let _ = std::fs::remove_file(key_file);
+
+        let manager = EncryptionManager::initialize(key_file)
+            .unwrap()
+            .with_nonce_check(NonceCheck::Always);
+
+        let mut seen = HashSet::new();
+        for i in 0..5000 {
+            let plaintext = format!("message {}", i % 10);
+            let encrypted = manager.encrypt(plaintext.as_bytes()).unwrap();
+            assert!(seen.insert(token_nonce(&encrypted).unwrap()), "IV repeated at {}", i);
+            assert_eq!(manager.decrypt(&encrypted).unwrap(), plaintext.as_bytes());
+        }
+        assert_eq!(manager.nonces.as_ref().unwrap().lock().unwrap().order.len(), 5000);
+
+        std::fs::remove_file(key_file).ok();
+    }
+
+    #[test]
+    fn test_nonce_tracker_detects_reuse_and_is_bounded() {
+        let mut tracker = NonceTracker::default();
+        assert!(tracker.record([1; FERNET_IV_LEN]));
+        assert!(!tracker.record([1; FERNET_IV_LEN]));
+
+        for i in 0..=MAX_TRACKED_NONCES as u32 {
+            let mut iv = [0u8; FERNET_IV_LEN];
+            iv[..4].copy_from_slice(&i.to_be_bytes());
+            iv[15] = 2;
+            tracker.record(iv);
+        }
+        assert_eq!(tracker.seen.len(), MAX_TRACKED_NONCES);
+        assert_eq!(tracker.order.len(), MAX_TRACKED_NONCES);
+        // The oldest IV has been forgotten
+        assert!(tracker.record([1; FERNET_IV_LEN]));
+    }
+
+    #[test]
+    fn test_decrypt_reads_prepended_iv() {
+        let key_file = "/tmp/test_encryption_iv_key";
+        let _ = std::fs::remove_file(key_file);
+
+        let manager = EncryptionManager::initialize(key_file).unwrap();
+        let encrypted = manager.encrypt(b"iv check").unwrap();
+        let iv = token_nonce(&encrypted).unwrap();
+
+        // Flipping a bit of the embedded IV breaks the token
+        let mut decoded = base64::engine::general_purpose::URL_SAFE.decode(&encrypted).unwrap();
+        assert_eq!(&decoded[FERNET_IV_OFFSET..FERNET_IV_OFFSET + FERNET_IV_LEN], &iv[..]);
+        decoded[FERNET_IV_OFFSET] ^= 1;
+        let tampered = base64::engine::general_purpose::URL_SAFE.encode(&decoded);
+        assert!(manager.decrypt(tampered.as_bytes()).is_err());
+        assert_eq!(manager.decrypt(&encrypted).unwrap(), b"iv check");
+
+        assert!(token_nonce(b"c2hvcnQ=").is_err());
+        assert!(token_nonce(b"not base64!").is_err());
+
+        std::fs::remove_file(key_file).ok();
+    }
+
+    #[test]
+    fn test_nonce_check_modes() {
+        assert!(!NonceCheck::Disabled.enabled());
+        assert!(NonceCheck::Always.enabled());
+        assert_eq!(NonceCheck::DebugBuilds.enabled(), cfg!(debug_assertions));
+        assert_eq!(NonceCheck::default(), NonceCheck::DebugBuilds);
+    }
+
     #[test]
     fn test_secure_buffer() {
         let data = vec![1, 2, 3, 4, 5];
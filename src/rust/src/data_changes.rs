@@ -0,0 +1,78 @@
+//! User Data Change Notifications
+//!
+//! Storage write paths (ingestion, extraction retry, event deletion, import,
+//! erasure, soft delete and restore, settings) call
+//! `notify_user_data_changed` once their write has committed. Anything
+//! caching derived results in the same process subscribes here so it never
+//! serves a response computed before the write; `HttpServer` subscribes its
+//! `QueryCache`.
+//!
+//! # Example
+//! ```ignore
+//! let listener: Arc<UserDataListener> = Arc::new(|user_id: &str| cache.invalidate_user(user_id));
+//! subscribe_user_data_changes(&listener);
+//! // Dropping the last clone of `listener` unsubscribes it
+//! ```
+
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+/// Callback receiving the id of a user whose stored data changed
+pub type UserDataListener = dyn Fn(&str) + Send + Sync;
+
+fn listeners() -> &'static Mutex<Vec<Weak<UserDataListener>>> {
+    static LISTENERS: OnceLock<Mutex<Vec<Weak<UserDataListener>>>> = OnceLock::new();
+    LISTENERS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Call `listener` after every write to a user's data
+///
+/// Only a weak reference is kept: the subscription ends when the last
+/// clone of `listener` is dropped.
+pub fn subscribe_user_data_changes(listener: &Arc<UserDataListener>) {
+    listeners()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(Arc::downgrade(listener));
+}
+
+/// Tell every subscriber that `user_id`'s stored data changed
+///
+/// Listeners run on the caller's thread, outside the registry lock.
+pub fn notify_user_data_changed(user_id: &str) {
+    let live: Vec<Arc<UserDataListener>> = {
+        let mut listeners = listeners().lock().unwrap_or_else(|e| e.into_inner());
+        listeners.retain(|listener| listener.strong_count() > 0);
+        listeners.iter().filter_map(Weak::upgrade).collect()
+    };
+    for listener in live {
+        listener(user_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listeners_hear_writes_until_dropped() {
+        let seen = Arc::new(Mutex::new(Vec::<String>::new()));
+        let recorder = seen.clone();
+        let listener: Arc<UserDataListener> =
+            Arc::new(move |user_id: &str| recorder.lock().unwrap().push(user_id.to_string()));
+        subscribe_user_data_changes(&listener);
+
+        notify_user_data_changed("data_changes_test_a");
+        drop(listener);
+        notify_user_data_changed("data_changes_test_b");
+
+        // Other tests may notify concurrently; only this test's ids matter
+        let seen: Vec<String> = seen
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|id| id.starts_with("data_changes_test_"))
+            .cloned()
+            .collect();
+        assert_eq!(seen, vec!["data_changes_test_a"]);
+    }
+}
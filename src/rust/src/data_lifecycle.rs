@@ -26,6 +26,7 @@ use std::path::Path;
 use base64::Engine;
 use uuid::Uuid;
 
+use crate::data_changes::notify_user_data_changed;
 use crate::error::{DirSoulError, Result};
 use crate::models::{RawMemory, EventMemory};
 use crate::schema::{raw_memories, event_memories};
@@ -92,6 +93,17 @@ pub struct TieringConfig {
 
     /// MinIO secret key
     pub minio_secret_key: Option<String>,
+
+    /// Days a soft-deleted memory stays restorable before hard deletion
+    #[serde(default = "default_soft_delete_retention_days")]
+    pub soft_delete_retention_days: i64,
+}
+
+/// Default grace period for soft-deleted memories
+pub const DEFAULT_SOFT_DELETE_RETENTION_DAYS: i64 = 30;
+
+fn default_soft_delete_retention_days() -> i64 {
+    DEFAULT_SOFT_DELETE_RETENTION_DAYS
 }
 
 impl Default for TieringConfig {
@@ -106,6 +118,7 @@ impl Default for TieringConfig {
             minio_bucket: Some("dirsoul-cold".to_string()),
             minio_access_key: None,
             minio_secret_key: None,
+            soft_delete_retention_days: DEFAULT_SOFT_DELETE_RETENTION_DAYS,
         }
     }
 }
@@ -416,6 +429,151 @@ impl DataLifecycleManager {
     pub fn get_config(&self) -> &TieringConfig {
         &self.config
     }
+
+    /// Tombstones older than this are due for hard deletion
+    pub fn purge_cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - Duration::days(self.config.soft_delete_retention_days)
+    }
+
+    /// Hard-delete memories whose soft-delete grace period has expired
+    pub fn run_purge_task(&self) -> Result<usize> {
+        let mut conn = PgConnection::establish(&self.database_url)?;
+        purge_soft_deleted_memories(&mut conn, self.purge_cutoff(Utc::now()))
+    }
+
+    /// Run `run_purge_task` every `archive_check_interval_hours`
+    ///
+    /// The first tick fires immediately. A failing run is logged and the
+    /// next tick tries again.
+    pub fn spawn_purge_task(self) -> tokio::task::JoinHandle<()> {
+        let interval = std::time::Duration::from_secs(
+            self.config.archive_check_interval_hours.max(1) * 3600,
+        );
+        let manager = std::sync::Arc::new(self);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+
+                let manager = std::sync::Arc::clone(&manager);
+                match tokio::task::spawn_blocking(move || manager.run_purge_task()).await {
+                    Ok(Ok(purged)) => tracing::info!("Purged {} soft-deleted memories", purged),
+                    Ok(Err(e)) => tracing::warn!("Soft-delete purge failed: {}", e),
+                    Err(e) => tracing::warn!("Soft-delete purge aborted: {}", e),
+                }
+            }
+        })
+    }
+}
+
+// ============================================================================
+// Soft Delete
+// ============================================================================
+
+/// Subquery type returned by `live_memory_ids`
+pub type LiveMemoryIds = diesel::dsl::Select<
+    diesel::dsl::Filter<
+        diesel::dsl::Filter<raw_memories::table, diesel::dsl::Eq<raw_memories::user_id, String>>,
+        diesel::dsl::IsNull<raw_memories::deleted_at>,
+    >,
+    raw_memories::memory_id,
+>;
+
+/// Subquery of a user's memory ids that are not soft-deleted
+///
+/// Event queries filter on `memory_id.eq_any(live_memory_ids(user_id))` so
+/// the events of a soft-deleted memory stay hidden until it is restored.
+pub fn live_memory_ids(user_id: &str) -> LiveMemoryIds {
+    raw_memories::table
+        .filter(raw_memories::user_id.eq(user_id.to_string()))
+        .filter(raw_memories::deleted_at.is_null())
+        .select(raw_memories::memory_id)
+}
+
+/// Soft-delete a raw memory
+///
+/// The row is kept (with its events) but hidden from default queries until
+/// it is restored or purged. Returns the tombstone timestamp.
+pub fn soft_delete_memory(
+    conn: &mut PgConnection,
+    user_id: &str,
+    memory_id: Uuid,
+) -> Result<DateTime<Utc>> {
+    let now = Utc::now();
+
+    let updated = diesel::update(
+        raw_memories::table
+            .filter(raw_memories::memory_id.eq(memory_id))
+            .filter(raw_memories::user_id.eq(user_id))
+            .filter(raw_memories::deleted_at.is_null()),
+    )
+    .set(raw_memories::deleted_at.eq(Some(now)))
+    .execute(conn)?;
+
+    if updated == 0 {
+        return Err(DirSoulError::NotFound(format!(
+            "Live memory {} not found for user {}",
+            memory_id, user_id
+        )));
+    }
+
+    notify_user_data_changed(user_id);
+    Ok(now)
+}
+
+/// Restore a soft-deleted memory within its retention window
+pub fn restore_memory(
+    conn: &mut PgConnection,
+    user_id: &str,
+    memory_id: Uuid,
+    retention_days: i64,
+) -> Result<()> {
+    let deleted_at: Option<DateTime<Utc>> = raw_memories::table
+        .filter(raw_memories::memory_id.eq(memory_id))
+        .filter(raw_memories::user_id.eq(user_id))
+        .select(raw_memories::deleted_at)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| {
+            DirSoulError::NotFound(format!("Memory {} not found for user {}", memory_id, user_id))
+        })?;
+
+    let deleted_at = deleted_at.ok_or_else(|| {
+        DirSoulError::Config(format!("Memory {} is not deleted", memory_id))
+    })?;
+
+    if Utc::now() >= deleted_at + Duration::days(retention_days) {
+        return Err(DirSoulError::Config(format!(
+            "Memory {} is past its {}-day restore window",
+            memory_id, retention_days
+        )));
+    }
+
+    diesel::update(
+        raw_memories::table
+            .filter(raw_memories::memory_id.eq(memory_id))
+            .filter(raw_memories::user_id.eq(user_id)),
+    )
+    .set(raw_memories::deleted_at.eq(None::<DateTime<Utc>>))
+    .execute(conn)?;
+
+    notify_user_data_changed(user_id);
+    Ok(())
+}
+
+/// Hard-delete memories tombstoned before `cutoff`
+///
+/// Events are removed by the `ON DELETE CASCADE` on `event_memories`.
+pub fn purge_soft_deleted_memories(conn: &mut PgConnection, cutoff: DateTime<Utc>) -> Result<usize> {
+    let purged = diesel::delete(
+        raw_memories::table.filter(raw_memories::deleted_at.lt(cutoff)),
+    )
+    .execute(conn)?;
+
+    Ok(purged)
 }
 
 /// Tier distribution statistics
@@ -431,6 +589,31 @@ pub struct TierDistribution {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_soft_delete_retention_default() {
+        let config: TieringConfig = toml::from_str(
+            r#"
+            hot_threshold_months = 3
+            warm_threshold_months = 24
+            enable_auto_archive = true
+            archive_check_interval_hours = 24
+            enable_compression = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.soft_delete_retention_days, DEFAULT_SOFT_DELETE_RETENTION_DAYS);
+
+        let manager = DataLifecycleManager::new(
+            TieringConfig {
+                soft_delete_retention_days: 7,
+                ..TieringConfig::default()
+            },
+            "postgresql://localhost/test".to_string(),
+        );
+        let now = Utc::now();
+        assert_eq!(manager.purge_cutoff(now), now - Duration::days(7));
+    }
+
     #[test]
     fn test_data_tier_age_threshold() {
         assert_eq!(DataTier::Hot.age_threshold_months(), 3);
@@ -491,6 +674,7 @@ mod tests {
                 unit: Some("piece".to_string()),
                 confidence: 0.9,
                 extractor_version: None,
+                metadata: None,
             },
         ];
 
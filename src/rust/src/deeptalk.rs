@@ -15,20 +15,25 @@
 //! - docs/skills/deeptalk_implementation.md
 
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use crate::agents::MemoryPermission;
-use crate::llm_provider::{ChatMessage, ChatResponse, LLMProvider};
+use crate::llm_provider::{ChatMessage, ChatResponse, GenerateOptions, LLMProvider};
 use crate::plugin::{
     PluginContext, PluginMetadata, PluginOutput, PluginResponse, UserPlugin,
 };
-use crate::prompt_manager::PromptManager;
+use crate::prompt_manager::{PromptManager, PromptTask};
+use crate::schema::emotional_trends;
 use crate::{DirSoulError, EventNotification, Result};
 
+/// Scores at or above this are a positive trend, at or below its negation negative
+pub const EMOTIONAL_TREND_THRESHOLD: f64 = 0.2;
+
 /// Emotional trend analysis result
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EmotionalTrend {
@@ -64,6 +69,135 @@ impl EmotionalTrend {
             Self::Negative => "stressed or concerned",
         }
     }
+
+    /// Representative score in the normalized [-1, 1] range
+    pub fn score(&self) -> f64 {
+        match self {
+            Self::Positive => 1.0,
+            Self::Neutral => 0.0,
+            Self::Negative => -1.0,
+        }
+    }
+
+    /// Classify a normalized score using `EMOTIONAL_TREND_THRESHOLD`
+    pub fn from_score(score: f64) -> Self {
+        if score >= EMOTIONAL_TREND_THRESHOLD {
+            Self::Positive
+        } else if score <= -EMOTIONAL_TREND_THRESHOLD {
+            Self::Negative
+        } else {
+            Self::Neutral
+        }
+    }
+
+    /// Trend of an extractor sentiment label ("positive", "negative", "neutral")
+    pub fn from_sentiment(label: &str) -> Option<Self> {
+        match label.trim().to_ascii_lowercase().as_str() {
+            "positive" => Some(Self::Positive),
+            "neutral" => Some(Self::Neutral),
+            "negative" => Some(Self::Negative),
+            _ => None,
+        }
+    }
+
+    /// Overall trend of a stored series, weighting each day by its sample count
+    ///
+    /// An empty series is neutral.
+    pub fn from_series(points: &[EmotionalTrendPoint]) -> Self {
+        let samples: i64 = points.iter().map(|p| p.sample_count as i64).sum();
+        if samples == 0 {
+            return Self::Neutral;
+        }
+        let weighted: f64 = points.iter().map(|p| p.score * p.sample_count as f64).sum();
+        Self::from_score(weighted / samples as f64)
+    }
+}
+
+/// One day of a user's emotional history
+///
+/// `score` is the mean of the day's recorded scores, normalized to [-1, 1]
+/// (-1 negative, 0 neutral, 1 positive).
+#[derive(Debug, Clone, PartialEq, Queryable, QueryableByName, Serialize, Deserialize)]
+#[diesel(table_name = emotional_trends)]
+pub struct EmotionalTrendPoint {
+    pub user_id: String,
+    pub day: NaiveDate,
+    pub score: f64,
+    pub sample_count: i32,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Persistence for the DeepTalk emotional timeline
+///
+/// Stores one row per user per day; recording again on the same day folds
+/// the score into that row's running mean.
+pub struct EmotionalTrendStore;
+
+impl EmotionalTrendStore {
+    /// Clamp a score to [-1, 1]; NaN is rejected
+    pub fn normalize_score(score: f64) -> Result<f64> {
+        if score.is_nan() {
+            return Err(DirSoulError::Config("Emotional score must be a number".to_string()));
+        }
+        Ok(score.clamp(-1.0, 1.0))
+    }
+
+    /// Record a score for `day`, returning the updated daily point
+    pub fn record(
+        conn: &mut PgConnection,
+        user_id: &str,
+        day: NaiveDate,
+        score: f64,
+    ) -> Result<EmotionalTrendPoint> {
+        let score = Self::normalize_score(score)?;
+
+        Ok(diesel::sql_query(
+            "INSERT INTO emotional_trends (user_id, day, score, sample_count, updated_at)
+             VALUES ($1, $2, $3, 1, NOW())
+             ON CONFLICT (user_id, day) DO UPDATE SET
+                 score = (emotional_trends.score * emotional_trends.sample_count + EXCLUDED.score)
+                         / (emotional_trends.sample_count + 1),
+                 sample_count = emotional_trends.sample_count + 1,
+                 updated_at = NOW()
+             RETURNING user_id, day, score, sample_count, updated_at",
+        )
+        .bind::<diesel::sql_types::Text, _>(user_id)
+        .bind::<diesel::sql_types::Date, _>(day)
+        .bind::<diesel::sql_types::Double, _>(score)
+        .get_result(conn)?)
+    }
+
+    /// Record a classified trend for `day` using its representative score
+    pub fn record_trend(
+        conn: &mut PgConnection,
+        user_id: &str,
+        day: NaiveDate,
+        trend: EmotionalTrend,
+    ) -> Result<EmotionalTrendPoint> {
+        Self::record(conn, user_id, day, trend.score())
+    }
+
+    /// Daily points from `start` to `end` (inclusive), oldest first
+    pub fn series(
+        conn: &mut PgConnection,
+        user_id: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<EmotionalTrendPoint>> {
+        if start > end {
+            return Err(DirSoulError::Config(format!(
+                "Invalid emotional trend range: {} is after {}",
+                start, end
+            )));
+        }
+
+        Ok(emotional_trends::table
+            .filter(emotional_trends::user_id.eq(user_id))
+            .filter(emotional_trends::day.ge(start))
+            .filter(emotional_trends::day.le(end))
+            .order(emotional_trends::day.asc())
+            .load(conn)?)
+    }
 }
 
 /// Conversation context for DeepTalk
@@ -82,6 +216,121 @@ pub struct ConversationContext {
     pub conversation_summary: String,
 }
 
+/// What happens to the oldest turns once a history exceeds its cap
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryOverflowPolicy {
+    /// Drop them
+    #[default]
+    TruncateOldest,
+    /// Fold them into the conversation summary
+    SummarizeOldest,
+}
+
+/// Limits on the conversation history DeepTalk keeps per user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConversationHistoryConfig {
+    /// Turns (user and assistant messages) kept verbatim (default: 40)
+    pub max_turns: usize,
+    /// Handling of turns beyond `max_turns` (default: truncate)
+    pub policy: HistoryOverflowPolicy,
+    /// Characters kept in the conversation summary (default: 2000)
+    pub max_summary_chars: usize,
+}
+
+impl Default for ConversationHistoryConfig {
+    fn default() -> Self {
+        Self {
+            max_turns: 40,
+            policy: HistoryOverflowPolicy::TruncateOldest,
+            max_summary_chars: 2000,
+        }
+    }
+}
+
+impl ConversationHistoryConfig {
+    /// Validate the limits
+    pub fn validate(&self) -> Result<()> {
+        if self.max_turns == 0 {
+            return Err(DirSoulError::Config("max_turns must be at least 1".to_string()));
+        }
+        if self.max_summary_chars == 0 {
+            return Err(DirSoulError::Config(
+                "max_summary_chars must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A user's stored conversation: recent turns plus a summary of older ones
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConversationHistory {
+    /// Summary of turns no longer kept verbatim
+    pub summary: String,
+    /// Most recent turns, oldest first
+    pub turns: Vec<ChatMessage>,
+}
+
+impl ConversationHistory {
+    /// Append turns, enforcing `config.max_turns`
+    ///
+    /// Returns the turns pushed out of the window. With `SummarizeOldest`
+    /// they are first folded into `summary` (see `condense_turns`).
+    pub fn append(
+        &mut self,
+        turns: impl IntoIterator<Item = ChatMessage>,
+        config: &ConversationHistoryConfig,
+    ) -> Vec<ChatMessage> {
+        self.turns.extend(turns);
+        let overflow = self.turns.len().saturating_sub(config.max_turns);
+        let evicted: Vec<ChatMessage> = self.turns.drain(..overflow).collect();
+        if config.policy == HistoryOverflowPolicy::SummarizeOldest && !evicted.is_empty() {
+            self.summary = condense_turns(&self.summary, &evicted, config.max_summary_chars);
+        }
+        evicted
+    }
+
+    /// Summary followed by the recent turns, one per line
+    pub fn render(&self) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        if !self.summary.is_empty() {
+            lines.push(format!("（较早的对话）{}", self.summary));
+        }
+        lines.extend(self.turns.iter().map(turn_line));
+        lines.join("\n")
+    }
+}
+
+/// One turn as a labelled line
+fn turn_line(turn: &ChatMessage) -> String {
+    let speaker = match turn.role.as_str() {
+        "user" => "用户",
+        "assistant" => "助手",
+        _ => "系统",
+    };
+    format!("{}: {}", speaker, turn.content.trim())
+}
+
+/// Append `turns` to `summary`, keeping its last `max_chars` characters
+///
+/// The rule-based summary used when no model summary is available.
+pub fn condense_turns(summary: &str, turns: &[ChatMessage], max_chars: usize) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    if !summary.is_empty() {
+        lines.push(summary.to_string());
+    }
+    lines.extend(turns.iter().map(turn_line));
+    keep_last_chars(&lines.join("\n"), max_chars)
+}
+
+/// The last `max_chars` characters of `text`
+fn keep_last_chars(text: &str, max_chars: usize) -> String {
+    let len = text.chars().count();
+    text.chars().skip(len.saturating_sub(max_chars)).collect()
+}
+
 /// DeepTalk - The always-on default plugin for deep, memory-augmented conversation
 ///
 /// # Features
@@ -102,6 +351,12 @@ pub struct DeepTalkPlugin {
 
     /// User ID for memory retrieval
     user_id: String,
+
+    /// This user's stored conversation
+    history: RwLock<ConversationHistory>,
+
+    /// Cap and overflow policy for `history`
+    history_config: ConversationHistoryConfig,
 }
 
 impl DeepTalkPlugin {
@@ -127,9 +382,64 @@ impl DeepTalkPlugin {
             prompt_manager: Arc::new(RwLock::new(prompt_manager)),
             metadata,
             user_id,
+            history: RwLock::new(ConversationHistory::default()),
+            history_config: ConversationHistoryConfig::default(),
         })
     }
 
+    /// Set the cap and overflow policy of the stored conversation
+    pub fn with_history_config(mut self, history_config: ConversationHistoryConfig) -> Self {
+        self.history_config = history_config;
+        self
+    }
+
+    /// Snapshot of the stored conversation
+    pub async fn history(&self) -> ConversationHistory {
+        self.history.read().await.clone()
+    }
+
+    /// Store a query and its reply, enforcing the history cap
+    ///
+    /// With `SummarizeOldest`, `append` first folds the turns leaving the
+    /// window into the rule-based `condense_turns` summary; the model then
+    /// rewrites that summary. The lock is released while the model runs and
+    /// the rewrite is only stored if no other exchange changed the summary
+    /// meanwhile, so a failed or stale rewrite keeps the condensed summary.
+    async fn record_exchange(&self, query: &str, reply: &str) {
+        let (condensed, evicted) = {
+            let mut history = self.history.write().await;
+            let previous_summary = history.summary.clone();
+            let evicted = history.append(
+                [ChatMessage::user(query), ChatMessage::assistant(reply)],
+                &self.history_config,
+            );
+            (condense_turns(&previous_summary, &evicted, usize::MAX), evicted)
+        };
+        if evicted.is_empty() || self.history_config.policy != HistoryOverflowPolicy::SummarizeOldest {
+            return;
+        }
+
+        let prompt = format!(
+            "将以下对话记录压缩为不超过{}字的中文摘要，只输出摘要。\n\n{}",
+            self.history_config.max_summary_chars, condensed
+        );
+        let summary = match self.llm.generate(&prompt, GenerateOptions::new(0.3, 512)).await {
+            Ok(summary) if !summary.trim().is_empty() => {
+                keep_last_chars(summary.trim(), self.history_config.max_summary_chars)
+            }
+            Ok(_) => return,
+            Err(e) => {
+                tracing::warn!("Conversation summary failed, keeping condensed turns: {}", e);
+                return;
+            }
+        };
+
+        let mut history = self.history.write().await;
+        if history.summary == keep_last_chars(&condensed, self.history_config.max_summary_chars) {
+            history.summary = summary;
+        }
+    }
+
     /// Build context for user query
     async fn build_context(&self, _query: &str) -> Result<ConversationContext> {
         let mut context = ConversationContext::default();
@@ -150,7 +460,7 @@ impl DeepTalkPlugin {
         context.emotional_trend = Self::analyze_emotional_trend_simple();
 
         // Get conversation summary
-        context.conversation_summary = String::new();
+        context.conversation_summary = self.history.read().await.render();
 
         Ok(context)
     }
@@ -201,7 +511,7 @@ impl DeepTalkPlugin {
         // Note: For now, use simple string replacement since Handlebars is not integrated
         let rendered = Self::render_simple(&template, vars);
 
-        Ok(rendered)
+        Ok(prompt_mgr.compose_prompt(PromptTask::Chat, &rendered))
     }
 
     /// Simple template rendering (placeholder for Handlebars)
@@ -316,7 +626,9 @@ impl UserPlugin for DeepTalkPlugin {
         let ctx = self.build_context(query).await?;
 
         // Generate response
-        self.generate_response(query, &ctx).await
+        let response = self.generate_response(query, &ctx).await?;
+        self.record_exchange(query, &response.content).await;
+        Ok(response)
     }
 
     fn subscriptions(&self) -> &[crate::plugin::EventSubscription] {
@@ -330,6 +642,8 @@ impl UserPlugin for DeepTalkPlugin {
     }
 
     async fn initialize(&self) -> Result<()> {
+        self.history_config.validate()?;
+
         // Load and cache prompts on initialization
         let mut prompt_mgr = self.prompt_manager.write().await;
         let _ = prompt_mgr.load_prompt("deeptalk")?;
@@ -340,6 +654,103 @@ impl UserPlugin for DeepTalkPlugin {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::llm_provider::mock::MockProvider;
+
+    fn exchange(i: usize) -> [ChatMessage; 2] {
+        [
+            ChatMessage::user(format!("问题{}", i)),
+            ChatMessage::assistant(format!("回答{}", i)),
+        ]
+    }
+
+    #[test]
+    fn test_history_truncates_oldest_beyond_cap() {
+        let config = ConversationHistoryConfig {
+            max_turns: 4,
+            ..Default::default()
+        };
+        let mut history = ConversationHistory::default();
+        assert!(history.append(exchange(1), &config).is_empty());
+        assert!(history.append(exchange(2), &config).is_empty());
+
+        let evicted = history.append(exchange(3), &config);
+        assert_eq!(evicted.len(), 2);
+        assert_eq!(evicted[0].content, "问题1");
+
+        let kept: Vec<&str> = history.turns.iter().map(|t| t.content.as_str()).collect();
+        assert_eq!(kept, vec!["问题2", "回答2", "问题3", "回答3"]);
+        assert!(history.summary.is_empty());
+    }
+
+    #[test]
+    fn test_history_summarizes_oldest_beyond_cap() {
+        let config = ConversationHistoryConfig {
+            max_turns: 2,
+            policy: HistoryOverflowPolicy::SummarizeOldest,
+            max_summary_chars: 20,
+        };
+        let mut history = ConversationHistory::default();
+        history.append(exchange(1), &config);
+        history.append(exchange(2), &config);
+        assert_eq!(history.summary, "用户: 问题1\n助手: 回答1");
+
+        history.append(exchange(3), &config);
+        let kept: Vec<&str> = history.turns.iter().map(|t| t.content.as_str()).collect();
+        assert_eq!(kept, vec!["问题3", "回答3"]);
+
+        // The summary keeps its most recent part within the budget
+        assert_eq!(history.summary.chars().count(), 20);
+        assert!(history.summary.ends_with("助手: 回答2"));
+        assert!(history.render().ends_with("用户: 问题3\n助手: 回答3"));
+    }
+
+    #[tokio::test]
+    async fn test_summary_call_does_not_hold_history_lock() {
+        let entered = Arc::new(tokio::sync::Notify::new());
+        let release = Arc::new(tokio::sync::Notify::new());
+        let llm = Arc::new(
+            MockProvider::new()
+                .with_reply("模型摘要")
+                .with_gate(entered.clone(), release.clone()),
+        );
+        let prompts = PromptManager::with_dir(std::env::temp_dir()).unwrap();
+        let plugin = Arc::new(
+            DeepTalkPlugin::new(llm, prompts, "user".to_string())
+                .unwrap()
+                .with_history_config(ConversationHistoryConfig {
+                    max_turns: 2,
+                    policy: HistoryOverflowPolicy::SummarizeOldest,
+                    max_summary_chars: 100,
+                }),
+        );
+        plugin.record_exchange("问题1", "回答1").await;
+
+        let recording = tokio::spawn({
+            let plugin = plugin.clone();
+            async move { plugin.record_exchange("问题2", "回答2").await }
+        });
+        entered.notified().await;
+
+        // The condensed summary is readable while the model is still running
+        let history = tokio::time::timeout(std::time::Duration::from_secs(1), plugin.history())
+            .await
+            .expect("history lock held across the summary call");
+        assert_eq!(history.summary, "用户: 问题1\n助手: 回答1");
+
+        release.notify_one();
+        recording.await.unwrap();
+        assert_eq!(plugin.history().await.summary, "模型摘要");
+    }
+
+    #[test]
+    fn test_history_config_validation() {
+        assert!(ConversationHistoryConfig::default().validate().is_ok());
+        let zero = ConversationHistoryConfig {
+            max_turns: 0,
+            ..Default::default()
+        };
+        assert!(zero.validate().is_err());
+    }
 
     #[test]
     fn test_emotional_trend_display() {
@@ -399,6 +810,53 @@ mod tests {
         assert!(!result.contains("Shown"));
     }
 
+    fn point(day: u32, score: f64, sample_count: i32) -> EmotionalTrendPoint {
+        EmotionalTrendPoint {
+            user_id: "test".to_string(),
+            day: NaiveDate::from_ymd_opt(2026, 2, day).unwrap(),
+            score,
+            sample_count,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_emotional_score_normalization() {
+        assert_eq!(EmotionalTrendStore::normalize_score(0.4).unwrap(), 0.4);
+        assert_eq!(EmotionalTrendStore::normalize_score(3.0).unwrap(), 1.0);
+        assert_eq!(EmotionalTrendStore::normalize_score(-7.5).unwrap(), -1.0);
+        assert!(EmotionalTrendStore::normalize_score(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_emotional_trend_from_score() {
+        for trend in [EmotionalTrend::Positive, EmotionalTrend::Neutral, EmotionalTrend::Negative] {
+            assert_eq!(EmotionalTrend::from_score(trend.score()), trend);
+        }
+        assert_eq!(EmotionalTrend::from_score(0.19), EmotionalTrend::Neutral);
+        assert_eq!(EmotionalTrend::from_score(-0.2), EmotionalTrend::Negative);
+    }
+
+    #[test]
+    fn test_emotional_trend_from_sentiment() {
+        assert_eq!(EmotionalTrend::from_sentiment("positive"), Some(EmotionalTrend::Positive));
+        assert_eq!(EmotionalTrend::from_sentiment(" Negative "), Some(EmotionalTrend::Negative));
+        assert_eq!(EmotionalTrend::from_sentiment("neutral"), Some(EmotionalTrend::Neutral));
+        assert_eq!(EmotionalTrend::from_sentiment("mixed"), None);
+    }
+
+    #[test]
+    fn test_emotional_trend_from_series_weights_samples() {
+        assert_eq!(EmotionalTrend::from_series(&[]), EmotionalTrend::Neutral);
+
+        // One very negative sample against three mildly positive ones
+        let series = [point(1, -0.9, 1), point(2, 0.7, 3)];
+        assert_eq!(EmotionalTrend::from_series(&series), EmotionalTrend::Positive);
+
+        let series = [point(1, -0.9, 3), point(2, 0.7, 1)];
+        assert_eq!(EmotionalTrend::from_series(&series), EmotionalTrend::Negative);
+    }
+
     #[test]
     fn test_emotional_trend_serialization() {
         let trend = EmotionalTrend::Positive;
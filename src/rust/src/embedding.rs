@@ -12,6 +12,31 @@
 //! - Default: nomic-embed-text:v1.5 (512 dimensions)
 //! - Fixed embedding model (not user-configurable) to avoid re-indexing
 //! - Inference model is user-configurable (phi4-mini, deepseek-r1, etc.)
+//! - `EmbeddingGenerator::from_models_config` uses the `[embedding]` section
+//!   of `config/models.toml`, independent of the chat model
+//!
+//! # Distance Metric
+//! Write-time normalization and query-time distance must agree. Both are set
+//! in `EmbeddingConfig`; `search_raw_memories` uses the matching pgvector
+//! operator (`<=>` cosine, `<->` L2, `<#>` negative inner product).
+//!
+//! # Model Drift
+//! Vectors from different models are not comparable. Each stored vector
+//! records the model that produced it (`raw_memories.embedding_model`);
+//! `check_embedding_consistency` reports the mix per user, and
+//! `search_raw_memories` warns or refuses (`ModelMismatchPolicy`) when the
+//! stored vectors came from a model other than the query model. Searches
+//! reuse a user's report for `EmbeddingConfig::consistency_ttl_secs`.
+//!
+//! # Backfill
+//! `spawn_embedding_backfill_loop` embeds memories stored without a vector,
+//! a batch per user per tick, through `store_embedding`.
+//!
+//! # Databases without pgvector
+//! `check_vector_capability` reports whether the `vector` extension and the
+//! `raw_memories.embedding` column exist. A generator given a missing
+//! capability (`apply_capability`) is disabled: storing and searching
+//! vectors become no-ops instead of failing on every call.
 //!
 //! # Example
 //! ```no_run
@@ -26,13 +51,17 @@
 //! }
 //! ```
 
+use diesel::prelude::*;
 use reqwest::Client;
-use serde::Deserialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
+use uuid::Uuid;
 
+use crate::llm_provider::{LLMProvider, LlmGovernor, ModelProviderFactory, ModelsConfig};
+use crate::schema::raw_memories;
 use crate::Result;
 
 /// Default embedding dimension (for nomic-embed-text:v1.5)
@@ -41,6 +70,263 @@ pub const EMBEDDING_DIM: usize = 512;
 /// Default Ollama host
 const DEFAULT_OLLAMA_HOST: &str = "http://127.0.0.1:11434";
 
+/// Distance used for nearest-neighbor search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceMetric {
+    /// Cosine distance (`<=>`), matches the `vector_cosine_ops` HNSW indexes
+    Cosine,
+    /// Euclidean distance (`<->`)
+    L2,
+    /// Negative inner product (`<#>`); equals cosine ordering only for unit vectors
+    InnerProduct,
+}
+
+impl DistanceMetric {
+    /// pgvector operator for this metric
+    pub fn operator(self) -> &'static str {
+        match self {
+            DistanceMetric::Cosine => "<=>",
+            DistanceMetric::L2 => "<->",
+            DistanceMetric::InnerProduct => "<#>",
+        }
+    }
+
+    /// Cosine similarity implied by a distance under this metric
+    ///
+    /// Exact for `Cosine`; for `L2` and `InnerProduct` it assumes unit
+    /// vectors, which holds when embeddings are normalized.
+    pub fn similarity(self, distance: f64) -> f64 {
+        match self {
+            DistanceMetric::Cosine => 1.0 - distance,
+            DistanceMetric::L2 => 1.0 - distance * distance / 2.0,
+            DistanceMetric::InnerProduct => -distance,
+        }
+    }
+
+    /// Distance between two vectors as pgvector computes it (smaller is closer)
+    ///
+    /// Vectors of different dimensions are infinitely far apart.
+    pub fn distance(self, a: &[f32], b: &[f32]) -> f32 {
+        if a.len() != b.len() {
+            return f32::INFINITY;
+        }
+
+        match self {
+            DistanceMetric::Cosine => 1.0 - EmbeddingGenerator::cosine_similarity(a, b),
+            DistanceMetric::L2 => a
+                .iter()
+                .zip(b)
+                .map(|(x, y)| (x - y) * (x - y))
+                .sum::<f32>()
+                .sqrt(),
+            DistanceMetric::InnerProduct => -a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>(),
+        }
+    }
+}
+
+/// Semantic search hit from `search_raw_memories`
+#[derive(Debug, Clone, QueryableByName, Serialize, Deserialize)]
+pub struct EmbeddingMatch {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub memory_id: Uuid,
+    /// Distance under the configured metric (smaller is closer)
+    #[diesel(sql_type = diesel::sql_types::Double)]
+    pub distance: f64,
+}
+
+/// Whether the database can store and search vectors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VectorCapability {
+    /// The pgvector `vector` extension is installed
+    pub extension_installed: bool,
+    /// `raw_memories.embedding` exists
+    pub embedding_column: bool,
+}
+
+impl VectorCapability {
+    /// Check if vectors can be stored and searched
+    pub fn is_available(&self) -> bool {
+        self.missing_reason().is_none()
+    }
+
+    /// Why semantic features are unavailable, if they are
+    pub fn missing_reason(&self) -> Option<String> {
+        if !self.extension_installed {
+            Some("the pgvector extension `vector` is not installed".to_string())
+        } else if !self.embedding_column {
+            Some("raw_memories.embedding does not exist; run the migrations".to_string())
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(QueryableByName)]
+struct VectorCapabilityRow {
+    #[diesel(sql_type = diesel::sql_types::Bool)]
+    extension_installed: bool,
+    #[diesel(sql_type = diesel::sql_types::Bool)]
+    embedding_column: bool,
+}
+
+/// Probe the database for pgvector support
+pub fn check_vector_capability(conn: &mut PgConnection) -> Result<VectorCapability> {
+    let row: VectorCapabilityRow = diesel::sql_query(
+        "SELECT
+             EXISTS (SELECT 1 FROM pg_extension WHERE extname = 'vector') AS extension_installed,
+             EXISTS (
+                 SELECT 1 FROM information_schema.columns
+                 WHERE table_schema = current_schema()
+                   AND table_name = 'raw_memories'
+                   AND column_name = 'embedding'
+             ) AS embedding_column",
+    )
+    .get_result(conn)?;
+
+    Ok(VectorCapability {
+        extension_installed: row.extension_installed,
+        embedding_column: row.embedding_column,
+    })
+}
+
+/// What semantic search does when stored vectors came from another model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelMismatchPolicy {
+    /// Log a warning and search anyway
+    #[default]
+    Warn,
+    /// Fail the search until the memories are re-embedded
+    Refuse,
+}
+
+/// Which models produced a user's stored vectors
+///
+/// Vectors stored before the model was tracked are counted as `untracked`
+/// and are not treated as a mismatch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingConsistencyReport {
+    pub user_id: String,
+    /// Vector count per embedding model
+    pub by_model: BTreeMap<String, i64>,
+    /// Vectors with no recorded model
+    pub untracked: i64,
+}
+
+impl EmbeddingConsistencyReport {
+    /// Build a report from `(model, count)` rows
+    pub fn from_counts(user_id: &str, counts: Vec<(Option<String>, i64)>) -> Self {
+        let mut report = Self {
+            user_id: user_id.to_string(),
+            by_model: BTreeMap::new(),
+            untracked: 0,
+        };
+        for (model, count) in counts {
+            match model {
+                Some(model) => *report.by_model.entry(model).or_insert(0) += count,
+                None => report.untracked += count,
+            }
+        }
+        report
+    }
+
+    /// Total number of stored vectors
+    pub fn total(&self) -> i64 {
+        self.by_model.values().sum::<i64>() + self.untracked
+    }
+
+    /// Whether vectors from more than one model are stored
+    pub fn is_mixed(&self) -> bool {
+        self.by_model.len() > 1
+    }
+
+    /// Vectors produced by a model other than `model`
+    pub fn mismatched(&self, model: &str) -> i64 {
+        self.by_model
+            .iter()
+            .filter(|(stored, _)| stored.as_str() != model)
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+/// Count a user's live embedded memories per embedding model
+pub fn check_embedding_consistency(
+    conn: &mut PgConnection,
+    user_id: &str,
+) -> Result<EmbeddingConsistencyReport> {
+    let counts: Vec<(Option<String>, i64)> = raw_memories::table
+        .filter(raw_memories::user_id.eq(user_id))
+        .filter(raw_memories::deleted_at.is_null())
+        .filter(raw_memories::embedding.is_not_null())
+        .group_by(raw_memories::embedding_model)
+        .select((raw_memories::embedding_model, diesel::dsl::count_star()))
+        .load(conn)?;
+
+    Ok(EmbeddingConsistencyReport::from_counts(user_id, counts))
+}
+
+/// Users with live plaintext memories stored without a vector
+pub fn users_with_pending_embeddings(conn: &mut PgConnection) -> Result<Vec<String>> {
+    Ok(raw_memories::table
+        .filter(raw_memories::deleted_at.is_null())
+        .filter(raw_memories::embedding.is_null())
+        .filter(raw_memories::content.is_not_null())
+        .select(raw_memories::user_id)
+        .distinct()
+        .load(conn)?)
+}
+
+/// Embed up to `batch_size` pending memories per user on a Tokio interval
+///
+/// The first tick fires immediately. A failing user is logged without
+/// stopping the others or later ticks; ticks are skipped while the
+/// generator is disabled.
+pub fn spawn_embedding_backfill_loop(
+    database_url: String,
+    embedder: Arc<EmbeddingGenerator>,
+    interval: std::time::Duration,
+    batch_size: i64,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            ticker.tick().await;
+            if !embedder.is_enabled() {
+                continue;
+            }
+
+            let mut conn = match PgConnection::establish(&database_url) {
+                Ok(conn) => conn,
+                Err(e) => {
+                    warn!("Embedding backfill skipped: {}", e);
+                    continue;
+                }
+            };
+            let user_ids = match users_with_pending_embeddings(&mut conn) {
+                Ok(user_ids) => user_ids,
+                Err(e) => {
+                    warn!("Embedding backfill skipped: {}", e);
+                    continue;
+                }
+            };
+
+            for user_id in user_ids {
+                match embedder.embed_pending_memories(&mut conn, &user_id, batch_size).await {
+                    Ok(stored) => debug!("Embedded {} memories for user {}", stored, user_id),
+                    Err(e) => warn!("Embedding backfill failed for user {}: {}", user_id, e),
+                }
+            }
+        }
+    })
+}
+
+/// A consistency report and when it was taken
+type CachedConsistency = (std::time::Instant, EmbeddingConsistencyReport);
+
 /// Configuration for embedding generation
 #[derive(Debug, Clone)]
 pub struct EmbeddingConfig {
@@ -52,6 +338,14 @@ pub struct EmbeddingConfig {
     pub batch_size: usize,
     /// Request timeout in seconds
     pub timeout_secs: u64,
+    /// L2-normalize vectors before they are returned and stored
+    pub normalize: bool,
+    /// Distance used by semantic search
+    pub metric: DistanceMetric,
+    /// Search behavior when stored vectors came from another model
+    pub on_model_mismatch: ModelMismatchPolicy,
+    /// How long a user's consistency report is reused by searches (seconds)
+    pub consistency_ttl_secs: u64,
 }
 
 impl Default for EmbeddingConfig {
@@ -61,6 +355,10 @@ impl Default for EmbeddingConfig {
             model: "nomic-embed-text:v1.5".to_string(),
             batch_size: 8,
             timeout_secs: 120,
+            normalize: true,
+            metric: DistanceMetric::Cosine,
+            on_model_mismatch: ModelMismatchPolicy::Warn,
+            consistency_ttl_secs: 300,
         }
     }
 }
@@ -134,6 +432,14 @@ pub struct EmbeddingGenerator {
     client: Client,
     config: EmbeddingConfig,
     cache: EmbeddingCache,
+    /// Embedding provider; when unset, Ollama is called directly
+    provider: Option<Arc<dyn LLMProvider>>,
+    /// Shared cap on in-flight LLM calls, if any
+    governor: Option<LlmGovernor>,
+    /// Why vector storage and search are off (see `apply_capability`)
+    disabled: std::sync::RwLock<Option<String>>,
+    /// Recent consistency reports by user, with when they were taken
+    consistency: std::sync::Mutex<HashMap<String, CachedConsistency>>,
 }
 
 impl EmbeddingGenerator {
@@ -161,9 +467,81 @@ impl EmbeddingGenerator {
             client,
             config,
             cache: EmbeddingCache::new(1000), // Cache up to 1000 embeddings
+            provider: None,
+            governor: None,
+            disabled: std::sync::RwLock::new(None),
+            consistency: std::sync::Mutex::new(HashMap::new()),
         })
     }
 
+    /// Create a generator backed by an existing embedding provider
+    ///
+    /// The provider's model name replaces `config.model`.
+    pub fn with_provider(provider: Arc<dyn LLMProvider>, mut config: EmbeddingConfig) -> Self {
+        config.model = provider.model_name();
+        info!("Initializing embedding generator with provider: model={}", config.model);
+
+        Self {
+            client: Client::new(),
+            config,
+            cache: EmbeddingCache::new(1000),
+            provider: Some(provider),
+            governor: None,
+            disabled: std::sync::RwLock::new(None),
+            consistency: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Create from the `[embedding]` section of the dual model configuration
+    pub fn from_models_config(models: &ModelsConfig) -> Result<Self> {
+        let provider = ModelProviderFactory::create_provider(models.embedding.clone())?;
+        Ok(Self::with_provider(provider, EmbeddingConfig::default()))
+    }
+
+    /// Count embedding requests against a shared `LlmGovernor`
+    ///
+    /// Cache hits never wait for a permit.
+    pub fn with_governor(mut self, governor: LlmGovernor) -> Self {
+        self.governor = Some(governor);
+        self
+    }
+
+    /// Model used for embeddings
+    pub fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    /// Distance used by semantic search
+    pub fn metric(&self) -> DistanceMetric {
+        self.config.metric
+    }
+
+    /// Turn vector storage and search on or off for `capability`
+    ///
+    /// Logs one warning when semantic features are disabled. Embedding
+    /// generation itself does not need the database and keeps working.
+    pub fn apply_capability(&self, capability: &VectorCapability) {
+        let reason = capability.missing_reason();
+        match &reason {
+            Some(reason) => warn!(
+                "Semantic features disabled: {}; keyword search remains available",
+                reason
+            ),
+            None => debug!("pgvector available, semantic features enabled"),
+        }
+        *self.disabled.write().unwrap_or_else(|e| e.into_inner()) = reason;
+    }
+
+    /// Check if vectors are stored and searched
+    pub fn is_enabled(&self) -> bool {
+        self.disabled_reason().is_none()
+    }
+
+    /// Why vector storage and search are disabled, if they are
+    pub fn disabled_reason(&self) -> Option<String> {
+        self.disabled.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
     /// Create with default configuration
     pub async fn default_config() -> Result<Self> {
         Self::new(EmbeddingConfig::default()).await
@@ -185,6 +563,30 @@ impl EmbeddingGenerator {
 
         debug!("Generating embedding for text: {} chars", text.len());
 
+        let permit = match &self.governor {
+            Some(governor) => Some(governor.acquire().await?),
+            None => None,
+        };
+        let raw = match &self.provider {
+            Some(provider) => provider.embed(text).await?,
+            None => self.request_ollama_embedding(text).await?,
+        };
+        drop(permit);
+
+        let embedding = if self.config.normalize {
+            Self::normalize_embedding(raw)
+        } else {
+            raw
+        };
+
+        // Cache the result
+        self.cache.set(text.to_string(), embedding.clone()).await;
+
+        Ok(embedding)
+    }
+
+    /// Call Ollama's embeddings endpoint directly
+    async fn request_ollama_embedding(&self, text: &str) -> Result<Vec<f32>> {
         let url = format!("{}/api/embeddings", self.config.host);
         let body = serde_json::json!({
             "model": self.config.model,
@@ -214,12 +616,7 @@ impl EmbeddingGenerator {
                 crate::DirSoulError::Encryption(format!("Failed to parse response: {}", e))
             })?;
 
-        let embedding = Self::normalize_embedding(response.embedding);
-
-        // Cache the result
-        self.cache.set(text.to_string(), embedding.clone()).await;
-
-        Ok(embedding)
+        Ok(response.embedding)
     }
 
     /// Generate embeddings for multiple texts (batch processing)
@@ -316,6 +713,212 @@ impl EmbeddingGenerator {
         embedding
     }
 
+    /// Rank candidates by distance to `query`, closest first, keeping `k`
+    pub fn nearest_neighbors<K: Clone>(
+        query: &[f32],
+        candidates: &[(K, Vec<f32>)],
+        metric: DistanceMetric,
+        k: usize,
+    ) -> Vec<(K, f32)> {
+        let mut ranked: Vec<(K, f32)> = candidates
+            .iter()
+            .map(|(key, embedding)| (key.clone(), metric.distance(query, embedding)))
+            .collect();
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+        ranked.truncate(k);
+        ranked
+    }
+
+    /// Find a user's raw memories closest to `query` under the configured metric
+    ///
+    /// `query` should come from `generate` on this generator so it was
+    /// normalized the same way as the stored embeddings. Stored vectors from
+    /// another model are handled per `EmbeddingConfig::on_model_mismatch`.
+    /// Returns no matches while the generator is disabled.
+    pub fn search_raw_memories(
+        &self,
+        conn: &mut PgConnection,
+        user_id: &str,
+        query: &[f32],
+        limit: i64,
+    ) -> Result<Vec<EmbeddingMatch>> {
+        if !self.is_enabled() {
+            debug!("Semantic search skipped for user {}: embeddings disabled", user_id);
+            return Ok(vec![]);
+        }
+
+        let report = self.consistency_report(conn, user_id)?;
+        self.check_model_drift(&report)?;
+
+        Ok(diesel::sql_query(Self::search_sql(self.config.metric))
+            .bind::<diesel::sql_types::Text, _>(user_id)
+            .bind::<diesel::sql_types::Text, _>(Self::vector_literal(query))
+            .bind::<diesel::sql_types::BigInt, _>(limit)
+            .load(conn)?)
+    }
+
+    /// `check_embedding_consistency`, reused for `consistency_ttl_secs`
+    ///
+    /// `store_embedding` drops the user's report, so this generator's own
+    /// writes are seen at once; other writers' once the report expires.
+    fn consistency_report(
+        &self,
+        conn: &mut PgConnection,
+        user_id: &str,
+    ) -> Result<EmbeddingConsistencyReport> {
+        let ttl = std::time::Duration::from_secs(self.config.consistency_ttl_secs);
+        {
+            let reports = self.consistency.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some((taken_at, report)) = reports.get(user_id) {
+                if taken_at.elapsed() < ttl {
+                    return Ok(report.clone());
+                }
+            }
+        }
+
+        let report = check_embedding_consistency(conn, user_id)?;
+        let mut reports = self.consistency.lock().unwrap_or_else(|e| e.into_inner());
+        reports.retain(|_, (taken_at, _)| taken_at.elapsed() < ttl);
+        reports.insert(user_id.to_string(), (std::time::Instant::now(), report.clone()));
+        Ok(report)
+    }
+
+    /// Store the vector of one of `user_id`'s memories, with this
+    /// generator's model name
+    ///
+    /// Does nothing while the generator is disabled. Fails with `NotFound`
+    /// when the memory does not belong to the user or was deleted.
+    pub fn store_embedding(
+        &self,
+        conn: &mut PgConnection,
+        user_id: &str,
+        memory_id: Uuid,
+        embedding: &[f32],
+    ) -> Result<()> {
+        if !self.is_enabled() {
+            debug!("Embedding for memory {} not stored: embeddings disabled", memory_id);
+            return Ok(());
+        }
+
+        let updated = diesel::sql_query(
+            "UPDATE raw_memories SET embedding = $3::vector, embedding_model = $4
+             WHERE memory_id = $1 AND user_id = $2 AND deleted_at IS NULL",
+        )
+        .bind::<diesel::sql_types::Uuid, _>(memory_id)
+        .bind::<diesel::sql_types::Text, _>(user_id)
+        .bind::<diesel::sql_types::Text, _>(Self::vector_literal(embedding))
+        .bind::<diesel::sql_types::Text, _>(self.model())
+        .execute(conn)?;
+        self.consistency
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(user_id);
+
+        if updated == 0 {
+            return Err(crate::DirSoulError::NotFound(format!(
+                "Memory {} not found for user {}",
+                memory_id, user_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Embed up to `limit` of a user's live memories stored without a vector
+    ///
+    /// Oldest first; memories without plaintext content are left alone, and
+    /// memories deleted meanwhile are skipped. Returns how many vectors were
+    /// stored, none while disabled.
+    pub async fn embed_pending_memories(
+        &self,
+        conn: &mut PgConnection,
+        user_id: &str,
+        limit: i64,
+    ) -> Result<usize> {
+        if !self.is_enabled() {
+            return Ok(0);
+        }
+
+        let pending: Vec<(Uuid, Option<String>)> = raw_memories::table
+            .filter(raw_memories::user_id.eq(user_id))
+            .filter(raw_memories::deleted_at.is_null())
+            .filter(raw_memories::embedding.is_null())
+            .filter(raw_memories::content.is_not_null())
+            .order(raw_memories::created_at.asc())
+            .limit(limit)
+            .select((raw_memories::memory_id, raw_memories::content))
+            .load(conn)?;
+        if pending.is_empty() {
+            return Ok(0);
+        }
+
+        let texts: Vec<String> =
+            pending.iter().map(|(_, content)| content.clone().unwrap_or_default()).collect();
+        let vectors = self.generate_batch(&texts).await?;
+        let mut stored = 0;
+        for ((memory_id, _), vector) in pending.iter().zip(&vectors) {
+            match self.store_embedding(conn, user_id, *memory_id, vector) {
+                Ok(()) => stored += 1,
+                Err(crate::DirSoulError::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(stored)
+    }
+
+    /// Apply the mismatch policy to a consistency report
+    ///
+    /// Returns `DirSoulError::Config` under `Refuse` when any stored vector
+    /// came from a model other than this generator's.
+    pub fn check_model_drift(&self, report: &EmbeddingConsistencyReport) -> Result<()> {
+        let mismatched = report.mismatched(self.model());
+        if mismatched == 0 {
+            return Ok(());
+        }
+
+        let stored: Vec<&str> = report.by_model.keys().map(|m| m.as_str()).collect();
+        match self.config.on_model_mismatch {
+            ModelMismatchPolicy::Warn => {
+                warn!(
+                    "{} of {} vectors for user {} come from other models ({:?}), query model is {}",
+                    mismatched,
+                    report.total(),
+                    report.user_id,
+                    stored,
+                    self.model()
+                );
+                Ok(())
+            }
+            ModelMismatchPolicy::Refuse => Err(crate::DirSoulError::Config(format!(
+                "Embedding model drift for user {}: {} vectors come from {:?} but the query model is {}; re-embed the memories first",
+                report.user_id,
+                mismatched,
+                stored,
+                self.model()
+            ))),
+        }
+    }
+
+    /// Nearest-neighbor SQL over `raw_memories` for a metric
+    ///
+    /// Binds: `$1` user id, `$2` query vector literal, `$3` limit.
+    fn search_sql(metric: DistanceMetric) -> String {
+        let op = metric.operator();
+        format!(
+            "SELECT memory_id, (embedding {op} $2::vector)::float8 AS distance
+             FROM raw_memories
+             WHERE user_id = $1 AND deleted_at IS NULL AND embedding IS NOT NULL
+             ORDER BY embedding {op} $2::vector
+             LIMIT $3",
+            op = op
+        )
+    }
+
+    /// pgvector text literal, e.g. `[0.6,0.8]`
+    fn vector_literal(embedding: &[f32]) -> String {
+        let values: Vec<String> = embedding.iter().map(|v| v.to_string()).collect();
+        format!("[{}]", values.join(","))
+    }
+
     /// Verify that the model is available in Ollama
     async fn verify_model(client: &Client, host: &str, model: &str) -> Result<()> {
         let url = format!("{}/api/tags", host);
@@ -355,6 +958,7 @@ impl EmbeddingGenerator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::llm_provider::mock::MockProvider;
 
     #[test]
     fn test_cosine_similarity_identical() {
@@ -414,6 +1018,45 @@ mod tests {
         assert_eq!(normalized, vec![0.0, 0.0, 0.0]);
     }
 
+    #[tokio::test]
+    async fn test_generator_uses_embedding_provider() {
+        let provider = Arc::new(
+            MockProvider::new()
+                .with_model("nomic-embed-text")
+                .with_embedding(vec![3.0, 4.0]),
+        );
+        let generator = EmbeddingGenerator::with_provider(provider.clone(), EmbeddingConfig::default());
+
+        assert_eq!(generator.model(), "nomic-embed-text");
+
+        let embedding = generator.generate("hello").await.unwrap();
+        assert!((embedding[0] - 0.6).abs() < 0.001);
+        assert!((embedding[1] - 0.8).abs() < 0.001);
+
+        // Second call is served from cache
+        generator.generate("hello").await.unwrap();
+        assert_eq!(provider.embedded(), vec!["hello".to_string()]);
+    }
+
+    #[test]
+    fn test_generator_from_models_config_uses_embedding_section() {
+        let models = ModelsConfig::from_toml_str(
+            r#"
+            [embedding]
+            provider = "ollama"
+            model = "nomic-embed-text"
+
+            [inference]
+            provider = "ollama"
+            model = "phi4-mini"
+            "#,
+        )
+        .unwrap();
+
+        let generator = EmbeddingGenerator::from_models_config(&models).unwrap();
+        assert_eq!(generator.model(), "nomic-embed-text");
+    }
+
     #[test]
     fn test_embedding_config_default() {
         let config = EmbeddingConfig::default();
@@ -421,5 +1064,191 @@ mod tests {
         assert_eq!(config.model, "nomic-embed-text:v1.5");
         assert_eq!(config.batch_size, 8);
         assert_eq!(config.timeout_secs, 120);
+        assert!(config.normalize);
+        assert_eq!(config.metric, DistanceMetric::Cosine);
+    }
+
+    #[test]
+    fn test_search_sql_uses_metric_operator() {
+        let cosine = EmbeddingGenerator::search_sql(DistanceMetric::Cosine);
+        assert!(cosine.contains("embedding <=> $2::vector"));
+        assert!(!cosine.contains("<->"));
+
+        assert!(EmbeddingGenerator::search_sql(DistanceMetric::L2).contains("ORDER BY embedding <-> $2::vector"));
+        assert!(EmbeddingGenerator::search_sql(DistanceMetric::InnerProduct).contains("ORDER BY embedding <#> $2::vector"));
+        assert_eq!(EmbeddingGenerator::vector_literal(&[0.6, 0.8]), "[0.6,0.8]");
+    }
+
+    #[test]
+    fn test_similarity_from_distance_agrees_across_metrics() {
+        let a = EmbeddingGenerator::normalize_embedding(vec![3.0, 4.0]);
+        let b = EmbeddingGenerator::normalize_embedding(vec![4.0, 3.0]);
+        let cosine = EmbeddingGenerator::cosine_similarity(&a, &b) as f64;
+
+        for metric in [DistanceMetric::Cosine, DistanceMetric::L2, DistanceMetric::InnerProduct] {
+            let similarity = metric.similarity(metric.distance(&a, &b) as f64);
+            assert!((similarity - cosine).abs() < 1e-5, "{:?}: {}", metric, similarity);
+        }
+    }
+
+    #[test]
+    fn test_normalized_cosine_nearest_neighbors() {
+        let candidates: Vec<(&str, Vec<f32>)> = vec![
+            ("far", vec![-1.0, 0.2]),
+            ("close_but_long", vec![30.0, 4.0]),
+            ("closest", vec![0.99, 0.1]),
+            ("orthogonal", vec![0.0, 2.0]),
+        ]
+        .into_iter()
+        .map(|(key, v)| (key, EmbeddingGenerator::normalize_embedding(v)))
+        .collect();
+        let query = EmbeddingGenerator::normalize_embedding(vec![5.0, 0.5]);
+
+        let ranked = EmbeddingGenerator::nearest_neighbors(&query, &candidates, DistanceMetric::Cosine, 3);
+        let keys: Vec<&str> = ranked.iter().map(|(key, _)| *key).collect();
+        assert_eq!(keys, vec!["closest", "close_but_long", "orthogonal"]);
+
+        // On unit vectors inner product and L2 agree with cosine
+        for metric in [DistanceMetric::InnerProduct, DistanceMetric::L2] {
+            let ranked = EmbeddingGenerator::nearest_neighbors(&query, &candidates, metric, 3);
+            let keys: Vec<&str> = ranked.iter().map(|(key, _)| *key).collect();
+            assert_eq!(keys, vec!["closest", "close_but_long", "orthogonal"]);
+        }
+    }
+
+    #[test]
+    fn test_unnormalized_inner_product_favors_long_vectors() {
+        // The classic mismatch: raw vectors searched with inner product
+        let candidates = vec![("closest", vec![0.99, 0.1]), ("long", vec![30.0, 4.0])];
+        let ranked = EmbeddingGenerator::nearest_neighbors(&[5.0, 0.5], &candidates, DistanceMetric::InnerProduct, 2);
+        assert_eq!(ranked[0].0, "long");
+    }
+
+    #[tokio::test]
+    async fn test_generator_skips_normalization_when_disabled() {
+        let provider = Arc::new(
+            MockProvider::new()
+                .with_model("nomic-embed-text")
+                .with_embedding(vec![3.0, 4.0]),
+        );
+        let generator = EmbeddingGenerator::with_provider(
+            provider,
+            EmbeddingConfig {
+                normalize: false,
+                metric: DistanceMetric::L2,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(generator.generate("hello").await.unwrap(), vec![3.0, 4.0]);
+        assert_eq!(generator.metric(), DistanceMetric::L2);
+    }
+
+    fn generator_for(model: &str, policy: ModelMismatchPolicy) -> EmbeddingGenerator {
+        let provider = Arc::new(MockProvider::new().with_model(model).with_embedding(vec![3.0, 4.0]));
+        EmbeddingGenerator::with_provider(
+            provider,
+            EmbeddingConfig {
+                on_model_mismatch: policy,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_consistency_report_detects_mixed_models() {
+        let report = EmbeddingConsistencyReport::from_counts(
+            "user",
+            vec![
+                (Some("nomic-embed-text:v1.5".to_string()), 40),
+                (Some("bge-m3".to_string()), 2),
+                (None, 5),
+            ],
+        );
+
+        assert!(report.is_mixed());
+        assert_eq!(report.total(), 47);
+        assert_eq!(report.untracked, 5);
+        assert_eq!(report.mismatched("nomic-embed-text:v1.5"), 2);
+        assert_eq!(report.mismatched("bge-m3"), 40);
+
+        let single = EmbeddingConsistencyReport::from_counts(
+            "user",
+            vec![(Some("bge-m3".to_string()), 3), (None, 1)],
+        );
+        assert!(!single.is_mixed());
+        assert_eq!(single.mismatched("bge-m3"), 0);
+    }
+
+    #[test]
+    fn test_vector_capability_reasons() {
+        let full = VectorCapability {
+            extension_installed: true,
+            embedding_column: true,
+        };
+        assert!(full.is_available());
+
+        let no_extension = VectorCapability {
+            extension_installed: false,
+            embedding_column: false,
+        };
+        assert!(!no_extension.is_available());
+        assert!(no_extension.missing_reason().unwrap().contains("pgvector"));
+
+        let no_column = VectorCapability {
+            extension_installed: true,
+            embedding_column: false,
+        };
+        assert!(no_column.missing_reason().unwrap().contains("raw_memories.embedding"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_extension_disables_generator() {
+        let generator = generator_for("nomic-embed-text:v1.5", ModelMismatchPolicy::Warn);
+        assert!(generator.is_enabled());
+
+        generator.apply_capability(&VectorCapability {
+            extension_installed: false,
+            embedding_column: true,
+        });
+        assert!(!generator.is_enabled());
+        assert!(generator.disabled_reason().unwrap().contains("pgvector"));
+
+        // Generating embeddings does not need the database
+        assert_eq!(generator.generate("苹果").await.unwrap().len(), 2);
+
+        generator.apply_capability(&VectorCapability {
+            extension_installed: true,
+            embedding_column: true,
+        });
+        assert!(generator.is_enabled());
+    }
+
+    #[test]
+    fn test_model_drift_policy() {
+        let report = EmbeddingConsistencyReport::from_counts(
+            "user",
+            vec![(Some("nomic-embed-text".to_string()), 10), (Some("bge-m3".to_string()), 3)],
+        );
+
+        let warn = generator_for("nomic-embed-text", ModelMismatchPolicy::Warn);
+        assert!(warn.check_model_drift(&report).is_ok());
+
+        let refuse = generator_for("nomic-embed-text", ModelMismatchPolicy::Refuse);
+        match refuse.check_model_drift(&report) {
+            Err(crate::DirSoulError::Config(message)) => {
+                assert!(message.contains("bge-m3"));
+                assert!(message.contains("re-embed"));
+            }
+            other => panic!("expected refusal, got {:?}", other),
+        }
+
+        // Matching and untracked vectors never trigger the policy
+        let consistent = EmbeddingConsistencyReport::from_counts(
+            "user",
+            vec![(Some("nomic-embed-text".to_string()), 10), (None, 4)],
+        );
+        assert!(refuse.check_model_drift(&consistent).is_ok());
+        assert_eq!(EmbeddingConfig::default().on_model_mismatch, ModelMismatchPolicy::Warn);
     }
 }
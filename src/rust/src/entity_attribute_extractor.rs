@@ -11,6 +11,7 @@
 //! - Extract attributes from event context (color, category, texture, etc.)
 //! - Update entity JSONB attributes with confidence scores
 //! - Merge new attributes with existing ones
+//! - Hold low-confidence attributes in a `tentative` bucket until corroborated
 
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -20,6 +21,9 @@ use std::collections::HashMap;
 use crate::error::Result;
 use crate::models::Entity;
 
+/// Key inside entity `attributes` holding attributes awaiting corroboration
+pub const TENTATIVE_ATTRIBUTES_KEY: &str = "tentative";
+
 /// Attribute types that can be extracted from events
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -87,29 +91,46 @@ impl Attribute {
 /// Entity attribute extractor
 ///
 /// Extracts attributes from event context and updates entities.
+/// Attributes below `min_attribute_confidence` are kept out of the main
+/// attributes and wait in the `tentative` bucket until corroborated.
 pub struct EntityAttributeExtractor {
-    /// Confidence threshold for accepting attributes
-    confidence_threshold: f64,
+    /// Minimum confidence for an attribute to enter the main attributes
+    min_attribute_confidence: f64,
+    /// Observations after which a tentative attribute is promoted anyway
+    corroboration_count: i32,
 }
 
 impl EntityAttributeExtractor {
     /// Create a new attribute extractor with default configuration
     pub fn new() -> Self {
         Self {
-            confidence_threshold: 0.5,
+            min_attribute_confidence: 0.5,
+            corroboration_count: 3,
         }
     }
 
     /// Create a new attribute extractor with custom confidence threshold
     ///
     /// # Arguments
-    /// * `confidence_threshold` - Minimum confidence for accepting attributes (0.0 to 1.0)
-    pub fn with_threshold(confidence_threshold: f64) -> Self {
+    /// * `min_attribute_confidence` - Minimum confidence for accepting attributes (0.0 to 1.0)
+    pub fn with_threshold(min_attribute_confidence: f64) -> Self {
         Self {
-            confidence_threshold: confidence_threshold.clamp(0.0, 1.0),
+            min_attribute_confidence: min_attribute_confidence.clamp(0.0, 1.0),
+            ..Self::new()
         }
     }
 
+    /// Set how many matching observations promote a tentative attribute
+    pub fn with_corroboration_count(mut self, count: i32) -> Self {
+        self.corroboration_count = count.max(1);
+        self
+    }
+
+    /// Minimum confidence for an attribute to enter the main attributes
+    pub fn min_attribute_confidence(&self) -> f64 {
+        self.min_attribute_confidence
+    }
+
     /// Extract attributes from event context using rule-based patterns
     ///
     /// This is a fallback when SLM is not available.
@@ -215,20 +236,55 @@ impl EntityAttributeExtractor {
     ) -> Result<Entity> {
         use crate::schema::entities::dsl::*;
 
-        // Get existing attributes
-        let mut existing_attrs = entity.attributes.unwrap_or(json!({}));
+        let existing_attrs = self.merge_attributes(
+            entity.attributes.unwrap_or(json!({})),
+            new_attributes,
+        )?;
 
-        // Merge new attributes
-        for (attr_type, new_attr) in new_attributes {
-            // Skip if below confidence threshold
-            if new_attr.confidence < self.confidence_threshold {
-                continue;
-            }
+        // Update in database
+        diesel::update(entities.find(entity.entity_id))
+            .set(attributes.eq(Some(existing_attrs)))
+            .execute(conn)?;
+
+        // Fetch updated entity
+        let updated_entity = entities
+            .find(entity.entity_id)
+            .first::<Entity>(conn)?;
+
+        Ok(updated_entity)
+    }
 
+    /// Merge new attributes into an entity's attributes JSON
+    ///
+    /// - Confident attributes (>= `min_attribute_confidence`) go into the main
+    ///   attributes, absorbing any tentative observation of the same value.
+    /// - Less confident ones are recorded under `tentative`, unless the main
+    ///   attributes already have that key. A tentative attribute is promoted
+    ///   once its averaged confidence reaches the threshold or it has been
+    ///   seen `corroboration_count` times.
+    pub fn merge_attributes(
+        &self,
+        mut existing_attrs: serde_json::Value,
+        new_attributes: HashMap<AttributeType, Attribute>,
+    ) -> Result<serde_json::Value> {
+        if !existing_attrs.is_object() {
+            existing_attrs = json!({});
+        }
+        let mut tentative = existing_attrs
+            .get(TENTATIVE_ATTRIBUTES_KEY)
+            .filter(|t| t.is_object())
+            .cloned()
+            .unwrap_or(json!({}));
+
+        for (attr_type, new_attr) in new_attributes {
             let attr_key = self.attr_type_to_key(&attr_type);
+            let confident = new_attr.confidence >= self.min_attribute_confidence;
 
             if let Some(existing_attr_json) = existing_attrs.get(&attr_key) {
-                // Attribute exists - update it
+                // Established attribute: low-confidence guesses don't touch it
+                if !confident {
+                    continue;
+                }
                 if let Ok(mut existing_attr) = serde_json::from_value::<Attribute>(existing_attr_json.clone()) {
                     existing_attr.update(new_attr.confidence);
                     existing_attrs[attr_key] = serde_json::to_value(existing_attr)?;
@@ -236,23 +292,43 @@ impl EntityAttributeExtractor {
                     // Failed to parse, create new
                     existing_attrs[attr_key] = serde_json::to_value(new_attr)?;
                 }
+                continue;
+            }
+
+            // Corroborate a pending observation of the same value
+            let pending = tentative
+                .get(&attr_key)
+                .and_then(|t| serde_json::from_value::<Attribute>(t.clone()).ok())
+                .filter(|t| t.value == new_attr.value);
+            let candidate = match pending {
+                Some(mut pending) => {
+                    pending.update(new_attr.confidence);
+                    pending
+                }
+                None => new_attr,
+            };
+
+            let promote = confident
+                || candidate.confidence >= self.min_attribute_confidence
+                || candidate.count >= self.corroboration_count;
+            if promote {
+                if let Some(bucket) = tentative.as_object_mut() {
+                    bucket.remove(&attr_key);
+                }
+                existing_attrs[attr_key] = serde_json::to_value(candidate)?;
             } else {
-                // New attribute - add it
-                existing_attrs[attr_key] = serde_json::to_value(new_attr)?;
+                tentative[attr_key] = serde_json::to_value(candidate)?;
             }
         }
 
-        // Update in database
-        diesel::update(entities.find(entity.entity_id))
-            .set(attributes.eq(Some(existing_attrs)))
-            .execute(conn)?;
-
-        // Fetch updated entity
-        let updated_entity = entities
-            .find(entity.entity_id)
-            .first::<Entity>(conn)?;
+        let bucket = existing_attrs.as_object_mut().expect("attributes is an object");
+        if tentative.as_object().is_some_and(|t| !t.is_empty()) {
+            bucket.insert(TENTATIVE_ATTRIBUTES_KEY.to_string(), tentative);
+        } else {
+            bucket.remove(TENTATIVE_ATTRIBUTES_KEY);
+        }
 
-        Ok(updated_entity)
+        Ok(existing_attrs)
     }
 
     /// Extract and update attributes in one operation
@@ -406,6 +482,56 @@ mod tests {
 
         // Attribute with 0.7 confidence should be filtered out
         // This test verifies the threshold is applied in update_entity_attributes
+        let merged = extractor.merge_attributes(json!({}), attrs).unwrap();
+        assert!(merged.get("color").is_none());
+        assert_eq!(merged[TENTATIVE_ATTRIBUTES_KEY]["color"]["value"], "红色");
+    }
+
+    #[test]
+    fn test_low_confidence_attribute_is_withheld() {
+        let extractor = EntityAttributeExtractor::new();
+        let new_attributes = HashMap::from([
+            (AttributeType::Color, Attribute::new("红色".to_string(), 0.9)),
+            (AttributeType::Taste, Attribute::new("酸".to_string(), 0.4)),
+        ]);
+
+        let merged = extractor.merge_attributes(json!({}), new_attributes).unwrap();
+
+        assert_eq!(merged["color"]["value"], "红色");
+        assert!(merged.get("taste").is_none());
+        assert_eq!(merged[TENTATIVE_ATTRIBUTES_KEY]["taste"]["value"], "酸");
+    }
+
+    #[test]
+    fn test_tentative_attribute_promoted_after_corroboration() {
+        let extractor = EntityAttributeExtractor::new().with_corroboration_count(3);
+        let observe = |attrs: serde_json::Value, value: &str, confidence: f64| {
+            let new_attributes = HashMap::from([(
+                AttributeType::Taste,
+                Attribute::new(value.to_string(), confidence),
+            )]);
+            extractor.merge_attributes(attrs, new_attributes).unwrap()
+        };
+
+        let attrs = observe(json!({}), "酸", 0.4);
+        let attrs = observe(attrs, "酸", 0.4);
+        assert!(attrs.get("taste").is_none());
+        assert_eq!(attrs[TENTATIVE_ATTRIBUTES_KEY]["taste"]["count"], 2);
+
+        // A different value restarts the pending observation
+        let attrs = observe(attrs, "苦", 0.4);
+        assert_eq!(attrs[TENTATIVE_ATTRIBUTES_KEY]["taste"]["count"], 1);
+
+        let attrs = observe(attrs, "苦", 0.4);
+        let attrs = observe(attrs, "苦", 0.4);
+        assert_eq!(attrs["taste"]["value"], "苦");
+        assert_eq!(attrs["taste"]["count"], 3);
+        assert!(attrs.get(TENTATIVE_ATTRIBUTES_KEY).is_none());
+
+        // Established attributes ignore low-confidence guesses
+        let attrs = observe(attrs, "甜", 0.3);
+        assert_eq!(attrs["taste"]["value"], "苦");
+        assert!(attrs.get(TENTATIVE_ATTRIBUTES_KEY).is_none());
     }
 
     #[test]
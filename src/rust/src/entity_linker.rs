@@ -9,13 +9,16 @@
 //!
 //! # Core Functionality
 //! - `link_entity()`: Link mentions to existing or new entities
+//! - `upsert_entity()`: Insert an extracted entity or fold it into the existing row
 //! - Context disambiguation: "吃苹果" → fruit, "买苹果股票" → company
 //! - Entity updates: occurrence_count, last_seen, attributes
 
 use diesel::prelude::*;
 
-use crate::error::Result;
-use crate::models::{Entity, EntityType, NewEntity};
+use crate::error::{DirSoulError, Result};
+use crate::models::{
+    add_surface_form, canonicalize_name, Entity, EntityType, NewEntity, SURFACE_FORMS_KEY,
+};
 
 /// Entity linker for connecting mentions to entities
 ///
@@ -89,56 +92,40 @@ impl EntityLinker {
         mention: &str,
         context: &str,
     ) -> Result<Entity> {
-        // Normalize the mention
-        let canonical_name = self.normalize_mention(mention);
+        // Infer the type up front so type-specific canonicalization applies
+        let etype = self.infer_entity_type(context);
+        let canonical_name = self.normalize_mention(mention, etype.clone());
 
         // Try to find exact match first
         if let Some(entity) = self.find_exact_match(conn, uid, &canonical_name)? {
-            return self.update_entity(conn, entity);
+            return self.update_entity(conn, entity, mention);
         }
 
         // Try fuzzy match
         if let Some(entity) = self.find_fuzzy_match(conn, uid, &canonical_name, context)? {
-            return self.update_entity(conn, entity);
+            return self.update_entity(conn, entity, mention);
         }
 
         // No match found - create new entity
-        self.create_entity(conn, uid, &canonical_name, context)
+        self.create_entity(conn, uid, &canonical_name, etype, mention)
     }
 
     /// Normalize entity mention to canonical form
     ///
     /// Handles:
-    /// - Whitespace trimming
-    /// - Case normalization for English
+    /// - Whitespace, width, case and honorific rules (`canonicalize_name`)
     /// - Common alias mapping (e.g., "苹果" → "Apple")
-    fn normalize_mention(&self, mention: &str) -> String {
-        let trimmed = mention.trim();
+    fn normalize_mention(&self, mention: &str, entity_type: EntityType) -> String {
+        let canonical = canonicalize_name(mention, entity_type);
 
         // Common Chinese-English alias mapping
         // In production, this could be a more comprehensive dictionary
-        match trimmed.to_lowercase().as_str() {
+        match canonical.to_lowercase().as_str() {
             "苹果" | "apple inc" | "apple computer" => "Apple".to_string(),
             "谷歌" | "google" => "Google".to_string(),
             "微软" | "microsoft" => "Microsoft".to_string(),
             "特斯拉" | "tesla" => "Tesla".to_string(),
-            _ => {
-                // Basic normalization: lowercase English, keep Chinese as-is
-                if trimmed.chars().all(|c| c.is_ascii()) {
-                    // All ASCII - lowercase and capitalize first letter
-                    let lower = trimmed.to_lowercase();
-                    if let Some(first_char) = lower.chars().next() {
-                        let mut result = first_char.to_uppercase().to_string();
-                        result.push_str(&lower[lower.len().min(1)..]);
-                        result
-                    } else {
-                        lower
-                    }
-                } else {
-                    // Contains non-ASCII (likely Chinese) - keep as-is
-                    trimmed.to_string()
-                }
-            }
+            _ => canonical,
         }
     }
 
@@ -378,8 +365,14 @@ impl EntityLinker {
         jaro_winkler
     }
 
-    /// Update existing entity (increment occurrence_count, update last_seen)
-    fn update_entity(&self, conn: &mut PgConnection, mut entity: Entity) -> Result<Entity> {
+    /// Update existing entity (increment occurrence_count, update last_seen,
+    /// record the mention's surface form)
+    fn update_entity(
+        &self,
+        conn: &mut PgConnection,
+        mut entity: Entity,
+        mention: &str,
+    ) -> Result<Entity> {
         use crate::schema::entities::dsl::*;
 
         let now = chrono::Utc::now();
@@ -387,50 +380,41 @@ impl EntityLinker {
         // Update in memory
         entity.occurrence_count += 1;
         entity.last_seen = now;
+        entity.attributes =
+            add_surface_form(entity.attributes.take(), mention, &entity.canonical_name);
 
         // Update in database
         diesel::update(entities.find(entity.entity_id))
             .set((
                 occurrence_count.eq(entity.occurrence_count),
                 last_seen.eq(entity.last_seen),
+                attributes.eq(&entity.attributes),
             ))
             .execute(conn)?;
 
         Ok(entity)
     }
 
-    /// Create new entity based on mention and context
+    /// Create new entity from the normalized name, keeping the mention as a
+    /// surface form
     fn create_entity(
         &self,
         conn: &mut PgConnection,
         uid: &str,
         cname: &str,
-        context: &str,
+        etype: EntityType,
+        mention: &str,
     ) -> Result<Entity> {
-        // Infer entity type from context
-        let etype = self.infer_entity_type(context);
-
         // Create new entity
         let new_entity = NewEntity::new(
             uid.to_string(),
             cname.to_string(),
             etype,
-        );
+        )
+        .with_surface_form(mention);
 
-        // Insert into database and query it back
-        diesel::insert_into(crate::schema::entities::table)
-            .values(&new_entity)
-            .execute(conn)?;
-
-        // Query the inserted entity (ordered by last_seen DESC to get the most recent)
-        use crate::schema::entities::dsl::*;
-        let inserted_entity = entities
-            .filter(user_id.eq(uid))
-            .filter(canonical_name.eq(cname))
-            .order(last_seen.desc())
-            .first::<Entity>(conn)?;
-
-        Ok(inserted_entity)
+        // A concurrent first mention of the same name folds into its row
+        upsert_entity(conn, &new_entity)
     }
 
     /// Infer entity type from context
@@ -484,6 +468,104 @@ impl EntityLinker {
     }
 }
 
+/// Insert an extracted entity, or record another mention of an existing one
+///
+/// An existing row matches on user, canonical name and entity type. A match
+/// has its `occurrence_count` incremented, `last_seen` advanced and
+/// attributes merged (see [`merge_entity_attributes`]); otherwise the entity
+/// is inserted as-is.
+///
+/// The key is claimed with `INSERT ... ON CONFLICT DO UPDATE`, so concurrent
+/// first mentions of the same name fold into one row instead of failing on
+/// the (user_id, canonical_name) constraint. A same-name entity of a
+/// different type cannot be stored alongside it and is rejected with a
+/// `Config` error.
+pub fn upsert_entity(conn: &mut PgConnection, new_entity: &NewEntity) -> Result<Entity> {
+    use crate::schema::entities::dsl::*;
+    use diesel::sql_types::{Float8, Int4, Jsonb, Nullable, Text, Timestamptz};
+
+    conn.transaction(|conn| {
+        // The conflicting row stays locked until commit, so the attribute
+        // merge below cannot interleave with another mention
+        let upserted = diesel::sql_query(
+            "INSERT INTO entities \
+                 (user_id, canonical_name, entity_type, attributes, first_seen, last_seen, \
+                  occurrence_count, confidence) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
+             ON CONFLICT (user_id, canonical_name) DO UPDATE SET \
+                 occurrence_count = entities.occurrence_count \
+                     + GREATEST(EXCLUDED.occurrence_count, 1), \
+                 last_seen = GREATEST(entities.last_seen, EXCLUDED.last_seen) \
+             WHERE entities.entity_type = EXCLUDED.entity_type \
+             RETURNING *",
+        )
+        .bind::<Text, _>(&new_entity.user_id)
+        .bind::<Text, _>(&new_entity.canonical_name)
+        .bind::<Text, _>(&new_entity.entity_type)
+        .bind::<Nullable<Jsonb>, _>(&new_entity.attributes)
+        .bind::<Timestamptz, _>(new_entity.first_seen)
+        .bind::<Timestamptz, _>(new_entity.last_seen)
+        .bind::<Int4, _>(new_entity.occurrence_count)
+        .bind::<Float8, _>(new_entity.confidence)
+        .get_result::<Entity>(conn)
+        .optional()?;
+
+        let Some(mut entity) = upserted else {
+            // The WHERE clause skipped the update: the name is taken by another type
+            let existing = entities
+                .filter(user_id.eq(&new_entity.user_id))
+                .filter(canonical_name.eq(&new_entity.canonical_name))
+                .first::<Entity>(conn)?;
+            return Err(DirSoulError::Config(format!(
+                "Entity '{}' already exists as {} (got {})",
+                existing.canonical_name, existing.entity_type, new_entity.entity_type
+            )));
+        };
+
+        // Merging is idempotent, so a freshly inserted row comes back unchanged
+        let merged =
+            merge_entity_attributes(entity.attributes.as_ref(), new_entity.attributes.as_ref());
+        if merged != entity.attributes {
+            diesel::update(entities.find(entity.entity_id))
+                .set(attributes.eq(&merged))
+                .execute(conn)?;
+            entity.attributes = merged;
+        }
+        Ok(entity)
+    })
+}
+
+/// Merge entity attributes, letting newly extracted keys win
+///
+/// Two JSON objects are merged key by key; otherwise the incoming value
+/// replaces the existing one unless it is absent. Surface forms accumulate
+/// instead of being replaced.
+pub fn merge_entity_attributes(
+    existing: Option<&serde_json::Value>,
+    incoming: Option<&serde_json::Value>,
+) -> Option<serde_json::Value> {
+    match (existing, incoming) {
+        (Some(serde_json::Value::Object(old)), Some(serde_json::Value::Object(new))) => {
+            let mut merged = old.clone();
+            for (key, value) in new {
+                if key == SURFACE_FORMS_KEY {
+                    continue;
+                }
+                merged.insert(key.clone(), value.clone());
+            }
+
+            let mut merged = Some(serde_json::Value::Object(merged));
+            let incoming_forms = new.get(SURFACE_FORMS_KEY).and_then(|forms| forms.as_array());
+            for form in incoming_forms.into_iter().flatten().filter_map(|form| form.as_str()) {
+                merged = add_surface_form(merged, form, "");
+            }
+            merged
+        }
+        (existing, None) => existing.cloned(),
+        (_, Some(incoming)) => Some(incoming.clone()),
+    }
+}
+
 impl Default for EntityLinker {
     fn default() -> Self {
         Self::new()
@@ -497,13 +579,13 @@ mod tests {
     #[test]
     fn test_normalize_mention_chinese() {
         let linker = EntityLinker::new();
-        assert_eq!(linker.normalize_mention("  苹果  "), "Apple");
+        assert_eq!(linker.normalize_mention("  苹果  ", EntityType::Object), "Apple");
     }
 
     #[test]
     fn test_normalize_mention_english() {
         let linker = EntityLinker::new();
-        assert_eq!(linker.normalize_mention("  apple  "), "Apple");
+        assert_eq!(linker.normalize_mention("  apple  ", EntityType::Object), "Apple");
     }
 
     #[test]
@@ -584,4 +666,45 @@ mod tests {
             EntityType::Concept
         );
     }
+
+    #[test]
+    fn test_merge_entity_attributes_is_idempotent() {
+        // `upsert_entity` merges into a freshly inserted row too
+        let mention = NewEntity::new("user123".to_string(), "苹果".to_string(), EntityType::Object)
+            .with_surface_form("apple")
+            .with_attributes(serde_json::json!({"color": "red"}));
+        let inserted = mention.attributes.clone();
+
+        assert_eq!(
+            merge_entity_attributes(inserted.as_ref(), mention.attributes.as_ref()),
+            inserted
+        );
+    }
+
+    #[test]
+    fn test_normalize_mention_person() {
+        let linker = EntityLinker::new();
+        assert_eq!(linker.normalize_mention(" 王先生 ", EntityType::Person), "王");
+        assert_eq!(linker.normalize_mention("ＧＯＯＧＬＥ", EntityType::Organization), "Google");
+    }
+
+    #[test]
+    fn test_merge_entity_attributes_accumulates_surface_forms() {
+        let existing = serde_json::json!({"color": "red", "surface_forms": ["苹果"]});
+        let incoming = serde_json::json!({"color": "green", "surface_forms": ["apple", "苹果"]});
+        assert_eq!(
+            merge_entity_attributes(Some(&existing), Some(&incoming)),
+            Some(serde_json::json!({"color": "green", "surface_forms": ["苹果", "apple"]}))
+        );
+    }
+
+    #[test]
+    fn test_merge_entity_attributes_keeps_existing_when_absent() {
+        let existing = serde_json::json!({"color": "red"});
+        assert_eq!(merge_entity_attributes(Some(&existing), None), Some(existing.clone()));
+        assert_eq!(
+            merge_entity_attributes(None, Some(&existing)),
+            Some(existing)
+        );
+    }
 }
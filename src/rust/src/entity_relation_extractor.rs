@@ -11,15 +11,76 @@
 //! - `extract_relations()`: Extract relations from events
 //! - `update_relation_strength()`: Calculate strength based on co-occurrence
 //! - `find_related_entities()`: Graph query for finding connected entities
+//! - `find_path()`: Bounded BFS between two entities
 
 use diesel::prelude::*;
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
 use crate::error::{DirSoulError, Result};
+use crate::llm_provider::{
+    parse_json_array_lenient, GenerateOptions, LLMProvider, LlmGovernor, OllamaProvider,
+};
 use crate::models::{Entity, EntityRelation, NewEntityRelation};
+use crate::prompt_manager::{PromptManager, PromptTask};
+
+/// Environment variable overriding `RelationExtractorConfig::strength_saturation`
+pub const STRENGTH_SATURATION_ENV: &str = "DIRSOUL_RELATION_STRENGTH_K";
+
+/// Stored strengths within this distance of the recomputed value are left
+/// alone by `sync_count_strengths`
+const STRENGTH_SYNC_TOLERANCE: f64 = 1e-9;
+
+/// Name of the external prompt template (`prompts/relation_extraction.txt`)
+const RELATION_PROMPT_NAME: &str = "relation_extraction";
+
+/// Built-in relation extraction template, used when no external template is available
+///
+/// Variables: `{{text}}`, `{{entities}}`, `{{relation_types}}`, `{{examples}}`
+const DEFAULT_RELATION_PROMPT: &str = r#"你是 DirSoul 实体关系抽取系统。从文本中提取实体之间的关系。
+
+文本：{{text}}
+
+实体列表：
+{{entities}}
+
+请分析这些实体之间的关系，输出 JSON 数组格式。每个关系包含：
+- source: 源实体名称（必须从上面列表中选择）
+- target: 目标实体名称（必须从上面列表中选择）
+- relation_type: 关系类型（{{relation_types}}）
+- confidence: 置信度（0-1之间的浮点数）
+
+只返回最确定的关系，不要过度推断。如果没有明确关系，返回空数组。
+
+输出格式示例：
+{{examples}}
+
+请只输出 JSON 数组，不要其他内容："#;
+
+/// Built-in template for batched extraction over several event segments
+///
+/// Variables: `{{segments}}`, `{{relation_types}}`, `{{examples}}`
+const DEFAULT_BATCH_RELATION_PROMPT: &str = r#"你是 DirSoul 实体关系抽取系统。下面有多个相互独立的文本片段，请分别提取每个片段内实体之间的关系。
+
+{{segments}}
+
+对每个片段输出一个对象：
+- segment: 片段编号
+- relations: 关系数组，每个关系包含 source、target（必须来自该片段的实体列表）、relation_type（{{relation_types}}）、confidence（0-1之间的浮点数）
+
+不要跨片段建立关系。没有明确关系的片段输出空数组。
+
+单个关系的格式示例：
+{{examples}}
+
+输出格式：[{"segment": 1, "relations": [...]}, {"segment": 2, "relations": []}]
+
+请只输出 JSON 数组，不要其他内容："#;
+
+/// Header that opens each segment in the batched prompt
+const SEGMENT_HEADER: &str = "### 片段";
 
 /// Relation type enumeration
 ///
@@ -112,6 +173,192 @@ pub struct ExtractedRelation {
     pub confidence: f64,
 }
 
+/// One event's text and entities, extracted together with others in a batch
+#[derive(Debug, Clone)]
+pub struct RelationSegment {
+    /// Event text
+    pub text: String,
+    /// Entities mentioned in the text
+    pub entities: Vec<Entity>,
+}
+
+/// Few-shot example rendered into the SLM relation prompt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelationExample {
+    pub source: String,
+    pub target: String,
+    pub relation_type: String,
+    pub confidence: f64,
+}
+
+impl RelationExample {
+    /// Create a new few-shot example
+    pub fn new(
+        source: impl Into<String>,
+        target: impl Into<String>,
+        relation_type: impl Into<String>,
+        confidence: f64,
+    ) -> Self {
+        Self {
+            source: source.into(),
+            target: target.into(),
+            relation_type: relation_type.into(),
+            confidence,
+        }
+    }
+}
+
+/// Default relation types offered to the SLM
+fn default_relation_types() -> Vec<String> {
+    [
+        "belongs_to",
+        "related_to",
+        "located_at",
+        "works_at",
+        "friends_with",
+        "family_of",
+        "owns",
+        "created_by",
+        "part_of",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+/// Default few-shot examples for the SLM relation prompt
+fn default_few_shot_examples() -> Vec<RelationExample> {
+    vec![
+        RelationExample::new("苹果", "水果", "belongs_to", 0.9),
+        RelationExample::new("张三", "北京", "located_at", 0.8),
+    ]
+}
+
+/// How a rule's trigger word has to appear in the text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleMatch {
+    /// Source, trigger and target written back to back ("苹果属于水果")
+    Adjacent,
+    /// Source before the first trigger, target within 50 bytes after it
+    /// ("苹果是一种水果")
+    Loose,
+    /// Trigger anywhere in the text; every pair of entities is related.
+    /// Only used when `rule_co_occurrence_enabled` is set
+    CoOccurrence,
+}
+
+/// Rule-based extraction: a trigger word, the relation it implies and how
+/// confident that relation is
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelationRule {
+    /// Trigger word, e.g. "属于"
+    pub trigger: String,
+    /// Relation type name (custom names map to `RelationType::Custom`)
+    pub relation_type: String,
+    /// Confidence of relations produced by this rule (0-1)
+    pub confidence: f64,
+    /// How the trigger has to appear
+    pub match_kind: RuleMatch,
+}
+
+impl RelationRule {
+    /// Create a new rule
+    pub fn new(
+        trigger: impl Into<String>,
+        relation_type: impl Into<String>,
+        confidence: f64,
+        match_kind: RuleMatch,
+    ) -> Self {
+        Self {
+            trigger: trigger.into(),
+            relation_type: relation_type.into(),
+            confidence,
+            match_kind,
+        }
+    }
+}
+
+/// Default rule table for `extract_relations_rule_based`
+fn default_relation_rules() -> Vec<RelationRule> {
+    vec![
+        RelationRule::new("是", "belongs_to", 0.7, RuleMatch::Loose),
+        RelationRule::new("属于", "belongs_to", 0.9, RuleMatch::Adjacent),
+        RelationRule::new(" 位于 ", "located_at", 0.9, RuleMatch::Adjacent),
+        RelationRule::new("位于", "located_at", 0.85, RuleMatch::Adjacent),
+        // Commercial activity; only applied with `rule_co_occurrence_enabled`
+        RelationRule::new("买", "related_to", 0.5, RuleMatch::CoOccurrence),
+        RelationRule::new("卖", "related_to", 0.5, RuleMatch::CoOccurrence),
+    ]
+}
+
+/// Longest gap (bytes) between a `Loose` trigger and its target
+const LOOSE_RULE_WINDOW_BYTES: usize = 50;
+
+/// How co-occurrence decides that an event mentions an entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CoOccurrenceMode {
+    /// The entity name is a substring of the event target (legacy behavior)
+    #[default]
+    Substring,
+    /// The event has a row in `event_entity_links` for the entity; events
+    /// without any links fall back to substring matching
+    Linked,
+}
+
+/// How `save_relations` folds a new observation into a relation's confidence
+///
+/// Blending only looks at the stored confidence and `observation_count`;
+/// `strength` follows the count alone and `co_occurrence` is maintained by
+/// `recompute_all_strengths`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfidenceBlending {
+    /// Mean over all observations, `(c * n + x) / (n + 1)`: every
+    /// observation counts equally, so late evidence moves a long-lived
+    /// relation less and less
+    #[default]
+    RunningMean,
+    /// Exponential moving average, `c + alpha * (x - c)`: the newest
+    /// observation always carries weight `alpha` (0 < alpha <= 1)
+    Ema { alpha: f64 },
+}
+
+impl ConfidenceBlending {
+    /// Confidence after observing `observed` once more
+    ///
+    /// `current` has been seen `observations` times so far (at least 1).
+    pub fn blend(self, current: f64, observations: i32, observed: f64) -> f64 {
+        let blended = match self {
+            ConfidenceBlending::RunningMean => {
+                let n = f64::from(observations.max(1));
+                (current * n + observed) / (n + 1.0)
+            }
+            ConfidenceBlending::Ema { alpha } => current + alpha * (observed - current),
+        };
+        blended.clamp(0.0, 1.0)
+    }
+}
+
+/// An event as seen by co-occurrence counting
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoOccurrenceEvent {
+    /// Lowercased event target
+    pub target: String,
+    /// Linked entity ids, `None` when the event has no links
+    pub entity_ids: Option<HashSet<Uuid>>,
+}
+
+impl CoOccurrenceEvent {
+    /// Whether this event mentions the entity
+    fn mentions(&self, entity_id: Uuid, name: &str) -> bool {
+        match &self.entity_ids {
+            Some(ids) => ids.contains(&entity_id),
+            None => self.target.contains(name),
+        }
+    }
+}
+
 /// Entity relation extractor configuration
 #[derive(Debug, Clone)]
 pub struct RelationExtractorConfig {
@@ -123,8 +370,131 @@ pub struct RelationExtractorConfig {
     pub timeout_secs: u64,
     /// Co-occurrence window for strength calculation (hours)
     pub co_occurrence_window_hours: i64,
-    /// Minimum strength threshold for keeping relations
+    /// Whether co-occurrence uses event-entity links or target substrings
+    pub co_occurrence_mode: CoOccurrenceMode,
+    /// Minimum strength for graph queries, and the co-occurrence below which
+    /// `recompute_all_strengths` prunes a relation when pruning is enabled
     pub min_strength_threshold: f64,
+    /// Let `recompute_all_strengths` delete relations with weak co-occurrence
+    pub prune_weak_relations: bool,
+    /// In-window events mentioning either entity needed before a relation
+    /// may be pruned
+    pub prune_min_samples: usize,
+    /// `k` in the count-based strength `count / (count + k)`: the
+    /// observation count at which a relation reaches strength 0.5
+    pub strength_saturation: f64,
+    /// How repeated observations update relation confidence
+    pub confidence_blending: ConfidenceBlending,
+    /// Rolling window (days) used by `recompute_all_strengths`
+    pub strength_recompute_window_days: i64,
+    /// Relation types listed in the SLM prompt (custom names map to `RelationType::Custom`)
+    pub relation_types: Vec<String>,
+    /// Few-shot examples rendered into the SLM prompt
+    pub few_shot_examples: Vec<RelationExample>,
+    /// Estimated token budget for the segments of one batched prompt
+    pub batch_max_prompt_tokens: usize,
+    /// Maximum batched SLM requests in flight
+    pub batch_concurrency: usize,
+    /// Trigger words used by `extract_relations_rule_based`
+    pub relation_rules: Vec<RelationRule>,
+    /// Apply `RuleMatch::CoOccurrence` rules; off by default since they relate
+    /// every pair of entities in the text
+    pub rule_co_occurrence_enabled: bool,
+    /// Most entities `find_path` discovers before giving up
+    pub path_max_visited_nodes: usize,
+}
+
+impl RelationExtractorConfig {
+    /// Defaults with `strength_saturation` read from `DIRSOUL_RELATION_STRENGTH_K`
+    ///
+    /// An unset variable keeps the default k; a value that is not a positive
+    /// number is a `DirSoulError::Config`.
+    pub fn from_env() -> Result<Self> {
+        Self::from_values(std::env::var(STRENGTH_SATURATION_ENV).ok().as_deref())
+    }
+
+    fn from_values(strength_saturation: Option<&str>) -> Result<Self> {
+        let mut config = Self::default();
+        if let Some(value) = strength_saturation.map(str::trim).filter(|v| !v.is_empty()) {
+            config.strength_saturation = value.parse().map_err(|_| {
+                DirSoulError::Config(format!("{} must be a number, got {}", STRENGTH_SATURATION_ENV, value))
+            })?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parse the configured relation type names into `RelationType`s
+    pub fn parsed_relation_types(&self) -> Vec<RelationType> {
+        self.relation_types.iter().map(|s| RelationType::from_str(s)).collect()
+    }
+
+    /// Strength in [0, 1) for a relation observed `observations` times
+    ///
+    /// Saturates toward 1.0 as the count grows and depends on nothing but the
+    /// count, so strengths of different entity pairs are comparable.
+    pub fn count_strength(&self, observations: i32) -> f64 {
+        let count = f64::from(observations.max(0));
+        count / (count + self.strength_saturation)
+    }
+
+    /// Validate the prompt-related configuration
+    ///
+    /// Relation type names must be non-empty, and every few-shot example must
+    /// use one of the configured relation types.
+    pub fn validate(&self) -> Result<()> {
+        if self.relation_types.iter().any(|t| t.trim().is_empty()) {
+            return Err(DirSoulError::Config("Relation type names must not be empty".to_string()));
+        }
+        if self.batch_concurrency == 0 {
+            return Err(DirSoulError::Config("batch_concurrency must be at least 1".to_string()));
+        }
+        if self.path_max_visited_nodes == 0 {
+            return Err(DirSoulError::Config("path_max_visited_nodes must be at least 1".to_string()));
+        }
+        if self.prune_min_samples == 0 {
+            return Err(DirSoulError::Config("prune_min_samples must be at least 1".to_string()));
+        }
+        if !(self.strength_saturation.is_finite() && self.strength_saturation > 0.0) {
+            return Err(DirSoulError::Config(format!(
+                "strength_saturation must be a positive number, got {}",
+                self.strength_saturation
+            )));
+        }
+        if let ConfidenceBlending::Ema { alpha } = self.confidence_blending {
+            if !(alpha > 0.0 && alpha <= 1.0) {
+                return Err(DirSoulError::Config(format!(
+                    "EMA alpha must be in (0, 1], got {}",
+                    alpha
+                )));
+            }
+        }
+        for rule in &self.relation_rules {
+            if rule.trigger.trim().is_empty() || rule.relation_type.trim().is_empty() {
+                return Err(DirSoulError::Config(
+                    "Relation rules need a trigger and a relation type".to_string(),
+                ));
+            }
+            if !(0.0..=1.0).contains(&rule.confidence) {
+                return Err(DirSoulError::Config(format!(
+                    "Relation rule '{}' has confidence {} outside 0-1",
+                    rule.trigger, rule.confidence
+                )));
+            }
+        }
+
+        let allowed = self.parsed_relation_types();
+        for example in &self.few_shot_examples {
+            if !allowed.contains(&RelationType::from_str(&example.relation_type)) {
+                return Err(DirSoulError::Config(format!(
+                    "Few-shot example uses unknown relation type: {}",
+                    example.relation_type
+                )));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for RelationExtractorConfig {
@@ -134,9 +504,117 @@ impl Default for RelationExtractorConfig {
             model: "phi4-mini".to_string(),
             timeout_secs: 30,
             co_occurrence_window_hours: 24, // 24 hour window
+            co_occurrence_mode: CoOccurrenceMode::Substring,
             min_strength_threshold: 0.1,
+            prune_weak_relations: false,
+            prune_min_samples: 5,
+            strength_saturation: 3.0,
+            confidence_blending: ConfidenceBlending::RunningMean,
+            strength_recompute_window_days: 30,
+            relation_types: default_relation_types(),
+            few_shot_examples: default_few_shot_examples(),
+            batch_max_prompt_tokens: 1500,
+            batch_concurrency: 2,
+            relation_rules: default_relation_rules(),
+            rule_co_occurrence_enabled: false,
+            path_max_visited_nodes: 10_000,
+        }
+    }
+}
+
+/// Outcome of a strength recomputation run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrengthRecomputeSummary {
+    /// Relations whose co-occurrence and strength were rewritten
+    pub relations_updated: usize,
+    /// Relations removed for co-occurrence below `min_strength_threshold`
+    pub relations_pruned: usize,
+    /// Relations with no mentions in the window, left as they were
+    pub relations_unchanged: usize,
+}
+
+/// What `recompute_all_strengths` does with one relation
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StrengthRecompute {
+    /// No in-window evidence: keep the stored co-occurrence
+    Keep,
+    /// Store the recomputed co-occurrence
+    Update(f64),
+    /// Delete the relation
+    Prune,
+}
+
+/// Outcome of `EntityRelationExtractor::find_path`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathSearch {
+    /// Entity ids from start to end, inclusive
+    Found(Vec<Uuid>),
+    /// No path within `max_depth`
+    NoPath,
+    /// Stopped after reaching `visited` entities; a path may still exist
+    Exhausted { visited: usize },
+}
+
+/// Breadth-first search from `start` to `end`
+///
+/// `neighbors` returns the entities adjacent to one entity. At most
+/// `max_visited` entities (including `start`) are discovered before the
+/// search gives up with `PathSearch::Exhausted`.
+fn shortest_path<F>(
+    start: Uuid,
+    end: Uuid,
+    max_depth: usize,
+    max_visited: usize,
+    mut neighbors: F,
+) -> Result<PathSearch>
+where
+    F: FnMut(Uuid) -> Result<Vec<Uuid>>,
+{
+    use std::collections::VecDeque;
+
+    if start == end {
+        return Ok(PathSearch::Found(vec![start]));
+    }
+
+    let mut queue = VecDeque::new();
+    let mut parents: HashMap<Uuid, Option<Uuid>> = HashMap::new();
+
+    queue.push_back((start, 0));
+    parents.insert(start, None);
+
+    while let Some((current, depth)) = queue.pop_front() {
+        if depth >= max_depth {
+            continue;
+        }
+
+        for next in neighbors(current)? {
+            if parents.contains_key(&next) {
+                continue;
+            }
+            if next != end && parents.len() >= max_visited {
+                return Ok(PathSearch::Exhausted { visited: parents.len() });
+            }
+            parents.insert(next, Some(current));
+
+            if next == end {
+                // Walk parent links back to the start
+                let mut path = vec![end];
+                let mut node = current;
+                path.push(node);
+                while let Some(Some(parent)) = parents.get(&node) {
+                    path.push(*parent);
+                    node = *parent;
+                }
+                path.reverse();
+                return Ok(PathSearch::Found(path));
+            }
+            queue.push_back((next, depth + 1));
         }
     }
+
+    // No path found
+    Ok(PathSearch::NoPath)
 }
 
 /// Entity relation extractor
@@ -144,7 +622,12 @@ impl Default for RelationExtractorConfig {
 /// Handles extraction of relationships between entities from events.
 pub struct EntityRelationExtractor {
     config: RelationExtractorConfig,
-    http_client: Client,
+    /// Backend for SLM extraction (Ollama at `ollama_url` by default)
+    provider: Arc<dyn LLMProvider>,
+    /// Optional external prompt templates (falls back to the built-in prompt)
+    prompt_manager: Option<Arc<Mutex<PromptManager>>>,
+    /// Shared cap on in-flight LLM calls, if any
+    governor: Option<LlmGovernor>,
 }
 
 impl EntityRelationExtractor {
@@ -155,19 +638,103 @@ impl EntityRelationExtractor {
 
     /// Create a new relation extractor with custom config
     pub fn with_config(config: RelationExtractorConfig) -> Self {
-        let timeout = std::time::Duration::from_secs(config.timeout_secs);
-        let http_client = Client::builder()
-            .timeout(timeout)
-            .build()
-            .unwrap_or_else(|_| Client::new());
+        let provider = OllamaProvider::new(config.ollama_url.clone(), config.model.clone())
+            .with_timeout(std::time::Duration::from_secs(config.timeout_secs));
+
+        Self {
+            config,
+            provider: Arc::new(provider),
+            prompt_manager: None,
+            governor: None,
+        }
+    }
+
+    /// Run SLM extraction through another provider instead of Ollama
+    pub fn with_provider(mut self, provider: Arc<dyn LLMProvider>) -> Self {
+        self.provider = provider;
+        self
+    }
+
+    /// Count SLM requests against a shared `LlmGovernor`
+    ///
+    /// Each batched request in flight holds its own permit, so relation
+    /// extraction shares the cap with chat and embedding.
+    pub fn with_governor(mut self, governor: LlmGovernor) -> Self {
+        self.governor = Some(governor);
+        self
+    }
+
+    /// Load the relation prompt from `relation_extraction.txt` via a PromptManager
+    ///
+    /// The manager's relation extraction system prompt is prepended to
+    /// both the loaded and the built-in prompt.
+    pub fn with_prompt_manager(mut self, prompt_manager: PromptManager) -> Self {
+        self.prompt_manager = Some(Arc::new(Mutex::new(prompt_manager)));
+        self
+    }
+
+    /// Render few-shot examples as a JSON array, one example per line
+    fn render_examples(&self) -> String {
+        let lines: Vec<String> = self
+            .config
+            .few_shot_examples
+            .iter()
+            .map(|ex| {
+                format!(
+                    "  {{\"source\": {}, \"target\": {}, \"relation_type\": {}, \"confidence\": {}}}",
+                    serde_json::Value::String(ex.source.clone()),
+                    serde_json::Value::String(ex.target.clone()),
+                    serde_json::Value::String(ex.relation_type.clone()),
+                    ex.confidence
+                )
+            })
+            .collect();
+
+        format!("[\n{}\n]", lines.join(",\n"))
+    }
+
+    /// Build the SLM relation extraction prompt
+    fn build_relation_prompt(&self, text: &str, entities: &[Entity]) -> String {
+        let entity_list: String = entities
+            .iter()
+            .enumerate()
+            .map(|(i, e)| format!("{}. {}", i + 1, e.canonical_name))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let relation_types = self.config.relation_types.join("/");
+        let examples = self.render_examples();
+
+        let mut vars = HashMap::new();
+        vars.insert("entities", entity_list.as_str());
+        vars.insert("relation_types", relation_types.as_str());
+        vars.insert("examples", examples.as_str());
+        // Substitute user text last so its content is never treated as a placeholder
+        vars.insert("text", text);
+
+        let mut manager = self.prompt_manager.as_ref().and_then(|m| m.lock().ok());
+        let rendered = manager
+            .as_mut()
+            .and_then(|manager| manager.render_prompt(RELATION_PROMPT_NAME, vars.clone()).ok());
+        let prompt = rendered.unwrap_or_else(|| {
+            let mut prompt = DEFAULT_RELATION_PROMPT.to_string();
+            for key in ["entities", "relation_types", "examples", "text"] {
+                prompt = prompt.replace(&format!("{{{{{}}}}}", key), vars[key]);
+            }
+            prompt
+        });
 
-        Self { config, http_client }
+        match manager {
+            Some(manager) => manager.compose_prompt(PromptTask::RelationExtraction, &prompt),
+            None => prompt,
+        }
     }
 
     /// Extract relations from event text using rule-based approach
     ///
     /// This is the fallback method when SLM is unavailable.
-    /// Uses patterns like "X 是 Y" (X is Y), "X 属于 Y" (X belongs to Y).
+    /// Applies `config.relation_rules`, e.g. "X 是 Y" (X is Y) and "X属于Y"
+    /// (X belongs to Y). Co-occurrence rules, which relate every pair of
+    /// entities, only run when `rule_co_occurrence_enabled` is set.
     ///
     /// # Arguments
     /// * `text` - The event text to analyze
@@ -184,40 +751,24 @@ impl EntityRelationExtractor {
 
         let entity_names: Vec<&str> = entities.iter().map(|e| e.canonical_name.as_str()).collect();
 
-        // Pattern 1: "X 是 Y" or "X 是 ... Y" - often indicates classification
-        // This handles "苹果是一种水果" where there are words between "是" and the target
+        // Pattern 1: explicit patterns between a source and a target
         for (i, source) in entity_names.iter().enumerate() {
             for target in entity_names.iter().skip(i + 1) {
-                // Check for "source ... 是 ... target" pattern (source before 是, target after 是)
-                if let Some(is_pos) = text.find("是") {
-                    let before_is = &text[..is_pos.min(text.len())];
-                    let after_is = &text[is_pos + 3..text.len().min(is_pos + 50)]; // +3 for "是" utf8
-
-                    // Check if source appears before "是" and target appears after "是"
-                    if before_is.contains(source) && after_is.contains(target) {
-                        relations.push(ExtractedRelation {
-                            source: source.to_string(),
-                            target: target.to_string(),
-                            relation_type: RelationType::BelongsTo,
-                            confidence: 0.7,
-                        });
-                    }
-                }
-
-                // Check for exact patterns (more specific, higher confidence)
-                let patterns = [
-                    (format!("{}属于{}", source, target), RelationType::BelongsTo, 0.9),
-                    (format!("{} 位于 {}", source, target), RelationType::LocatedAt, 0.9),
-                    (format!("{}位于{}", source, target), RelationType::LocatedAt, 0.85),
-                ];
+                for rule in &self.config.relation_rules {
+                    let matched = match rule.match_kind {
+                        RuleMatch::Adjacent => {
+                            text.contains(&format!("{}{}{}", source, rule.trigger, target))
+                        }
+                        RuleMatch::Loose => Self::loose_match(text, source, &rule.trigger, target),
+                        RuleMatch::CoOccurrence => false,
+                    };
 
-                for (pattern, rel_type, conf) in patterns {
-                    if text.contains(&pattern) {
+                    if matched {
                         relations.push(ExtractedRelation {
                             source: source.to_string(),
                             target: target.to_string(),
-                            relation_type: rel_type,
-                            confidence: conf,
+                            relation_type: RelationType::from_str(&rule.relation_type),
+                            confidence: rule.confidence,
                         });
                     }
                 }
@@ -226,16 +777,28 @@ impl EntityRelationExtractor {
 
         // Pattern 2: Context-based inference
         // If entities appear together in an action context, they're related
-        if entity_names.len() >= 2 {
-            if text.contains("买") || text.contains("卖") {
-                // Commercial activity -> ownership relation
+        if self.config.rule_co_occurrence_enabled && entity_names.len() >= 2 {
+            let mut co_occurrence: Vec<(RelationType, f64)> = Vec::new();
+            for rule in &self.config.relation_rules {
+                if rule.match_kind != RuleMatch::CoOccurrence || !text.contains(&rule.trigger) {
+                    continue;
+                }
+                // Several triggers for one type relate each pair once, at the highest confidence
+                let rel_type = RelationType::from_str(&rule.relation_type);
+                match co_occurrence.iter_mut().find(|(t, _)| *t == rel_type) {
+                    Some((_, conf)) => *conf = conf.max(rule.confidence),
+                    None => co_occurrence.push((rel_type, rule.confidence)),
+                }
+            }
+
+            for (rel_type, conf) in co_occurrence {
                 for (i, source) in entity_names.iter().enumerate() {
                     for target in entity_names.iter().skip(i + 1) {
                         relations.push(ExtractedRelation {
                             source: source.to_string(),
                             target: target.to_string(),
-                            relation_type: RelationType::RelatedTo,
-                            confidence: 0.5,
+                            relation_type: rel_type.clone(),
+                            confidence: conf,
                         });
                     }
                 }
@@ -245,6 +808,21 @@ impl EntityRelationExtractor {
         relations
     }
 
+    /// Whether `source` precedes the first `trigger` and `target` follows it
+    /// within `LOOSE_RULE_WINDOW_BYTES`
+    fn loose_match(text: &str, source: &str, trigger: &str, target: &str) -> bool {
+        let Some(pos) = text.find(trigger) else {
+            return false;
+        };
+        let after_start = pos + trigger.len();
+        let mut after_end = text.len().min(after_start + LOOSE_RULE_WINDOW_BYTES);
+        while !text.is_char_boundary(after_end) {
+            after_end -= 1;
+        }
+
+        text[..pos].contains(source) && text[after_start..after_end].contains(target)
+    }
+
     /// Extract relations using SLM (Phi-4-mini via Ollama)
     ///
     /// # Arguments
@@ -262,98 +840,184 @@ impl EntityRelationExtractor {
             return Ok(Vec::new());
         }
 
-        let entity_list: String = entities
+        let prompt = self.build_relation_prompt(text, entities);
+        let response_text = self.generate(&prompt, 500).await?;
+
+        // Parse JSON array from response (tolerates fences and surrounding prose)
+        Ok(parse_json_array_lenient(&response_text)
             .iter()
-            .enumerate()
-            .map(|(i, e)| format!("{}. {}", i + 1, e.canonical_name))
-            .collect::<Vec<_>>()
-            .join("\n");
+            .filter_map(Self::parse_relation)
+            .collect())
+    }
 
-        let prompt = format!(
-            r#"你是 DirSoul 实体关系抽取系统。从文本中提取实体之间的关系。
+    /// Extract relations for many events with as few SLM calls as possible
+    ///
+    /// Segments are packed into prompts up to `batch_max_prompt_tokens`, and at
+    /// most `batch_concurrency` prompts are in flight. The result has one entry
+    /// per input segment, in order. Segments with fewer than two entities are
+    /// not sent; malformed output for a segment leaves its entry empty, and
+    /// relations naming entities outside their segment are dropped.
+    pub async fn extract_relations_slm_batch(
+        &self,
+        segments: &[RelationSegment],
+    ) -> Result<Vec<Vec<ExtractedRelation>>> {
+        use futures_util::stream::{self, StreamExt};
+
+        let mut results: Vec<Vec<ExtractedRelation>> = vec![Vec::new(); segments.len()];
+        let batches = self.plan_batches(segments);
+
+        let mut pending = stream::iter(batches)
+            .map(|batch| async move {
+                let prompt = self.build_batch_prompt(segments, &batch);
+                let response = self.generate(&prompt, 500 * batch.len()).await;
+                (batch, response)
+            })
+            .buffer_unordered(self.config.batch_concurrency.max(1));
+
+        while let Some((batch, response)) = pending.next().await {
+            for (index, relations) in Self::parse_batch_response(segments, &batch, &response?) {
+                results[index] = relations;
+            }
+        }
 
-文本：{}
+        Ok(results)
+    }
 
-实体列表：
-{}
+    /// Group extractable segment indices into batches within the token budget
+    ///
+    /// A segment larger than the budget on its own still gets a batch.
+    fn plan_batches(&self, segments: &[RelationSegment]) -> Vec<Vec<usize>> {
+        let mut batches = Vec::new();
+        let mut current = Vec::new();
+        let mut current_tokens = 0;
+
+        for (index, segment) in segments.iter().enumerate() {
+            if segment.entities.len() < 2 {
+                continue;
+            }
 
-请分析这些实体之间的关系，输出 JSON 数组格式。每个关系包含：
-- source: 源实体名称（必须从上面列表中选择）
-- target: 目标实体名称（必须从上面列表中选择）
-- relation_type: 关系类型（belongs_to/related_to/located_at/works_at/friends_with/family_of/owns/created_by/part_of）
-- confidence: 置信度（0-1之间的浮点数）
+            let tokens = crate::http_api::estimate_tokens(&Self::render_segment(1, segment));
+            if !current.is_empty() && current_tokens + tokens > self.config.batch_max_prompt_tokens {
+                batches.push(std::mem::take(&mut current));
+                current_tokens = 0;
+            }
+            current.push(index);
+            current_tokens += tokens;
+        }
 
-只返回最确定的关系，不要过度推断。如果没有明确关系，返回空数组。
+        if !current.is_empty() {
+            batches.push(current);
+        }
+        batches
+    }
 
-输出格式示例：
-[
-  {{"source": "苹果", "target": "水果", "relation_type": "belongs_to", "confidence": 0.9}},
-  {{"source": "张三", "target": "北京", "relation_type": "located_at", "confidence": 0.8}}
-]
+    /// Render one numbered segment of the batched prompt
+    fn render_segment(number: usize, segment: &RelationSegment) -> String {
+        let entity_list: String = segment
+            .entities
+            .iter()
+            .enumerate()
+            .map(|(i, e)| format!("{}. {}", i + 1, e.canonical_name))
+            .collect::<Vec<_>>()
+            .join("\n");
 
-请只输出 JSON 数组，不要其他内容："#,
-            text, entity_list
-        );
+        format!(
+            "{} {}\n文本：{}\n实体列表：\n{}\n",
+            SEGMENT_HEADER, number, segment.text, entity_list
+        )
+    }
 
-        let response = self
-            .http_client
-            .post(format!("{}/api/generate", self.config.ollama_url))
-            .json(&serde_json::json!({
-                "model": self.config.model,
-                "prompt": prompt,
-                "stream": false,
-                "options": {
-                    "temperature": 0.3,
-                    "num_predict": 500
-                }
-            }))
-            .send()
-            .await
-            .map_err(|e| DirSoulError::ExternalError(format!("Ollama request failed: {}", e)))?;
+    /// Build the batched prompt; segments are numbered from 1 within the batch
+    fn build_batch_prompt(&self, segments: &[RelationSegment], batch: &[usize]) -> String {
+        let rendered: String = batch
+            .iter()
+            .enumerate()
+            .map(|(position, &index)| Self::render_segment(position + 1, &segments[index]))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let relation_types = self.config.relation_types.join("/");
+        let examples = self.render_examples();
+
+        // Segments go in last so event text is never treated as a placeholder
+        DEFAULT_BATCH_RELATION_PROMPT
+            .replace("{{relation_types}}", &relation_types)
+            .replace("{{examples}}", &examples)
+            .replace("{{segments}}", &rendered)
+    }
 
-        if !response.status().is_success() {
-            return Err(DirSoulError::ExternalError(format!(
-                "Ollama returned status: {}",
-                response.status()
-            )));
+    /// Map a batched response back to `(segment index, relations)` pairs
+    ///
+    /// Items with an unknown segment number or a non-array `relations` are
+    /// skipped, as are relations whose entities are not in their segment.
+    fn parse_batch_response(
+        segments: &[RelationSegment],
+        batch: &[usize],
+        response_text: &str,
+    ) -> Vec<(usize, Vec<ExtractedRelation>)> {
+        let mut parsed = Vec::new();
+
+        for item in parse_json_array_lenient(response_text) {
+            let index = match item
+                .get("segment")
+                .and_then(|v| v.as_u64())
+                .and_then(|n| (n as usize).checked_sub(1))
+                .and_then(|position| batch.get(position))
+            {
+                Some(&index) => index,
+                None => {
+                    tracing::debug!("Skipping relation output for unknown segment: {}", item);
+                    continue;
+                }
+            };
+            let Some(raw_relations) = item.get("relations").and_then(|v| v.as_array()) else {
+                tracing::debug!("Skipping malformed relations for segment {}", index);
+                continue;
+            };
+
+            let names: HashSet<&str> = segments[index]
+                .entities
+                .iter()
+                .map(|e| e.canonical_name.as_str())
+                .collect();
+            let relations = raw_relations
+                .iter()
+                .filter_map(Self::parse_relation)
+                .filter(|r| names.contains(r.source.as_str()) && names.contains(r.target.as_str()))
+                .collect();
+            parsed.push((index, relations));
         }
 
-        let json: serde_json::Value = response
-            .json::<serde_json::Value>()
-            .await
-            .map_err(|e| DirSoulError::ExternalError(format!("Failed to parse Ollama response: {}", e)))?;
-
-        let response_text = json["response"]
-            .as_str()
-            .ok_or_else(|| DirSoulError::ExternalError("No response text".to_string()))?;
-
-        // Parse JSON array from response
-        let parsed_relations: Vec<serde_json::Value> = serde_json::from_str(response_text)
-            .unwrap_or_else(|_| serde_json::from_str::<Vec<serde_json::Value>>("[]").unwrap());
+        parsed
+    }
 
-        let mut relations = Vec::new();
-        for rel in parsed_relations {
-            if let (Some(source), Some(target), Some(rel_type), Some(confidence)) = (
-                rel.get("source").and_then(|v| v.as_str()),
-                rel.get("target").and_then(|v| v.as_str()),
-                rel.get("relation_type").and_then(|v| v.as_str()),
-                rel.get("confidence").and_then(|v| v.as_f64()),
-            ) {
-                relations.push(ExtractedRelation {
-                    source: source.to_string(),
-                    target: target.to_string(),
-                    relation_type: RelationType::from_str(rel_type),
-                    confidence: confidence.clamp(0.0, 1.0),
-                });
-            }
-        }
+    /// Parse one relation object from SLM output
+    fn parse_relation(rel: &serde_json::Value) -> Option<ExtractedRelation> {
+        Some(ExtractedRelation {
+            source: rel.get("source")?.as_str()?.to_string(),
+            target: rel.get("target")?.as_str()?.to_string(),
+            relation_type: RelationType::from_str(rel.get("relation_type")?.as_str()?),
+            confidence: rel.get("confidence")?.as_f64()?.clamp(0.0, 1.0),
+        })
+    }
 
-        Ok(relations)
+    /// Send a prompt to the SLM and return the generated text
+    async fn generate(&self, prompt: &str, num_predict: usize) -> Result<String> {
+        let _permit = match &self.governor {
+            Some(governor) => Some(governor.acquire().await?),
+            None => None,
+        };
+        self.provider
+            .generate(prompt, GenerateOptions::new(0.3, num_predict as u32))
+            .await
     }
 
     /// Save relations to database
     ///
     /// Creates or updates relation records based on extracted relations.
+    /// A repeated observation bumps `observation_count` and blends
+    /// `conf_value` into the confidence per `confidence_blending`.
+    /// `strength` is always `count_strength(observation_count)`; the
+    /// co-occurrence is left to `recompute_all_strengths`.
     pub fn save_relations(
         &self,
         conn: &mut PgConnection,
@@ -380,16 +1044,20 @@ impl EntityRelationExtractor {
                 // Update existing relation
                 let now = chrono::Utc::now();
 
-                // Update confidence using weighted average
-                let new_confidence = (rel.confidence * rel.strength + conf_value) / (rel.strength + 1.0);
-                rel.strength += 1.0;
-                rel.confidence = new_confidence;
+                rel.confidence = self.config.confidence_blending.blend(
+                    rel.confidence,
+                    rel.observation_count,
+                    conf_value,
+                );
+                rel.observation_count += 1;
+                rel.strength = self.config.count_strength(rel.observation_count);
                 rel.last_seen = now;
 
                 diesel::update(entity_relations.find(rel.relation_id))
                     .set((
-                        strength.eq(rel.strength),
+                        observation_count.eq(rel.observation_count),
                         confidence.eq(rel.confidence),
+                        strength.eq(rel.strength),
                         last_seen.eq(rel.last_seen),
                     ))
                     .execute(conn)?;
@@ -405,7 +1073,7 @@ impl EntityRelationExtractor {
                     relation_type_str.clone(),
                 )
                 .with_confidence(conf_value)
-                .with_strength(1.0);
+                .with_strength(self.config.count_strength(1));
 
                 diesel::insert_into(entity_relations)
                     .values(&new_relation)
@@ -427,7 +1095,8 @@ impl EntityRelationExtractor {
 
     /// Calculate relation strength based on co-occurrence
     ///
-    /// Analyzes events to find how often entities appear together within a time window.
+    /// Analyzes events to find how often entities appear together within
+    /// `co_occurrence_window_hours`.
     pub fn calculate_co_occurrence_strength(
         &self,
         conn: &mut PgConnection,
@@ -435,21 +1104,27 @@ impl EntityRelationExtractor {
         entity_id_1: Uuid,
         entity_id_2: Uuid,
     ) -> Result<f64> {
-        use crate::schema::event_memories::dsl::*;
-        use crate::schema::entities::dsl as entities_dsl;
-
-        let window_start = chrono::Utc::now() - chrono::Duration::hours(self.config.co_occurrence_window_hours);
+        self.calculate_co_occurrence_strength_in_window(
+            conn,
+            uid,
+            entity_id_1,
+            entity_id_2,
+            chrono::Duration::hours(self.config.co_occurrence_window_hours),
+        )
+    }
 
-        // Find events where both entities appear in the target field
-        // This is a simplified approach - in production, we'd need full entity linking
-        let events = event_memories
-            .filter(user_id.eq(uid))
-            .filter(timestamp.ge(window_start))
-            .load::<crate::models::EventMemory>(conn)?;
+    /// Calculate co-occurrence strength over an explicit window ending now
+    pub fn calculate_co_occurrence_strength_in_window(
+        &self,
+        conn: &mut PgConnection,
+        uid: &str,
+        entity_id_1: Uuid,
+        entity_id_2: Uuid,
+        window: chrono::Duration,
+    ) -> Result<f64> {
+        use crate::schema::entities::dsl as entities_dsl;
 
-        let mut co_occurrence_count = 0;
-        let mut entity1_count = 0;
-        let mut entity2_count = 0;
+        let events = self.load_co_occurrence_events(conn, uid, chrono::Utc::now() - window)?;
 
         // Get entity names
         let entity1_name = entities_dsl::entities
@@ -459,19 +1134,134 @@ impl EntityRelationExtractor {
             .find(entity_id_2)
             .first::<Entity>(conn);
 
-        let e1_name = entity1_name.map(|e| e.canonical_name.to_lowercase()).ok();
-        let e2_name = entity2_name.map(|e| e.canonical_name.to_lowercase()).ok();
+        let (e1_name, e2_name) = match (entity1_name, entity2_name) {
+            (Ok(e1), Ok(e2)) => (e1.canonical_name.to_lowercase(), e2.canonical_name.to_lowercase()),
+            _ => return Ok(0.0),
+        };
 
-        for event in &events {
-            // Check if entity names appear in event target
-            // In production, we'd have proper entity_id references in events
-            let target_lower = event.target.to_lowercase();
+        Ok(Self::event_co_occurrence_strength(
+            &events,
+            (entity_id_1, &e1_name),
+            (entity_id_2, &e2_name),
+        ))
+    }
 
-            let entity1_present = e1_name.as_ref().map_or(false, |n| target_lower.contains(n));
-            let entity2_present = e2_name.as_ref().map_or(false, |n| target_lower.contains(n));
+    /// Record that an event mentions the given entities
+    ///
+    /// Existing links are left untouched. Returns the number of new links.
+    pub fn link_event_entities(
+        &self,
+        conn: &mut PgConnection,
+        uid: &str,
+        event: Uuid,
+        entity_ids: &[Uuid],
+    ) -> Result<usize> {
+        use crate::schema::event_entity_links::dsl::*;
 
-            if entity1_present {
-                entity1_count += 1;
+        let rows: Vec<_> = entity_ids
+            .iter()
+            .map(|id| (event_id.eq(event), entity_id.eq(*id), user_id.eq(uid)))
+            .collect();
+
+        Ok(diesel::insert_into(event_entity_links)
+            .values(&rows)
+            .on_conflict_do_nothing()
+            .execute(conn)?)
+    }
+
+    /// Load a user's events since `window_start` for co-occurrence counting
+    ///
+    /// In `Linked` mode each event carries its linked entity ids, if any.
+    fn load_co_occurrence_events(
+        &self,
+        conn: &mut PgConnection,
+        uid: &str,
+        window_start: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<CoOccurrenceEvent>> {
+        use crate::schema::event_entity_links::dsl as links_dsl;
+        use crate::schema::event_memories::dsl as events_dsl;
+
+        let events: Vec<(Uuid, String)> = events_dsl::event_memories
+            .filter(events_dsl::user_id.eq(uid))
+            .filter(events_dsl::timestamp.ge(window_start))
+            .select((events_dsl::event_id, events_dsl::target))
+            .load(conn)?;
+
+        let mut links: HashMap<Uuid, HashSet<Uuid>> = HashMap::new();
+        if self.config.co_occurrence_mode == CoOccurrenceMode::Linked {
+            let rows: Vec<(Uuid, Uuid)> = links_dsl::event_entity_links
+                .inner_join(events_dsl::event_memories)
+                .filter(events_dsl::user_id.eq(uid))
+                .filter(events_dsl::timestamp.ge(window_start))
+                .select((links_dsl::event_id, links_dsl::entity_id))
+                .load(conn)?;
+            for (event_id, entity_id) in rows {
+                links.entry(event_id).or_default().insert(entity_id);
+            }
+        }
+
+        Ok(events
+            .into_iter()
+            .map(|(event_id, target)| CoOccurrenceEvent {
+                target: target.to_lowercase(),
+                entity_ids: links.remove(&event_id),
+            })
+            .collect())
+    }
+
+    /// Jaccard-like co-occurrence coefficient over lowercased event targets
+    ///
+    /// Returns a value in [0, 1]: co-occurrences / events mentioning either entity.
+    fn co_occurrence_strength(targets: &[String], name1: &str, name2: &str) -> f64 {
+        Self::jaccard(
+            targets
+                .iter()
+                .map(|target| (target.contains(name1), target.contains(name2))),
+        )
+    }
+
+    /// Co-occurrence coefficient over events, honoring their entity links
+    ///
+    /// Entities are given as `(entity_id, lowercased name)`; the name is only
+    /// used for events without links.
+    pub fn event_co_occurrence_strength(
+        events: &[CoOccurrenceEvent],
+        entity1: (Uuid, &str),
+        entity2: (Uuid, &str),
+    ) -> f64 {
+        Self::event_co_occurrence(events, entity1, entity2).0
+    }
+
+    /// Co-occurrence coefficient over events and the number of events
+    /// mentioning either entity
+    fn event_co_occurrence(
+        events: &[CoOccurrenceEvent],
+        entity1: (Uuid, &str),
+        entity2: (Uuid, &str),
+    ) -> (f64, usize) {
+        Self::jaccard_with_samples(events.iter().map(|event| {
+            (
+                event.mentions(entity1.0, entity1.1),
+                event.mentions(entity2.0, entity2.1),
+            )
+        }))
+    }
+
+    /// Co-occurrences / events mentioning either entity, from per-event
+    /// `(entity1 present, entity2 present)` flags
+    fn jaccard(presence: impl Iterator<Item = (bool, bool)>) -> f64 {
+        Self::jaccard_with_samples(presence).0
+    }
+
+    /// `jaccard` plus the number of events mentioning either entity
+    fn jaccard_with_samples(presence: impl Iterator<Item = (bool, bool)>) -> (f64, usize) {
+        let mut co_occurrence_count = 0;
+        let mut entity1_count = 0;
+        let mut entity2_count = 0;
+
+        for (entity1_present, entity2_present) in presence {
+            if entity1_present {
+                entity1_count += 1;
             }
             if entity2_present {
                 entity2_count += 1;
@@ -481,19 +1271,133 @@ impl EntityRelationExtractor {
             }
         }
 
-        // Calculate strength using Jaccard-like coefficient
-        let strength = if entity1_count == 0 || entity2_count == 0 {
-            0.0
-        } else {
-            let union = entity1_count + entity2_count - co_occurrence_count;
-            if union == 0 {
-                0.0
-            } else {
-                co_occurrence_count as f64 / union as f64
+        let union = entity1_count + entity2_count - co_occurrence_count;
+        if entity1_count == 0 || entity2_count == 0 {
+            return (0.0, union);
+        }
+
+        (co_occurrence_count as f64 / union as f64, union)
+    }
+
+    /// Decide what a recompute does with a relation, given its in-window
+    /// co-occurrence and the number of events mentioning either entity
+    ///
+    /// A relation without in-window mentions keeps its stored co-occurrence;
+    /// absence of recent evidence says nothing about the relation. Pruning
+    /// only applies when enabled and backed by `prune_min_samples` events.
+    fn recompute_action(&self, co_occurrence: f64, samples: usize) -> StrengthRecompute {
+        if samples == 0 {
+            return StrengthRecompute::Keep;
+        }
+        if self.config.prune_weak_relations
+            && samples >= self.config.prune_min_samples
+            && co_occurrence < self.config.min_strength_threshold
+        {
+            return StrengthRecompute::Prune;
+        }
+        StrengthRecompute::Update(co_occurrence)
+    }
+
+    /// Recompute every relation's co-occurrence for a user from recent events
+    ///
+    /// Run on a schedule by `spawn_strength_recompute_loop`. `co_occurrence`
+    /// is rewritten as the normalized coefficient over the last
+    /// `strength_recompute_window_days`, and `strength` re-derived from
+    /// `observation_count` with the configured `strength_saturation`; neither
+    /// overwrites the other. Relations neither entity of which is mentioned in
+    /// the window keep their co-occurrence. With `prune_weak_relations`,
+    /// relations mentioned in at least `prune_min_samples` events whose
+    /// co-occurrence falls below `min_strength_threshold` are deleted.
+    pub fn recompute_all_strengths(
+        &self,
+        conn: &mut PgConnection,
+        uid: &str,
+    ) -> Result<StrengthRecomputeSummary> {
+        use crate::schema::entities::dsl as entities_dsl;
+        use crate::schema::entity_relations::dsl as relations_dsl;
+
+        let window_start =
+            chrono::Utc::now() - chrono::Duration::days(self.config.strength_recompute_window_days);
+
+        let events = self.load_co_occurrence_events(conn, uid, window_start)?;
+
+        let names: HashMap<Uuid, String> = entities_dsl::entities
+            .filter(entities_dsl::user_id.eq(uid))
+            .select((entities_dsl::entity_id, entities_dsl::canonical_name))
+            .load::<(Uuid, String)>(conn)?
+            .into_iter()
+            .map(|(id, name)| (id, name.to_lowercase()))
+            .collect();
+
+        let relations = relations_dsl::entity_relations
+            .filter(relations_dsl::user_id.eq(uid))
+            .load::<EntityRelation>(conn)?;
+
+        conn.transaction::<_, DirSoulError, _>(|conn| {
+            let mut summary = StrengthRecomputeSummary::default();
+
+            for rel in relations {
+                // Relations whose entities are gone have no evidence either way
+                let (co_occurrence, samples) = match (
+                    names.get(&rel.source_entity_id),
+                    names.get(&rel.target_entity_id),
+                ) {
+                    (Some(source), Some(target)) => Self::event_co_occurrence(
+                        &events,
+                        (rel.source_entity_id, source),
+                        (rel.target_entity_id, target),
+                    ),
+                    _ => (0.0, 0),
+                };
+                let strength = self.config.count_strength(rel.observation_count);
+
+                match self.recompute_action(co_occurrence, samples) {
+                    StrengthRecompute::Prune => {
+                        diesel::delete(relations_dsl::entity_relations.find(rel.relation_id))
+                            .execute(conn)?;
+                        summary.relations_pruned += 1;
+                    }
+                    StrengthRecompute::Update(co_occurrence) => {
+                        diesel::update(relations_dsl::entity_relations.find(rel.relation_id))
+                            .set((
+                                relations_dsl::co_occurrence.eq(co_occurrence),
+                                relations_dsl::strength.eq(strength),
+                            ))
+                            .execute(conn)?;
+                        summary.relations_updated += 1;
+                    }
+                    StrengthRecompute::Keep => {
+                        if rel.strength != strength {
+                            diesel::update(relations_dsl::entity_relations.find(rel.relation_id))
+                                .set(relations_dsl::strength.eq(strength))
+                                .execute(conn)?;
+                        }
+                        summary.relations_unchanged += 1;
+                    }
+                }
             }
-        };
 
-        Ok(strength)
+            Ok(summary)
+        })
+    }
+
+    /// Re-derive every user's relation strengths with the configured `k`
+    ///
+    /// Run at startup so strengths follow a changed `strength_saturation`
+    /// and legacy rows holding raw counts are normalized. Only rows whose
+    /// stored strength differs from the recomputed one are written, so with
+    /// an unchanged k this touches nothing. Returns the number of relations
+    /// rewritten.
+    pub fn sync_count_strengths(&self, conn: &mut PgConnection) -> Result<usize> {
+        let rewritten = diesel::sql_query(
+            "UPDATE entity_relations
+             SET strength = observation_count::FLOAT8 / (observation_count + $1)
+             WHERE ABS(strength - observation_count::FLOAT8 / (observation_count + $1)) > $2",
+        )
+        .bind::<diesel::sql_types::Double, _>(self.config.strength_saturation)
+        .bind::<diesel::sql_types::Double, _>(STRENGTH_SYNC_TOLERANCE)
+        .execute(conn)?;
+        Ok(rewritten)
     }
 
     /// Find entities related to a given entity
@@ -505,26 +1409,32 @@ impl EntityRelationExtractor {
     /// * `uid` - User ID
     /// * `entity_id` - Entity to find relations for
     /// * `min_strength` - Minimum relation strength threshold
+    /// * `limit` - Keep only the strongest `limit` relations
     ///
     /// # Returns
-    /// List of tuples: (related_entity, relation, reverse_relation)
+    /// List of tuples: (related_entity, relation, reverse_relation), strongest
+    /// relation first
     pub fn find_related_entities(
         &self,
         conn: &mut PgConnection,
         uid: &str,
         entity_id: Uuid,
         min_strength: Option<f64>,
+        limit: Option<usize>,
     ) -> Result<Vec<(Entity, EntityRelation, Option<EntityRelation>)>> {
         use crate::schema::entity_relations::dsl::*;
         use crate::schema::entities::dsl as entities_dsl;
 
         let strength_threshold = min_strength.unwrap_or(self.config.min_strength_threshold);
+        let row_limit = limit.map_or(i64::MAX, |n| n as i64);
 
         // Find outgoing relations (source = entity_id)
         let outgoing = entity_relations
             .filter(user_id.eq(uid))
             .filter(source_entity_id.eq(entity_id))
             .filter(strength.ge(strength_threshold))
+            .order(strength.desc())
+            .limit(row_limit)
             .load::<EntityRelation>(conn)?;
 
         // Find incoming relations (target = entity_id)
@@ -532,21 +1442,30 @@ impl EntityRelationExtractor {
             .filter(user_id.eq(uid))
             .filter(target_entity_id.eq(entity_id))
             .filter(strength.ge(strength_threshold))
+            .order(strength.desc())
+            .limit(row_limit)
             .load::<EntityRelation>(conn)?;
 
-        let mut results = Vec::new();
-
-        // Process outgoing relations
-        for rel in outgoing {
-            if let Ok(target_entity) = entities_dsl::entities.find(rel.target_entity_id).first::<Entity>(conn) {
-                results.push((target_entity, rel.clone(), None));
-            }
+        // Strongest relations across both directions, before loading entities
+        let mut relations: Vec<(EntityRelation, bool)> = outgoing
+            .into_iter()
+            .map(|rel| (rel, false))
+            .chain(incoming.into_iter().map(|rel| (rel, true)))
+            .collect();
+        relations.sort_by(|a, b| b.0.strength.total_cmp(&a.0.strength));
+        if let Some(limit) = limit {
+            relations.truncate(limit);
         }
 
-        // Process incoming relations
-        for rel in incoming {
-            if let Ok(source_entity) = entities_dsl::entities.find(rel.source_entity_id).first::<Entity>(conn) {
-                results.push((source_entity, rel.clone(), Some(rel)));
+        let mut results = Vec::new();
+
+        for (rel, is_incoming) in relations {
+            if is_incoming {
+                if let Ok(source_entity) = entities_dsl::entities.find(rel.source_entity_id).first::<Entity>(conn) {
+                    results.push((source_entity, rel.clone(), Some(rel)));
+                }
+            } else if let Ok(target_entity) = entities_dsl::entities.find(rel.target_entity_id).first::<Entity>(conn) {
+                results.push((target_entity, rel, None));
             }
         }
 
@@ -555,10 +1474,13 @@ impl EntityRelationExtractor {
 
     /// Find shortest path between two entities (BFS)
     ///
-    /// Graph traversal to find how two entities are connected.
+    /// Graph traversal to find how two entities are connected. The search
+    /// stops at `max_depth` hops and after reaching
+    /// `config.path_max_visited_nodes` entities, whichever comes first.
     ///
     /// # Returns
-    /// List of entity IDs forming the path, or empty if no path exists
+    /// The path as entity IDs, `NoPath` when every entity within `max_depth`
+    /// was searched, or `Exhausted` when the node cap stopped the search
     pub fn find_path(
         &self,
         conn: &mut PgConnection,
@@ -566,122 +1488,53 @@ impl EntityRelationExtractor {
         start_id: Uuid,
         end_id: Uuid,
         max_depth: usize,
-    ) -> Result<Vec<Uuid>> {
+    ) -> Result<PathSearch> {
         use crate::schema::entity_relations::dsl::*;
-        use std::collections::{HashMap, VecDeque};
 
-        if start_id == end_id {
-            return Ok(vec![start_id]);
-        }
-
-        // BFS
-        let mut queue = VecDeque::new();
-        let mut visited: HashMap<Uuid, Option<Uuid>> = HashMap::new();
-
-        queue.push_back((start_id, 0));
-        visited.insert(start_id, None);
-
-        while let Some((current, depth)) = queue.pop_front() {
-            if depth >= max_depth {
-                continue;
-            }
-
-            // Find all neighbors
-            let outgoing = entity_relations
+        shortest_path(start_id, end_id, max_depth, self.config.path_max_visited_nodes, |current| {
+            // Outgoing neighbors first, then incoming
+            let mut neighbors: Vec<Uuid> = entity_relations
                 .filter(user_id.eq(uid))
                 .filter(source_entity_id.eq(current))
-                .load::<EntityRelation>(conn)?;
-
-            for rel in outgoing {
-                if !visited.contains_key(&rel.target_entity_id) {
-                    visited.insert(rel.target_entity_id, Some(current));
-                    queue.push_back((rel.target_entity_id, depth + 1));
-
-                    if rel.target_entity_id == end_id {
-                        // Reconstruct path
-                        let mut path = vec![end_id];
-                        let mut current_id = Some(end_id);
-
-                        while let Some(id) = current_id {
-                            if let Some(&parent) = visited.get(&id) {
-                                if let Some(p) = parent {
-                                    path.push(p);
-                                    current_id = Some(p);
-                                } else {
-                                    current_id = None;
-                                }
-                            } else {
-                                current_id = None;
-                            }
-                        }
-
-                        path.reverse();
-                        return Ok(path);
-                    }
-                }
-            }
-
-            // Also check incoming relations
-            let incoming = entity_relations
-                .filter(user_id.eq(uid))
-                .filter(target_entity_id.eq(current))
-                .load::<EntityRelation>(conn)?;
-
-            for rel in incoming {
-                if !visited.contains_key(&rel.source_entity_id) {
-                    visited.insert(rel.source_entity_id, Some(current));
-                    queue.push_back((rel.source_entity_id, depth + 1));
-
-                    if rel.source_entity_id == end_id {
-                        // Same path reconstruction
-                        let mut path = vec![end_id];
-                        let mut current_id = Some(end_id);
-
-                        while let Some(id) = current_id {
-                            if let Some(&parent) = visited.get(&id) {
-                                if let Some(p) = parent {
-                                    path.push(p);
-                                    current_id = Some(p);
-                                } else {
-                                    current_id = None;
-                                }
-                            } else {
-                                current_id = None;
-                            }
-                        }
-
-                        path.reverse();
-                        return Ok(path);
-                    }
-                }
-            }
-        }
-
-        // No path found
-        Ok(Vec::new())
+                .select(target_entity_id)
+                .load(conn)?;
+            neighbors.extend(
+                entity_relations
+                    .filter(user_id.eq(uid))
+                    .filter(target_entity_id.eq(current))
+                    .select(source_entity_id)
+                    .load::<Uuid>(conn)?,
+            );
+            Ok(neighbors)
+        })
     }
 
     /// Get relation statistics for an entity
     ///
-    /// Returns counts of different relation types for the entity.
+    /// Returns counts of different relation types for the entity, over the
+    /// same relations `find_related_entities` returns for `min_strength`.
     pub fn get_relation_stats(
         &self,
         conn: &mut PgConnection,
         uid: &str,
         entity_id: Uuid,
+        min_strength: Option<f64>,
     ) -> Result<HashMap<String, i64>> {
         use crate::schema::entity_relations::dsl::*;
 
-        let relations = entity_relations
+        let strength_threshold = min_strength.unwrap_or(self.config.min_strength_threshold);
+
+        let relation_types = entity_relations
             .filter(user_id.eq(uid))
-            .filter(source_entity_id.eq(entity_id))
-            .or_filter(target_entity_id.eq(entity_id))
-            .load::<EntityRelation>(conn)?;
+            .filter(source_entity_id.eq(entity_id).or(target_entity_id.eq(entity_id)))
+            .filter(strength.ge(strength_threshold))
+            .select(relation_type)
+            .load::<String>(conn)?;
 
         let mut stats = HashMap::new();
 
-        for rel in relations {
-            *stats.entry(rel.relation_type.clone()).or_insert(0) += 1;
+        for rel_type in relation_types {
+            *stats.entry(rel_type).or_insert(0) += 1;
         }
 
         Ok(stats)
@@ -694,9 +1547,70 @@ impl Default for EntityRelationExtractor {
     }
 }
 
+/// Users owning at least one entity relation
+pub fn users_with_relations(conn: &mut PgConnection) -> Result<Vec<String>> {
+    use crate::schema::entity_relations::dsl as relations_dsl;
+
+    Ok(relations_dsl::entity_relations
+        .select(relations_dsl::user_id)
+        .distinct()
+        .load(conn)?)
+}
+
+/// Run `recompute_all_strengths` for every user with relations on a Tokio
+/// interval
+///
+/// The first tick fires immediately. Each tick runs on the blocking pool
+/// with its own connection; a failing user is logged without stopping the
+/// others or later ticks.
+pub fn spawn_strength_recompute_loop(
+    database_url: String,
+    interval: std::time::Duration,
+    extractor: EntityRelationExtractor,
+) -> tokio::task::JoinHandle<()> {
+    let extractor = Arc::new(extractor);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            ticker.tick().await;
+
+            let database_url = database_url.clone();
+            let extractor = Arc::clone(&extractor);
+            let run = tokio::task::spawn_blocking(move || -> Result<()> {
+                let mut conn = PgConnection::establish(&database_url)?;
+                for user_id in users_with_relations(&mut conn)? {
+                    match extractor.recompute_all_strengths(&mut conn, &user_id) {
+                        Ok(summary) => tracing::info!(
+                            "Relation strengths for {}: {} updated, {} pruned, {} without recent mentions",
+                            user_id,
+                            summary.relations_updated,
+                            summary.relations_pruned,
+                            summary.relations_unchanged
+                        ),
+                        Err(e) => {
+                            tracing::warn!("Relation strength recompute failed for {}: {}", user_id, e)
+                        }
+                    }
+                }
+                Ok(())
+            });
+
+            match run.await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::warn!("Relation strength recompute tick failed: {}", e),
+                Err(e) => tracing::warn!("Relation strength recompute tick aborted: {}", e),
+            }
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::llm_provider::mock::MockProvider;
 
     #[test]
     fn test_relation_type_from_str() {
@@ -819,5 +1733,773 @@ mod tests {
         assert_eq!(config.model, "phi4-mini");
         assert_eq!(config.timeout_secs, 30);
         assert_eq!(config.co_occurrence_window_hours, 24);
+        assert_eq!(config.strength_recompute_window_days, 30);
+        assert_eq!(config.confidence_blending, ConfidenceBlending::RunningMean);
+    }
+
+    /// Confidence after each observation, starting from the first one
+    fn blend_sequence(blending: ConfidenceBlending, observations: &[f64]) -> Vec<f64> {
+        let mut confidence = observations[0];
+        let mut trace = vec![confidence];
+        for (seen, &observed) in observations.iter().enumerate().skip(1) {
+            confidence = blending.blend(confidence, seen as i32, observed);
+            trace.push(confidence);
+        }
+        trace
+    }
+
+    #[test]
+    fn test_running_mean_matches_previous_formula() {
+        let observations = [0.4, 0.6, 0.9, 0.9, 0.2];
+        let trace = blend_sequence(ConfidenceBlending::RunningMean, &observations);
+
+        // Old save_relations: (confidence * n + x) / (n + 1), n counted in strength
+        let mut expected = observations[0];
+        for (n, &x) in observations.iter().enumerate().skip(1) {
+            expected = (expected * n as f64 + x) / (n as f64 + 1.0);
+        }
+        assert!((trace[4] - expected).abs() < 1e-12);
+
+        // The running mean is the plain average of all observations
+        let mean = observations.iter().sum::<f64>() / observations.len() as f64;
+        assert!((trace[4] - mean).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_ema_follows_strong_new_evidence_faster() {
+        // Long run of weak evidence, then consistently strong evidence
+        let mut observations = vec![0.3; 10];
+        observations.extend([0.95; 3]);
+
+        let mean = blend_sequence(ConfidenceBlending::RunningMean, &observations);
+        let ema = blend_sequence(ConfidenceBlending::Ema { alpha: 0.5 }, &observations);
+
+        assert!((mean[9] - 0.3).abs() < 1e-12);
+        assert!((ema[9] - 0.3).abs() < 1e-12);
+        // After three strong observations the mean has barely moved
+        assert!(mean[12] < 0.5, "{}", mean[12]);
+        // EMA with alpha 0.5 closes 7/8 of the gap: 0.3 + 0.65 * 0.875
+        assert!((ema[12] - 0.86875).abs() < 1e-9, "{}", ema[12]);
+
+        // EMA ignores how many observations came before
+        let fresh = ConfidenceBlending::Ema { alpha: 0.5 }.blend(0.3, 1, 0.95);
+        let seasoned = ConfidenceBlending::Ema { alpha: 0.5 }.blend(0.3, 100, 0.95);
+        assert_eq!(fresh, seasoned);
+        assert!((ConfidenceBlending::Ema { alpha: 1.0 }.blend(0.3, 5, 0.95) - 0.95).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_invalid_ema_alpha_is_rejected() {
+        for alpha in [0.0, -0.1, 1.5, f64::NAN] {
+            let config = RelationExtractorConfig {
+                confidence_blending: ConfidenceBlending::Ema { alpha },
+                ..Default::default()
+            };
+            assert!(config.validate().is_err(), "alpha {}", alpha);
+        }
+        let config = RelationExtractorConfig {
+            confidence_blending: ConfidenceBlending::Ema { alpha: 0.3 },
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+    }
+
+    fn test_entity(name: &str, entity_type: &str) -> Entity {
+        Entity {
+            entity_id: Uuid::new_v4(),
+            user_id: "test".to_string(),
+            canonical_name: name.to_string(),
+            entity_type: entity_type.to_string(),
+            attributes: None,
+            first_seen: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+            occurrence_count: 1,
+            confidence: 0.8,
+        }
+    }
+
+    #[test]
+    fn test_default_relation_prompt_matches_builtin_examples() {
+        let extractor = EntityRelationExtractor::new();
+        let entities = vec![test_entity("苹果", "object"), test_entity("水果", "concept")];
+
+        let prompt = extractor.build_relation_prompt("苹果是一种水果", &entities);
+        assert!(prompt.contains("文本：苹果是一种水果"));
+        assert!(prompt.contains("1. 苹果\n2. 水果"));
+        assert!(prompt.contains(
+            "relation_type: 关系类型（belongs_to/related_to/located_at/works_at/friends_with/family_of/owns/created_by/part_of）"
+        ));
+        assert!(prompt.contains(
+            "[\n  {\"source\": \"苹果\", \"target\": \"水果\", \"relation_type\": \"belongs_to\", \"confidence\": 0.9},\n  {\"source\": \"张三\", \"target\": \"北京\", \"relation_type\": \"located_at\", \"confidence\": 0.8}\n]"
+        ));
+        assert!(!prompt.contains("{{"));
+    }
+
+    #[test]
+    fn test_custom_few_shot_examples_rendered() {
+        let config = RelationExtractorConfig {
+            relation_types: vec!["mentor_of".to_string(), "works_at".to_string()],
+            few_shot_examples: vec![RelationExample::new("Alice", "Bob", "mentor_of", 0.85)],
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        let extractor = EntityRelationExtractor::with_config(config);
+        let entities = vec![test_entity("Alice", "person"), test_entity("Bob", "person")];
+        let prompt = extractor.build_relation_prompt("Alice mentors Bob", &entities);
+
+        assert!(prompt.contains("mentor_of/works_at"));
+        assert!(prompt.contains("{\"source\": \"Alice\", \"target\": \"Bob\", \"relation_type\": \"mentor_of\", \"confidence\": 0.85}"));
+        assert!(!prompt.contains("张三"));
+    }
+
+    #[test]
+    fn test_custom_relation_types_map_through_from_str() {
+        let config = RelationExtractorConfig {
+            relation_types: vec!["located_at".to_string(), "mentor_of".to_string()],
+            few_shot_examples: vec![],
+            ..Default::default()
+        };
+        assert_eq!(
+            config.parsed_relation_types(),
+            vec![RelationType::LocatedAt, RelationType::Custom("mentor_of".to_string())]
+        );
+
+        // Examples must reference a configured type
+        let invalid = RelationExtractorConfig {
+            relation_types: vec!["located_at".to_string()],
+            few_shot_examples: vec![RelationExample::new("A", "B", "mentor_of", 0.9)],
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+
+        let empty_name = RelationExtractorConfig {
+            relation_types: vec![" ".to_string()],
+            few_shot_examples: vec![],
+            ..Default::default()
+        };
+        assert!(empty_name.validate().is_err());
+    }
+
+    #[test]
+    fn test_co_occurrence_rules_off_by_default() {
+        let entities = vec![
+            test_entity("张三", "person"),
+            test_entity("北京", "place"),
+            test_entity("苹果", "object"),
+        ];
+        let text = "张三位于北京，买了苹果";
+
+        // Only the explicit pattern fires; no pairwise related_to relations
+        let relations = EntityRelationExtractor::new().extract_relations_rule_based(text, &entities);
+        assert_eq!(relations.len(), 1);
+        assert_eq!(relations[0].source, "张三");
+        assert_eq!(relations[0].target, "北京");
+        assert_eq!(relations[0].relation_type, RelationType::LocatedAt);
+
+        let extractor = EntityRelationExtractor::with_config(RelationExtractorConfig {
+            rule_co_occurrence_enabled: true,
+            ..Default::default()
+        });
+        let relations = extractor.extract_relations_rule_based(text, &entities);
+        let related: Vec<&ExtractedRelation> = relations
+            .iter()
+            .filter(|r| r.relation_type == RelationType::RelatedTo)
+            .collect();
+        assert_eq!(related.len(), 3);
+        assert!(related.iter().all(|r| r.confidence == 0.5));
+        assert!(relations.iter().any(|r| r.relation_type == RelationType::LocatedAt));
+    }
+
+    #[test]
+    fn test_relation_rules_are_configurable() {
+        let entities = vec![test_entity("张三", "person"), test_entity("Acme", "organization")];
+
+        let extractor = EntityRelationExtractor::with_config(RelationExtractorConfig {
+            relation_rules: vec![
+                RelationRule::new("就职于", "works_at", 0.8, RuleMatch::Adjacent),
+                RelationRule::new("买", "owns", 0.3, RuleMatch::CoOccurrence),
+                RelationRule::new("卖", "owns", 0.4, RuleMatch::CoOccurrence),
+            ],
+            rule_co_occurrence_enabled: true,
+            ..Default::default()
+        });
+
+        let relations = extractor.extract_relations_rule_based("张三就职于Acme", &entities);
+        assert_eq!(relations.len(), 1);
+        assert_eq!(relations[0].relation_type, RelationType::WorksAt);
+        assert_eq!(relations[0].confidence, 0.8);
+
+        // The default "位于" rule is gone from the table
+        assert!(extractor.extract_relations_rule_based("张三位于Acme", &entities).is_empty());
+
+        // Two triggers for one type relate the pair once, at the higher confidence
+        let relations = extractor.extract_relations_rule_based("张三买卖Acme股票", &entities);
+        assert_eq!(relations.len(), 1);
+        assert_eq!(relations[0].relation_type, RelationType::Owns);
+        assert_eq!(relations[0].confidence, 0.4);
+
+        let invalid = RelationExtractorConfig {
+            relation_rules: vec![RelationRule::new("是", "belongs_to", 1.5, RuleMatch::Loose)],
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+        let empty_trigger = RelationExtractorConfig {
+            relation_rules: vec![RelationRule::new(" ", "belongs_to", 0.5, RuleMatch::Adjacent)],
+            ..Default::default()
+        };
+        assert!(empty_trigger.validate().is_err());
+    }
+
+    /// Node `i` of a synthetic graph
+    fn node(i: u128) -> Uuid {
+        Uuid::from_u128(i)
+    }
+
+    /// Chain 0 - 1 - ... - (len - 1)
+    fn chain_neighbors(len: u128) -> impl FnMut(Uuid) -> Result<Vec<Uuid>> {
+        move |id| {
+            let i = id.as_u128();
+            Ok([i.checked_sub(1), Some(i + 1).filter(|&n| n < len)]
+                .into_iter()
+                .flatten()
+                .map(node)
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_shortest_path_on_chain() {
+        assert_eq!(
+            shortest_path(node(0), node(3), 10, 100, chain_neighbors(10)).unwrap(),
+            PathSearch::Found(vec![node(0), node(1), node(2), node(3)])
+        );
+        assert_eq!(
+            shortest_path(node(5), node(5), 0, 1, chain_neighbors(10)).unwrap(),
+            PathSearch::Found(vec![node(5)])
+        );
+        // Beyond max_depth, or disconnected, is a plain miss
+        assert_eq!(
+            shortest_path(node(0), node(9), 3, 100, chain_neighbors(10)).unwrap(),
+            PathSearch::NoPath
+        );
+        assert_eq!(
+            shortest_path(node(0), node(99), 200, 100, chain_neighbors(10)).unwrap(),
+            PathSearch::NoPath
+        );
+    }
+
+    #[test]
+    fn test_shortest_path_node_cap_on_dense_graph() {
+        // Hub 0 linked to 50_000 leaves; the target hangs off the last leaf
+        let leaves: u128 = 50_000;
+        let target = node(leaves + 1);
+        let mut expanded = 0;
+        let neighbors = |id: Uuid| {
+            expanded += 1;
+            let i = id.as_u128();
+            Ok(match i {
+                0 => (1..=leaves).map(node).collect(),
+                i if i == leaves => vec![node(0), target],
+                _ => vec![node(0)],
+            })
+        };
+
+        let result = shortest_path(node(0), target, 5, 1_000, neighbors).unwrap();
+        assert_eq!(result, PathSearch::Exhausted { visited: 1_000 });
+        // Gave up while expanding the hub
+        assert_eq!(expanded, 1);
+
+        // A large enough cap finds the path
+        let neighbors = |id: Uuid| {
+            let i = id.as_u128();
+            Ok(match i {
+                0 => (1..=leaves).map(node).collect(),
+                i if i == leaves => vec![node(0), target],
+                _ => vec![node(0)],
+            })
+        };
+        assert_eq!(
+            shortest_path(node(0), target, 5, 100_000, neighbors).unwrap(),
+            PathSearch::Found(vec![node(0), node(leaves), target])
+        );
+    }
+
+    #[test]
+    fn test_shortest_path_cap_counts_discovered_nodes() {
+        // The end is always reachable once discovered, even at the cap
+        assert_eq!(
+            shortest_path(node(0), node(1), 10, 1, chain_neighbors(10)).unwrap(),
+            PathSearch::Found(vec![node(0), node(1)])
+        );
+        assert_eq!(
+            shortest_path(node(0), node(9), 20, 5, chain_neighbors(10)).unwrap(),
+            PathSearch::Exhausted { visited: 5 }
+        );
+
+        let invalid = RelationExtractorConfig {
+            path_max_visited_nodes: 0,
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_loose_rule_window_respects_char_boundaries() {
+        let entities = vec![test_entity("苹果", "object"), test_entity("水果", "concept")];
+        // The 50-byte window after "是" ends mid-character
+        let text = format!("苹果是{}水果", "很".repeat(20));
+        let relations = EntityRelationExtractor::new().extract_relations_rule_based(&text, &entities);
+        assert!(relations.is_empty());
+    }
+
+    #[test]
+    fn test_relation_prompt_from_prompt_manager() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("relation_extraction.txt"),
+            "Types: {{relation_types}}\nExamples: {{examples}}\nText: {{text}}",
+        )
+        .unwrap();
+
+        let extractor = EntityRelationExtractor::new()
+            .with_prompt_manager(PromptManager::with_dir(temp_dir.path()).unwrap());
+        let entities = vec![test_entity("苹果", "object"), test_entity("水果", "concept")];
+        let prompt = extractor.build_relation_prompt("苹果是一种水果", &entities);
+
+        assert!(prompt.starts_with("Types: belongs_to/related_to"));
+        assert!(prompt.contains("\"source\": \"苹果\""));
+        assert!(prompt.ends_with("Text: 苹果是一种水果"));
+    }
+
+    #[test]
+    fn test_relation_prompt_prepends_system_prompt() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let manager = PromptManager::with_dir(temp_dir.path()).unwrap().with_system_prompts(
+            crate::prompt_manager::SystemPrompts::new("Base instructions")
+                .with_override(PromptTask::RelationExtraction, "Only food relations."),
+        );
+
+        let extractor = EntityRelationExtractor::new().with_prompt_manager(manager);
+        let entities = vec![test_entity("苹果", "object"), test_entity("水果", "concept")];
+        let prompt = extractor.build_relation_prompt("苹果是一种水果", &entities);
+
+        // No template in the directory: the built-in prompt follows the system prompt
+        assert!(prompt.starts_with("Only food relations.\n\n"));
+        assert!(prompt.contains("苹果是一种水果"));
+    }
+
+    #[test]
+    fn test_co_occurrence_strength_high_for_frequent_pairs() {
+        let config = RelationExtractorConfig::default();
+        let targets: Vec<String> = vec![
+            "咖啡和牛奶", "咖啡和牛奶", "咖啡和牛奶", "咖啡和牛奶", "咖啡",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+        let strength = EntityRelationExtractor::co_occurrence_strength(&targets, "咖啡", "牛奶");
+        assert!((strength - 0.8).abs() < 1e-9);
+        assert!(strength >= config.min_strength_threshold);
+    }
+
+    #[test]
+    fn test_co_occurrence_strength_prunes_coincidental_pairs() {
+        let config = RelationExtractorConfig::default();
+        let mut targets: Vec<String> = Vec::new();
+        for _ in 0..10 {
+            targets.push("苹果".to_string());
+            targets.push("北京".to_string());
+        }
+        targets.push("在北京吃苹果".to_string());
+
+        // 1 co-occurrence out of 21 events mentioning either entity
+        let strength = EntityRelationExtractor::co_occurrence_strength(&targets, "苹果", "北京");
+        assert!(strength > 0.0);
+        assert!(strength < config.min_strength_threshold);
+    }
+
+    #[test]
+    fn test_recompute_prunes_only_when_enabled_with_enough_samples() {
+        let keep_all = EntityRelationExtractor::new();
+        // No mentions in the window: keep what is stored, pruning or not
+        assert_eq!(keep_all.recompute_action(0.0, 0), StrengthRecompute::Keep);
+        // Pruning is off by default
+        assert_eq!(keep_all.recompute_action(0.05, 50), StrengthRecompute::Update(0.05));
+
+        let pruning = EntityRelationExtractor::with_config(RelationExtractorConfig {
+            prune_weak_relations: true,
+            prune_min_samples: 5,
+            ..Default::default()
+        });
+        assert_eq!(pruning.recompute_action(0.0, 0), StrengthRecompute::Keep);
+        assert_eq!(pruning.recompute_action(0.0, 4), StrengthRecompute::Update(0.0));
+        assert_eq!(pruning.recompute_action(0.05, 5), StrengthRecompute::Prune);
+        assert_eq!(pruning.recompute_action(0.5, 5), StrengthRecompute::Update(0.5));
+
+        let invalid = RelationExtractorConfig {
+            prune_min_samples: 0,
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_co_occurrence_samples_count_events_mentioning_either_entity() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let events = vec![
+            unlinked_event("苹果"),
+            unlinked_event("在北京吃苹果"),
+            unlinked_event("咖啡"),
+        ];
+        let (strength, samples) =
+            EntityRelationExtractor::event_co_occurrence(&events, (a, "苹果"), (b, "北京"));
+        assert_eq!(samples, 2);
+        assert!((strength - 0.5).abs() < 1e-9);
+
+        // One side mentioned alone still counts as evidence of a weak pair
+        let (strength, samples) =
+            EntityRelationExtractor::event_co_occurrence(&events, (a, "咖啡"), (b, "茶"));
+        assert_eq!((strength, samples), (0.0, 1));
+    }
+
+    #[test]
+    fn test_co_occurrence_strength_zero_without_mentions() {
+        let targets = vec!["咖啡".to_string()];
+        assert_eq!(EntityRelationExtractor::co_occurrence_strength(&targets, "咖啡", "茶"), 0.0);
+        assert_eq!(EntityRelationExtractor::co_occurrence_strength(&[], "咖啡", "茶"), 0.0);
+    }
+
+    fn linked_event(target: &str, ids: &[Uuid]) -> CoOccurrenceEvent {
+        CoOccurrenceEvent {
+            target: target.to_string(),
+            entity_ids: Some(ids.iter().copied().collect()),
+        }
+    }
+
+    fn unlinked_event(target: &str) -> CoOccurrenceEvent {
+        CoOccurrenceEvent { target: target.to_string(), entity_ids: None }
+    }
+
+    #[test]
+    fn test_co_occurrence_mode_default_is_substring() {
+        assert_eq!(RelationExtractorConfig::default().co_occurrence_mode, CoOccurrenceMode::Substring);
+    }
+
+    #[test]
+    fn test_linked_mode_ignores_substring_false_positives() {
+        let apple = Uuid::new_v4();
+        let beijing = Uuid::new_v4();
+        let targets = ["在北京吃苹果", "北京苹果店买手机", "北京苹果店修电脑", "苹果"];
+
+        // Substring mode: the Apple store mentions count as eating apples in Beijing
+        let substring: Vec<CoOccurrenceEvent> = targets.iter().map(|t| unlinked_event(t)).collect();
+        let substring_strength = EntityRelationExtractor::event_co_occurrence_strength(
+            &substring,
+            (apple, "苹果"),
+            (beijing, "北京"),
+        );
+        assert!((substring_strength - 0.75).abs() < 1e-9);
+
+        // Linked mode: the store events link to a different entity
+        let store = Uuid::new_v4();
+        let linked = vec![
+            linked_event(targets[0], &[apple, beijing]),
+            linked_event(targets[1], &[store, beijing]),
+            linked_event(targets[2], &[store, beijing]),
+            linked_event(targets[3], &[apple]),
+        ];
+        let linked_strength = EntityRelationExtractor::event_co_occurrence_strength(
+            &linked,
+            (apple, "苹果"),
+            (beijing, "北京"),
+        );
+        assert!((linked_strength - 0.25).abs() < 1e-9);
+        assert!(linked_strength < substring_strength);
+    }
+
+    #[test]
+    fn test_linked_mode_counts_links_missing_from_target() {
+        let coffee = Uuid::new_v4();
+        let milk = Uuid::new_v4();
+        let events = vec![
+            linked_event("拿铁", &[coffee, milk]),
+            linked_event("拿铁", &[coffee, milk]),
+        ];
+
+        let by_name: Vec<CoOccurrenceEvent> = events.iter().map(|e| unlinked_event(&e.target)).collect();
+        assert_eq!(
+            EntityRelationExtractor::event_co_occurrence_strength(&by_name, (coffee, "咖啡"), (milk, "牛奶")),
+            0.0
+        );
+        assert_eq!(
+            EntityRelationExtractor::event_co_occurrence_strength(&events, (coffee, "咖啡"), (milk, "牛奶")),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_unlinked_events_fall_back_to_substring() {
+        let coffee = Uuid::new_v4();
+        let milk = Uuid::new_v4();
+        let events = vec![
+            linked_event("早餐", &[coffee, milk]),
+            unlinked_event("咖啡加牛奶"),
+            unlinked_event("咖啡"),
+        ];
+
+        let strength =
+            EntityRelationExtractor::event_co_occurrence_strength(&events, (coffee, "咖啡"), (milk, "牛奶"));
+        assert!((strength - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    fn segment(text: &str, names: &[&str]) -> RelationSegment {
+        RelationSegment {
+            text: text.to_string(),
+            entities: names.iter().map(|name| test_entity(name, "object")).collect(),
+        }
+    }
+
+    /// Mock Ollama that answers every segment of a batched prompt
+    ///
+    /// Each segment gets a relation between its first two entities, plus one
+    /// naming an entity from another segment. Segments whose text contains
+    /// "坏" get a malformed `relations` value, and every reply carries an item
+    /// for a segment number that does not exist. Returns the server address,
+    /// the number of requests and the peak number in flight.
+    fn spawn_batch_server() -> (
+        std::net::SocketAddr,
+        Arc<std::sync::atomic::AtomicUsize>,
+        Arc<std::sync::atomic::AtomicUsize>,
+    ) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use warp::Filter;
+
+        let requests = Arc::new(AtomicUsize::new(0));
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (requests_out, peak_out) = (Arc::clone(&requests), Arc::clone(&peak));
+
+        let generate = warp::path!("api" / "generate")
+            .and(warp::post())
+            .and(warp::body::json())
+            .and_then(move |body: serde_json::Value| {
+                let requests = Arc::clone(&requests);
+                let in_flight = Arc::clone(&in_flight);
+                let peak = Arc::clone(&peak);
+                async move {
+                    requests.fetch_add(1, Ordering::SeqCst);
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+
+                    let prompt = body["prompt"].as_str().unwrap_or_default().to_string();
+                    let mut items = vec![serde_json::json!({"segment": 99, "relations": []})];
+                    for chunk in prompt.split(SEGMENT_HEADER).skip(1) {
+                        let number: u64 = chunk
+                            .trim_start()
+                            .chars()
+                            .take_while(|c| c.is_ascii_digit())
+                            .collect::<String>()
+                            .parse()
+                            .unwrap();
+                        if chunk.contains("坏") {
+                            items.push(serde_json::json!({"segment": number, "relations": "oops"}));
+                            continue;
+                        }
+                        let names: Vec<&str> = chunk
+                            .lines()
+                            .filter_map(|line| line.split_once(". "))
+                            .filter(|(n, _)| n.parse::<usize>().is_ok())
+                            .map(|(_, name)| name)
+                            .collect();
+                        items.push(serde_json::json!({
+                            "segment": number,
+                            "relations": [
+                                {"source": names[0], "target": names[1], "relation_type": "related_to", "confidence": 0.8},
+                                {"source": names[0], "target": "外人", "relation_type": "related_to", "confidence": 0.8}
+                            ]
+                        }));
+                    }
+
+                    tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    let response = serde_json::to_string(&items).unwrap();
+                    Ok::<_, warp::Rejection>(warp::reply::json(&serde_json::json!({ "response": response })))
+                }
+            });
+
+        let (addr, server) = warp::serve(generate).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        (addr, requests_out, peak_out)
+    }
+
+    fn batch_extractor(addr: std::net::SocketAddr, budget: usize, concurrency: usize) -> EntityRelationExtractor {
+        EntityRelationExtractor::with_config(RelationExtractorConfig {
+            ollama_url: format!("http://{}", addr),
+            batch_max_prompt_tokens: budget,
+            batch_concurrency: concurrency,
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_batch_relations_attributed_to_segments() {
+        use std::sync::atomic::Ordering;
+
+        let (addr, requests, _) = spawn_batch_server();
+        let extractor = batch_extractor(addr, 10_000, 2);
+
+        let segments = vec![
+            segment("小明在北京", &["小明", "北京"]),
+            segment("只有咖啡", &["咖啡"]),
+            segment("坏掉的输出", &["手机", "电脑"]),
+            segment("小红在上海", &["小红", "上海"]),
+        ];
+        let results = extractor.extract_relations_slm_batch(&segments).await.unwrap();
+
+        // One prompt for the whole batch
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert_eq!(results.len(), 4);
+
+        let pairs = |i: usize| -> Vec<(String, String)> {
+            results[i].iter().map(|r| (r.source.clone(), r.target.clone())).collect()
+        };
+        assert_eq!(pairs(0), vec![("小明".to_string(), "北京".to_string())]);
+        // Not sent: fewer than two entities
+        assert!(results[1].is_empty());
+        // Malformed segment skipped without failing the batch
+        assert!(results[2].is_empty());
+        assert_eq!(pairs(3), vec![("小红".to_string(), "上海".to_string())]);
+        assert_eq!(results[3][0].relation_type, RelationType::RelatedTo);
+    }
+
+    #[tokio::test]
+    async fn test_batch_respects_token_budget_and_concurrency() {
+        use std::sync::atomic::Ordering;
+
+        let (addr, requests, peak) = spawn_batch_server();
+        // Small budget: every segment needs its own prompt
+        let extractor = batch_extractor(addr, 10, 2);
+
+        let segments: Vec<RelationSegment> = (0..6)
+            .map(|i| segment(&format!("事件{}", i), &[&format!("甲{}", i), &format!("乙{}", i)]))
+            .collect();
+        let results = extractor.extract_relations_slm_batch(&segments).await.unwrap();
+
+        assert_eq!(requests.load(Ordering::SeqCst), 6);
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+        for (i, relations) in results.iter().enumerate() {
+            assert_eq!(relations.len(), 1);
+            assert_eq!(relations[0].source, format!("甲{}", i));
+            assert_eq!(relations[0].target, format!("乙{}", i));
+        }
+    }
+
+    #[test]
+    fn test_plan_batches_packs_within_budget() {
+        let segments = vec![
+            segment("一", &["甲", "乙"]),
+            segment("二", &["甲"]),
+            segment("三", &["丙", "丁"]),
+            segment("四", &["戊", "己"]),
+        ];
+        let one = crate::http_api::estimate_tokens(&EntityRelationExtractor::render_segment(1, &segments[0]));
+
+        let extractor = EntityRelationExtractor::with_config(RelationExtractorConfig {
+            batch_max_prompt_tokens: one * 2,
+            ..Default::default()
+        });
+        assert_eq!(extractor.plan_batches(&segments), vec![vec![0, 2], vec![3]]);
+
+        // Oversized segments still get a batch of their own
+        let extractor = EntityRelationExtractor::with_config(RelationExtractorConfig {
+            batch_max_prompt_tokens: 1,
+            ..Default::default()
+        });
+        assert_eq!(extractor.plan_batches(&segments), vec![vec![0], vec![2], vec![3]]);
+    }
+
+    #[test]
+    fn test_count_strength_saturates_toward_one() {
+        let config = RelationExtractorConfig::default();
+        let strengths: Vec<f64> =
+            [1, 2, 5, 20, 1000].iter().map(|&n| config.count_strength(n)).collect();
+
+        assert!((strengths[0] - 0.25).abs() < 1e-9);
+        assert!(strengths.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(strengths.iter().all(|s| (0.0..1.0).contains(s)));
+        assert!(strengths[4] > 0.99);
+        assert_eq!(config.count_strength(3), 0.5);
+        assert_eq!(config.count_strength(0), 0.0);
+        assert!(config.count_strength(1) >= config.min_strength_threshold);
+    }
+
+    #[test]
+    fn test_strength_saturation_from_env_value() {
+        assert_eq!(RelationExtractorConfig::from_values(None).unwrap().strength_saturation, 3.0);
+        assert_eq!(RelationExtractorConfig::from_values(Some(" ")).unwrap().strength_saturation, 3.0);
+        assert_eq!(RelationExtractorConfig::from_values(Some("10")).unwrap().strength_saturation, 10.0);
+        for invalid in ["ten", "0", "-2", "NaN"] {
+            assert!(
+                matches!(RelationExtractorConfig::from_values(Some(invalid)), Err(DirSoulError::Config(_))),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_count_strength_is_comparable_across_pairs() {
+        let config = RelationExtractorConfig {
+            strength_saturation: 10.0,
+            ..Default::default()
+        };
+        // A pair seen twice as often is always stronger, whatever the pair
+        let (rare, frequent) = (config.count_strength(4), config.count_strength(8));
+        assert!(frequent > rare);
+        assert_eq!(config.count_strength(4), rare);
+
+        // A smaller k saturates faster
+        assert!(RelationExtractorConfig::default().count_strength(4) > rare);
+    }
+
+    #[test]
+    fn test_config_rejects_non_positive_strength_saturation() {
+        for strength_saturation in [0.0, -1.0, f64::NAN] {
+            let config = RelationExtractorConfig {
+                strength_saturation,
+                ..Default::default()
+            };
+            assert!(config.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_config_rejects_zero_batch_concurrency() {
+        let config = RelationExtractorConfig {
+            batch_concurrency: 0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_slm_extraction_goes_through_provider() {
+        let provider = Arc::new(MockProvider::new().with_reply(
+            "结果：\n[{\"source\": \"小明\", \"target\": \"北京\", \"relation_type\": \"located_at\", \"confidence\": 0.9}]",
+        ));
+        let extractor = EntityRelationExtractor::new().with_provider(provider.clone());
+
+        let entities = vec![test_entity("小明", "person"), test_entity("北京", "place")];
+        let relations = extractor.extract_relations_slm("小明在北京", &entities).await.unwrap();
+        assert_eq!(relations.len(), 1);
+        assert_eq!(relations[0].source, "小明");
+        assert_eq!(relations[0].relation_type, RelationType::LocatedAt);
+
+        let calls = provider.calls();
+        assert_eq!(calls.len(), 1);
+        assert!(calls[0].prompt().contains("文本：小明在北京"));
+        assert_eq!(calls[0].options, GenerateOptions::new(0.3, 500));
     }
 }
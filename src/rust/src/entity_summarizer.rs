@@ -17,8 +17,9 @@ use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 use uuid::Uuid;
 
+use crate::entity_attribute_extractor::TENTATIVE_ATTRIBUTES_KEY;
 use crate::error::{DirSoulError, Result};
-use crate::models::Entity;
+use crate::models::{rank_entities_by_importance, Entity, EntityImportanceConfig};
 
 /// In-memory cache for entity summaries
 ///
@@ -229,7 +230,11 @@ impl EntitySummarizer {
             if let Some(obj) = attrs.as_object() {
                 if !obj.is_empty() {
                     context.push_str("属性:\n");
-                    for (key, value) in obj {
+                    // Tentative attributes are unconfirmed guesses
+                    for (key, value) in obj
+                        .iter()
+                        .filter(|(key, _)| key.as_str() != TENTATIVE_ATTRIBUTES_KEY)
+                    {
                         context.push_str(&format!("  - {}: {}\n", key, value));
                     }
                 }
@@ -329,6 +334,42 @@ impl EntitySummarizer {
         Ok(results)
     }
 
+    /// Summarize a user's `limit` most important entities first
+    ///
+    /// Entities are ranked by `entity_importance`, so recently active ones
+    /// are summarized before stale high-frequency ones.
+    ///
+    /// # Arguments
+    /// * `conn` - Database connection
+    /// * `uid` - User whose entities to summarize
+    /// * `limit` - Maximum number of entities to summarize
+    /// * `config` - Importance weights
+    ///
+    /// # Returns
+    /// Entity IDs in importance order with their summaries
+    pub async fn summarize_most_important(
+        &self,
+        conn: &mut PgConnection,
+        uid: &str,
+        limit: usize,
+        config: &EntityImportanceConfig,
+    ) -> Result<Vec<(Uuid, String)>> {
+        use crate::schema::entities::dsl::*;
+
+        config.validate()?;
+        let candidates = entities.filter(user_id.eq(uid)).load::<Entity>(conn)?;
+        let ranked = rank_entities_by_importance(candidates, chrono::Utc::now(), config);
+
+        let mut results = Vec::new();
+        for (entity, _) in ranked.into_iter().take(limit) {
+            match self.generate_summary(conn, entity.entity_id).await {
+                Ok(summary) => results.push((entity.entity_id, summary)),
+                Err(e) => eprintln!("Failed to generate summary for {}: {}", entity.entity_id, e),
+            }
+        }
+        Ok(results)
+    }
+
     /// Invalidate cached summary for an entity
     ///
     /// Call this when entity changes significantly.
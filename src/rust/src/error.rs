@@ -41,7 +41,89 @@ pub enum DirSoulError {
 
     #[error("权限拒绝: {0}")]
     PermissionDenied(String),
+
+    #[error("未认证: {0}")]
+    Unauthorized(String),
+}
+
+impl DirSoulError {
+    /// 是否为可重试的瞬时故障（数据库不可达、外部服务失败、超时）
+    ///
+    /// 输入错误、未找到、权限拒绝等重试也不会成功，返回 false。
+    pub fn retryable(&self) -> bool {
+        use diesel::result::{DatabaseErrorKind, Error as DieselError};
+
+        match self {
+            DirSoulError::DatabaseConnection(_)
+            | DirSoulError::ExternalError(_)
+            | DirSoulError::PluginTimeout(_) => true,
+            DirSoulError::Database(e) => matches!(
+                e,
+                DieselError::DatabaseError(
+                    DatabaseErrorKind::ClosedConnection
+                        | DatabaseErrorKind::UnableToSendCommand
+                        | DatabaseErrorKind::SerializationFailure,
+                    _
+                )
+            ),
+            DirSoulError::HttpReqwest(e) => {
+                e.is_timeout()
+                    || e.is_connect()
+                    || e.status().map_or(false, |status| status.is_server_error())
+            }
+            DirSoulError::Io(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::ConnectionRefused
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+            ),
+            DirSoulError::Serialization(_)
+            | DirSoulError::Encryption(_)
+            | DirSoulError::Config(_)
+            | DirSoulError::NotFound(_)
+            | DirSoulError::Plugin(_)
+            | DirSoulError::PluginNotFound(_)
+            | DirSoulError::PermissionDenied(_)
+            | DirSoulError::Unauthorized(_) => false,
+        }
+    }
 }
 
 /// DirSoul 统一 Result 类型
 pub type Result<T> = std::result::Result<T, DirSoulError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transient_failures_are_retryable() {
+        let closed = diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::ClosedConnection,
+            Box::new("server closed the connection".to_string()),
+        );
+        assert!(DirSoulError::from(closed).retryable());
+        assert!(DirSoulError::ExternalError("ollama down".to_string()).retryable());
+        assert!(DirSoulError::PluginTimeout("slow".to_string()).retryable());
+        assert!(DirSoulError::from(std::io::Error::from(std::io::ErrorKind::TimedOut)).retryable());
+    }
+
+    #[test]
+    fn test_input_failures_are_not_retryable() {
+        let parse = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        assert!(!DirSoulError::from(parse).retryable());
+        assert!(!DirSoulError::Config("Invalid start_date".to_string()).retryable());
+        assert!(!DirSoulError::NotFound("no concept".to_string()).retryable());
+        assert!(!DirSoulError::PermissionDenied("read".to_string()).retryable());
+        assert!(!DirSoulError::Unauthorized("no token".to_string()).retryable());
+        assert!(!DirSoulError::from(diesel::result::Error::NotFound).retryable());
+
+        let unique = diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UniqueViolation,
+            Box::new("duplicate key".to_string()),
+        );
+        assert!(!DirSoulError::from(unique).retryable());
+    }
+}
@@ -8,14 +8,23 @@
 //! - 多种聚合类型（SUM/COUNT/AVG）
 //! - 高效的数据库查询
 
-use chrono::{DateTime, Datelike, Duration, Local, Timelike, Utc};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Timelike, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use tracing::debug;
 
-use crate::error::Result;
+use crate::data_lifecycle::live_memory_ids;
+use crate::error::{DirSoulError, Result};
+use crate::event_extractor::ActionNormalizer;
+use crate::models::EventMemory;
 use crate::schema::event_memories;
 
+/// 时间序列最多允许的桶数（防止 hour 粒度配合长时间范围产生巨大响应）
+pub const MAX_SERIES_BUCKETS: usize = 2000;
+
 /// 聚合类型
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AggregationType {
@@ -27,6 +36,192 @@ pub enum AggregationType {
     Avg,
 }
 
+impl FromStr for AggregationType {
+    type Err = DirSoulError;
+
+    /// 解析 "sum" / "count" / "avg"（不区分大小写）
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "sum" => Ok(AggregationType::Sum),
+            "count" => Ok(AggregationType::Count),
+            "avg" => Ok(AggregationType::Avg),
+            other => Err(DirSoulError::Config(format!(
+                "Invalid agg_type: {}. Expected: sum, count, or avg",
+                other
+            ))),
+        }
+    }
+}
+
+/// 分组维度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    /// 按动作分组
+    Action,
+    /// 按目标分组
+    Target,
+}
+
+impl FromStr for GroupBy {
+    type Err = DirSoulError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "action" => Ok(GroupBy::Action),
+            "target" => Ok(GroupBy::Target),
+            other => Err(DirSoulError::Config(format!(
+                "Invalid group_by: {}. Expected: action or target",
+                other
+            ))),
+        }
+    }
+}
+
+/// 时间序列的桶粒度（按本地时区对齐）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeBucket {
+    /// 小时
+    Hour,
+    /// 天
+    Day,
+    /// 周（周一开始）
+    Week,
+}
+
+impl FromStr for TimeBucket {
+    type Err = DirSoulError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "hour" => Ok(TimeBucket::Hour),
+            "day" => Ok(TimeBucket::Day),
+            "week" => Ok(TimeBucket::Week),
+            other => Err(DirSoulError::Config(format!(
+                "Invalid bucket: {}. Expected: hour, day, or week",
+                other
+            ))),
+        }
+    }
+}
+
+impl TimeBucket {
+    /// 时间点所在桶的起点
+    pub fn bucket_start(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        let local = timestamp.with_timezone(&Local);
+        let naive = match self {
+            TimeBucket::Hour => local.date_naive().and_hms_opt(local.hour(), 0, 0).unwrap(),
+            TimeBucket::Day => local.date_naive().and_hms_opt(0, 0, 0).unwrap(),
+            TimeBucket::Week => {
+                let monday = local.date_naive()
+                    - Duration::days(local.weekday().num_days_from_monday() as i64);
+                monday.and_hms_opt(0, 0, 0).unwrap()
+            }
+        };
+        // DST 间隙中不存在的本地时间退回到 UTC 解释
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|t| t.with_timezone(&Utc))
+            .unwrap_or_else(|| Utc.from_utc_datetime(&naive))
+    }
+
+    /// 下一个桶的起点
+    fn next(&self, bucket_start: DateTime<Utc>) -> DateTime<Utc> {
+        let step = match self {
+            TimeBucket::Hour => Duration::hours(1),
+            TimeBucket::Day => Duration::days(1),
+            TimeBucket::Week => Duration::weeks(1),
+        };
+        // 跨 DST 时加上步长后重新对齐
+        self.bucket_start(bucket_start + step + Duration::minutes(90).min(step / 2))
+    }
+}
+
+/// 时间序列中的一个点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketPoint {
+    /// 桶起点
+    pub bucket_start: DateTime<Utc>,
+    /// 该桶的聚合结果
+    pub result: AggregationResult,
+}
+
+/// 聚合查询：标量、分组或时间序列
+#[derive(Debug, Clone)]
+pub struct AggregateQuery {
+    /// 时间范围
+    pub time_range: TimeRange,
+    /// 聚合类型
+    pub agg_type: AggregationType,
+    /// 分组维度（与 bucket 互斥）
+    pub group_by: Option<GroupBy>,
+    /// 时间桶（与 group_by 互斥）
+    pub bucket: Option<TimeBucket>,
+    /// 按动作分组前的动词规范化（None 时按原始动作分组）
+    pub action_normalizer: Option<ActionNormalizer>,
+}
+
+impl AggregateQuery {
+    /// 从字符串参数构造并校验查询
+    ///
+    /// 未知的 range / agg_type / group_by / bucket 返回 `DirSoulError::Config`
+    pub fn parse(
+        range: &str,
+        agg_type: &str,
+        group_by: Option<&str>,
+        bucket: Option<&str>,
+    ) -> Result<Self> {
+        let query = Self {
+            time_range: range.parse::<TimeRange>()?,
+            agg_type: agg_type.parse::<AggregationType>()?,
+            group_by: group_by.map(str::parse::<GroupBy>).transpose()?,
+            bucket: bucket.map(str::parse::<TimeBucket>).transpose()?,
+            action_normalizer: None,
+        };
+
+        if query.group_by.is_some() && query.bucket.is_some() {
+            return Err(DirSoulError::Config(
+                "group_by and bucket cannot be combined".to_string(),
+            ));
+        }
+
+        Ok(query)
+    }
+
+    /// 按动作分组时先用 `normalizer` 合并同义动词（如 "吃了" 与 "eat"）
+    pub fn with_action_normalizer(mut self, normalizer: ActionNormalizer) -> Self {
+        self.action_normalizer = Some(normalizer);
+        self
+    }
+}
+
+/// 聚合查询的输出
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AggregateOutput {
+    /// 单个值
+    Scalar {
+        /// 聚合结果
+        result: AggregationResult,
+    },
+    /// 每组一个值
+    Grouped {
+        /// 分组维度
+        group_by: GroupBy,
+        /// 组名 → 聚合结果
+        groups: BTreeMap<String, AggregationResult>,
+    },
+    /// 时间序列（空桶也会出现，值为 0）
+    Series {
+        /// 桶粒度
+        bucket: TimeBucket,
+        /// 按时间排序的点
+        points: Vec<BucketPoint>,
+    },
+}
+
 /// 时间范围
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TimeRange {
@@ -48,6 +243,35 @@ pub enum TimeRange {
     Custom(DateTime<Utc>, DateTime<Utc>),
 }
 
+impl FromStr for TimeRange {
+    type Err = DirSoulError;
+
+    /// 解析 "today"、"yesterday"、"this_week"、"last_week"、"this_month"、
+    /// "last_month" 或 "Nd"（最近 N 天，如 "30d"）
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        match s.as_str() {
+            "today" => Ok(TimeRange::Today),
+            "yesterday" => Ok(TimeRange::Yesterday),
+            "this_week" => Ok(TimeRange::ThisWeek),
+            "last_week" => Ok(TimeRange::LastWeek),
+            "this_month" => Ok(TimeRange::ThisMonth),
+            "last_month" => Ok(TimeRange::LastMonth),
+            other => other
+                .strip_suffix('d')
+                .and_then(|n| n.parse::<i64>().ok())
+                .filter(|n| *n > 0)
+                .map(TimeRange::LastDays)
+                .ok_or_else(|| {
+                    DirSoulError::Config(format!(
+                        "Invalid range: {}. Expected: today, yesterday, this_week, last_week, this_month, last_month, or Nd",
+                        other
+                    ))
+                }),
+        }
+    }
+}
+
 /// 聚合结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AggregationResult {
@@ -111,6 +335,7 @@ impl EventAggregator {
 
         let mut query = event_memories::table
             .filter(event_memories::user_id.eq(user_id))
+            .filter(event_memories::memory_id.eq_any(live_memory_ids(user_id)))
             .filter(event_memories::timestamp.ge(start))
             .filter(event_memories::timestamp.le(end))
             .into_boxed();
@@ -147,6 +372,7 @@ impl EventAggregator {
                 // 重新构造查询以获取 count
                 let count_query = event_memories::table
                     .filter(event_memories::user_id.eq(user_id))
+                    .filter(event_memories::memory_id.eq_any(live_memory_ids(user_id)))
                     .filter(event_memories::timestamp.ge(start))
                     .filter(event_memories::timestamp.le(end))
                     .into_boxed();
@@ -184,6 +410,7 @@ impl EventAggregator {
                 // 重新构造查询以获取 count
                 let count_query = event_memories::table
                     .filter(event_memories::user_id.eq(user_id))
+                    .filter(event_memories::memory_id.eq_any(live_memory_ids(user_id)))
                     .filter(event_memories::timestamp.ge(start))
                     .filter(event_memories::timestamp.le(end))
                     .filter(event_memories::quantity.is_not_null())
@@ -212,6 +439,125 @@ impl EventAggregator {
         }
     }
 
+    /// 加载时间范围内的事件（排除已软删除的原始记忆）
+    pub fn load_events(
+        conn: &mut PgConnection,
+        user_id: &str,
+        action: Option<&str>,
+        target: Option<&str>,
+        time_range: &TimeRange,
+    ) -> Result<Vec<EventMemory>> {
+        let (start, end) = Self::parse_time_range(time_range);
+
+        let mut query = event_memories::table
+            .filter(event_memories::user_id.eq(user_id))
+            .filter(event_memories::memory_id.eq_any(live_memory_ids(user_id)))
+            .filter(event_memories::timestamp.ge(start))
+            .filter(event_memories::timestamp.le(end))
+            .into_boxed();
+
+        if let Some(a) = action {
+            query = query.filter(event_memories::action.eq(a));
+        }
+
+        if let Some(t) = target {
+            query = query.filter(event_memories::target.eq(t));
+        }
+
+        Ok(query.order(event_memories::timestamp.asc()).load(conn)?)
+    }
+
+    /// 在内存中聚合已加载的事件
+    ///
+    /// 语义与 `aggregate_events` 一致：Sum 把 NULL quantity 当作 0，
+    /// Avg 只统计有 quantity 的事件。
+    pub fn aggregate_loaded(events: &[&EventMemory], agg_type: AggregationType) -> AggregationResult {
+        match agg_type {
+            AggregationType::Count => AggregationResult {
+                agg_type,
+                value: events.len() as f64,
+                count: events.len() as i64,
+            },
+            AggregationType::Sum => AggregationResult {
+                agg_type,
+                value: events.iter().filter_map(|e| e.quantity).sum(),
+                count: events.len() as i64,
+            },
+            AggregationType::Avg => {
+                let quantities: Vec<f64> = events.iter().filter_map(|e| e.quantity).collect();
+                let value = if quantities.is_empty() {
+                    0.0
+                } else {
+                    quantities.iter().sum::<f64>() / quantities.len() as f64
+                };
+                AggregationResult {
+                    agg_type,
+                    value,
+                    count: quantities.len() as i64,
+                }
+            }
+        }
+    }
+
+    /// 执行聚合查询：根据参数返回标量、分组或时间序列
+    pub fn run_query(events: &[EventMemory], query: &AggregateQuery) -> Result<AggregateOutput> {
+        if let Some(group_by) = query.group_by {
+            let mut buckets: BTreeMap<String, Vec<&EventMemory>> = BTreeMap::new();
+            for event in events {
+                let key = match group_by {
+                    GroupBy::Action => match &query.action_normalizer {
+                        Some(normalizer) => normalizer.normalize(&event.action),
+                        None => event.action.clone(),
+                    },
+                    GroupBy::Target => event.target.clone(),
+                };
+                buckets.entry(key).or_default().push(event);
+            }
+            let groups = buckets
+                .into_iter()
+                .map(|(key, group)| (key, Self::aggregate_loaded(&group, query.agg_type)))
+                .collect();
+            return Ok(AggregateOutput::Grouped { group_by, groups });
+        }
+
+        if let Some(bucket) = query.bucket {
+            let (start, end) = Self::parse_time_range(&query.time_range);
+            let mut series: BTreeMap<DateTime<Utc>, Vec<&EventMemory>> = BTreeMap::new();
+
+            let mut cursor = bucket.bucket_start(start);
+            while cursor <= end {
+                if series.len() >= MAX_SERIES_BUCKETS {
+                    return Err(DirSoulError::Config(format!(
+                        "Too many buckets (max {}); use a coarser bucket or shorter range",
+                        MAX_SERIES_BUCKETS
+                    )));
+                }
+                series.insert(cursor, Vec::new());
+                cursor = bucket.next(cursor);
+            }
+            for event in events {
+                series
+                    .entry(bucket.bucket_start(event.timestamp))
+                    .or_default()
+                    .push(event);
+            }
+
+            let points = series
+                .into_iter()
+                .map(|(bucket_start, group)| BucketPoint {
+                    bucket_start,
+                    result: Self::aggregate_loaded(&group, query.agg_type),
+                })
+                .collect();
+            return Ok(AggregateOutput::Series { bucket, points });
+        }
+
+        let all: Vec<&EventMemory> = events.iter().collect();
+        Ok(AggregateOutput::Scalar {
+            result: Self::aggregate_loaded(&all, query.agg_type),
+        })
+    }
+
     /// 解析时间范围为 DateTime 范围
     fn parse_time_range(range: &TimeRange) -> (DateTime<Utc>, DateTime<Utc>) {
         let now = Local::now();
@@ -357,4 +703,116 @@ mod tests {
         let serialized = serde_json::to_string(&agg).unwrap();
         assert_eq!(serialized, "\"Sum\"");
     }
+
+    fn event_at(timestamp: DateTime<Utc>, action: &str, target: &str, quantity: Option<f64>) -> EventMemory {
+        EventMemory {
+            event_id: uuid::Uuid::new_v4(),
+            memory_id: uuid::Uuid::new_v4(),
+            user_id: "user123".to_string(),
+            timestamp,
+            actor: None,
+            action: action.to_string(),
+            target: target.to_string(),
+            quantity,
+            unit: None,
+            confidence: 1.0,
+            extractor_version: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_aggregate_query_validates_values() {
+        let query = AggregateQuery::parse("30d", "Sum", Some("action"), None).unwrap();
+        assert!(matches!(query.time_range, TimeRange::LastDays(30)));
+        assert_eq!(query.agg_type, AggregationType::Sum);
+        assert_eq!(query.group_by, Some(GroupBy::Action));
+
+        assert!(AggregateQuery::parse("30d", "median", None, None).is_err());
+        assert!(AggregateQuery::parse("30d", "sum", Some("color"), None).is_err());
+        assert!(AggregateQuery::parse("forever", "sum", None, None).is_err());
+        assert!(AggregateQuery::parse("30d", "sum", Some("action"), Some("day")).is_err());
+    }
+
+    #[test]
+    fn test_run_query_scalar_and_grouped() {
+        let now = Utc::now();
+        let events = vec![
+            event_at(now, "喝", "咖啡", Some(1.0)),
+            event_at(now, "喝", "咖啡", Some(2.0)),
+            event_at(now, "吃", "苹果", None),
+        ];
+
+        let query = AggregateQuery::parse("7d", "avg", None, None).unwrap();
+        match EventAggregator::run_query(&events, &query).unwrap() {
+            AggregateOutput::Scalar { result } => {
+                assert_eq!(result.value, 1.5);
+                assert_eq!(result.count, 2);
+            }
+            other => panic!("expected scalar, got {:?}", other),
+        }
+
+        let query = AggregateQuery::parse("7d", "count", Some("target"), None).unwrap();
+        match EventAggregator::run_query(&events, &query).unwrap() {
+            AggregateOutput::Grouped { groups, .. } => {
+                assert_eq!(groups["咖啡"].value, 2.0);
+                assert_eq!(groups["苹果"].value, 1.0);
+            }
+            other => panic!("expected grouped, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_run_query_groups_normalized_actions() {
+        let now = Utc::now();
+        let events = vec![
+            event_at(now, "吃了", "苹果", Some(1.0)),
+            event_at(now, "eat", "apple", Some(2.0)),
+            event_at(now, "冥想", "", None),
+        ];
+
+        let query = AggregateQuery::parse("7d", "count", Some("action"), None).unwrap();
+        match EventAggregator::run_query(&events, &query).unwrap() {
+            AggregateOutput::Grouped { groups, .. } => assert_eq!(groups.len(), 3),
+            other => panic!("expected grouped, got {:?}", other),
+        }
+
+        let query = query.with_action_normalizer(ActionNormalizer::new());
+        match EventAggregator::run_query(&events, &query).unwrap() {
+            AggregateOutput::Grouped { groups, .. } => {
+                assert_eq!(groups.len(), 2);
+                assert_eq!(groups["eat"].value, 2.0);
+                assert_eq!(groups["冥想"].value, 1.0);
+            }
+            other => panic!("expected grouped, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_run_query_series_fills_empty_buckets() {
+        let today = TimeBucket::Day.bucket_start(Utc::now());
+        let events = vec![
+            event_at(today + Duration::hours(1), "喝", "咖啡", Some(1.0)),
+            event_at(today - Duration::days(2) + Duration::hours(1), "喝", "咖啡", Some(1.0)),
+            event_at(today - Duration::days(2) + Duration::hours(2), "喝", "咖啡", Some(1.0)),
+        ];
+
+        let query = AggregateQuery::parse("3d", "count", None, Some("day")).unwrap();
+        let points = match EventAggregator::run_query(&events, &query).unwrap() {
+            AggregateOutput::Series { points, .. } => points,
+            other => panic!("expected series, got {:?}", other),
+        };
+
+        let count_at = |start: DateTime<Utc>| {
+            points
+                .iter()
+                .find(|p| p.bucket_start == start)
+                .map(|p| p.result.value)
+        };
+        assert_eq!(count_at(today), Some(1.0));
+        assert_eq!(count_at(TimeBucket::Day.bucket_start(today - Duration::days(1))), Some(0.0));
+        assert_eq!(count_at(TimeBucket::Day.bucket_start(today - Duration::days(2))), Some(2.0));
+        assert!(points.len() >= 4);
+        assert!(points.windows(2).all(|w| w[0].bucket_start < w[1].bucket_start));
+    }
 }
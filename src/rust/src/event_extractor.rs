@@ -22,13 +22,17 @@
 //! ```
 
 use chrono::{DateTime, Datelike, Duration, Local, Utc};
+use futures_util::stream::{self, Stream, StreamExt};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 use crate::Result;
-use crate::prompt_manager::PromptManager;
+use crate::lexicon::LexiconSet;
+use crate::llm_provider::LlmGovernor;
+use crate::models::{EVENT_NEGATED_KEY, EVENT_RAW_SPAN_KEY, EVENT_SENTIMENT_KEY};
+use crate::prompt_manager::{PromptManager, PromptTask};
 
 /// 提取的事件结构
 ///
@@ -49,6 +53,9 @@ pub struct ExtractedEvent {
     pub confidence: f64,
     /// 提取方法（rule/slm）
     pub method: String,
+    /// 附加信号（否定、情感、原文位置等），写入事件的 metadata
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
 }
 
 impl ExtractedEvent {
@@ -62,6 +69,7 @@ impl ExtractedEvent {
             actor: None,
             confidence: 0.5,
             method: "rule".to_string(),
+            metadata: None,
         }
     }
 
@@ -89,6 +97,292 @@ impl ExtractedEvent {
         self.method = method;
         self
     }
+
+    /// 添加一项 metadata（同名项被覆盖）
+    pub fn with_metadata_entry(mut self, key: &str, value: serde_json::Value) -> Self {
+        let metadata = self.metadata.get_or_insert_with(|| serde_json::json!({}));
+        if let Some(map) = metadata.as_object_mut() {
+            map.insert(key.to_string(), value);
+        }
+        self
+    }
+
+    /// 标记事件被否定（"没吃苹果"）
+    pub fn with_negated(self, negated: bool) -> Self {
+        self.with_metadata_entry(EVENT_NEGATED_KEY, serde_json::Value::Bool(negated))
+    }
+
+    /// 记录事件在原文中的位置（`span` 为 `text` 的字节区间，按字符偏移存储）
+    pub fn with_raw_span(self, text: &str, span: std::ops::Range<usize>) -> Self {
+        let start = text[..span.start].chars().count();
+        let end = start + text[span].chars().count();
+        self.with_metadata_entry(EVENT_RAW_SPAN_KEY, serde_json::json!({"start": start, "end": end}))
+    }
+}
+
+/// 第一人称标记（出现即视为用户本人参与）
+const FIRST_PERSON_MARKERS: &[&str] = &["我", "咱", "本人", "自己"];
+
+/// 第三人称标记（无第一人称标记时视为他人的事件）
+const THIRD_PERSON_MARKERS: &[&str] = &["他", "她", "它", "别人", "大家", "对方"];
+
+/// 含人称字但不是人称代词的词（"其他人"、"弹吉他"），匹配标记前先排除
+const NON_PRONOUN_WORDS: &[&str] = &["其他", "其它", "其她", "吉他"];
+
+/// 句子分隔符，执行者按事件所在的句子推断
+const SENTENCE_DELIMITERS: &[char] = &['。', '！', '？', '；', '!', '?', ';', '\n'];
+
+/// 执行者推断配置
+///
+/// 中文常省略主语（"吃了苹果"即"我吃了苹果"）。启用后，抽取器未给出执行者且
+/// 原文为第一人称时，以 `self_identifier`（未设置时为记忆的 `user_id`）作为执行者。
+/// 已抽取出的执行者从不覆盖。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActorInference {
+    /// 是否启用推断（默认：关闭）
+    pub enabled: bool,
+    /// 代表用户本人的执行者名称（默认：使用 `user_id`）
+    pub self_identifier: Option<String>,
+}
+
+impl ActorInference {
+    /// 启用推断，以 `user_id` 作为执行者
+    pub fn enabled() -> Self {
+        Self {
+            enabled: true,
+            self_identifier: None,
+        }
+    }
+
+    /// 设置代表用户本人的执行者名称
+    pub fn with_self_identifier(mut self, self_identifier: String) -> Self {
+        self.self_identifier = Some(self_identifier);
+        self
+    }
+
+    /// 判断原文是否为第一人称
+    ///
+    /// 含第一人称标记，或既无第一人称也无第三人称标记（主语省略）时返回 true。
+    /// "其他"、"吉他" 等词中的人称字不算标记。
+    pub fn is_first_person(text: &str) -> bool {
+        let text = NON_PRONOUN_WORDS
+            .iter()
+            .fold(text.to_string(), |text, word| text.replace(word, " "));
+        if FIRST_PERSON_MARKERS.iter().any(|m| text.contains(m)) {
+            return true;
+        }
+        !THIRD_PERSON_MARKERS.iter().any(|m| text.contains(m))
+    }
+
+    /// 取原文中包含第 `char_offset` 个字符的句子
+    ///
+    /// 一段原文可能描述多人的事件（"我吃了苹果。他去跑步"），推断只看事件所在的
+    /// 句子。未给出位置时返回整段原文。
+    pub fn sentence_at(text: &str, char_offset: Option<usize>) -> &str {
+        let Some(offset) = char_offset else {
+            return text;
+        };
+        let byte_offset = text
+            .char_indices()
+            .nth(offset)
+            .map(|(index, _)| index)
+            .unwrap_or(text.len());
+        let start = text[..byte_offset]
+            .rfind(SENTENCE_DELIMITERS)
+            .map(|index| index + text[index..].chars().next().map_or(0, char::len_utf8))
+            .unwrap_or(0);
+        let end = text[byte_offset..]
+            .find(SENTENCE_DELIMITERS)
+            .map(|index| byte_offset + index)
+            .unwrap_or(text.len());
+        &text[start..end]
+    }
+
+    /// 推断事件的执行者
+    ///
+    /// # 参数
+    /// * `actor` - 抽取器给出的执行者
+    /// * `text` - 原文
+    /// * `user_id` - 记忆所属用户
+    pub fn infer(&self, actor: Option<String>, text: &str, user_id: &str) -> Option<String> {
+        let explicit = actor.filter(|a| !a.trim().is_empty());
+        if explicit.is_some() || !self.enabled || !Self::is_first_person(text) {
+            return explicit;
+        }
+        Some(self.self_identifier.clone().unwrap_or_else(|| user_id.to_string()))
+    }
+}
+
+/// 批处理并发上限（8GB 内存优化）
+const MAX_BATCH_SIZE: usize = 5;
+
+/// 批量抽取的累计统计
+///
+/// 随每条进度一起更新，最后一条进度携带的即为最终汇总。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ExtractionTotals {
+    /// 输入文本总数
+    pub total: usize,
+    /// 已完成数量（成功 + 失败）
+    pub completed: usize,
+    /// 成功数量
+    pub succeeded: usize,
+    /// 失败数量
+    pub failed: usize,
+    /// 成功抽取的事件总数
+    pub events: usize,
+}
+
+impl ExtractionTotals {
+    /// 记录一条抽取结果
+    fn record(&mut self, result: &Result<Vec<ExtractedEvent>>) {
+        self.completed += 1;
+        match result {
+            Ok(events) => {
+                self.succeeded += 1;
+                self.events += events.len();
+            }
+            Err(_) => self.failed += 1,
+        }
+    }
+
+    /// 是否所有输入都已完成
+    pub fn is_finished(&self) -> bool {
+        self.completed == self.total
+    }
+}
+
+/// 单条文本的抽取进度
+///
+/// 按完成顺序产生，`index` 对应输入切片中的位置。
+#[derive(Debug)]
+pub struct ExtractionProgress {
+    /// 输入文本的下标
+    pub index: usize,
+    /// 该文本的抽取结果
+    pub result: Result<Vec<ExtractedEvent>>,
+    /// 截至当前的累计统计
+    pub totals: ExtractionTotals,
+}
+
+/// 默认动作词典：规范动作码 → 表层动词（中英文、时态变化）
+const DEFAULT_ACTION_LEXICON: &[(&str, &[&str])] = &[
+    ("eat", &["吃", "吃饭", "eat", "eats", "ate", "eaten", "eating"]),
+    ("drink", &["喝", "drink", "drinks", "drank", "drunk", "drinking"]),
+    ("buy", &["买", "购", "购买", "buy", "buys", "bought", "buying"]),
+    ("read", &["读", "阅读", "看书", "read", "reads", "reading"]),
+    ("run", &["跑", "跑步", "run", "runs", "ran", "running"]),
+    ("sleep", &["睡", "睡觉", "sleep", "sleeps", "slept", "sleeping"]),
+    ("exercise", &["运动", "锻炼", "exercise", "exercises", "exercised", "exercising"]),
+    ("study", &["学习", "study", "studies", "studied", "studying"]),
+    ("work", &["工作", "上班", "work", "works", "worked", "working"]),
+    ("pay", &["支付", "付款", "pay", "pays", "paid", "paying"]),
+];
+
+/// 中文动态助词（"吃了"、"吃过"、"吃着"），查表前剥离
+const ASPECT_PARTICLES: &[char] = &['了', '过', '着'];
+
+/// 动作规范化器
+///
+/// 将抽取器产生的表层动词（"吃"、"吃了"、"eat"、"ate"）映射为规范动作码（"eat"），
+/// 使聚合与模式检测不会把同一行为拆成多组。词典中没有的动词原样返回。
+///
+/// # 示例
+/// ```
+/// use dirsoul::event_extractor::ActionNormalizer;
+///
+/// let normalizer = ActionNormalizer::new();
+/// assert_eq!(normalizer.normalize("吃了"), "eat");
+/// assert_eq!(normalizer.normalize("Ate"), "eat");
+/// assert_eq!(normalizer.normalize("冥想"), "冥想");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionNormalizer {
+    /// 表层动词（小写）→ 规范动作码
+    lexicon: HashMap<String, String>,
+}
+
+impl ActionNormalizer {
+    /// 使用默认词典创建
+    pub fn new() -> Self {
+        let mut normalizer = Self::empty();
+        for (canonical, surfaces) in DEFAULT_ACTION_LEXICON {
+            normalizer = normalizer.with_mapping(canonical, surfaces.iter().copied());
+        }
+        normalizer
+    }
+
+    /// 创建空词典（所有动词原样返回）
+    pub fn empty() -> Self {
+        Self {
+            lexicon: HashMap::new(),
+        }
+    }
+
+    /// 从 TOML 词典加载（规范动作码 = [表层动词...]）
+    ///
+    /// ```toml
+    /// eat = ["吃", "ate"]
+    /// meditate = ["冥想", "打坐"]
+    /// ```
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        let table: HashMap<String, Vec<String>> = toml::from_str(content).map_err(|e| {
+            crate::DirSoulError::Config(format!("Invalid action lexicon: {}", e))
+        })?;
+
+        let mut normalizer = Self::empty();
+        for (canonical, surfaces) in table {
+            if canonical.trim().is_empty() {
+                return Err(crate::DirSoulError::Config(
+                    "Action lexicon contains an empty canonical action".to_string(),
+                ));
+            }
+            normalizer = normalizer.with_mapping(&canonical, surfaces);
+        }
+        Ok(normalizer)
+    }
+
+    /// 添加映射：`surfaces` 中的每个动词以及规范码本身都映射到 `canonical`
+    ///
+    /// 已存在的表层动词会被覆盖。
+    pub fn with_mapping<I, S>(mut self, canonical: &str, surfaces: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let canonical = canonical.trim().to_string();
+        self.lexicon.insert(canonical.to_lowercase(), canonical.clone());
+        for surface in surfaces {
+            self.lexicon
+                .insert(surface.as_ref().trim().to_lowercase(), canonical.clone());
+        }
+        self
+    }
+
+    /// 规范化单个动词
+    ///
+    /// 先按原词（去空白、小写）查表，再剥离末尾动态助词查表；都未命中时原样返回。
+    pub fn normalize(&self, action: &str) -> String {
+        let key = action.trim().to_lowercase();
+        if let Some(canonical) = self.lexicon.get(&key) {
+            return canonical.clone();
+        }
+
+        let stem = key.trim_end_matches(ASPECT_PARTICLES);
+        if !stem.is_empty() {
+            if let Some(canonical) = self.lexicon.get(stem) {
+                return canonical.clone();
+            }
+        }
+
+        action.to_string()
+    }
+}
+
+impl Default for ActionNormalizer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// 中文时间范围解析器
@@ -256,6 +550,8 @@ pub struct RuleExtractor {
     units: Vec<String>,
     /// 数词-数字映射
     number_map: HashMap<String, f64>,
+    /// 否定标记词典（紧邻动词之前出现时视为否定事件）
+    lexicon: LexiconSet,
 }
 
 impl RuleExtractor {
@@ -314,9 +610,16 @@ impl RuleExtractor {
                 "千".to_string(), "万".to_string(),
             ],
             number_map,
+            lexicon: LexiconSet::default(),
         }
     }
 
+    /// 使用指定的词典判断否定（与冲突检测共用同一份否定标记）
+    pub fn with_lexicon(mut self, lexicon: LexiconSet) -> Self {
+        self.lexicon = lexicon;
+        self
+    }
+
     /// 从文本中提取事件
     ///
     /// # 示例
@@ -339,6 +642,7 @@ impl RuleExtractor {
             let quantity_str = &caps[3];
             let unit = caps[4].to_string();
             let target = caps[5].trim().to_string();
+            let span = caps.get(0).map_or(0..0, |m| m.range());
 
             let quantity = self.parse_quantity(quantity_str)?;
 
@@ -347,6 +651,8 @@ impl RuleExtractor {
                     .with_quantity(quantity, unit)
                     .with_confidence(0.7) // 规则匹配的置信度
                     .with_method("rule".to_string())
+                    .with_negated(self.lexicon.ends_with_negation(&text[..span.start]))
+                    .with_raw_span(text, span)
             );
         }
 
@@ -358,11 +664,14 @@ impl RuleExtractor {
             if let Some(caps) = pattern2.captures(text) {
                 let action = self.normalize_action(&caps[1]);
                 let target = caps[3].trim().to_string();
+                let span = caps.get(0).map_or(0..0, |m| m.range());
 
                 events.push(
                     ExtractedEvent::new(action, target)
                         .with_confidence(0.5) // 无数量的置信度较低
                         .with_method("rule".to_string())
+                        .with_negated(self.lexicon.ends_with_negation(&text[..span.start]))
+                        .with_raw_span(text, span)
                 );
             }
         }
@@ -467,6 +776,8 @@ pub struct SlmExtractor {
     timeout_secs: u64,
     /// Prompt管理器（使用Mutex支持内部可变性）
     prompt_manager: Arc<Mutex<PromptManager>>,
+    /// 全局 LLM 并发上限（与对话、嵌入共享），未设置时不限制
+    governor: Option<LlmGovernor>,
 }
 
 impl SlmExtractor {
@@ -496,6 +807,7 @@ impl SlmExtractor {
             rule_fallback: RuleExtractor::new(),
             timeout_secs: 120,
             prompt_manager,
+            governor: None,
         })
     }
 
@@ -504,6 +816,26 @@ impl SlmExtractor {
         Self::new(None, None).await
     }
 
+    /// 使用指定的 PromptManager（模板目录与系统提示词）
+    pub fn with_prompt_manager(mut self, prompt_manager: PromptManager) -> Self {
+        self.prompt_manager = Arc::new(Mutex::new(prompt_manager));
+        self
+    }
+
+    /// 兜底规则抽取使用指定的词典判断否定
+    pub fn with_lexicon(mut self, lexicon: LexiconSet) -> Self {
+        self.rule_fallback = self.rule_fallback.with_lexicon(lexicon);
+        self
+    }
+
+    /// 共享全局 LLM 并发上限
+    ///
+    /// 每次 SLM 请求都需先取得许可，批量抽取与对话、嵌入共用同一上限。
+    pub fn with_governor(mut self, governor: LlmGovernor) -> Self {
+        self.governor = Some(governor);
+        self
+    }
+
     /// 从文本中提取事件（SLM 优先）
     ///
     /// # 流程
@@ -539,8 +871,6 @@ impl SlmExtractor {
     ///
     /// 8GB 内存优化：限制批处理大小
     pub async fn extract_batch(&self, texts: &[String]) -> Result<Vec<Vec<ExtractedEvent>>> {
-        const MAX_BATCH_SIZE: usize = 5; // 限制批处理大小
-
         let mut results = Vec::with_capacity(texts.len());
 
         for chunk in texts.chunks(MAX_BATCH_SIZE) {
@@ -553,6 +883,48 @@ impl SlmExtractor {
         Ok(results)
     }
 
+    /// 流式批量提取事件
+    ///
+    /// 每条文本完成后立即产生一条 [`ExtractionProgress`]（按完成顺序，而非输入顺序），
+    /// 单条失败不会中断整批。最多同时处理 `MAX_BATCH_SIZE` 条。
+    ///
+    /// 取消：流是惰性的，丢弃流即会丢弃所有进行中的请求，不再发起新请求。
+    ///
+    /// # 示例
+    /// ```no_run
+    /// # use dirsoul::event_extractor::SlmExtractor;
+    /// # use futures_util::StreamExt;
+    /// # async fn example(texts: Vec<String>) -> dirsoul::Result<()> {
+    /// let extractor = SlmExtractor::default_config().await?;
+    /// let mut progress = Box::pin(extractor.extract_batch_stream(&texts));
+    /// while let Some(item) = progress.next().await {
+    ///     println!("{}/{}", item.totals.completed, item.totals.total);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract_batch_stream<'a>(
+        &'a self,
+        texts: &'a [String],
+    ) -> impl Stream<Item = ExtractionProgress> + 'a {
+        let totals = ExtractionTotals {
+            total: texts.len(),
+            ..Default::default()
+        };
+
+        stream::iter(texts.iter().enumerate())
+            .map(move |(index, text)| async move { (index, self.extract(text).await) })
+            .buffer_unordered(MAX_BATCH_SIZE)
+            .scan(totals, |totals, (index, result)| {
+                totals.record(&result);
+                futures_util::future::ready(Some(ExtractionProgress {
+                    index,
+                    result,
+                    totals: *totals,
+                }))
+            })
+    }
+
     /// 使用 SLM 提取事件（内部方法）
     async fn extract_with_slm(&self, text: &str) -> Result<Vec<ExtractedEvent>> {
         let prompt = self.build_prompt(text);
@@ -566,6 +938,10 @@ impl SlmExtractor {
 
         let url = format!("{}/api/generate", self.host);
 
+        let _permit = match &self.governor {
+            Some(governor) => Some(governor.acquire().await?),
+            None => None,
+        };
         let response = self
             .client
             .post(&url)
@@ -586,10 +962,12 @@ impl SlmExtractor {
                 crate::DirSoulError::Config(format!("Failed to parse response: {}", e))
             })?;
 
-        self.parse_slm_response(&response.response)
+        self.parse_slm_response(&response.response, text)
     }
 
     /// 构建 Prompt
+    ///
+    /// 配置了事件抽取的系统提示词时，将其置于模板之前。
     fn build_prompt(&self, text: &str) -> String {
         // 使用PromptManager从外部文件加载prompt模板
         let mut vars = HashMap::new();
@@ -597,10 +975,11 @@ impl SlmExtractor {
 
         match self.prompt_manager.lock() {
             Ok(mut manager) => {
-                match manager.render_prompt("event_extraction", vars) {
+                let prompt = match manager.render_prompt("event_extraction", vars) {
                     Ok(prompt) => prompt,
                     Err(_) => self.build_fallback_prompt(text),
-                }
+                };
+                manager.compose_prompt(PromptTask::EventExtraction, &prompt)
             }
             Err(_) => self.build_fallback_prompt(text),
         }
@@ -615,7 +994,8 @@ impl SlmExtractor {
 1. 每个事件包含：action（行为）、target（对象）
 2. 如果有数量，添加 quantity（数字）和 unit（单位）
 3. 置信度 confidence（0-1），基于匹配确信度
-4. 只输出 JSON，不要其他文字
+4. 可选：negated（原文否定该事件时为 true，如"没吃"）、sentiment（positive/negative/neutral）、span（事件对应的原文片段）
+5. 只输出 JSON，不要其他文字
 
 # 文本
 {}
@@ -634,7 +1014,10 @@ impl SlmExtractor {
     }
 
     /// 解析 SLM 响应
-    fn parse_slm_response(&self, response: &str) -> Result<Vec<ExtractedEvent>> {
+    ///
+    /// 模型给出的 negated、sentiment 写入事件 metadata；span 在原文 `text`
+    /// 中找到时记录为原文位置。
+    fn parse_slm_response(&self, response: &str, text: &str) -> Result<Vec<ExtractedEvent>> {
         // 尝试解析 JSON
         #[derive(Deserialize)]
         struct SlmOutput {
@@ -648,6 +1031,12 @@ impl SlmExtractor {
             quantity: Option<f64>,
             unit: Option<String>,
             confidence: f64,
+            #[serde(default)]
+            negated: Option<bool>,
+            #[serde(default)]
+            sentiment: Option<String>,
+            #[serde(default)]
+            span: Option<String>,
         }
 
         let output: SlmOutput = serde_json::from_str(response).map_err(|e| {
@@ -665,6 +1054,20 @@ impl SlmExtractor {
             if let (Some(q), Some(u)) = (slm_event.quantity, slm_event.unit) {
                 event = event.with_quantity(q, u);
             }
+            if let Some(negated) = slm_event.negated {
+                event = event.with_negated(negated);
+            }
+            if let Some(sentiment) = slm_event.sentiment {
+                event = event
+                    .with_metadata_entry(EVENT_SENTIMENT_KEY, serde_json::Value::String(sentiment));
+            }
+            if let Some(span) = slm_event
+                .span
+                .filter(|span| !span.is_empty())
+                .and_then(|span| text.find(&span).map(|start| start..start + span.len()))
+            {
+                event = event.with_raw_span(text, span);
+            }
 
             events.push(event);
         }
@@ -804,4 +1207,199 @@ mod tests {
         assert_eq!(event.confidence, 0.8);
         assert_eq!(event.method, "test");
     }
+
+    #[test]
+    fn test_action_normalizer_maps_surface_verbs() {
+        let normalizer = ActionNormalizer::new();
+
+        assert_eq!(normalizer.normalize("吃了"), "eat");
+        assert_eq!(normalizer.normalize("eat"), "eat");
+        assert_eq!(normalizer.normalize("吃"), "eat");
+        assert_eq!(normalizer.normalize(" Ate "), "eat");
+        assert_eq!(normalizer.normalize("买过"), "buy");
+        // 未知动词原样返回
+        assert_eq!(normalizer.normalize("冥想"), "冥想");
+        assert_eq!(normalizer.normalize("了"), "了");
+    }
+
+    #[test]
+    fn test_action_normalizer_from_toml() {
+        let normalizer = ActionNormalizer::from_toml_str(
+            r#"
+            eat = ["吃", "ate"]
+            meditate = ["冥想", "打坐"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(normalizer.normalize("吃了"), "eat");
+        assert_eq!(normalizer.normalize("打坐"), "meditate");
+        // 自定义词典不包含默认映射
+        assert_eq!(normalizer.normalize("喝"), "喝");
+
+        assert!(ActionNormalizer::from_toml_str("eat = \"吃\"").is_err());
+    }
+
+    #[test]
+    fn test_actor_inference_first_person() {
+        let inference = ActorInference::enabled();
+        assert_eq!(inference.infer(None, "今天吃了3个苹果", "alice"), Some("alice".to_string()));
+        assert_eq!(inference.infer(None, "我和他去跑步", "alice"), Some("alice".to_string()));
+
+        let named = ActorInference::enabled().with_self_identifier("我".to_string());
+        assert_eq!(named.infer(Some(" ".to_string()), "吃了苹果", "alice"), Some("我".to_string()));
+
+        // 默认关闭
+        assert_eq!(ActorInference::default().infer(None, "吃了苹果", "alice"), None);
+    }
+
+    #[test]
+    fn test_is_first_person_ignores_non_pronoun_words() {
+        assert!(ActorInference::is_first_person("买了其他水果"));
+        assert!(ActorInference::is_first_person("把其它东西收好"));
+        assert!(ActorInference::is_first_person("练了一小时吉他"));
+        assert!(!ActorInference::is_first_person("其他人说他吃了苹果"));
+    }
+
+    #[test]
+    fn test_sentence_at_picks_the_event_sentence() {
+        let text = "我吃了苹果。他去跑步！";
+        assert_eq!(ActorInference::sentence_at(text, Some(1)), "我吃了苹果");
+        assert_eq!(ActorInference::sentence_at(text, Some(7)), "他去跑步");
+        assert_eq!(ActorInference::sentence_at(text, None), text);
+        assert_eq!(ActorInference::sentence_at(text, Some(99)), "");
+        assert!(!ActorInference::is_first_person(ActorInference::sentence_at(text, Some(7))));
+    }
+
+    #[test]
+    fn test_actor_inference_keeps_third_person() {
+        let inference = ActorInference::enabled();
+        assert_eq!(
+            inference.infer(Some("妈妈".to_string()), "妈妈吃了苹果", "alice"),
+            Some("妈妈".to_string())
+        );
+        assert_eq!(
+            inference.infer(Some("我".to_string()), "我吃了苹果", "alice"),
+            Some("我".to_string())
+        );
+        assert_eq!(inference.infer(None, "他吃了苹果", "alice"), None);
+    }
+
+    #[test]
+    fn test_rule_extractor_records_negation_and_span() {
+        let extractor = RuleExtractor::new();
+
+        let events = extractor.extract("今天没吃苹果").unwrap();
+        assert_eq!(events[0].target, "苹果");
+        let metadata = events[0].metadata.as_ref().unwrap();
+        assert_eq!(metadata[EVENT_NEGATED_KEY], true);
+        assert_eq!(metadata[EVENT_RAW_SPAN_KEY], serde_json::json!({"start": 3, "end": 6}));
+
+        let events = extractor.extract("今天吃了3个苹果").unwrap();
+        let metadata = events[0].metadata.as_ref().unwrap();
+        assert_eq!(metadata[EVENT_NEGATED_KEY], false);
+        assert_eq!(metadata[EVENT_RAW_SPAN_KEY], serde_json::json!({"start": 2, "end": 8}));
+    }
+
+    #[test]
+    fn test_rule_extractor_reads_negation_markers_from_lexicon() {
+        let lexicon = LexiconSet::from_toml_str("negation_markers = [\"并非\"]").unwrap();
+        let extractor = RuleExtractor::new().with_lexicon(lexicon);
+        let events = extractor.extract("我并非吃苹果").unwrap();
+        assert_eq!(events[0].metadata.as_ref().unwrap()[EVENT_NEGATED_KEY], true);
+
+        let extractor = RuleExtractor::new().with_lexicon(LexiconSet::empty());
+        let events = extractor.extract("今天没吃苹果").unwrap();
+        assert_eq!(events[0].metadata.as_ref().unwrap()[EVENT_NEGATED_KEY], false);
+    }
+
+    #[tokio::test]
+    async fn test_slm_response_metadata() {
+        let extractor = offline_extractor().await;
+        let response = r#"{"events": [
+            {"action": "吃", "target": "苹果", "confidence": 0.9,
+             "negated": true, "sentiment": "negative", "span": "没吃苹果"},
+            {"action": "喝", "target": "咖啡", "confidence": 0.8, "span": "不在原文"}
+        ]}"#;
+
+        let events = extractor.parse_slm_response(response, "今天没吃苹果，喝了咖啡").unwrap();
+        assert_eq!(
+            events[0].metadata,
+            Some(serde_json::json!({
+                "negated": true,
+                "sentiment": "negative",
+                "raw_span": {"start": 2, "end": 6},
+            }))
+        );
+        // 原文中找不到的片段不记录位置
+        assert_eq!(events[1].metadata, None);
+    }
+
+    #[tokio::test]
+    async fn test_slm_prompt_starts_with_system_prompt() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let manager = PromptManager::with_dir(temp_dir.path()).unwrap().with_system_prompts(
+            crate::prompt_manager::SystemPrompts::new("只抽取饮食相关事件"),
+        );
+        let extractor = offline_extractor().await.with_prompt_manager(manager);
+
+        // 模板不存在时使用内置 prompt，系统提示词仍在最前
+        let prompt = extractor.build_prompt("今天吃了3个苹果");
+        assert!(prompt.starts_with("只抽取饮食相关事件\n\n你是 DirSoul 事件抽取系统"));
+        assert!(prompt.contains("今天吃了3个苹果"));
+    }
+
+    /// 指向不可达端口，SLM 立即失败并回退到规则引擎
+    async fn offline_extractor() -> SlmExtractor {
+        SlmExtractor::new(Some("http://127.0.0.1:1".to_string()), None)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_extract_batch_stream_reports_each_input() {
+        let extractor = offline_extractor().await;
+        let texts: Vec<String> = ["今天吃了3个苹果", "买了1本书", "去跑步", "你好"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+
+        let progress: Vec<ExtractionProgress> =
+            extractor.extract_batch_stream(&texts).collect().await;
+
+        assert_eq!(progress.len(), texts.len());
+        let mut indices: Vec<usize> = progress.iter().map(|p| p.index).collect();
+        indices.sort_unstable();
+        assert_eq!(indices, vec![0, 1, 2, 3]);
+
+        for (i, item) in progress.iter().enumerate() {
+            assert_eq!(item.totals.completed, i + 1);
+        }
+
+        let last = progress.last().unwrap().totals;
+        assert!(last.is_finished());
+        assert_eq!(
+            last,
+            ExtractionTotals {
+                total: 4,
+                completed: 4,
+                succeeded: 4,
+                failed: 0,
+                events: 3,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extract_batch_stream_stops_when_dropped() {
+        let extractor = offline_extractor().await;
+        let texts: Vec<String> = (0..20).map(|i| format!("吃了{}个苹果", i + 1)).collect();
+
+        let progress: Vec<ExtractionProgress> =
+            extractor.extract_batch_stream(&texts).take(2).collect().await;
+
+        assert_eq!(progress.len(), 2);
+        assert!(!progress[1].totals.is_finished());
+        assert_eq!(progress[1].totals.total, 20);
+    }
 }
@@ -8,13 +8,24 @@
 //! - 重试机制：指数退避处理临时失败
 //! - 异步优先：tokio 非阻塞操作
 
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
-use tracing::{debug, info};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
 
-use crate::error::Result;
-use crate::event_extractor::{ExtractedEvent, SlmExtractor, TimeParser};
-use crate::models::{EventMemory, NewEventMemory, NewRawMemory, RawMemory};
-use crate::schema::{event_memories, raw_memories};
+use crate::audit::NewAuditLog;
+use crate::data_changes::notify_user_data_changed;
+use crate::data_lifecycle::live_memory_ids;
+use crate::deeptalk::{EmotionalTrend, EmotionalTrendStore};
+use crate::error::{DirSoulError, Result};
+use crate::event_extractor::{ActorInference, ExtractedEvent, SlmExtractor, TimeParser};
+use crate::models::{
+    EventMemory, ExtractorVersion, NewEventMemory, NewRawMemory, RawMemory, EVENT_RAW_SPAN_KEY,
+    EVENT_SENTIMENT_KEY,
+};
+use crate::plugin::EventFilter;
+use crate::schema::{audit_logs, event_memories, extraction_failures, raw_memories};
 
 /// 事件存储处理器
 ///
@@ -24,6 +35,10 @@ pub struct EventStorage {
     extractor: SlmExtractor,
     /// 时间解析器
     time_parser: TimeParser,
+    /// 执行者推断配置
+    actor_inference: ActorInference,
+    /// 抽取失败后的重试配置
+    retry_config: ExtractionRetryConfig,
     /// 用户 ID
     user_id: String,
 }
@@ -38,10 +53,117 @@ impl EventStorage {
         Self {
             extractor,
             time_parser: TimeParser::new(),
+            actor_inference: ActorInference::default(),
+            retry_config: ExtractionRetryConfig::default(),
             user_id,
         }
     }
 
+    /// 设置执行者推断配置
+    pub fn with_actor_inference(mut self, actor_inference: ActorInference) -> Self {
+        self.actor_inference = actor_inference;
+        self
+    }
+
+    /// 设置抽取失败后的重试配置
+    pub fn with_retry_config(mut self, retry_config: ExtractionRetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
+    }
+
+    /// 处理输入并存储记忆
+    ///
+    /// # 流程
+    /// 1. 插入原始记忆（先于抽取提交，抽取失败也不会丢失输入）
+    /// 2. 抽取事件
+    /// 3. 一次性插入全部事件记忆，并将其 sentiment 计入情绪趋势
+    /// 4. 抽取失败时记录到 `extraction_failures`，由 `spawn_extraction_retry_loop`
+    ///    按退避重试，此时返回空列表
+    ///
+    /// # 参数
+    /// * `conn` - 数据库连接
+    /// * `input` - 输入数据
+    ///
+    /// # 返回
+    /// 插入的事件记忆列表
+    pub async fn process_input(
+        &self,
+        conn: &mut PgConnection,
+        input: &NewRawMemory,
+    ) -> Result<Vec<EventMemory>> {
+        info!("Processing input for user '{}'", self.user_id);
+
+        let (memory_id, created_at): (Uuid, DateTime<Utc>) =
+            diesel::insert_into(raw_memories::table)
+                .values(input)
+                .returning((raw_memories::memory_id, raw_memories::created_at))
+                .get_result(conn)?;
+        notify_user_data_changed(&input.user_id);
+
+        let extracted = match input.content.as_deref() {
+            Some(text) => self.extractor.extract(text).await,
+            None => Err(DirSoulError::Config(
+                "Memory has no plaintext content to extract".to_string(),
+            )),
+        };
+        let events = match extracted {
+            Ok(events) => events,
+            Err(e) => {
+                warn!("Extraction failed for memory {}: {}", memory_id, e);
+                record_extraction_failure(
+                    conn,
+                    memory_id,
+                    &input.user_id,
+                    &e.to_string(),
+                    &self.retry_config,
+                )?;
+                return Ok(vec![]);
+            }
+        };
+
+        let new_events: Vec<NewEventMemory> = events
+            .into_iter()
+            .map(|event| {
+                build_event_memory(
+                    &self.time_parser,
+                    &self.actor_inference,
+                    memory_id,
+                    &input.user_id,
+                    created_at,
+                    input.content.as_deref(),
+                    event,
+                )
+            })
+            .collect();
+        if new_events.is_empty() {
+            return Ok(vec![]);
+        }
+        let inserted: Vec<EventMemory> = conn.transaction::<_, DirSoulError, _>(|conn| {
+            let inserted = diesel::insert_into(event_memories::table)
+                .values(&new_events)
+                .get_results(conn)?;
+            record_event_sentiments(conn, &new_events)?;
+            Ok(inserted)
+        })?;
+        notify_user_data_changed(&input.user_id);
+
+        debug!("Inserted {} events for memory {}", inserted.len(), memory_id);
+        Ok(inserted)
+    }
+
+    /// 用本处理器的抽取器重试该用户到期的失败抽取
+    ///
+    /// 见 [`retry_failed_extractions`]。
+    pub async fn retry_failed_extractions(
+        &self,
+        conn: &mut PgConnection,
+    ) -> Result<ExtractionRetryReport> {
+        retry_failed_extractions(conn, &self.user_id, &self.retry_config, |text| async move {
+            self.extractor.extract(&text).await
+        })
+        .await
+    }
+
     /// 处理输入并存储记忆（同步版本）
     ///
     /// # 流程
@@ -81,29 +203,15 @@ impl EventStorage {
         raw_memory: &RawMemory,
         extracted: ExtractedEvent,
     ) -> Result<NewEventMemory> {
-        // 解析时间戳（如果有时间信息）
-        let timestamp = if let Some(content) = &raw_memory.content {
-            if let Some(parsed) = self.time_parser.parse(content) {
-                parsed
-            } else {
-                raw_memory.created_at
-            }
-        } else {
-            raw_memory.created_at
-        };
-
-        Ok(NewEventMemory {
-            memory_id: raw_memory.memory_id,
-            user_id: raw_memory.user_id.clone(),
-            timestamp,
-            actor: extracted.actor,
-            action: extracted.action,
-            target: extracted.target,
-            quantity: extracted.quantity,
-            unit: extracted.unit,
-            confidence: extracted.confidence,
-            extractor_version: Some(format!("{}-slm", env!("CARGO_PKG_VERSION"))),
-        })
+        Ok(build_event_memory(
+            &self.time_parser,
+            &self.actor_inference,
+            raw_memory.memory_id,
+            &raw_memory.user_id,
+            raw_memory.created_at,
+            raw_memory.content.as_deref(),
+            extracted,
+        ))
     }
 
     /// 插入事件记忆到数据库
@@ -132,8 +240,674 @@ impl EventStorage {
             unit: event.unit.clone(),
             confidence: event.confidence,
             extractor_version: event.extractor_version.clone(),
+            metadata: event.metadata.clone(),
         })
     }
+
+    /// 按过滤条件查询事件记忆
+    ///
+    /// 过滤条件与插件接口的 `EventFilter` 相同：时间范围与数量范围均为闭区间，
+    /// `actions` / `targets` / `unit` 为精确匹配，`limit` 限制按时间升序返回的条数。
+    /// 设置了数量条件时，没有数量的事件不会返回。
+    ///
+    /// # 参数
+    /// * `conn` - 数据库连接
+    /// * `user_id` - 用户 ID
+    /// * `filter` - 查询条件
+    ///
+    /// # 返回
+    /// 按时间升序排列的事件
+    pub fn query_events(
+        conn: &mut PgConnection,
+        user_id: &str,
+        filter: &EventFilter,
+    ) -> Result<Vec<EventMemory>> {
+        filter.validate()?;
+        Ok(filtered_events(user_id, filter).load(conn)?)
+    }
+
+    /// 按过滤条件批量删除事件记忆
+    ///
+    /// 过滤条件的含义与 `query_events` 相同，`limit` 限制按时间升序删除的条数。
+    /// 删除与审计记录在同一事务中完成。
+    ///
+    /// # 参数
+    /// * `conn` - 数据库连接
+    /// * `user_id` - 用户 ID
+    /// * `filter` - 删除条件
+    /// * `allow_all` - 是否允许空过滤条件（即删除该用户的全部事件）
+    ///
+    /// # 返回
+    /// 删除的事件数量
+    pub fn delete_events(
+        conn: &mut PgConnection,
+        user_id: &str,
+        filter: EventFilter,
+        allow_all: bool,
+    ) -> Result<usize> {
+        if is_unbounded(&filter) && !allow_all {
+            return Err(DirSoulError::Config(
+                "Refusing to delete all events without allow_all".to_string(),
+            ));
+        }
+        filter.validate()?;
+
+        let deleted = conn.transaction::<_, DirSoulError, _>(|conn| {
+            let event_ids: Vec<uuid::Uuid> = filtered_events(user_id, &filter)
+                .select(event_memories::event_id)
+                .load(conn)?;
+            let deleted = diesel::delete(
+                event_memories::table.filter(event_memories::event_id.eq_any(&event_ids)),
+            )
+            .execute(conn)?;
+
+            diesel::insert_into(audit_logs::table)
+                .values(
+                    &NewAuditLog::new(user_id.to_string(), "delete".to_string(), "events".to_string())
+                        .with_result_count(deleted as i32)
+                        .with_metadata(serde_json::json!({
+                            "filter": filter,
+                            "allow_all": allow_all,
+                        })),
+                )
+                .execute(conn)?;
+
+            info!("Deleted {} events for user '{}'", deleted, user_id);
+            Ok(deleted)
+        })?;
+
+        notify_user_data_changed(user_id);
+        Ok(deleted)
+    }
+
+    /// 按抽取器版本统计用户的事件数量
+    ///
+    /// 用于判断升级抽取器后还有多少旧版本事件需要重新抽取。
+    /// 未标记版本的历史事件归入 `None`。结果按数量降序，数量相同时按版本名排序。
+    ///
+    /// # 参数
+    /// * `conn` - 数据库连接
+    /// * `user_id` - 用户 ID
+    ///
+    /// # 返回
+    /// `(extractor_version, 事件数量)` 列表
+    pub fn count_events_by_extractor_version(
+        conn: &mut PgConnection,
+        user_id: &str,
+    ) -> Result<Vec<(Option<String>, i64)>> {
+        let mut counts: Vec<(Option<String>, i64)> = event_memories::table
+            .filter(event_memories::user_id.eq(user_id))
+            .group_by(event_memories::extractor_version)
+            .select((event_memories::extractor_version, diesel::dsl::count_star()))
+            .load(conn)?;
+
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(counts)
+    }
+}
+
+/// 由抽取结果构建 NewEventMemory
+///
+/// 原文包含时间信息时以解析出的时间为事件时间，否则使用原始记忆的创建时间。
+/// 抽取器未给出执行者时按 `actor_inference` 推断。
+fn build_event_memory(
+    time_parser: &TimeParser,
+    actor_inference: &ActorInference,
+    memory_id: Uuid,
+    user_id: &str,
+    created_at: DateTime<Utc>,
+    content: Option<&str>,
+    extracted: ExtractedEvent,
+) -> NewEventMemory {
+    let timestamp = content
+        .and_then(|content| time_parser.parse(content))
+        .unwrap_or(created_at);
+    // 只看事件所在的句子，同段原文中他人的事件不影响推断
+    let span_start = extracted
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata[EVENT_RAW_SPAN_KEY]["start"].as_u64())
+        .map(|start| start as usize);
+    let sentence = ActorInference::sentence_at(content.unwrap_or_default(), span_start);
+    let actor = actor_inference.infer(extracted.actor, sentence, user_id);
+
+    NewEventMemory {
+        memory_id,
+        user_id: user_id.to_string(),
+        timestamp,
+        actor,
+        action: extracted.action,
+        target: extracted.target,
+        quantity: extracted.quantity,
+        unit: extracted.unit,
+        confidence: extracted.confidence,
+        extractor_version: Some(
+            ExtractorVersion::from_method(&extracted.method)
+                .unwrap_or(ExtractorVersion::Slm)
+                .tag(),
+        ),
+        metadata: extracted.metadata,
+    }
+}
+
+/// 将事件 metadata 中的 sentiment 计入其所有者的情绪趋势
+///
+/// 每个带可识别 sentiment 的事件按事件时间的 UTC 日期记录一次，供 DeepTalk
+/// 的情绪时间线使用；没有 sentiment 的事件不计入。
+fn record_event_sentiments(conn: &mut PgConnection, events: &[NewEventMemory]) -> Result<()> {
+    for event in events {
+        let trend = event
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(EVENT_SENTIMENT_KEY))
+            .and_then(|sentiment| sentiment.as_str())
+            .and_then(EmotionalTrend::from_sentiment);
+        if let Some(trend) = trend {
+            EmotionalTrendStore::record_trend(
+                conn,
+                &event.user_id,
+                event.timestamp.date_naive(),
+                trend,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// 抽取失败记录的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtractionFailureStatus {
+    /// 等待重试
+    Pending,
+    /// 重试次数耗尽，等待人工处理
+    DeadLetter,
+}
+
+impl ExtractionFailureStatus {
+    /// 数据库中的取值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExtractionFailureStatus::Pending => "pending",
+            ExtractionFailureStatus::DeadLetter => "dead_letter",
+        }
+    }
+}
+
+impl From<&str> for ExtractionFailureStatus {
+    fn from(s: &str) -> Self {
+        match s {
+            "dead_letter" => ExtractionFailureStatus::DeadLetter,
+            _ => ExtractionFailureStatus::Pending,
+        }
+    }
+}
+
+/// 抽取失败记录（`extraction_failures` 表）
+///
+/// 每条原始记忆至多一条记录；抽取成功后记录被删除。
+#[derive(Debug, Clone, Queryable, Identifiable, Serialize, Deserialize)]
+#[diesel(table_name = extraction_failures)]
+#[diesel(primary_key(failure_id))]
+pub struct ExtractionFailure {
+    pub failure_id: Uuid,
+    pub memory_id: Uuid,
+    pub user_id: String,
+    /// 已失败的抽取次数（含首次抽取）
+    pub attempts: i32,
+    /// 最近一次失败的错误信息
+    pub last_error: String,
+    pub status: String,
+    /// 下次重试时间；进入死信后为 NULL
+    pub next_retry_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ExtractionFailure {
+    /// 解析后的状态
+    pub fn get_status(&self) -> ExtractionFailureStatus {
+        self.status.as_str().into()
+    }
+}
+
+/// 抽取重试配置
+///
+/// 第 n 次失败后等待 `base_delay * 2^(n-1)`（不超过 `max_delay`）再重试；
+/// 失败次数达到 `max_attempts` 后转入死信。
+#[derive(Debug, Clone)]
+pub struct ExtractionRetryConfig {
+    /// 最大抽取次数（含首次抽取，默认：5）
+    pub max_attempts: i32,
+    /// 首次重试前的等待时间（默认：60 秒）
+    pub base_delay: std::time::Duration,
+    /// 重试间隔上限（默认：1 小时）
+    pub max_delay: std::time::Duration,
+    /// 每次运行最多重试的记录数（默认：50）
+    pub batch_size: i64,
+    /// 重新抽取出的事件的执行者推断配置（默认：关闭）
+    pub actor_inference: ActorInference,
+}
+
+impl Default for ExtractionRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: std::time::Duration::from_secs(60),
+            max_delay: std::time::Duration::from_secs(3600),
+            batch_size: 50,
+            actor_inference: ActorInference::default(),
+        }
+    }
+}
+
+impl ExtractionRetryConfig {
+    /// 校验配置
+    pub fn validate(&self) -> Result<()> {
+        if self.max_attempts < 1 {
+            return Err(DirSoulError::Config("max_attempts must be at least 1".to_string()));
+        }
+        if self.batch_size < 1 {
+            return Err(DirSoulError::Config("batch_size must be at least 1".to_string()));
+        }
+        if self.base_delay > self.max_delay {
+            return Err(DirSoulError::Config(
+                "base_delay must not exceed max_delay".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 第 `attempts` 次失败后的重试间隔
+    pub fn retry_delay(&self, attempts: i32) -> std::time::Duration {
+        let doublings = attempts.saturating_sub(1).clamp(0, 31) as u32;
+        self.base_delay
+            .checked_mul(1u32 << doublings)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+
+    /// 第 `attempts` 次失败后的状态与下次重试时间
+    pub fn after_failure(
+        &self,
+        attempts: i32,
+        now: DateTime<Utc>,
+    ) -> (ExtractionFailureStatus, Option<DateTime<Utc>>) {
+        if attempts >= self.max_attempts {
+            return (ExtractionFailureStatus::DeadLetter, None);
+        }
+        let delay = chrono::Duration::from_std(self.retry_delay(attempts))
+            .unwrap_or_else(|_| chrono::Duration::hours(1));
+        (ExtractionFailureStatus::Pending, Some(now + delay))
+    }
+}
+
+/// 一次重试运行的结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ExtractionRetryReport {
+    /// 本次重试的记录数
+    pub attempted: usize,
+    /// 抽取成功的原始记忆
+    pub succeeded: Vec<Uuid>,
+    /// 成功抽取后插入的事件数
+    pub events_inserted: usize,
+    /// 再次失败、已安排下次重试的记录数
+    pub rescheduled: usize,
+    /// 本次转入死信的原始记忆
+    pub dead_lettered: Vec<Uuid>,
+    /// 原始记忆已删除、直接丢弃的记录数
+    pub discarded: usize,
+}
+
+/// 记录一次抽取失败
+///
+/// 首次失败时新建记录，之后累加失败次数；次数达到 `max_attempts` 时转入死信。
+///
+/// # 参数
+/// * `conn` - 数据库连接
+/// * `memory_id` - 抽取失败的原始记忆
+/// * `user_id` - 用户 ID
+/// * `error` - 错误信息
+/// * `config` - 重试配置
+pub fn record_extraction_failure(
+    conn: &mut PgConnection,
+    memory_id: Uuid,
+    user_id: &str,
+    error: &str,
+    config: &ExtractionRetryConfig,
+) -> Result<ExtractionFailure> {
+    conn.transaction::<_, DirSoulError, _>(|conn| {
+        let existing: Option<ExtractionFailure> = extraction_failures::table
+            .filter(extraction_failures::memory_id.eq(memory_id))
+            .for_update()
+            .first(conn)
+            .optional()?;
+
+        let now = Utc::now();
+        let attempts = existing.as_ref().map_or(1, |f| f.attempts + 1);
+        let (status, next_retry_at) = config.after_failure(attempts, now);
+
+        let failure: ExtractionFailure = match existing {
+            Some(failure) => diesel::update(extraction_failures::table.find(failure.failure_id))
+                .set((
+                    extraction_failures::attempts.eq(attempts),
+                    extraction_failures::last_error.eq(error),
+                    extraction_failures::status.eq(status.as_str()),
+                    extraction_failures::next_retry_at.eq(next_retry_at),
+                    extraction_failures::updated_at.eq(now),
+                ))
+                .get_result(conn)?,
+            None => diesel::insert_into(extraction_failures::table)
+                .values((
+                    extraction_failures::memory_id.eq(memory_id),
+                    extraction_failures::user_id.eq(user_id),
+                    extraction_failures::attempts.eq(attempts),
+                    extraction_failures::last_error.eq(error),
+                    extraction_failures::status.eq(status.as_str()),
+                    extraction_failures::next_retry_at.eq(next_retry_at),
+                ))
+                .get_result(conn)?,
+        };
+
+        if status == ExtractionFailureStatus::DeadLetter {
+            warn!(
+                "Extraction for memory {} dead-lettered after {} attempts: {}",
+                memory_id, attempts, error
+            );
+        }
+        Ok(failure)
+    })
+}
+
+/// 重试用户到期的失败抽取
+///
+/// 按到期时间顺序认领至多 `batch_size` 条待重试记录，用 `extract` 重新抽取原文：
+/// 成功则在同一事务中插入事件并删除记录；失败则累加次数并按退避安排下次重试，
+/// 次数耗尽时转入死信。没有明文内容（已加密）的记忆按失败处理，最终进入死信
+/// 由人工处理；原始记忆已被软删除的记录直接丢弃。
+///
+/// 认领使用 `FOR UPDATE SKIP LOCKED` 并把下次重试时间推迟 `max_delay`，
+/// 并发运行的多个任务不会重复抽取同一条记忆；任务中途退出时记录在租期过后重新到期。
+///
+/// 由 `spawn_extraction_retry_loop` 定期运行，`extract` 通常包装 `SlmExtractor::extract`。
+///
+/// # 参数
+/// * `conn` - 数据库连接
+/// * `user_id` - 用户 ID
+/// * `config` - 重试配置
+/// * `extract` - 对原文进行事件抽取
+pub async fn retry_failed_extractions<F, Fut>(
+    conn: &mut PgConnection,
+    user_id: &str,
+    config: &ExtractionRetryConfig,
+    mut extract: F,
+) -> Result<ExtractionRetryReport>
+where
+    F: FnMut(String) -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<ExtractedEvent>>>,
+{
+    config.validate()?;
+
+    let due = claim_due_extractions(conn, user_id, config)?;
+
+    let time_parser = TimeParser::new();
+    let mut report = ExtractionRetryReport::default();
+
+    for failure in due {
+        report.attempted += 1;
+
+        let memory: Option<(DateTime<Utc>, Option<String>)> = raw_memories::table
+            .find(failure.memory_id)
+            .filter(raw_memories::deleted_at.is_null())
+            .select((raw_memories::created_at, raw_memories::content))
+            .first(conn)
+            .optional()?;
+        let Some((created_at, content)) = memory else {
+            diesel::delete(extraction_failures::table.find(failure.failure_id)).execute(conn)?;
+            report.discarded += 1;
+            continue;
+        };
+
+        let extracted = match content.clone() {
+            Some(text) => extract(text).await,
+            None => Err(DirSoulError::Config(
+                "Memory has no plaintext content to extract".to_string(),
+            )),
+        };
+
+        match extracted {
+            Ok(events) => {
+                let inserted = conn.transaction::<_, DirSoulError, _>(|conn| {
+                    let new_events: Vec<NewEventMemory> = events
+                        .into_iter()
+                        .map(|event| {
+                            build_event_memory(
+                                &time_parser,
+                                &config.actor_inference,
+                                failure.memory_id,
+                                &failure.user_id,
+                                created_at,
+                                content.as_deref(),
+                                event,
+                            )
+                        })
+                        .collect();
+                    let inserted = diesel::insert_into(event_memories::table)
+                        .values(&new_events)
+                        .execute(conn)?;
+                    record_event_sentiments(conn, &new_events)?;
+                    diesel::delete(extraction_failures::table.find(failure.failure_id))
+                        .execute(conn)?;
+                    Ok(inserted)
+                })?;
+
+                notify_user_data_changed(&failure.user_id);
+                report.succeeded.push(failure.memory_id);
+                report.events_inserted += inserted;
+            }
+            Err(e) => {
+                let updated = record_extraction_failure(
+                    conn,
+                    failure.memory_id,
+                    &failure.user_id,
+                    &e.to_string(),
+                    config,
+                )?;
+                match updated.get_status() {
+                    ExtractionFailureStatus::DeadLetter => {
+                        report.dead_lettered.push(failure.memory_id)
+                    }
+                    ExtractionFailureStatus::Pending => report.rescheduled += 1,
+                }
+            }
+        }
+    }
+
+    info!(
+        "Extraction retry for user '{}': {} attempted, {} succeeded, {} rescheduled, {} dead-lettered",
+        user_id,
+        report.attempted,
+        report.succeeded.len(),
+        report.rescheduled,
+        report.dead_lettered.len()
+    );
+    Ok(report)
+}
+
+/// 认领用户到期的失败抽取记录
+///
+/// 已被其他任务锁定的记录直接跳过；认领的记录在 `max_delay` 之后才会再次到期。
+fn claim_due_extractions(
+    conn: &mut PgConnection,
+    user_id: &str,
+    config: &ExtractionRetryConfig,
+) -> Result<Vec<ExtractionFailure>> {
+    let now = Utc::now();
+    let lease = chrono::Duration::from_std(config.max_delay)
+        .unwrap_or_else(|_| chrono::Duration::hours(1));
+
+    conn.transaction::<_, DirSoulError, _>(|conn| {
+        let due: Vec<ExtractionFailure> = extraction_failures::table
+            .filter(extraction_failures::user_id.eq(user_id))
+            .filter(extraction_failures::status.eq(ExtractionFailureStatus::Pending.as_str()))
+            .filter(extraction_failures::next_retry_at.le(now))
+            .order(extraction_failures::next_retry_at.asc())
+            .limit(config.batch_size)
+            .for_update()
+            .skip_locked()
+            .load(conn)?;
+
+        let failure_ids: Vec<Uuid> = due.iter().map(|failure| failure.failure_id).collect();
+        diesel::update(
+            extraction_failures::table.filter(extraction_failures::failure_id.eq_any(&failure_ids)),
+        )
+        .set(extraction_failures::next_retry_at.eq(now + lease))
+        .execute(conn)?;
+
+        Ok(due)
+    })
+}
+
+/// 有到期待重试抽取记录的用户
+pub fn users_with_due_extractions(conn: &mut PgConnection) -> Result<Vec<String>> {
+    Ok(extraction_failures::table
+        .filter(extraction_failures::status.eq(ExtractionFailureStatus::Pending.as_str()))
+        .filter(extraction_failures::next_retry_at.le(Utc::now()))
+        .select(extraction_failures::user_id)
+        .distinct()
+        .load(conn)?)
+}
+
+/// 按固定间隔为所有用户重试到期的失败抽取
+///
+/// 首次立即执行。单个用户失败只记录日志，不影响其他用户和后续运行。
+pub fn spawn_extraction_retry_loop(
+    database_url: String,
+    extractor: std::sync::Arc<SlmExtractor>,
+    interval: std::time::Duration,
+    config: ExtractionRetryConfig,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            ticker.tick().await;
+
+            let mut conn = match PgConnection::establish(&database_url) {
+                Ok(conn) => conn,
+                Err(e) => {
+                    warn!("Extraction retry skipped: {}", e);
+                    continue;
+                }
+            };
+            let user_ids = match users_with_due_extractions(&mut conn) {
+                Ok(user_ids) => user_ids,
+                Err(e) => {
+                    warn!("Extraction retry skipped: {}", e);
+                    continue;
+                }
+            };
+
+            for user_id in user_ids {
+                let extractor = &extractor;
+                let retried =
+                    retry_failed_extractions(&mut conn, &user_id, &config, |text| async move {
+                        extractor.extract(&text).await
+                    })
+                    .await;
+                if let Err(e) = retried {
+                    warn!("Extraction retry failed for user '{}': {}", user_id, e);
+                }
+            }
+        }
+    })
+}
+
+/// 用户进入死信、等待人工处理的抽取失败记录（按时间升序）
+pub fn list_dead_letter_extractions(
+    conn: &mut PgConnection,
+    user_id: &str,
+) -> Result<Vec<ExtractionFailure>> {
+    Ok(extraction_failures::table
+        .filter(extraction_failures::user_id.eq(user_id))
+        .filter(extraction_failures::status.eq(ExtractionFailureStatus::DeadLetter.as_str()))
+        .order(extraction_failures::updated_at.asc())
+        .load(conn)?)
+}
+
+/// 按 `EventFilter` 筛选用户未删除记忆的事件，按时间升序
+fn filtered_events<'a>(
+    user_id: &'a str,
+    filter: &EventFilter,
+) -> event_memories::BoxedQuery<'a, diesel::pg::Pg> {
+    let mut query = event_memories::table
+        .filter(event_memories::user_id.eq(user_id))
+        .filter(event_memories::memory_id.eq_any(live_memory_ids(user_id)))
+        .order(event_memories::timestamp.asc())
+        .into_boxed();
+
+    if let Some(start) = filter.start_time {
+        query = query.filter(event_memories::timestamp.ge(start));
+    }
+    if let Some(end) = filter.end_time {
+        query = query.filter(event_memories::timestamp.le(end));
+    }
+    if let Some(actions) = &filter.actions {
+        query = query.filter(event_memories::action.eq_any(actions.clone()));
+    }
+    if let Some(targets) = &filter.targets {
+        query = query.filter(event_memories::target.eq_any(targets.clone()));
+    }
+    query = filter_by_quantity(
+        query,
+        filter.min_quantity,
+        filter.max_quantity,
+        filter.unit.as_deref(),
+    );
+    if let Some(limit) = filter.limit {
+        query = query.limit(limit as i64);
+    }
+    query
+}
+
+/// 按数量范围（闭区间）与单位筛选事件
+///
+/// 设置了任一条件时，数量为 NULL 的事件一律排除，而不是依赖比较 NULL 的结果。
+pub(crate) fn filter_by_quantity<'a>(
+    mut query: event_memories::BoxedQuery<'a, diesel::pg::Pg>,
+    min_quantity: Option<f64>,
+    max_quantity: Option<f64>,
+    unit: Option<&str>,
+) -> event_memories::BoxedQuery<'a, diesel::pg::Pg> {
+    if min_quantity.is_none() && max_quantity.is_none() && unit.is_none() {
+        return query;
+    }
+
+    query = query.filter(event_memories::quantity.is_not_null());
+    if let Some(min) = min_quantity {
+        query = query.filter(event_memories::quantity.assume_not_null().ge(min));
+    }
+    if let Some(max) = max_quantity {
+        query = query.filter(event_memories::quantity.assume_not_null().le(max));
+    }
+    if let Some(unit) = unit {
+        query = query.filter(event_memories::unit.is_not_null());
+        query = query.filter(event_memories::unit.assume_not_null().eq(unit.to_string()));
+    }
+    query
+}
+
+/// 过滤条件是否为空（不限定任何范围）
+///
+/// `limit` 不算作范围限定；`Some(vec![])` 视为限定（匹配不到任何事件）。
+fn is_unbounded(filter: &EventFilter) -> bool {
+    filter.start_time.is_none()
+        && filter.end_time.is_none()
+        && filter.actions.is_none()
+        && filter.targets.is_none()
+        && filter.min_quantity.is_none()
+        && filter.max_quantity.is_none()
+        && filter.unit.is_none()
 }
 
 #[cfg(test)]
@@ -145,4 +919,137 @@ mod tests {
         // 基本创建测试
         // 集成测试会在 Task 3.6 中完成
     }
+
+    fn empty_filter() -> EventFilter {
+        EventFilter::default()
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_cap() {
+        let config = ExtractionRetryConfig {
+            base_delay: std::time::Duration::from_secs(60),
+            max_delay: std::time::Duration::from_secs(300),
+            ..Default::default()
+        };
+        assert_eq!(config.retry_delay(1).as_secs(), 60);
+        assert_eq!(config.retry_delay(2).as_secs(), 120);
+        assert_eq!(config.retry_delay(3).as_secs(), 240);
+        assert_eq!(config.retry_delay(4).as_secs(), 300);
+        assert_eq!(config.retry_delay(100).as_secs(), 300);
+    }
+
+    #[test]
+    fn test_after_failure_dead_letters_at_max_attempts() {
+        let config = ExtractionRetryConfig {
+            max_attempts: 3,
+            ..Default::default()
+        };
+        let now = chrono::Utc::now();
+
+        let (status, next) = config.after_failure(2, now);
+        assert_eq!(status, ExtractionFailureStatus::Pending);
+        assert_eq!(next, Some(now + chrono::Duration::seconds(120)));
+
+        let (status, next) = config.after_failure(3, now);
+        assert_eq!(status, ExtractionFailureStatus::DeadLetter);
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn test_extraction_retry_config_validation() {
+        assert!(ExtractionRetryConfig::default().validate().is_ok());
+        assert!(ExtractionRetryConfig { max_attempts: 0, ..Default::default() }.validate().is_err());
+        assert!(ExtractionRetryConfig { batch_size: 0, ..Default::default() }.validate().is_err());
+        assert!(ExtractionRetryConfig {
+            base_delay: std::time::Duration::from_secs(7200),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_build_event_memory_infers_actor() {
+        let time_parser = TimeParser::new();
+        let inference = ActorInference::enabled();
+        let build = |content: &str, extracted: ExtractedEvent| {
+            build_event_memory(
+                &time_parser,
+                &inference,
+                Uuid::new_v4(),
+                "alice",
+                chrono::Utc::now(),
+                Some(content),
+                extracted,
+            )
+        };
+
+        // 第一人称、未抽取出执行者：以用户为执行者
+        let event = build("吃了苹果", ExtractedEvent::new("吃".to_string(), "苹果".to_string()));
+        assert_eq!(event.actor.as_deref(), Some("alice"));
+
+        // 第三人称：保留抽取出的执行者
+        let event = build(
+            "妈妈吃了苹果",
+            ExtractedEvent::new("吃".to_string(), "苹果".to_string()).with_actor("妈妈".to_string()),
+        );
+        assert_eq!(event.actor.as_deref(), Some("妈妈"));
+    }
+
+    #[test]
+    fn test_build_event_memory_keeps_metadata() {
+        let extracted = ExtractedEvent::new("吃".to_string(), "苹果".to_string())
+            .with_negated(true)
+            .with_metadata_entry("sentiment", serde_json::json!("negative"));
+        let event = build_event_memory(
+            &TimeParser::new(),
+            &ActorInference::default(),
+            Uuid::new_v4(),
+            "alice",
+            chrono::Utc::now(),
+            Some("没吃苹果"),
+            extracted,
+        );
+        assert_eq!(
+            event.metadata,
+            Some(serde_json::json!({"negated": true, "sentiment": "negative"}))
+        );
+
+        // 没有附加信号的事件不写 metadata
+        let event = build_event_memory(
+            &TimeParser::new(),
+            &ActorInference::default(),
+            Uuid::new_v4(),
+            "alice",
+            chrono::Utc::now(),
+            None,
+            ExtractedEvent::new("吃".to_string(), "苹果".to_string()),
+        );
+        assert_eq!(event.metadata, None);
+    }
+
+    #[test]
+    fn test_extraction_failure_status_round_trip() {
+        for status in [ExtractionFailureStatus::Pending, ExtractionFailureStatus::DeadLetter] {
+            assert_eq!(ExtractionFailureStatus::from(status.as_str()), status);
+        }
+    }
+
+    #[test]
+    fn test_is_unbounded() {
+        assert!(is_unbounded(&empty_filter()));
+        assert!(is_unbounded(&EventFilter { limit: Some(10), ..empty_filter() }));
+        assert!(!is_unbounded(&EventFilter {
+            actions: Some(vec!["eat".to_string()]),
+            ..empty_filter()
+        }));
+        assert!(!is_unbounded(&EventFilter {
+            start_time: Some(chrono::Utc::now()),
+            ..empty_filter()
+        }));
+        assert!(!is_unbounded(&EventFilter {
+            min_quantity: Some(3.0),
+            ..empty_filter()
+        }));
+    }
 }
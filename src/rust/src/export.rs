@@ -7,6 +7,7 @@
 //! - **GDPR合规**: 一键导出所有数据
 //! - **自动备份**: 指定目录镜像备份
 //! - **端到端加密**: 备份数据加密保护
+//! - **被遗忘权**: `erase_user` 一次性清除用户全部数据
 //!
 //! # Example
 //! ```text
@@ -19,14 +20,23 @@
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use base64::Engine;
 
+use crate::agents::Agent;
+use crate::audit::{AuditLog, NewAuditLog};
 use crate::crypto::EncryptionManager;
 use crate::cognitive::{CognitiveView, StableConcept};
+use crate::data_changes::notify_user_data_changed;
+use crate::data_lifecycle::live_memory_ids;
+use crate::deeptalk::EmotionalTrendPoint;
 use crate::error::{DirSoulError, Result};
-use crate::models::{Entity, EventMemory};
-use crate::schema::{entities, event_memories, raw_memories, stable_concepts, cognitive_views};
+use crate::models::{Entity, EntityRelation, EventMemory};
+use crate::schema::{
+    agents, audit_logs, cognitive_views, emotional_trends, entities, entity_relations,
+    event_memories, raw_memories, stable_concepts, user_settings,
+};
 use diesel::sql_types::{Jsonb, Nullable, Text, Timestamptz};
 use uuid::Uuid;
 
@@ -49,9 +59,70 @@ pub struct RawMemoryExport {
     pub metadata: Option<serde_json::Value>,
 }
 
+/// Sections written by `DataExporter`
+///
+/// Defaults to a full export. The options used are stored in the export's
+/// `sections`, so an importer can tell an omitted section from an empty one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    /// Raw memories
+    pub include_raw: bool,
+    /// Event memories
+    pub include_events: bool,
+    /// Entities
+    pub include_entities: bool,
+    /// Entity relations
+    pub include_relations: bool,
+    /// Cognitive views
+    pub include_views: bool,
+    /// Stable concepts
+    pub include_concepts: bool,
+    /// Daily emotional trend points
+    pub include_trends: bool,
+    /// The user's agents and plugins
+    pub include_agents: bool,
+    /// Audit logs
+    pub include_audit: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            include_raw: true,
+            include_events: true,
+            include_entities: true,
+            include_relations: true,
+            include_views: true,
+            include_concepts: true,
+            include_trends: true,
+            include_agents: true,
+            include_audit: true,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// No sections; combine with struct update syntax to pick a few
+    pub fn none() -> Self {
+        Self {
+            include_raw: false,
+            include_events: false,
+            include_entities: false,
+            include_relations: false,
+            include_views: false,
+            include_concepts: false,
+            include_trends: false,
+            include_agents: false,
+            include_audit: false,
+        }
+    }
+}
+
 /// Complete user data export
 ///
-/// Contains all user data for GDPR compliance and backup.
+/// Contains all user data for GDPR compliance and backup. Sections left out
+/// by `sections` are empty and omitted from the JSON.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserDataExport {
     /// User ID
@@ -63,25 +134,76 @@ pub struct UserDataExport {
     /// Export format version
     pub version: String,
 
+    /// Sections present in this export (full for exports that predate it)
+    #[serde(default)]
+    pub sections: ExportOptions,
+
     /// Raw memories (as JSON due to pgvector type)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub raw_memories: Vec<serde_json::Value>,
 
     /// Event memories
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub event_memories: Vec<EventMemory>,
 
     /// Entities
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub entities: Vec<Entity>,
 
-    /// Stable concepts
-    pub stable_concepts: Vec<serde_json::Value>,
+    /// Relations between the user's entities
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entity_relations: Vec<EntityRelation>,
 
-    /// Cognitive views
-    pub cognitive_views: Vec<serde_json::Value>,
+    /// Stable concepts, including deprecated versions of each chain
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stable_concepts: Vec<StableConcept>,
+
+    /// Cognitive views, including promoted and rejected ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cognitive_views: Vec<CognitiveView>,
+
+    /// Daily emotional trend points, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub emotional_trends: Vec<EmotionalTrendPoint>,
+
+    /// Agents and plugins registered by the user
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub agents: Vec<Agent>,
+
+    /// Audit log entries
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audit_logs: Vec<AuditLog>,
 
     /// Metadata
     pub metadata: ExportMetadata,
 }
 
+impl UserDataExport {
+    /// Fail with `Config` if a section marked as omitted carries data
+    pub fn validate_sections(&self) -> Result<()> {
+        let sections = [
+            ("raw_memories", self.sections.include_raw, self.raw_memories.len()),
+            ("event_memories", self.sections.include_events, self.event_memories.len()),
+            ("entities", self.sections.include_entities, self.entities.len()),
+            ("entity_relations", self.sections.include_relations, self.entity_relations.len()),
+            ("cognitive_views", self.sections.include_views, self.cognitive_views.len()),
+            ("stable_concepts", self.sections.include_concepts, self.stable_concepts.len()),
+            ("emotional_trends", self.sections.include_trends, self.emotional_trends.len()),
+            ("agents", self.sections.include_agents, self.agents.len()),
+            ("audit_logs", self.sections.include_audit, self.audit_logs.len()),
+        ];
+        for (name, included, count) in sections {
+            if !included && count > 0 {
+                return Err(DirSoulError::Config(format!(
+                    "Export marks {} as omitted but contains {} of them",
+                    name, count
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Export metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportMetadata {
@@ -94,12 +216,28 @@ pub struct ExportMetadata {
     /// Total entities
     pub entity_count: usize,
 
+    /// Total entity relations
+    #[serde(default)]
+    pub entity_relation_count: usize,
+
     /// Total stable concepts
     pub stable_concept_count: usize,
 
     /// Total cognitive views
     pub cognitive_view_count: usize,
 
+    /// Total emotional trend points
+    #[serde(default)]
+    pub emotional_trend_count: usize,
+
+    /// Total agents
+    #[serde(default)]
+    pub agent_count: usize,
+
+    /// Total audit log entries
+    #[serde(default)]
+    pub audit_log_count: usize,
+
     /// Data size (encrypted bytes)
     pub encrypted_size: Option<usize>,
 
@@ -113,8 +251,12 @@ impl Default for ExportMetadata {
             raw_memory_count: 0,
             event_memory_count: 0,
             entity_count: 0,
+            entity_relation_count: 0,
             stable_concept_count: 0,
             cognitive_view_count: 0,
+            emotional_trend_count: 0,
+            agent_count: 0,
+            audit_log_count: 0,
             encrypted_size: None,
             export_duration_secs: None,
         }
@@ -148,65 +290,120 @@ pub struct EncryptedDataExport {
 /// Data exporter for GDPR compliance and backup
 pub struct DataExporter {
     database_url: String,
+    options: ExportOptions,
 }
 
 impl DataExporter {
-    /// Create a new data exporter
+    /// Create a new data exporter (full export)
     pub fn new(database_url: String) -> Self {
-        Self { database_url }
+        Self {
+            database_url,
+            options: ExportOptions::default(),
+        }
     }
 
-    /// Export all user data
+    /// Only export the sections enabled in `options`
+    pub fn with_options(mut self, options: ExportOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Export the user's data for the configured sections
     pub fn export_user_data(&self, user_id: &str) -> Result<UserDataExport> {
         let start_time = Utc::now();
+        let options = self.options;
         let mut conn = PgConnection::establish(&self.database_url)
             .map_err(|e| DirSoulError::DatabaseConnection(e))?;
 
         // Export raw memories using raw SQL to handle pgvector type
-        let raw_memory_rows: Vec<RawMemoryExport> = diesel::sql_query(
-            "SELECT memory_id, user_id, created_at, content_type, content, encrypted, metadata
-             FROM raw_memories
-             WHERE user_id = $1
-             ORDER BY created_at DESC"
-        )
-        .bind::<diesel::sql_types::Text, _>(user_id)
-        .load(&mut conn)?;
-        let raw_memories: Vec<serde_json::Value> = raw_memory_rows
-            .into_iter()
-            .map(|row| serde_json::to_value(row).unwrap())
-            .collect();
+        let mut raw_memories: Vec<serde_json::Value> = Vec::new();
+        if options.include_raw {
+            let raw_memory_rows: Vec<RawMemoryExport> = diesel::sql_query(
+                "SELECT memory_id, user_id, created_at, content_type, content, encrypted, metadata
+                 FROM raw_memories
+                 WHERE user_id = $1 AND deleted_at IS NULL
+                 ORDER BY created_at DESC"
+            )
+            .bind::<diesel::sql_types::Text, _>(user_id)
+            .load(&mut conn)?;
+            raw_memories = raw_memory_rows
+                .into_iter()
+                .map(|row| serde_json::to_value(row).unwrap())
+                .collect();
+        }
 
         // Export event memories
-        let event_memories: Vec<EventMemory> = event_memories::table
-            .filter(event_memories::user_id.eq(user_id))
-            .order(event_memories::timestamp.desc())
-            .load(&mut conn)?;
+        let mut event_memories: Vec<EventMemory> = Vec::new();
+        if options.include_events {
+            event_memories = event_memories::table
+                .filter(event_memories::user_id.eq(user_id))
+                .filter(event_memories::memory_id.eq_any(live_memory_ids(user_id)))
+                .order(event_memories::timestamp.desc())
+                .load(&mut conn)?;
+        }
 
         // Export entities
-        let entities: Vec<Entity> = entities::table
-            .filter(entities::user_id.eq(user_id))
-            .order(entities::first_seen.asc())
-            .load(&mut conn)?;
+        let mut entities: Vec<Entity> = Vec::new();
+        if options.include_entities {
+            entities = entities::table
+                .filter(entities::user_id.eq(user_id))
+                .order(entities::first_seen.asc())
+                .load(&mut conn)?;
+        }
 
-        // Export stable concepts (as JSON due to complex structure)
-        let stable_concepts_db: Vec<StableConcept> = stable_concepts::table
-            .filter(stable_concepts::user_id.eq(user_id))
-            .order(stable_concepts::created_at.desc())
-            .load(&mut conn)?;
-        let stable_concepts: Vec<serde_json::Value> = stable_concepts_db
-            .iter()
-            .map(|row| serde_json::to_value(row).unwrap())
-            .collect();
-
-        // Export cognitive views (as JSON)
-        let cognitive_views_db: Vec<CognitiveView> = cognitive_views::table
-            .filter(cognitive_views::user_id.eq(user_id))
-            .order(cognitive_views::created_at.desc())
-            .load(&mut conn)?;
-        let cognitive_views: Vec<serde_json::Value> = cognitive_views_db
-            .iter()
-            .map(|row| serde_json::to_value(row).unwrap())
-            .collect();
+        // Export entity relations
+        let mut entity_relations: Vec<EntityRelation> = Vec::new();
+        if options.include_relations {
+            entity_relations = entity_relations::table
+                .filter(entity_relations::user_id.eq(user_id))
+                .order(entity_relations::first_seen.asc())
+                .load(&mut conn)?;
+        }
+
+        // Export stable concepts (every version, so parent_concept_id chains stay whole)
+        let mut stable_concepts: Vec<StableConcept> = Vec::new();
+        if options.include_concepts {
+            stable_concepts = stable_concepts::table
+                .filter(stable_concepts::user_id.eq(user_id))
+                .order((stable_concepts::canonical_name.asc(), stable_concepts::version.asc()))
+                .load(&mut conn)?;
+        }
+
+        // Export cognitive views (promoted_to links point into stable_concepts)
+        let mut cognitive_views: Vec<CognitiveView> = Vec::new();
+        if options.include_views {
+            cognitive_views = cognitive_views::table
+                .filter(cognitive_views::user_id.eq(user_id))
+                .order(cognitive_views::created_at.desc())
+                .load(&mut conn)?;
+        }
+
+        // Export the emotional timeline
+        let mut emotional_trends: Vec<EmotionalTrendPoint> = Vec::new();
+        if options.include_trends {
+            emotional_trends = emotional_trends::table
+                .filter(emotional_trends::user_id.eq(user_id))
+                .order(emotional_trends::day.asc())
+                .load(&mut conn)?;
+        }
+
+        // Export agents
+        let mut agents: Vec<Agent> = Vec::new();
+        if options.include_agents {
+            agents = agents::table
+                .filter(agents::user_id.eq(user_id))
+                .order(agents::created_at.asc())
+                .load(&mut conn)?;
+        }
+
+        // Export audit logs
+        let mut audit_logs: Vec<AuditLog> = Vec::new();
+        if options.include_audit {
+            audit_logs = audit_logs::table
+                .filter(audit_logs::user_id.eq(user_id))
+                .order(audit_logs::timestamp.asc())
+                .load(&mut conn)?;
+        }
 
         let end_time = Utc::now();
         let duration = (end_time - start_time).num_seconds() as f64;
@@ -215,8 +412,12 @@ impl DataExporter {
             raw_memory_count: raw_memories.len(),
             event_memory_count: event_memories.len(),
             entity_count: entities.len(),
+            entity_relation_count: entity_relations.len(),
             stable_concept_count: stable_concepts.len(),
             cognitive_view_count: cognitive_views.len(),
+            emotional_trend_count: emotional_trends.len(),
+            agent_count: agents.len(),
+            audit_log_count: audit_logs.len(),
             encrypted_size: None,
             export_duration_secs: Some(duration),
         };
@@ -225,11 +426,16 @@ impl DataExporter {
             user_id: user_id.to_string(),
             exported_at: end_time,
             version: "1.0.0".to_string(),
+            sections: options,
             raw_memories,
             event_memories,
             entities,
+            entity_relations,
             stable_concepts,
             cognitive_views,
+            emotional_trends,
+            agents,
+            audit_logs,
             metadata,
         })
     }
@@ -329,35 +535,123 @@ impl DataImporter {
         &self,
         export: &UserDataExport,
     ) -> Result<ImportSummary> {
+        self.import(export, false)
+    }
+
+    /// Import user data, or preview the import with `dry_run`
+    ///
+    /// A dry run performs the same inserts inside a transaction that is then
+    /// rolled back, so the returned summary (and any validation error) is
+    /// exactly what a real import would produce, but nothing is written.
+    /// The summary counts rows actually inserted; audit logs are not
+    /// restored.
+    pub fn import(&self, export: &UserDataExport, dry_run: bool) -> Result<ImportSummary> {
         let mut conn = PgConnection::establish(&self.database_url)
             .map_err(|e| DirSoulError::DatabaseConnection(e))?;
 
-        // Begin transaction
-        conn.transaction::<_, DirSoulError, _>(|conn| {
-            // Check if user already has data
-            let existing_count: i64 = raw_memories::table
-                .filter(raw_memories::user_id.eq(&export.user_id))
-                .count()
-                .get_result(conn)?;
+        if !dry_run {
+            let summary = conn.transaction::<_, DirSoulError, _>(|conn| apply_import(conn, export))?;
+            notify_user_data_changed(&export.user_id);
+            return Ok(summary);
+        }
+
+        let mut preview = None;
+        let outcome = conn.transaction::<(), DirSoulError, _>(|conn| {
+            preview = Some(apply_import(conn, export)?);
+            Err(diesel::result::Error::RollbackTransaction.into())
+        });
 
-            if existing_count > 0 {
-                return Err(DirSoulError::Config(
-                    format!("User {} already has {} records. Import not supported yet.",
-                            export.user_id, existing_count)
-                ));
+        match (outcome, preview) {
+            (Err(DirSoulError::Database(diesel::result::Error::RollbackTransaction)), Some(summary)) => {
+                Ok(ImportSummary { dry_run: true, ..summary })
             }
+            (Err(e), _) => Err(e),
+            (Ok(()), _) => unreachable!("dry-run transaction always rolls back"),
+        }
+    }
 
-            // TODO: Implement actual data import
-            // For now, just count what would be imported
+    /// Import user data in batched transactions, resumably
+    ///
+    /// Rows are written `options.batch_size` at a time, each batch in its own
+    /// transaction, and `on_progress` is called after every committed batch.
+    /// A failing batch (or an error returned by `on_progress`) stops the
+    /// import without undoing earlier batches: the summary then carries the
+    /// error in `failure`, and its `watermark` passed back as
+    /// `options.resume_from` continues from the first uncommitted row. A
+    /// rerun without the watermark skips the rows an earlier run wrote.
+    pub fn import_batched<F>(
+        &self,
+        export: &UserDataExport,
+        options: &ImportOptions,
+        mut on_progress: F,
+    ) -> Result<ImportSummary>
+    where
+        F: FnMut(&ImportProgress) -> Result<()>,
+    {
+        options.validate()?;
+        export.validate_sections()?;
+        let mut conn = PgConnection::establish(&self.database_url)
+            .map_err(|e| DirSoulError::DatabaseConnection(e))?;
 
-            Ok(ImportSummary {
-                user_id: export.user_id.clone(),
-                raw_memories_imported: export.raw_memories.len(),
-                event_memories_imported: export.event_memories.len(),
-                entities_imported: export.entities.len(),
-                stable_concepts_imported: export.stable_concepts.len(),
-                cognitive_views_imported: export.cognitive_views.len(),
-            })
+        let import_id = options
+            .resume_from
+            .map_or_else(|| export_import_id(export), |w| w.import_id);
+        let rows = import_rows(export, import_id)?;
+        let mut watermark = match options.resume_from {
+            Some(watermark) => {
+                if watermark.total_rows != rows.len() || watermark.rows_committed > rows.len() {
+                    return Err(DirSoulError::Config(format!(
+                        "Watermark ({} of {} rows) does not match an export of {} rows",
+                        watermark.rows_committed,
+                        watermark.total_rows,
+                        rows.len()
+                    )));
+                }
+                watermark
+            }
+            None => {
+                ensure_no_existing_memories(&mut conn, export, &rows)?;
+                ImportWatermark {
+                    import_id,
+                    rows_committed: 0,
+                    total_rows: rows.len(),
+                }
+            }
+        };
+
+        let start = watermark.rows_committed;
+        let mut counts = ImportCounts::default();
+        let mut failure = None;
+        for batch in rows[start..].chunks(options.batch_size) {
+            let outcome = conn
+                .transaction::<_, DirSoulError, _>(|conn| {
+                    let mut batch_counts = ImportCounts::default();
+                    for row in batch {
+                        batch_counts.record(row, row.insert(conn)?);
+                    }
+                    Ok(batch_counts)
+                })
+                .and_then(|batch_counts| {
+                    counts.add(batch_counts);
+                    watermark.rows_committed += batch.len();
+                    on_progress(&ImportProgress {
+                        rows_processed: watermark.rows_committed,
+                        total_rows: watermark.total_rows,
+                    })
+                });
+            if let Err(e) = outcome {
+                failure = Some(e.to_string());
+                break;
+            }
+        }
+        if watermark.rows_committed > start {
+            notify_user_data_changed(&export.user_id);
+        }
+
+        Ok(ImportSummary {
+            watermark: Some(watermark),
+            failure,
+            ..counts.summary(&export.user_id)
         })
     }
 
@@ -382,6 +676,546 @@ impl DataImporter {
     }
 }
 
+/// Options for `DataImporter::import_batched`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImportOptions {
+    /// Rows written per transaction (default: 100)
+    pub batch_size: usize,
+    /// Watermark of an interrupted import to continue from
+    pub resume_from: Option<ImportWatermark>,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 100,
+            resume_from: None,
+        }
+    }
+}
+
+impl ImportOptions {
+    /// Continue the import that stopped at `watermark`
+    pub fn with_resume_from(mut self, watermark: ImportWatermark) -> Self {
+        self.resume_from = Some(watermark);
+        self
+    }
+
+    /// Validate the options
+    pub fn validate(&self) -> Result<()> {
+        if self.batch_size == 0 {
+            return Err(DirSoulError::Config("batch_size must be at least 1".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// How far a batched import has durably got
+///
+/// Imported rows get ids derived from `import_id`, so resuming with the
+/// same watermark assigns the same ids and links as the interrupted run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportWatermark {
+    /// Identifies the import across resumes
+    pub import_id: Uuid,
+    /// Rows committed so far, in import order
+    pub rows_committed: usize,
+    /// Rows the export contains
+    pub total_rows: usize,
+}
+
+impl ImportWatermark {
+    /// Every row has been committed
+    pub fn is_complete(&self) -> bool {
+        self.rows_committed >= self.total_rows
+    }
+}
+
+/// Progress reported after each committed batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ImportProgress {
+    /// Rows committed so far, including earlier runs of a resumed import
+    pub rows_processed: usize,
+    /// Rows the export contains
+    pub total_rows: usize,
+}
+
+/// One row written by an import
+#[derive(Debug, Clone)]
+enum ImportRow {
+    RawMemory(RawMemoryExport),
+    Entity(Entity),
+    Event(EventMemory),
+    Relation(EntityRelation),
+    Concept(StableConcept),
+    View(CognitiveView),
+    Trend(EmotionalTrendPoint),
+    Agent(Agent),
+}
+
+impl ImportRow {
+    /// Insert the row unless it is already there; `false` when skipped
+    ///
+    /// Rows are keyed by their derived ids, so a rerun of the same import
+    /// skips what an earlier run wrote. Events whose memory and relations
+    /// whose entities exist neither in the export nor in the database are
+    /// skipped as well.
+    fn insert(&self, conn: &mut PgConnection) -> Result<bool> {
+        let written = match self {
+            ImportRow::RawMemory(memory) => diesel::insert_into(raw_memories::table)
+                .values((
+                    raw_memories::memory_id.eq(memory.memory_id),
+                    raw_memories::user_id.eq(&memory.user_id),
+                    raw_memories::created_at.eq(memory.created_at),
+                    raw_memories::content_type.eq(&memory.content_type),
+                    raw_memories::content.eq(&memory.content),
+                    raw_memories::encrypted.eq(&memory.encrypted),
+                    raw_memories::metadata.eq(&memory.metadata),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?,
+            ImportRow::Entity(entity) => diesel::insert_into(entities::table)
+                .values((
+                    entities::entity_id.eq(entity.entity_id),
+                    entities::user_id.eq(&entity.user_id),
+                    entities::canonical_name.eq(&entity.canonical_name),
+                    entities::entity_type.eq(&entity.entity_type),
+                    entities::attributes.eq(&entity.attributes),
+                    entities::first_seen.eq(entity.first_seen),
+                    entities::last_seen.eq(entity.last_seen),
+                    entities::occurrence_count.eq(entity.occurrence_count),
+                    entities::confidence.eq(entity.confidence),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?,
+            ImportRow::Event(event) => {
+                let memory_exists: bool = diesel::select(diesel::dsl::exists(
+                    raw_memories::table
+                        .filter(raw_memories::memory_id.eq(event.memory_id))
+                        .filter(raw_memories::user_id.eq(&event.user_id)),
+                ))
+                .get_result(conn)?;
+                if !memory_exists {
+                    return Ok(false);
+                }
+                diesel::insert_into(event_memories::table)
+                    .values((
+                        event_memories::event_id.eq(event.event_id),
+                        event_memories::memory_id.eq(event.memory_id),
+                        event_memories::user_id.eq(&event.user_id),
+                        event_memories::timestamp.eq(event.timestamp),
+                        event_memories::actor.eq(&event.actor),
+                        event_memories::action.eq(&event.action),
+                        event_memories::target.eq(&event.target),
+                        event_memories::quantity.eq(event.quantity),
+                        event_memories::unit.eq(&event.unit),
+                        event_memories::confidence.eq(event.confidence),
+                        event_memories::extractor_version.eq(&event.extractor_version),
+                        event_memories::metadata.eq(&event.metadata),
+                    ))
+                    .on_conflict_do_nothing()
+                    .execute(conn)?
+            }
+            ImportRow::Relation(relation) => {
+                let endpoints = [relation.source_entity_id, relation.target_entity_id];
+                let found: i64 = entities::table
+                    .filter(entities::entity_id.eq_any(&endpoints))
+                    .filter(entities::user_id.eq(&relation.user_id))
+                    .count()
+                    .get_result(conn)?;
+                let expected = if endpoints[0] == endpoints[1] { 1 } else { 2 };
+                if found < expected {
+                    return Ok(false);
+                }
+                diesel::insert_into(entity_relations::table)
+                    .values((
+                        entity_relations::relation_id.eq(relation.relation_id),
+                        entity_relations::user_id.eq(&relation.user_id),
+                        entity_relations::source_entity_id.eq(relation.source_entity_id),
+                        entity_relations::target_entity_id.eq(relation.target_entity_id),
+                        entity_relations::relation_type.eq(&relation.relation_type),
+                        entity_relations::confidence.eq(relation.confidence),
+                        entity_relations::first_seen.eq(relation.first_seen),
+                        entity_relations::last_seen.eq(relation.last_seen),
+                        entity_relations::strength.eq(relation.strength),
+                        entity_relations::observation_count.eq(relation.observation_count),
+                        entity_relations::co_occurrence.eq(relation.co_occurrence),
+                    ))
+                    .on_conflict_do_nothing()
+                    .execute(conn)?
+            }
+            ImportRow::Concept(concept) => diesel::insert_into(stable_concepts::table)
+                .values(concept)
+                .on_conflict_do_nothing()
+                .execute(conn)?,
+            ImportRow::View(view) => diesel::insert_into(cognitive_views::table)
+                .values(view)
+                .on_conflict_do_nothing()
+                .execute(conn)?,
+            ImportRow::Trend(point) => diesel::insert_into(emotional_trends::table)
+                .values((
+                    emotional_trends::user_id.eq(&point.user_id),
+                    emotional_trends::day.eq(point.day),
+                    emotional_trends::score.eq(point.score),
+                    emotional_trends::sample_count.eq(point.sample_count),
+                    emotional_trends::updated_at.eq(point.updated_at),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?,
+            ImportRow::Agent(agent) => diesel::insert_into(agents::table)
+                .values((
+                    agents::agent_id.eq(agent.agent_id),
+                    agents::user_id.eq(&agent.user_id),
+                    agents::name.eq(&agent.name),
+                    agents::agent_type.eq(&agent.agent_type),
+                    agents::version.eq(&agent.version),
+                    agents::description.eq(&agent.description),
+                    agents::author.eq(&agent.author),
+                    agents::permissions.eq(&agent.permissions),
+                    agents::is_active.eq(agent.is_active),
+                    agents::is_builtin.eq(agent.is_builtin),
+                    agents::created_at.eq(agent.created_at),
+                    agents::updated_at.eq(agent.updated_at),
+                    agents::last_used_at.eq(agent.last_used_at),
+                    agents::metadata.eq(&agent.metadata),
+                    agents::tags.eq(&agent.tags),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?,
+        };
+        Ok(written > 0)
+    }
+}
+
+/// Rows actually written (and skipped) by an import
+#[derive(Debug, Clone, Copy, Default)]
+struct ImportCounts {
+    raw_memories: usize,
+    event_memories: usize,
+    entities: usize,
+    entity_relations: usize,
+    stable_concepts: usize,
+    cognitive_views: usize,
+    emotional_trends: usize,
+    agents: usize,
+    skipped: usize,
+}
+
+impl ImportCounts {
+    fn record(&mut self, row: &ImportRow, written: bool) {
+        if !written {
+            self.skipped += 1;
+            return;
+        }
+        match row {
+            ImportRow::RawMemory(_) => self.raw_memories += 1,
+            ImportRow::Entity(_) => self.entities += 1,
+            ImportRow::Event(_) => self.event_memories += 1,
+            ImportRow::Relation(_) => self.entity_relations += 1,
+            ImportRow::Concept(_) => self.stable_concepts += 1,
+            ImportRow::View(_) => self.cognitive_views += 1,
+            ImportRow::Trend(_) => self.emotional_trends += 1,
+            ImportRow::Agent(_) => self.agents += 1,
+        }
+    }
+
+    fn add(&mut self, other: ImportCounts) {
+        self.raw_memories += other.raw_memories;
+        self.event_memories += other.event_memories;
+        self.entities += other.entities;
+        self.entity_relations += other.entity_relations;
+        self.stable_concepts += other.stable_concepts;
+        self.cognitive_views += other.cognitive_views;
+        self.emotional_trends += other.emotional_trends;
+        self.agents += other.agents;
+        self.skipped += other.skipped;
+    }
+
+    fn summary(&self, user_id: &str) -> ImportSummary {
+        ImportSummary {
+            user_id: user_id.to_string(),
+            raw_memories_imported: self.raw_memories,
+            event_memories_imported: self.event_memories,
+            entities_imported: self.entities,
+            entity_relations_imported: self.entity_relations,
+            stable_concepts_imported: self.stable_concepts,
+            cognitive_views_imported: self.cognitive_views,
+            emotional_trends_imported: self.emotional_trends,
+            agents_imported: self.agents,
+            rows_skipped: self.skipped,
+            dry_run: false,
+            watermark: None,
+            failure: None,
+        }
+    }
+}
+
+/// Import id of an import that doesn't resume an earlier run
+///
+/// Derived from the export itself, so importing the same backup again
+/// assigns the same ids and skips every row the first run wrote.
+fn export_import_id(export: &UserDataExport) -> Uuid {
+    let key = format!("{}/{}", export.user_id, export.exported_at.to_rfc3339());
+    Uuid::new_v5(&Uuid::NAMESPACE_OID, key.as_bytes())
+}
+
+/// Rows of an export in import order, with ids derived from `import_id`
+///
+/// Rows precede the rows linking to them: raw memories, entities, events,
+/// relations, then concepts (parents before children) and the views whose
+/// `promoted_to` references them, then trend points and agents. Links to rows outside the export keep
+/// their old id; `derived_from` and `counter_evidence` follow the events.
+fn import_rows(export: &UserDataExport, import_id: Uuid) -> Result<Vec<ImportRow>> {
+    let derive = |old_id: Uuid| Uuid::new_v5(&import_id, old_id.as_bytes());
+    let remap = |ids: &HashMap<Uuid, Uuid>, id: Uuid| ids.get(&id).copied().unwrap_or(id);
+
+    let raw_memories = export
+        .raw_memories
+        .iter()
+        .map(|value| serde_json::from_value::<RawMemoryExport>(value.clone()))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let memory_ids: HashMap<Uuid, Uuid> =
+        raw_memories.iter().map(|m| (m.memory_id, derive(m.memory_id))).collect();
+    let entity_ids: HashMap<Uuid, Uuid> =
+        export.entities.iter().map(|e| (e.entity_id, derive(e.entity_id))).collect();
+    let event_ids: HashMap<Uuid, Uuid> =
+        export.event_memories.iter().map(|e| (e.event_id, derive(e.event_id))).collect();
+
+    let mut rows: Vec<ImportRow> = raw_memories
+        .into_iter()
+        .map(|memory| {
+            ImportRow::RawMemory(RawMemoryExport {
+                memory_id: memory_ids[&memory.memory_id],
+                user_id: export.user_id.clone(),
+                ..memory
+            })
+        })
+        .collect();
+    rows.extend(export.entities.iter().map(|entity| {
+        ImportRow::Entity(Entity {
+            entity_id: entity_ids[&entity.entity_id],
+            user_id: export.user_id.clone(),
+            ..entity.clone()
+        })
+    }));
+    rows.extend(export.event_memories.iter().map(|event| {
+        ImportRow::Event(EventMemory {
+            event_id: event_ids[&event.event_id],
+            memory_id: remap(&memory_ids, event.memory_id),
+            user_id: export.user_id.clone(),
+            ..event.clone()
+        })
+    }));
+    rows.extend(export.entity_relations.iter().map(|relation| {
+        ImportRow::Relation(EntityRelation {
+            relation_id: derive(relation.relation_id),
+            user_id: export.user_id.clone(),
+            source_entity_id: remap(&entity_ids, relation.source_entity_id),
+            target_entity_id: remap(&entity_ids, relation.target_entity_id),
+            ..relation.clone()
+        })
+    }));
+
+    let layer =
+        remap_cognitive_layer_with(&export.cognitive_views, &export.stable_concepts, derive);
+    rows.extend(layer.concepts.into_iter().map(|concept| {
+        ImportRow::Concept(StableConcept {
+            user_id: export.user_id.clone(),
+            ..concept
+        })
+    }));
+    for view in layer.views {
+        let remap_events = |ids: &serde_json::Value, field: &str| -> Result<serde_json::Value> {
+            let ids: Vec<Uuid> = serde_json::from_value(ids.clone()).map_err(|e| {
+                DirSoulError::Config(format!(
+                    "View {} has malformed {}: {}",
+                    view.view_id, field, e
+                ))
+            })?;
+            let ids: Vec<Uuid> = ids.into_iter().map(|id| remap(&event_ids, id)).collect();
+            Ok(serde_json::to_value(ids)?)
+        };
+        let derived_from = remap_events(&view.derived_from, "derived_from")?;
+        let counter_evidence = remap_events(&view.counter_evidence, "counter_evidence")?;
+        rows.push(ImportRow::View(CognitiveView {
+            user_id: export.user_id.clone(),
+            derived_from,
+            counter_evidence,
+            ..view
+        }));
+    }
+
+    rows.extend(export.emotional_trends.iter().map(|point| {
+        ImportRow::Trend(EmotionalTrendPoint {
+            user_id: export.user_id.clone(),
+            ..point.clone()
+        })
+    }));
+    rows.extend(export.agents.iter().map(|agent| {
+        ImportRow::Agent(Agent {
+            agent_id: derive(agent.agent_id),
+            user_id: export.user_id.clone(),
+            ..agent.clone()
+        })
+    }));
+    Ok(rows)
+}
+
+/// Imports carrying raw memories only restore users without memories
+///
+/// Memories written by an earlier run of the same import don't count, so a
+/// rerun skips what it already wrote instead of failing. Exports without
+/// the raw section add no memories, and their events link to the user's
+/// existing ones.
+fn ensure_no_existing_memories(
+    conn: &mut PgConnection,
+    export: &UserDataExport,
+    rows: &[ImportRow],
+) -> Result<()> {
+    if !export.sections.include_raw {
+        return Ok(());
+    }
+    let user_id = export.user_id.as_str();
+    let imported: Vec<Uuid> = rows
+        .iter()
+        .filter_map(|row| match row {
+            ImportRow::RawMemory(memory) => Some(memory.memory_id),
+            _ => None,
+        })
+        .collect();
+    let existing_count: i64 = raw_memories::table
+        .filter(raw_memories::user_id.eq(user_id))
+        .filter(raw_memories::memory_id.ne_all(imported))
+        .count()
+        .get_result(conn)?;
+
+    if existing_count > 0 {
+        return Err(DirSoulError::Config(
+            format!("User {} already has {} records. Import not supported yet.",
+                    user_id, existing_count)
+        ));
+    }
+    Ok(())
+}
+
+/// Write an export for its user; the caller owns the transaction
+fn apply_import(conn: &mut PgConnection, export: &UserDataExport) -> Result<ImportSummary> {
+    export.validate_sections()?;
+    let rows = import_rows(export, export_import_id(export))?;
+    ensure_no_existing_memories(conn, export, &rows)?;
+
+    let mut counts = ImportCounts::default();
+    for row in &rows {
+        counts.record(row, row.insert(conn)?);
+    }
+    Ok(counts.summary(&export.user_id))
+}
+
+/// Cognitive layer prepared for import
+///
+/// Every view and concept gets a fresh id; `id_map` maps old ids to new.
+#[derive(Debug, Clone)]
+pub struct RemappedCognitiveLayer {
+    /// Views with `promoted_to` pointing at remapped concepts
+    pub views: Vec<CognitiveView>,
+    /// Concepts ordered so each parent precedes its children
+    pub concepts: Vec<StableConcept>,
+    /// Old id -> new id for both views and concepts
+    pub id_map: HashMap<Uuid, Uuid>,
+}
+
+/// Assign fresh ids to exported views/concepts and remap their links
+///
+/// `parent_concept_id` and `promoted_to` are foreign keys, so links to rows
+/// missing from the export are dropped. `promoted_from` has no constraint
+/// and is kept as-is when its view is not part of the export.
+pub fn remap_cognitive_layer(
+    views: &[CognitiveView],
+    concepts: &[StableConcept],
+) -> RemappedCognitiveLayer {
+    remap_cognitive_layer_with(views, concepts, |_| Uuid::new_v4())
+}
+
+/// `remap_cognitive_layer` with the new id of each old id from `new_id`
+fn remap_cognitive_layer_with(
+    views: &[CognitiveView],
+    concepts: &[StableConcept],
+    mut new_id: impl FnMut(Uuid) -> Uuid,
+) -> RemappedCognitiveLayer {
+    let mut id_map: HashMap<Uuid, Uuid> = HashMap::new();
+    for view in views {
+        id_map.insert(view.view_id, new_id(view.view_id));
+    }
+    for concept in concepts {
+        id_map.insert(concept.concept_id, new_id(concept.concept_id));
+    }
+
+    // Topological order: a concept is emitted once its parent has been
+    let concept_ids: HashSet<Uuid> = concepts.iter().map(|c| c.concept_id).collect();
+    let mut emitted: HashSet<Uuid> = HashSet::new();
+    let mut ordered: Vec<&StableConcept> = Vec::with_capacity(concepts.len());
+    let mut pending: Vec<&StableConcept> = concepts.iter().collect();
+    let mut detached: HashSet<Uuid> = HashSet::new();
+    while !pending.is_empty() {
+        let before = pending.len();
+        pending.retain(|concept| {
+            let ready = match concept.parent_concept_id {
+                Some(parent) => !concept_ids.contains(&parent) || emitted.contains(&parent),
+                None => true,
+            };
+            if ready {
+                emitted.insert(concept.concept_id);
+                ordered.push(*concept);
+            }
+            !ready
+        });
+        if pending.len() == before {
+            // Cycle in parent links: break it by emitting the rest as roots
+            for concept in pending.drain(..) {
+                detached.insert(concept.concept_id);
+                ordered.push(concept);
+            }
+        }
+    }
+
+    let concepts = ordered
+        .into_iter()
+        .map(|concept| {
+            let parent_concept_id = concept
+                .parent_concept_id
+                .filter(|parent| concept_ids.contains(parent))
+                .filter(|_| !detached.contains(&concept.concept_id))
+                .map(|parent| id_map[&parent]);
+            StableConcept {
+                concept_id: id_map[&concept.concept_id],
+                parent_concept_id,
+                promoted_from: concept
+                    .promoted_from
+                    .map(|view_id| id_map.get(&view_id).copied().unwrap_or(view_id)),
+                ..concept.clone()
+            }
+        })
+        .collect();
+
+    let views = views
+        .iter()
+        .map(|view| CognitiveView {
+            view_id: id_map[&view.view_id],
+            promoted_to: view
+                .promoted_to
+                .filter(|concept_id| concept_ids.contains(concept_id))
+                .map(|concept_id| id_map[&concept_id]),
+            ..view.clone()
+        })
+        .collect();
+
+    RemappedCognitiveLayer {
+        views,
+        concepts,
+        id_map,
+    }
+}
+
 /// Summary of import operation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImportSummary {
@@ -389,8 +1223,188 @@ pub struct ImportSummary {
     pub raw_memories_imported: usize,
     pub event_memories_imported: usize,
     pub entities_imported: usize,
+    #[serde(default)]
+    pub entity_relations_imported: usize,
     pub stable_concepts_imported: usize,
     pub cognitive_views_imported: usize,
+    #[serde(default)]
+    pub emotional_trends_imported: usize,
+    #[serde(default)]
+    pub agents_imported: usize,
+    /// Rows not written: already imported by an earlier run of the same
+    /// import, or linked to a memory or entity that doesn't exist
+    #[serde(default)]
+    pub rows_skipped: usize,
+    /// The import was previewed and rolled back
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Last committed position of a batched import
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watermark: Option<ImportWatermark>,
+    /// Error that stopped a batched import before its last row
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+}
+
+// ============================================================================
+// Right to Erasure
+// ============================================================================
+
+/// Confirmation token the caller must echo back to `erase_user`
+pub fn erasure_confirmation_token(user_id: &str) -> String {
+    format!("ERASE:{}", user_id)
+}
+
+/// Reject erasure requests whose token doesn't match the user
+pub fn verify_erasure_token(user_id: &str, confirm_token: &str) -> Result<()> {
+    if user_id.is_empty() {
+        return Err(DirSoulError::Config("Cannot erase an empty user_id".to_string()));
+    }
+    if confirm_token != erasure_confirmation_token(user_id) {
+        return Err(DirSoulError::PermissionDenied(format!(
+            "Invalid erasure confirmation token for user {}",
+            user_id
+        )));
+    }
+    Ok(())
+}
+
+/// Rows removed (or anonymized) by `erase_user`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EraseReport {
+    pub user_id: String,
+    pub raw_memories_deleted: usize,
+    pub event_memories_deleted: usize,
+    pub entities_deleted: usize,
+    pub entity_relations_deleted: usize,
+    pub cognitive_views_deleted: usize,
+    pub stable_concepts_deleted: usize,
+    #[serde(default)]
+    pub emotional_trends_deleted: usize,
+    #[serde(default)]
+    pub agents_deleted: usize,
+    /// Audit entries kept for compliance but detached from the user
+    pub audit_logs_anonymized: usize,
+    /// Pseudonymous id now carried by the anonymized audit entries
+    pub audit_pseudonym: String,
+    pub erased_at: DateTime<Utc>,
+}
+
+impl EraseReport {
+    /// Total number of deleted data rows (excluding anonymized audit logs)
+    pub fn total_deleted(&self) -> usize {
+        self.raw_memories_deleted
+            + self.event_memories_deleted
+            + self.entities_deleted
+            + self.entity_relations_deleted
+            + self.cognitive_views_deleted
+            + self.stable_concepts_deleted
+            + self.emotional_trends_deleted
+            + self.agents_deleted
+    }
+}
+
+/// Erase every row belonging to a user in a single transaction
+///
+/// Data tables are deleted; audit logs are anonymized instead so the access
+/// history survives without pointing at the user. A final audit record of
+/// the erasure itself is written under the same pseudonym.
+///
+/// `confirm_token` must equal `erasure_confirmation_token(user_id)`.
+pub fn erase_user(conn: &mut PgConnection, user_id: &str, confirm_token: &str) -> Result<EraseReport> {
+    verify_erasure_token(user_id, confirm_token)?;
+
+    let audit_pseudonym = format!("erased:{}", Uuid::new_v4());
+
+    let report = conn.transaction::<_, DirSoulError, _>(|conn| {
+        // Relations reference entities, events reference raw memories
+        let entity_relations_deleted = diesel::delete(
+            entity_relations::table.filter(entity_relations::user_id.eq(user_id)),
+        )
+        .execute(conn)?;
+
+        let event_memories_deleted = diesel::delete(
+            event_memories::table.filter(event_memories::user_id.eq(user_id)),
+        )
+        .execute(conn)?;
+
+        let raw_memories_deleted = diesel::delete(
+            raw_memories::table.filter(raw_memories::user_id.eq(user_id)),
+        )
+        .execute(conn)?;
+
+        let entities_deleted = diesel::delete(entities::table.filter(entities::user_id.eq(user_id)))
+            .execute(conn)?;
+
+        // Views may point at concepts via promoted_to
+        let cognitive_views_deleted = diesel::delete(
+            cognitive_views::table.filter(cognitive_views::user_id.eq(user_id)),
+        )
+        .execute(conn)?;
+
+        let stable_concepts_deleted = diesel::delete(
+            stable_concepts::table.filter(stable_concepts::user_id.eq(user_id)),
+        )
+        .execute(conn)?;
+
+        let emotional_trends_deleted = diesel::delete(
+            emotional_trends::table.filter(emotional_trends::user_id.eq(user_id)),
+        )
+        .execute(conn)?;
+
+        let agents_deleted = diesel::delete(agents::table.filter(agents::user_id.eq(user_id)))
+            .execute(conn)?;
+
+        // Settings are preferences, not memories, so they aren't counted
+        diesel::delete(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+            .execute(conn)?;
+
+        let audit_logs_anonymized = diesel::update(audit_logs::table.filter(audit_logs::user_id.eq(user_id)))
+            .set((
+                audit_logs::user_id.eq(&audit_pseudonym),
+                audit_logs::ip_address.eq(None::<String>),
+                audit_logs::metadata.eq(None::<serde_json::Value>),
+            ))
+            .execute(conn)?;
+
+        let report = EraseReport {
+            user_id: user_id.to_string(),
+            raw_memories_deleted,
+            event_memories_deleted,
+            entities_deleted,
+            entity_relations_deleted,
+            cognitive_views_deleted,
+            stable_concepts_deleted,
+            emotional_trends_deleted,
+            agents_deleted,
+            audit_logs_anonymized,
+            audit_pseudonym: audit_pseudonym.clone(),
+            erased_at: Utc::now(),
+        };
+
+        let final_record = NewAuditLog::new(audit_pseudonym.clone(), "erase".to_string(), "user_data".to_string())
+            .with_result_count(report.total_deleted() as i32)
+            .with_metadata(serde_json::json!({
+                "raw_memories": report.raw_memories_deleted,
+                "event_memories": report.event_memories_deleted,
+                "entities": report.entities_deleted,
+                "entity_relations": report.entity_relations_deleted,
+                "cognitive_views": report.cognitive_views_deleted,
+                "stable_concepts": report.stable_concepts_deleted,
+                "emotional_trends": report.emotional_trends_deleted,
+                "agents": report.agents_deleted,
+                "audit_logs_anonymized": report.audit_logs_anonymized,
+            }));
+
+        diesel::insert_into(audit_logs::table)
+            .values(&final_record)
+            .execute(conn)?;
+
+        Ok(report)
+    })?;
+
+    notify_user_data_changed(user_id);
+    Ok(report)
 }
 
 /// Auto-backup manager for scheduled backups
@@ -398,6 +1412,7 @@ pub struct AutoBackupManager {
     database_url: String,
     backup_dir: std::path::PathBuf,
     encryption: EncryptionManager,
+    export_options: ExportOptions,
 }
 
 impl AutoBackupManager {
@@ -417,12 +1432,19 @@ impl AutoBackupManager {
             database_url,
             backup_dir,
             encryption,
+            export_options: ExportOptions::default(),
         })
     }
 
+    /// Back up only the sections enabled in `options`
+    pub fn with_export_options(mut self, options: ExportOptions) -> Self {
+        self.export_options = options;
+        self
+    }
+
     /// Create backup for a specific user
     pub fn backup_user(&self, user_id: &str) -> Result<EncryptedDataExport> {
-        let exporter = DataExporter::new(self.database_url.clone());
+        let exporter = DataExporter::new(self.database_url.clone()).with_options(self.export_options);
 
         // Create filename with timestamp
         let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
@@ -455,17 +1477,52 @@ impl AutoBackupManager {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_verify_erasure_token() {
+        let token = erasure_confirmation_token("user123");
+        assert!(verify_erasure_token("user123", &token).is_ok());
+
+        // Token for another user, or a blank one, is refused
+        assert!(verify_erasure_token("user123", &erasure_confirmation_token("user456")).is_err());
+        assert!(verify_erasure_token("user123", "").is_err());
+        assert!(verify_erasure_token("", &erasure_confirmation_token("")).is_err());
+    }
+
+    #[test]
+    fn test_erase_report_total() {
+        let report = EraseReport {
+            user_id: "user123".to_string(),
+            raw_memories_deleted: 3,
+            event_memories_deleted: 5,
+            entities_deleted: 2,
+            entity_relations_deleted: 1,
+            cognitive_views_deleted: 1,
+            stable_concepts_deleted: 1,
+            emotional_trends_deleted: 4,
+            agents_deleted: 1,
+            audit_logs_anonymized: 7,
+            audit_pseudonym: "erased:test".to_string(),
+            erased_at: Utc::now(),
+        };
+        assert_eq!(report.total_deleted(), 18);
+    }
+
     #[test]
     fn test_user_data_export_serialization() {
         let export = UserDataExport {
             user_id: "test_user".to_string(),
             exported_at: Utc::now(),
             version: "1.0.0".to_string(),
+            sections: ExportOptions::default(),
             raw_memories: vec![],
             event_memories: vec![],
             entities: vec![],
+            entity_relations: vec![],
             stable_concepts: vec![],
             cognitive_views: vec![],
+            emotional_trends: vec![],
+            agents: vec![],
+            audit_logs: vec![],
             metadata: ExportMetadata::default(),
         };
 
@@ -473,6 +1530,200 @@ mod tests {
         let _deserialized: UserDataExport = serde_json::from_str(&json).unwrap();
     }
 
+    fn test_view(promoted_to: Option<Uuid>) -> CognitiveView {
+        let now = Utc::now();
+        CognitiveView {
+            view_id: Uuid::new_v4(),
+            user_id: "test_user".to_string(),
+            hypothesis: "用户喜欢吃水果".to_string(),
+            view_type: "preference".to_string(),
+            description: None,
+            derived_from: serde_json::json!([]),
+            evidence_count: 12,
+            confidence: 0.9,
+            validation_count: 4,
+            last_validated_at: Some(now),
+            status: "promoted".to_string(),
+            created_at: now - chrono::Duration::days(40),
+            updated_at: now,
+            expires_at: now - chrono::Duration::days(10),
+            promoted_to,
+            source: "pattern_detector".to_string(),
+            tags: None,
+            metadata: None,
+            counter_evidence: serde_json::json!([]),
+            counter_evidence_count: 0,
+        }
+    }
+
+    fn test_concept(version: i32, parent: Option<Uuid>, promoted_from: Option<Uuid>) -> StableConcept {
+        let now = Utc::now();
+        StableConcept {
+            concept_id: Uuid::new_v4(),
+            user_id: "test_user".to_string(),
+            canonical_name: "likes_fruit".to_string(),
+            display_name: format!("喜欢吃水果 v{}", version),
+            concept_type: "preference".to_string(),
+            description: None,
+            definition: serde_json::json!({"version": version}),
+            version,
+            parent_concept_id: parent,
+            is_deprecated: version == 1,
+            promoted_from,
+            promoted_at: now,
+            promotion_confidence: 0.9,
+            created_at: now,
+            updated_at: now,
+            deprecated_at: None,
+            access_count: 0,
+            last_accessed_at: None,
+            source: "promotion_gate".to_string(),
+            tags: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_cognitive_layer_round_trip() {
+        // view -> v1 (promoted) -> v2 (new version of v1)
+        let mut view = test_view(None);
+        let v1 = test_concept(1, None, Some(view.view_id));
+        let v2 = test_concept(2, Some(v1.concept_id), Some(view.view_id));
+        view.promoted_to = Some(v1.concept_id);
+
+        let export = UserDataExport {
+            user_id: "test_user".to_string(),
+            exported_at: Utc::now(),
+            version: "1.0.0".to_string(),
+            sections: ExportOptions::default(),
+            raw_memories: vec![],
+            event_memories: vec![],
+            entities: vec![],
+            entity_relations: vec![],
+            // Child listed before its parent on purpose
+            stable_concepts: vec![v2.clone(), v1.clone()],
+            cognitive_views: vec![view.clone()],
+            emotional_trends: vec![],
+            agents: vec![],
+            audit_logs: vec![],
+            metadata: ExportMetadata::default(),
+        };
+
+        let json = serde_json::to_string(&export).unwrap();
+        let restored: UserDataExport = serde_json::from_str(&json).unwrap();
+        let layer = remap_cognitive_layer(&restored.cognitive_views, &restored.stable_concepts);
+
+        assert_eq!(layer.views.len(), 1);
+        assert_eq!(layer.concepts.len(), 2);
+
+        let new_view = &layer.views[0];
+        let new_v1 = &layer.concepts[0];
+        let new_v2 = &layer.concepts[1];
+
+        // Parents are ordered first and every id is fresh
+        assert_eq!(new_v1.version, 1);
+        assert_eq!(new_v2.version, 2);
+        assert_ne!(new_view.view_id, view.view_id);
+        assert_ne!(new_v1.concept_id, v1.concept_id);
+        assert_ne!(new_v2.concept_id, v2.concept_id);
+
+        // Links follow the remapped ids
+        assert_eq!(new_v2.parent_concept_id, Some(new_v1.concept_id));
+        assert_eq!(new_v1.parent_concept_id, None);
+        assert_eq!(new_view.promoted_to, Some(new_v1.concept_id));
+        assert_eq!(new_v1.promoted_from, Some(new_view.view_id));
+        assert_eq!(new_v2.promoted_from, Some(new_view.view_id));
+
+        // Content is preserved
+        assert_eq!(new_v2.display_name, v2.display_name);
+        assert_eq!(new_v2.definition, v2.definition);
+        assert!(new_v1.is_deprecated);
+        assert_eq!(new_view.hypothesis, view.hypothesis);
+        assert_eq!(layer.id_map[&v1.concept_id], new_v1.concept_id);
+    }
+
+    #[test]
+    fn test_remap_drops_links_outside_export() {
+        let mut view = test_view(Some(Uuid::new_v4()));
+        let external_view = Uuid::new_v4();
+        let orphan = test_concept(2, Some(Uuid::new_v4()), Some(external_view));
+        view.promoted_to = Some(Uuid::new_v4());
+
+        let layer = remap_cognitive_layer(&[view], &[orphan]);
+
+        // FK-backed links are dropped, provenance without FK is kept
+        assert_eq!(layer.views[0].promoted_to, None);
+        assert_eq!(layer.concepts[0].parent_concept_id, None);
+        assert_eq!(layer.concepts[0].promoted_from, Some(external_view));
+    }
+
+    #[test]
+    fn test_partial_export_omits_sections() {
+        let export = UserDataExport {
+            user_id: "test_user".to_string(),
+            exported_at: Utc::now(),
+            version: "1.0.0".to_string(),
+            sections: ExportOptions {
+                include_events: true,
+                ..ExportOptions::none()
+            },
+            raw_memories: vec![],
+            event_memories: vec![],
+            entities: vec![],
+            entity_relations: vec![],
+            stable_concepts: vec![],
+            cognitive_views: vec![],
+            emotional_trends: vec![],
+            agents: vec![],
+            audit_logs: vec![],
+            metadata: ExportMetadata::default(),
+        };
+        assert!(export.validate_sections().is_ok());
+
+        let json: serde_json::Value = serde_json::to_value(&export).unwrap();
+        for section in ["raw_memories", "entities", "entity_relations", "stable_concepts", "cognitive_views", "audit_logs"] {
+            assert!(json.get(section).is_none(), "{} should be omitted", section);
+        }
+        assert_eq!(json["sections"]["include_events"], true);
+        assert_eq!(json["sections"]["include_audit"], false);
+
+        let restored: UserDataExport = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.sections, export.sections);
+        assert!(restored.cognitive_views.is_empty());
+
+        // Data in a section marked as omitted is rejected
+        let mut inconsistent = restored;
+        inconsistent.cognitive_views.push(test_view(None));
+        assert!(matches!(inconsistent.validate_sections(), Err(DirSoulError::Config(_))));
+    }
+
+    #[test]
+    fn test_legacy_export_reads_as_full() {
+        let legacy = r#"{
+            "user_id": "u",
+            "exported_at": "2026-01-01T00:00:00Z",
+            "version": "1.0.0",
+            "raw_memories": [],
+            "event_memories": [],
+            "entities": [],
+            "stable_concepts": [],
+            "cognitive_views": [],
+            "metadata": {
+                "raw_memory_count": 0,
+                "event_memory_count": 0,
+                "entity_count": 0,
+                "stable_concept_count": 0,
+                "cognitive_view_count": 0,
+                "encrypted_size": null,
+                "export_duration_secs": null
+            }
+        }"#;
+        let export: UserDataExport = serde_json::from_str(legacy).unwrap();
+        assert_eq!(export.sections, ExportOptions::default());
+        assert!(export.entity_relations.is_empty());
+        assert_eq!(export.metadata.audit_log_count, 0);
+    }
+
     #[test]
     fn test_encrypted_data_export_serialization() {
         let export = EncryptedDataExport {
@@ -502,12 +1753,190 @@ mod tests {
             raw_memories_imported: 10,
             event_memories_imported: 20,
             entities_imported: 5,
+            entity_relations_imported: 4,
             stable_concepts_imported: 2,
             cognitive_views_imported: 3,
+            emotional_trends_imported: 0,
+            agents_imported: 0,
+            rows_skipped: 1,
+            dry_run: true,
+            watermark: None,
+            failure: None,
         };
 
         let json = serde_json::to_string(&summary).unwrap();
-        let _deserialized: ImportSummary = serde_json::from_str(&json).unwrap();
+        let deserialized: ImportSummary = serde_json::from_str(&json).unwrap();
+        assert!(deserialized.dry_run);
+        assert_eq!(deserialized.watermark, None);
+
+        // Summaries written before dry runs existed
+        let legacy: ImportSummary = serde_json::from_str(
+            r#"{"user_id":"u","raw_memories_imported":0,"event_memories_imported":0,"entities_imported":0,"stable_concepts_imported":0,"cognitive_views_imported":0}"#,
+        )
+        .unwrap();
+        assert!(!legacy.dry_run);
+    }
+
+    #[test]
+    fn test_import_rows_are_stable_per_import() {
+        let concept = test_concept(1, None, None);
+        let view = test_view(Some(concept.concept_id));
+        let export = UserDataExport {
+            user_id: "test_user".to_string(),
+            exported_at: Utc::now(),
+            version: "1.0.0".to_string(),
+            sections: ExportOptions::default(),
+            raw_memories: vec![],
+            event_memories: vec![],
+            entities: vec![],
+            entity_relations: vec![],
+            stable_concepts: vec![concept],
+            cognitive_views: vec![view],
+            emotional_trends: vec![],
+            agents: vec![],
+            audit_logs: vec![],
+            metadata: ExportMetadata::default(),
+        };
+
+        let import_id = Uuid::new_v4();
+        let ids = |rows: Vec<ImportRow>| -> Vec<(Uuid, Option<Uuid>)> {
+            rows.into_iter()
+                .map(|row| match row {
+                    ImportRow::Concept(c) => (c.concept_id, c.parent_concept_id),
+                    ImportRow::View(v) => (v.view_id, v.promoted_to),
+                    other => panic!("unexpected row {:?}", other),
+                })
+                .collect()
+        };
+        let first = ids(import_rows(&export, import_id).unwrap());
+        assert_eq!(first, ids(import_rows(&export, import_id).unwrap()));
+        assert_ne!(first, ids(import_rows(&export, Uuid::new_v4()).unwrap()));
+
+        // Concepts precede the views linking to them
+        assert_eq!(first[1].1, Some(first[0].0));
+    }
+
+    #[test]
+    fn test_import_rows_assign_concepts_and_views_to_export_user() {
+        let concept = StableConcept {
+            user_id: "other_user".to_string(),
+            ..test_concept(1, None, None)
+        };
+        let view = CognitiveView {
+            user_id: "other_user".to_string(),
+            ..test_view(Some(concept.concept_id))
+        };
+        let export = UserDataExport {
+            user_id: "test_user".to_string(),
+            exported_at: Utc::now(),
+            version: "1.0.0".to_string(),
+            sections: ExportOptions::default(),
+            raw_memories: vec![],
+            event_memories: vec![],
+            entities: vec![],
+            entity_relations: vec![],
+            stable_concepts: vec![concept],
+            cognitive_views: vec![view],
+            emotional_trends: vec![],
+            agents: vec![],
+            audit_logs: vec![],
+            metadata: ExportMetadata::default(),
+        };
+
+        let rows = import_rows(&export, Uuid::new_v4()).unwrap();
+        assert_eq!(rows.len(), 2);
+        for row in rows {
+            let user_id = match row {
+                ImportRow::Concept(c) => c.user_id,
+                ImportRow::View(v) => v.user_id,
+                other => panic!("unexpected row {:?}", other),
+            };
+            assert_eq!(user_id, "test_user");
+        }
+    }
+
+    #[test]
+    fn test_import_rows_remap_links_to_imported_rows() {
+        let now = Utc::now();
+        let memory_id = Uuid::new_v4();
+        let event = EventMemory {
+            event_id: Uuid::new_v4(),
+            memory_id,
+            user_id: "test_user".to_string(),
+            timestamp: now,
+            actor: None,
+            action: "吃".to_string(),
+            target: "苹果".to_string(),
+            quantity: None,
+            unit: None,
+            confidence: 0.9,
+            extractor_version: None,
+            metadata: None,
+        };
+        let outside_event = Uuid::new_v4();
+        let view = CognitiveView {
+            derived_from: serde_json::json!([event.event_id, outside_event]),
+            ..test_view(None)
+        };
+        let raw = RawMemoryExport {
+            memory_id,
+            user_id: "test_user".to_string(),
+            created_at: now,
+            content_type: "text".to_string(),
+            content: Some("吃了苹果".to_string()),
+            encrypted: None,
+            metadata: None,
+        };
+        let mut export = UserDataExport {
+            user_id: "test_user".to_string(),
+            exported_at: now,
+            version: "1.0.0".to_string(),
+            sections: ExportOptions::default(),
+            raw_memories: vec![serde_json::to_value(&raw).unwrap()],
+            event_memories: vec![event.clone()],
+            entities: vec![],
+            entity_relations: vec![],
+            stable_concepts: vec![],
+            cognitive_views: vec![view],
+            emotional_trends: vec![],
+            agents: vec![],
+            audit_logs: vec![],
+            metadata: ExportMetadata::default(),
+        };
+
+        let rows = import_rows(&export, Uuid::new_v4()).unwrap();
+        let (ImportRow::RawMemory(memory), ImportRow::Event(imported), ImportRow::View(view)) =
+            (&rows[0], &rows[1], &rows[2])
+        else {
+            panic!("unexpected rows {:?}", rows);
+        };
+        assert_ne!(memory.memory_id, memory_id);
+        assert_eq!(imported.memory_id, memory.memory_id);
+        // Supporting events follow the import; others keep their id
+        assert_eq!(view.derived_from, serde_json::json!([imported.event_id, outside_event]));
+
+        // A malformed link is an error, not an empty list
+        export.cognitive_views[0].derived_from = serde_json::json!({"ids": []});
+        assert!(matches!(
+            import_rows(&export, Uuid::new_v4()),
+            Err(DirSoulError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_import_options_and_watermark() {
+        assert!(ImportOptions::default().validate().is_ok());
+        assert!(ImportOptions { batch_size: 0, ..Default::default() }.validate().is_err());
+
+        let watermark = ImportWatermark {
+            import_id: Uuid::new_v4(),
+            rows_committed: 2,
+            total_rows: 5,
+        };
+        assert!(!watermark.is_complete());
+        assert!(ImportWatermark { rows_committed: 5, ..watermark }.is_complete());
+        let options = ImportOptions::default().with_resume_from(watermark);
+        assert_eq!(options.resume_from, Some(watermark));
     }
 
     #[test]
@@ -19,14 +19,65 @@ use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
 use warp::Filter;
 
-use crate::audit::ThreadSafeAuditLogger;
+use crate::audit::{AuditBufferConfig, ThreadSafeAuditLogger};
+use crate::cognitive::{
+    approve_view_promotion, list_promotion_candidates, list_views_ready_for_promotion,
+    rollback_concept, CognitiveView, PromotionCandidateConfig, PromotionCandidatePage,
+    StableConcept, ViewStatus,
+};
+use crate::data_changes::{subscribe_user_data_changes, UserDataListener};
+use crate::data_lifecycle::live_memory_ids;
+use crate::embedding::{check_vector_capability, EmbeddingGenerator};
+use crate::entity_relation_extractor::EntityRelationExtractor;
 use crate::error::{DirSoulError, Result};
-use crate::llm_provider::ChatMessage;
-use crate::models::{EventMemory, Entity, RawMemory, NewRawMemory};
-use crate::schema::{event_memories, entities, raw_memories};
+use crate::event_aggregator::{AggregateOutput, AggregateQuery, EventAggregator};
+use crate::event_storage::filter_by_quantity;
+use crate::llm_provider::{ChatMessage, GenerateOptions, LLMProvider, LlmGovernor, OllamaProvider};
+use crate::models::{
+    rank_entities_by_importance, EventMemory, Entity, EntityImportanceConfig, EntityRelation,
+    EntityType, RawMemory, NewRawMemory,
+};
+use crate::pattern_detector::{
+    DetectionTimeRange, PatternDetectionResult, PatternDetectionScheduler, PatternDetector,
+    PatternDetectorConfig, PatternType,
+};
+use crate::plugin::{validate_quantity_range, CommandResponse, CommandRouter, PluginManager};
+use crate::prompt_manager::{PromptTask, SystemPrompts};
+use crate::schema::{
+    cognitive_views, entities, event_entity_links, event_memories, raw_memories, stable_concepts,
+};
+use crate::user_settings::UserSettings;
+use crate::view_generator::ViewGenerator;
+use crate::webhook::{WebhookEvent, WebhookNotifier};
+
+/// Promotion candidates per page when the request gives no `limit`
+const DEFAULT_CANDIDATE_PAGE_SIZE: usize = 20;
+
+/// Most promotion candidates per page
+const MAX_CANDIDATE_PAGE_SIZE: usize = 100;
+
+/// Entities listed in `/api/stats`
+const TOP_ENTITY_COUNT: usize = 10;
+
+/// Most frequent and most recent entities each considered for the top list
+const TOP_ENTITY_CANDIDATES: i64 = 200;
+
+/// Longest window accepted by `POST /api/patterns/detect`
+const MAX_PATTERN_DETECTION_DAYS: i64 = 365;
+
+/// Ollama host answering `/api/chat` unless `with_chat_provider` is used
+const DEFAULT_CHAT_HOST: &str = "http://localhost:11434";
+
+/// Model answering `/api/chat` unless `with_chat_provider` is used
+///
+/// The binary passes the models.toml inference model instead, so the
+/// served model is the one it preloads.
+const DEFAULT_CHAT_MODEL: &str = "qwen2:0.5b";
+
+/// Timeout for one chat generation request
+const CHAT_TIMEOUT_SECS: u64 = 15;
 
 /// Chat request from Python
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,769 +93,6691 @@ pub struct ChatRequest {
 
     /// Optional context
     pub context: Option<serde_json::Value>,
+
+    /// Replay key; the `Idempotency-Key` header takes precedence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
 }
 
-/// Chat response to Python (renamed to avoid conflict with llm_provider::ChatResponse)
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ApiChatResponse {
-    /// Response text
-    pub response: String,
+/// Longest accepted idempotency key
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
 
-    /// Updated conversation history
-    pub history: Vec<ChatMessage>,
+/// Replay cache settings for `/api/chat`
+///
+/// A retried request carrying the same idempotency key as an earlier one
+/// from the same user gets the earlier response back instead of being
+/// processed again. Reusing a key for a different request is refused with
+/// 422. Failed requests are not cached.
+#[derive(Debug, Clone)]
+pub struct IdempotencyConfig {
+    /// How long a response stays replayable (default: 10 minutes)
+    pub ttl: std::time::Duration,
 
-    /// Memories recorded (IDs)
-    pub recorded_memory_ids: Vec<String>,
+    /// Most cached responses across all users; the oldest is evicted first
+    /// (default: 1000)
+    pub max_entries: usize,
+}
 
-    /// Processing time in milliseconds
-    pub processing_time_ms: u64,
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            ttl: std::time::Duration::from_secs(600),
+            max_entries: 1000,
+        }
+    }
+}
 
-    /// Additional metadata
-    pub metadata: Option<serde_json::Value>,
+/// Cached chat response; concurrent retries wait on the same cell
+struct ChatReplayEntry {
+    response: Arc<tokio::sync::OnceCell<ApiChatResponse>>,
+    /// SHA-256 of the request the key was first used for
+    fingerprint: [u8; 32],
+    stored_at: std::time::Instant,
 }
 
-/// Timeline request
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TimelineRequest {
-    /// User ID
-    pub user_id: String,
+/// Fingerprint of a chat request, ignoring where its idempotency key came from
+fn chat_fingerprint(req: &ChatRequest) -> Result<[u8; 32]> {
+    use sha2::{Digest, Sha256};
 
-    /// Start date (ISO format)
-    pub start_date: String,
+    let body = serde_json::to_vec(&ChatRequest {
+        idempotency_key: None,
+        ..req.clone()
+    })?;
+    Ok(Sha256::digest(body).into())
+}
 
-    /// End date (ISO format)
-    pub end_date: String,
+/// Result of processing a chat request under an idempotency key
+#[derive(Debug)]
+enum ChatOutcome {
+    /// Processed by this request
+    Processed(ApiChatResponse),
+    /// Replayed from an earlier request with the same key
+    Replayed(ApiChatResponse),
+    /// The key was already used for a different request
+    KeyReused,
+}
 
-    /// Filters
-    pub filters: Option<TimelineFilters>,
+/// Bounded chat response cache keyed by (user id, idempotency key)
+struct ChatReplayCache {
+    config: IdempotencyConfig,
+    entries: std::sync::Mutex<HashMap<(String, String), ChatReplayEntry>>,
 }
 
-/// Timeline filters
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TimelineFilters {
-    /// Filter by entity
-    pub entities: Option<Vec<String>>,
+impl ChatReplayCache {
+    fn new(config: IdempotencyConfig) -> Self {
+        Self {
+            config,
+            entries: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
 
-    /// Filter by event type
-    pub event_types: Option<Vec<String>>,
+    /// Lock the entries, recovering them when a request panicked holding the lock
+    fn lock_entries(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<(String, String), ChatReplayEntry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
 
-    /// Minimum confidence
-    pub min_confidence: Option<f64>,
-}
+    /// Cell holding the response for a key, creating it if needed
+    ///
+    /// `None` when the key is cached for a request with another fingerprint.
+    fn slot(
+        &self,
+        user_id: &str,
+        key: &str,
+        fingerprint: [u8; 32],
+    ) -> Option<Arc<tokio::sync::OnceCell<ApiChatResponse>>> {
+        let now = std::time::Instant::now();
+        let mut entries = self.lock_entries();
+        entries.retain(|_, entry| now.duration_since(entry.stored_at) < self.config.ttl);
 
-/// Timeline event
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TimelineEvent {
-    /// Event ID
-    pub event_id: String,
+        let cache_key = (user_id.to_string(), key.to_string());
+        if let Some(entry) = entries.get(&cache_key) {
+            return (entry.fingerprint == fingerprint).then(|| entry.response.clone());
+        }
 
-    /// Timestamp
-    pub timestamp: String,
+        while entries.len() >= self.config.max_entries.max(1) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.stored_at)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => entries.remove(&oldest),
+                None => break,
+            };
+        }
 
-    /// Actor
-    pub actor: Option<String>,
+        let response = Arc::new(tokio::sync::OnceCell::new());
+        entries.insert(
+            cache_key,
+            ChatReplayEntry {
+                response: response.clone(),
+                fingerprint,
+                stored_at: now,
+            },
+        );
+        Some(response)
+    }
 
-    /// Action
-    pub action: String,
+    fn len(&self) -> usize {
+        self.lock_entries().len()
+    }
+}
 
-    /// Target
-    pub target: String,
+/// Response cache settings for `/api/stats` and `/api/timeline`
+///
+/// Dashboards poll these endpoints with identical parameters; a repeated
+/// request within `ttl` is answered from the cache without querying the
+/// database. Entries are dropped early by `QueryCache::invalidate_user`.
+#[derive(Debug, Clone)]
+pub struct QueryCacheConfig {
+    /// How long a response is served from the cache; zero disables caching
+    /// (default: 30 seconds)
+    pub ttl: std::time::Duration,
 
-    /// Quantity
-    pub quantity: Option<f64>,
+    /// Most cached responses across all users (default: 500)
+    pub max_entries: usize,
 
-    /// Unit
-    pub unit: Option<String>,
+    /// Most bytes of cached response bodies across all users; larger
+    /// responses are never cached (default: 16 MiB)
+    pub max_bytes: usize,
+}
 
-    /// Confidence
-    pub confidence: f64,
+impl Default for QueryCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: std::time::Duration::from_secs(30),
+            max_entries: 500,
+            max_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
 
-    /// Related entities
-    pub entities: Vec<String>,
+/// Cache key: endpoint, user and the serialized request parameters
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct QueryCacheKey {
+    endpoint: &'static str,
+    user_id: String,
+    params: String,
 }
 
-/// Timeline response
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TimelineResponse {
-    /// Events grouped by date
-    pub events_by_date: HashMap<String, Vec<TimelineEvent>>,
+impl QueryCacheKey {
+    fn stats(req: &StatsRequest) -> Self {
+        Self {
+            endpoint: "stats",
+            user_id: req.user_id.clone(),
+            params: req.time_range.clone(),
+        }
+    }
 
-    /// Total event count
-    pub total_events: usize,
+    fn timeline(req: &TimelineRequest) -> Self {
+        Self {
+            endpoint: "timeline",
+            user_id: req.user_id.clone(),
+            params: serde_json::to_string(&(&req.start_date, &req.end_date, &req.timezone, &req.filters))
+                .unwrap_or_default(),
+        }
+    }
+}
 
-    /// Summary statistics
-    pub summary: TimelineSummary,
+/// Serialized response with the result count recorded in the audit log
+#[derive(Debug, Clone)]
+struct CachedQuery {
+    body: Arc<str>,
+    result_count: i32,
 }
 
-/// Timeline summary statistics
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TimelineSummary {
-    /// Total days in range
-    pub total_days: i64,
+impl CachedQuery {
+    fn into_reply(self) -> warp::reply::Response {
+        use warp::Reply;
+        warp::reply::with_header(self.body.to_string(), "content-type", "application/json").into_response()
+    }
+}
 
-    /// Average events per day
-    pub avg_events_per_day: f64,
+struct QueryCacheEntry {
+    response: CachedQuery,
+    stored_at: std::time::Instant,
+}
 
-    /// Most active date
-    pub most_active_date: String,
+#[derive(Default)]
+struct QueryCacheState {
+    entries: HashMap<QueryCacheKey, QueryCacheEntry>,
+    /// Total body bytes of `entries`
+    bytes: usize,
+    /// Bumped by every invalidation, so a query that raced a write is not stored
+    writes: u64,
+}
 
-    /// Top entities
-    pub top_entities: Vec<String>,
+/// Bounded, short-lived cache of serialized query responses
+///
+/// `HttpServer` subscribes its cache to `data_changes`, so every storage
+/// write in the process calls `invalidate_user` and users never see stale
+/// results for longer than a request.
+pub struct QueryCache {
+    config: QueryCacheConfig,
+    state: std::sync::Mutex<QueryCacheState>,
 }
 
-/// Statistics request
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StatsRequest {
-    /// User ID
-    pub user_id: String,
+impl QueryCache {
+    /// Create an empty cache
+    pub fn new(config: QueryCacheConfig) -> Self {
+        Self {
+            config,
+            state: std::sync::Mutex::new(QueryCacheState::default()),
+        }
+    }
 
-    /// Time range: "7d", "30d", "90d", "all"
-    pub time_range: String,
-}
+    /// Lock the state, recovering it when a query panicked while holding it
+    ///
+    /// Every critical section leaves `entries` usable, so a poisoned lock
+    /// must not turn one failed request into failures for every later one.
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, QueryCacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
 
-/// Statistics response
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StatsResponse {
-    /// Total memories
-    pub total_memories: usize,
+    /// Drop every cached response for `user_id` after their data changed
+    pub fn invalidate_user(&self, user_id: &str) {
+        let mut state = self.lock_state();
+        state.writes += 1;
+        let mut freed = 0;
+        state.entries.retain(|key, entry| {
+            let keep = key.user_id != user_id;
+            if !keep {
+                freed += entry.response.body.len();
+            }
+            keep
+        });
+        state.bytes -= freed;
+    }
 
-    /// Total events
-    pub total_events: usize,
+    /// Number of cached responses
+    pub fn len(&self) -> usize {
+        self.lock_state().entries.len()
+    }
 
-    /// Total entities
-    pub total_entities: usize,
+    /// Whether nothing is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 
-    /// Events per day
-    pub events_per_day: HashMap<String, i64>,
+    /// Serve `key` from the cache, or run the query and cache its response
+    ///
+    /// Failed queries are not cached. `count` gives the result count logged
+    /// for the response.
+    fn get_or_run<T, F, C>(&self, key: QueryCacheKey, run: F, count: C) -> Result<CachedQuery>
+    where
+        T: Serialize,
+        F: FnOnce() -> Result<T>,
+        C: FnOnce(&T) -> i32,
+    {
+        let writes = {
+            let now = std::time::Instant::now();
+            let mut state = self.lock_state();
+            let ttl = self.config.ttl;
+            let mut freed = 0;
+            state.entries.retain(|_, entry| {
+                let fresh = now.duration_since(entry.stored_at) < ttl;
+                if !fresh {
+                    freed += entry.response.body.len();
+                }
+                fresh
+            });
+            state.bytes -= freed;
 
-    /// Event type distribution
-    pub event_types: HashMap<String, i64>,
+            if let Some(entry) = state.entries.get(&key) {
+                return Ok(entry.response.clone());
+            }
+            state.writes
+        };
 
-    /// Entity frequency
-    pub entities: Vec<EntityStat>,
+        let value = run()?;
+        let response = CachedQuery {
+            body: serde_json::to_string(&value)?.into(),
+            result_count: count(&value),
+        };
 
-    /// Time range stats
-    pub time_range: TimeRangeStats,
+        let size = response.body.len();
+        let max_entries = self.config.max_entries.max(1);
+        let mut state = self.lock_state();
+        if self.config.ttl.is_zero() || size > self.config.max_bytes || state.writes != writes {
+            return Ok(response);
+        }
+        while state.entries.len() >= max_entries || state.bytes + size > self.config.max_bytes {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.stored_at)
+                .map(|(key, _)| key.clone());
+            match oldest.and_then(|oldest| state.entries.remove(&oldest)) {
+                Some(evicted) => state.bytes -= evicted.response.body.len(),
+                None => break,
+            }
+        }
+        state.bytes += size;
+        state.entries.insert(
+            key,
+            QueryCacheEntry {
+                response: response.clone(),
+                stored_at: std::time::Instant::now(),
+            },
+        );
+        Ok(response)
+    }
 }
 
-/// Entity statistic
+/// Generation settings for `/api/chat`
+///
+/// History is sent newest-first, a whole turn (user + assistant message)
+/// at a time, until `history_turns` turns are included or the next turn
+/// would push the prompt past `max_prompt_tokens`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EntityStat {
-    /// Entity name
-    pub name: String,
+#[serde(default)]
+pub struct ChatConfig {
+    /// Most recent conversation turns sent to the model (default: 2)
+    pub history_turns: usize,
 
-    /// Frequency
-    pub frequency: i64,
+    /// Estimated prompt token budget; `None` limits by turn count only
+    pub max_prompt_tokens: Option<usize>,
 
-    /// First seen
-    pub first_seen: String,
+    /// Maximum tokens generated per reply (`num_predict`, default: 256)
+    pub max_output_tokens: u32,
 
-    /// Last seen
-    pub last_seen: String,
+    /// Sampling temperature (default: 0.7)
+    pub temperature: f32,
+
+    /// Most retrieved sources added to the prompt; 0 disables retrieval
+    /// (default: 3). Only used when a `ChatRetriever` is set.
+    pub retrieval_top_k: usize,
 }
 
-/// Time range statistics
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TimeRangeStats {
-    /// Start date
-    pub start_date: String,
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            history_turns: 2,
+            max_prompt_tokens: None,
+            max_output_tokens: 256,
+            temperature: 0.7,
+            retrieval_top_k: 3,
+        }
+    }
+}
 
-    /// End date
-    pub end_date: String,
+/// Longest snippet shown in a recall explanation, in characters
+const MAX_SNIPPET_CHARS: usize = 80;
 
-    /// Total days
-    pub total_days: i64,
+/// What a recall explanation points at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecallSource {
+    /// A raw memory
+    Memory,
+    /// An event extracted from a memory
+    Event,
+    /// An active cognitive view derived from such events
+    View,
+}
 
-    /// Most active day
-    pub most_active_day: String,
+/// One retrieved source that informed a chat reply
+///
+/// Returned in `ApiChatResponse.metadata.explanations`, most relevant first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecallExplanation {
+    /// Kind of source
+    pub source: RecallSource,
 
-    /// Least active day
-    pub least_active_day: String,
+    /// Memory, event or view id
+    pub id: uuid::Uuid,
+
+    /// Short excerpt shown to the user and added to the prompt
+    pub snippet: String,
+
+    /// Similarity of the source to the message (higher is closer)
+    pub relevance: f64,
 }
 
-/// HTTP API server
-pub struct HttpServer {
-    /// Bind address
-    bind_address: String,
-    /// Database URL
-    database_url: String,
-    /// In-memory data store (for demo purposes)
-    data: Arc<RwLock<HashMap<String, serde_json::Value>>>,
-    /// Audit logger for recording all operations
-    audit_logger: Arc<ThreadSafeAuditLogger>,
+/// Finds the sources a chat reply is grounded in
+#[async_trait::async_trait]
+pub trait ChatRetriever: Send + Sync {
+    /// Up to `top_k` memories relevant to `query`, plus what was derived from them
+    async fn retrieve(&self, user_id: &str, query: &str, top_k: usize) -> Result<Vec<RecallExplanation>>;
 }
 
-impl HttpServer {
-    /// Create a new HTTP server
-    pub fn new(bind_address: String, database_url: String) -> Result<Self> {
-        let audit_logger = Arc::new(ThreadSafeAuditLogger::new(database_url.clone()));
+/// `ChatRetriever` backed by semantic search over raw memories
+///
+/// Each memory above `min_similarity` is returned together with the events
+/// extracted from it and the active views derived from those events; events
+/// and views inherit the relevance of their memory.
+pub struct SemanticChatRetriever {
+    database_url: String,
+    embedder: Arc<EmbeddingGenerator>,
+    min_similarity: f64,
+    keyword_index: KeywordIndexConfig,
+}
 
-        Ok(Self {
-            bind_address,
+impl SemanticChatRetriever {
+    /// Retriever over the memories in `database_url`
+    pub fn new(database_url: String, embedder: Arc<EmbeddingGenerator>) -> Self {
+        Self {
             database_url,
-            data: Arc::new(RwLock::new(HashMap::new())),
-            audit_logger,
-        })
+            embedder,
+            min_similarity: SearchConfig::default().min_similarity,
+            keyword_index: KeywordIndexConfig::default(),
+        }
     }
 
-    /// Process chat message - V3 Simplified (Client-side history)
-    /// Uses client-provided history and calls LLM for semantic understanding
-    fn process_chat(&self, req: ChatRequest) -> Result<ApiChatResponse> {
-        let start = std::time::Instant::now();
+    /// Set the minimum similarity for a memory to be used
+    pub fn with_min_similarity(mut self, min_similarity: f64) -> Self {
+        self.min_similarity = min_similarity;
+        self
+    }
 
-        // Build LLM prompt - 只包含年龄计算的few-shot
-        let mut conversation = String::from(r#"今年25→明年26。今年30→明年31。
-"#);
+    /// Set the tokenizer used when falling back to keyword search
+    pub fn with_keyword_index(mut self, keyword_index: KeywordIndexConfig) -> Self {
+        self.keyword_index = keyword_index;
+        self
+    }
+}
 
-        // 只发送最近2轮对话（4条消息）- 倒序排列
-        let recent_count = 4;
-        let start_idx = if req.history.len() > recent_count {
-            req.history.len() - recent_count
-        } else {
-            0
-        };
+#[async_trait::async_trait]
+impl ChatRetriever for SemanticChatRetriever {
+    async fn retrieve(&self, user_id: &str, query: &str, top_k: usize) -> Result<Vec<RecallExplanation>> {
+        let min_similarity = self.min_similarity;
+        let hits: Vec<SearchHit> = search_memories(
+            self.database_url.clone(),
+            Some(self.embedder.clone()),
+            self.keyword_index.clone(),
+            user_id.to_string(),
+            query.to_string(),
+            top_k,
+        )
+        .await?
+        .into_iter()
+        .filter(|hit| hit.similarity >= min_similarity)
+        .collect();
+        if hits.is_empty() {
+            return Ok(vec![]);
+        }
 
-        // 收集最近的对话
-        let recent_messages: Vec<_> = req.history.iter().skip(start_idx).collect();
+        let database_url = self.database_url.clone();
+        let user_id = user_id.to_string();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = PgConnection::establish(&database_url)?;
+            let ids: Vec<uuid::Uuid> = hits.iter().map(|hit| hit.memory_id).collect();
 
-        // 倒序添加历史（最新的在最前面）
-        for msg in recent_messages.iter().rev() {
-            conversation.push_str(&format!("{}: {}\n",
-                if msg.role == "user" { "用户" } else { "助手" },
-                msg.content
-            ));
-        }
+            let memories: Vec<(uuid::Uuid, Option<String>)> = raw_memories::table
+                .filter(raw_memories::memory_id.eq_any(&ids))
+                .filter(raw_memories::deleted_at.is_null())
+                .select((raw_memories::memory_id, raw_memories::content))
+                .load(&mut conn)?;
+            let events: Vec<EventMemory> = event_memories::table
+                .filter(event_memories::memory_id.eq_any(&ids))
+                .load(&mut conn)?;
+            let views: Vec<CognitiveView> = cognitive_views::table
+                .filter(cognitive_views::user_id.eq(&user_id))
+                .filter(cognitive_views::status.eq(String::from(ViewStatus::Active)))
+                .load(&mut conn)?;
 
-        // 添加最新消息
-        conversation.push_str(&format!("用户: {}\n", req.message));
+            Ok(build_recall_explanations(&hits, &memories, &events, &views))
+        })
+        .await
+        .map_err(|e| DirSoulError::ExternalError(format!("Chat retrieval task failed: {}", e)))?
+    }
+}
 
-        // 添加简短提示
-        conversation.push_str("回答（10字内）：\n");
-
-        // Call LLM - 使用qwen2:0.5b
-        let ollama_url = format!("{}/api/generate", "http://localhost:11434");
-        let ollama_request = serde_json::json!({
-            "model": "qwen2:0.5b",
-            "prompt": conversation,
-            "stream": false,
-            "options": {
-                "num_predict": 30,
-                "temperature": 0.7
-            }
-        });
+/// First `MAX_SNIPPET_CHARS` characters of `text`, with an ellipsis if cut
+fn snippet(text: &str) -> String {
+    let text = text.trim();
+    if text.chars().count() <= MAX_SNIPPET_CHARS {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(MAX_SNIPPET_CHARS).collect();
+    cut.push('…');
+    cut
+}
 
-        let client = reqwest::blocking::Client::builder()
-            .timeout(std::time::Duration::from_secs(15))
-            .build()
-            .map_err(|e| DirSoulError::Config(format!("Failed to create HTTP client: {}", e)))?;
-
-        let response_text = match client
-            .post(&ollama_url)
-            .header("Content-Type", "application/json")
-            .body(ollama_request.to_string())
-            .send() {
-            Ok(resp) => {
-                eprintln!("HTTP status: {}", resp.status());
-                if resp.status().is_success() {
-                    match resp.json::<serde_json::Value>() {
-                        Ok(json) => {
-                            eprintln!("JSON response: {:?}", json);
-                            let raw = json["response"].as_str()
-                                .unwrap_or("").trim();
-                            if raw.is_empty() {
-                                "我收到你的消息了。".to_string()
-                            } else {
-                                raw.to_string()
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("JSON error: {:?}", e);
-                            "我收到你的消息了。".to_string()
-                        }
-                    }
-                } else {
-                    let body = resp.text().unwrap_or_default();
-                    eprintln!("HTTP error, body: {}", body);
-                    "我收到你的消息了。".to_string()
-                }
-            }
-            Err(e) => {
-                eprintln!("Request error: {:?}", e);
-                "我收到你的消息了。".to_string()
-            }
-        };
+/// Explanations for the memories in `hits` and what was derived from them
+///
+/// Memories missing from `memories` (deleted since the search) are dropped
+/// along with their events. Sorted by relevance, memories before the events
+/// and views that share their score.
+fn build_recall_explanations(
+    hits: &[SearchHit],
+    memories: &[(uuid::Uuid, Option<String>)],
+    events: &[EventMemory],
+    views: &[CognitiveView],
+) -> Vec<RecallExplanation> {
+    let relevance: HashMap<uuid::Uuid, f64> = hits
+        .iter()
+        .filter(|hit| memories.iter().any(|(id, _)| *id == hit.memory_id))
+        .map(|hit| (hit.memory_id, hit.similarity))
+        .collect();
 
-        // Update history
-        let mut updated_history = req.history.clone();
-        updated_history.push(ChatMessage {
-            role: "user".to_string(),
-            content: req.message.clone(),
-        });
-        updated_history.push(ChatMessage {
-            role: "assistant".to_string(),
-            content: response_text.clone(),
+    let mut explanations: Vec<RecallExplanation> = memories
+        .iter()
+        .filter_map(|(id, content)| {
+            Some(RecallExplanation {
+                source: RecallSource::Memory,
+                id: *id,
+                snippet: snippet(content.as_deref().unwrap_or("(encrypted)")),
+                relevance: *relevance.get(id)?,
+            })
+        })
+        .collect();
+
+    let mut event_relevance: HashMap<uuid::Uuid, f64> = HashMap::new();
+    for event in events {
+        let Some(&score) = relevance.get(&event.memory_id) else {
+            continue;
+        };
+        event_relevance.insert(event.event_id, score);
+        explanations.push(RecallExplanation {
+            source: RecallSource::Event,
+            id: event.event_id,
+            snippet: snippet(&format!("{} {}", event.action, event.target)),
+            relevance: score,
         });
+    }
 
-        Ok(ApiChatResponse {
-            response: response_text,
+    for view in views {
+        let derived_from: Vec<uuid::Uuid> =
+            serde_json::from_value(view.derived_from.clone()).unwrap_or_default();
+        let score = derived_from
+            .iter()
+            .filter_map(|id| event_relevance.get(id))
+            .copied()
+            .reduce(f64::max);
+        if let Some(score) = score {
+            explanations.push(RecallExplanation {
+                source: RecallSource::View,
+                id: view.view_id,
+                snippet: snippet(&view.hypothesis),
+                relevance: score,
+            });
+        }
+    }
+
+    // Stable sort keeps memories ahead of equally scored events and views
+    explanations.sort_by(|a, b| b.relevance.total_cmp(&a.relevance));
+    explanations
+}
+
+/// Semantic search settings
+///
+/// Loaded from TOML with `from_file`; omitted keys keep their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchConfig {
+    /// Minimum cosine similarity for a hit to be returned (default: 0.5)
+    pub min_similarity: f64,
+
+    /// `top_k` used when the request omits it (default: 10)
+    pub default_top_k: usize,
+
+    /// Largest `top_k` a request may ask for; larger values are clamped (default: 50)
+    pub max_top_k: usize,
+
+    /// How the keyword fallback splits queries and memories into terms
+    pub keyword_index: KeywordIndexConfig,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            min_similarity: 0.5,
+            default_top_k: 10,
+            max_top_k: 50,
+            keyword_index: KeywordIndexConfig::default(),
+        }
+    }
+}
+
+impl SearchConfig {
+    /// Load and validate a TOML search config
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        let content = std::fs::read_to_string(path.as_ref()).map_err(DirSoulError::Io)?;
+        Self::from_toml_str(&content)
+    }
+
+    /// Parse and validate a TOML search config
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        let config: Self = toml::from_str(content)
+            .map_err(|e| DirSoulError::Config(format!("Invalid search config: {}", e)))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check that the cutoff is a similarity and the `top_k` bounds are consistent
+    pub fn validate(&self) -> Result<()> {
+        if !(-1.0..=1.0).contains(&self.min_similarity) {
+            return Err(DirSoulError::Config(format!(
+                "min_similarity must be between -1 and 1, got {}",
+                self.min_similarity
+            )));
+        }
+        if self.default_top_k == 0 || self.max_top_k < self.default_top_k {
+            return Err(DirSoulError::Config(format!(
+                "top_k bounds must satisfy 1 <= default_top_k ({}) <= max_top_k ({})",
+                self.default_top_k, self.max_top_k
+            )));
+        }
+        self.keyword_index.validate()
+    }
+
+    /// `top_k` for a request: the default when omitted, clamped to `max_top_k`
+    fn resolve_top_k(&self, requested: Option<usize>) -> Result<usize> {
+        match requested {
+            None => Ok(self.default_top_k),
+            Some(0) => Err(DirSoulError::Config("top_k must be at least 1".to_string())),
+            Some(k) => Ok(k.min(self.max_top_k)),
+        }
+    }
+}
+
+/// Function words skipped by the keyword index unless configured otherwise
+const DEFAULT_KEYWORD_STOP_WORDS: &[&str] = &[
+    "的", "了", "是", "在", "和", "也", "都", "就", "与", "着", "the", "a", "an", "and", "or",
+    "of", "to", "in", "on", "is", "are",
+];
+
+/// How the keyword search splits text into index terms
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeywordTokenizer {
+    /// Latin/digit words, and character bigrams of Chinese runs (a lone
+    /// character is kept as is), so unspaced Chinese text still matches
+    #[default]
+    ChineseBigram,
+    /// Whitespace-separated words only
+    Whitespace,
+}
+
+/// Term extraction for the keyword search fallback
+///
+/// Queries and memories go through the same tokenizer; stop words and
+/// terms shorter than `min_token_chars` never become terms, so they neither
+/// match nor dilute the score.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeywordIndexConfig {
+    /// Tokenizer (default: `chinese_bigram`)
+    pub tokenizer: KeywordTokenizer,
+
+    /// Words never indexed, matched case-insensitively (default: common
+    /// Chinese and English function words)
+    pub stop_words: Vec<String>,
+
+    /// Shortest indexed term, in characters (default: 1)
+    pub min_token_chars: usize,
+}
+
+impl Default for KeywordIndexConfig {
+    fn default() -> Self {
+        Self {
+            tokenizer: KeywordTokenizer::default(),
+            stop_words: DEFAULT_KEYWORD_STOP_WORDS.iter().map(|w| w.to_string()).collect(),
+            min_token_chars: 1,
+        }
+    }
+}
+
+impl KeywordIndexConfig {
+    /// Reject blank stop words and a minimum length no term can reach
+    pub fn validate(&self) -> Result<()> {
+        if self.stop_words.iter().any(|w| w.trim().is_empty()) {
+            return Err(DirSoulError::Config(
+                "Keyword index contains a blank stop word".to_string(),
+            ));
+        }
+        if self.min_token_chars == 0 {
+            return Err(DirSoulError::Config("min_token_chars must be at least 1".to_string()));
+        }
+        if self.tokenizer == KeywordTokenizer::ChineseBigram && self.min_token_chars > 2 {
+            return Err(DirSoulError::Config(format!(
+                "min_token_chars {} would drop every Chinese bigram; use at most 2",
+                self.min_token_chars
+            )));
+        }
+        Ok(())
+    }
+
+    /// Lowercased, de-duplicated index terms of `text`, in order of appearance
+    pub fn terms(&self, text: &str) -> Vec<String> {
+        let text = text.to_lowercase();
+        let stop_words: Vec<String> =
+            self.stop_words.iter().map(|w| w.trim().to_lowercase()).collect();
+
+        let mut terms = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        let mut push = |term: String| {
+            if term.chars().count() >= self.min_token_chars
+                && !stop_words.contains(&term)
+                && seen.insert(term.clone())
+            {
+                terms.push(term);
+            }
+        };
+
+        match self.tokenizer {
+            KeywordTokenizer::Whitespace => {
+                text.split_whitespace().map(str::to_string).for_each(push)
+            }
+            KeywordTokenizer::ChineseBigram => {
+                for segment in text.split(|c: char| !c.is_alphanumeric()) {
+                    let chars: Vec<char> = segment.chars().collect();
+                    let mut start = 0;
+                    while start < chars.len() {
+                        let latin = chars[start].is_ascii();
+                        let end = chars[start..]
+                            .iter()
+                            .position(|c| c.is_ascii() != latin)
+                            .map(|offset| start + offset)
+                            .unwrap_or(chars.len());
+                        let run: String = chars[start..end].iter().collect();
+                        start = end;
+
+                        if latin {
+                            push(run);
+                            continue;
+                        }
+                        for piece in strip_stop_words(&run, &stop_words).split_whitespace() {
+                            let piece: Vec<char> = piece.chars().collect();
+                            if piece.len() == 1 {
+                                push(piece.iter().collect());
+                            } else {
+                                for pair in piece.windows(2) {
+                                    push(pair.iter().collect());
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        terms
+    }
+}
+
+/// Blank out stop words inside an unspaced Chinese run, longest first
+fn strip_stop_words(run: &str, stop_words: &[String]) -> String {
+    let mut words: Vec<&String> = stop_words.iter().filter(|w| !w.is_ascii()).collect();
+    words.sort_by_key(|word| std::cmp::Reverse(word.chars().count()));
+
+    let mut rest = run.to_string();
+    for word in words {
+        rest = rest.replace(word.as_str(), " ");
+    }
+    rest
+}
+
+/// Largest accepted request body per endpoint, in bytes
+///
+/// Bodies over the limit (or without a `Content-Length`) are rejected
+/// before they are read, with 413 Payload Too Large (411 when the length is
+/// missing), so a single request cannot buffer an unbounded payload.
+#[derive(Debug, Clone)]
+pub struct BodyLimits {
+    /// `/api/chat`, which carries the client-side history (default: 256 KiB)
+    pub chat: u64,
+
+    /// Query endpoints: timeline, stats, aggregate, pattern detection,
+    /// search and concept rollback (default: 16 KiB)
+    pub query: u64,
+}
+
+impl Default for BodyLimits {
+    fn default() -> Self {
+        Self {
+            chat: 256 * 1024,
+            query: 16 * 1024,
+        }
+    }
+}
+
+impl BodyLimits {
+    /// Check that every limit admits a body
+    pub fn validate(&self) -> Result<()> {
+        if self.chat == 0 || self.query == 0 {
+            return Err(DirSoulError::Config("Body limits must be at least 1 byte".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// JSON body of at most `limit` bytes
+fn json_body<T>(limit: u64) -> impl Filter<Extract = (T,), Error = warp::Rejection> + Clone
+where
+    T: serde::de::DeserializeOwned + Send,
+{
+    warp::body::content_length_limit(limit).and(warp::body::json())
+}
+
+/// Per-dependency timeouts for `/health/ready`
+///
+/// Readiness probes run concurrently, so the endpoint answers within the
+/// longest of these even when a dependency hangs.
+#[derive(Debug, Clone)]
+pub struct HealthConfig {
+    /// Database connect and `SELECT 1` (default: 2s)
+    pub database_timeout: std::time::Duration,
+
+    /// Chat provider health check (default: 3s)
+    pub llm_timeout: std::time::Duration,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            database_timeout: std::time::Duration::from_secs(2),
+            llm_timeout: std::time::Duration::from_secs(3),
+        }
+    }
+}
+
+impl HealthConfig {
+    /// Check that every probe gets some time to answer
+    pub fn validate(&self) -> Result<()> {
+        if self.database_timeout.is_zero() || self.llm_timeout.is_zero() {
+            return Err(DirSoulError::Config("Health check timeouts must be non-zero".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of probing one dependency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DependencyHealth {
+    /// Dependency name ("database", "llm")
+    pub name: String,
+    pub healthy: bool,
+    /// Time the probe took, capped by its timeout
+    pub latency_ms: u64,
+    /// Why the probe failed
+    pub error: Option<String>,
+}
+
+/// `/health/ready` response body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadinessReport {
+    /// Every dependency is healthy
+    pub ready: bool,
+    pub dependencies: Vec<DependencyHealth>,
+}
+
+impl ReadinessReport {
+    /// Report over the given probe results
+    pub fn new(dependencies: Vec<DependencyHealth>) -> Self {
+        Self {
+            ready: dependencies.iter().all(|d| d.healthy),
+            dependencies,
+        }
+    }
+
+    /// Names of the dependencies that failed their probe
+    pub fn failed(&self) -> Vec<&str> {
+        self.dependencies
+            .iter()
+            .filter(|d| !d.healthy)
+            .map(|d| d.name.as_str())
+            .collect()
+    }
+}
+
+/// Run one readiness probe, failing it when it outlasts `timeout`
+async fn probe_dependency<F>(name: &str, timeout: std::time::Duration, check: F) -> DependencyHealth
+where
+    F: std::future::Future<Output = Result<()>>,
+{
+    let start = std::time::Instant::now();
+    let error = match tokio::time::timeout(timeout, check).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("timed out after {}ms", timeout.as_millis())),
+    };
+
+    DependencyHealth {
+        name: name.to_string(),
+        healthy: error.is_none(),
+        latency_ms: start.elapsed().as_millis() as u64,
+        error,
+    }
+}
+
+/// `database_url` with a libpq `connect_timeout` bounding the readiness probe
+///
+/// `tokio::time::timeout` cannot cancel a blocking connect, so without this an
+/// unreachable database pins one blocking thread per probe. A timeout already
+/// in the URL is kept. libpq counts whole seconds, so `timeout` is rounded up.
+fn with_connect_timeout(database_url: &str, timeout: std::time::Duration) -> String {
+    if database_url.contains("connect_timeout=") {
+        return database_url.to_string();
+    }
+    let secs = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+    if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+        let separator = if database_url.contains('?') { '&' } else { '?' };
+        format!("{}{}connect_timeout={}", database_url, separator, secs.max(1))
+    } else {
+        format!("{} connect_timeout={}", database_url, secs.max(1))
+    }
+}
+
+/// Estimate the token count of `text`
+///
+/// No model tokenizer is bundled, so this counts each non-ASCII (CJK)
+/// character as one token and every four ASCII characters as one token.
+pub fn estimate_tokens(text: &str) -> usize {
+    let ascii = text.chars().filter(|c| c.is_ascii()).count();
+    let other = text.chars().count() - ascii;
+    other + (ascii + 3) / 4
+}
+
+/// Prompt line for one history message
+fn history_line(message: &ChatMessage) -> String {
+    format!(
+        "{}: {}\n",
+        if message.role == "user" { "用户" } else { "助手" },
+        message.content
+    )
+}
+
+/// Pick the most recent history that fits the chat config
+///
+/// `fixed_tokens` is the estimated size of the rest of the prompt. Turns are
+/// taken newest-first and never split; the oldest turn of an odd-length
+/// history is its single leading message.
+fn select_history_window<'a>(
+    history: &'a [ChatMessage],
+    fixed_tokens: usize,
+    config: &ChatConfig,
+) -> &'a [ChatMessage] {
+    let mut start = history.len();
+    let mut used = fixed_tokens;
+
+    for _ in 0..config.history_turns {
+        if start == 0 {
+            break;
+        }
+        let turn_start = start.saturating_sub(2);
+        let turn_tokens: usize = history[turn_start..start]
+            .iter()
+            .map(|message| estimate_tokens(&history_line(message)))
+            .sum();
+        if config
+            .max_prompt_tokens
+            .is_some_and(|budget| used + turn_tokens > budget)
+        {
+            break;
+        }
+        used += turn_tokens;
+        start = turn_start;
+    }
+
+    &history[start..]
+}
+
+/// Chat response to Python (renamed to avoid conflict with llm_provider::ChatResponse)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiChatResponse {
+    /// Response text
+    pub response: String,
+
+    /// Updated conversation history
+    pub history: Vec<ChatMessage>,
+
+    /// Memories recorded (IDs)
+    pub recorded_memory_ids: Vec<String>,
+
+    /// Processing time in milliseconds
+    pub processing_time_ms: u64,
+
+    /// Additional metadata
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Timeline request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineRequest {
+    /// User ID
+    pub user_id: String,
+
+    /// Start date (ISO format)
+    pub start_date: String,
+
+    /// End date (ISO format)
+    pub end_date: String,
+
+    /// Time zone for date bounds and per-day grouping: an IANA name
+    /// ("Asia/Shanghai") or a UTC offset ("+08:00"); defaults to the user's
+    /// `timezone` setting, then UTC
+    #[serde(default)]
+    pub timezone: Option<String>,
+
+    /// Filters
+    pub filters: Option<TimelineFilters>,
+}
+
+/// Timeline filters
+///
+/// Missing or empty lists don't restrict the timeline.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimelineFilters {
+    /// Filter by entity name (case-insensitive)
+    pub entities: Option<Vec<String>>,
+
+    /// Filter by event type (the event action)
+    pub event_types: Option<Vec<String>>,
+
+    /// Minimum confidence
+    pub min_confidence: Option<f64>,
+
+    /// Minimum quantity (inclusive); events without a quantity are excluded
+    #[serde(default)]
+    pub min_quantity: Option<f64>,
+
+    /// Maximum quantity (inclusive); events without a quantity are excluded
+    #[serde(default)]
+    pub max_quantity: Option<f64>,
+
+    /// Unit the quantity must be measured in
+    #[serde(default)]
+    pub unit: Option<String>,
+}
+
+impl TimelineFilters {
+    /// Reject a `min_confidence` outside [0, 1] or an inverted quantity range
+    pub fn validate(&self) -> Result<()> {
+        match self.min_confidence {
+            Some(value) if !(0.0..=1.0).contains(&value) => Err(DirSoulError::Config(format!(
+                "min_confidence must be between 0 and 1, got {}",
+                value
+            ))),
+            _ => validate_quantity_range(self.min_quantity, self.max_quantity),
+        }
+    }
+
+    /// Entity names to match, lowercased; `None` when not filtering
+    fn entity_names(&self) -> Option<Vec<String>> {
+        self.entities
+            .as_ref()
+            .filter(|names| !names.is_empty())
+            .map(|names| names.iter().map(|name| name.to_lowercase()).collect())
+    }
+
+    /// Event types to match; `None` when not filtering
+    fn event_types(&self) -> Option<&[String]> {
+        self.event_types.as_deref().filter(|types| !types.is_empty())
+    }
+
+    /// Whether an event passes every filter
+    ///
+    /// `linked_entities` are the names of the entities linked to the event.
+    /// Events without links fall back to matching the names against the
+    /// event target.
+    pub fn matches(&self, event: &EventMemory, linked_entities: Option<&[String]>) -> bool {
+        if self.min_confidence.map_or(false, |min| event.confidence < min) {
+            return false;
+        }
+        if self.event_types().map_or(false, |types| !types.contains(&event.action)) {
+            return false;
+        }
+        if !event.quantity_matches(self.min_quantity, self.max_quantity, self.unit.as_deref()) {
+            return false;
+        }
+        let Some(names) = self.entity_names() else {
+            return true;
+        };
+        match linked_entities.filter(|linked| !linked.is_empty()) {
+            Some(linked) => linked
+                .iter()
+                .any(|entity| names.contains(&entity.to_lowercase())),
+            None => {
+                let target = event.target.to_lowercase();
+                names.iter().any(|name| target.contains(name.as_str()))
+            }
+        }
+    }
+}
+
+/// Timeline event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    /// Event ID
+    pub event_id: String,
+
+    /// Timestamp
+    pub timestamp: String,
+
+    /// Actor
+    pub actor: Option<String>,
+
+    /// Action
+    pub action: String,
+
+    /// Target
+    pub target: String,
+
+    /// Quantity
+    pub quantity: Option<f64>,
+
+    /// Unit
+    pub unit: Option<String>,
+
+    /// Confidence
+    pub confidence: f64,
+
+    /// Related entities
+    pub entities: Vec<String>,
+
+    /// Extractor signals (negated, sentiment, raw_span)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Timeline response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineResponse {
+    /// Events grouped by date
+    pub events_by_date: HashMap<String, Vec<TimelineEvent>>,
+
+    /// Total event count
+    pub total_events: usize,
+
+    /// Summary statistics
+    pub summary: TimelineSummary,
+}
+
+/// Timeline summary statistics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineSummary {
+    /// Total days in range
+    pub total_days: i64,
+
+    /// Average events per day
+    pub avg_events_per_day: f64,
+
+    /// Most active date
+    pub most_active_date: String,
+
+    /// Top entities
+    pub top_entities: Vec<String>,
+}
+
+/// Query string for `GET /api/timeline.csv`
+///
+/// Filters are flattened: `entities` and `event_types` are comma-separated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineCsvQuery {
+    /// User ID
+    pub user_id: String,
+
+    /// Start date (ISO format)
+    pub start_date: String,
+
+    /// End date (ISO format)
+    pub end_date: String,
+
+    /// Time zone for date bounds and exported timestamps; defaults to the
+    /// user's `timezone` setting, then UTC
+    #[serde(default)]
+    pub timezone: Option<String>,
+
+    /// Comma-separated entity names
+    #[serde(default)]
+    pub entities: Option<String>,
+
+    /// Comma-separated event types
+    #[serde(default)]
+    pub event_types: Option<String>,
+
+    /// Minimum confidence
+    #[serde(default)]
+    pub min_confidence: Option<f64>,
+
+    /// Minimum quantity
+    #[serde(default)]
+    pub min_quantity: Option<f64>,
+
+    /// Maximum quantity
+    #[serde(default)]
+    pub max_quantity: Option<f64>,
+
+    /// Quantity unit
+    #[serde(default)]
+    pub unit: Option<String>,
+}
+
+impl TimelineCsvQuery {
+    /// Same filters as the JSON timeline request
+    pub fn filters(&self) -> TimelineFilters {
+        let split = |list: &Option<String>| {
+            list.as_ref().map(|list| {
+                list.split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+        };
+        TimelineFilters {
+            entities: split(&self.entities),
+            event_types: split(&self.event_types),
+            min_confidence: self.min_confidence,
+            min_quantity: self.min_quantity,
+            max_quantity: self.max_quantity,
+            unit: self.unit.clone(),
+        }
+    }
+}
+
+/// Statistics request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsRequest {
+    /// User ID
+    pub user_id: String,
+
+    /// Time range: "7d", "30d", "90d", "all"
+    pub time_range: String,
+}
+
+/// Statistics response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsResponse {
+    /// Total memories
+    pub total_memories: usize,
+
+    /// Total events
+    pub total_events: usize,
+
+    /// Total entities
+    pub total_entities: usize,
+
+    /// Events per day
+    pub events_per_day: HashMap<String, i64>,
+
+    /// Event type distribution
+    pub event_types: HashMap<String, i64>,
+
+    /// Entity type distribution, custom types included
+    pub entity_types: HashMap<String, i64>,
+
+    /// Entity frequency
+    pub entities: Vec<EntityStat>,
+
+    /// Time range stats
+    pub time_range: TimeRangeStats,
+}
+
+/// Entity statistic
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityStat {
+    /// Entity name
+    pub name: String,
+
+    /// Entity type (built-in or custom)
+    pub entity_type: String,
+
+    /// Frequency
+    pub frequency: i64,
+
+    /// First seen
+    pub first_seen: String,
+
+    /// Last seen
+    pub last_seen: String,
+
+    /// `entity_importance` score the entities are ranked by
+    #[serde(default)]
+    pub importance: f64,
+}
+
+/// Time range statistics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeRangeStats {
+    /// Start date
+    pub start_date: String,
+
+    /// End date
+    pub end_date: String,
+
+    /// Total days
+    pub total_days: i64,
+
+    /// Most active day
+    pub most_active_day: String,
+
+    /// Least active day
+    pub least_active_day: String,
+}
+
+/// Concept rollback request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConceptRollbackRequest {
+    /// User ID (must own the concept)
+    pub user_id: String,
+
+    /// Version to restore
+    pub to_version: i32,
+}
+
+/// Aggregation request
+///
+/// Returns a scalar by default, a per-group map with `group_by`
+/// ("action"/"target"), or a time series with `bucket` ("hour"/"day"/"week").
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregateRequest {
+    /// User ID
+    pub user_id: String,
+
+    /// Time range ("today", "this_week", "last_month", "30d", ...)
+    pub range: String,
+
+    /// Aggregation type ("sum", "count", "avg")
+    pub agg_type: String,
+
+    /// Optional grouping dimension
+    #[serde(default)]
+    pub group_by: Option<String>,
+
+    /// Optional time bucket
+    #[serde(default)]
+    pub bucket: Option<String>,
+
+    /// Optional action filter
+    #[serde(default)]
+    pub action: Option<String>,
+
+    /// Optional target filter
+    #[serde(default)]
+    pub target: Option<String>,
+}
+
+/// Pattern detection request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectPatternsRequest {
+    /// User ID
+    pub user_id: String,
+
+    /// Number of days to analyze, ending now (1-365)
+    pub days: i64,
+}
+
+/// Query string for `GET /api/views/ready`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewsQuery {
+    /// User ID
+    pub user_id: String,
+}
+
+/// Query string for `GET /api/views/promotion-candidates`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromotionCandidatesQuery {
+    /// User ID
+    pub user_id: String,
+
+    /// Candidates skipped, from `next_offset` of the previous page
+    #[serde(default)]
+    pub offset: usize,
+
+    /// Page size (1-100, default: 20)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl PromotionCandidatesQuery {
+    /// The requested page size, checked against the bounds
+    pub fn page_size(&self) -> Result<usize> {
+        let limit = self.limit.unwrap_or(DEFAULT_CANDIDATE_PAGE_SIZE);
+        if !(1..=MAX_CANDIDATE_PAGE_SIZE).contains(&limit) {
+            return Err(DirSoulError::Config(format!(
+                "limit must be between 1 and {}, got {}",
+                MAX_CANDIDATE_PAGE_SIZE, limit
+            )));
+        }
+        Ok(limit)
+    }
+}
+
+/// Body of `POST /api/command`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandRequest {
+    /// User ID
+    pub user_id: String,
+
+    /// Raw input such as "@决策 要不要换工作"; input without an `@plugin`
+    /// command goes to the default plugin
+    pub input: String,
+}
+
+/// Body of `POST /api/views/{id}/promote`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewPromotionRequest {
+    /// User ID (must own the view)
+    pub user_id: String,
+}
+
+/// Query string for `GET /api/patterns`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternsQuery {
+    /// User ID
+    pub user_id: String,
+}
+
+/// Query string for `GET /api/settings`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsQuery {
+    /// User ID
+    pub user_id: String,
+}
+
+/// Query string for `GET /api/entities/{id}/relations` and `/relation-stats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelationsQuery {
+    /// User ID; the entity must belong to this user
+    pub user_id: String,
+
+    /// Minimum relation strength (0-1); defaults to the extractor's threshold
+    #[serde(default)]
+    pub min_strength: Option<f64>,
+
+    /// Most neighbors returned, strongest first (relations endpoint only)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Direction of a relation as seen from the requested entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationDirection {
+    /// The requested entity is the relation's source
+    Outgoing,
+    /// The requested entity is the relation's target
+    Incoming,
+}
+
+/// An entity connected to the requested entity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelatedEntity {
+    /// The neighboring entity
+    pub entity: Entity,
+    /// Relation ID
+    pub relation_id: uuid::Uuid,
+    /// Relation type (belongs_to, works_at, ...)
+    pub relation_type: String,
+    /// Relation strength (0-1)
+    pub strength: f64,
+    /// Relation confidence (0-1)
+    pub confidence: f64,
+    /// Direction of the relation
+    pub direction: RelationDirection,
+}
+
+/// Response for `GET /api/entities/{id}/relations`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelatedEntitiesResponse {
+    /// Requested entity
+    pub entity_id: uuid::Uuid,
+    /// Neighbors, strongest relation first
+    pub related: Vec<RelatedEntity>,
+}
+
+/// Response for `GET /api/entities/{id}/relation-stats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelationStatsResponse {
+    /// Requested entity
+    pub entity_id: uuid::Uuid,
+    /// Total relations counted
+    pub total_relations: i64,
+    /// Relation count per relation type
+    pub by_type: HashMap<String, i64>,
+}
+
+/// Semantic search request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SemanticSearchRequest {
+    /// User ID
+    pub user_id: String,
+
+    /// Free-text query
+    pub query: String,
+
+    /// Maximum number of results (defaults to `SearchConfig::default_top_k`)
+    #[serde(default)]
+    pub top_k: Option<usize>,
+}
+
+/// One semantic search result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    /// Matching raw memory
+    pub memory_id: uuid::Uuid,
+
+    /// Cosine similarity to the query (higher is closer)
+    pub similarity: f64,
+}
+
+/// Semantic search response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SemanticSearchResponse {
+    /// Hits at or above the cutoff, most similar first
+    pub results: Vec<SearchHit>,
+
+    /// Cutoff applied by the server
+    pub min_similarity: f64,
+}
+
+/// Error body for non-200 responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiErrorResponse {
+    /// Error message
+    pub error: String,
+}
+
+/// Map a domain error to an HTTP status
+///
+/// Transient failures (database or LLM unavailable) map to 503 so clients
+/// know a retry may succeed.
+fn error_status(error: &DirSoulError) -> warp::http::StatusCode {
+    match error {
+        DirSoulError::Config(_) => warp::http::StatusCode::BAD_REQUEST,
+        DirSoulError::NotFound(_) | DirSoulError::PluginNotFound(_) => warp::http::StatusCode::NOT_FOUND,
+        DirSoulError::Unauthorized(_) => warp::http::StatusCode::UNAUTHORIZED,
+        DirSoulError::PermissionDenied(_) => warp::http::StatusCode::FORBIDDEN,
+        e if e.retryable() => warp::http::StatusCode::SERVICE_UNAVAILABLE,
+        _ => warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Bearer tokens of the authenticated endpoints, each bound to one user
+///
+/// A token only authorizes requests acting for its own user, so holding
+/// one user's token does not let a client review, configure or run plugins
+/// for another. Without any token the endpoints refuse every request.
+#[derive(Clone, Default)]
+pub struct ApiTokens {
+    /// (user_id, token) pairs
+    bindings: Vec<(String, String)>,
+}
+
+impl std::fmt::Debug for ApiTokens {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let users: Vec<&str> = self.bindings.iter().map(|(user_id, _)| user_id.as_str()).collect();
+        f.debug_struct("ApiTokens").field("users", &users).finish_non_exhaustive()
+    }
+}
+
+impl ApiTokens {
+    /// No tokens: every authenticated request is refused
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a token acting for `user_id`
+    pub fn with_token(mut self, user_id: impl Into<String>, token: impl Into<String>) -> Self {
+        self.bindings.push((user_id.into(), token.into()));
+        self
+    }
+
+    /// Parse comma-separated `user_id=token` pairs, as in `DIRSOUL_API_TOKENS`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut tokens = Self::new();
+        for pair in spec.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let Some((user_id, token)) = pair.split_once('=') else {
+                return Err(DirSoulError::Config(
+                    "API tokens must be given as user_id=token pairs".to_string(),
+                ));
+            };
+            tokens = tokens.with_token(user_id.trim(), token.trim());
+        }
+        tokens.validate()?;
+        Ok(tokens)
+    }
+
+    /// Check if no token is configured
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    /// Reject blank users or tokens and tokens shared between bindings
+    pub fn validate(&self) -> Result<()> {
+        for (index, (user_id, token)) in self.bindings.iter().enumerate() {
+            if user_id.trim().is_empty() || token.trim().is_empty() {
+                return Err(DirSoulError::Config(
+                    "API tokens need a non-blank user and token".to_string(),
+                ));
+            }
+            if self.bindings[..index].iter().any(|(_, other)| other == token) {
+                return Err(DirSoulError::Config(format!(
+                    "The API token of user {} is already bound",
+                    user_id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check an `Authorization: Bearer <token>` header for a request acting
+    /// as `user_id`
+    ///
+    /// Fails closed: without a configured token every request is refused.
+    /// A missing or unknown token is `Unauthorized`; a valid token acting for
+    /// another user is `PermissionDenied`.
+    fn authorize(&self, authorization: Option<&str>, user_id: &str) -> Result<()> {
+        if self.bindings.is_empty() {
+            return Err(DirSoulError::PermissionDenied(
+                "No API token configured on the server".to_string(),
+            ));
+        }
+        let Some(token) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+            return Err(DirSoulError::Unauthorized("Missing bearer token".to_string()));
+        };
+        // Compare against every binding so timing does not reveal which matched
+        let token = token.trim().as_bytes();
+        let owner = self.bindings.iter().fold(None, |owner, (bound_user, expected)| {
+            if tokens_match(token, expected.as_bytes()) {
+                Some(bound_user)
+            } else {
+                owner
+            }
+        });
+        match owner {
+            None => Err(DirSoulError::Unauthorized("Invalid API token".to_string())),
+            Some(owner) if owner != user_id => Err(DirSoulError::PermissionDenied(format!(
+                "API token does not act for user {}",
+                user_id
+            ))),
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+/// Compare tokens in time independent of where they differ
+fn tokens_match(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Entity counts keyed by stored type name
+///
+/// Stored names are normalized through `EntityType`, so legacy spellings
+/// ("Person") merge with "person" and custom types keep their own bucket.
+fn entity_type_counts(rows: Vec<(String, i64)>) -> HashMap<String, i64> {
+    let mut counts = HashMap::new();
+    for (entity_type, count) in rows {
+        *counts.entry(String::from(EntityType::from(entity_type))).or_insert(0) += count;
+    }
+    counts
+}
+
+/// Time zone used to bucket timeline events by local date
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimelineZone {
+    /// IANA zone, e.g. "Asia/Shanghai" (follows DST)
+    Named(chrono_tz::Tz),
+    /// Fixed UTC offset, e.g. "+08:00"
+    Offset(chrono::FixedOffset),
+}
+
+impl Default for TimelineZone {
+    fn default() -> Self {
+        TimelineZone::Named(chrono_tz::UTC)
+    }
+}
+
+impl TimelineZone {
+    /// Parse an optional zone; `None` or blank means UTC
+    ///
+    /// Accepts IANA names ("Asia/Shanghai") and offsets with an optional
+    /// "UTC"/"GMT" prefix ("+08:00", "+0800", "+8", "UTC-05:30").
+    pub fn parse(value: Option<&str>) -> Result<Self> {
+        let value = match value.map(str::trim) {
+            None | Some("") => return Ok(Self::default()),
+            Some(value) => value,
+        };
+
+        let upper = value.to_ascii_uppercase();
+        let offset = upper
+            .strip_prefix("UTC")
+            .or_else(|| upper.strip_prefix("GMT"))
+            .unwrap_or(&upper);
+        if offset.is_empty() || offset == "Z" {
+            return Ok(Self::default());
+        }
+        if offset.starts_with('+') || offset.starts_with('-') {
+            return Self::parse_offset(offset)
+                .map(TimelineZone::Offset)
+                .ok_or_else(|| DirSoulError::Config(format!("Invalid timezone offset: {}", value)));
+        }
+
+        value
+            .parse::<chrono_tz::Tz>()
+            .map(TimelineZone::Named)
+            .map_err(|_| DirSoulError::Config(format!("Unknown timezone: {}", value)))
+    }
+
+    /// Parse "+HH", "+HHMM" or "+HH:MM" (sign required)
+    fn parse_offset(value: &str) -> Option<chrono::FixedOffset> {
+        let sign = if value.starts_with('-') { -1 } else { 1 };
+        let digits = &value[1..];
+        // Byte-index splitting below is only safe on ASCII
+        if !digits.bytes().all(|b| b.is_ascii_digit() || b == b':') {
+            return None;
+        }
+        let (hours, minutes) = match digits.split_once(':') {
+            Some((h, m)) => (h, m),
+            None if digits.len() > 2 => digits.split_at(digits.len() - 2),
+            None => (digits, "0"),
+        };
+        let hours: i32 = hours.parse().ok()?;
+        let minutes: i32 = minutes.parse().ok()?;
+        if hours > 14 || minutes >= 60 {
+            return None;
+        }
+        chrono::FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+    }
+
+    /// Local calendar date of a UTC timestamp
+    pub fn local_date(&self, timestamp: chrono::DateTime<chrono::Utc>) -> chrono::NaiveDate {
+        match self {
+            TimelineZone::Named(tz) => timestamp.with_timezone(tz).date_naive(),
+            TimelineZone::Offset(offset) => timestamp.with_timezone(offset).date_naive(),
+        }
+    }
+
+    /// RFC3339 rendering of a UTC timestamp in this zone
+    pub fn to_local_rfc3339(&self, timestamp: chrono::DateTime<chrono::Utc>) -> String {
+        match self {
+            TimelineZone::Named(tz) => timestamp.with_timezone(tz).to_rfc3339(),
+            TimelineZone::Offset(offset) => timestamp.with_timezone(offset).to_rfc3339(),
+        }
+    }
+
+    /// Convert a local wall-clock time to UTC
+    ///
+    /// Ambiguous times (DST fall-back) resolve to the earlier instant; times
+    /// skipped by a DST jump are rejected.
+    fn to_utc(&self, local: chrono::NaiveDateTime) -> Option<chrono::DateTime<chrono::Utc>> {
+        use chrono::TimeZone;
+        match self {
+            TimelineZone::Named(tz) => tz
+                .from_local_datetime(&local)
+                .earliest()
+                .map(|dt| dt.with_timezone(&chrono::Utc)),
+            TimelineZone::Offset(offset) => offset
+                .from_local_datetime(&local)
+                .earliest()
+                .map(|dt| dt.with_timezone(&chrono::Utc)),
+        }
+    }
+}
+
+/// Parse a timeline date bound: RFC3339, or YYYY-MM-DD expanded to the
+/// start or end of that day in `zone`
+fn parse_timeline_bound(
+    value: &str,
+    field: &str,
+    end_of_day: bool,
+    zone: &TimelineZone,
+) -> Result<chrono::DateTime<chrono::Utc>> {
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&chrono::Utc));
+    }
+
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|e| DirSoulError::Config(format!("Invalid {}: {}", field, e)))?;
+    let time = if end_of_day {
+        date.and_hms_opt(23, 59, 59)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    };
+    time.and_then(|local| zone.to_utc(local))
+        .ok_or_else(|| DirSoulError::Config(format!("Invalid {}", field)))
+}
+
+/// Group timeline events by local date in `zone`
+///
+/// Events keep their input order within a day. `most_active_date` is the day
+/// with the most events (earliest on ties).
+fn build_timeline_response(events: Vec<EventMemory>, zone: &TimelineZone) -> TimelineResponse {
+    let mut events_by_date: HashMap<String, Vec<TimelineEvent>> = HashMap::new();
+    for event in events {
+        let date = zone.local_date(event.timestamp).format("%Y-%m-%d").to_string();
+        let timeline_event = TimelineEvent {
+            event_id: event.id().to_string(),
+            timestamp: zone.to_local_rfc3339(event.timestamp),
+            actor: event.actor,
+            action: event.action,
+            target: event.target,
+            quantity: event.quantity,
+            unit: event.unit,
+            confidence: event.confidence,
+            entities: vec![], // TODO: query related entities
+            metadata: event.metadata,
+        };
+        events_by_date.entry(date).or_insert_with(Vec::new).push(timeline_event);
+    }
+
+    let total_events = events_by_date.values().map(|v| v.len()).sum();
+    let most_active_date = events_by_date
+        .iter()
+        .max_by(|(a_date, a), (b_date, b)| a.len().cmp(&b.len()).then_with(|| b_date.cmp(a_date)))
+        .map(|(date, _)| date.clone())
+        .unwrap_or_default();
+
+    TimelineResponse {
+        events_by_date,
+        total_events,
+        summary: TimelineSummary {
+            total_days: 0, // TODO: calculate from date range
+            avg_events_per_day: 0.0,
+            most_active_date,
+            top_entities: vec![],
+        },
+    }
+}
+
+/// Rows fetched per query while streaming `GET /api/timeline.csv`
+const TIMELINE_CSV_PAGE_SIZE: i64 = 500;
+
+/// Pages buffered between the query thread and the response body
+const TIMELINE_CSV_CHANNEL_PAGES: usize = 4;
+
+/// Header row of the timeline CSV export
+const TIMELINE_CSV_HEADER: &str = "timestamp,actor,action,target,quantity,unit,confidence\r\n";
+
+/// Keyset position in a timeline export: (timestamp, event_id) of the last row read
+type TimelineCursor = (chrono::DateTime<chrono::Utc>, uuid::Uuid);
+
+/// One page of a streamed timeline export
+struct TimelinePage {
+    /// Events that passed the filters, oldest first
+    events: Vec<EventMemory>,
+    /// Where the next page starts; `None` after the last page
+    next: Option<TimelineCursor>,
+}
+
+/// Quote a CSV field when it contains a comma, quote or line break
+fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+    if value.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+        format!("\"{}\"", value.replace('"', "\"\"")).into()
+    } else {
+        value.into()
+    }
+}
+
+/// One CSV record for an event, timestamp rendered in `zone`
+fn timeline_csv_row(event: &EventMemory, zone: &TimelineZone) -> String {
+    format!(
+        "{},{},{},{},{},{},{}\r\n",
+        zone.to_local_rfc3339(event.timestamp),
+        csv_field(event.actor.as_deref().unwrap_or("")),
+        csv_field(&event.action),
+        csv_field(&event.target),
+        event.quantity.map(|q| q.to_string()).unwrap_or_default(),
+        csv_field(event.unit.as_deref().unwrap_or("")),
+        event.confidence,
+    )
+}
+
+/// Stream the CSV export page by page
+///
+/// `fetch_page` runs on a blocking thread and is called with the cursor of
+/// the previous page until it reports no next page. At most
+/// `TIMELINE_CSV_CHANNEL_PAGES` pages wait for the client, so memory stays
+/// bounded however large the range is. A failing page ends the body with
+/// an error; a disconnected client stops the queries.
+fn timeline_csv_stream<P>(
+    zone: TimelineZone,
+    mut fetch_page: P,
+) -> tokio_stream::wrappers::ReceiverStream<std::io::Result<warp::hyper::body::Bytes>>
+where
+    P: FnMut(Option<TimelineCursor>) -> Result<TimelinePage> + Send + 'static,
+{
+    let (tx, rx) = tokio::sync::mpsc::channel(TIMELINE_CSV_CHANNEL_PAGES);
+
+    tokio::task::spawn_blocking(move || {
+        if tx.blocking_send(Ok(TIMELINE_CSV_HEADER.into())).is_err() {
+            return;
+        }
+
+        let mut cursor = None;
+        loop {
+            let page = match fetch_page(cursor) {
+                Ok(page) => page,
+                Err(e) => {
+                    let _ = tx.blocking_send(Err(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())));
+                    return;
+                }
+            };
+
+            if !page.events.is_empty() {
+                let chunk: String = page.events.iter().map(|event| timeline_csv_row(event, &zone)).collect();
+                if tx.blocking_send(Ok(chunk.into())).is_err() {
+                    return;
+                }
+            }
+
+            match page.next {
+                Some(next) => cursor = Some(next),
+                None => return,
+            }
+        }
+    });
+
+    tokio_stream::wrappers::ReceiverStream::new(rx)
+}
+
+/// Reply with the JSON value, or an error body with the mapped status
+fn json_result_reply<T: Serialize>(result: &Result<T>) -> warp::reply::WithStatus<warp::reply::Json> {
+    match result {
+        Ok(value) => warp::reply::with_status(warp::reply::json(value), warp::http::StatusCode::OK),
+        Err(e) => warp::reply::with_status(
+            warp::reply::json(&ApiErrorResponse { error: e.to_string() }),
+            error_status(e),
+        ),
+    }
+}
+
+/// Reply with the new head concept, or an error status
+fn concept_rollback_reply(result: &Result<StableConcept>) -> warp::reply::WithStatus<warp::reply::Json> {
+    json_result_reply(result)
+}
+
+/// `POST /api/aggregate` route
+///
+/// `load_events` fetches the events for a validated request; the
+/// aggregation itself is done by `EventAggregator::run_query`.
+fn aggregate_route<L>(
+    load_events: L,
+    body_limit: u64,
+) -> impl Filter<Extract = (warp::reply::WithStatus<warp::reply::Json>,), Error = warp::Rejection> + Clone
+where
+    L: Fn(&AggregateRequest, &AggregateQuery) -> Result<Vec<EventMemory>> + Clone + Send + Sync + 'static,
+{
+    warp::path!("api" / "aggregate")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .map(move |req: AggregateRequest| {
+            let result = AggregateQuery::parse(
+                &req.range,
+                &req.agg_type,
+                req.group_by.as_deref(),
+                req.bucket.as_deref(),
+            )
+            .and_then(|query| {
+                let events = load_events(&req, &query)?;
+                EventAggregator::run_query(&events, &query)
+            });
+            json_result_reply::<AggregateOutput>(&result)
+        })
+}
+
+/// `GET /api/views/promotion-candidates?user_id=...` route
+///
+/// Requires a bearer token acting for the queried user. `load` fetches one
+/// page for the query and the validated page size.
+fn promotion_candidates_route<L>(
+    load: L,
+    api_tokens: ApiTokens,
+) -> impl Filter<Extract = (warp::reply::WithStatus<warp::reply::Json>,), Error = warp::Rejection> + Clone
+where
+    L: Fn(&PromotionCandidatesQuery, usize) -> Result<PromotionCandidatePage> + Clone + Send + Sync + 'static,
+{
+    warp::path!("api" / "views" / "promotion-candidates")
+        .and(warp::get())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::query::<PromotionCandidatesQuery>())
+        .map(move |authorization: Option<String>, query: PromotionCandidatesQuery| {
+            let result = api_tokens
+                .authorize(authorization.as_deref(), &query.user_id)
+                .and_then(|()| query.page_size())
+                .and_then(|limit| load(&query, limit));
+            json_result_reply(&result)
+        })
+}
+
+/// `POST /api/patterns/detect` route
+///
+/// Detection saves the patterns it finds as views, so it requires a bearer
+/// token acting for the requested user. `detect` runs on the blocking pool,
+/// since detection loads and scans every event in the window.
+fn detect_patterns_route<D>(
+    detect: D,
+    body_limit: u64,
+    api_tokens: ApiTokens,
+) -> impl Filter<Extract = (warp::reply::WithStatus<warp::reply::Json>,), Error = warp::Rejection> + Clone
+where
+    D: Fn(&DetectPatternsRequest) -> Result<PatternDetectionResult> + Clone + Send + Sync + 'static,
+{
+    warp::path!("api" / "patterns" / "detect")
+        .and(warp::post())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(json_body(body_limit))
+        .and_then(move |authorization: Option<String>, req: DetectPatternsRequest| {
+            let detect = detect.clone();
+            let authorized = api_tokens.authorize(authorization.as_deref(), &req.user_id);
+            async move {
+                let result = match authorized {
+                    Ok(()) => run_pattern_detection(detect, req).await,
+                    Err(e) => Err(e),
+                };
+                Ok::<_, warp::Rejection>(json_result_reply(&result))
+            }
+        })
+}
+
+/// Validate the window, then run `detect` off the async runtime
+async fn run_pattern_detection<D>(detect: D, req: DetectPatternsRequest) -> Result<PatternDetectionResult>
+where
+    D: Fn(&DetectPatternsRequest) -> Result<PatternDetectionResult> + Send + 'static,
+{
+    if !(1..=MAX_PATTERN_DETECTION_DAYS).contains(&req.days) {
+        return Err(DirSoulError::Config(format!(
+            "days must be between 1 and {}, got {}",
+            MAX_PATTERN_DETECTION_DAYS, req.days
+        )));
+    }
+
+    tokio::task::spawn_blocking(move || detect(&req))
+        .await
+        .map_err(|e| DirSoulError::ExternalError(format!("Pattern detection task failed: {}", e)))?
+}
+
+/// `GET /api/timeline.csv?user_id=...&start_date=...&end_date=...` route
+///
+/// `resolve_zone` picks the export's time zone. `open_pages` validates the
+/// query and returns the page fetcher before any bytes are sent, so bad input
+/// still gets a JSON error with its status.
+fn timeline_csv_route<Z, O, P>(
+    resolve_zone: Z,
+    open_pages: O,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone
+where
+    Z: Fn(&TimelineCsvQuery) -> Result<TimelineZone> + Clone + Send + Sync + 'static,
+    O: Fn(&TimelineCsvQuery, &TimelineZone) -> Result<P> + Clone + Send + Sync + 'static,
+    P: FnMut(Option<TimelineCursor>) -> Result<TimelinePage> + Send + 'static,
+{
+    use warp::Reply;
+
+    warp::path!("api" / "timeline.csv")
+        .and(warp::get())
+        .and(warp::query::<TimelineCsvQuery>())
+        .map(move |query: TimelineCsvQuery| {
+            let opened = resolve_zone(&query)
+                .and_then(|zone| open_pages(&query, &zone).map(|pages| (zone, pages)));
+            match opened {
+                Ok((zone, pages)) => {
+                    let body = warp::hyper::Body::wrap_stream(timeline_csv_stream(zone, pages));
+                    let mut response = warp::reply::Response::new(body);
+                    let headers = response.headers_mut();
+                    headers.insert(
+                        warp::http::header::CONTENT_TYPE,
+                        warp::http::HeaderValue::from_static("text/csv; charset=utf-8"),
+                    );
+                    headers.insert(
+                        warp::http::header::CONTENT_DISPOSITION,
+                        warp::http::HeaderValue::from_static("attachment; filename=\"timeline.csv\""),
+                    );
+                    response
+                }
+                Err(e) => json_result_reply::<()>(&Err(e)).into_response(),
+            }
+        })
+}
+
+/// `GET /api/patterns?user_id=...` route
+fn list_patterns_route<L>(
+    load_patterns: L,
+) -> impl Filter<Extract = (warp::reply::WithStatus<warp::reply::Json>,), Error = warp::Rejection> + Clone
+where
+    L: Fn(&str) -> Result<Vec<CognitiveView>> + Clone + Send + Sync + 'static,
+{
+    warp::path!("api" / "patterns")
+        .and(warp::get())
+        .and(warp::query::<PatternsQuery>())
+        .map(move |query: PatternsQuery| json_result_reply(&load_patterns(&query.user_id)))
+}
+
+/// `GET /api/settings?user_id=...` and `PUT /api/settings` routes
+///
+/// `load` returns the user's stored overrides. `save` receives a validated
+/// body and replaces them; omitted fields fall back to the global config.
+/// Both require a bearer token acting for the user, since the settings
+/// include the user's Promotion Gate.
+fn settings_routes<L, S>(
+    load: L,
+    save: S,
+    api_tokens: ApiTokens,
+    body_limit: u64,
+) -> impl Filter<Extract = (warp::reply::WithStatus<warp::reply::Json>,), Error = warp::Rejection> + Clone
+where
+    L: Fn(&str) -> Result<UserSettings> + Clone + Send + Sync + 'static,
+    S: Fn(&UserSettings) -> Result<UserSettings> + Clone + Send + Sync + 'static,
+{
+    let read_tokens = api_tokens.clone();
+    let get = warp::path!("api" / "settings")
+        .and(warp::get())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::query::<SettingsQuery>())
+        .map(move |authorization: Option<String>, query: SettingsQuery| {
+            let result = read_tokens
+                .authorize(authorization.as_deref(), &query.user_id)
+                .and_then(|()| load(&query.user_id));
+            json_result_reply(&result)
+        });
+    let put = warp::path!("api" / "settings")
+        .and(warp::put())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(json_body(body_limit))
+        .map(move |authorization: Option<String>, settings: UserSettings| {
+            let result = api_tokens
+                .authorize(authorization.as_deref(), &settings.user_id)
+                .and_then(|()| settings.validate())
+                .and_then(|()| save(&settings));
+            json_result_reply(&result)
+        });
+    get.or(put).unify()
+}
+
+/// Reject `min_strength` outside 0-1
+fn validate_min_strength(min_strength: Option<f64>) -> Result<()> {
+    match min_strength {
+        Some(value) if !(0.0..=1.0).contains(&value) => Err(DirSoulError::Config(format!(
+            "min_strength must be between 0 and 1, got {}",
+            value
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Neighbors from `find_related_entities`, strongest relation first
+fn build_related_response(
+    entity_id: uuid::Uuid,
+    neighbors: Vec<(Entity, EntityRelation, Option<EntityRelation>)>,
+) -> RelatedEntitiesResponse {
+    let mut related: Vec<RelatedEntity> = neighbors
+        .into_iter()
+        .map(|(entity, relation, reverse)| RelatedEntity {
+            entity,
+            relation_id: relation.relation_id,
+            relation_type: relation.relation_type,
+            strength: relation.strength,
+            confidence: relation.confidence,
+            direction: if reverse.is_some() {
+                RelationDirection::Incoming
+            } else {
+                RelationDirection::Outgoing
+            },
+        })
+        .collect();
+    related.sort_by(|a, b| b.strength.total_cmp(&a.strength));
+
+    RelatedEntitiesResponse { entity_id, related }
+}
+
+/// `GET /api/entities/{id}/relations?user_id=...&min_strength=...` route
+///
+/// Requires a bearer token acting for the queried user.
+fn related_entities_route<L>(
+    load_related: L,
+    api_tokens: ApiTokens,
+) -> impl Filter<Extract = (warp::reply::WithStatus<warp::reply::Json>,), Error = warp::Rejection> + Clone
+where
+    L: Fn(&RelationsQuery, uuid::Uuid) -> Result<Vec<(Entity, EntityRelation, Option<EntityRelation>)>>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    warp::path!("api" / "entities" / uuid::Uuid / "relations")
+        .and(warp::get())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::query::<RelationsQuery>())
+        .map(move |entity_id: uuid::Uuid, authorization: Option<String>, query: RelationsQuery| {
+            let result = api_tokens
+                .authorize(authorization.as_deref(), &query.user_id)
+                .and_then(|()| validate_min_strength(query.min_strength))
+                .and_then(|()| load_related(&query, entity_id))
+                .map(|neighbors| build_related_response(entity_id, neighbors));
+            json_result_reply(&result)
+        })
+}
+
+/// `GET /api/entities/{id}/relation-stats?user_id=...&min_strength=...` route
+///
+/// Requires a bearer token acting for the queried user.
+fn relation_stats_route<L>(
+    load_stats: L,
+    api_tokens: ApiTokens,
+) -> impl Filter<Extract = (warp::reply::WithStatus<warp::reply::Json>,), Error = warp::Rejection> + Clone
+where
+    L: Fn(&RelationsQuery, uuid::Uuid) -> Result<HashMap<String, i64>> + Clone + Send + Sync + 'static,
+{
+    warp::path!("api" / "entities" / uuid::Uuid / "relation-stats")
+        .and(warp::get())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::query::<RelationsQuery>())
+        .map(move |entity_id: uuid::Uuid, authorization: Option<String>, query: RelationsQuery| {
+            let result = api_tokens
+                .authorize(authorization.as_deref(), &query.user_id)
+                .and_then(|()| validate_min_strength(query.min_strength))
+                .and_then(|()| load_stats(&query, entity_id))
+                .map(|by_type| RelationStatsResponse {
+                    entity_id,
+                    total_relations: by_type.values().sum(),
+                    by_type,
+                });
+            json_result_reply(&result)
+        })
+}
+
+/// `POST /api/search` route
+///
+/// `search` returns up to `top_k` candidates for a validated request; hits
+/// below `config.min_similarity` are then dropped, even if fewer than
+/// `top_k` remain.
+fn semantic_search_route<S, Fut>(
+    search: S,
+    config: SearchConfig,
+    body_limit: u64,
+) -> impl Filter<Extract = (warp::reply::WithStatus<warp::reply::Json>,), Error = warp::Rejection> + Clone
+where
+    S: Fn(SemanticSearchRequest, usize) -> Fut + Clone + Send + Sync + 'static,
+    Fut: std::future::Future<Output = Result<Vec<SearchHit>>> + Send + 'static,
+{
+    warp::path!("api" / "search")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and_then(move |req: SemanticSearchRequest| {
+            let search = search.clone();
+            let config = config.clone();
+            async move {
+                let result = run_semantic_search(search, &config, req).await;
+                Ok::<_, warp::Rejection>(json_result_reply(&result))
+            }
+        })
+}
+
+/// `POST /api/command` route
+///
+/// Plugins act on the user's memories, so the request needs a bearer token
+/// acting for the body's `user_id`. `route` runs the parsed command; unknown plugins map to 404 and
+/// plugins owned by another user to 403.
+fn command_route<R, Fut>(
+    route: R,
+    api_tokens: ApiTokens,
+    body_limit: u64,
+) -> impl Filter<Extract = (warp::reply::WithStatus<warp::reply::Json>,), Error = warp::Rejection> + Clone
+where
+    R: Fn(CommandRequest) -> Fut + Clone + Send + Sync + 'static,
+    Fut: std::future::Future<Output = Result<CommandResponse>> + Send + 'static,
+{
+    warp::path!("api" / "command")
+        .and(warp::post())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(json_body(body_limit))
+        .and_then(move |authorization: Option<String>, req: CommandRequest| {
+            let route = route.clone();
+            let authorized = api_tokens.authorize(authorization.as_deref(), &req.user_id);
+            async move {
+                let result = match authorized {
+                    Ok(()) => route(req).await,
+                    Err(e) => Err(e),
+                };
+                Ok::<_, warp::Rejection>(json_result_reply(&result))
+            }
+        })
+}
+
+/// Route a user's `@plugin` command through a `CommandRouter` bound to them
+async fn run_command(
+    plugins: Arc<PluginManager>,
+    default_plugin: Option<String>,
+    req: CommandRequest,
+) -> Result<CommandResponse> {
+    if req.user_id.trim().is_empty() {
+        return Err(DirSoulError::Config("user_id must not be empty".to_string()));
+    }
+
+    let mut router = CommandRouter::new(plugins, req.user_id);
+    if let Some(plugin_id) = default_plugin {
+        router.set_default_plugin(plugin_id);
+    }
+    router.route(&req.input).await
+}
+
+/// Validate the request, run `search` and apply the similarity cutoff
+async fn run_semantic_search<S, Fut>(
+    search: S,
+    config: &SearchConfig,
+    req: SemanticSearchRequest,
+) -> Result<SemanticSearchResponse>
+where
+    S: Fn(SemanticSearchRequest, usize) -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<SearchHit>>>,
+{
+    if req.query.trim().is_empty() {
+        return Err(DirSoulError::Config("query must not be empty".to_string()));
+    }
+    let top_k = config.resolve_top_k(req.top_k)?;
+    let hits = search(req, top_k).await?;
+
+    Ok(SemanticSearchResponse {
+        results: apply_similarity_cutoff(hits, config.min_similarity, top_k),
+        min_similarity: config.min_similarity,
+    })
+}
+
+/// Keep hits at or above `min_similarity`, most similar first, at most `top_k`
+fn apply_similarity_cutoff(mut hits: Vec<SearchHit>, min_similarity: f64, top_k: usize) -> Vec<SearchHit> {
+    hits.retain(|hit| hit.similarity >= min_similarity);
+    hits.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    hits.truncate(top_k);
+    hits
+}
+
+/// Embed `query` and find the user's closest raw memories
+///
+/// Without a usable embedder (none configured, or disabled because the
+/// database lacks pgvector) this falls back to `keyword_search_memories`.
+async fn search_memories(
+    database_url: String,
+    embedder: Option<Arc<EmbeddingGenerator>>,
+    index: KeywordIndexConfig,
+    user_id: String,
+    query: String,
+    top_k: usize,
+) -> Result<Vec<SearchHit>> {
+    let Some(embedder) = embedder.filter(|e| e.is_enabled()) else {
+        return keyword_search_memories(database_url, index, user_id, query, top_k).await;
+    };
+    let vector = embedder.generate(&query).await?;
+
+    tokio::task::spawn_blocking(move || {
+        let mut conn = PgConnection::establish(&database_url)?;
+        let metric = embedder.metric();
+        let matches = embedder.search_raw_memories(&mut conn, &user_id, &vector, top_k as i64)?;
+        Ok(matches
+            .into_iter()
+            .map(|m| SearchHit {
+                memory_id: m.memory_id,
+                similarity: metric.similarity(m.distance),
+            })
+            .collect())
+    })
+    .await
+    .map_err(|e| DirSoulError::ExternalError(format!("Semantic search task failed: {}", e)))?
+}
+
+/// Most recent matching memories scored by the keyword fallback
+const MAX_KEYWORD_CANDIDATES: i64 = 500;
+
+#[derive(QueryableByName)]
+struct KeywordCandidate {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    memory_id: uuid::Uuid,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    content: String,
+}
+
+/// Fraction of the query `terms` among the index terms of `content`
+///
+/// Content is indexed as bigrams, so a one-character Chinese term ("茶")
+/// is matched as a substring instead.
+fn keyword_score(content: &str, terms: &[String], index: &KeywordIndexConfig) -> f64 {
+    if terms.is_empty() {
+        return 0.0;
+    }
+    let indexed: std::collections::HashSet<String> = index.terms(content).into_iter().collect();
+    let lowered = content.to_lowercase();
+    let found = terms
+        .iter()
+        .filter(|term| {
+            let lone_char = term.chars().count() == 1 && !term.is_ascii();
+            indexed.contains(*term) || (lone_char && lowered.contains(term.as_str()))
+        })
+        .count();
+    found as f64 / terms.len() as f64
+}
+
+/// Score candidates against the query terms and keep the best `top_k`
+fn rank_keyword_candidates(
+    candidates: Vec<KeywordCandidate>,
+    terms: &[String],
+    index: &KeywordIndexConfig,
+    top_k: usize,
+) -> Vec<SearchHit> {
+    let mut hits: Vec<SearchHit> = candidates
+        .into_iter()
+        .map(|c| SearchHit {
+            memory_id: c.memory_id,
+            similarity: keyword_score(&c.content, terms, index),
+        })
+        .filter(|hit| hit.similarity > 0.0)
+        .collect();
+    hits.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    hits.truncate(top_k);
+    hits
+}
+
+/// `ILIKE` pattern matching `term` anywhere, with wildcards escaped
+fn contains_pattern(term: &str) -> String {
+    let escaped = term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Keyword search over a user's plaintext memories
+///
+/// Matches live memories containing any query term; `similarity` is the
+/// fraction of terms found among the memory's terms, so the `SearchConfig`
+/// cutoff still applies. Only the `MAX_KEYWORD_CANDIDATES` most recent
+/// matches are scored. A query made only of stop words finds nothing.
+async fn keyword_search_memories(
+    database_url: String,
+    index: KeywordIndexConfig,
+    user_id: String,
+    query: String,
+    top_k: usize,
+) -> Result<Vec<SearchHit>> {
+    let terms = index.terms(&query);
+    if terms.is_empty() {
+        return Ok(vec![]);
+    }
+    let patterns: Vec<String> = terms.iter().map(|term| contains_pattern(term)).collect();
+
+    tokio::task::spawn_blocking(move || {
+        let mut conn = PgConnection::establish(&database_url)?;
+        let candidates: Vec<KeywordCandidate> = diesel::sql_query(
+            "SELECT memory_id, content FROM raw_memories
+             WHERE user_id = $1 AND deleted_at IS NULL AND content ILIKE ANY($2)
+             ORDER BY created_at DESC
+             LIMIT $3",
+        )
+        .bind::<diesel::sql_types::Text, _>(&user_id)
+        .bind::<diesel::sql_types::Array<diesel::sql_types::Text>, _>(&patterns)
+        .bind::<diesel::sql_types::BigInt, _>(MAX_KEYWORD_CANDIDATES)
+        .load(&mut conn)?;
+
+        Ok(rank_keyword_candidates(candidates, &terms, &index, top_k))
+    })
+    .await
+    .map_err(|e| DirSoulError::ExternalError(format!("Keyword search task failed: {}", e)))?
+}
+
+/// A query cache that every `notify_user_data_changed` invalidates
+///
+/// The listener holds the cache weakly; the subscription lasts as long as
+/// the returned listener.
+fn subscribed_query_cache(config: QueryCacheConfig) -> (Arc<QueryCache>, Arc<UserDataListener>) {
+    let cache = Arc::new(QueryCache::new(config));
+    let weak = Arc::downgrade(&cache);
+    let listener: Arc<UserDataListener> = Arc::new(move |user_id: &str| {
+        if let Some(cache) = weak.upgrade() {
+            cache.invalidate_user(user_id);
+        }
+    });
+    subscribe_user_data_changes(&listener);
+    (cache, listener)
+}
+
+/// HTTP API server
+pub struct HttpServer {
+    /// Bind address
+    bind_address: String,
+    /// Database URL
+    database_url: String,
+    /// Audit logger for recording all operations
+    audit_logger: Arc<ThreadSafeAuditLogger>,
+    /// Chat generation settings
+    chat_config: ChatConfig,
+    /// Semantic search cutoff and `top_k` bounds
+    search_config: SearchConfig,
+    /// Embeds search queries; semantic search is unavailable without it
+    embedder: Option<Arc<EmbeddingGenerator>>,
+    /// Graph queries for the entity relation endpoints
+    relations: EntityRelationExtractor,
+    /// Model that answers `/api/chat`, governed by `llm_governor` if set
+    chat_provider: Arc<dyn LLMProvider>,
+    /// `chat_provider` before governing, so a later governor replaces the
+    /// earlier one instead of nesting inside it
+    chat_backend: Arc<dyn LLMProvider>,
+    /// Replayable `/api/chat` responses by idempotency key
+    chat_replays: Arc<ChatReplayCache>,
+    /// Request body size caps per endpoint
+    body_limits: BodyLimits,
+    /// Grounds `/api/chat` replies; chat runs without retrieval when unset
+    chat_retriever: Option<Arc<dyn ChatRetriever>>,
+    /// Readiness probe timeouts
+    health_config: HealthConfig,
+    /// System prompt prepended to `/api/chat` prompts
+    system_prompts: SystemPrompts,
+    /// Ranks the top entities of `/api/stats`
+    importance_config: EntityImportanceConfig,
+    /// Gate and margin of `/api/views/promotion-candidates`
+    candidate_config: PromotionCandidateConfig,
+    /// Detector settings of `/api/patterns/detect`, before the user's locale
+    pattern_config: PatternDetectorConfig,
+    /// Per-user bearer tokens required by the review, settings and command
+    /// endpoints; they refuse every request when empty
+    api_tokens: ApiTokens,
+    /// Recent `/api/stats` and `/api/timeline` responses
+    query_cache: Arc<QueryCache>,
+    /// Keeps `query_cache` subscribed to `data_changes`
+    _query_cache_listener: Arc<UserDataListener>,
+    /// Installed plugins reachable through `/api/command`
+    plugins: Arc<PluginManager>,
+    /// Plugin answering `/api/command` input without an `@plugin` command,
+    /// unless the user's settings name another
+    default_plugin: Option<String>,
+    /// Shared cap on in-flight LLM calls, applied to the chat provider
+    llm_governor: Option<LlmGovernor>,
+    /// Told about manual promotions and detected anomalies
+    webhooks: Option<WebhookNotifier>,
+}
+
+impl HttpServer {
+    /// Create a new HTTP server
+    pub fn new(bind_address: String, database_url: String) -> Result<Self> {
+        let audit_logger = Arc::new(ThreadSafeAuditLogger::new(database_url.clone()));
+        let (query_cache, query_cache_listener) = subscribed_query_cache(QueryCacheConfig::default());
+        let chat_backend: Arc<dyn LLMProvider> = Arc::new(
+            OllamaProvider::new(DEFAULT_CHAT_HOST, DEFAULT_CHAT_MODEL)
+                .with_timeout(std::time::Duration::from_secs(CHAT_TIMEOUT_SECS)),
+        );
+
+        Ok(Self {
+            bind_address,
+            database_url,
+            audit_logger,
+            chat_config: ChatConfig::default(),
+            search_config: SearchConfig::default(),
+            embedder: None,
+            relations: EntityRelationExtractor::new(),
+            chat_provider: chat_backend.clone(),
+            chat_backend,
+            chat_replays: Arc::new(ChatReplayCache::new(IdempotencyConfig::default())),
+            body_limits: BodyLimits::default(),
+            chat_retriever: None,
+            health_config: HealthConfig::default(),
+            system_prompts: SystemPrompts::default(),
+            importance_config: EntityImportanceConfig::default(),
+            candidate_config: PromotionCandidateConfig::default(),
+            pattern_config: PatternDetectorConfig::default(),
+            api_tokens: ApiTokens::new(),
+            query_cache,
+            _query_cache_listener: query_cache_listener,
+            plugins: Arc::new(PluginManager::new()),
+            default_plugin: None,
+            llm_governor: None,
+            webhooks: None,
+        })
+    }
+
+    /// Set chat history and generation settings
+    pub fn with_chat_config(mut self, chat_config: ChatConfig) -> Self {
+        self.chat_config = chat_config;
+        self
+    }
+
+    /// Set the semantic search cutoff and `top_k` bounds
+    pub fn with_search_config(mut self, search_config: SearchConfig) -> Self {
+        self.search_config = search_config;
+        self
+    }
+
+    /// Set the generator used to embed search queries
+    ///
+    /// Run `check_vector_support` before serving so a database without
+    /// pgvector falls back to keyword search.
+    pub fn with_embedder(mut self, embedder: Arc<EmbeddingGenerator>) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+
+    /// Set the model that answers `/api/chat`
+    pub fn with_chat_provider(mut self, provider: Arc<dyn LLMProvider>) -> Self {
+        self.chat_provider = match &self.llm_governor {
+            Some(governor) => governor.govern(provider.clone()),
+            None => provider.clone(),
+        };
+        self.chat_backend = provider;
+        self
+    }
+
+    /// Count `/api/chat` calls against a shared `LlmGovernor`
+    ///
+    /// Pass the same governor to the embedder and extractors (their
+    /// `with_governor`) so that all of them stay under one cap together.
+    /// Calling this again replaces the governor rather than adding one.
+    pub fn with_llm_governor(mut self, governor: LlmGovernor) -> Self {
+        self.chat_provider = governor.govern(self.chat_backend.clone());
+        self.llm_governor = Some(governor);
+        self
+    }
+
+    /// Set how long and how many chat responses are kept for replay
+    pub fn with_idempotency_config(mut self, config: IdempotencyConfig) -> Self {
+        self.chat_replays = Arc::new(ChatReplayCache::new(config));
+        self
+    }
+
+    /// Ground `/api/chat` replies in retrieved memories
+    ///
+    /// See `SemanticChatRetriever` for the semantic search implementation.
+    pub fn with_chat_retriever(mut self, retriever: Arc<dyn ChatRetriever>) -> Self {
+        self.chat_retriever = Some(retriever);
+        self
+    }
+
+    /// Set the request body size caps
+    pub fn with_body_limits(mut self, body_limits: BodyLimits) -> Self {
+        self.body_limits = body_limits;
+        self
+    }
+
+    /// Set the readiness probe timeouts
+    pub fn with_health_config(mut self, health_config: HealthConfig) -> Self {
+        self.health_config = health_config;
+        self
+    }
+
+    /// Set the detector settings of `/api/patterns/detect`, such as the
+    /// `min_event_confidence` floor for events behind detected patterns and views
+    pub fn with_pattern_config(mut self, pattern_config: PatternDetectorConfig) -> Self {
+        self.pattern_config = pattern_config;
+        self
+    }
+
+    /// Set how `/api/stats` weighs frequency against recency for top entities
+    pub fn with_importance_config(mut self, importance_config: EntityImportanceConfig) -> Self {
+        self.importance_config = importance_config;
+        self
+    }
+
+    /// Set the gate and near-miss margin of `/api/views/promotion-candidates`
+    pub fn with_candidate_config(mut self, candidate_config: PromotionCandidateConfig) -> Self {
+        self.candidate_config = candidate_config;
+        self
+    }
+
+    /// Let `Authorization: Bearer <token>` act for `user_id` on the review
+    /// (promotion candidates and manual promotion), settings and command
+    /// endpoints
+    pub fn with_api_token(mut self, user_id: impl Into<String>, token: impl Into<String>) -> Self {
+        self.api_tokens = self.api_tokens.with_token(user_id, token);
+        self
+    }
+
+    /// Replace the per-user bearer tokens
+    pub fn with_api_tokens(mut self, api_tokens: ApiTokens) -> Self {
+        self.api_tokens = api_tokens;
+        self
+    }
+
+    /// Set how long and how much `/api/stats` and `/api/timeline` cache
+    pub fn with_query_cache_config(mut self, config: QueryCacheConfig) -> Self {
+        let (query_cache, listener) = subscribed_query_cache(config);
+        self.query_cache = query_cache;
+        self._query_cache_listener = listener;
+        self
+    }
+
+    /// Response cache of `/api/stats` and `/api/timeline`
+    ///
+    /// Storage writes invalidate it through `data_changes`; writers outside
+    /// this crate's storage functions may call `invalidate_user` directly.
+    pub fn query_cache(&self) -> Arc<QueryCache> {
+        self.query_cache.clone()
+    }
+
+    /// Set the plugins that `/api/command` routes to
+    pub fn with_plugin_manager(mut self, plugins: Arc<PluginManager>) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    /// Send `/api/command` input without an `@plugin` command to `plugin_id`
+    /// (usually DeepTalk)
+    pub fn with_default_plugin(mut self, plugin_id: impl Into<String>) -> Self {
+        self.default_plugin = Some(plugin_id.into());
+        self
+    }
+
+    /// Prepend the chat system prompt of `system_prompts` to `/api/chat`
+    /// prompts, e.g. `prompt_manager.system_prompts().clone()`
+    pub fn with_system_prompts(mut self, system_prompts: SystemPrompts) -> Self {
+        self.system_prompts = system_prompts;
+        self
+    }
+
+    /// Notify webhook subscribers of promoted concepts and detected anomalies
+    pub fn with_webhooks(mut self, webhooks: WebhookNotifier) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// Batch audit entries instead of inserting one row per request
+    ///
+    /// The buffer is flushed when the server shuts down; see
+    /// `AuditBufferConfig` for what a crash can lose.
+    pub fn with_audit_buffer(mut self, config: AuditBufferConfig) -> Self {
+        self.audit_logger = Arc::new(
+            ThreadSafeAuditLogger::new(self.database_url.clone()).with_buffer(config),
+        );
+        self
+    }
+
+    /// Disable the embedder when the database lacks pgvector
+    ///
+    /// Semantic search then falls back to keyword search. If the check
+    /// itself fails (e.g. the database is down) the embedder stays enabled.
+    /// The embedder is shared, so one check also covers anything else using
+    /// it, such as the chat retriever and the embedding backfill; call this
+    /// before `start` and before starting those.
+    pub async fn check_vector_support(&self) {
+        let Some(embedder) = self.embedder.clone() else {
+            println!("ℹ️ No embedding generator configured, search uses keywords");
+            return;
+        };
+
+        let database_url = self.database_url.clone();
+        let capability = tokio::task::spawn_blocking(move || {
+            let mut conn = PgConnection::establish(&database_url)?;
+            check_vector_capability(&mut conn)
+        })
+        .await
+        .map_err(|e| DirSoulError::ExternalError(format!("pgvector check task failed: {}", e)))
+        .and_then(|result| result);
+
+        match capability {
+            Ok(capability) => {
+                embedder.apply_capability(&capability);
+                if let Some(reason) = capability.missing_reason() {
+                    println!("⚠️ Semantic search disabled ({}), search uses keywords", reason);
+                }
+            }
+            Err(e) => eprintln!("Could not check pgvector support: {}", e),
+        }
+    }
+
+    /// Probe the database and the chat provider for `/health/ready`
+    ///
+    /// Both probes run concurrently, each bounded by its `HealthConfig`
+    /// timeout.
+    async fn check_readiness(&self) -> ReadinessReport {
+        let database_timeout = self.health_config.database_timeout;
+        let database_url = with_connect_timeout(&self.database_url, database_timeout);
+        let database = probe_dependency("database", database_timeout, async move {
+            tokio::task::spawn_blocking(move || -> Result<()> {
+                let mut conn = PgConnection::establish(&database_url)?;
+                // A server that accepts but never answers must not hold the thread either
+                diesel::sql_query(format!(
+                    "SET statement_timeout = {}",
+                    database_timeout.as_millis().max(1)
+                ))
+                .execute(&mut conn)?;
+                diesel::sql_query("SELECT 1").execute(&mut conn)?;
+                Ok(())
+            })
+            .await
+            .map_err(|e| DirSoulError::ExternalError(format!("Database probe task failed: {}", e)))?
+        });
+
+        let provider = self.chat_provider.clone();
+        let llm = probe_dependency("llm", self.health_config.llm_timeout, async move {
+            if provider.health_check().await? {
+                Ok(())
+            } else {
+                Err(DirSoulError::ExternalError(format!(
+                    "{} reported unhealthy",
+                    provider.model_name()
+                )))
+            }
+        });
+
+        let (database, llm) = tokio::join!(database, llm);
+        ReadinessReport::new(vec![database, llm])
+    }
+
+    /// Process chat message - V3 Simplified (Client-side history)
+    /// Uses client-provided history and calls LLM for semantic understanding
+    async fn process_chat(&self, req: ChatRequest) -> Result<ApiChatResponse> {
+        let start = std::time::Instant::now();
+
+        // 检索相关记忆（未配置检索器时为空）
+        let explanations = self.retrieve_for_chat(&req).await;
+
+        // Build LLM prompt - 只包含年龄计算的few-shot
+        let mut conversation = String::from(r#"今年25→明年26。今年30→明年31。
+"#);
+        if !explanations.is_empty() {
+            conversation.push_str("相关记忆：\n");
+            for explanation in &explanations {
+                conversation.push_str(&format!("- {}\n", explanation.snippet));
+            }
+        }
+        let latest = format!("用户: {}\n", req.message);
+        let instruction = "回答（10字内）：\n";
+
+        // 按轮数和 token 预算选取最近的对话
+        let system_prompt = self.system_prompts.for_task(PromptTask::Chat).unwrap_or_default();
+        let fixed_tokens = estimate_tokens(system_prompt)
+            + estimate_tokens(&conversation)
+            + estimate_tokens(&latest)
+            + estimate_tokens(instruction);
+        let recent_messages = select_history_window(&req.history, fixed_tokens, &self.chat_config);
+
+        // 倒序添加历史（最新的在最前面）
+        for msg in recent_messages.iter().rev() {
+            conversation.push_str(&history_line(msg));
+        }
+
+        // 添加最新消息
+        conversation.push_str(&latest);
+
+        // 添加简短提示
+        conversation.push_str(instruction);
+        let conversation = self.system_prompts.compose_prompt(PromptTask::Chat, &conversation);
+
+        // Call LLM
+        let options = GenerateOptions::new(self.chat_config.temperature, self.chat_config.max_output_tokens);
+        let response_text = match self.chat_provider.generate(&conversation, options).await {
+            Ok(raw) if !raw.trim().is_empty() => raw.trim().to_string(),
+            Ok(_) => "我收到你的消息了。".to_string(),
+            Err(e) => {
+                eprintln!("Chat generation error: {}", e);
+                "我收到你的消息了。".to_string()
+            }
+        };
+
+        // Update history
+        let mut updated_history = req.history.clone();
+        updated_history.push(ChatMessage {
+            role: "user".to_string(),
+            content: req.message.clone(),
+        });
+        updated_history.push(ChatMessage {
+            role: "assistant".to_string(),
+            content: response_text.clone(),
+        });
+
+        Ok(ApiChatResponse {
+            response: response_text,
             history: updated_history,
             recorded_memory_ids: vec![],
-            processing_time_ms: start.elapsed().as_millis() as u64,
-            metadata: Some(serde_json::json!({
-                "version": "3.0.0",
-                "mode": "client-history+llm",
-                "model": "qwen2:0.5b"
-            })),
-        })
+            processing_time_ms: start.elapsed().as_millis() as u64,
+            metadata: Some(serde_json::json!({
+                "version": "3.0.0",
+                "mode": "client-history+llm",
+                "model": self.chat_provider.model_name(),
+                "explanations": explanations,
+            })),
+        })
+    }
+
+    /// Sources that ground a chat reply
+    ///
+    /// Empty when no retriever is set or `retrieval_top_k` is 0. A failed
+    /// retrieval is logged and the reply is generated without sources.
+    async fn retrieve_for_chat(&self, req: &ChatRequest) -> Vec<RecallExplanation> {
+        let Some(retriever) = &self.chat_retriever else {
+            return vec![];
+        };
+        if self.chat_config.retrieval_top_k == 0 {
+            return vec![];
+        }
+
+        match retriever
+            .retrieve(&req.user_id, &req.message, self.chat_config.retrieval_top_k)
+            .await
+        {
+            Ok(explanations) => explanations,
+            Err(e) => {
+                eprintln!("Chat retrieval error: {}", e);
+                vec![]
+            }
+        }
+    }
+
+    /// Process a chat message at most once per idempotency key
+    ///
+    /// `header_key` comes from the `Idempotency-Key` header and wins over
+    /// `req.idempotency_key`. Without a key every request is processed. A
+    /// key already used for a different message, history or context is
+    /// reported as `ChatOutcome::KeyReused` instead of replaying the old
+    /// response.
+    async fn process_chat_idempotent(
+        &self,
+        mut req: ChatRequest,
+        header_key: Option<String>,
+    ) -> Result<ChatOutcome> {
+        let key = header_key
+            .or_else(|| req.idempotency_key.take())
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty());
+
+        let Some(key) = key else {
+            return Ok(ChatOutcome::Processed(self.process_chat(req).await?));
+        };
+        if key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+            return Err(DirSoulError::Config(format!(
+                "Idempotency key longer than {} bytes",
+                MAX_IDEMPOTENCY_KEY_LEN
+            )));
+        }
+
+        let fingerprint = chat_fingerprint(&req)?;
+        let Some(slot) = self.chat_replays.slot(&req.user_id, &key, fingerprint) else {
+            return Ok(ChatOutcome::KeyReused);
+        };
+        let mut processed = false;
+        let response = slot
+            .get_or_try_init(|| {
+                processed = true;
+                self.process_chat(req)
+            })
+            .await?
+            .clone();
+        Ok(if processed {
+            ChatOutcome::Processed(response)
+        } else {
+            ChatOutcome::Replayed(response)
+        })
+    }
+
+    /// Query timeline events from database
+    ///
+    /// Input is validated before connecting, so bad dates or filters surface
+    /// as `Config` even while the database is down. Confidence and event type
+    /// filters run in SQL; entity filters use `event_entity_links`, falling
+    /// back to the event target for events without links.
+    fn query_timeline(
+        &self,
+        user_id: &str,
+        start_date: &str,
+        end_date: &str,
+        zone: &TimelineZone,
+        filters: Option<&TimelineFilters>,
+    ) -> Result<Vec<EventMemory>> {
+        let start = parse_timeline_bound(start_date, "start_date", false, zone)?;
+        let end = parse_timeline_bound(end_date, "end_date", true, zone)?;
+        if let Some(filters) = filters {
+            filters.validate()?;
+        }
+
+        let mut conn = PgConnection::establish(&self.database_url)?;
+
+        // Query events within time range (excluding soft-deleted memories)
+        let mut events = Self::timeline_query(user_id, start, end, filters)
+            .order(event_memories::timestamp.desc())
+            .load::<EventMemory>(&mut conn)?;
+
+        if let Some(filters) = filters {
+            Self::retain_entity_matches(&mut conn, user_id, &mut events, filters)?;
+        }
+        Ok(events)
+    }
+
+    /// Timeline events in range with the SQL-side filters applied
+    fn timeline_query<'a>(
+        user_id: &'a str,
+        start: chrono::DateTime<chrono::Utc>,
+        end: chrono::DateTime<chrono::Utc>,
+        filters: Option<&TimelineFilters>,
+    ) -> event_memories::BoxedQuery<'a, diesel::pg::Pg> {
+        let mut query = event_memories::table
+            .filter(event_memories::user_id.eq(user_id))
+            .filter(event_memories::memory_id.eq_any(live_memory_ids(user_id)))
+            .filter(event_memories::timestamp.ge(start))
+            .filter(event_memories::timestamp.le(end))
+            .into_boxed();
+        if let Some(min_confidence) = filters.and_then(|f| f.min_confidence) {
+            query = query.filter(event_memories::confidence.ge(min_confidence));
+        }
+        if let Some(event_types) = filters.and_then(|f| f.event_types()) {
+            query = query.filter(event_memories::action.eq_any(event_types.to_vec()));
+        }
+        if let Some(filters) = filters {
+            query = filter_by_quantity(
+                query,
+                filters.min_quantity,
+                filters.max_quantity,
+                filters.unit.as_deref(),
+            );
+        }
+        query
+    }
+
+    /// Drop events that fail the entity filter, if there is one
+    fn retain_entity_matches(
+        conn: &mut PgConnection,
+        user_id: &str,
+        events: &mut Vec<EventMemory>,
+        filters: &TimelineFilters,
+    ) -> Result<()> {
+        if filters.entity_names().is_none() {
+            return Ok(());
+        }
+
+        let event_ids: Vec<uuid::Uuid> = events.iter().map(|event| event.event_id).collect();
+        let mut links: HashMap<uuid::Uuid, Vec<String>> = HashMap::new();
+        for (event_id, name) in event_entity_links::table
+            .inner_join(entities::table)
+            .filter(event_entity_links::user_id.eq(user_id))
+            .filter(event_entity_links::event_id.eq_any(event_ids))
+            .select((event_entity_links::event_id, entities::canonical_name))
+            .load::<(uuid::Uuid, String)>(conn)?
+        {
+            links.entry(event_id).or_default().push(name);
+        }
+
+        events.retain(|event| filters.matches(event, links.get(&event.event_id).map(Vec::as_slice)));
+        Ok(())
+    }
+
+    /// Validate a CSV export request and return its page fetcher
+    ///
+    /// Pages are read oldest first with keyset pagination on
+    /// (timestamp, event_id); the connection is opened on the first page.
+    fn open_timeline_csv(
+        &self,
+        query: &TimelineCsvQuery,
+        zone: &TimelineZone,
+    ) -> Result<impl FnMut(Option<TimelineCursor>) -> Result<TimelinePage> + Send + 'static> {
+        let start = parse_timeline_bound(&query.start_date, "start_date", false, zone)?;
+        let end = parse_timeline_bound(&query.end_date, "end_date", true, zone)?;
+        let filters = query.filters();
+        filters.validate()?;
+
+        let database_url = self.database_url.clone();
+        let user_id = query.user_id.clone();
+        let mut conn: Option<PgConnection> = None;
+
+        Ok(move |after: Option<TimelineCursor>| {
+            let conn = match &mut conn {
+                Some(conn) => conn,
+                None => conn.insert(PgConnection::establish(&database_url)?),
+            };
+
+            let mut query = Self::timeline_query(&user_id, start, end, Some(&filters));
+            if let Some((timestamp, event_id)) = after {
+                query = query.filter(
+                    event_memories::timestamp.gt(timestamp).or(event_memories::timestamp
+                        .eq(timestamp)
+                        .and(event_memories::event_id.gt(event_id))),
+                );
+            }
+            let mut events = query
+                .order((event_memories::timestamp.asc(), event_memories::event_id.asc()))
+                .limit(TIMELINE_CSV_PAGE_SIZE)
+                .load::<EventMemory>(conn)?;
+
+            // The cursor follows the unfiltered page so entity filtering can't end the export early
+            let next = if events.len() as i64 == TIMELINE_CSV_PAGE_SIZE {
+                events.last().map(|event| (event.timestamp, event.event_id))
+            } else {
+                None
+            };
+            Self::retain_entity_matches(conn, &user_id, &mut events, &filters)?;
+
+            Ok(TimelinePage { events, next })
+        })
+    }
+
+    /// Roll a concept back to a prior version
+    fn rollback_concept(&self, concept_id: uuid::Uuid, req: &ConceptRollbackRequest) -> Result<StableConcept> {
+        let mut conn = PgConnection::establish(&self.database_url)?;
+        rollback_concept(&mut conn, &req.user_id, concept_id, req.to_version)
+    }
+
+    /// Active views waiting for manual promotion
+    fn load_ready_views(&self, user_id: &str) -> Result<Vec<CognitiveView>> {
+        let mut conn = PgConnection::establish(&self.database_url)?;
+        list_views_ready_for_promotion(&mut conn, user_id)
+    }
+
+    /// One page of views passing, or nearly passing, the gate
+    fn load_promotion_candidates(
+        &self,
+        query: &PromotionCandidatesQuery,
+        limit: usize,
+    ) -> Result<PromotionCandidatePage> {
+        let mut conn = PgConnection::establish(&self.database_url)?;
+        let settings = UserSettings::get(&mut conn, &query.user_id)?;
+        let config = PromotionCandidateConfig {
+            gate: settings.promotion_gate(&self.candidate_config.gate),
+            ..self.candidate_config.clone()
+        };
+        list_promotion_candidates(&mut conn, &query.user_id, &config, query.offset, limit)
+    }
+
+    /// Approve a view flagged `ready_for_promotion`
+    ///
+    /// Webhook subscribers get the new concept; failing to load it is
+    /// logged, since the promotion itself has been committed.
+    fn promote_view(&self, view_id: uuid::Uuid, req: &ViewPromotionRequest) -> Result<CognitiveView> {
+        let mut conn = PgConnection::establish(&self.database_url)?;
+        let view =
+            approve_view_promotion(&mut conn, &req.user_id, view_id, &self.candidate_config.gate)?;
+
+        if let (Some(webhooks), Some(concept_id)) = (&self.webhooks, view.promoted_to) {
+            match stable_concepts::table.find(concept_id).first::<StableConcept>(&mut conn) {
+                Ok(concept) => {
+                    webhooks.notify(WebhookEvent::concept_promoted(&concept));
+                }
+                Err(e) => tracing::warn!("Promoted concept {} not announced: {}", concept_id, e),
+            }
+        }
+        Ok(view)
+    }
+
+    /// Load events for an aggregation request
+    fn load_aggregate_events(&self, req: &AggregateRequest, query: &AggregateQuery) -> Result<Vec<EventMemory>> {
+        let mut conn = PgConnection::establish(&self.database_url)?;
+        EventAggregator::load_events(
+            &mut conn,
+            &req.user_id,
+            req.action.as_deref(),
+            req.target.as_deref(),
+            &query.time_range,
+        )
+    }
+
+    /// Detect patterns over the last `days` days and persist them as views
+    fn detect_patterns(&self, req: &DetectPatternsRequest) -> Result<PatternDetectionResult> {
+        let mut conn = PgConnection::establish(&self.database_url)?;
+        let settings = UserSettings::get(&mut conn, &req.user_id)?;
+        let config = settings.pattern_config(&self.pattern_config);
+        let result = PatternDetector::with_config(config).detect_patterns(
+            &mut conn,
+            &req.user_id,
+            DetectionTimeRange::last_n_days(req.days),
+        )?;
+        PatternDetectionScheduler::persist_result(
+            &mut conn,
+            &req.user_id,
+            &result,
+            &ViewGenerator::new(),
+        )?;
+
+        if let Some(webhooks) = &self.webhooks {
+            for pattern in &result.patterns {
+                if pattern.pattern_type == PatternType::Anomaly {
+                    webhooks.notify(WebhookEvent::anomaly_detected(pattern));
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// A user's stored settings; no overrides when the user has none
+    fn load_user_settings(&self, user_id: &str) -> Result<UserSettings> {
+        let mut conn = PgConnection::establish(&self.database_url)?;
+        UserSettings::get(&mut conn, user_id)
+    }
+
+    /// Replace a user's stored settings
+    fn save_user_settings(&self, settings: &UserSettings) -> Result<UserSettings> {
+        let mut conn = PgConnection::establish(&self.database_url)?;
+        UserSettings::update(&mut conn, settings)
+    }
+
+    /// The user's default plugin, else the server's
+    async fn user_default_plugin(self: Arc<Self>, user_id: &str) -> Result<Option<String>> {
+        let user_id = user_id.to_string();
+        tokio::task::spawn_blocking(move || {
+            let settings = self.load_user_settings(&user_id)?;
+            Ok(settings.default_plugin_or(self.default_plugin.clone()))
+        })
+        .await
+        .map_err(|e| DirSoulError::ExternalError(format!("Settings lookup failed: {}", e)))?
+    }
+
+    /// Zone for grouping a user's timeline
+    ///
+    /// A zone named by the request wins without a settings lookup;
+    /// otherwise the user's stored zone, then UTC.
+    fn user_timeline_zone(&self, user_id: &str, requested: Option<&str>) -> Result<TimelineZone> {
+        if requested.is_some_and(|zone| !zone.trim().is_empty()) {
+            return TimelineZone::parse(requested);
+        }
+        self.load_user_settings(user_id)?.timeline_zone(None)
+    }
+
+    /// Load the active views persisted by pattern detection, newest first
+    fn load_patterns(&self, user_id: &str) -> Result<Vec<CognitiveView>> {
+        let mut conn = PgConnection::establish(&self.database_url)?;
+        Ok(cognitive_views::table
+            .filter(cognitive_views::user_id.eq(user_id))
+            .filter(cognitive_views::source.eq("pattern_detector"))
+            .filter(cognitive_views::status.eq(String::from(ViewStatus::Active)))
+            .order(cognitive_views::created_at.desc())
+            .load(&mut conn)?)
+    }
+
+    /// Fail with `NotFound` unless the entity belongs to the user
+    fn ensure_entity_owner(conn: &mut PgConnection, user_id: &str, entity_id: uuid::Uuid) -> Result<()> {
+        let owned: i64 = entities::table
+            .filter(entities::entity_id.eq(entity_id))
+            .filter(entities::user_id.eq(user_id))
+            .count()
+            .get_result(conn)?;
+        if owned == 0 {
+            return Err(DirSoulError::NotFound(format!("Entity {} not found", entity_id)));
+        }
+        Ok(())
+    }
+
+    /// Load an entity's neighbors in the relation graph
+    fn load_related_entities(
+        &self,
+        query: &RelationsQuery,
+        entity_id: uuid::Uuid,
+    ) -> Result<Vec<(Entity, EntityRelation, Option<EntityRelation>)>> {
+        let mut conn = PgConnection::establish(&self.database_url)?;
+        Self::ensure_entity_owner(&mut conn, &query.user_id, entity_id)?;
+        self.relations
+            .find_related_entities(&mut conn, &query.user_id, entity_id, query.min_strength, query.limit)
+    }
+
+    /// Count an entity's relations by type
+    fn load_relation_stats(&self, query: &RelationsQuery, entity_id: uuid::Uuid) -> Result<HashMap<String, i64>> {
+        let mut conn = PgConnection::establish(&self.database_url)?;
+        Self::ensure_entity_owner(&mut conn, &query.user_id, entity_id)?;
+        self.relations
+            .get_relation_stats(&mut conn, &query.user_id, entity_id, query.min_strength)
+    }
+
+    /// Query statistics from database
+    fn query_stats(&self, user_id: &str, time_range: &str) -> Result<StatsResponse> {
+        // Calculate time range before connecting
+        let (start, end) = match time_range {
+            "7d" => {
+                let start = chrono::Utc::now() - chrono::Duration::days(7);
+                (start, chrono::Utc::now())
+            }
+            "30d" => {
+                let start = chrono::Utc::now() - chrono::Duration::days(30);
+                (start, chrono::Utc::now())
+            }
+            "90d" => {
+                let start = chrono::Utc::now() - chrono::Duration::days(90);
+                (start, chrono::Utc::now())
+            }
+            "all" => {
+                // Query all time
+                let start = chrono::DateTime::from_timestamp(0, 0).unwrap();
+                (start, chrono::Utc::now())
+            }
+            _ => {
+                return Err(DirSoulError::Config(format!(
+                    "Invalid time_range: {}. Expected: 7d, 30d, 90d, or all",
+                    time_range
+                )));
+            }
+        };
+
+        let mut conn = PgConnection::establish(&self.database_url)?;
+
+        // Count total events
+        let total_events: i64 = event_memories::table
+            .filter(event_memories::user_id.eq(user_id))
+            .filter(event_memories::memory_id.eq_any(live_memory_ids(user_id)))
+            .filter(event_memories::timestamp.ge(start))
+            .filter(event_memories::timestamp.le(end))
+            .count()
+            .get_result(&mut conn)?;
+
+        // Count total entities
+        let total_entities: i64 = entities::table
+            .filter(entities::user_id.eq(user_id))
+            .count()
+            .get_result(&mut conn)?;
+
+        // Get events per day
+        let events: Vec<EventMemory> = event_memories::table
+            .filter(event_memories::user_id.eq(user_id))
+            .filter(event_memories::memory_id.eq_any(live_memory_ids(user_id)))
+            .filter(event_memories::timestamp.ge(start))
+            .filter(event_memories::timestamp.le(end))
+            .load(&mut conn)?;
+
+        let mut events_per_day = HashMap::new();
+        for event in &events {
+            let date = event.timestamp.format("%Y-%m-%d").to_string();
+            *events_per_day.entry(date).or_insert(0) += 1;
+        }
+
+        // Get event type distribution
+        let mut event_types = HashMap::new();
+        for event in &events {
+            *event_types.entry(event.action.clone()).or_insert(0) += 1;
+        }
+
+        // Calculate time range stats
+        let total_days = (end.timestamp() - start.timestamp()) / 86400;
+        let avg_events_per_day = if total_days > 0 {
+            total_events as f64 / total_days as f64
+        } else {
+            0.0
+        };
+
+        let most_active_day = events_per_day
+            .iter()
+            .max_by_key(|(_, &count)| count)
+            .map(|(day, _)| day.clone())
+            .unwrap_or_default();
+
+        // Get entity type distribution
+        let type_rows: Vec<(String, i64)> = entities::table
+            .filter(entities::user_id.eq(user_id))
+            .group_by(entities::entity_type)
+            .select((entities::entity_type, diesel::dsl::count_star()))
+            .load(&mut conn)?;
+        let entity_types = entity_type_counts(type_rows);
+
+        // Get top entities: the most important among the most frequent
+        // and the most recently seen
+        let mut candidates = entities::table
+            .filter(entities::user_id.eq(user_id))
+            .order(entities::occurrence_count.desc())
+            .limit(TOP_ENTITY_CANDIDATES)
+            .load::<Entity>(&mut conn)?;
+        let recent = entities::table
+            .filter(entities::user_id.eq(user_id))
+            .order(entities::last_seen.desc())
+            .limit(TOP_ENTITY_CANDIDATES)
+            .load::<Entity>(&mut conn)?;
+        for entity in recent {
+            if !candidates.iter().any(|c| c.entity_id == entity.entity_id) {
+                candidates.push(entity);
+            }
+        }
+
+        let mut ranked =
+            rank_entities_by_importance(candidates, chrono::Utc::now(), &self.importance_config);
+        ranked.truncate(TOP_ENTITY_COUNT);
+        let entities_stats: Vec<EntityStat> = ranked
+            .into_iter()
+            .map(|(e, importance)| EntityStat {
+                entity_type: e.get_type().into(),
+                name: e.canonical_name,
+                frequency: e.occurrence_count as i64,
+                first_seen: e.first_seen.to_rfc3339(),
+                last_seen: e.last_seen.to_rfc3339(),
+                importance,
+            })
+            .collect();
+
+        Ok(StatsResponse {
+            total_memories: 0, // TODO: count from raw_memories
+            total_events: total_events as usize,
+            total_entities: total_entities as usize,
+            events_per_day,
+            event_types,
+            entity_types,
+            entities: entities_stats,
+            time_range: TimeRangeStats {
+                start_date: start.format("%Y-%m-%d").to_string(),
+                end_date: end.format("%Y-%m-%d").to_string(),
+                total_days,
+                most_active_day,
+                least_active_day: String::new(),
+            },
+        })
+    }
+
+    /// All API routes
+    ///
+    /// Every handler shares this one server (database URL, audit logger and
+    /// configs), so serving a request allocates no per-request server state.
+    /// `start` serves these with CORS; tests drive them with `warp::test`.
+    pub fn routes(self: Arc<Self>) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        use warp::Reply;
+
+        // Health check endpoint
+        let health = warp::path("health")
+            .and(warp::path::end())
+            .and(warp::get())
+            .map(|| {
+                warp::reply::json(&serde_json::json!({
+                    "status": "healthy",
+                    "service": "dirsoul-api",
+                    "version": "1.0.0"
+                }))
+            });
+
+        // Liveness: the process is serving; never touches dependencies
+        let health_live = warp::path!("health" / "live")
+            .and(warp::get())
+            .map(|| warp::reply::json(&serde_json::json!({ "status": "alive" })));
+
+        // Readiness: the database and chat provider are reachable
+        let server_ready = self.clone();
+        let health_ready = warp::path!("health" / "ready")
+            .and(warp::get())
+            .and_then(move || {
+                let server = server_ready.clone();
+                async move {
+                    let report = server.check_readiness().await;
+                    let status = if report.ready {
+                        warp::http::StatusCode::OK
+                    } else {
+                        warp::http::StatusCode::SERVICE_UNAVAILABLE
+                    };
+                    Ok::<_, warp::Rejection>(warp::reply::with_status(warp::reply::json(&report), status))
+                }
+            });
+
+        // Chat endpoint
+        let server_chat = self.clone();
+        let audit_logger_chat = self.audit_logger.clone();
+        let chat = warp::path("api")
+            .and(warp::path("chat"))
+            .and(warp::post())
+            .and(warp::header::optional::<String>("idempotency-key"))
+            .and(json_body(self.body_limits.chat))
+            .then(move |idempotency_key: Option<String>, req: ChatRequest| {
+                let server_chat = server_chat.clone();
+                let audit_logger_chat = audit_logger_chat.clone();
+                async move {
+                    let user_id = req.user_id.clone();
+
+                    let ok = warp::http::StatusCode::OK;
+                    match server_chat.process_chat_idempotent(req, idempotency_key).await {
+                        // Replays were audited when first processed
+                        Ok(ChatOutcome::Replayed(response)) => {
+                            warp::reply::with_status(warp::reply::json(&response), ok)
+                        }
+                        Ok(ChatOutcome::KeyReused) => warp::reply::with_status(
+                            warp::reply::json(&ApiErrorResponse {
+                                error: "Idempotency key was already used for a different request"
+                                    .to_string(),
+                            }),
+                            warp::http::StatusCode::UNPROCESSABLE_ENTITY,
+                        ),
+                        Ok(ChatOutcome::Processed(response)) => {
+                            // Extract result count before moving response
+                            let result_count = response.recorded_memory_ids.len() as i32;
+                            if result_count > 0 {
+                                server_chat.query_cache.invalidate_user(&user_id);
+                            }
+
+                            // Log the query asynchronously (don't block response)
+                            let logger = audit_logger_chat.clone();
+                            let user_id_clone = user_id.clone();
+                            tokio::spawn(async move {
+                                let _ = logger.log_query(
+                                    &user_id_clone,
+                                    "chat",
+                                    true,
+                                    result_count,
+                                ).await;
+                            });
+
+                            warp::reply::with_status(warp::reply::json(&response), ok)
+                        }
+                        Err(e) => {
+                            // Log the failed query
+                            let logger = audit_logger_chat.clone();
+                            tokio::spawn(async move {
+                                let _ = logger.log_query(&user_id, "chat", false, 0).await;
+                            });
+
+                            let error_response = ApiChatResponse {
+                                response: format!("Error: {}", e),
+                                history: vec![],
+                                recorded_memory_ids: vec![],
+                                processing_time_ms: 0,
+                                metadata: None,
+                            };
+                            warp::reply::with_status(warp::reply::json(&error_response), ok)
+                        }
+                    }
+                }
+            });
+
+        // Timeline endpoint
+        let server_timeline = self.clone();
+        let audit_logger_timeline = self.audit_logger.clone();
+        let timeline = warp::path("api")
+            .and(warp::path("timeline"))
+            .and(warp::post())
+            .and(json_body(self.body_limits.query))
+            .map(move |req: TimelineRequest| {
+                let user_id = req.user_id.clone();
+                let start_date = req.start_date.clone();
+                let end_date = req.end_date.clone();
+
+                // The user's zone is resolved on a miss only; saving settings
+                // drops the user's cached timelines
+                let result = server_timeline.query_cache.get_or_run(
+                    QueryCacheKey::timeline(&req),
+                    || {
+                        let zone = server_timeline
+                            .user_timeline_zone(&req.user_id, req.timezone.as_deref())?;
+                        let events = server_timeline.query_timeline(
+                            &req.user_id,
+                            &req.start_date,
+                            &req.end_date,
+                            &zone,
+                            req.filters.as_ref(),
+                        )?;
+                        Ok(build_timeline_response(events, &zone))
+                    },
+                    |response| response.total_events as i32,
+                );
+
+                match result {
+                    Ok(response) => {
+                        let result_count = response.result_count;
+
+                        // Log the query asynchronously
+                        let logger = audit_logger_timeline.clone();
+                        tokio::spawn(async move {
+                            let _ = logger.log_query(
+                                &user_id,
+                                &format!("timeline:{}:{}", start_date, end_date),
+                                true,
+                                result_count,
+                            ).await;
+                        });
+
+                        response.into_reply()
+                    }
+                    Err(e) => {
+                        // Log the failed query
+                        let logger = audit_logger_timeline.clone();
+                        tokio::spawn(async move {
+                            let _ = logger.log_query(
+                                &user_id,
+                                &format!("timeline:{}:{}", start_date, end_date),
+                                false,
+                                0,
+                            ).await;
+                        });
+
+                        let error_response = TimelineResponse {
+                            events_by_date: HashMap::new(),
+                            total_events: 0,
+                            summary: TimelineSummary {
+                                total_days: 0,
+                                avg_events_per_day: 0.0,
+                                most_active_date: format!("Error: {}", e),
+                                top_entities: vec![],
+                            },
+                        };
+                        warp::reply::json(&error_response).into_response()
+                    }
+                }
+            });
+
+        // Timeline CSV export
+        let server_timeline_csv = self.clone();
+        let server_timeline_csv_zone = self.clone();
+        let audit_logger_timeline_csv = self.audit_logger.clone();
+        let resolve_csv_zone = move |query: &TimelineCsvQuery| {
+            server_timeline_csv_zone.user_timeline_zone(&query.user_id, query.timezone.as_deref())
+        };
+        let timeline_csv = timeline_csv_route(resolve_csv_zone, move |query: &TimelineCsvQuery, zone: &TimelineZone| {
+            let target = format!("timeline_csv:{}:{}", query.start_date, query.end_date);
+            let logger = audit_logger_timeline_csv.clone();
+            let user_id = query.user_id.clone();
+
+            let mut fetch = match server_timeline_csv.open_timeline_csv(query, zone) {
+                Ok(fetch) => fetch,
+                Err(e) => {
+                    tokio::spawn(async move {
+                        let _ = logger.log_query(&user_id, &target, false, 0).await;
+                    });
+                    return Err(e);
+                }
+            };
+
+            // Pages are fetched on a blocking thread; log through the runtime once the export ends
+            let runtime = tokio::runtime::Handle::current();
+            let mut rows = 0;
+            Ok(move |after: Option<TimelineCursor>| {
+                let page = fetch(after);
+                let finished = match &page {
+                    Ok(page) => {
+                        rows += page.events.len();
+                        page.next.is_none().then_some(true)
+                    }
+                    Err(_) => Some(false),
+                };
+                if let Some(success) = finished {
+                    let (logger, user_id, target) = (logger.clone(), user_id.clone(), target.clone());
+                    let result_count = rows as i32;
+                    runtime.spawn(async move {
+                        let _ = logger.log_query(&user_id, &target, success, result_count).await;
+                    });
+                }
+                page
+            })
+        });
+
+        // Statistics endpoint
+        let server_stats = self.clone();
+        let audit_logger_stats = self.audit_logger.clone();
+        let stats = warp::path("api")
+            .and(warp::path("stats"))
+            .and(warp::post())
+            .and(json_body(self.body_limits.query))
+            .map(move |req: StatsRequest| {
+                let user_id = req.user_id.clone();
+                let time_range = req.time_range.clone();
+
+                let result = server_stats.query_cache.get_or_run(
+                    QueryCacheKey::stats(&req),
+                    || server_stats.query_stats(&req.user_id, &req.time_range),
+                    |response| (response.total_events + response.total_memories) as i32,
+                );
+
+                match result {
+                    Ok(response) => {
+                        let result_count = response.result_count;
+
+                        // Log the query asynchronously
+                        let logger = audit_logger_stats.clone();
+                        tokio::spawn(async move {
+                            let _ = logger.log_query(
+                                &user_id,
+                                &format!("stats:{}", time_range),
+                                true,
+                                result_count,
+                            ).await;
+                        });
+
+                        response.into_reply()
+                    }
+                    Err(e) => {
+                        // Log the failed query
+                        let logger = audit_logger_stats.clone();
+                        tokio::spawn(async move {
+                            let _ = logger.log_query(
+                                &user_id,
+                                &format!("stats:{}", time_range),
+                                false,
+                                0,
+                            ).await;
+                        });
+
+                        let error_response = StatsResponse {
+                            total_memories: 0,
+                            total_events: 0,
+                            total_entities: 0,
+                            events_per_day: HashMap::new(),
+                            event_types: HashMap::new(),
+                            entity_types: HashMap::new(),
+                            entities: vec![],
+                            time_range: TimeRangeStats {
+                                start_date: String::new(),
+                                end_date: String::new(),
+                                total_days: 0,
+                                most_active_day: format!("Error: {}", e),
+                                least_active_day: String::new(),
+                            },
+                        };
+                        warp::reply::json(&error_response).into_response()
+                    }
+                }
+            });
+
+        // Concept rollback endpoint
+        let server_rollback = self.clone();
+        let audit_logger_rollback = self.audit_logger.clone();
+        let concept_rollback = warp::path!("api" / "concepts" / uuid::Uuid / "rollback")
+            .and(warp::post())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(json_body(self.body_limits.query))
+            .map(move |concept_id: uuid::Uuid, authorization: Option<String>, req: ConceptRollbackRequest| {
+                let result = server_rollback
+                    .api_tokens
+                    .authorize(authorization.as_deref(), &req.user_id)
+                    .and_then(|()| server_rollback.rollback_concept(concept_id, &req));
+
+                let logger = audit_logger_rollback.clone();
+                let user_id = req.user_id.clone();
+                let success = result.is_ok();
+                tokio::spawn(async move {
+                    let _ = logger.log_update(
+                        &user_id,
+                        &format!("concept_rollback:{}:v{}", concept_id, req.to_version),
+                        success,
+                    ).await;
+                });
+
+                concept_rollback_reply(&result)
+            });
+
+        // Manual view promotion endpoints
+        let server_ready_views = self.clone();
+        let audit_logger_ready_views = self.audit_logger.clone();
+        let ready_views = warp::path!("api" / "views" / "ready")
+            .and(warp::get())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::query::<ViewsQuery>())
+            .map(move |authorization: Option<String>, query: ViewsQuery| {
+                let result = server_ready_views
+                    .api_tokens
+                    .authorize(authorization.as_deref(), &query.user_id)
+                    .and_then(|()| server_ready_views.load_ready_views(&query.user_id));
+
+                let logger = audit_logger_ready_views.clone();
+                let (success, result_count) = match &result {
+                    Ok(views) => (true, views.len() as i32),
+                    Err(_) => (false, 0),
+                };
+                tokio::spawn(async move {
+                    let _ = logger.log_query(&query.user_id, "views_ready", success, result_count).await;
+                });
+
+                json_result_reply(&result)
+            });
+
+        let server_candidates = self.clone();
+        let audit_logger_candidates = self.audit_logger.clone();
+        let promotion_candidates = promotion_candidates_route(
+            move |query: &PromotionCandidatesQuery, limit: usize| {
+                let result = server_candidates.load_promotion_candidates(query, limit);
+
+                let logger = audit_logger_candidates.clone();
+                let user_id = query.user_id.clone();
+                let (success, result_count) = match &result {
+                    Ok(page) => (true, page.candidates.len() as i32),
+                    Err(_) => (false, 0),
+                };
+                tokio::spawn(async move {
+                    let _ = logger
+                        .log_query(&user_id, "views_promotion_candidates", success, result_count)
+                        .await;
+                });
+
+                result
+            },
+            self.api_tokens.clone(),
+        );
+
+        let server_promote = self.clone();
+        let audit_logger_promote = self.audit_logger.clone();
+        let view_promote = warp::path!("api" / "views" / uuid::Uuid / "promote")
+            .and(warp::post())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(json_body(self.body_limits.query))
+            .map(move |view_id: uuid::Uuid, authorization: Option<String>, req: ViewPromotionRequest| {
+                let result = server_promote
+                    .api_tokens
+                    .authorize(authorization.as_deref(), &req.user_id)
+                    .and_then(|()| server_promote.promote_view(view_id, &req));
+
+                let logger = audit_logger_promote.clone();
+                let success = result.is_ok();
+                tokio::spawn(async move {
+                    let _ = logger
+                        .log_update(&req.user_id, &format!("view_promote:{}", view_id), success)
+                        .await;
+                });
+
+                json_result_reply(&result)
+            });
+
+        // Aggregation endpoint
+        let server_aggregate = self.clone();
+        let audit_logger_aggregate = self.audit_logger.clone();
+        let aggregate = aggregate_route(
+            move |req: &AggregateRequest, query: &AggregateQuery| {
+                let result = server_aggregate.load_aggregate_events(req, query);
+
+                let logger = audit_logger_aggregate.clone();
+                let user_id = req.user_id.clone();
+                let query_text = format!("aggregate:{}:{}", req.range, req.agg_type);
+                let (success, result_count) = match &result {
+                    Ok(events) => (true, events.len() as i32),
+                    Err(_) => (false, 0),
+                };
+                tokio::spawn(async move {
+                    let _ = logger.log_query(&user_id, &query_text, success, result_count).await;
+                });
+
+                result
+            },
+            self.body_limits.query,
+        );
+
+        // Pattern detection endpoints
+        let server_detect = self.clone();
+        let audit_logger_detect = self.audit_logger.clone();
+        let detect_patterns = detect_patterns_route(
+            move |req: &DetectPatternsRequest| {
+                let result = server_detect.detect_patterns(req);
+
+                let logger = audit_logger_detect.clone();
+                let user_id = req.user_id.clone();
+                let metadata = serde_json::json!({
+                    "days": req.days,
+                    "patterns": result.as_ref().map(|r| r.patterns.len()).unwrap_or(0),
+                });
+                let success = result.is_ok();
+                tokio::spawn(async move {
+                    let _ = logger.log_custom(&user_id, "detect", "patterns", success, Some(metadata)).await;
+                });
+
+                result
+            },
+            self.body_limits.query,
+            self.api_tokens.clone(),
+        );
+
+        let server_patterns = self.clone();
+        let audit_logger_patterns = self.audit_logger.clone();
+        let list_patterns = list_patterns_route(move |user_id: &str| {
+            let result = server_patterns.load_patterns(user_id);
+
+            let logger = audit_logger_patterns.clone();
+            let user_id = user_id.to_string();
+            let (success, result_count) = match &result {
+                Ok(views) => (true, views.len() as i32),
+                Err(_) => (false, 0),
+            };
+            tokio::spawn(async move {
+                let _ = logger.log_query(&user_id, "patterns", success, result_count).await;
+            });
+
+            result
+        });
+
+        // Entity relation endpoints
+        let server_related = self.clone();
+        let audit_logger_related = self.audit_logger.clone();
+        let related_entities = related_entities_route(
+            move |query: &RelationsQuery, entity_id: uuid::Uuid| {
+                let result = server_related.load_related_entities(query, entity_id);
+
+                let logger = audit_logger_related.clone();
+                let user_id = query.user_id.clone();
+                let (success, result_count) = match &result {
+                    Ok(neighbors) => (true, neighbors.len() as i32),
+                    Err(_) => (false, 0),
+                };
+                tokio::spawn(async move {
+                    let _ = logger
+                        .log_query(&user_id, &format!("relations:{}", entity_id), success, result_count)
+                        .await;
+                });
+
+                result
+            },
+            self.api_tokens.clone(),
+        );
+
+        let server_relation_stats = self.clone();
+        let audit_logger_relation_stats = self.audit_logger.clone();
+        let relation_stats = relation_stats_route(
+            move |query: &RelationsQuery, entity_id: uuid::Uuid| {
+                let result = server_relation_stats.load_relation_stats(query, entity_id);
+
+                let logger = audit_logger_relation_stats.clone();
+                let user_id = query.user_id.clone();
+                let (success, result_count) = match &result {
+                    Ok(stats) => (true, stats.values().sum::<i64>() as i32),
+                    Err(_) => (false, 0),
+                };
+                tokio::spawn(async move {
+                    let _ = logger
+                        .log_query(&user_id, &format!("relation_stats:{}", entity_id), success, result_count)
+                        .await;
+                });
+
+                result
+            },
+            self.api_tokens.clone(),
+        );
+
+        // Semantic search endpoint
+        let db_url_search = self.database_url.clone();
+        let embedder_search = self.embedder.clone();
+        let keyword_index = self.search_config.keyword_index.clone();
+        let audit_logger_search = self.audit_logger.clone();
+        let search = semantic_search_route(
+            move |req: SemanticSearchRequest, top_k: usize| {
+                let database_url = db_url_search.clone();
+                let embedder = embedder_search.clone();
+                let index = keyword_index.clone();
+                let logger = audit_logger_search.clone();
+                async move {
+                    let user_id = req.user_id.clone();
+                    let result =
+                        search_memories(database_url, embedder, index, req.user_id, req.query, top_k)
+                            .await;
+
+                    let (success, result_count) = match &result {
+                        Ok(hits) => (true, hits.len() as i32),
+                        Err(_) => (false, 0),
+                    };
+                    let _ = logger.log_query(&user_id, "search", success, result_count).await;
+
+                    result
+                }
+            },
+            self.search_config.clone(),
+            self.body_limits.query,
+        );
+
+        // Plugin command endpoint
+        let server_command = self.clone();
+        let audit_logger_command = self.audit_logger.clone();
+        let command = command_route(
+            move |req: CommandRequest| {
+                let server = server_command.clone();
+                let logger = audit_logger_command.clone();
+                async move {
+                    let user_id = req.user_id.clone();
+                    let result = match server.clone().user_default_plugin(&user_id).await {
+                        Ok(default_plugin) => {
+                            run_command(server.plugins.clone(), default_plugin, req).await
+                        }
+                        Err(e) => Err(e),
+                    };
+
+                    let success = matches!(
+                        result,
+                        Ok(CommandResponse::Plugin(_)) | Ok(CommandResponse::Default(_))
+                    );
+                    let _ = logger.log_query(&user_id, "command", success, success as i32).await;
+
+                    result
+                }
+            },
+            self.api_tokens.clone(),
+            self.body_limits.chat,
+        );
+
+        // Per-user settings
+        let server_settings_get = self.clone();
+        let server_settings_put = self.clone();
+        let audit_logger_settings_get = self.audit_logger.clone();
+        let audit_logger_settings_put = self.audit_logger.clone();
+        let settings = settings_routes(
+            move |user_id: &str| {
+                let result = server_settings_get.load_user_settings(user_id);
+
+                let logger = audit_logger_settings_get.clone();
+                let user_id = user_id.to_string();
+                let success = result.is_ok();
+                tokio::spawn(async move {
+                    let _ = logger.log_query(&user_id, "settings", success, success as i32).await;
+                });
+
+                result
+            },
+            move |settings: &UserSettings| {
+                let result = server_settings_put.save_user_settings(settings);
+
+                let logger = audit_logger_settings_put.clone();
+                let user_id = settings.user_id.clone();
+                let success = result.is_ok();
+                tokio::spawn(async move {
+                    let _ = logger.log_custom(&user_id, "update", "settings", success, None).await;
+                });
+
+                result
+            },
+            self.api_tokens.clone(),
+            self.body_limits.query,
+        );
+
+        // Combine routes
+        health
+            .or(health_live)
+            .or(health_ready)
+            .or(chat)
+            .or(timeline_csv)
+            .or(timeline)
+            .or(stats)
+            .or(concept_rollback)
+            .or(ready_views)
+            .or(promotion_candidates)
+            .or(view_promote)
+            .or(aggregate)
+            .or(detect_patterns)
+            .or(list_patterns)
+            .or(related_entities)
+            .or(relation_stats)
+            .or(search)
+            .or(command)
+            .or(settings)
+    }
+
+    /// Start the HTTP server
+    ///
+    /// Runs until Ctrl-C, then flushes any buffered audit entries.
+    pub async fn start(self) -> Result<()> {
+        self.search_config.validate()?;
+        self.body_limits.validate()?;
+        self.health_config.validate()?;
+        self.importance_config.validate()?;
+        self.candidate_config.validate()?;
+        self.pattern_config.validate()?;
+        self.api_tokens.validate()?;
+
+        // CORS headers
+        let cors = warp::cors()
+            .allow_any_origin()
+            .allow_headers(vec!["content-type", "authorization"])
+            .allow_methods(vec![
+                warp::http::Method::GET,
+                warp::http::Method::POST,
+                warp::http::Method::PUT,
+            ]);
+
+        let addr = self.bind_address.clone();
+        let audit_logger = self.audit_logger.clone();
+        let routes = Arc::new(self).routes().with(cors);
+
+        // Start server
+        println!("🚀 DirSoul API Server starting on {}", addr);
+        println!("❤️ Health endpoints: http://{}/health/live, /health/ready", addr);
+        println!("💬 Chat endpoint: http://{}/api/chat", addr);
+        println!("📅 Timeline endpoint: http://{}/api/timeline", addr);
+        println!("📊 Stats endpoint: http://{}/api/stats", addr);
+        println!("⏪ Concept rollback: http://{}/api/concepts/{{id}}/rollback", addr);
+        println!("✅ View promotion: http://{}/api/views/ready, /promotion-candidates, /{{id}}/promote", addr);
+        println!("📈 Aggregate endpoint: http://{}/api/aggregate", addr);
+        println!("🔍 Pattern endpoints: http://{}/api/patterns[/detect]", addr);
+        println!("🕸️ Relation endpoints: http://{}/api/entities/{{id}}/relations, /relation-stats", addr);
+        println!("🔎 Search endpoint: http://{}/api/search", addr);
+        println!("🔌 Command endpoint: http://{}/api/command", addr);
+
+        // Parse address
+        let socket_addr: std::net::SocketAddr = addr.parse()
+            .map_err(|e| DirSoulError::Io(std::io::Error::new(std::io::ErrorKind::InvalidInput, e)))?;
+
+        let (_, server) = warp::serve(routes).bind_with_graceful_shutdown(socket_addr, async {
+            let _ = tokio::signal::ctrl_c().await;
+        });
+        server.await;
+
+        // Write audit entries still waiting in the buffer
+        audit_logger.shutdown().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_provider::mock::MockProvider;
+
+    #[test]
+    fn test_chat_request_serialization() {
+        let req = ChatRequest {
+            message: "Hello".to_string(),
+            user_id: "test_user".to_string(),
+            history: vec![],
+            context: None,
+            idempotency_key: None,
+        };
+
+        let json = serde_json::to_string(&req).unwrap();
+        let _deserialized: ChatRequest = serde_json::from_str(&json).unwrap();
+    }
+
+    #[test]
+    fn test_api_chat_response_serialization() {
+        let resp = ApiChatResponse {
+            response: "Hi".to_string(),
+            history: vec![],
+            recorded_memory_ids: vec![],
+            processing_time_ms: 100,
+            metadata: None,
+        };
+
+        let json = serde_json::to_string(&resp).unwrap();
+        let _deserialized: ApiChatResponse = serde_json::from_str(&json).unwrap();
+    }
+
+    #[test]
+    fn test_concept_rollback_request_deserialization() {
+        let req: ConceptRollbackRequest =
+            serde_json::from_str(r#"{"user_id": "test_user", "to_version": 2}"#).unwrap();
+        assert_eq!(req.user_id, "test_user");
+        assert_eq!(req.to_version, 2);
+
+        // to_version is required
+        assert!(serde_json::from_str::<ConceptRollbackRequest>(r#"{"user_id": "test_user"}"#).is_err());
+    }
+
+    #[test]
+    fn test_view_promotion_request_deserialization() {
+        let req: ViewPromotionRequest = serde_json::from_str(r#"{"user_id": "test_user"}"#).unwrap();
+        assert_eq!(req.user_id, "test_user");
+        assert!(serde_json::from_str::<ViewPromotionRequest>("{}").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_view_promotion_routes_reach_database() {
+        let routes = Arc::new(unreachable_server()).routes();
+
+        // Listing and promotion both need the review token
+        let response = warp::test::request()
+            .method("GET")
+            .path("/api/views/ready?user_id=test_user")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 403);
+
+        let promote_path = format!("/api/views/{}/promote", uuid::Uuid::new_v4());
+        let response = warp::test::request()
+            .method("POST")
+            .path(&promote_path)
+            .json(&serde_json::json!({"user_id": "test_user"}))
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 403);
+
+        let routes = Arc::new(unreachable_server().with_api_token("test_user", "secret")).routes();
+        let response = warp::test::request()
+            .method("GET")
+            .path("/api/views/ready?user_id=test_user")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 401);
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/api/views/ready?user_id=test_user")
+            .header("authorization", "Bearer secret")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 503);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path(&promote_path)
+            .header("authorization", "Bearer wrong")
+            .json(&serde_json::json!({"user_id": "test_user"}))
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 401);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path(&promote_path)
+            .header("authorization", "Bearer secret")
+            .json(&serde_json::json!({"user_id": "test_user"}))
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 503);
+
+        // The user is required
+        let response = warp::test::request()
+            .method("GET")
+            .path("/api/views/ready")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 400);
+    }
+
+    #[test]
+    fn test_api_tokens_act_for_their_user() {
+        let tokens = ApiTokens::new().with_token("alice", "secret").with_token("bob", "other");
+        assert!(tokens.authorize(Some("Bearer secret"), "alice").is_ok());
+        assert!(tokens.authorize(Some("Bearer other"), "bob").is_ok());
+        assert!(matches!(
+            ApiTokens::new().authorize(Some("Bearer secret"), "alice"),
+            Err(DirSoulError::PermissionDenied(_))
+        ));
+        for header in [None, Some("secret"), Some("Bearer wrong"), Some("Bearer secret2")] {
+            let result = tokens.authorize(header, "alice");
+            assert!(matches!(result, Err(DirSoulError::Unauthorized(_))), "{:?}", header);
+        }
+        // A valid token cannot act for another user
+        let result = tokens.authorize(Some("Bearer other"), "alice");
+        assert!(matches!(result, Err(DirSoulError::PermissionDenied(_))));
+
+        // Debug output never shows the tokens
+        assert!(!format!("{:?}", tokens).contains("secret"));
+    }
+
+    #[test]
+    fn test_api_tokens_parse() {
+        let tokens = ApiTokens::parse(" alice=secret, bob = other ,").unwrap();
+        assert!(tokens.authorize(Some("Bearer other"), "bob").is_ok());
+        assert!(ApiTokens::parse("").unwrap().is_empty());
+
+        for spec in ["secret", "alice=", "=secret", "alice=secret,bob=secret"] {
+            let err = ApiTokens::parse(spec).unwrap_err();
+            assert!(matches!(err, DirSoulError::Config(_)), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_promotion_candidates_page_size() {
+        let query: PromotionCandidatesQuery =
+            serde_json::from_str(r#"{"user_id": "test_user"}"#).unwrap();
+        assert_eq!(query.offset, 0);
+        assert_eq!(query.page_size().unwrap(), DEFAULT_CANDIDATE_PAGE_SIZE);
+
+        for (limit, valid) in [
+            (1, true),
+            (MAX_CANDIDATE_PAGE_SIZE, true),
+            (0, false),
+            (MAX_CANDIDATE_PAGE_SIZE + 1, false),
+        ] {
+            let query = PromotionCandidatesQuery {
+                limit: Some(limit),
+                ..query.clone()
+            };
+            assert_eq!(query.page_size().is_ok(), valid, "limit {}", limit);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_promotion_candidates_route_requires_token() {
+        let route = promotion_candidates_route(
+            |query: &PromotionCandidatesQuery, limit: usize| {
+                assert_eq!(query.user_id, "test_user");
+                assert_eq!((query.offset, limit), (20, 5));
+                Ok(PromotionCandidatePage {
+                    candidates: Vec::new(),
+                    total: 20,
+                    next_offset: None,
+                })
+            },
+            ApiTokens::new().with_token("test_user", "secret").with_token("other_user", "other"),
+        );
+        let path = "/api/views/promotion-candidates?user_id=test_user&offset=20&limit=5";
+
+        let response = warp::test::request().method("GET").path(path).reply(&route).await;
+        assert_eq!(response.status(), 401);
+
+        // Neither a wrong token nor another user's token is accepted
+        for (token, status) in [("Bearer wrong", 401), ("Bearer other", 403)] {
+            let response = warp::test::request()
+                .method("GET")
+                .path(path)
+                .header("authorization", token)
+                .reply(&route)
+                .await;
+            assert_eq!(response.status(), status);
+        }
+
+        let response = warp::test::request()
+            .method("GET")
+            .path(path)
+            .header("authorization", "Bearer secret")
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), 200);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["total"], 20);
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/api/views/promotion-candidates?user_id=test_user&limit=0")
+            .header("authorization", "Bearer secret")
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_promotion_candidates_route_reaches_database() {
+        // Without a configured token the endpoint is closed
+        let routes = Arc::new(unreachable_server()).routes();
+        let response = warp::test::request()
+            .method("GET")
+            .path("/api/views/promotion-candidates?user_id=test_user")
+            .header("authorization", "Bearer secret")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 403);
+
+        let routes = Arc::new(unreachable_server().with_api_token("test_user", "secret")).routes();
+        let response = warp::test::request()
+            .method("GET")
+            .path("/api/views/promotion-candidates?user_id=test_user")
+            .header("authorization", "Bearer secret")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 503);
+    }
+
+    #[tokio::test]
+    async fn test_concept_rollback_requires_token() {
+        let rollback_path = format!("/api/concepts/{}/rollback", uuid::Uuid::new_v4());
+        let body = serde_json::json!({"user_id": "test_user", "to_version": 1});
+
+        let routes = Arc::new(unreachable_server().with_api_token("test_user", "secret")).routes();
+        let response = warp::test::request()
+            .method("POST")
+            .path(&rollback_path)
+            .json(&body)
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 401);
+
+        // A valid token cannot roll back another user's concept
+        let response = warp::test::request()
+            .method("POST")
+            .path(&rollback_path)
+            .header("authorization", "Bearer secret")
+            .json(&serde_json::json!({"user_id": "other_user", "to_version": 1}))
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 403);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path(&rollback_path)
+            .header("authorization", "Bearer secret")
+            .json(&body)
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 503);
+    }
+
+    #[test]
+    fn test_concept_rollback_reply_status() {
+        use warp::Reply;
+
+        let invalid: Result<StableConcept> =
+            Err(DirSoulError::Config("Version 9 does not exist".to_string()));
+        let response = concept_rollback_reply(&invalid).into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::BAD_REQUEST);
+
+        let missing: Result<StableConcept> = Err(DirSoulError::NotFound("no concept".to_string()));
+        let response = concept_rollback_reply(&missing).into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::NOT_FOUND);
+
+        let now = chrono::Utc::now();
+        let head: Result<StableConcept> = Ok(StableConcept {
+            concept_id: uuid::Uuid::new_v4(),
+            user_id: "test_user".to_string(),
+            canonical_name: "likes_fruit".to_string(),
+            display_name: "喜欢吃水果".to_string(),
+            concept_type: "preference".to_string(),
+            description: None,
+            definition: serde_json::json!({}),
+            version: 4,
+            parent_concept_id: None,
+            is_deprecated: false,
+            promoted_from: None,
+            promoted_at: now,
+            promotion_confidence: 0.9,
+            created_at: now,
+            updated_at: now,
+            deprecated_at: None,
+            access_count: 0,
+            last_accessed_at: None,
+            source: "rollback_from_v3".to_string(),
+            tags: None,
+            metadata: None,
+        });
+        let response = concept_rollback_reply(&head).into_response();
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+    }
+
+    fn aggregate_fixture(_: &AggregateRequest, _: &AggregateQuery) -> Result<Vec<EventMemory>> {
+        let today = crate::event_aggregator::TimeBucket::Day.bucket_start(chrono::Utc::now());
+        let event = |days_ago: i64, action: &str, target: &str, quantity: f64| EventMemory {
+            event_id: uuid::Uuid::new_v4(),
+            memory_id: uuid::Uuid::new_v4(),
+            user_id: "test_user".to_string(),
+            timestamp: today - chrono::Duration::days(days_ago) + chrono::Duration::hours(1),
+            actor: None,
+            action: action.to_string(),
+            target: target.to_string(),
+            quantity: Some(quantity),
+            unit: None,
+            confidence: 1.0,
+            extractor_version: None,
+            metadata: None,
+        };
+        Ok(vec![
+            event(1, "喝", "咖啡", 1.0),
+            event(1, "喝", "咖啡", 2.0),
+            event(0, "吃", "苹果", 3.0),
+        ])
+    }
+
+    async fn post_aggregate(body: serde_json::Value) -> (warp::http::StatusCode, serde_json::Value) {
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/aggregate")
+            .json(&body)
+            .reply(&aggregate_route(aggregate_fixture, BodyLimits::default().query))
+            .await;
+        (response.status(), serde_json::from_slice(response.body()).unwrap())
+    }
+
+    /// Detection over a seeded coffee-every-morning history
+    fn detect_fixture(req: &DetectPatternsRequest) -> Result<PatternDetectionResult> {
+        let time_range = DetectionTimeRange::last_n_days(req.days);
+        let events: Vec<EventMemory> = (0..req.days)
+            .map(|day| EventMemory {
+                event_id: uuid::Uuid::new_v4(),
+                memory_id: uuid::Uuid::new_v4(),
+                user_id: req.user_id.clone(),
+                timestamp: time_range.start + chrono::Duration::days(day) + chrono::Duration::hours(1),
+                actor: None,
+                action: "喝".to_string(),
+                target: "咖啡".to_string(),
+                quantity: Some(1.0),
+                unit: Some("杯".to_string()),
+                confidence: 1.0,
+                extractor_version: None,
+                metadata: None,
+            })
+            .collect();
+        PatternDetector::new().detect_patterns_in_events(&req.user_id, &events, &[], time_range)
+    }
+
+    fn detect_tokens() -> ApiTokens {
+        ApiTokens::new().with_token("test_user", "secret")
+    }
+
+    #[tokio::test]
+    async fn test_detect_patterns_requires_token() {
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/patterns/detect")
+            .json(&serde_json::json!({"user_id": "test_user", "days": 14}))
+            .reply(&detect_patterns_route(detect_fixture, BodyLimits::default().query, detect_tokens()))
+            .await;
+        assert_eq!(response.status(), warp::http::StatusCode::UNAUTHORIZED);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/patterns/detect")
+            .header("authorization", "Bearer secret")
+            .json(&serde_json::json!({"user_id": "other_user", "days": 14}))
+            .reply(&detect_patterns_route(detect_fixture, BodyLimits::default().query, detect_tokens()))
+            .await;
+        assert_eq!(response.status(), warp::http::StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_detect_patterns_finds_high_frequency_habit() {
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/patterns/detect")
+            .header("authorization", "Bearer secret")
+            .json(&serde_json::json!({"user_id": "test_user", "days": 14}))
+            .reply(&detect_patterns_route(detect_fixture, BodyLimits::default().query, detect_tokens()))
+            .await;
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["events_analyzed"], 14);
+        let patterns = body["patterns"].as_array().unwrap();
+        assert!(patterns.iter().any(|p| {
+            p["pattern_type"] == "HighFrequency" && p["action"] == "喝" && p["target"] == "咖啡"
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_detect_patterns_rejects_bad_window() {
+        for days in [0, -3, MAX_PATTERN_DETECTION_DAYS + 1] {
+            let response = warp::test::request()
+                .method("POST")
+                .path("/api/patterns/detect")
+                .header("authorization", "Bearer secret")
+                .json(&serde_json::json!({"user_id": "test_user", "days": days}))
+                .reply(&detect_patterns_route(detect_fixture, BodyLimits::default().query, detect_tokens()))
+                .await;
+            assert_eq!(response.status(), warp::http::StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_patterns_reads_user_from_query() {
+        let route = list_patterns_route(|user_id: &str| {
+            assert_eq!(user_id, "test_user");
+            Ok(Vec::new())
+        });
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/api/patterns?user_id=test_user")
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        assert_eq!(response.body().as_ref(), b"[]");
+
+        let response = warp::test::request().method("GET").path("/api/patterns").reply(&route).await;
+        assert_eq!(response.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_settings_routes() {
+        let saved = Arc::new(std::sync::Mutex::new(Vec::<UserSettings>::new()));
+        let saved_put = saved.clone();
+        let route = settings_routes(
+            |user_id: &str| Ok(UserSettings::new(user_id)),
+            move |settings: &UserSettings| {
+                saved_put.lock().unwrap().push(settings.clone());
+                Ok(settings.clone())
+            },
+            ApiTokens::new().with_token("test_user", "secret"),
+            BodyLimits::default().query,
+        );
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/api/settings?user_id=test_user")
+            .header("authorization", "Bearer secret")
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["user_id"], "test_user");
+        assert!(body["timezone"].is_null());
+
+        // Reading needs the user's own API token too
+        for (user_id, authorization, status) in [
+            ("test_user", None, warp::http::StatusCode::UNAUTHORIZED),
+            ("other_user", Some("Bearer secret"), warp::http::StatusCode::FORBIDDEN),
+        ] {
+            let mut request = warp::test::request()
+                .method("GET")
+                .path(&format!("/api/settings?user_id={}", user_id));
+            if let Some(authorization) = authorization {
+                request = request.header("authorization", authorization);
+            }
+            let response = request.reply(&route).await;
+            assert_eq!(response.status(), status);
+        }
+
+        // Saving needs the user's own API token
+        for (user_id, authorization, status) in [
+            ("test_user", None, warp::http::StatusCode::UNAUTHORIZED),
+            ("test_user", Some("Bearer wrong"), warp::http::StatusCode::UNAUTHORIZED),
+            ("other_user", Some("Bearer secret"), warp::http::StatusCode::FORBIDDEN),
+        ] {
+            let mut request = warp::test::request()
+                .method("PUT")
+                .path("/api/settings")
+                .json(&serde_json::json!({"user_id": user_id, "promotion_min_confidence": 0.1}));
+            if let Some(authorization) = authorization {
+                request = request.header("authorization", authorization);
+            }
+            let response = request.reply(&route).await;
+            assert_eq!(response.status(), status);
+        }
+        assert!(saved.lock().unwrap().is_empty());
+
+        let response = warp::test::request()
+            .method("PUT")
+            .path("/api/settings")
+            .header("authorization", "Bearer secret")
+            .json(&serde_json::json!({"user_id": "test_user", "timezone": "+08:00"}))
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        assert_eq!(saved.lock().unwrap()[0].timezone.as_deref(), Some("+08:00"));
+
+        // Invalid settings are rejected before they are saved
+        let response = warp::test::request()
+            .method("PUT")
+            .path("/api/settings")
+            .header("authorization", "Bearer secret")
+            .json(&serde_json::json!({"user_id": "test_user", "timezone": "Mars/Base"}))
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), warp::http::StatusCode::BAD_REQUEST);
+        assert_eq!(saved.lock().unwrap().len(), 1);
+
+        let response = warp::test::request().method("GET").path("/api/settings").reply(&route).await;
+        assert_eq!(response.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_scalar_sum() {
+        let (status, body) = post_aggregate(serde_json::json!({
+            "user_id": "test_user", "range": "7d", "agg_type": "sum"
+        }))
+        .await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(body["kind"], "scalar");
+        assert_eq!(body["result"]["value"], 6.0);
+        assert_eq!(body["result"]["count"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_grouped() {
+        let (status, body) = post_aggregate(serde_json::json!({
+            "user_id": "test_user", "range": "7d", "agg_type": "count", "group_by": "target"
+        }))
+        .await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(body["kind"], "grouped");
+        assert_eq!(body["groups"]["咖啡"]["value"], 2.0);
+        assert_eq!(body["groups"]["苹果"]["value"], 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_bucketed_series() {
+        let (status, body) = post_aggregate(serde_json::json!({
+            "user_id": "test_user", "range": "7d", "agg_type": "count", "bucket": "day"
+        }))
+        .await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(body["kind"], "series");
+
+        let values: Vec<f64> = body["points"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["result"]["value"].as_f64().unwrap())
+            .collect();
+        // Today and yesterday are the last two buckets; earlier days are empty
+        assert!(values.len() >= 8);
+        assert_eq!(values[values.len() - 2..], [2.0, 1.0]);
+        assert_eq!(values.iter().sum::<f64>(), 3.0);
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_rejects_unknown_values() {
+        let (status, body) = post_aggregate(serde_json::json!({
+            "user_id": "test_user", "range": "7d", "agg_type": "median"
+        }))
+        .await;
+        assert_eq!(status, warp::http::StatusCode::BAD_REQUEST);
+        assert!(body["error"].as_str().unwrap().contains("agg_type"));
+
+        let (status, _) = post_aggregate(serde_json::json!({
+            "user_id": "test_user", "range": "7d", "agg_type": "sum", "group_by": "color"
+        }))
+        .await;
+        assert_eq!(status, warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    fn search_fixture(
+        _req: SemanticSearchRequest,
+        top_k: usize,
+    ) -> std::future::Ready<Result<Vec<SearchHit>>> {
+        let similarities = [0.35, 0.92, 0.55, 0.81, 0.62];
+        let hits = similarities
+            .iter()
+            .enumerate()
+            .map(|(i, &similarity)| SearchHit {
+                memory_id: uuid::Uuid::from_u128(i as u128 + 1),
+                similarity,
+            })
+            .take(top_k)
+            .collect();
+        std::future::ready(Ok(hits))
+    }
+
+    async fn post_search(
+        config: SearchConfig,
+        body: serde_json::Value,
+    ) -> (warp::http::StatusCode, serde_json::Value) {
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/search")
+            .json(&body)
+            .reply(&semantic_search_route(search_fixture, config, BodyLimits::default().query))
+            .await;
+        (response.status(), serde_json::from_slice(response.body()).unwrap())
+    }
+
+    fn similarities(body: &serde_json::Value) -> Vec<f64> {
+        body["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|hit| hit["similarity"].as_f64().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_search_cutoff_drops_weak_hits() {
+        let body = serde_json::json!({"user_id": "u1", "query": "咖啡"});
+
+        let (status, default) = post_search(SearchConfig::default(), body.clone()).await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(similarities(&default), vec![0.92, 0.81, 0.62, 0.55]);
+        assert_eq!(default["min_similarity"], 0.5);
+
+        let strict = SearchConfig {
+            min_similarity: 0.8,
+            ..Default::default()
+        };
+        let (status, strict) = post_search(strict, body).await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        // Fewer than top_k remain, strong matches are kept
+        assert_eq!(similarities(&strict), vec![0.92, 0.81]);
+        assert_eq!(strict["results"][0]["memory_id"], uuid::Uuid::from_u128(2).to_string());
+    }
+
+    #[tokio::test]
+    async fn test_search_top_k_default_and_clamp() {
+        let config = SearchConfig {
+            min_similarity: 0.0,
+            default_top_k: 2,
+            max_top_k: 3,
+        };
+
+        let (_, body) = post_search(config.clone(), serde_json::json!({"user_id": "u1", "query": "q"})).await;
+        assert_eq!(similarities(&body), vec![0.92, 0.35]);
+
+        let (_, body) =
+            post_search(config.clone(), serde_json::json!({"user_id": "u1", "query": "q", "top_k": 100})).await;
+        assert_eq!(similarities(&body).len(), 3);
+
+        let (status, _) =
+            post_search(config.clone(), serde_json::json!({"user_id": "u1", "query": "q", "top_k": 0})).await;
+        assert_eq!(status, warp::http::StatusCode::BAD_REQUEST);
+
+        let (status, _) = post_search(config, serde_json::json!({"user_id": "u1", "query": "  "})).await;
+        assert_eq!(status, warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_search_config_validation() {
+        assert!(SearchConfig::default().validate().is_ok());
+        assert!(SearchConfig { min_similarity: 1.5, ..Default::default() }.validate().is_err());
+        assert!(SearchConfig { default_top_k: 0, ..Default::default() }.validate().is_err());
+        assert!(SearchConfig { default_top_k: 20, max_top_k: 10, ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_search_config_from_toml() {
+        let config = SearchConfig::from_toml_str(
+            "min_similarity = 0.3\n[keyword_index]\ntokenizer = \"whitespace\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.min_similarity, 0.3);
+        assert_eq!(config.default_top_k, SearchConfig::default().default_top_k);
+        assert_eq!(config.keyword_index.tokenizer, KeywordTokenizer::Whitespace);
+        assert_eq!(config.keyword_index.stop_words, KeywordIndexConfig::default().stop_words);
+
+        // Loading validates, so a bad file fails at startup
+        let err = SearchConfig::from_toml_str("default_top_k = 0").unwrap_err();
+        assert!(matches!(err, DirSoulError::Config(_)), "{:?}", err);
+        assert!(SearchConfig::from_toml_str("min_similarity = \"high\"").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_without_embedder_is_unavailable() {
+        let err = search_memories(
+            "postgresql://dirsoul@127.0.0.1:1/dirsoul".to_string(),
+            None,
+            KeywordIndexConfig::default(),
+            "u1".to_string(),
+            "咖啡".to_string(),
+            5,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error_status(&err), warp::http::StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    /// Plugin manager with an installed `决策` plugin echoing user and query
+    async fn command_plugins() -> Arc<PluginManager> {
+        let plugin = crate::plugin_builder::PluginBuilder::new("决策", "Decision")
+            .on_query(|query, context| async move {
+                Ok(crate::plugin::PluginResponse {
+                    content: format!("{}: {}", context.user_id, query),
+                    sources: vec![],
+                    confidence: 0.9,
+                    metadata: serde_json::json!({}),
+                    timestamp: chrono::Utc::now(),
+                })
+            })
+            .build()
+            .unwrap();
+        let manager = PluginManager::new();
+        manager
+            .install(plugin, crate::agents::MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+        Arc::new(manager)
+    }
+
+    async fn post_command(
+        plugins: Arc<PluginManager>,
+        default_plugin: Option<&str>,
+        body: serde_json::Value,
+    ) -> (warp::http::StatusCode, serde_json::Value) {
+        let default_plugin = default_plugin.map(str::to_string);
+        // The token acts for whichever user the body names
+        let user_id = body["user_id"].as_str().unwrap_or_default();
+        let route = command_route(
+            move |req: CommandRequest| run_command(plugins.clone(), default_plugin.clone(), req),
+            ApiTokens::new().with_token(user_id, "secret"),
+            BodyLimits::default().chat,
+        );
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/command")
+            .header("authorization", "Bearer secret")
+            .json(&body)
+            .reply(&route)
+            .await;
+        (response.status(), serde_json::from_slice(response.body()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_command_routes_to_installed_plugin() {
+        let plugins = command_plugins().await;
+
+        let (status, body) = post_command(
+            plugins.clone(),
+            None,
+            serde_json::json!({"user_id": "u1", "input": "@决策 要不要换工作"}),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(body["Plugin"]["content"], "u1: 要不要换工作");
+
+        // Plain input goes to the default plugin
+        let (status, body) = post_command(
+            plugins.clone(),
+            Some("决策"),
+            serde_json::json!({"user_id": "u2", "input": "最近很迷茫"}),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(body["Default"]["content"], "u2: 最近很迷茫");
+
+        // A command without a query never reaches the plugin
+        let (status, body) = post_command(
+            plugins,
+            None,
+            serde_json::json!({"user_id": "u1", "input": "@决策"}),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(body["Error"], "empty query for @决策");
+    }
+
+    #[tokio::test]
+    async fn test_command_requires_token_and_plugin_ownership() {
+        let plugins = command_plugins().await;
+        let owned = crate::plugin_builder::PluginBuilder::new("日记", "Diary")
+            .on_query(|query, _context| async move {
+                Ok(crate::plugin::PluginResponse {
+                    content: query,
+                    sources: vec![],
+                    confidence: 0.9,
+                    metadata: serde_json::json!({}),
+                    timestamp: chrono::Utc::now(),
+                })
+            })
+            .build()
+            .unwrap();
+        plugins
+            .install_for_user("u1", owned, crate::agents::MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+
+        let route = {
+            let plugins = plugins.clone();
+            command_route(
+                move |req: CommandRequest| run_command(plugins.clone(), None, req),
+                ApiTokens::new().with_token("u1", "secret").with_token("u2", "other"),
+                BodyLimits::default().chat,
+            )
+        };
+        // u2's token cannot run a command as u1
+        for (authorization, status) in [
+            (None, warp::http::StatusCode::UNAUTHORIZED),
+            (Some("Bearer wrong"), warp::http::StatusCode::UNAUTHORIZED),
+            (Some("Bearer other"), warp::http::StatusCode::FORBIDDEN),
+        ] {
+            let mut request = warp::test::request()
+                .method("POST")
+                .path("/api/command")
+                .json(&serde_json::json!({"user_id": "u1", "input": "@决策 要不要换工作"}));
+            if let Some(authorization) = authorization {
+                request = request.header("authorization", authorization);
+            }
+            let response = request.reply(&route).await;
+            assert_eq!(response.status(), status);
+        }
+
+        // Another user's plugin is refused; the owner reaches it
+        let (status, _) = post_command(
+            plugins.clone(),
+            None,
+            serde_json::json!({"user_id": "u2", "input": "@日记 今天很开心"}),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::FORBIDDEN);
+        let (status, body) = post_command(
+            plugins,
+            None,
+            serde_json::json!({"user_id": "u1", "input": "@日记 今天很开心"}),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(body["Plugin"]["content"], "今天很开心");
+    }
+
+    #[tokio::test]
+    async fn test_command_to_unknown_plugin_is_not_found() {
+        let (status, body) = post_command(
+            command_plugins().await,
+            None,
+            serde_json::json!({"user_id": "u1", "input": "@心理分析 我最近压力很大"}),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::NOT_FOUND);
+        assert!(body["error"].as_str().unwrap().contains("心理分析"), "{}", body);
+
+        let (status, _) = post_command(
+            command_plugins().await,
+            None,
+            serde_json::json!({"user_id": " ", "input": "@决策 要不要换工作"}),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    fn graph_entity(id: u128, name: &str, entity_type: &str) -> Entity {
+        Entity {
+            entity_id: uuid::Uuid::from_u128(id),
+            user_id: "u1".to_string(),
+            canonical_name: name.to_string(),
+            entity_type: entity_type.to_string(),
+            attributes: None,
+            first_seen: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+            occurrence_count: 1,
+            confidence: 0.9,
+        }
+    }
+
+    fn graph_relation(source: u128, target: u128, relation_type: &str, strength: f64) -> EntityRelation {
+        EntityRelation {
+            relation_id: uuid::Uuid::new_v4(),
+            user_id: "u1".to_string(),
+            source_entity_id: uuid::Uuid::from_u128(source),
+            target_entity_id: uuid::Uuid::from_u128(target),
+            relation_type: relation_type.to_string(),
+            confidence: 0.8,
+            first_seen: chrono::Utc::now(),
+            last_seen: chrono::Utc::now(),
+            strength,
+            observation_count: 1,
+            co_occurrence: strength,
+        }
+    }
+
+    /// Alice (1) works at Acme (2) and lives in 北京 (3); Bob (4) is her friend
+    fn related_fixture(
+        query: &RelationsQuery,
+        entity_id: uuid::Uuid,
+    ) -> Result<Vec<(Entity, EntityRelation, Option<EntityRelation>)>> {
+        let entities = vec![
+            graph_entity(1, "Alice", "person"),
+            graph_entity(2, "Acme", "organization"),
+            graph_entity(3, "北京", "place"),
+            graph_entity(4, "Bob", "person"),
+        ];
+        let relations = vec![
+            graph_relation(1, 2, "works_at", 0.9),
+            graph_relation(1, 3, "located_at", 0.4),
+            graph_relation(4, 1, "friends_with", 0.7),
+        ];
+
+        if !entities.iter().any(|e| e.entity_id == entity_id && e.user_id == query.user_id) {
+            return Err(DirSoulError::NotFound(format!("Entity {} not found", entity_id)));
+        }
+        let entity = |id: uuid::Uuid| entities.iter().find(|e| e.entity_id == id).cloned().unwrap();
+        let threshold = query.min_strength.unwrap_or(0.1);
+
+        let mut neighbors = Vec::new();
+        for rel in relations.into_iter().filter(|r| r.strength >= threshold) {
+            if rel.source_entity_id == entity_id {
+                neighbors.push((entity(rel.target_entity_id), rel, None));
+            } else if rel.target_entity_id == entity_id {
+                neighbors.push((entity(rel.source_entity_id), rel.clone(), Some(rel)));
+            }
+        }
+        Ok(neighbors)
+    }
+
+    fn relation_stats_fixture(query: &RelationsQuery, entity_id: uuid::Uuid) -> Result<HashMap<String, i64>> {
+        let mut stats = HashMap::new();
+        for (_, rel, _) in related_fixture(query, entity_id)? {
+            *stats.entry(rel.relation_type).or_insert(0) += 1;
+        }
+        Ok(stats)
+    }
+
+    /// u1 and u2 each hold their own token
+    fn relation_tokens() -> ApiTokens {
+        ApiTokens::new().with_token("u1", "secret").with_token("u2", "other")
+    }
+
+    fn relation_request(path: &str, authorization: Option<&str>) -> warp::test::RequestBuilder {
+        let request = warp::test::request().method("GET").path(path);
+        match authorization {
+            Some(authorization) => request.header("authorization", authorization),
+            None => request,
+        }
+    }
+
+    async fn get_related(path: &str, authorization: Option<&str>) -> (warp::http::StatusCode, serde_json::Value) {
+        let response = relation_request(path, authorization)
+            .reply(&related_entities_route(related_fixture, relation_tokens()))
+            .await;
+        let body = serde_json::from_slice(response.body()).unwrap_or(serde_json::Value::Null);
+        (response.status(), body)
+    }
+
+    async fn get_relation_stats(
+        path: &str,
+        authorization: Option<&str>,
+    ) -> (warp::http::StatusCode, serde_json::Value) {
+        let response = relation_request(path, authorization)
+            .reply(&relation_stats_route(relation_stats_fixture, relation_tokens()))
+            .await;
+        let body = serde_json::from_slice(response.body()).unwrap_or(serde_json::Value::Null);
+        (response.status(), body)
+    }
+
+    fn neighbor_names(body: &serde_json::Value) -> Vec<(String, String)> {
+        body["related"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| {
+                (
+                    r["entity"]["canonical_name"].as_str().unwrap().to_string(),
+                    r["direction"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_related_entities_returns_neighbors() {
+        let alice = uuid::Uuid::from_u128(1);
+
+        let (status, body) = get_related(
+            &format!("/api/entities/{}/relations?user_id=u1", alice),
+            Some("Bearer secret"),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(body["entity_id"], alice.to_string());
+        assert_eq!(
+            neighbor_names(&body),
+            vec![
+                ("Acme".to_string(), "outgoing".to_string()),
+                ("Bob".to_string(), "incoming".to_string()),
+                ("北京".to_string(), "outgoing".to_string()),
+            ]
+        );
+        assert_eq!(body["related"][0]["relation_type"], "works_at");
+        assert_eq!(body["related"][0]["strength"], 0.9);
+
+        let (status, body) = get_related(
+            &format!("/api/entities/{}/relations?user_id=u1&min_strength=0.5", alice),
+            Some("Bearer secret"),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(
+            neighbor_names(&body),
+            vec![
+                ("Acme".to_string(), "outgoing".to_string()),
+                ("Bob".to_string(), "incoming".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_relation_stats_histogram() {
+        let alice = uuid::Uuid::from_u128(1);
+
+        let (status, body) = get_relation_stats(
+            &format!("/api/entities/{}/relation-stats?user_id=u1", alice),
+            Some("Bearer secret"),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::OK);
+        assert_eq!(body["total_relations"], 3);
+        assert_eq!(
+            body["by_type"],
+            serde_json::json!({"works_at": 1, "located_at": 1, "friends_with": 1})
+        );
+
+        let (_, body) = get_relation_stats(
+            &format!("/api/entities/{}/relation-stats?user_id=u1&min_strength=0.8", alice),
+            Some("Bearer secret"),
+        )
+        .await;
+        assert_eq!(body["total_relations"], 1);
+        assert_eq!(body["by_type"], serde_json::json!({"works_at": 1}));
+    }
+
+    #[tokio::test]
+    async fn test_relation_endpoints_reject_bad_input() {
+        let alice = uuid::Uuid::from_u128(1);
+
+        let (status, _) = get_related(
+            &format!("/api/entities/{}/relations?user_id=u1&min_strength=1.5", alice),
+            Some("Bearer secret"),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::BAD_REQUEST);
+
+        // Another user's entity looks the same as a missing one
+        let (status, _) = get_related(
+            &format!("/api/entities/{}/relations?user_id=u2", alice),
+            Some("Bearer other"),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::NOT_FOUND);
+        let (status, _) = get_relation_stats(
+            &format!("/api/entities/{}/relation-stats?user_id=u2", alice),
+            Some("Bearer other"),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::NOT_FOUND);
+
+        let (status, _) = get_relation_stats(
+            &format!("/api/entities/{}/relation-stats", alice),
+            Some("Bearer secret"),
+        )
+        .await;
+        assert_eq!(status, warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_relation_endpoints_require_token() {
+        let alice = uuid::Uuid::from_u128(1);
+        let related = format!("/api/entities/{}/relations?user_id=u1", alice);
+        let stats = format!("/api/entities/{}/relation-stats?user_id=u1", alice);
+
+        for (authorization, expected) in [
+            (None, warp::http::StatusCode::UNAUTHORIZED),
+            (Some("Bearer wrong"), warp::http::StatusCode::UNAUTHORIZED),
+            // u2's token cannot read u1's graph
+            (Some("Bearer other"), warp::http::StatusCode::FORBIDDEN),
+        ] {
+            let (status, _) = get_related(&related, authorization).await;
+            assert_eq!(status, expected, "{:?}", authorization);
+            let (status, _) = get_relation_stats(&stats, authorization).await;
+            assert_eq!(status, expected, "{:?}", authorization);
+        }
+    }
+
+    fn history(turns: usize) -> Vec<ChatMessage> {
+        (0..turns)
+            .flat_map(|i| {
+                vec![
+                    ChatMessage::user(format!("问题{}", i)),
+                    ChatMessage::assistant(format!("回答{}", i)),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("苹果"), 2);
+        assert_eq!(estimate_tokens("abcdefgh"), 2);
+        assert_eq!(estimate_tokens("吃abc"), 2);
+    }
+
+    #[test]
+    fn test_history_window_respects_turn_count() {
+        let messages = history(5);
+
+        // Default matches the old fixed window of the last 4 messages
+        let window = select_history_window(&messages, 0, &ChatConfig::default());
+        assert_eq!(window.len(), 4);
+        assert_eq!(window[0].content, "问题3");
+
+        let config = ChatConfig {
+            history_turns: 3,
+            ..Default::default()
+        };
+        let window = select_history_window(&messages, 0, &config);
+        assert_eq!(window.len(), 6);
+        assert_eq!(window[0].content, "问题2");
+
+        // An odd-length history ends with a lone leading message
+        let window = select_history_window(&messages[1..], 0, &ChatConfig {
+            history_turns: 10,
+            ..Default::default()
+        });
+        assert_eq!(window.len(), 9);
+    }
+
+    #[test]
+    fn test_history_window_respects_token_budget() {
+        // "用户: 问题0\n" and "助手: 回答0\n" are 5 tokens each, 10 per turn
+        let messages = history(5);
+        let config = ChatConfig {
+            history_turns: 10,
+            max_prompt_tokens: Some(40),
+            ..Default::default()
+        };
+
+        // 15 + 10 + 10 fits, a third turn would reach 45
+        let window = select_history_window(&messages, 15, &config);
+        assert_eq!(window.len(), 4);
+        assert_eq!(window[0].content, "问题3");
+
+        // Nothing fits when the fixed prompt already uses the budget
+        assert!(select_history_window(&messages, 35, &config).is_empty());
+    }
+
+    #[test]
+    fn test_http_server_creation() {
+        let server = HttpServer::new("127.0.0.1:8080".to_string(), "postgresql://localhost/test".to_string()).unwrap();
+        assert_eq!(server.bind_address, "127.0.0.1:8080");
+    }
+
+    /// Server pointed at a port nothing listens on
+    fn unreachable_server() -> HttpServer {
+        HttpServer::new(
+            "127.0.0.1:8080".to_string(),
+            "postgresql://dirsoul@127.0.0.1:1/dirsoul".to_string(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_entity_type_counts_keep_custom_types() {
+        let counts = entity_type_counts(vec![
+            ("person".to_string(), 2),
+            ("Person".to_string(), 1),
+            ("medication".to_string(), 3),
+            ("Project".to_string(), 1),
+        ]);
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts["person"], 3);
+        assert_eq!(counts["medication"], 3);
+        assert_eq!(counts["project"], 1);
+        assert!(!counts.contains_key("object"));
+    }
+
+    #[test]
+    fn test_connection_failure_is_retryable() {
+        let server = unreachable_server();
+
+        let err = server.query_timeline("test_user", "2026-01-01", "2026-01-31", &TimelineZone::default(), None).unwrap_err();
+        assert!(matches!(err, DirSoulError::DatabaseConnection(_)), "{:?}", err);
+        assert!(err.retryable());
+        assert_eq!(error_status(&err), warp::http::StatusCode::SERVICE_UNAVAILABLE);
+
+        let err = server.query_stats("test_user", "7d").unwrap_err();
+        assert!(matches!(err, DirSoulError::DatabaseConnection(_)), "{:?}", err);
+        assert!(err.retryable());
+    }
+
+    #[tokio::test]
+    async fn test_requests_share_one_server() {
+        let server = Arc::new(unreachable_server());
+        let routes = server.clone().routes();
+        let handles = Arc::strong_count(&server);
+
+        // Rejected before the (unreachable) database is touched
+        for _ in 0..20 {
+            let response = warp::test::request()
+                .method("POST")
+                .path("/api/timeline")
+                .json(&serde_json::json!({
+                    "user_id": "test_user",
+                    "start_date": "yesterday",
+                    "end_date": "2026-01-31",
+                }))
+                .reply(&routes)
+                .await;
+            assert_eq!(response.status(), warp::http::StatusCode::OK);
+        }
+
+        // Handlers only borrow the shared server; nothing accumulates per request
+        assert_eq!(Arc::strong_count(&server), handles);
+        drop(routes);
+        assert_eq!(Arc::strong_count(&server), 1);
+    }
+
+    #[test]
+    fn test_parse_failure_is_not_retryable() {
+        // Input is rejected before the (unreachable) database is touched
+        let server = unreachable_server();
+
+        let err = server.query_timeline("test_user", "yesterday", "2026-01-31", &TimelineZone::default(), None).unwrap_err();
+        assert!(matches!(err, DirSoulError::Config(_)), "{:?}", err);
+        assert!(!err.retryable());
+        assert_eq!(error_status(&err), warp::http::StatusCode::BAD_REQUEST);
+
+        let err = server.query_stats("test_user", "1y").unwrap_err();
+        assert!(matches!(err, DirSoulError::Config(_)), "{:?}", err);
+        assert!(!err.retryable());
+    }
+
+    #[test]
+    fn test_parse_timeline_bound() {
+        let utc = TimelineZone::default();
+        let start = parse_timeline_bound("2026-01-31", "start_date", false, &utc).unwrap();
+        assert_eq!(start.to_rfc3339(), "2026-01-31T00:00:00+00:00");
+        let end = parse_timeline_bound("2026-01-31", "end_date", true, &utc).unwrap();
+        assert_eq!(end.to_rfc3339(), "2026-01-31T23:59:59+00:00");
+        let exact = parse_timeline_bound("2026-01-31T08:30:00+08:00", "end_date", true, &utc).unwrap();
+        assert_eq!(exact.to_rfc3339(), "2026-01-31T00:30:00+00:00");
+
+        let err = parse_timeline_bound("31/01/2026", "end_date", true, &utc).unwrap_err();
+        assert!(err.to_string().contains("Invalid end_date"));
+
+        // Date-only bounds are local days in the requested zone
+        let shanghai = TimelineZone::parse(Some("Asia/Shanghai")).unwrap();
+        let start = parse_timeline_bound("2026-01-31", "start_date", false, &shanghai).unwrap();
+        assert_eq!(start.to_rfc3339(), "2026-01-30T16:00:00+00:00");
+    }
+
+    #[test]
+    fn test_parse_timeline_zone() {
+        assert_eq!(TimelineZone::parse(None).unwrap(), TimelineZone::default());
+        assert_eq!(TimelineZone::parse(Some(" ")).unwrap(), TimelineZone::default());
+        assert_eq!(TimelineZone::parse(Some("UTC")).unwrap(), TimelineZone::default());
+
+        let plus_eight = TimelineZone::Offset(chrono::FixedOffset::east_opt(8 * 3600).unwrap());
+        for value in ["+08:00", "+0800", "+8", "UTC+8", "gmt+08:00"] {
+            assert_eq!(TimelineZone::parse(Some(value)).unwrap(), plus_eight, "{}", value);
+        }
+        assert_eq!(
+            TimelineZone::parse(Some("-05:30")).unwrap(),
+            TimelineZone::Offset(chrono::FixedOffset::west_opt(5 * 3600 + 1800).unwrap())
+        );
+        assert_eq!(
+            TimelineZone::parse(Some("Asia/Shanghai")).unwrap(),
+            TimelineZone::Named(chrono_tz::Asia::Shanghai)
+        );
+
+        for value in ["Mars/Olympus", "+25:00", "+08:75", "+ab", "+-5", "+1中", "-中文", "UTC+08:3０"] {
+            let err = TimelineZone::parse(Some(value)).unwrap_err();
+            assert!(matches!(err, DirSoulError::Config(_)), "{}: {:?}", value, err);
+        }
+    }
+
+    fn timeline_event_at(timestamp: &str) -> EventMemory {
+        EventMemory {
+            event_id: uuid::Uuid::new_v4(),
+            memory_id: uuid::Uuid::new_v4(),
+            user_id: "test_user".to_string(),
+            timestamp: chrono::DateTime::parse_from_rfc3339(timestamp).unwrap().with_timezone(&chrono::Utc),
+            actor: None,
+            action: "eat".to_string(),
+            target: "noodles".to_string(),
+            quantity: None,
+            unit: None,
+            confidence: 0.9,
+            extractor_version: None,
+            metadata: None,
+        }
     }
 
-    /// Query timeline events from database
-    fn query_timeline(&self, user_id: &str, start_date: &str, end_date: &str) -> Result<Vec<EventMemory>> {
-        let mut conn = PgConnection::establish(&self.database_url)?;
+    #[test]
+    fn test_timeline_groups_by_local_date() {
+        let events = vec![
+            timeline_event_at("2026-01-31T23:30:00Z"),
+            timeline_event_at("2026-01-31T10:00:00Z"),
+        ];
 
-        // Parse date strings - support both date (YYYY-MM-DD) and datetime (RFC3339) formats
-        let start = if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(start_date) {
-            dt.with_timezone(&chrono::Utc)
-        } else {
-            // Try parsing as date only
-            let date = chrono::NaiveDate::parse_from_str(start_date, "%Y-%m-%d")
-                .map_err(|e| DirSoulError::Config(format!("Invalid start_date: {}", e)))?;
-            date.and_hms_opt(0, 0, 0)
-                .ok_or_else(|| DirSoulError::Config("Invalid start_date".to_string()))?
-                .and_utc()
-        };
+        let utc = build_timeline_response(events.clone(), &TimelineZone::default());
+        assert_eq!(utc.events_by_date["2026-01-31"].len(), 2);
+        assert_eq!(utc.events_by_date["2026-01-31"][0].timestamp, "2026-01-31T23:30:00+00:00");
 
-        let end = if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(end_date) {
-            dt.with_timezone(&chrono::Utc)
-        } else {
-            // Try parsing as date only
-            let date = chrono::NaiveDate::parse_from_str(end_date, "%Y-%m-%d")
-                .map_err(|e| DirSoulError::Config(format!("Invalid end_date: {}", e)))?;
-            date.and_hms_opt(23, 59, 59)
-                .ok_or_else(|| DirSoulError::Config("Invalid end_date".to_string()))?
-                .and_utc()
+        for zone in ["+08:00", "Asia/Shanghai"] {
+            let zone = TimelineZone::parse(Some(zone)).unwrap();
+            let local = build_timeline_response(events.clone(), &zone);
+            assert_eq!(local.total_events, 2);
+            assert_eq!(local.events_by_date["2026-02-01"].len(), 1);
+            assert_eq!(local.events_by_date["2026-02-01"][0].timestamp, "2026-02-01T07:30:00+08:00");
+            assert_eq!(local.events_by_date["2026-01-31"].len(), 1);
+            // Tie between the two days: earliest wins
+            assert_eq!(local.summary.most_active_date, "2026-01-31");
+        }
+    }
+
+    #[test]
+    fn test_user_timezone_setting_buckets_only_that_users_timeline() {
+        let events = vec![timeline_event_at("2026-01-31T23:30:00Z")];
+        let shanghai = UserSettings {
+            timezone: Some("Asia/Shanghai".to_string()),
+            ..UserSettings::new("shanghai_user")
         };
+        let default = UserSettings::new("default_user");
 
-        // Query events within time range
-        let events = event_memories::table
-            .filter(event_memories::user_id.eq(user_id))
-            .filter(event_memories::timestamp.ge(start))
-            .filter(event_memories::timestamp.le(end))
-            .order(event_memories::timestamp.desc())
-            .load::<EventMemory>(&mut conn)?;
+        let zone = shanghai.timeline_zone(None).unwrap();
+        let local = build_timeline_response(events.clone(), &zone);
+        assert_eq!(local.events_by_date.keys().collect::<Vec<_>>(), vec!["2026-02-01"]);
 
-        Ok(events)
+        let zone = default.timeline_zone(None).unwrap();
+        let utc = build_timeline_response(events.clone(), &zone);
+        assert_eq!(utc.events_by_date.keys().collect::<Vec<_>>(), vec!["2026-01-31"]);
+
+        // A zone named by the request still wins over the setting
+        let zone = shanghai.timeline_zone(Some("UTC")).unwrap();
+        let requested = build_timeline_response(events, &zone);
+        assert_eq!(requested.events_by_date.keys().collect::<Vec<_>>(), vec!["2026-01-31"]);
     }
 
-    /// Query statistics from database
-    fn query_stats(&self, user_id: &str, time_range: &str) -> Result<StatsResponse> {
-        let mut conn = PgConnection::establish(&self.database_url)?;
+    #[test]
+    fn test_timeline_includes_event_metadata() {
+        let flagged = EventMemory {
+            metadata: Some(serde_json::json!({"negated": true, "sentiment": "negative"})),
+            ..timeline_event_at("2026-01-31T10:00:00Z")
+        };
+        let events = vec![flagged, timeline_event_at("2026-01-31T12:00:00Z")];
 
-        // Calculate time range
-        let (start, end) = match time_range {
-            "7d" => {
-                let start = chrono::Utc::now() - chrono::Duration::days(7);
-                (start, chrono::Utc::now())
-            }
-            "30d" => {
-                let start = chrono::Utc::now() - chrono::Duration::days(30);
-                (start, chrono::Utc::now())
+        let response = build_timeline_response(events, &TimelineZone::default());
+        let json = serde_json::to_value(&response.events_by_date["2026-01-31"]).unwrap();
+        assert_eq!(json[0]["metadata"]["negated"], true);
+        assert_eq!(json[0]["metadata"]["sentiment"], "negative");
+        assert!(json[1].get("metadata").is_none());
+    }
+
+    /// Minimal RFC 4180 reader for checking the CSV export
+    fn parse_csv(text: &str) -> Vec<Vec<String>> {
+        let mut records = Vec::new();
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut in_quotes = false;
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if in_quotes {
+                match c {
+                    '"' if chars.peek() == Some(&'"') => {
+                        field.push('"');
+                        chars.next();
+                    }
+                    '"' => in_quotes = false,
+                    _ => field.push(c),
+                }
+                continue;
             }
-            "90d" => {
-                let start = chrono::Utc::now() - chrono::Duration::days(90);
-                (start, chrono::Utc::now())
+            match c {
+                '"' => in_quotes = true,
+                ',' => record.push(std::mem::take(&mut field)),
+                '\r' => {}
+                '\n' => {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                _ => field.push(c),
             }
-            "all" => {
-                // Query all time
-                let start = chrono::DateTime::from_timestamp(0, 0).unwrap();
-                (start, chrono::Utc::now())
+        }
+        assert!(!in_quotes, "unterminated quoted field");
+        assert!(field.is_empty() && record.is_empty(), "missing final line break");
+        records
+    }
+
+    /// Page fetcher over in-memory events, mirroring the keyset pagination
+    fn paged(mut events: Vec<EventMemory>, page_size: usize) -> impl FnMut(Option<TimelineCursor>) -> Result<TimelinePage> + Send + 'static {
+        events.sort_by_key(|event| (event.timestamp, event.event_id));
+        move |after: Option<TimelineCursor>| {
+            let rest: Vec<EventMemory> = events
+                .iter()
+                .filter(|event| after.map_or(true, |cursor| (event.timestamp, event.event_id) > cursor))
+                .take(page_size)
+                .cloned()
+                .collect();
+            let next = if rest.len() == page_size {
+                rest.last().map(|event| (event.timestamp, event.event_id))
+            } else {
+                None
+            };
+            Ok(TimelinePage { events: rest, next })
+        }
+    }
+
+    #[test]
+    fn test_csv_field_escaping() {
+        assert_eq!(csv_field("noodles"), "noodles");
+        assert_eq!(csv_field("salt, pepper"), "\"salt, pepper\"");
+        assert_eq!(csv_field("the \"good\" one"), "\"the \"\"good\"\" one\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[tokio::test]
+    async fn test_timeline_csv_stream_round_trips() {
+        use futures_util::StreamExt;
+
+        let start = chrono::DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z").unwrap().with_timezone(&chrono::Utc);
+        let mut events: Vec<EventMemory> = (0..1200)
+            .map(|i| EventMemory {
+                timestamp: start + chrono::Duration::minutes(i),
+                ..timeline_event_at("2026-01-01T00:00:00Z")
+            })
+            .collect();
+        events[7].target = "rice, beans".to_string();
+        events[7].quantity = Some(2.0);
+        events[7].unit = Some("bowl".to_string());
+        events[8].actor = Some("Mom said \"hi\"\nthen left".to_string());
+        let expected_7 = events[7].timestamp;
+
+        let chunks: Vec<warp::hyper::body::Bytes> = timeline_csv_stream(TimelineZone::default(), paged(events, 500))
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        // Header plus one chunk per non-empty page
+        assert_eq!(chunks.len(), 4);
+
+        let text: String = chunks.iter().map(|chunk| String::from_utf8(chunk.to_vec()).unwrap()).collect();
+        let records = parse_csv(&text);
+        assert_eq!(records.len(), 1201);
+        assert_eq!(records[0], vec!["timestamp", "actor", "action", "target", "quantity", "unit", "confidence"]);
+        assert!(records.iter().all(|record| record.len() == 7));
+
+        let row = &records[8];
+        assert_eq!(row[0], TimelineZone::default().to_local_rfc3339(expected_7));
+        assert_eq!(row[3], "rice, beans");
+        assert_eq!(row[4], "2");
+        assert_eq!(row[5], "bowl");
+        assert_eq!(records[9][1], "Mom said \"hi\"\nthen left");
+        assert_eq!(records[1][6], "0.9");
+    }
+
+    #[tokio::test]
+    async fn test_timeline_csv_stream_reports_page_errors() {
+        use futures_util::StreamExt;
+
+        let mut calls = 0;
+        let fetch = move |_after: Option<TimelineCursor>| {
+            calls += 1;
+            if calls == 1 {
+                Ok(TimelinePage {
+                    events: vec![timeline_event_at("2026-01-01T00:00:00Z")],
+                    next: Some((chrono::Utc::now(), uuid::Uuid::nil())),
+                })
+            } else {
+                Err(DirSoulError::Database(diesel::result::Error::NotFound))
             }
-            _ => {
-                return Err(DirSoulError::Config(format!(
-                    "Invalid time_range: {}. Expected: 7d, 30d, 90d, or all",
-                    time_range
-                )));
+        };
+
+        let items: Vec<std::io::Result<warp::hyper::body::Bytes>> =
+            timeline_csv_stream(TimelineZone::default(), fetch).collect().await;
+        assert_eq!(items.len(), 3);
+        assert!(items[0].is_ok() && items[1].is_ok());
+        assert!(items[2].is_err());
+    }
+
+    #[tokio::test]
+    async fn test_timeline_csv_route() {
+        let mut comma = timeline_event_at("2026-01-02T08:00:00Z");
+        comma.target = "coffee, black".to_string();
+        let events = vec![
+            timeline_event_at("2026-01-01T08:00:00Z"),
+            comma,
+            EventMemory {
+                action: "run".to_string(),
+                ..timeline_event_at("2026-01-03T08:00:00Z")
+            },
+        ];
+
+        let resolve_zone =
+            |query: &TimelineCsvQuery| TimelineZone::parse(query.timezone.as_deref());
+        let route = timeline_csv_route(resolve_zone, move |query: &TimelineCsvQuery, _: &TimelineZone| {
+            let filters = query.filters();
+            filters.validate()?;
+            let kept: Vec<EventMemory> = events.iter().filter(|e| filters.matches(e, None)).cloned().collect();
+            Ok(paged(kept, 2))
+        });
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/api/timeline.csv?user_id=u&start_date=2026-01-01&end_date=2026-01-31&event_types=eat")
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], "text/csv; charset=utf-8");
+
+        let records = parse_csv(std::str::from_utf8(response.body()).unwrap());
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2][3], "coffee, black");
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/api/timeline.csv?user_id=u&start_date=2026-01-01&end_date=2026-01-31&min_confidence=2")
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), 400);
+
+        let response = warp::test::request()
+            .method("GET")
+            .path("/api/timeline.csv?user_id=u&start_date=2026-01-01&end_date=2026-01-31&timezone=Mars/Base")
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), 400);
+    }
+
+    #[test]
+    fn test_timeline_csv_query_filters() {
+        let query: TimelineCsvQuery = serde_json::from_value(serde_json::json!({
+            "user_id": "u",
+            "start_date": "2026-01-01",
+            "end_date": "2026-01-31",
+            "entities": "Coffee, Tea,",
+            "min_confidence": 0.5
+        }))
+        .unwrap();
+        let filters = query.filters();
+        assert_eq!(filters.entities, Some(vec!["Coffee".to_string(), "Tea".to_string()]));
+        assert_eq!(filters.event_types, None);
+        assert_eq!(filters.min_confidence, Some(0.5));
+    }
+
+    fn filtered(filters: &TimelineFilters, events: &[EventMemory]) -> Vec<(String, String)> {
+        events
+            .iter()
+            .filter(|event| filters.matches(event, None))
+            .map(|event| (event.action.clone(), event.target.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_timeline_filters_confidence_and_event_types() {
+        let event = |action: &str, target: &str, confidence: f64| EventMemory {
+            action: action.to_string(),
+            target: target.to_string(),
+            confidence,
+            ..timeline_event_at("2026-01-31T10:00:00Z")
+        };
+        let events = vec![
+            event("eat", "noodles", 0.95),
+            event("eat", "apple", 0.6),
+            event("run", "park", 0.8),
+            event("buy", "coffee", 0.3),
+        ];
+
+        // No filters keeps everything
+        assert_eq!(filtered(&TimelineFilters::default(), &events).len(), 4);
+
+        let confident = TimelineFilters {
+            min_confidence: Some(0.8),
+            ..Default::default()
+        };
+        assert_eq!(
+            filtered(&confident, &events),
+            vec![("eat".to_string(), "noodles".to_string()), ("run".to_string(), "park".to_string())]
+        );
+
+        let eat_or_buy = TimelineFilters {
+            event_types: Some(vec!["eat".to_string(), "buy".to_string()]),
+            ..Default::default()
+        };
+        let actions: Vec<String> = filtered(&eat_or_buy, &events).into_iter().map(|(a, _)| a).collect();
+        assert_eq!(actions, vec!["eat", "eat", "buy"]);
+
+        // Filters combine; an empty list doesn't restrict
+        let combined = TimelineFilters {
+            event_types: Some(vec!["eat".to_string()]),
+            entities: Some(vec![]),
+            min_confidence: Some(0.8),
+            ..Default::default()
+        };
+        assert_eq!(filtered(&combined, &events), vec![("eat".to_string(), "noodles".to_string())]);
+    }
+
+    #[test]
+    fn test_timeline_filters_quantity_range() {
+        let event = |target: &str, quantity: Option<f64>| EventMemory {
+            action: "eat".to_string(),
+            target: target.to_string(),
+            quantity,
+            unit: quantity.map(|_| "个".to_string()),
+            ..timeline_event_at("2026-01-31T10:00:00Z")
+        };
+        let events = vec![
+            event("apple", Some(2.0)),
+            event("apple", Some(4.0)),
+            event("apple", None),
+            event("pear", Some(6.0)),
+        ];
+
+        let more_than_three = TimelineFilters {
+            min_quantity: Some(3.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            filtered(&more_than_three, &events),
+            vec![("eat".to_string(), "apple".to_string()), ("eat".to_string(), "pear".to_string())]
+        );
+
+        // Quantity-less events are excluded once a bound is set
+        let up_to_five = TimelineFilters {
+            max_quantity: Some(5.0),
+            unit: Some("个".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(filtered(&up_to_five, &events).len(), 2);
+
+        let inverted = TimelineFilters {
+            min_quantity: Some(5.0),
+            max_quantity: Some(3.0),
+            ..Default::default()
+        };
+        assert!(matches!(inverted.validate(), Err(DirSoulError::Config(_))));
+
+        let query: TimelineCsvQuery = serde_json::from_value(serde_json::json!({
+            "user_id": "u",
+            "start_date": "2026-01-01",
+            "end_date": "2026-01-31",
+            "min_quantity": 3,
+            "unit": "个"
+        }))
+        .unwrap();
+        let filters = query.filters();
+        assert_eq!((filters.min_quantity, filters.max_quantity), (Some(3.0), None));
+        assert_eq!(filters.unit.as_deref(), Some("个"));
+    }
+
+    #[test]
+    fn test_timeline_entity_filter_prefers_links() {
+        let filters = TimelineFilters {
+            entities: Some(vec!["Coffee".to_string()]),
+            ..Default::default()
+        };
+        let latte = EventMemory {
+            target: "latte".to_string(),
+            ..timeline_event_at("2026-01-31T10:00:00Z")
+        };
+        let coffee_beans = EventMemory {
+            target: "coffee beans".to_string(),
+            ..timeline_event_at("2026-01-31T10:00:00Z")
+        };
+
+        // Without links the target is matched, case-insensitively
+        assert!(!filters.matches(&latte, None));
+        assert!(filters.matches(&coffee_beans, None));
+        assert!(filters.matches(&coffee_beans, Some(&[][..])));
+
+        // With links only the linked entities count
+        assert!(filters.matches(&latte, Some(&["coffee".to_string()][..])));
+        assert!(!filters.matches(&coffee_beans, Some(&["beans".to_string()][..])));
+    }
+
+    #[test]
+    fn test_invalid_min_confidence_is_rejected_before_connecting() {
+        let server = unreachable_server();
+        let filters = TimelineFilters {
+            min_confidence: Some(1.5),
+            ..Default::default()
+        };
+
+        let err = server
+            .query_timeline("test_user", "2026-01-01", "2026-01-31", &TimelineZone::default(), Some(&filters))
+            .unwrap_err();
+        assert!(matches!(err, DirSoulError::Config(_)), "{:?}", err);
+        assert!(TimelineFilters { min_confidence: Some(0.8), ..Default::default() }.validate().is_ok());
+    }
+
+    fn canned_provider(reply: &str) -> Arc<MockProvider> {
+        Arc::new(MockProvider::new().with_model("canned").with_reply(reply))
+    }
+
+    #[tokio::test]
+    async fn test_repeated_governor_does_not_nest() {
+        let governor = LlmGovernor::new(1).unwrap();
+        let server = unreachable_server()
+            .with_llm_governor(governor.clone())
+            .with_chat_provider(canned_provider("ok"))
+            .with_llm_governor(governor.clone());
+
+        // Nested wrappers would wait on the single permit they already hold
+        let reply = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            server.chat_provider.generate("hi", GenerateOptions::default()),
+        )
+        .await
+        .expect("nested governors deadlock at a cap of 1");
+        assert_eq!(reply.unwrap(), "ok");
+        assert_eq!(governor.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_liveness_ignores_downed_dependencies() {
+        let server = Arc::new(unreachable_server().with_chat_provider(Arc::new(MockProvider::new().failing())));
+        let routes = server.routes();
+
+        for path in ["/health", "/health/live"] {
+            let response = warp::test::request().method("GET").path(path).reply(&routes).await;
+            assert_eq!(response.status(), 200, "{}", path);
+        }
+
+        let response = warp::test::request().method("GET").path("/health/ready").reply(&routes).await;
+        assert_eq!(response.status(), 503);
+        let report: ReadinessReport = serde_json::from_slice(response.body()).unwrap();
+        assert!(!report.ready);
+        assert_eq!(report.failed(), vec!["database", "llm"]);
+        assert!(report.dependencies.iter().all(|d| d.error.is_some()));
+    }
+
+    #[tokio::test]
+    async fn test_readiness_names_failed_dependency() {
+        let server = unreachable_server().with_chat_provider(canned_provider("ok"));
+
+        let report = server.check_readiness().await;
+        assert!(!report.ready);
+        assert_eq!(report.failed(), vec!["database"]);
+        let llm = report.dependencies.iter().find(|d| d.name == "llm").unwrap();
+        assert!(llm.healthy);
+        assert_eq!(llm.error, None);
+    }
+
+    #[tokio::test]
+    async fn test_readiness_probe_times_out() {
+        let server = unreachable_server()
+            .with_chat_provider(Arc::new(MockProvider::new().stalled()))
+            .with_health_config(HealthConfig {
+                llm_timeout: std::time::Duration::from_millis(50),
+                ..Default::default()
+            });
+
+        let start = std::time::Instant::now();
+        let report = server.check_readiness().await;
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+
+        let llm = report.dependencies.iter().find(|d| d.name == "llm").unwrap();
+        assert!(!llm.healthy);
+        assert_eq!(llm.error.as_deref(), Some("timed out after 50ms"));
+    }
+
+    #[test]
+    fn test_with_connect_timeout() {
+        let timeout = std::time::Duration::from_millis(1500);
+        assert_eq!(
+            with_connect_timeout("postgres://localhost/dirsoul", timeout),
+            "postgres://localhost/dirsoul?connect_timeout=2"
+        );
+        assert_eq!(
+            with_connect_timeout("postgres://localhost/dirsoul?sslmode=disable", timeout),
+            "postgres://localhost/dirsoul?sslmode=disable&connect_timeout=2"
+        );
+        assert_eq!(
+            with_connect_timeout("host=localhost dbname=dirsoul", timeout),
+            "host=localhost dbname=dirsoul connect_timeout=2"
+        );
+        // A timeout chosen by the operator is kept
+        assert_eq!(
+            with_connect_timeout("postgres://localhost/dirsoul?connect_timeout=9", timeout),
+            "postgres://localhost/dirsoul?connect_timeout=9"
+        );
+    }
+
+    #[test]
+    fn test_health_config_validation() {
+        assert!(HealthConfig::default().validate().is_ok());
+        let config = HealthConfig {
+            database_timeout: std::time::Duration::ZERO,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_chat_generates_through_provider() {
+        let provider = canned_provider("  明年26  ");
+        let server = Arc::new(
+            unreachable_server()
+                .with_chat_config(ChatConfig {
+                    max_output_tokens: 32,
+                    temperature: 0.2,
+                    ..Default::default()
+                })
+                .with_chat_provider(provider.clone()),
+        );
+        let routes = server.routes();
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/chat")
+            .json(&serde_json::json!({
+                "message": "我今年25岁",
+                "user_id": "test_user",
+                "history": [],
+                "context": null,
+            }))
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+
+        let body: ApiChatResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body.response, "明年26");
+        assert_eq!(body.history.len(), 2);
+        assert_eq!(body.metadata.unwrap()["model"], "canned");
+
+        let calls = provider.calls();
+        assert_eq!(calls.len(), 1);
+        assert!(calls[0].prompt().contains("用户: 我今年25岁"));
+        assert_eq!(calls[0].options, GenerateOptions::new(0.2, 32));
+    }
+
+    #[tokio::test]
+    async fn test_chat_prompt_starts_with_system_prompt() {
+        let provider = canned_provider("好的");
+        let server = Arc::new(
+            unreachable_server()
+                .with_chat_provider(provider.clone())
+                .with_system_prompts(
+                    SystemPrompts::new("Extraction only")
+                        .with_override(PromptTask::Chat, "你是用户的私人助理。"),
+                ),
+        );
+        let routes = server.routes();
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/chat")
+            .json(&serde_json::json!({
+                "message": "你好",
+                "user_id": "test_user",
+                "history": [],
+                "context": null,
+            }))
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+
+        let prompt = provider.calls()[0].prompt();
+        assert!(prompt.starts_with("你是用户的私人助理。\n\n"));
+        assert!(!prompt.contains("Extraction only"));
+    }
+
+    #[tokio::test]
+    async fn test_chat_falls_back_when_generation_fails() {
+        let server = unreachable_server().with_chat_provider(Arc::new(MockProvider::new().failing()));
+        let req = ChatRequest {
+            message: "你好".to_string(),
+            user_id: "test_user".to_string(),
+            history: vec![],
+            context: None,
+            idempotency_key: None,
+        };
+
+        let response = server.process_chat(req).await.unwrap();
+        assert_eq!(response.response, "我收到你的消息了。");
+    }
+
+    async fn post_chat(
+        server: &Arc<HttpServer>,
+        user_id: &str,
+        header_key: Option<&str>,
+        body_key: Option<&str>,
+    ) -> ApiChatResponse {
+        let mut request = warp::test::request().method("POST").path("/api/chat");
+        if let Some(key) = header_key {
+            request = request.header("Idempotency-Key", key);
+        }
+        let response = request
+            .json(&serde_json::json!({
+                "message": "我今年25岁",
+                "user_id": user_id,
+                "history": [],
+                "context": null,
+                "idempotency_key": body_key,
+            }))
+            .reply(&server.clone().routes())
+            .await;
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        serde_json::from_slice(response.body()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_chat_replays_response_for_same_idempotency_key() {
+        let provider = canned_provider("明年26");
+        let server = Arc::new(unreachable_server().with_chat_provider(provider.clone()));
+
+        let first = post_chat(&server, "test_user", Some("retry-1"), None).await;
+        let replay = post_chat(&server, "test_user", Some("retry-1"), None).await;
+        assert_eq!(
+            serde_json::to_value(&first).unwrap(),
+            serde_json::to_value(&replay).unwrap()
+        );
+        assert_eq!(provider.calls().len(), 1);
+
+        // The body field works too; the header wins when both are sent
+        post_chat(&server, "test_user", None, Some("retry-2")).await;
+        post_chat(&server, "test_user", Some("retry-2"), Some("other")).await;
+        assert_eq!(provider.calls().len(), 2);
+
+        // Keys are scoped per user, and requests without a key always run
+        post_chat(&server, "other_user", Some("retry-1"), None).await;
+        post_chat(&server, "test_user", None, None).await;
+        post_chat(&server, "test_user", None, None).await;
+        assert_eq!(provider.calls().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_retries_are_processed_once() {
+        let provider = canned_provider("明年26");
+        let server = Arc::new(unreachable_server().with_chat_provider(provider.clone()));
+        let request = |key: &str| ChatRequest {
+            message: "你好".to_string(),
+            user_id: "test_user".to_string(),
+            history: vec![],
+            context: None,
+            idempotency_key: Some(key.to_string()),
+        };
+
+        let (a, b) = tokio::join!(
+            server.process_chat_idempotent(request("same"), None),
+            server.process_chat_idempotent(request("same"), None)
+        );
+        match (a.unwrap(), b.unwrap()) {
+            (ChatOutcome::Processed(a), ChatOutcome::Replayed(b))
+            | (ChatOutcome::Replayed(a), ChatOutcome::Processed(b)) => {
+                assert_eq!(a.processing_time_ms, b.processing_time_ms);
             }
+            outcomes => panic!("exactly one of the two should be a replay: {:?}", outcomes),
+        }
+        assert_eq!(provider.calls().len(), 1);
+
+        let long_key = "k".repeat(MAX_IDEMPOTENCY_KEY_LEN + 1);
+        assert!(matches!(
+            server.process_chat_idempotent(request(&long_key), None).await,
+            Err(DirSoulError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_chat_replay_cache_is_bounded_and_expires() {
+        let cache = ChatReplayCache::new(IdempotencyConfig {
+            ttl: std::time::Duration::from_secs(60),
+            max_entries: 2,
+        });
+        let slot = |cache: &ChatReplayCache, key: &str| cache.slot("user", key, [0; 32]).unwrap();
+        let first = slot(&cache, "a");
+        slot(&cache, "b");
+        slot(&cache, "c");
+        assert_eq!(cache.len(), 2);
+        // "a" was evicted, so its key gets a fresh cell
+        assert!(!Arc::ptr_eq(&first, &slot(&cache, "a")));
+        assert!(Arc::ptr_eq(&slot(&cache, "a"), &slot(&cache, "a")));
+        // A different request under the same key gets no cell
+        assert!(cache.slot("user", "a", [1; 32]).is_none());
+
+        let expiring = ChatReplayCache::new(IdempotencyConfig {
+            ttl: std::time::Duration::ZERO,
+            ..Default::default()
+        });
+        let cell = slot(&expiring, "a");
+        assert!(!Arc::ptr_eq(&cell, &slot(&expiring, "a")));
+    }
+
+    #[tokio::test]
+    async fn test_reused_idempotency_key_with_other_payload_is_refused() {
+        let provider = canned_provider("明年26");
+        let server = Arc::new(unreachable_server().with_chat_provider(provider.clone()));
+        post_chat(&server, "test_user", Some("retry-1"), None).await;
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/chat")
+            .header("Idempotency-Key", "retry-1")
+            .json(&serde_json::json!({
+                "message": "我明年30岁",
+                "user_id": "test_user",
+                "history": [],
+                "context": null,
+            }))
+            .reply(&server.clone().routes())
+            .await;
+        assert_eq!(response.status(), warp::http::StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(provider.calls().len(), 1);
+    }
+
+    #[test]
+    fn test_chat_replay_cache_survives_poisoned_lock() {
+        let cache = Arc::new(ChatReplayCache::new(IdempotencyConfig::default()));
+        let poisoner = cache.clone();
+        let _ = std::thread::spawn(move || {
+            let _entries = poisoner.entries.lock().unwrap();
+            panic!("poison the replay cache");
+        })
+        .join();
+
+        assert!(cache.slot("user", "a", [0; 32]).is_some());
+        assert_eq!(cache.len(), 1);
+    }
+
+    fn stats_fixture(total_events: usize) -> StatsResponse {
+        StatsResponse {
+            total_memories: 1,
+            total_events,
+            total_entities: 0,
+            events_per_day: HashMap::new(),
+            event_types: HashMap::new(),
+            entity_types: HashMap::new(),
+            entities: vec![],
+            time_range: TimeRangeStats {
+                start_date: "2026-01-01".to_string(),
+                end_date: "2026-01-07".to_string(),
+                total_days: 7,
+                most_active_day: String::new(),
+                least_active_day: String::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_query_cache_serves_repeated_stats_until_invalidated() {
+        let cache = QueryCache::new(QueryCacheConfig::default());
+        let queries = std::cell::Cell::new(0);
+        let stats = |user_id: &str, time_range: &str| {
+            let req = StatsRequest {
+                user_id: user_id.to_string(),
+                time_range: time_range.to_string(),
+            };
+            cache
+                .get_or_run(
+                    QueryCacheKey::stats(&req),
+                    || {
+                        queries.set(queries.get() + 1);
+                        Ok(stats_fixture(queries.get()))
+                    },
+                    |response| response.total_events as i32,
+                )
+                .unwrap()
         };
 
-        // Count total events
-        let total_events: i64 = event_memories::table
-            .filter(event_memories::user_id.eq(user_id))
-            .filter(event_memories::timestamp.ge(start))
-            .filter(event_memories::timestamp.le(end))
-            .count()
-            .get_result(&mut conn)?;
+        let first = stats("u", "7d");
+        let second = stats("u", "7d");
+        assert_eq!(queries.get(), 1);
+        assert_eq!(first.body, second.body);
+        assert_eq!(second.result_count, 1);
 
-        // Count total entities
-        let total_entities: i64 = entities::table
-            .filter(entities::user_id.eq(user_id))
-            .count()
-            .get_result(&mut conn)?;
+        // Other parameters and other users are cached separately
+        stats("u", "30d");
+        stats("v", "7d");
+        assert_eq!(queries.get(), 3);
 
-        // Get events per day
-        let events: Vec<EventMemory> = event_memories::table
-            .filter(event_memories::user_id.eq(user_id))
-            .filter(event_memories::timestamp.ge(start))
-            .filter(event_memories::timestamp.le(end))
-            .load(&mut conn)?;
+        // A new event for "u" drops only that user's responses
+        cache.invalidate_user("u");
+        assert_eq!(cache.len(), 1);
+        let refreshed = stats("u", "7d");
+        assert_eq!(queries.get(), 4);
+        assert_eq!(refreshed.result_count, 4);
+        stats("v", "7d");
+        assert_eq!(queries.get(), 4);
+    }
 
-        let mut events_per_day = HashMap::new();
-        for event in &events {
-            let date = event.timestamp.format("%Y-%m-%d").to_string();
-            *events_per_day.entry(date).or_insert(0) += 1;
+    #[test]
+    fn test_query_cache_skips_failures_and_racing_writes() {
+        let cache = QueryCache::new(QueryCacheConfig::default());
+        let key = || QueryCacheKey::stats(&StatsRequest {
+            user_id: "u".to_string(),
+            time_range: "7d".to_string(),
+        });
+
+        let failed = cache.get_or_run(
+            key(),
+            || Err::<StatsResponse, _>(DirSoulError::Config("bad range".to_string())),
+            |_| 0,
+        );
+        assert!(failed.is_err());
+        assert!(cache.is_empty());
+
+        // An event stored while the query ran makes its result stale
+        cache
+            .get_or_run(
+                key(),
+                || {
+                    cache.invalidate_user("u");
+                    Ok(stats_fixture(1))
+                },
+                |_| 1,
+            )
+            .unwrap();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_storage_writes_invalidate_server_cache() {
+        let server =
+            HttpServer::new("127.0.0.1:0".to_string(), "postgres://unused".to_string()).unwrap();
+        let cache = server.query_cache();
+        let cached = |user_id: &str| {
+            let req = StatsRequest {
+                user_id: user_id.to_string(),
+                time_range: "7d".to_string(),
+            };
+            cache
+                .get_or_run(QueryCacheKey::stats(&req), || Ok(stats_fixture(1)), |_| 1)
+                .unwrap();
+        };
+        cached("cache_write_test_u");
+        cached("cache_write_test_v");
+
+        crate::data_changes::notify_user_data_changed("cache_write_test_u");
+        assert_eq!(cache.len(), 1);
+
+        // A resized cache stays subscribed
+        let server = server.with_query_cache_config(QueryCacheConfig::default());
+        let cache = server.query_cache();
+        cache
+            .get_or_run(
+                QueryCacheKey::stats(&StatsRequest {
+                    user_id: "cache_write_test_u".to_string(),
+                    time_range: "7d".to_string(),
+                }),
+                || Ok(stats_fixture(1)),
+                |_| 1,
+            )
+            .unwrap();
+        crate::data_changes::notify_user_data_changed("cache_write_test_u");
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_query_cache_recovers_from_panicked_query() {
+        let cache = Arc::new(QueryCache::new(QueryCacheConfig::default()));
+        let poisoner = cache.clone();
+        let panicked = std::thread::spawn(move || {
+            let _state = poisoner.state.lock().unwrap();
+            panic!("query panicked while holding the cache lock");
+        })
+        .join();
+        assert!(panicked.is_err());
+        assert!(cache.state.is_poisoned());
+
+        let req = StatsRequest {
+            user_id: "u".to_string(),
+            time_range: "7d".to_string(),
+        };
+        cache
+            .get_or_run(QueryCacheKey::stats(&req), || Ok(stats_fixture(1)), |_| 1)
+            .unwrap();
+        assert_eq!(cache.len(), 1);
+        cache.invalidate_user("u");
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_query_cache_is_bounded_and_expires() {
+        let key = |params: &str| QueryCacheKey {
+            endpoint: "timeline",
+            user_id: "u".to_string(),
+            params: params.to_string(),
+        };
+        let body = "x".repeat(40);
+
+        let cache = QueryCache::new(QueryCacheConfig {
+            max_entries: 2,
+            ..Default::default()
+        });
+        for params in ["a", "b", "c"] {
+            cache.get_or_run(key(params), || Ok(body.clone()), |_| 0).unwrap();
+        }
+        assert_eq!(cache.len(), 2);
+
+        // Byte budget: two 42-byte bodies fit in 100, a third evicts the oldest,
+        // and a body over the whole budget is never stored
+        let cache = QueryCache::new(QueryCacheConfig {
+            max_bytes: 100,
+            ..Default::default()
+        });
+        for params in ["a", "b", "c"] {
+            cache.get_or_run(key(params), || Ok(body.clone()), |_| 0).unwrap();
         }
+        assert_eq!(cache.len(), 2);
+        assert!(cache.state.lock().unwrap().bytes <= 100);
+        cache.get_or_run(key("big"), || Ok("y".repeat(200)), |_| 0).unwrap();
+        assert_eq!(cache.len(), 2);
+
+        let disabled = QueryCache::new(QueryCacheConfig {
+            ttl: std::time::Duration::ZERO,
+            ..Default::default()
+        });
+        disabled.get_or_run(key("a"), || Ok(body.clone()), |_| 0).unwrap();
+        assert!(disabled.is_empty());
+    }
+
+    #[test]
+    fn test_timeline_cache_key_covers_parameters() {
+        let req = TimelineRequest {
+            user_id: "u".to_string(),
+            start_date: "2026-01-01".to_string(),
+            end_date: "2026-01-31".to_string(),
+            timezone: None,
+            filters: None,
+        };
+        let filtered = TimelineRequest {
+            filters: Some(TimelineFilters {
+                min_confidence: Some(0.5),
+                ..Default::default()
+            }),
+            ..req.clone()
+        };
+        let zoned = TimelineRequest {
+            timezone: Some("+08:00".to_string()),
+            ..req.clone()
+        };
 
-        // Get event type distribution
-        let mut event_types = HashMap::new();
-        for event in &events {
-            *event_types.entry(event.action.clone()).or_insert(0) += 1;
-        }
+        assert_eq!(QueryCacheKey::timeline(&req), QueryCacheKey::timeline(&req.clone()));
+        assert_ne!(QueryCacheKey::timeline(&req), QueryCacheKey::timeline(&filtered));
+        assert_ne!(QueryCacheKey::timeline(&req), QueryCacheKey::timeline(&zoned));
+        assert_ne!(
+            QueryCacheKey::timeline(&req),
+            QueryCacheKey::stats(&StatsRequest {
+                user_id: "u".to_string(),
+                time_range: "all".to_string(),
+            })
+        );
+    }
 
-        // Calculate time range stats
-        let total_days = (end.timestamp() - start.timestamp()) / 86400;
-        let avg_events_per_day = if total_days > 0 {
-            total_events as f64 / total_days as f64
-        } else {
-            0.0
+    #[tokio::test]
+    async fn test_oversized_bodies_are_rejected() {
+        let provider = canned_provider("明年26");
+        let server = Arc::new(
+            unreachable_server()
+                .with_chat_provider(provider.clone())
+                .with_body_limits(BodyLimits { chat: 1024, query: 256 }),
+        );
+        let routes = server.clone().routes();
+        let chat = |message: String| {
+            warp::test::request().method("POST").path("/api/chat").json(&serde_json::json!({
+                "message": message,
+                "user_id": "test_user",
+                "history": [],
+                "context": null,
+            }))
         };
 
-        let most_active_day = events_per_day
-            .iter()
-            .max_by_key(|(_, &count)| count)
-            .map(|(day, _)| day.clone())
-            .unwrap_or_default();
+        let response = chat("我今年25岁".to_string()).reply(&routes).await;
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
 
-        // Get top entities
-        let entity_list = entities::table
-            .filter(entities::user_id.eq(user_id))
-            .limit(10)
-            .load::<Entity>(&mut conn)?;
+        let response = chat("很".repeat(1000)).reply(&routes).await;
+        assert_eq!(response.status(), warp::http::StatusCode::PAYLOAD_TOO_LARGE);
+        // Rejected before reaching the model
+        assert_eq!(provider.calls().len(), 1);
 
-        let entities_stats: Vec<EntityStat> = entity_list
-            .into_iter()
-            .map(|e| EntityStat {
-                name: e.canonical_name,
-                frequency: 1, // TODO: calculate actual frequency
-                first_seen: e.first_seen.to_rfc3339(),
-                last_seen: e.last_seen.to_rfc3339(),
-            })
-            .collect();
+        // Query endpoints use the smaller limit
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/stats")
+            .json(&serde_json::json!({ "user_id": "u".repeat(300), "time_range": "7d" }))
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), warp::http::StatusCode::PAYLOAD_TOO_LARGE);
 
-        Ok(StatsResponse {
-            total_memories: 0, // TODO: count from raw_memories
-            total_events: total_events as usize,
-            total_entities: total_entities as usize,
-            events_per_day,
-            event_types,
-            entities: entities_stats,
-            time_range: TimeRangeStats {
-                start_date: start.format("%Y-%m-%d").to_string(),
-                end_date: end.format("%Y-%m-%d").to_string(),
-                total_days,
-                most_active_day,
-                least_active_day: String::new(),
-            },
-        })
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/search")
+            .json(&serde_json::json!({ "user_id": "test_user", "query": "水果" }))
+            .reply(&semantic_search_route(search_fixture, SearchConfig::default(), 16))
+            .await;
+        assert_eq!(response.status(), warp::http::StatusCode::PAYLOAD_TOO_LARGE);
     }
 
-    /// Start the HTTP server (runs forever)
-    pub async fn start(self) -> Result<()> {
-        // CORS headers
-        let cors = warp::cors()
-            .allow_any_origin()
-            .allow_headers(vec!["content-type"])
-            .allow_methods(vec![warp::http::Method::GET, warp::http::Method::POST]);
+    #[test]
+    fn test_keyword_scoring() {
+        let index = KeywordIndexConfig::default();
+        let terms = index.terms("Apple  苹果 apple");
+        assert_eq!(terms, vec!["apple".to_string(), "苹果".to_string()]);
 
-        // Health check endpoint
-        let health = warp::path("health")
-            .and(warp::get())
-            .map(|| {
-                warp::reply::json(&serde_json::json!({
-                    "status": "healthy",
-                    "service": "dirsoul-api",
-                    "version": "1.0.0"
-                }))
-            });
+        assert_eq!(keyword_score("今天吃了一个苹果", &terms, &index), 0.5);
+        assert_eq!(keyword_score("APPLE 苹果派", &terms, &index), 1.0);
+        assert_eq!(keyword_score("香蕉", &terms, &index), 0.0);
+        assert_eq!(keyword_score("anything", &[], &index), 0.0);
 
-        // Chat endpoint
-        let db_url_chat = self.database_url.clone();
-        let audit_logger_chat = self.audit_logger.clone();
-        let chat = warp::path("api")
-            .and(warp::path("chat"))
-            .and(warp::post())
-            .and(warp::filters::body::json())
-            .map(move |req: ChatRequest| {
-                let user_id = req.user_id.clone();
-                let message_len = req.message.len();
-
-                // Create a temporary server instance for processing
-                let server = HttpServer {
-                    bind_address: String::new(),
-                    database_url: db_url_chat.clone(),
-                    data: Arc::new(RwLock::new(HashMap::new())),
-                    audit_logger: audit_logger_chat.clone(),
-                };
+        // A one-character query still finds it inside a bigram-indexed memory
+        let terms = index.terms("茶");
+        assert_eq!(keyword_score("我喜欢喝茶", &terms, &index), 1.0);
+        assert_eq!(keyword_score("我喜欢喝咖啡", &terms, &index), 0.0);
 
-                match server.process_chat(req) {
-                    Ok(response) => {
-                        // Extract result count before moving response
-                        let result_count = response.recorded_memory_ids.len() as i32;
+        assert_eq!(contains_pattern("100%_a\\b"), "%100\\%\\_a\\\\b%");
+    }
 
-                        // Log the query asynchronously (don't block response)
-                        let logger = audit_logger_chat.clone();
-                        let user_id_clone = user_id.clone();
-                        tokio::spawn(async move {
-                            let _ = logger.log_query(
-                                &user_id_clone,
-                                "chat",
-                                true,
-                                result_count,
-                            ).await;
-                        });
+    #[test]
+    fn test_keyword_index_excludes_stop_words() {
+        let index = KeywordIndexConfig::default();
+        assert_eq!(index.terms("我的咖啡"), vec!["我".to_string(), "咖啡".to_string()]);
+        assert_eq!(index.terms("the coffee of Paris"), vec!["coffee".to_string(), "paris".to_string()]);
+        assert!(index.terms("的 了 The").is_empty());
 
-                        warp::reply::json(&response)
-                    }
-                    Err(e) => {
-                        // Log the failed query
-                        let logger = audit_logger_chat.clone();
-                        tokio::spawn(async move {
-                            let _ = logger.log_query(&user_id, "chat", false, 0).await;
-                        });
+        let whitespace = KeywordIndexConfig {
+            tokenizer: KeywordTokenizer::Whitespace,
+            stop_words: vec!["咖啡".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(whitespace.terms("咖啡 拿铁咖啡"), vec!["拿铁咖啡".to_string()]);
+    }
 
-                        let error_response = ApiChatResponse {
-                            response: format!("Error: {}", e),
-                            history: vec![],
-                            recorded_memory_ids: vec![],
-                            processing_time_ms: 0,
-                            metadata: None,
-                        };
-                        warp::reply::json(&error_response)
-                    }
-                }
-            });
+    #[test]
+    fn test_keyword_index_drops_short_tokens() {
+        let index = KeywordIndexConfig {
+            min_token_chars: 2,
+            ..Default::default()
+        };
+        assert_eq!(index.terms("x 我的 coffee"), vec!["coffee".to_string()]);
 
-        // Timeline endpoint
-        let db_url_timeline = self.database_url.clone();
-        let audit_logger_timeline = self.audit_logger.clone();
-        let timeline = warp::path("api")
-            .and(warp::path("timeline"))
-            .and(warp::post())
-            .and(warp::filters::body::json())
-            .map(move |req: TimelineRequest| {
-                let user_id = req.user_id.clone();
-                let start_date = req.start_date.clone();
-                let end_date = req.end_date.clone();
+        let whitespace = KeywordIndexConfig {
+            tokenizer: KeywordTokenizer::Whitespace,
+            min_token_chars: 3,
+            ..Default::default()
+        };
+        assert_eq!(whitespace.terms("go tea coffee"), vec!["tea".to_string(), "coffee".to_string()]);
+    }
 
-                let server = HttpServer {
-                    bind_address: String::new(),
-                    database_url: db_url_timeline.clone(),
-                    data: Arc::new(RwLock::new(HashMap::new())),
-                    audit_logger: audit_logger_timeline.clone(),
-                };
+    #[test]
+    fn test_keyword_ranking_ignores_stop_words() {
+        let candidate = |content: &str| KeywordCandidate {
+            memory_id: uuid::Uuid::new_v4(),
+            content: content.to_string(),
+        };
+        let candidates = || vec![candidate("the of and the"), candidate("coffee with milk")];
+        let query = "the coffee";
 
-                match server.query_timeline(&req.user_id, &req.start_date, &req.end_date) {
-                    Ok(events) => {
-                        let result_count = events.len() as i32;
-
-                        // Convert to timeline format
-                        let mut events_by_date: HashMap<String, Vec<TimelineEvent>> = HashMap::new();
-                        for event in events {
-                            let date = event.timestamp.format("%Y-%m-%d").to_string();
-                            let timeline_event = TimelineEvent {
-                                event_id: event.id().to_string(),
-                                timestamp: event.timestamp.to_rfc3339(),
-                                actor: event.actor,
-                                action: event.action,
-                                target: event.target,
-                                quantity: event.quantity,
-                                unit: event.unit,
-                                confidence: event.confidence,
-                                entities: vec![], // TODO: query related entities
-                            };
-                            events_by_date.entry(date).or_insert_with(Vec::new).push(timeline_event);
-                        }
+        // A stop word neither matches on its own nor halves the real match
+        let index = KeywordIndexConfig::default();
+        let terms = index.terms(query);
+        let hits = rank_keyword_candidates(candidates(), &terms, &index, 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].similarity, 1.0);
 
-                        let total_events = events_by_date.values().map(|v| v.len()).sum();
+        let unfiltered = KeywordIndexConfig {
+            stop_words: vec![],
+            ..Default::default()
+        };
+        let terms = unfiltered.terms(query);
+        let hits = rank_keyword_candidates(candidates(), &terms, &unfiltered, 10);
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().all(|hit| hit.similarity == 0.5));
+    }
 
-                        let response = TimelineResponse {
-                            events_by_date,
-                            total_events,
-                            summary: TimelineSummary {
-                                total_days: 0, // TODO: calculate from date range
-                                avg_events_per_day: 0.0,
-                                most_active_date: String::new(),
-                                top_entities: vec![],
-                            },
-                        };
+    #[test]
+    fn test_keyword_index_validation() {
+        assert!(KeywordIndexConfig::default().validate().is_ok());
+        assert!(KeywordIndexConfig { min_token_chars: 0, ..Default::default() }.validate().is_err());
+        assert!(KeywordIndexConfig { min_token_chars: 3, ..Default::default() }.validate().is_err());
+        assert!(KeywordIndexConfig {
+            tokenizer: KeywordTokenizer::Whitespace,
+            min_token_chars: 3,
+            ..Default::default()
+        }
+        .validate()
+        .is_ok());
+        assert!(KeywordIndexConfig { stop_words: vec![" ".to_string()], ..Default::default() }
+            .validate()
+            .is_err());
 
-                        // Log the query asynchronously
-                        let logger = audit_logger_timeline.clone();
-                        tokio::spawn(async move {
-                            let _ = logger.log_query(
-                                &user_id,
-                                &format!("timeline:{}:{}", start_date, end_date),
-                                true,
-                                result_count,
-                            ).await;
-                        });
+        let search = SearchConfig {
+            keyword_index: KeywordIndexConfig { min_token_chars: 0, ..Default::default() },
+            ..Default::default()
+        };
+        assert!(search.validate().is_err());
+    }
 
-                        warp::reply::json(&response)
-                    }
-                    Err(e) => {
-                        // Log the failed query
-                        let logger = audit_logger_timeline.clone();
-                        tokio::spawn(async move {
-                            let _ = logger.log_query(
-                                &user_id,
-                                &format!("timeline:{}:{}", start_date, end_date),
-                                false,
-                                0,
-                            ).await;
-                        });
+    #[tokio::test]
+    async fn test_disabled_embedder_falls_back_to_keyword_search() {
+        let embedder = Arc::new(EmbeddingGenerator::with_provider(
+            canned_provider("ok"),
+            crate::embedding::EmbeddingConfig::default(),
+        ));
+        embedder.apply_capability(&crate::embedding::VectorCapability {
+            extension_installed: false,
+            embedding_column: false,
+        });
 
-                        let error_response = TimelineResponse {
-                            events_by_date: HashMap::new(),
-                            total_events: 0,
-                            summary: TimelineSummary {
-                                total_days: 0,
-                                avg_events_per_day: 0.0,
-                                most_active_date: format!("Error: {}", e),
-                                top_entities: vec![],
-                            },
-                        };
-                        warp::reply::json(&error_response)
-                    }
-                }
-            });
+        // The query is never embedded; the keyword search reaches the
+        // (unreachable) database instead of failing on pgvector
+        let err = search_memories(
+            "postgresql://dirsoul@127.0.0.1:1/dirsoul".to_string(),
+            Some(embedder),
+            KeywordIndexConfig::default(),
+            "test_user".to_string(),
+            "苹果".to_string(),
+            5,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, DirSoulError::DatabaseConnection(_)), "{:?}", err);
+    }
 
-        // Statistics endpoint
-        let db_url_stats = self.database_url.clone();
-        let audit_logger_stats = self.audit_logger.clone();
-        let stats = warp::path("api")
-            .and(warp::path("stats"))
-            .and(warp::post())
-            .and(warp::filters::body::json())
-            .map(move |req: StatsRequest| {
-                let user_id = req.user_id.clone();
-                let time_range = req.time_range.clone();
+    #[test]
+    fn test_body_limits_validation() {
+        assert!(BodyLimits::default().validate().is_ok());
+        assert!(BodyLimits { chat: 0, ..Default::default() }.validate().is_err());
+    }
 
-                let server = HttpServer {
-                    bind_address: String::new(),
-                    database_url: db_url_stats.clone(),
-                    data: Arc::new(RwLock::new(HashMap::new())),
-                    audit_logger: audit_logger_stats.clone(),
-                };
+    /// Retriever over a fixed set of seeded memories
+    struct SeededRetriever {
+        memories: Vec<(uuid::Uuid, &'static str)>,
+    }
 
-                match server.query_stats(&req.user_id, &req.time_range) {
-                    Ok(response) => {
-                        let result_count = response.total_events + response.total_memories;
+    #[async_trait::async_trait]
+    impl ChatRetriever for SeededRetriever {
+        async fn retrieve(&self, _user_id: &str, query: &str, top_k: usize) -> Result<Vec<RecallExplanation>> {
+            if query == "fail" {
+                return Err(DirSoulError::ExternalError("embedding service down".to_string()));
+            }
+            let hits: Vec<SearchHit> = self
+                .memories
+                .iter()
+                .enumerate()
+                .map(|(i, (id, _))| SearchHit {
+                    memory_id: *id,
+                    similarity: 0.9 - i as f64 * 0.1,
+                })
+                .take(top_k)
+                .collect();
+            let memories: Vec<(uuid::Uuid, Option<String>)> =
+                self.memories.iter().map(|(id, text)| (*id, Some(text.to_string()))).collect();
+            Ok(build_recall_explanations(&hits, &memories, &[], &[]))
+        }
+    }
 
-                        // Log the query asynchronously
-                        let logger = audit_logger_stats.clone();
-                        tokio::spawn(async move {
-                            let _ = logger.log_query(
-                                &user_id,
-                                &format!("stats:{}", time_range),
-                                true,
-                                result_count as i32,
-                            ).await;
-                        });
+    async fn chat_explanations(server: HttpServer, message: &str) -> Vec<RecallExplanation> {
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/chat")
+            .json(&serde_json::json!({
+                "message": message,
+                "user_id": "test_user",
+                "history": [],
+                "context": null,
+            }))
+            .reply(&Arc::new(server).routes())
+            .await;
+        assert_eq!(response.status(), warp::http::StatusCode::OK);
+        let body: ApiChatResponse = serde_json::from_slice(response.body()).unwrap();
+        serde_json::from_value(body.metadata.unwrap()["explanations"].clone()).unwrap()
+    }
 
-                        warp::reply::json(&response)
-                    }
-                    Err(e) => {
-                        // Log the failed query
-                        let logger = audit_logger_stats.clone();
-                        tokio::spawn(async move {
-                            let _ = logger.log_query(
-                                &user_id,
-                                &format!("stats:{}", time_range),
-                                false,
-                                0,
-                            ).await;
-                        });
+    #[tokio::test]
+    async fn test_chat_explains_retrieved_memories() {
+        let seeded = vec![
+            (uuid::Uuid::new_v4(), "今天早上吃了一个苹果"),
+            (uuid::Uuid::new_v4(), "周末去公园跑步五公里"),
+            (uuid::Uuid::new_v4(), "晚上喝了一杯咖啡"),
+        ];
+        let retriever = Arc::new(SeededRetriever { memories: seeded.clone() });
+        let provider = canned_provider("你早上吃了苹果");
 
-                        let error_response = StatsResponse {
-                            total_memories: 0,
-                            total_events: 0,
-                            total_entities: 0,
-                            events_per_day: HashMap::new(),
-                            event_types: HashMap::new(),
-                            entities: vec![],
-                            time_range: TimeRangeStats {
-                                start_date: String::new(),
-                                end_date: String::new(),
-                                total_days: 0,
-                                most_active_day: format!("Error: {}", e),
-                                least_active_day: String::new(),
-                            },
-                        };
-                        warp::reply::json(&error_response)
-                    }
-                }
-            });
+        let server = unreachable_server()
+            .with_chat_provider(provider.clone())
+            .with_chat_config(ChatConfig {
+                retrieval_top_k: 2,
+                ..Default::default()
+            })
+            .with_chat_retriever(retriever.clone());
+        let explanations = chat_explanations(server, "我早上吃了什么？").await;
 
-        // Combine routes
-        let routes = health
-            .or(chat)
-            .or(timeline)
-            .or(stats)
-            .with(cors);
+        let ids: Vec<uuid::Uuid> = explanations.iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![seeded[0].0, seeded[1].0]);
+        assert!(explanations.iter().all(|e| e.source == RecallSource::Memory));
+        assert_eq!(explanations[0].snippet, "今天早上吃了一个苹果");
+        assert!(explanations[0].relevance > explanations[1].relevance);
 
-        // Start server
-        let addr = self.bind_address.clone();
-        println!("🚀 DirSoul API Server starting on {}", addr);
-        println!("💬 Chat endpoint: http://{}/api/chat", addr);
-        println!("📅 Timeline endpoint: http://{}/api/timeline", addr);
-        println!("📊 Stats endpoint: http://{}/api/stats", addr);
+        // The reply was grounded in the same sources
+        let prompt = provider.calls()[0].prompt();
+        assert!(prompt.contains("- 今天早上吃了一个苹果"));
+        assert!(!prompt.contains("咖啡"));
 
-        // Parse address
-        let socket_addr: std::net::SocketAddr = addr.parse()
-            .map_err(|e| DirSoulError::Io(std::io::Error::new(std::io::ErrorKind::InvalidInput, e)))?;
+        // Disabled retrieval, no retriever and failed retrieval all give an empty list
+        let disabled = unreachable_server()
+            .with_chat_provider(canned_provider("好的"))
+            .with_chat_config(ChatConfig {
+                retrieval_top_k: 0,
+                ..Default::default()
+            })
+            .with_chat_retriever(retriever.clone());
+        assert!(chat_explanations(disabled, "我早上吃了什么？").await.is_empty());
 
-        // warp::serve().run() runs forever and returns (), so we can't use ?
-        // It will only return if there's an error connecting
-        warp::serve(routes).run(socket_addr).await;
+        let plain = unreachable_server().with_chat_provider(canned_provider("好的"));
+        assert!(chat_explanations(plain, "我早上吃了什么？").await.is_empty());
 
-        // This line is unreachable, but needed for type signature
-        Ok(())
+        let failing = unreachable_server()
+            .with_chat_provider(canned_provider("好的"))
+            .with_chat_retriever(retriever);
+        assert!(chat_explanations(failing, "fail").await.is_empty());
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     #[test]
-    fn test_chat_request_serialization() {
-        let req = ChatRequest {
-            message: "Hello".to_string(),
+    fn test_recall_explanations_include_derived_sources() {
+        let apple = uuid::Uuid::new_v4();
+        let run = uuid::Uuid::new_v4();
+        let deleted = uuid::Uuid::new_v4();
+        let hits = vec![
+            SearchHit { memory_id: apple, similarity: 0.9 },
+            SearchHit { memory_id: deleted, similarity: 0.8 },
+            SearchHit { memory_id: run, similarity: 0.6 },
+        ];
+        let memories = vec![
+            (run, Some("周末去公园跑步五公里".to_string())),
+            (apple, Some(format!("今天早上吃了一个苹果{}", "。".repeat(100)))),
+        ];
+
+        let event = EventMemory {
+            event_id: uuid::Uuid::new_v4(),
+            memory_id: apple,
             user_id: "test_user".to_string(),
-            history: vec![],
-            context: None,
+            timestamp: chrono::Utc::now(),
+            actor: None,
+            action: "吃".to_string(),
+            target: "苹果".to_string(),
+            quantity: Some(1.0),
+            unit: Some("个".to_string()),
+            confidence: 0.9,
+            extractor_version: None,
+            metadata: None,
+        };
+        let orphan = EventMemory {
+            event_id: uuid::Uuid::new_v4(),
+            memory_id: deleted,
+            ..event.clone()
         };
 
-        let json = serde_json::to_string(&req).unwrap();
-        let _deserialized: ChatRequest = serde_json::from_str(&json).unwrap();
-    }
-
-    #[test]
-    fn test_api_chat_response_serialization() {
-        let resp = ApiChatResponse {
-            response: "Hi".to_string(),
-            history: vec![],
-            recorded_memory_ids: vec![],
-            processing_time_ms: 100,
+        let now = chrono::Utc::now();
+        let view = CognitiveView {
+            view_id: uuid::Uuid::new_v4(),
+            user_id: "test_user".to_string(),
+            hypothesis: "用户喜欢吃水果".to_string(),
+            view_type: "preference".to_string(),
+            description: None,
+            derived_from: serde_json::json!([event.event_id]),
+            evidence_count: 1,
+            confidence: 0.7,
+            validation_count: 0,
+            last_validated_at: None,
+            status: ViewStatus::Active.into(),
+            created_at: now,
+            updated_at: now,
+            expires_at: now + chrono::Duration::days(30),
+            promoted_to: None,
+            source: "test".to_string(),
+            tags: None,
             metadata: None,
+            counter_evidence: serde_json::json!([]),
+            counter_evidence_count: 0,
+        };
+        let unrelated_view = CognitiveView {
+            view_id: uuid::Uuid::new_v4(),
+            derived_from: serde_json::json!([uuid::Uuid::new_v4()]),
+            ..view.clone()
         };
 
-        let json = serde_json::to_string(&resp).unwrap();
-        let _deserialized: ApiChatResponse = serde_json::from_str(&json).unwrap();
-    }
-
-    #[test]
-    fn test_http_server_creation() {
-        let server = HttpServer::new("127.0.0.1:8080".to_string(), "postgresql://localhost/test".to_string()).unwrap();
-        assert_eq!(server.bind_address, "127.0.0.1:8080");
+        let explanations =
+            build_recall_explanations(&hits, &memories, &[event.clone(), orphan], &[view.clone(), unrelated_view]);
+        let summary: Vec<(RecallSource, uuid::Uuid)> = explanations.iter().map(|e| (e.source, e.id)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (RecallSource::Memory, apple),
+                (RecallSource::Event, event.event_id),
+                (RecallSource::View, view.view_id),
+                (RecallSource::Memory, run),
+            ]
+        );
+        assert_eq!(explanations[1].snippet, "吃 苹果");
+        assert_eq!(explanations[2].relevance, 0.9);
+        // Long memories are cut
+        assert_eq!(explanations[0].snippet.chars().count(), MAX_SNIPPET_CHARS + 1);
+        assert!(explanations[0].snippet.ends_with('…'));
     }
 }
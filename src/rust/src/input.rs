@@ -20,10 +20,12 @@
 //! ```
 
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info, warn};
 
+use crate::content_filter::{seal_redactions, ContentFilter, NoopFilter, REDACTIONS_METADATA_KEY};
 use crate::crypto::EncryptionManager;
 use crate::models::{ContentType, NewRawMemory};
 use crate::Result;
@@ -177,10 +179,12 @@ pub enum DocumentFormat {
 
 /// Input processor for converting RawInput to NewRawMemory
 ///
-/// Handles the conversion logic including optional encryption.
+/// Handles the conversion logic including optional encryption and content
+/// filtering (PII redaction) of text before it is stored.
 pub struct InputProcessor {
     user_id: String,
     encryption: Option<EncryptionManager>,
+    content_filter: Arc<dyn ContentFilter>,
 }
 
 impl InputProcessor {
@@ -192,9 +196,19 @@ impl InputProcessor {
         Self {
             user_id: user_id.into(),
             encryption: None,
+            content_filter: Arc::new(NoopFilter),
         }
     }
 
+    /// Set the content filter applied to text before persistence
+    ///
+    /// Redactions are recorded under the `redactions` metadata key; with
+    /// encryption enabled the originals are sealed so they can be restored.
+    pub fn with_content_filter(mut self, filter: Arc<dyn ContentFilter>) -> Self {
+        self.content_filter = filter;
+        self
+    }
+
     /// Set the encryption manager for encrypted storage
     pub fn with_encryption(mut self, encryption: EncryptionManager) -> Self {
         self.encryption = Some(encryption);
@@ -285,6 +299,8 @@ impl InputProcessor {
     ) -> Result<NewRawMemory> {
         debug!("Processing text content: {} bytes", content.len());
 
+        let (content, redactions) = self.apply_content_filter(&content)?;
+
         let mut base_metadata = serde_json::json!({
             "source": "text_input",
             "length": content.len(),
@@ -301,6 +317,10 @@ impl InputProcessor {
             }
         }
 
+        if let Some(redactions) = redactions {
+            self.merge_metadata(&mut base_metadata, Self::redactions_metadata(redactions));
+        }
+
         // Create memory (encrypted or plaintext based on configuration)
         let memory = if let Some(ref enc) = self.encryption {
             let encrypted = enc.encrypt_string(&content)?;
@@ -413,10 +433,17 @@ impl InputProcessor {
         }
 
         // Store extracted text content if available
-        let text_content = content.unwrap_or_else(|| {
+        let text_content = match content {
+            Some(content) => {
+                let (filtered, redactions) = self.apply_content_filter(&content)?;
+                if let Some(redactions) = redactions {
+                    self.merge_metadata(&mut meta, Self::redactions_metadata(redactions));
+                }
+                filtered
+            }
             // If no text extracted, store file path reference
-            format!("[Document: {}]", file_path.display())
-        });
+            None => format!("[Document: {}]", file_path.display()),
+        };
 
         let memory = if let Some(ref enc) = self.encryption {
             let encrypted = enc.encrypt_string(&text_content)?;
@@ -518,6 +545,29 @@ impl InputProcessor {
         Ok(memory)
     }
 
+    /// Run the content filter, returning the text and sealed redactions (if any)
+    fn apply_content_filter(&self, content: &str) -> Result<(String, Option<serde_json::Value>)> {
+        let filtered = self.content_filter.filter(content);
+        if filtered.redactions.is_empty() {
+            return Ok((filtered.text, None));
+        }
+
+        debug!(
+            "Content filter '{}' redacted {} value(s)",
+            self.content_filter.name(),
+            filtered.redactions.len()
+        );
+        let sealed = seal_redactions(&filtered.redactions, self.encryption.as_ref())?;
+        Ok((filtered.text, Some(sealed)))
+    }
+
+    /// Metadata object holding sealed redactions
+    fn redactions_metadata(redactions: serde_json::Value) -> serde_json::Value {
+        let mut metadata = serde_json::Map::new();
+        metadata.insert(REDACTIONS_METADATA_KEY.to_string(), redactions);
+        serde_json::Value::Object(metadata)
+    }
+
     /// Helper function to merge metadata
     fn merge_metadata(&self, base: &mut serde_json::Value, additional: serde_json::Value) {
         if let (Some(base_obj), Some(add_obj)) = (base.as_object_mut(), additional.as_object()) {
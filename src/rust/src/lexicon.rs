@@ -0,0 +1,522 @@
+//! Keyword Lexicons
+//!
+//! Sentiment words, negation markers and contradiction pairs used by the
+//! programmatic conflict detection in `cognitive`. The built-in lists cover
+//! common Chinese phrasing; users can extend them for their own domain or
+//! language with a TOML or JSON file loaded at startup.
+//!
+//! # Topic Matching
+//! Two contradicting hypotheses only conflict when they are about the same
+//! topic. `topic_tokens` strips every lexicon word plus `stop_words` (function
+//! words and common verbs), keeps `topic_seeds` (known nouns) whole and
+//! splits the rest into character bigrams (words for Latin text). Topics
+//! match when the Jaccard overlap reaches `min_topic_overlap`.
+//!
+//! # Example
+//! ```toml
+//! sentiment_words = ["偏爱"]
+//! negation_markers = ["并非"]
+//! non_negations = ["不如"]
+//! contradiction_pairs = [["偏爱", "排斥"]]
+//! stop_words = ["品尝"]
+//! topic_seeds = ["西红柿"]
+//! min_topic_overlap = 0.5
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::error::{DirSoulError, Result};
+
+/// Words ignored when comparing hypothesis targets
+const DEFAULT_SENTIMENT_WORDS: &[&str] = &["喜欢", "讨厌", "爱", "恨", "经常", "很少", "总是", "从不"];
+
+/// Markers that flip the polarity of the word right after them
+const DEFAULT_NEGATION_MARKERS: &[&str] = &["不", "没", "没有", "别", "未"];
+
+/// Words that contain a negation marker without negating anything
+const DEFAULT_NON_NEGATIONS: &[&str] = &[
+    "不错", "不过", "不少", "不久", "不断", "不但", "不仅", "不管", "不得不", "差不多", "没错",
+    "别人", "别的", "特别", "分别", "区别", "未来",
+];
+
+/// Function words and common verbs that never name a topic
+const DEFAULT_STOP_WORDS: &[&str] = &[
+    "用户", "我", "的", "了", "是", "在", "和", "也", "都", "很", "吃", "喝", "做", "去", "用",
+];
+
+/// Default minimum Jaccard overlap of topic tokens
+const DEFAULT_MIN_TOPIC_OVERLAP: f64 = 0.3;
+
+/// (positive, negative) keyword pairs that contradict each other
+const DEFAULT_CONTRADICTION_PAIRS: &[(&str, &str)] = &[
+    ("喜欢", "讨厌"),
+    ("喜欢", "不喜欢"),
+    ("爱", "恨"),
+    ("经常", "很少"),
+    ("总是", "从不"),
+    ("每天", "从不"),
+    ("习惯", "讨厌"),
+];
+
+/// Lexicons for conflict detection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LexiconSet {
+    /// Words that carry sentiment rather than the hypothesis target
+    pub sentiment_words: Vec<String>,
+    /// Markers that negate the following positive keyword ("不经常")
+    pub negation_markers: Vec<String>,
+    /// Words containing a marker that are not negations ("不错", "不过")
+    pub non_negations: Vec<String>,
+    /// (positive, negative) keyword pairs
+    pub contradiction_pairs: Vec<(String, String)>,
+    /// Words ignored when extracting topic tokens
+    pub stop_words: Vec<String>,
+    /// Nouns kept as one topic token instead of being split into bigrams
+    pub topic_seeds: Vec<String>,
+    /// Minimum Jaccard overlap of topic tokens for two hypotheses to share a topic
+    pub min_topic_overlap: f64,
+}
+
+/// On-disk lexicon file
+///
+/// Listed terms are added to the built-in lists unless `replace_defaults`
+/// is set, in which case the file is the whole lexicon.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LexiconFile {
+    replace_defaults: bool,
+    sentiment_words: Vec<String>,
+    negation_markers: Vec<String>,
+    non_negations: Vec<String>,
+    contradiction_pairs: Vec<(String, String)>,
+    stop_words: Vec<String>,
+    topic_seeds: Vec<String>,
+    min_topic_overlap: Option<f64>,
+}
+
+impl Default for LexiconSet {
+    fn default() -> Self {
+        Self {
+            sentiment_words: DEFAULT_SENTIMENT_WORDS.iter().map(|w| w.to_string()).collect(),
+            negation_markers: DEFAULT_NEGATION_MARKERS.iter().map(|w| w.to_string()).collect(),
+            non_negations: DEFAULT_NON_NEGATIONS.iter().map(|w| w.to_string()).collect(),
+            contradiction_pairs: DEFAULT_CONTRADICTION_PAIRS
+                .iter()
+                .map(|(positive, negative)| (positive.to_string(), negative.to_string()))
+                .collect(),
+            stop_words: DEFAULT_STOP_WORDS.iter().map(|w| w.to_string()).collect(),
+            topic_seeds: Vec::new(),
+            min_topic_overlap: DEFAULT_MIN_TOPIC_OVERLAP,
+        }
+    }
+}
+
+impl LexiconSet {
+    /// Lexicon with no terms
+    pub fn empty() -> Self {
+        Self {
+            sentiment_words: Vec::new(),
+            negation_markers: Vec::new(),
+            non_negations: Vec::new(),
+            contradiction_pairs: Vec::new(),
+            stop_words: Vec::new(),
+            topic_seeds: Vec::new(),
+            min_topic_overlap: DEFAULT_MIN_TOPIC_OVERLAP,
+        }
+    }
+
+    /// Load a lexicon file; `.json` files are parsed as JSON, anything else as TOML
+    ///
+    /// Unreadable, malformed or invalid files are an error so a bad lexicon
+    /// stops startup instead of silently falling back to the defaults.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(DirSoulError::Io)?;
+        let is_json = path
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("json"))
+            .unwrap_or(false);
+
+        if is_json {
+            Self::from_json_str(&content)
+        } else {
+            Self::from_toml_str(&content)
+        }
+    }
+
+    /// Parse a TOML lexicon
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        let file: LexiconFile = toml::from_str(content)
+            .map_err(|e| DirSoulError::Config(format!("Invalid lexicon: {}", e)))?;
+        Self::from_lexicon_file(file)
+    }
+
+    /// Parse a JSON lexicon
+    pub fn from_json_str(content: &str) -> Result<Self> {
+        let file: LexiconFile = serde_json::from_str(content)
+            .map_err(|e| DirSoulError::Config(format!("Invalid lexicon: {}", e)))?;
+        Self::from_lexicon_file(file)
+    }
+
+    fn from_lexicon_file(file: LexiconFile) -> Result<Self> {
+        let mut lexicon = if file.replace_defaults {
+            Self::empty()
+        } else {
+            Self::default()
+        };
+        lexicon.extend(Self {
+            sentiment_words: file.sentiment_words,
+            negation_markers: file.negation_markers,
+            non_negations: file.non_negations,
+            contradiction_pairs: file.contradiction_pairs,
+            stop_words: file.stop_words,
+            topic_seeds: file.topic_seeds,
+            min_topic_overlap: lexicon.min_topic_overlap,
+        });
+        if let Some(min_topic_overlap) = file.min_topic_overlap {
+            lexicon.min_topic_overlap = min_topic_overlap;
+        }
+        lexicon.validate()?;
+        Ok(lexicon)
+    }
+
+    /// Add the terms of `other`, skipping ones already present
+    ///
+    /// `min_topic_overlap` is left unchanged.
+    pub fn extend(&mut self, other: LexiconSet) {
+        for word in other.sentiment_words {
+            let word = word.trim().to_string();
+            if !self.sentiment_words.contains(&word) {
+                self.sentiment_words.push(word);
+            }
+        }
+        for marker in other.negation_markers {
+            let marker = marker.trim().to_string();
+            if !self.negation_markers.contains(&marker) {
+                self.negation_markers.push(marker);
+            }
+        }
+        for word in other.non_negations {
+            let word = word.trim().to_string();
+            if !self.non_negations.contains(&word) {
+                self.non_negations.push(word);
+            }
+        }
+        for (positive, negative) in other.contradiction_pairs {
+            let pair = (positive.trim().to_string(), negative.trim().to_string());
+            if !self.contradiction_pairs.contains(&pair) {
+                self.contradiction_pairs.push(pair);
+            }
+        }
+        for word in other.stop_words {
+            let word = word.trim().to_string();
+            if !self.stop_words.contains(&word) {
+                self.stop_words.push(word);
+            }
+        }
+        for seed in other.topic_seeds {
+            let seed = seed.trim().to_lowercase();
+            if !self.topic_seeds.contains(&seed) {
+                self.topic_seeds.push(seed);
+            }
+        }
+    }
+
+    /// Reject blank terms and pairs that contradict themselves
+    pub fn validate(&self) -> Result<()> {
+        if self.sentiment_words.iter().any(|w| w.trim().is_empty()) {
+            return Err(DirSoulError::Config("Lexicon contains a blank sentiment word".to_string()));
+        }
+        if self.negation_markers.iter().any(|m| m.trim().is_empty()) {
+            return Err(DirSoulError::Config("Lexicon contains a blank negation marker".to_string()));
+        }
+        if self.non_negations.iter().any(|w| w.trim().is_empty()) {
+            return Err(DirSoulError::Config("Lexicon contains a blank non-negation word".to_string()));
+        }
+        if self.stop_words.iter().chain(&self.topic_seeds).any(|w| w.trim().is_empty()) {
+            return Err(DirSoulError::Config("Lexicon contains a blank stop word or topic seed".to_string()));
+        }
+        if !(0.0..=1.0).contains(&self.min_topic_overlap) {
+            return Err(DirSoulError::Config(format!(
+                "min_topic_overlap must be between 0 and 1, got {}",
+                self.min_topic_overlap
+            )));
+        }
+        for (positive, negative) in &self.contradiction_pairs {
+            if positive.trim().is_empty() || negative.trim().is_empty() {
+                return Err(DirSoulError::Config(
+                    "Lexicon contains a contradiction pair with a blank side".to_string(),
+                ));
+            }
+            if positive == negative {
+                return Err(DirSoulError::Config(format!(
+                    "Contradiction pair ('{}', '{}') has the same word on both sides",
+                    positive, negative
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `word` is a sentiment word
+    pub fn is_sentiment_word(&self, word: &str) -> bool {
+        self.sentiment_words.iter().any(|w| w == word)
+    }
+
+    /// Whether `text` contains `keyword` with no negation marker right before it
+    pub fn contains_affirmed(&self, text: &str, keyword: &str) -> bool {
+        text.match_indices(keyword).any(|(start, _)| {
+            let before = &text[..start];
+            !self.negation_markers.iter().any(|marker| before.ends_with(marker.as_str()))
+        })
+    }
+
+    /// Candidate topic tokens of a hypothesis
+    ///
+    /// Lowercases the text, keeps `topic_seeds` as whole tokens, removes
+    /// sentiment words, negation markers, contradiction keywords and stop
+    /// words, then tokenizes what is left: Latin/digit runs become words,
+    /// CJK runs become character bigrams (a lone character is kept as is).
+    pub fn topic_tokens(&self, text: &str) -> BTreeSet<String> {
+        let mut rest = text.to_lowercase();
+        let mut tokens = BTreeSet::new();
+
+        let mut seeds: Vec<&str> = self.topic_seeds.iter().map(|s| s.as_str()).collect();
+        seeds.sort_by_key(|seed| std::cmp::Reverse(seed.chars().count()));
+        for seed in seeds {
+            if rest.contains(seed) {
+                tokens.insert(seed.to_string());
+                rest = rest.replace(seed, " ");
+            }
+        }
+
+        // Longest first so "没有" goes before "没"
+        let mut ignored: Vec<&str> = self
+            .sentiment_words
+            .iter()
+            .chain(&self.negation_markers)
+            .chain(&self.non_negations)
+            .chain(&self.stop_words)
+            .map(|w| w.as_str())
+            .chain(self.contradiction_pairs.iter().flat_map(|(p, n)| [p.as_str(), n.as_str()]))
+            .collect();
+        ignored.sort_by_key(|word| std::cmp::Reverse(word.chars().count()));
+        for word in ignored {
+            rest = rest.replace(&word.to_lowercase(), " ");
+        }
+
+        for segment in rest.split(|c: char| !c.is_alphanumeric()) {
+            let chars: Vec<char> = segment.chars().collect();
+            let mut start = 0;
+            while start < chars.len() {
+                let latin = chars[start].is_ascii();
+                let end = chars[start..]
+                    .iter()
+                    .position(|c| c.is_ascii() != latin)
+                    .map(|offset| start + offset)
+                    .unwrap_or(chars.len());
+                let run = &chars[start..end];
+
+                if latin || run.len() == 1 {
+                    tokens.insert(run.iter().collect());
+                } else {
+                    tokens.extend(run.windows(2).map(|pair| pair.iter().collect::<String>()));
+                }
+                start = end;
+            }
+        }
+
+        tokens
+    }
+
+    /// Jaccard overlap of the topic tokens of two texts (0 when either has none)
+    pub fn topic_overlap(&self, a: &str, b: &str) -> f64 {
+        let (a, b) = (self.topic_tokens(a), self.topic_tokens(b));
+        if a.is_empty() || b.is_empty() {
+            return 0.0;
+        }
+        let shared = a.intersection(&b).count();
+        shared as f64 / (a.len() + b.len() - shared) as f64
+    }
+
+    /// Whether two texts are about the same topic per `min_topic_overlap`
+    pub fn same_topic(&self, a: &str, b: &str) -> bool {
+        let overlap = self.topic_overlap(a, b);
+        overlap > 0.0 && overlap >= self.min_topic_overlap
+    }
+
+    /// Whether `text` contains a negation marker outside a `non_negations` word
+    ///
+    /// "不吃苹果" is negated, "苹果不错" is not.
+    pub fn is_negated(&self, text: &str) -> bool {
+        let mut words: Vec<&str> = self.non_negations.iter().map(|w| w.as_str()).collect();
+        words.sort_by_key(|word| std::cmp::Reverse(word.chars().count()));
+        let mut rest = text.to_string();
+        for word in words {
+            rest = rest.replace(word, " ");
+        }
+        self.negation_markers.iter().any(|marker| rest.contains(marker.as_str()))
+    }
+
+    /// Whether `prefix` (the text before a verb) ends with a negation marker
+    ///
+    /// Used by the rule extractor: "今天没" + "吃苹果" is a negated event.
+    pub fn ends_with_negation(&self, prefix: &str) -> bool {
+        let prefix = prefix.trim_end();
+        self.negation_markers.iter().any(|marker| prefix.ends_with(marker.as_str()))
+    }
+
+    /// Whether `text` contains `keyword` right after a negation marker
+    pub fn contains_negated(&self, text: &str, keyword: &str) -> bool {
+        text.match_indices(keyword).any(|(start, _)| {
+            let before = &text[..start];
+            self.negation_markers.iter().any(|marker| before.ends_with(marker.as_str()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_lexicon_is_valid() {
+        let lexicon = LexiconSet::default();
+        assert!(lexicon.validate().is_ok());
+        assert!(lexicon.is_sentiment_word("喜欢"));
+        assert!(lexicon.contradiction_pairs.contains(&("爱".to_string(), "恨".to_string())));
+    }
+
+    #[test]
+    fn test_negation_markers() {
+        let lexicon = LexiconSet::default();
+        assert!(lexicon.ends_with_negation("今天没 "));
+        assert!(lexicon.ends_with_negation("别"));
+        assert!(!lexicon.ends_with_negation("今天"));
+
+        assert!(lexicon.is_negated("别吃苹果"));
+        assert!(!lexicon.is_negated("别人吃苹果"));
+        assert!(!lexicon.is_negated("未来想吃苹果"));
+    }
+
+    #[test]
+    fn test_toml_lexicon_extends_defaults() {
+        let lexicon = LexiconSet::from_toml_str(
+            r#"
+            sentiment_words = ["偏爱", "喜欢"]
+            contradiction_pairs = [["偏爱", "排斥"]]
+            "#,
+        )
+        .unwrap();
+
+        assert!(lexicon.is_sentiment_word("偏爱"));
+        assert!(lexicon.is_sentiment_word("讨厌"));
+        assert_eq!(
+            lexicon.sentiment_words.iter().filter(|w| w.as_str() == "喜欢").count(),
+            1
+        );
+        assert_eq!(lexicon.contradiction_pairs.len(), DEFAULT_CONTRADICTION_PAIRS.len() + 1);
+    }
+
+    #[test]
+    fn test_json_lexicon_can_replace_defaults() {
+        let lexicon = LexiconSet::from_json_str(
+            r#"{"replace_defaults": true, "sentiment_words": ["like"], "negation_markers": ["not "],
+                "contradiction_pairs": [["like", "dislike"]]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(lexicon.sentiment_words, vec!["like".to_string()]);
+        assert_eq!(lexicon.contradiction_pairs.len(), 1);
+        assert!(!lexicon.is_sentiment_word("喜欢"));
+    }
+
+    #[test]
+    fn test_invalid_lexicon_fails() {
+        assert!(matches!(
+            LexiconSet::from_toml_str("sentiment_words = \"喜欢\""),
+            Err(DirSoulError::Config(_))
+        ));
+        assert!(LexiconSet::from_toml_str("unknown_list = []").is_err());
+        assert!(LexiconSet::from_toml_str("negation_markers = [\" \"]").is_err());
+        assert!(LexiconSet::from_toml_str("contradiction_pairs = [[\"爱\", \"爱\"]]").is_err());
+        assert!(LexiconSet::from_json_str("{\"contradiction_pairs\": [[\"爱\"]]}").is_err());
+        assert!(LexiconSet::from_file("/nonexistent/lexicon.toml").is_err());
+    }
+
+    #[test]
+    fn test_from_file_picks_format_by_extension() {
+        let dir = std::env::temp_dir().join(format!("dirsoul_lexicon_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let toml_path = dir.join("lexicon.toml");
+        std::fs::write(&toml_path, "negation_markers = [\"并非\"]").unwrap();
+        let lexicon = LexiconSet::from_file(&toml_path).unwrap();
+        assert!(lexicon.negation_markers.contains(&"并非".to_string()));
+
+        let json_path = dir.join("lexicon.json");
+        std::fs::write(&json_path, "{\"sentiment_words\": [\"偏爱\"]}").unwrap();
+        let lexicon = LexiconSet::from_file(&json_path).unwrap();
+        assert!(lexicon.is_sentiment_word("偏爱"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_topic_tokens_skip_lexicon_and_stop_words() {
+        let lexicon = LexiconSet::default();
+        let tokens = |text: &str| lexicon.topic_tokens(text).into_iter().collect::<Vec<_>>();
+
+        assert_eq!(tokens("喜欢吃水果"), vec!["水果"]);
+        assert_eq!(tokens("用户不喜欢吃新鲜水果"), vec!["新鲜", "水果", "鲜水"]);
+        assert_eq!(tokens("很少喝 Coffee!"), vec!["coffee"]);
+        assert!(tokens("喜欢").is_empty());
+    }
+
+    #[test]
+    fn test_topic_overlap_and_seeds() {
+        let lexicon = LexiconSet::default();
+        assert_eq!(lexicon.topic_overlap("喜欢吃水果", "讨厌吃蔬菜"), 0.0);
+        assert_eq!(lexicon.topic_overlap("喜欢吃水果", "讨厌水果"), 1.0);
+        assert!(lexicon.same_topic("经常跑步", "从不跑步"));
+        assert!(!lexicon.same_topic("喜欢", "讨厌"));
+
+        let seeded = LexiconSet::from_toml_str(
+            "topic_seeds = [\"西红柿\"]\nmin_topic_overlap = 0.5",
+        )
+        .unwrap();
+        assert_eq!(seeded.min_topic_overlap, 0.5);
+        assert_eq!(
+            seeded.topic_tokens("喜欢西红柿炒蛋").into_iter().collect::<Vec<_>>(),
+            vec!["炒蛋", "西红柿"]
+        );
+        assert!(seeded.same_topic("喜欢西红柿炒蛋", "讨厌西红柿"));
+
+        assert!(LexiconSet::from_toml_str("min_topic_overlap = 1.5").is_err());
+    }
+
+    #[test]
+    fn test_negation_markers() {
+        let lexicon = LexiconSet::default();
+        assert!(lexicon.contains_affirmed("经常跑步", "经常"));
+        assert!(!lexicon.contains_affirmed("不经常跑步", "经常"));
+        assert!(lexicon.contains_negated("不经常跑步", "经常"));
+        assert!(!lexicon.contains_negated("经常跑步", "经常"));
+    }
+
+    #[test]
+    fn test_is_negated_skips_non_negation_words() {
+        let lexicon = LexiconSet::default();
+        assert!(lexicon.is_negated("用户不吃苹果"));
+        assert!(lexicon.is_negated("最近没有跑步"));
+        assert!(!lexicon.is_negated("今天的苹果不错"));
+        assert!(!lexicon.is_negated("跑了不少路"));
+        assert!(lexicon.is_negated("苹果不错，但不想吃"));
+
+        let custom = LexiconSet::from_toml_str(r#"non_negations = ["不如"]"#).unwrap();
+        assert!(!custom.is_negated("不如去跑步"));
+        assert!(LexiconSet::from_toml_str(r#"non_negations = [" "]"#).is_err());
+    }
+}
@@ -3,7 +3,9 @@ pub mod agents;
 pub mod audit;
 pub mod built_in_plugins;
 pub mod cognitive;
+pub mod content_filter;
 pub mod crypto;
+pub mod data_changes;
 pub mod data_lifecycle;
 pub mod deeptalk;
 pub mod embedding;
@@ -18,15 +20,19 @@ pub mod event_storage;
 pub mod export;
 pub mod http_api;
 pub mod input;
+pub mod lexicon;
 pub mod llm_provider;
 pub mod models;
 pub mod pattern_detector;
 pub mod plugin;
+pub mod plugin_builder;
 pub mod prompt_manager;
 pub mod resource_manager;
 pub mod schema;
 pub mod security_tests;
+pub mod user_settings;
 pub mod view_generator;
+pub mod webhook;
 
 pub use agents::{
     Agent, AgentPermissions, AgentRepository, AgentUpdate, MemoryPermission, NewAgent,
@@ -35,45 +41,104 @@ pub use plugin::{
     EntityFilter, EventFilter, EventSubscription, PluginContext, PluginMemoryInterface,
     PluginMetadata, PluginOutput, PluginResponse, PluginSpec, PluginTimeRange, Statistics, UserPlugin,
 };
-pub use crypto::{EncryptionManager, SecureBuffer, DEFAULT_KEY_FILE};
-pub use embedding::{EmbeddingConfig, EmbeddingGenerator, EMBEDDING_DIM};
-pub use entity_attribute_extractor::{Attribute, AttributeType, EntityAttributeExtractor};
-pub use entity_linker::EntityLinker;
+pub use plugin_builder::PluginBuilder;
+pub use content_filter::{
+    ContentFilter, ContentFilterConfig, FilterChain, FilteredText, FilteringProvider, NoopFilter,
+    Redaction, RegexPiiFilter, restore_redactions, seal_redactions,
+};
+pub use crypto::{
+    token_nonce, EncryptionManager, NonceCheck, SecureBuffer, DEFAULT_KEY_FILE, FERNET_IV_LEN,
+};
+pub use data_changes::{notify_user_data_changed, subscribe_user_data_changes, UserDataListener};
+pub use embedding::{
+    check_embedding_consistency, check_vector_capability, spawn_embedding_backfill_loop,
+    users_with_pending_embeddings, DistanceMetric, EmbeddingConfig, EmbeddingConsistencyReport,
+    EmbeddingGenerator, EmbeddingMatch, ModelMismatchPolicy, VectorCapability, EMBEDDING_DIM,
+};
+pub use entity_attribute_extractor::{
+    Attribute, AttributeType, EntityAttributeExtractor, TENTATIVE_ATTRIBUTES_KEY,
+};
+pub use entity_linker::{merge_entity_attributes, upsert_entity, EntityLinker};
 pub use entity_relation_extractor::{
-    EntityRelationExtractor, ExtractedRelation, RelationExtractorConfig, RelationType,
+    CoOccurrenceEvent, CoOccurrenceMode, ConfidenceBlending, EntityRelationExtractor,
+    ExtractedRelation, PathSearch, RelationExtractorConfig, RelationRule, RelationSegment,
+    RelationType, RuleMatch, StrengthRecomputeSummary, spawn_strength_recompute_loop,
+    users_with_relations,
 };
 pub use entity_summarizer::EntitySummarizer;
 pub use error::{DirSoulError, Result};
-pub use event_aggregator::{AggregationResult, AggregationType, EventAggregator, TimeRange};
-pub use event_extractor::{ExtractedEvent, RuleExtractor, SlmExtractor, TimeParser};
-pub use event_storage::EventStorage;
+pub use event_aggregator::{
+    AggregateOutput, AggregateQuery, AggregationResult, AggregationType, BucketPoint, EventAggregator,
+    GroupBy, TimeBucket, TimeRange,
+};
+pub use event_extractor::{
+    ActionNormalizer, ActorInference, ExtractedEvent, ExtractionProgress, ExtractionTotals,
+    RuleExtractor, SlmExtractor, TimeParser,
+};
+pub use event_storage::{
+    EventStorage, ExtractionFailure, ExtractionFailureStatus, ExtractionRetryConfig,
+    ExtractionRetryReport, list_dead_letter_extractions, record_extraction_failure,
+    retry_failed_extractions, spawn_extraction_retry_loop, users_with_due_extractions,
+};
 pub use input::{InputProcessor, RawInput};
+pub use lexicon::LexiconSet;
 pub use llm_provider::{
-    ChatMessage, ChatResponse, LLMProvider, ModelConfig, ModelProviderFactory,
-    OllamaProvider, OpenAICompatibleProvider, extract_response_text,
+    AzureConfig, AzureOpenAIProvider, ChatMessage, ChatResponse, DualModelProvider, GenerateOptions,
+    GovernedProvider, LLMProvider, LlmGovernor, LoggingProvider, CircuitBreakerConfig,
+    CircuitBreakerProvider, ModelConfig, ModelsConfig, ModelProviderFactory, OllamaProvider,
+    OpenAICompatibleProvider,
+    PayloadLogging, Redaction, ResponseFilterConfig, ResponseFilterProvider, TagStripper, extract_response_text, parse_json_array_lenient,
+    sanitize_sampling, strip_wrapper_tags, validate_chat_messages,
 };
 pub use models::{
-    ContentType, Entity, EntityRelation, EntityType, NewEntity, NewEntityRelation,
-    EventMemory, NewEventMemory, NewRawMemory, RawMemory, UpdateRawMemory,
+    ContentType, Entity, EntityImportanceConfig, EntityRelation, EntityType, NewEntity,
+    NewEntityRelation, EventMemory, ExtractorVersion, NewEventMemory, NewRawMemory, RawMemory,
+    UpdateRawMemory, canonicalize_name, entity_importance, rank_entities_by_importance,
+    EVENT_NEGATED_KEY, EVENT_RAW_SPAN_KEY, EVENT_SENTIMENT_KEY, SURFACE_FORMS_KEY,
 };
-pub use prompt_manager::PromptManager;
+pub use prompt_manager::{PromptManager, PromptTask, SystemPrompts};
 pub use cognitive::{
-    CognitiveView, NewCognitiveView, StableConcept, NewStableConcept, ViewStatus,
+    CandidateReadiness, CognitiveView, ConfidenceBump, CriterionCheck, DuplicateConceptPolicy,
+    EvidenceKind, GateCriterion, NewCognitiveView, PromotionCandidate, PromotionCandidateConfig,
+    PromotionCandidatePage, PromotionGateConfig, PromotionPlan, RevalidationConfig,
+    RevalidationReport, StableConcept, NewStableConcept, RollbackPlan, ViewDecision, ViewDefaults, ViewStatus, ViewType,
+    approve_view_promotion, find_concept_by_canonical_name, get_latest_version,
+    get_version_history, list_promotion_candidates, list_views_ready_for_promotion, order_version_history, plan_promotion, plan_rollback, promote_concept, promote_concept_with,
+    revalidate_active_views, rollback_concept, select_latest_version, spawn_revalidation_loop,
+    users_with_active_views, validate_view, validate_view_with,
 };
 pub use pattern_detector::{
-    DetectionTimeRange, DetectedPattern, PatternDetector, PatternDetectorConfig,
-    PatternDetectionResult, PatternDetectionScheduler, PatternMetadata, PatternType, TrendDirection,
+    AnomalyBaseline, ConsistencyMetric, ConsistencyPeriod, DailyDetectionReport, DayNameLocale, DetectionTimeRange, DetectedPattern, PatternDetector, PatternDetectorConfig,
+    PatternDetectionResult, PatternDetectionScheduler, PatternIdStrategy, PatternMetadata, PatternType,
+    PgPool, TimeBucketing, TrendDirection, DEFAULT_DETECTION_WORKERS, detect_users_parallel,
+    spawn_detection_loop, throttled_workers, users_with_memories,
+};
+pub use user_settings::UserSettings;
+pub use view_generator::{SimilarityFn, ViewGenerator, ViewGeneratorBuilder, ViewGeneratorConfig};
+pub use deeptalk::{
+    condense_turns, ConversationContext, ConversationHistory, ConversationHistoryConfig,
+    DeepTalkPlugin, EmotionalTrend, EmotionalTrendPoint, EmotionalTrendStore, HistoryOverflowPolicy,
 };
-pub use view_generator::{ViewGenerator, ViewGeneratorBuilder, ViewGeneratorConfig};
-pub use deeptalk::{ConversationContext, DeepTalkPlugin, EmotionalTrend};
 pub use actor_agent::EventNotification;
 pub use built_in_plugins::{DecisionContext, DecisionPlugin, PsychologyContext, PsychologyPlugin};
-pub use audit::{AuditLog, AuditLogRepository, AuditLogger, NewAuditLog, ThreadSafeAuditLogger};
-pub use export::{AutoBackupManager, DataExporter, DataImporter, EncryptedDataExport, ImportSummary, UserDataExport};
+pub use audit::{
+    AuditBatchWriter, AuditBufferConfig, AuditLog, AuditLogRepository, AuditLogger, NewAuditLog,
+    ThreadSafeAuditLogger,
+};
+pub use export::{
+    AutoBackupManager, DataExporter, DataImporter, EncryptedDataExport, EraseReport, ExportOptions,
+    ImportOptions, ImportProgress, ImportSummary, ImportWatermark,
+    RemappedCognitiveLayer, UserDataExport, erase_user, erasure_confirmation_token,
+    remap_cognitive_layer,
+};
 pub use http_api::{
-    ApiChatResponse, ChatRequest, EntityStat, HttpServer, StatsRequest,
-    StatsResponse, TimelineEvent, TimelineFilters, TimelineRequest, TimelineResponse,
-    TimelineSummary, TimeRangeStats,
+    ApiChatResponse, ApiErrorResponse, ApiTokens, BodyLimits, ChatConfig, ChatRequest, ChatRetriever, CommandRequest,
+    ConceptRollbackRequest, DependencyHealth, DetectPatternsRequest, EntityStat, HealthConfig,
+    HttpServer, IdempotencyConfig, KeywordIndexConfig, KeywordTokenizer, PatternsQuery, QueryCache, QueryCacheConfig, ReadinessReport, RecallExplanation, RecallSource, RelatedEntitiesResponse, RelatedEntity,
+    RelationDirection, RelationStatsResponse, RelationsQuery, SearchConfig, SearchHit,
+    SemanticChatRetriever, SemanticSearchRequest, SemanticSearchResponse, StatsRequest, StatsResponse, TimelineEvent,
+    TimelineFilters, TimelineRequest, TimelineResponse, TimelineSummary, TimelineZone,
+    TimeRangeStats, ViewPromotionRequest, ViewsQuery,
 };
 pub use resource_manager::{
     background_memory_monitor, CircuitBreaker, MemoryUsage, ResourceManager,
@@ -81,9 +146,14 @@ pub use resource_manager::{
 };
 pub use data_lifecycle::{
     CompressedData, DataLifecycleManager, DataSummary, DataTier, SummaryStatistics,
-    TierDistribution, TieringConfig, ArchiveStats,
+    TierDistribution, TieringConfig, ArchiveStats, live_memory_ids, purge_soft_deleted_memories,
+    restore_memory, soft_delete_memory, LiveMemoryIds,
 };
 pub use security_tests::{
     run_security_benchmarks, SecurityBenchmarkResults, SecurityTestResult, SecurityTestSuite,
     SecurityTestSuiteResults,
-};
\ No newline at end of file
+};
+pub use webhook::{
+    sign_delivery, sign_payload, verify_delivery, WebhookConfig, WebhookEndpoint, WebhookEvent,
+    WebhookEventType, WebhookNotifier,
+};
@@ -13,19 +13,59 @@
 //! LLMProvider (trait)
 //!     ├── OllamaProvider (local models: phi4-mini, deepseek-r1, llama-3, etc.)
 //!     ├── OpenAICompatibleProvider (APIs: DeepSeek V3, SiliconFlow, OpenAI, etc.)
-//!     └── Future: AzureOpenAIProvider, AnthropicProvider, etc.
+//!     ├── AzureOpenAIProvider (Azure OpenAI deployments)
+//!     ├── DualModelProvider (chat → inference model, embed → embedding model)
+//!     ├── CircuitBreakerProvider (fast-fails while a backend keeps failing)
+//!     ├── ResponseFilterProvider (strips reasoning blocks such as <think>)
+//!     ├── LoggingProvider (status/latency/token logs, redacted payloads at TRACE)
+//!     ├── GovernedProvider (shares a global cap on in-flight calls via LlmGovernor)
+//!     └── Future: AnthropicProvider, etc.
 //! ```
 
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
+use crate::resource_manager::CircuitBreaker;
 use crate::Result;
 
 /// Default Ollama host
 const DEFAULT_OLLAMA_HOST: &str = "http://127.0.0.1:11434";
 
+/// Default number of concurrent Ollama `embed` requests in `embed_batch`
+const DEFAULT_OLLAMA_EMBED_CONCURRENCY: usize = 4;
+
+/// Default path prefix for OpenAI-compatible APIs
+const DEFAULT_OPENAI_API_PATH_PREFIX: &str = "v1";
+
+/// Lowest sampling temperature sent to any backend
+pub const MIN_TEMPERATURE: f32 = 0.0;
+
+/// Highest temperature sent to Ollama; beyond this output is noise
+pub const OLLAMA_MAX_TEMPERATURE: f32 = 2.0;
+
+/// Highest temperature accepted by OpenAI-compatible APIs (incl. Azure)
+pub const OPENAI_MAX_TEMPERATURE: f32 = 2.0;
+
+/// Default consecutive failures before the provider circuit breaker opens
+const DEFAULT_BREAKER_FAILURE_THRESHOLD: u32 = 5;
+
+/// Default provider circuit breaker cooldown in seconds
+const DEFAULT_BREAKER_COOLDOWN_SEC: u64 = 30;
+
+/// Env var that turns on TRACE payload logging in `LoggingProvider` ("1", "true")
+pub const LOG_PAYLOADS_ENV: &str = "DIRSOUL_LLM_LOG_PAYLOADS";
+
+/// Env var selecting payload redaction: "hash" (default), "truncate[:N]" or "none"
+pub const LOG_REDACTION_ENV: &str = "DIRSOUL_LLM_LOG_REDACTION";
+
+/// Characters kept by `Redaction::Truncate` when no length is given
+const DEFAULT_REDACTION_TRUNCATE_CHARS: usize = 16;
+
+/// Payload fields holding user or model text; everything below them is redacted
+const REDACTED_PAYLOAD_KEYS: &[&str] = &["content", "prompt", "response", "input"];
+
 /// LLM chat message
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
@@ -158,6 +198,23 @@ pub struct StreamChunk {
     pub done: bool,
 }
 
+/// Sampling options for a raw `generate` call
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GenerateOptions {
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+}
+
+impl GenerateOptions {
+    /// Options with the given temperature and output token limit
+    pub fn new(temperature: f32, max_tokens: u32) -> Self {
+        Self {
+            temperature: Some(temperature),
+            max_tokens: Some(max_tokens),
+        }
+    }
+}
+
 // ============================================================================
 // LLM Provider Trait
 // ============================================================================
@@ -184,12 +241,32 @@ pub trait LLMProvider: Send + Sync {
         max_tokens: Option<u32>,
     ) -> Result<tokio::sync::mpsc::Receiver<StreamChunk>>;
 
+    /// Complete a raw prompt and return the generated text
+    ///
+    /// The default sends the prompt as a single user message through `chat`,
+    /// for backends without a raw completion endpoint.
+    async fn generate(&self, prompt: &str, options: GenerateOptions) -> Result<String> {
+        let response = self
+            .chat(vec![ChatMessage::user(prompt)], options.temperature, options.max_tokens)
+            .await?;
+        Ok(extract_response_text(&response))
+    }
+
     /// Generate embedding for a single text
     async fn embed(&self, text: &str) -> Result<Vec<f32>>;
 
     /// Generate embeddings for multiple texts (batch)
     async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
 
+    /// Concurrent single `embed` requests `embed_batch` sends
+    ///
+    /// 1 for backends with a batch endpoint. Wrappers report their inner
+    /// provider's value, so `GovernedProvider` can send the requests itself
+    /// and count each one against the cap.
+    fn embed_batch_fan_out(&self) -> usize {
+        1
+    }
+
     /// Get the model name being used
     fn model_name(&self) -> String;
 
@@ -210,6 +287,155 @@ pub fn extract_response_text(response: &ChatResponse) -> String {
     }
 }
 
+/// Chat roles accepted by every provider
+pub const CHAT_ROLES: [&str; 3] = ["system", "user", "assistant"];
+
+/// Validate chat messages before they are sent to a provider
+///
+/// Roles are trimmed and lower-cased so "User" or " assistant" still pass;
+/// any other role is rejected, as is a conversation without a user message.
+/// Catching these locally gives a clear error instead of an opaque 400 (or a
+/// silently ignored message) from the backend.
+pub fn validate_chat_messages(messages: Vec<ChatMessage>) -> Result<Vec<ChatMessage>> {
+    let messages = messages
+        .into_iter()
+        .enumerate()
+        .map(|(index, message)| {
+            let role = message.role.trim().to_lowercase();
+            if !CHAT_ROLES.contains(&role.as_str()) {
+                return Err(crate::error::DirSoulError::Config(format!(
+                    "Invalid chat role '{}' at message {} (expected one of: {})",
+                    message.role,
+                    index,
+                    CHAT_ROLES.join(", ")
+                )));
+            }
+            Ok(ChatMessage {
+                role,
+                content: message.content,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if !messages.iter().any(|m| m.role == "user") {
+        return Err(crate::error::DirSoulError::Config(
+            "Chat request must contain at least one user message".to_string(),
+        ));
+    }
+
+    Ok(messages)
+}
+
+/// Normalize sampling parameters before they are sent to a backend
+///
+/// `temperature` is clamped into `[MIN_TEMPERATURE, max_temperature]`
+/// (NaN is dropped) and `max_tokens: Some(0)` is treated as unset, each with a
+/// logged warning, so every backend sees the same sane values instead of
+/// rejecting them in its own way.
+pub fn sanitize_sampling(
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+    max_temperature: f32,
+) -> (Option<f32>, Option<u32>) {
+    let temperature = match temperature {
+        Some(t) if t.is_nan() => {
+            tracing::warn!("Ignoring NaN temperature");
+            None
+        }
+        Some(t) if !(MIN_TEMPERATURE..=max_temperature).contains(&t) => {
+            let clamped = t.clamp(MIN_TEMPERATURE, max_temperature);
+            tracing::warn!("Clamping temperature {} to {}", t, clamped);
+            Some(clamped)
+        }
+        other => other,
+    };
+
+    let max_tokens = match max_tokens {
+        Some(0) => {
+            tracing::warn!("Ignoring max_tokens = 0; using the backend default");
+            None
+        }
+        other => other,
+    };
+
+    (temperature, max_tokens)
+}
+
+/// Leniently extract a JSON array from an LLM response
+///
+/// Small models often wrap JSON in markdown fences (```json ... ```) or add
+/// prose around it. This strips fences, then parses the first balanced
+/// `[...]` block that is valid JSON. Returns an empty vec when no array
+/// can be recovered.
+pub fn parse_json_array_lenient(text: &str) -> Vec<serde_json::Value> {
+    let body = strip_markdown_fence(text);
+
+    if let Ok(values) = serde_json::from_str::<Vec<serde_json::Value>>(body.trim()) {
+        return values;
+    }
+
+    for (start, ch) in body.char_indices() {
+        if ch != '[' {
+            continue;
+        }
+        if let Some(end) = find_balanced_end(&body[start..]) {
+            if let Ok(values) = serde_json::from_str::<Vec<serde_json::Value>>(&body[start..start + end]) {
+                return values;
+            }
+        }
+    }
+
+    Vec::new()
+}
+
+/// Return the contents of the first markdown code fence, or the input unchanged
+fn strip_markdown_fence(text: &str) -> &str {
+    let Some(open) = text.find("```") else {
+        return text;
+    };
+    let after_open = &text[open + 3..];
+    // Skip the language tag (e.g. "json") up to the end of the fence line
+    let content_start = after_open.find('\n').map(|i| i + 1).unwrap_or(after_open.len());
+    let content = &after_open[content_start..];
+    match content.find("```") {
+        Some(close) => &content[..close],
+        None => content,
+    }
+}
+
+/// Byte length of the balanced bracket block starting at `s[0]`, ignoring
+/// brackets inside JSON strings
+fn find_balanced_end(s: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, ch) in s.char_indices() {
+        if in_string {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match ch {
+            '"' => in_string = true,
+            '[' | '{' => depth += 1,
+            ']' | '}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i + ch.len_utf8());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
 // ============================================================================
 // Model Configuration
 // ============================================================================
@@ -217,7 +443,7 @@ pub fn extract_response_text(response: &ChatResponse) -> String {
 /// Model provider configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelConfig {
-    /// Provider type: "ollama" or "openai_compatible"
+    /// Provider type: "ollama", "openai_compatible" or "azure_openai"
     pub provider: String,
     
     /// Model name (e.g., "phi4-mini", "nomic-embed-text:v1.5", "deepseek-chat")
@@ -230,6 +456,60 @@ pub struct ModelConfig {
     /// OpenAI-compatible API configuration
     #[serde(default)]
     pub openai_compatible: Option<OpenAICompatibleConfig>,
+
+    /// Azure OpenAI configuration
+    #[serde(default)]
+    pub azure_openai: Option<AzureConfig>,
+
+    /// Wrap the provider in a circuit breaker when set
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+
+    /// Strip reasoning blocks (e.g. deepseek-r1's `<think>`) from responses when set
+    #[serde(default)]
+    pub response_filter: Option<ResponseFilterConfig>,
+}
+
+/// Dual model configuration (mirrors `config/models.toml`)
+///
+/// Embedding and inference are configured independently so a user can run,
+/// for example, `phi4-mini` for chat and `nomic-embed-text` for embeddings,
+/// even on different providers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelsConfig {
+    /// Cap on LLM calls in flight across chat, extraction and embedding;
+    /// unlimited when unset
+    #[serde(default)]
+    pub max_concurrent_requests: Option<usize>,
+
+    /// Embedding model (changing it requires re-indexing all memories)
+    pub embedding: ModelConfig,
+
+    /// Inference/chat model (user-selectable)
+    pub inference: ModelConfig,
+}
+
+impl ModelsConfig {
+    /// The governor for `max_concurrent_requests`, `None` when unlimited
+    ///
+    /// Call once and share the result between every subsystem that talks to
+    /// a model; separate governors don't limit each other.
+    pub fn governor(&self) -> Result<Option<LlmGovernor>> {
+        self.max_concurrent_requests.map(LlmGovernor::new).transpose()
+    }
+
+    /// Load from a TOML file such as `config/models.toml`
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        let content = std::fs::read_to_string(path.as_ref())
+            .map_err(crate::error::DirSoulError::Io)?;
+        Self::from_toml_str(&content)
+    }
+
+    /// Parse from a TOML string
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        toml::from_str(content)
+            .map_err(|e| crate::error::DirSoulError::Config(format!("Invalid models config: {}", e)))
+    }
 }
 
 /// Ollama configuration
@@ -238,6 +518,15 @@ pub struct OllamaConfig {
     /// Ollama host URL (default: http://127.0.0.1:11434)
     #[serde(default = "default_ollama_host")]
     pub host: String,
+
+    /// Concurrent `embed` requests used by `embed_batch` (default: 4)
+    #[serde(default)]
+    pub embed_concurrency: Option<usize>,
+
+    /// How long Ollama keeps the model loaded after a request, e.g. "10m"
+    /// or "-1m" for indefinitely (default: Ollama's own, 5 minutes)
+    #[serde(default)]
+    pub keep_alive: Option<String>,
 }
 
 fn default_ollama_host() -> String {
@@ -252,6 +541,76 @@ pub struct OpenAICompatibleConfig {
     
     /// API key for authentication
     pub api_key: String,
+
+    /// Path prefix inserted between base URL and endpoint (default: "v1").
+    /// Set to "" for gateways that expose endpoints directly under the base URL.
+    #[serde(default)]
+    pub api_path_prefix: Option<String>,
+}
+
+/// Azure OpenAI configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AzureConfig {
+    /// Resource endpoint (e.g., https://my-resource.openai.azure.com)
+    pub endpoint: String,
+
+    /// API key, sent in the `api-key` header
+    pub api_key: String,
+
+    /// Deployment name that requests are routed to
+    pub deployment: String,
+
+    /// API version query parameter (e.g., 2024-02-01)
+    pub api_version: String,
+}
+
+/// Circuit breaker configuration for `CircuitBreakerProvider`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open the breaker (default: 5)
+    #[serde(default = "default_breaker_failure_threshold")]
+    pub failure_threshold: u32,
+
+    /// Seconds to fast-fail before letting a trial call through (default: 30)
+    #[serde(default = "default_breaker_cooldown_sec")]
+    pub cooldown_sec: u64,
+}
+
+fn default_breaker_failure_threshold() -> u32 {
+    DEFAULT_BREAKER_FAILURE_THRESHOLD
+}
+
+fn default_breaker_cooldown_sec() -> u64 {
+    DEFAULT_BREAKER_COOLDOWN_SEC
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_breaker_failure_threshold(),
+            cooldown_sec: default_breaker_cooldown_sec(),
+        }
+    }
+}
+
+/// Response post-processing for `ResponseFilterProvider`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseFilterConfig {
+    /// Wrapper tag names whose blocks are removed (default: think, reasoning)
+    #[serde(default = "default_wrapper_tags")]
+    pub tags: Vec<String>,
+}
+
+fn default_wrapper_tags() -> Vec<String> {
+    vec!["think".to_string(), "reasoning".to_string()]
+}
+
+impl Default for ResponseFilterConfig {
+    fn default() -> Self {
+        Self {
+            tags: default_wrapper_tags(),
+        }
+    }
 }
 
 // ============================================================================
@@ -266,6 +625,8 @@ pub struct OllamaProvider {
     client: Client,
     host: String,
     model: String,
+    embed_concurrency: usize,
+    keep_alive: Option<String>,
 }
 
 impl OllamaProvider {
@@ -275,13 +636,105 @@ impl OllamaProvider {
             client: Client::new(),
             host: host.into(),
             model: model.into(),
+            embed_concurrency: DEFAULT_OLLAMA_EMBED_CONCURRENCY,
+            keep_alive: None,
+        }
+    }
+
+    /// Create a provider for `model` from an `[*.ollama]` config section
+    pub fn from_config(model: impl Into<String>, config: OllamaConfig) -> Self {
+        let mut provider = Self::new(config.host, model);
+        if let Some(concurrency) = config.embed_concurrency {
+            provider = provider.with_embed_concurrency(concurrency);
         }
+        if let Some(keep_alive) = config.keep_alive {
+            provider = provider.with_keep_alive(keep_alive);
+        }
+        provider
+    }
+
+    /// Set how many `embed` requests `embed_batch` keeps in flight (min 1)
+    pub fn with_embed_concurrency(mut self, concurrency: usize) -> Self {
+        self.embed_concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Ask Ollama to keep the model loaded for `keep_alive` (e.g. "10m")
+    /// after each request; sent as the API's `keep_alive` field
+    pub fn with_keep_alive(mut self, keep_alive: impl Into<String>) -> Self {
+        self.keep_alive = Some(keep_alive.into());
+        self
+    }
+
+    /// Fail requests that take longer than `timeout`
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_else(|_| Client::new());
+        self
     }
 
     /// Build the full API URL for an endpoint
     fn url(&self, endpoint: &str) -> String {
         format!("{}/api/{}", self.host.trim_end_matches('/'), endpoint)
     }
+
+    /// Load a chat model into memory ahead of the first request
+    ///
+    /// Ollama loads the model without generating anything when `/api/generate`
+    /// is called without a prompt. Embedding-only models cannot generate; use
+    /// `preload_embedding` for those.
+    pub async fn preload(&self) -> Result<()> {
+        #[derive(Serialize)]
+        struct PreloadRequest<'a> {
+            model: &'a str,
+            stream: bool,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            keep_alive: Option<&'a str>,
+        }
+
+        let request = PreloadRequest {
+            model: &self.model,
+            stream: false,
+            keep_alive: self.keep_alive.as_deref(),
+        };
+        self.send_preload("generate", &request).await
+    }
+
+    /// Load an embedding model into memory ahead of the first request
+    ///
+    /// An `/api/embed` call with empty input loads the model and returns no
+    /// vectors.
+    pub async fn preload_embedding(&self) -> Result<()> {
+        #[derive(Serialize)]
+        struct PreloadRequest<'a> {
+            model: &'a str,
+            input: [&'a str; 0],
+            #[serde(skip_serializing_if = "Option::is_none")]
+            keep_alive: Option<&'a str>,
+        }
+
+        let request = PreloadRequest {
+            model: &self.model,
+            input: [],
+            keep_alive: self.keep_alive.as_deref(),
+        };
+        self.send_preload("embed", &request).await
+    }
+
+    async fn send_preload<T: Serialize>(&self, endpoint: &str, request: &T) -> Result<()> {
+        let response = self.client.post(&self.url(endpoint)).json(request).send().await?;
+
+        if !response.status().is_success() {
+            return Err(crate::error::DirSoulError::ExternalError(format!(
+                "Ollama preload of {} failed: {}",
+                self.model,
+                response.status()
+            )));
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -292,6 +745,10 @@ impl LLMProvider for OllamaProvider {
         temperature: Option<f32>,
         max_tokens: Option<u32>,
     ) -> Result<ChatResponse> {
+        let messages = validate_chat_messages(messages)?;
+        let (temperature, max_tokens) =
+            sanitize_sampling(temperature, max_tokens, OLLAMA_MAX_TEMPERATURE);
+
         #[derive(Serialize)]
         struct ChatRequest {
             model: String,
@@ -299,6 +756,8 @@ impl LLMProvider for OllamaProvider {
             stream: bool,
             #[serde(skip_serializing_if = "Option::is_none")]
             options: Option<ChatOptions>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            keep_alive: Option<String>,
         }
 
         #[derive(Serialize)]
@@ -317,6 +776,7 @@ impl LLMProvider for OllamaProvider {
                 temperature,
                 num_predict: max_tokens,
             }),
+            keep_alive: self.keep_alive.clone(),
         };
 
         let response = self
@@ -343,6 +803,10 @@ impl LLMProvider for OllamaProvider {
         temperature: Option<f32>,
         max_tokens: Option<u32>,
     ) -> Result<tokio::sync::mpsc::Receiver<StreamChunk>> {
+        let messages = validate_chat_messages(messages)?;
+        let (temperature, max_tokens) =
+            sanitize_sampling(temperature, max_tokens, OLLAMA_MAX_TEMPERATURE);
+
         use tokio::sync::mpsc;
 
         #[derive(Serialize)]
@@ -352,6 +816,8 @@ impl LLMProvider for OllamaProvider {
             stream: bool,
             #[serde(skip_serializing_if = "Option::is_none")]
             options: Option<ChatOptions>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            keep_alive: Option<String>,
         }
 
         #[derive(Serialize)]
@@ -372,6 +838,7 @@ impl LLMProvider for OllamaProvider {
                 temperature,
                 num_predict: max_tokens,
             }),
+            keep_alive: self.keep_alive.clone(),
         };
 
         let url = self.url("chat");
@@ -388,16 +855,71 @@ impl LLMProvider for OllamaProvider {
         Ok(rx)
     }
 
+    /// Uses Ollama's native `/api/generate` endpoint
+    async fn generate(&self, prompt: &str, options: GenerateOptions) -> Result<String> {
+        let (temperature, max_tokens) =
+            sanitize_sampling(options.temperature, options.max_tokens, OLLAMA_MAX_TEMPERATURE);
+
+        #[derive(Serialize)]
+        struct GenerateRequest<'a> {
+            model: &'a str,
+            prompt: &'a str,
+            stream: bool,
+            options: GenerateRequestOptions,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            keep_alive: Option<&'a str>,
+        }
+
+        #[derive(Serialize)]
+        struct GenerateRequestOptions {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            temperature: Option<f32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            num_predict: Option<u32>,
+        }
+
+        let request = GenerateRequest {
+            model: &self.model,
+            prompt,
+            stream: false,
+            options: GenerateRequestOptions {
+                temperature,
+                num_predict: max_tokens,
+            },
+            keep_alive: self.keep_alive.as_deref(),
+        };
+
+        let response = self
+            .client
+            .post(&self.url("generate"))
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(crate::error::DirSoulError::ExternalError(format!(
+                "Ollama generate failed: {}",
+                response.status()
+            )));
+        }
+
+        let ollama_response: OllamaChatResponse = response.json().await?;
+        Ok(ollama_response.response)
+    }
+
     async fn embed(&self, text: &str) -> Result<Vec<f32>> {
         #[derive(Serialize)]
         struct EmbedRequest {
             model: String,
             prompt: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            keep_alive: Option<String>,
         }
 
         let request = EmbedRequest {
             model: self.model.clone(),
             prompt: text.to_string(),
+            keep_alive: self.keep_alive.clone(),
         };
 
         let response = self
@@ -419,12 +941,32 @@ impl LLMProvider for OllamaProvider {
     }
 
     async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
-        // Ollama doesn't support batch embeddings, so we process sequentially
-        let mut embeddings = Vec::with_capacity(texts.len());
-        for text in texts {
-            embeddings.push(self.embed(text).await?);
+        use futures_util::stream::{self, StreamExt};
+
+        // Ollama has no batch endpoint, so issue a bounded number of
+        // concurrent single requests and put results back in input order
+        let mut embeddings: Vec<Option<Vec<f32>>> = vec![None; texts.len()];
+        let mut pending = stream::iter(texts.iter().enumerate())
+            .map(|(index, text)| async move { (index, self.embed(text).await) })
+            .buffer_unordered(self.embed_concurrency);
+
+        while let Some((index, result)) = pending.next().await {
+            match result {
+                Ok(embedding) => embeddings[index] = Some(embedding),
+                Err(e) => {
+                    return Err(crate::error::DirSoulError::ExternalError(format!(
+                        "Ollama embed failed at batch index {}: {}",
+                        index, e
+                    )))
+                }
+            }
         }
-        Ok(embeddings)
+
+        Ok(embeddings.into_iter().map(Option::unwrap_or_default).collect())
+    }
+
+    fn embed_batch_fan_out(&self) -> usize {
+        self.embed_concurrency
     }
 
     fn model_name(&self) -> String {
@@ -456,43 +998,91 @@ impl OllamaProvider {
         }
 
         let mut stream = resp.bytes_stream();
-        let mut buffer = String::new();
+        let mut framer = NdjsonFramer::default();
 
         while let Some(chunk_result) = stream.next().await {
             let chunk = chunk_result?;
-            let data = String::from_utf8_lossy(&chunk);
-            
-            for line in data.lines() {
-                if line.is_empty() {
-                    continue;
-                }
-                buffer.push_str(line);
-                
-                // Try to parse as JSON
-                if let Ok(value) = serde_json::from_str::<serde_json::Value>(&buffer) {
-                    buffer.clear();
-                    
-                    let done = value.get("done").and_then(|d| d.as_bool()).unwrap_or(false);
-                    let content = value.get("response")
-                        .and_then(|r| r.as_str())
-                        .unwrap_or("")
-                        .to_string();
-                    
-                    if tx.send(StreamChunk { content, done }).await.is_err() {
+
+            for line in framer.push(&chunk) {
+                if let Some(chunk) = parse_ollama_stream_line(&line) {
+                    let done = chunk.done;
+
+                    if tx.send(chunk).await.is_err() {
                         return Ok(()); // Receiver dropped
                     }
-                    
+
                     if done {
                         return Ok(());
                     }
                 }
             }
         }
-        
+
+        // The final object may arrive without a trailing newline
+        if let Some(chunk) = framer.finish().and_then(|line| parse_ollama_stream_line(&line)) {
+            let _ = tx.send(chunk).await;
+        }
+
         Ok(())
     }
 }
 
+/// Newline-delimited JSON framing over a byte stream
+///
+/// Network chunks can end mid-object (or mid UTF-8 sequence) and can carry
+/// several objects at once. Only complete lines are emitted; the trailing
+/// partial line is kept until the next chunk completes it.
+#[derive(Debug, Default)]
+struct NdjsonFramer {
+    remainder: Vec<u8>,
+}
+
+impl NdjsonFramer {
+    /// Feed a chunk and return every line it completes
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.remainder.extend_from_slice(chunk);
+
+        let mut lines = Vec::new();
+        let mut start = 0;
+        while let Some(pos) = self.remainder[start..].iter().position(|&b| b == b'\n') {
+            let end = start + pos;
+            let line = String::from_utf8_lossy(&self.remainder[start..end]);
+            let line = line.trim();
+            if !line.is_empty() {
+                lines.push(line.to_string());
+            }
+            start = end + 1;
+        }
+        self.remainder.drain(..start);
+
+        lines
+    }
+
+    /// Flush whatever is left once the stream ends
+    fn finish(self) -> Option<String> {
+        let line = String::from_utf8_lossy(&self.remainder).trim().to_string();
+        if line.is_empty() {
+            None
+        } else {
+            Some(line)
+        }
+    }
+}
+
+/// Parse one complete line of Ollama's `/api/generate` stream
+fn parse_ollama_stream_line(line: &str) -> Option<StreamChunk> {
+    let value = serde_json::from_str::<serde_json::Value>(line).ok()?;
+
+    let done = value.get("done").and_then(|d| d.as_bool()).unwrap_or(false);
+    let content = value
+        .get("response")
+        .and_then(|r| r.as_str())
+        .unwrap_or("")
+        .to_string();
+
+    Some(StreamChunk { content, done })
+}
+
 // ============================================================================
 // OpenAI-Compatible Provider Implementation
 // ============================================================================
@@ -509,6 +1099,7 @@ pub struct OpenAICompatibleProvider {
     base_url: String,
     api_key: String,
     model: String,
+    api_path_prefix: String,
 }
 
 impl OpenAICompatibleProvider {
@@ -519,13 +1110,26 @@ impl OpenAICompatibleProvider {
             base_url: base_url.into(),
             api_key: api_key.into(),
             model: model.into(),
+            api_path_prefix: DEFAULT_OPENAI_API_PATH_PREFIX.to_string(),
         }
     }
 
+    /// Override the path prefix (default "v1"); an empty prefix addresses
+    /// endpoints directly under the base URL
+    pub fn with_api_path_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.api_path_prefix = prefix.into().trim_matches('/').to_string();
+        self
+    }
+
     /// Build the full API URL for an endpoint
     fn url(&self, endpoint: &str) -> String {
         let base = self.base_url.trim_end_matches('/');
-        format!("{}/v1/{}", base, endpoint.trim_start_matches('/'))
+        let endpoint = endpoint.trim_start_matches('/');
+        if self.api_path_prefix.is_empty() {
+            format!("{}/{}", base, endpoint)
+        } else {
+            format!("{}/{}/{}", base, self.api_path_prefix, endpoint)
+        }
     }
 
     /// Get authorization header value
@@ -542,6 +1146,10 @@ impl LLMProvider for OpenAICompatibleProvider {
         temperature: Option<f32>,
         max_tokens: Option<u32>,
     ) -> Result<ChatResponse> {
+        let messages = validate_chat_messages(messages)?;
+        let (temperature, max_tokens) =
+            sanitize_sampling(temperature, max_tokens, OPENAI_MAX_TEMPERATURE);
+
         #[derive(Serialize)]
         struct ChatRequest {
             model: String,
@@ -587,6 +1195,10 @@ impl LLMProvider for OpenAICompatibleProvider {
         temperature: Option<f32>,
         max_tokens: Option<u32>,
     ) -> Result<tokio::sync::mpsc::Receiver<StreamChunk>> {
+        let messages = validate_chat_messages(messages)?;
+        let (temperature, max_tokens) =
+            sanitize_sampling(temperature, max_tokens, OPENAI_MAX_TEMPERATURE);
+
         use tokio::sync::mpsc;
 
         #[derive(Serialize)]
@@ -730,260 +1342,2858 @@ impl OpenAICompatibleProvider {
         request: serde_json::Value,
         tx: tokio::sync::mpsc::Sender<StreamChunk>,
     ) -> Result<()> {
-        use futures_util::StreamExt;
-
-        let resp = client
+        let builder = client
             .post(&url)
             .header("Authorization", auth)
-            .json(&request)
-            .send()
-            .await?;
+            .json(&request);
 
-        if !resp.status().is_success() {
-            return Err(crate::error::DirSoulError::ExternalError(format!(
-                "Stream request failed: {}",
-                resp.status()
-            )));
-        }
+        stream_openai_sse(builder, tx).await
+    }
+}
 
-        let mut stream = resp.bytes_stream();
+/// Parse one line of an OpenAI-style SSE stream
+///
+/// Handles the `data: ` prefix, the `[DONE]` terminator, and Azure's
+/// extra chunks (empty `choices` for content-filter results, explicit
+/// `"finish_reason": null` on every delta). Returns `None` for lines
+/// that carry nothing to forward.
+fn parse_openai_sse_line(line: &str) -> Option<StreamChunk> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with(':') {
+        return None;
+    }
+    let line = line.strip_prefix("data:").map(|l| l.trim_start()).unwrap_or(line);
 
-        while let Some(chunk_result) = stream.next().await {
-            let chunk = chunk_result?;
-            let data = String::from_utf8_lossy(&chunk);
-            
-            for line in data.lines() {
-                let line = line.strip_prefix("data: ").unwrap_or(line);
-                
-                if line == "[DONE]" {
-                    let _ = tx.send(StreamChunk {
-                        content: String::new(),
-                        done: true,
-                    }).await;
-                    return Ok(());
-                }
-                
-                if let Ok(value) = serde_json::from_str::<serde_json::Value>(line) {
-                    let content = value
-                        .get("choices")
-                        .and_then(|c| c.get(0))
-                        .and_then(|c| c.get("delta"))
-                        .and_then(|d| d.get("content"))
-                        .and_then(|c| c.as_str())
-                        .unwrap_or("")
-                        .to_string();
-                    
-                    let done = value
-                        .get("choices")
-                        .and_then(|c| c.get(0))
-                        .and_then(|c| c.get("finish_reason"))
-                        .is_some();
-                    
-                    if tx.send(StreamChunk { content, done }).await.is_err() {
-                        return Ok(()); // Receiver dropped
+    if line == "[DONE]" {
+        return Some(StreamChunk {
+            content: String::new(),
+            done: true,
+        });
+    }
+
+    let value = serde_json::from_str::<serde_json::Value>(line).ok()?;
+    let choice = value.get("choices").and_then(|c| c.get(0))?;
+
+    let content = choice
+        .get("delta")
+        .and_then(|d| d.get("content"))
+        .and_then(|c| c.as_str())
+        .unwrap_or("")
+        .to_string();
+
+    let done = choice
+        .get("finish_reason")
+        .and_then(|f| f.as_str())
+        .is_some();
+
+    Some(StreamChunk { content, done })
+}
+
+/// Send a streaming request and forward OpenAI-style SSE chunks to `tx`
+async fn stream_openai_sse(
+    request: reqwest::RequestBuilder,
+    tx: tokio::sync::mpsc::Sender<StreamChunk>,
+) -> Result<()> {
+    use futures_util::StreamExt;
+
+    let resp = request.send().await?;
+
+    if !resp.status().is_success() {
+        return Err(crate::error::DirSoulError::ExternalError(format!(
+            "Stream request failed: {}",
+            resp.status()
+        )));
+    }
+
+    let mut stream = resp.bytes_stream();
+
+    while let Some(chunk_result) = stream.next().await {
+        let chunk = chunk_result?;
+        let data = String::from_utf8_lossy(&chunk);
+
+        for line in data.lines() {
+            if let Some(chunk) = parse_openai_sse_line(line) {
+                let done = chunk.done;
+
+                if tx.send(chunk).await.is_err() {
+                    return Ok(()); // Receiver dropped
+                }
+
+                if done {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// Azure OpenAI Provider Implementation
+// ============================================================================
+
+/// Azure OpenAI provider
+///
+/// Routes requests to a single deployment:
+/// `{endpoint}/openai/deployments/{deployment}/{route}?api-version={version}`
+/// and authenticates with the `api-key` header instead of a Bearer token.
+pub struct AzureOpenAIProvider {
+    client: Client,
+    endpoint: String,
+    api_key: String,
+    deployment: String,
+    api_version: String,
+}
+
+impl AzureOpenAIProvider {
+    /// Create a new Azure OpenAI provider
+    pub fn new(config: AzureConfig) -> Self {
+        Self {
+            client: Client::new(),
+            endpoint: config.endpoint,
+            api_key: config.api_key,
+            deployment: config.deployment,
+            api_version: config.api_version,
+        }
+    }
+
+    /// Build the deployment-scoped URL for a route (e.g. "chat/completions")
+    fn deployment_url(&self, route: &str) -> String {
+        format!(
+            "{}/openai/deployments/{}/{}?api-version={}",
+            self.endpoint.trim_end_matches('/'),
+            self.deployment,
+            route.trim_start_matches('/'),
+            self.api_version
+        )
+    }
+
+    /// Send an embeddings request and return the parsed response
+    async fn request_embeddings(&self, input: serde_json::Value) -> Result<OpenAIEmbeddingResponse> {
+        let response = self
+            .client
+            .post(&self.deployment_url("embeddings"))
+            .header("api-key", &self.api_key)
+            .json(&serde_json::json!({ "input": input }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(crate::error::DirSoulError::ExternalError(format!(
+                "Azure OpenAI embed failed: {} - {}",
+                status,
+                error_text
+            )));
+        }
+
+        Ok(response.json().await?)
+    }
+}
+
+#[async_trait]
+impl LLMProvider for AzureOpenAIProvider {
+    async fn chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<ChatResponse> {
+        let messages = validate_chat_messages(messages)?;
+        let (temperature, max_tokens) =
+            sanitize_sampling(temperature, max_tokens, OPENAI_MAX_TEMPERATURE);
+
+        #[derive(Serialize)]
+        struct ChatRequest {
+            messages: Vec<ChatMessage>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            temperature: Option<f32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            max_tokens: Option<u32>,
+        }
+
+        let request = ChatRequest {
+            messages,
+            temperature,
+            max_tokens,
+        };
+
+        let response = self
+            .client
+            .post(&self.deployment_url("chat/completions"))
+            .header("api-key", &self.api_key)
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(crate::error::DirSoulError::ExternalError(format!(
+                "Azure OpenAI chat failed: {} - {}",
+                status,
+                error_text
+            )));
+        }
+
+        let azure_response: OpenAIChatResponse = response.json().await?;
+        Ok(ChatResponse::OpenAI(azure_response))
+    }
+
+    async fn stream_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<tokio::sync::mpsc::Receiver<StreamChunk>> {
+        let messages = validate_chat_messages(messages)?;
+        let (temperature, max_tokens) =
+            sanitize_sampling(temperature, max_tokens, OPENAI_MAX_TEMPERATURE);
+
+        use tokio::sync::mpsc;
+
+        #[derive(Serialize)]
+        struct ChatRequest {
+            messages: Vec<ChatMessage>,
+            stream: bool,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            temperature: Option<f32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            max_tokens: Option<u32>,
+        }
+
+        let (tx, rx) = mpsc::channel(100);
+
+        let request = ChatRequest {
+            messages,
+            stream: true,
+            temperature,
+            max_tokens,
+        };
+
+        let builder = self
+            .client
+            .post(&self.deployment_url("chat/completions"))
+            .header("api-key", &self.api_key)
+            .json(&request);
+
+        tokio::spawn(async move {
+            if let Err(e) = stream_openai_sse(builder, tx).await {
+                tracing::error!("Azure OpenAI stream error: {:?}", e);
+            }
+        });
+
+        Ok(rx)
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let azure_response = self.request_embeddings(serde_json::json!(text)).await?;
+
+        azure_response
+            .data
+            .into_iter()
+            .find(|d| d.index == 0)
+            .map(|d| Ok(d.embedding))
+            .unwrap_or_else(|| Err(crate::error::DirSoulError::ExternalError("No embedding in response".to_string())))
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let azure_response = self.request_embeddings(serde_json::json!(texts)).await?;
+
+        let mut embeddings = vec![Vec::new(); texts.len()];
+        for data in azure_response.data {
+            if data.index < embeddings.len() {
+                embeddings[data.index] = data.embedding;
+            }
+        }
+
+        Ok(embeddings)
+    }
+
+    fn model_name(&self) -> String {
+        self.deployment.clone()
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        let url = format!(
+            "{}/openai/models?api-version={}",
+            self.endpoint.trim_end_matches('/'),
+            self.api_version
+        );
+        let response = self
+            .client
+            .get(&url)
+            .header("api-key", &self.api_key)
+            .send()
+            .await?;
+        Ok(response.status().is_success())
+    }
+}
+
+// ============================================================================
+// Dual Model Provider
+// ============================================================================
+
+/// Routes chat calls to the inference provider and embed calls to the
+/// embedding provider
+pub struct DualModelProvider {
+    chat: Arc<dyn LLMProvider>,
+    embedding: Arc<dyn LLMProvider>,
+}
+
+impl DualModelProvider {
+    /// Combine a chat provider and an embedding provider
+    pub fn new(chat: Arc<dyn LLMProvider>, embedding: Arc<dyn LLMProvider>) -> Self {
+        Self { chat, embedding }
+    }
+
+    /// Provider used for chat/inference
+    pub fn chat_provider(&self) -> Arc<dyn LLMProvider> {
+        Arc::clone(&self.chat)
+    }
+
+    /// Provider used for embeddings
+    pub fn embedding_provider(&self) -> Arc<dyn LLMProvider> {
+        Arc::clone(&self.embedding)
+    }
+}
+
+#[async_trait]
+impl LLMProvider for DualModelProvider {
+    async fn chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<ChatResponse> {
+        self.chat.chat(messages, temperature, max_tokens).await
+    }
+
+    async fn stream_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<tokio::sync::mpsc::Receiver<StreamChunk>> {
+        self.chat.stream_chat(messages, temperature, max_tokens).await
+    }
+
+    async fn generate(&self, prompt: &str, options: GenerateOptions) -> Result<String> {
+        self.chat.generate(prompt, options).await
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.embedding.embed(text).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embedding.embed_batch(texts).await
+    }
+
+    fn embed_batch_fan_out(&self) -> usize {
+        self.embedding.embed_batch_fan_out()
+    }
+
+    /// Reports the chat model; use `embedding_provider()` for the other one
+    fn model_name(&self) -> String {
+        self.chat.model_name()
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        Ok(self.chat.health_check().await? && self.embedding.health_check().await?)
+    }
+}
+
+// ============================================================================
+// Circuit Breaker Provider
+// ============================================================================
+
+/// Breaker bookkeeping shared by all calls through one wrapper
+struct BreakerState {
+    breaker: CircuitBreaker,
+    consecutive_failures: u32,
+    /// A trial call is in flight after the cooldown (half-open)
+    probing: bool,
+    /// A trial was cancelled before it finished, so the next call is a trial
+    trial_due: bool,
+}
+
+/// One call let through the breaker, settled by `after_call`
+///
+/// If the caller's future is dropped mid-call (a timeout or a disconnected
+/// client), `after_call` never runs. Dropping an unsettled trial then
+/// releases the half-open slot, so later calls are not fast-failed forever.
+struct BreakerCall<'a> {
+    state: &'a Mutex<BreakerState>,
+    probing: bool,
+}
+
+impl Drop for BreakerCall<'_> {
+    fn drop(&mut self) {
+        if self.probing {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.probing = false;
+            state.trial_due = true;
+        }
+    }
+}
+
+/// Wraps a provider so a dead backend fails fast instead of hanging every call
+///
+/// After `failure_threshold` consecutive failures the breaker opens and
+/// `chat`, `stream_chat`, `generate`, `embed` and `embed_batch` return an
+/// error without touching the inner provider. Once the cooldown has passed a
+/// single trial call is let through (half-open): success closes the breaker,
+/// failure opens it again for another cooldown. A trial whose caller is
+/// cancelled gives no verdict, so the next call becomes the trial. Only
+/// `retryable()` errors count as backend failures, so bad input does not
+/// open the breaker, and `health_check` always reaches the backend.
+pub struct CircuitBreakerProvider<P: LLMProvider + ?Sized> {
+    inner: Arc<P>,
+    failure_threshold: u32,
+    cooldown_sec: u64,
+    state: Mutex<BreakerState>,
+}
+
+impl<P: LLMProvider + ?Sized> CircuitBreakerProvider<P> {
+    /// Wrap `inner` with the given breaker settings
+    pub fn new(inner: Arc<P>, config: CircuitBreakerConfig) -> Self {
+        Self {
+            inner,
+            failure_threshold: config.failure_threshold.max(1),
+            cooldown_sec: config.cooldown_sec,
+            state: Mutex::new(BreakerState {
+                breaker: CircuitBreaker::new(config.cooldown_sec),
+                consecutive_failures: 0,
+                probing: false,
+                trial_due: false,
+            }),
+        }
+    }
+
+    /// Whether calls are currently being fast-failed
+    pub fn is_open(&self) -> bool {
+        let state = self.lock_state();
+        state.breaker.is_open() || state.probing
+    }
+
+    /// The wrapped provider
+    pub fn inner(&self) -> Arc<P> {
+        Arc::clone(&self.inner)
+    }
+
+    /// Lock the breaker state, recovering it when a call panicked holding it
+    ///
+    /// Every critical section leaves the state consistent, so a poisoned
+    /// lock must not turn into a panic for every later provider call.
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn before_call(&self) -> Result<BreakerCall<'_>> {
+        let mut state = self.lock_state();
+        let open_error = || {
+            crate::error::DirSoulError::ExternalError(format!(
+                "Circuit breaker open for model '{}': backend unavailable, retrying after {}s cooldown",
+                self.inner.model_name(),
+                self.cooldown_sec
+            ))
+        };
+
+        if state.probing {
+            return Err(open_error());
+        }
+        let mut probing = false;
+        if state.breaker.is_open() || state.trial_due {
+            if !state.breaker.allow_task() {
+                return Err(open_error());
+            }
+            state.trial_due = false;
+            state.probing = true;
+            probing = true;
+        }
+        Ok(BreakerCall {
+            state: &self.state,
+            probing,
+        })
+    }
+
+    fn after_call<T>(&self, mut call: BreakerCall<'_>, result: &Result<T>) {
+        // Settled here, so dropping the call no longer releases the trial
+        call.probing = false;
+        let mut state = self.lock_state();
+        let was_probing = std::mem::replace(&mut state.probing, false);
+
+        match result {
+            // Only transient failures count towards opening the breaker
+            Err(e) if e.retryable() => {
+                state.consecutive_failures += 1;
+                if was_probing || state.consecutive_failures >= self.failure_threshold {
+                    tracing::warn!(
+                        "Opening circuit breaker for model '{}' after {} consecutive failures: {}",
+                        self.inner.model_name(),
+                        state.consecutive_failures,
+                        e
+                    );
+                    state.breaker.trip();
+                    state.consecutive_failures = 0;
+                }
+            }
+            _ => {
+                state.consecutive_failures = 0;
+                if was_probing {
+                    state.breaker.reset();
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<P: LLMProvider + ?Sized> LLMProvider for CircuitBreakerProvider<P> {
+    async fn chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<ChatResponse> {
+        let call = self.before_call()?;
+        let result = self.inner.chat(messages, temperature, max_tokens).await;
+        self.after_call(call, &result);
+        result
+    }
+
+    async fn stream_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<tokio::sync::mpsc::Receiver<StreamChunk>> {
+        let call = self.before_call()?;
+        let result = self.inner.stream_chat(messages, temperature, max_tokens).await;
+        self.after_call(call, &result);
+        result
+    }
+
+    async fn generate(&self, prompt: &str, options: GenerateOptions) -> Result<String> {
+        let call = self.before_call()?;
+        let result = self.inner.generate(prompt, options).await;
+        self.after_call(call, &result);
+        result
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let call = self.before_call()?;
+        let result = self.inner.embed(text).await;
+        self.after_call(call, &result);
+        result
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let call = self.before_call()?;
+        let result = self.inner.embed_batch(texts).await;
+        self.after_call(call, &result);
+        result
+    }
+
+    fn embed_batch_fan_out(&self) -> usize {
+        self.inner.embed_batch_fan_out()
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        self.inner.health_check().await
+    }
+}
+
+// ============================================================================
+// Response Filter Provider
+// ============================================================================
+
+/// Remove `<tag>...</tag>` blocks for each configured tag name
+///
+/// Whitespace right after a removed block is dropped too, so
+/// "<think>...</think>\n\nAnswer" becomes "Answer". A block left open at the
+/// end (a truncated reasoning trace) is removed to the end of the text.
+pub fn strip_wrapper_tags(text: &str, tags: &[String]) -> String {
+    let mut stripper = TagStripper::new(tags);
+    let mut out = stripper.push(text);
+    out.push_str(&stripper.finish());
+    out
+}
+
+/// Incremental `strip_wrapper_tags` for streamed text
+///
+/// Text that might be the start of a tag ("<thi") is held back until the
+/// next chunk decides it, so partial tags never reach the caller.
+#[derive(Debug, Clone)]
+pub struct TagStripper {
+    /// (opening, closing) tag pairs
+    tags: Vec<(String, String)>,
+    /// Undecided text carried over to the next chunk
+    pending: String,
+    /// Index of the block currently being skipped
+    inside: Option<usize>,
+    /// Drop whitespace until the next visible character
+    trim_leading: bool,
+}
+
+impl TagStripper {
+    /// Strip blocks for the given tag names ("think" → `<think>...</think>`)
+    pub fn new(tags: &[String]) -> Self {
+        Self {
+            tags: tags
+                .iter()
+                .filter(|tag| !tag.trim().is_empty())
+                .map(|tag| (format!("<{}>", tag.trim()), format!("</{}>", tag.trim())))
+                .collect(),
+            pending: String::new(),
+            inside: None,
+            trim_leading: false,
+        }
+    }
+
+    /// Feed a chunk and return the text that is safe to emit
+    pub fn push(&mut self, chunk: &str) -> String {
+        self.pending.push_str(chunk);
+        let mut out = String::new();
+
+        loop {
+            if let Some(index) = self.inside {
+                let close = &self.tags[index].1;
+                match self.pending.find(close.as_str()) {
+                    Some(pos) => {
+                        self.pending.drain(..pos + close.len());
+                        self.inside = None;
+                        self.trim_leading = true;
                     }
-                    
-                    if done {
-                        return Ok(());
+                    None => {
+                        // Keep only what could be the start of the closing tag
+                        let keep = partial_tag_start(&self.pending, std::iter::once(close.as_str()));
+                        self.pending.drain(..keep);
+                        return out;
+                    }
+                }
+            } else {
+                let next_open = self
+                    .tags
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, (open, _))| self.pending.find(open.as_str()).map(|pos| (pos, index)))
+                    .min();
+                match next_open {
+                    Some((pos, index)) => {
+                        self.emit(&mut out, pos);
+                        self.pending.drain(..self.tags[index].0.len());
+                        self.inside = Some(index);
                     }
+                    None => {
+                        let keep = partial_tag_start(&self.pending, self.tags.iter().map(|(open, _)| open.as_str()));
+                        self.emit(&mut out, keep);
+                        return out;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Flush held-back text once the stream ends
+    ///
+    /// An unfinished block is discarded; an unfinished tag is plain text.
+    pub fn finish(&mut self) -> String {
+        let mut out = String::new();
+        if self.inside.take().is_none() {
+            let end = self.pending.len();
+            self.emit(&mut out, end);
+        }
+        self.pending.clear();
+        out
+    }
+
+    /// Move `pending[..end]` to `out`, honoring `trim_leading`
+    fn emit(&mut self, out: &mut String, end: usize) {
+        let text: String = self.pending.drain(..end).collect();
+        if self.trim_leading {
+            let trimmed = text.trim_start();
+            if !trimmed.is_empty() {
+                self.trim_leading = false;
+            }
+            out.push_str(trimmed);
+        } else {
+            out.push_str(&text);
+        }
+    }
+}
+
+/// Byte offset of a trailing `<...` in `text` that is a proper prefix of one
+/// of `tags`, or `text.len()` when there is none
+fn partial_tag_start<'a>(text: &str, tags: impl Iterator<Item = &'a str> + Clone) -> usize {
+    text.match_indices('<')
+        .map(|(pos, _)| pos)
+        .find(|&pos| {
+            let tail = &text[pos..];
+            tags.clone().any(|tag| tag.len() > tail.len() && tag.starts_with(tail))
+        })
+        .unwrap_or(text.len())
+}
+
+/// Wraps a provider so responses come back without reasoning blocks
+///
+/// `chat` responses are rewritten in place, so `extract_response_text` sees
+/// clean text, and `generate` output is stripped the same way; `stream_chat`
+/// output is filtered chunk by chunk with a `TagStripper`. Embeddings pass
+/// through untouched.
+pub struct ResponseFilterProvider<P: LLMProvider + ?Sized> {
+    inner: Arc<P>,
+    tags: Vec<String>,
+}
+
+impl<P: LLMProvider + ?Sized> ResponseFilterProvider<P> {
+    /// Wrap `inner`, stripping the configured tags
+    pub fn new(inner: Arc<P>, config: ResponseFilterConfig) -> Self {
+        Self {
+            inner,
+            tags: config.tags,
+        }
+    }
+
+    /// The wrapped provider
+    pub fn inner(&self) -> Arc<P> {
+        Arc::clone(&self.inner)
+    }
+
+    fn filter_response(&self, response: ChatResponse) -> ChatResponse {
+        match response {
+            ChatResponse::Ollama(mut ollama) => {
+                ollama.response = strip_wrapper_tags(&ollama.response, &self.tags);
+                ChatResponse::Ollama(ollama)
+            }
+            ChatResponse::OpenAI(mut openai) => {
+                for choice in &mut openai.choices {
+                    choice.message.content = strip_wrapper_tags(&choice.message.content, &self.tags);
+                }
+                ChatResponse::OpenAI(openai)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<P: LLMProvider + ?Sized> LLMProvider for ResponseFilterProvider<P> {
+    async fn chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<ChatResponse> {
+        let response = self.inner.chat(messages, temperature, max_tokens).await?;
+        Ok(self.filter_response(response))
+    }
+
+    async fn stream_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<tokio::sync::mpsc::Receiver<StreamChunk>> {
+        let mut inner_rx = self.inner.stream_chat(messages, temperature, max_tokens).await?;
+        let (tx, rx) = tokio::sync::mpsc::channel(100);
+        let mut stripper = TagStripper::new(&self.tags);
+
+        tokio::spawn(async move {
+            while let Some(chunk) = inner_rx.recv().await {
+                let mut content = stripper.push(&chunk.content);
+                if chunk.done {
+                    content.push_str(&stripper.finish());
+                }
+                // Chunks swallowed entirely by a block are not forwarded
+                if content.is_empty() && !chunk.done {
+                    continue;
+                }
+                if tx.send(StreamChunk { content, done: chunk.done }).await.is_err() || chunk.done {
+                    return;
+                }
+            }
+
+            // Inner stream ended without a final chunk
+            let content = stripper.finish();
+            if !content.is_empty() {
+                let _ = tx.send(StreamChunk { content, done: false }).await;
+            }
+        });
+
+        Ok(rx)
+    }
+
+    async fn generate(&self, prompt: &str, options: GenerateOptions) -> Result<String> {
+        let text = self.inner.generate(prompt, options).await?;
+        Ok(strip_wrapper_tags(&text, &self.tags))
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.inner.embed(text).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.inner.embed_batch(texts).await
+    }
+
+    fn embed_batch_fan_out(&self) -> usize {
+        self.inner.embed_batch_fan_out()
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        self.inner.health_check().await
+    }
+}
+
+// ============================================================================
+// Logging Provider
+// ============================================================================
+
+/// How message text appears in logged payloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Redaction {
+    /// Replace with a short SHA-256 digest and the length, so equal texts
+    /// can still be matched across log lines
+    #[default]
+    Hash,
+    /// Keep the first N characters
+    Truncate(usize),
+    /// Log text unchanged
+    None,
+}
+
+impl Redaction {
+    /// Parse "hash", "truncate", "truncate:N" or "none"
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        match value.as_str() {
+            "hash" => Ok(Redaction::Hash),
+            "none" => Ok(Redaction::None),
+            "truncate" => Ok(Redaction::Truncate(DEFAULT_REDACTION_TRUNCATE_CHARS)),
+            _ => value
+                .strip_prefix("truncate:")
+                .and_then(|n| n.parse().ok())
+                .map(Redaction::Truncate)
+                .ok_or_else(|| {
+                    crate::error::DirSoulError::Config(format!("Unknown payload redaction: {}", value))
+                }),
+        }
+    }
+
+    /// Redacted form of `text`
+    pub fn apply(&self, text: &str) -> String {
+        match *self {
+            Redaction::Hash => {
+                use sha2::{Digest, Sha256};
+                let digest: String = Sha256::digest(text.as_bytes())
+                    .iter()
+                    .take(6)
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect();
+                format!("[sha256:{} len={}]", digest, text.chars().count())
+            }
+            Redaction::Truncate(keep) => {
+                let len = text.chars().count();
+                if len <= keep {
+                    return text.to_string();
+                }
+                let kept: String = text.chars().take(keep).collect();
+                format!("{}…[+{} chars]", kept, len - keep)
+            }
+            Redaction::None => text.to_string(),
+        }
+    }
+
+    /// Redact the text fields of a payload in place
+    ///
+    /// Strings under `content`, `prompt`, `response` and `input` (including
+    /// arrays of them) are replaced; keys, roles, models and numbers are
+    /// kept, so the logged payload keeps its shape.
+    pub fn redact_payload(&self, payload: &mut serde_json::Value) {
+        self.redact_value(payload, false);
+    }
+
+    fn redact_value(&self, value: &mut serde_json::Value, is_text: bool) {
+        match value {
+            serde_json::Value::String(text) if is_text => *text = self.apply(text),
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    self.redact_value(item, is_text);
+                }
+            }
+            serde_json::Value::Object(fields) => {
+                for (key, item) in fields.iter_mut() {
+                    self.redact_value(item, is_text || REDACTED_PAYLOAD_KEYS.contains(&key.as_str()));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Settings for `LoggingProvider`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PayloadLogging {
+    /// Log request and response payloads at TRACE (default: off)
+    pub log_payloads: bool,
+
+    /// Redaction applied to payload text
+    pub redaction: Redaction,
+}
+
+impl PayloadLogging {
+    /// Read `DIRSOUL_LLM_LOG_PAYLOADS` and `DIRSOUL_LLM_LOG_REDACTION`
+    pub fn from_env() -> Self {
+        Self::from_values(
+            std::env::var(LOG_PAYLOADS_ENV).ok().as_deref(),
+            std::env::var(LOG_REDACTION_ENV).ok().as_deref(),
+        )
+    }
+
+    /// An unknown redaction falls back to `Hash` rather than logging raw text
+    fn from_values(log_payloads: Option<&str>, redaction: Option<&str>) -> Self {
+        let log_payloads = matches!(
+            log_payloads.map(|v| v.trim().to_ascii_lowercase()).as_deref(),
+            Some("1" | "true" | "yes" | "on")
+        );
+        let redaction = match redaction.map(Redaction::parse) {
+            Some(Ok(redaction)) => redaction,
+            Some(Err(e)) => {
+                tracing::warn!("{}; hashing logged payloads instead", e);
+                Redaction::Hash
+            }
+            None => Redaction::Hash,
+        };
+        Self {
+            log_payloads,
+            redaction,
+        }
+    }
+}
+
+/// (prompt, completion) token counts reported by the backend
+fn token_usage(response: &ChatResponse) -> (Option<u32>, Option<u32>) {
+    match response {
+        ChatResponse::Ollama(ollama) => (ollama.prompt_eval_count, ollama.eval_count),
+        ChatResponse::OpenAI(openai) => match &openai.usage {
+            Some(usage) => (Some(usage.prompt_tokens), Some(usage.completion_tokens)),
+            None => (None, None),
+        },
+    }
+}
+
+/// Loggable form of a chat response
+fn chat_response_payload(response: &ChatResponse) -> serde_json::Value {
+    match response {
+        ChatResponse::Ollama(ollama) => serde_json::json!({
+            "response": ollama.response,
+            "done": ollama.done,
+            "prompt_eval_count": ollama.prompt_eval_count,
+            "eval_count": ollama.eval_count,
+        }),
+        ChatResponse::OpenAI(openai) => serde_json::json!({
+            "id": openai.id,
+            "model": openai.model,
+            "choices": openai.choices.iter().map(|choice| serde_json::json!({
+                "index": choice.index,
+                "message": { "role": choice.message.role, "content": choice.message.content },
+                "finish_reason": choice.finish_reason,
+            })).collect::<Vec<_>>(),
+            "usage": openai.usage.as_ref().map(|usage| serde_json::json!({
+                "prompt_tokens": usage.prompt_tokens,
+                "completion_tokens": usage.completion_tokens,
+                "total_tokens": usage.total_tokens,
+            })),
+        }),
+    }
+}
+
+/// Wraps a provider to log every call
+///
+/// Status, latency and token usage (when the backend reports it) are always
+/// logged at DEBUG. With `log_payloads` on, request and response payloads
+/// are also logged at TRACE, with message text redacted per `redaction`.
+/// Embedding vectors are never logged, only their count and size; streamed
+/// chunks are not logged.
+pub struct LoggingProvider<P: LLMProvider + ?Sized> {
+    inner: Arc<P>,
+    config: PayloadLogging,
+}
+
+impl<P: LLMProvider + ?Sized> LoggingProvider<P> {
+    /// Wrap `inner` with the given payload settings
+    pub fn new(inner: Arc<P>, config: PayloadLogging) -> Self {
+        Self { inner, config }
+    }
+
+    /// The wrapped provider
+    pub fn inner(&self) -> Arc<P> {
+        Arc::clone(&self.inner)
+    }
+
+    /// Log a payload at TRACE; `payload` is only built when it will be logged
+    fn trace_payload(&self, operation: &str, direction: &str, payload: impl FnOnce() -> serde_json::Value) {
+        if !self.config.log_payloads || !tracing::enabled!(tracing::Level::TRACE) {
+            return;
+        }
+        let mut payload = payload();
+        self.config.redaction.redact_payload(&mut payload);
+        tracing::trace!("LLM {} {} {}: {}", self.inner.model_name(), operation, direction, payload);
+    }
+
+    /// Log the status, latency and token usage of a call at DEBUG
+    fn debug_outcome<T>(
+        &self,
+        operation: &str,
+        started: std::time::Instant,
+        result: &Result<T>,
+        tokens: (Option<u32>, Option<u32>),
+    ) {
+        let model = self.inner.model_name();
+        let elapsed_ms = started.elapsed().as_millis();
+        let count = |n: Option<u32>| n.map_or_else(|| "-".to_string(), |n| n.to_string());
+        match result {
+            Ok(_) => tracing::debug!(
+                "LLM {} {}: ok in {} ms (prompt_tokens={}, completion_tokens={})",
+                model,
+                operation,
+                elapsed_ms,
+                count(tokens.0),
+                count(tokens.1)
+            ),
+            Err(e) => tracing::debug!("LLM {} {}: error in {} ms: {}", model, operation, elapsed_ms, e),
+        }
+    }
+}
+
+#[async_trait]
+impl<P: LLMProvider + ?Sized> LLMProvider for LoggingProvider<P> {
+    async fn chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<ChatResponse> {
+        self.trace_payload("chat", "request", || {
+            serde_json::json!({
+                "messages": messages,
+                "temperature": temperature,
+                "max_tokens": max_tokens,
+            })
+        });
+
+        let started = std::time::Instant::now();
+        let result = self.inner.chat(messages, temperature, max_tokens).await;
+        let tokens = result.as_ref().map(token_usage).unwrap_or((None, None));
+        self.debug_outcome("chat", started, &result, tokens);
+        if let Ok(response) = &result {
+            self.trace_payload("chat", "response", || chat_response_payload(response));
+        }
+        result
+    }
+
+    async fn stream_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<tokio::sync::mpsc::Receiver<StreamChunk>> {
+        self.trace_payload("stream_chat", "request", || {
+            serde_json::json!({
+                "messages": messages,
+                "temperature": temperature,
+                "max_tokens": max_tokens,
+            })
+        });
+
+        // Latency up to the stream being opened
+        let started = std::time::Instant::now();
+        let result = self.inner.stream_chat(messages, temperature, max_tokens).await;
+        self.debug_outcome("stream_chat", started, &result, (None, None));
+        result
+    }
+
+    async fn generate(&self, prompt: &str, options: GenerateOptions) -> Result<String> {
+        self.trace_payload("generate", "request", || {
+            serde_json::json!({
+                "prompt": prompt,
+                "temperature": options.temperature,
+                "max_tokens": options.max_tokens,
+            })
+        });
+
+        let started = std::time::Instant::now();
+        let result = self.inner.generate(prompt, options).await;
+        self.debug_outcome("generate", started, &result, (None, None));
+        if let Ok(text) = &result {
+            self.trace_payload("generate", "response", || serde_json::json!({ "response": text }));
+        }
+        result
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.trace_payload("embed", "request", || serde_json::json!({ "input": text }));
+
+        let started = std::time::Instant::now();
+        let result = self.inner.embed(text).await;
+        self.debug_outcome("embed", started, &result, (None, None));
+        if let Ok(vector) = &result {
+            self.trace_payload("embed", "response", || serde_json::json!({ "dimensions": vector.len() }));
+        }
+        result
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.trace_payload("embed_batch", "request", || serde_json::json!({ "input": texts }));
+
+        let started = std::time::Instant::now();
+        let result = self.inner.embed_batch(texts).await;
+        self.debug_outcome("embed_batch", started, &result, (None, None));
+        if let Ok(vectors) = &result {
+            self.trace_payload("embed_batch", "response", || {
+                serde_json::json!({
+                    "count": vectors.len(),
+                    "dimensions": vectors.first().map(|v| v.len()),
+                })
+            });
+        }
+        result
+    }
+
+    fn embed_batch_fan_out(&self) -> usize {
+        self.inner.embed_batch_fan_out()
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        self.inner.health_check().await
+    }
+}
+
+// ============================================================================
+// LLM Concurrency Governor
+// ============================================================================
+
+/// Global cap on in-flight LLM calls
+///
+/// Clones share one semaphore, so live chat, extraction and embedding
+/// jobs handed the same governor never exceed `max_in_flight` calls
+/// together. Waiters are served in arrival order, so neither background
+/// jobs nor interactive requests can starve the other.
+#[derive(Debug, Clone)]
+pub struct LlmGovernor {
+    permits: Arc<tokio::sync::Semaphore>,
+    max_in_flight: usize,
+}
+
+impl LlmGovernor {
+    /// Governor allowing `max_in_flight` concurrent calls (at least 1)
+    pub fn new(max_in_flight: usize) -> Result<Self> {
+        if max_in_flight == 0 {
+            return Err(crate::error::DirSoulError::Config(
+                "max_concurrent_requests must be at least 1".to_string(),
+            ));
+        }
+        Ok(Self {
+            permits: Arc::new(tokio::sync::Semaphore::new(max_in_flight)),
+            max_in_flight,
+        })
+    }
+
+    /// Configured cap
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    /// Calls currently holding a permit
+    pub fn in_flight(&self) -> usize {
+        self.max_in_flight - self.permits.available_permits()
+    }
+
+    /// Wait for a permit; the call counts as in flight until it is dropped
+    pub async fn acquire(&self) -> Result<tokio::sync::OwnedSemaphorePermit> {
+        Arc::clone(&self.permits).acquire_owned().await.map_err(|_| {
+            crate::error::DirSoulError::ExternalError("LLM concurrency governor closed".to_string())
+        })
+    }
+
+    /// Wrap `provider` so every call goes through this governor
+    pub fn govern(&self, provider: Arc<dyn LLMProvider>) -> Arc<dyn LLMProvider> {
+        Arc::new(GovernedProvider::new(provider, self.clone()))
+    }
+}
+
+/// Wraps a provider to hold an `LlmGovernor` permit for every call
+///
+/// A streamed chat keeps its permit until the stream ends or is dropped.
+/// `embed_batch` holds one permit per backend request: for a backend that
+/// fans a batch out into single requests (such as Ollama's
+/// `embed_concurrency`) the wrapper sends those requests itself. Health
+/// checks are not limited, so probes never queue behind model calls.
+///
+/// Wrap a provider once; nesting two wrappers around one governor takes
+/// two permits per call and deadlocks at a cap of 1.
+pub struct GovernedProvider<P: LLMProvider + ?Sized> {
+    inner: Arc<P>,
+    governor: LlmGovernor,
+}
+
+impl<P: LLMProvider + ?Sized> GovernedProvider<P> {
+    /// Wrap `inner`, sharing `governor`'s permits
+    pub fn new(inner: Arc<P>, governor: LlmGovernor) -> Self {
+        Self { inner, governor }
+    }
+
+    /// The shared governor
+    pub fn governor(&self) -> &LlmGovernor {
+        &self.governor
+    }
+}
+
+#[async_trait]
+impl<P: LLMProvider + ?Sized> LLMProvider for GovernedProvider<P> {
+    async fn chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<ChatResponse> {
+        let _permit = self.governor.acquire().await?;
+        self.inner.chat(messages, temperature, max_tokens).await
+    }
+
+    async fn stream_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Result<tokio::sync::mpsc::Receiver<StreamChunk>> {
+        let permit = self.governor.acquire().await?;
+        let mut inner_rx = self.inner.stream_chat(messages, temperature, max_tokens).await?;
+        let (tx, rx) = tokio::sync::mpsc::channel(100);
+
+        tokio::spawn(async move {
+            let _permit = permit;
+            while let Some(chunk) = inner_rx.recv().await {
+                let done = chunk.done;
+                if tx.send(chunk).await.is_err() || done {
+                    return;
+                }
+            }
+        });
+
+        Ok(rx)
+    }
+
+    async fn generate(&self, prompt: &str, options: GenerateOptions) -> Result<String> {
+        let _permit = self.governor.acquire().await?;
+        self.inner.generate(prompt, options).await
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let _permit = self.governor.acquire().await?;
+        self.inner.embed(text).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        use futures_util::stream::{self, StreamExt};
+
+        let fan_out = self.inner.embed_batch_fan_out();
+        if fan_out <= 1 {
+            let _permit = self.governor.acquire().await?;
+            return self.inner.embed_batch(texts).await;
+        }
+
+        // Send the backend's single requests here so each holds a permit
+        let mut embeddings = vec![Vec::new(); texts.len()];
+        let mut pending = stream::iter(texts.iter().enumerate())
+            .map(|(index, text)| async move {
+                let _permit = self.governor.acquire().await?;
+                self.inner.embed(text).await.map(|embedding| (index, embedding))
+            })
+            .buffer_unordered(fan_out);
+        while let Some(result) = pending.next().await {
+            let (index, embedding) = result?;
+            embeddings[index] = embedding;
+        }
+        Ok(embeddings)
+    }
+
+    fn embed_batch_fan_out(&self) -> usize {
+        self.inner.embed_batch_fan_out()
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        self.inner.health_check().await
+    }
+}
+
+// ============================================================================
+// Model Provider Factory
+// ============================================================================
+
+/// Factory for creating LLM providers from configuration
+///
+/// # Example
+/// See `OllamaProvider` and `OpenAICompatibleProvider` for direct usage examples.
+pub struct ModelProviderFactory;
+
+impl ModelProviderFactory {
+    /// Create an LLM provider from configuration
+    ///
+    /// The backend is wrapped in a `LoggingProvider` configured from the
+    /// environment (see `PayloadLogging::from_env`), so logged payloads are
+    /// what the backend actually returned. On top of that it is wrapped in a
+    /// `ResponseFilterProvider` when `response_filter` is configured, and in
+    /// a `CircuitBreakerProvider` when `circuit_breaker` is configured.
+    pub fn create_provider(config: ModelConfig) -> Result<Arc<dyn LLMProvider>> {
+        let breaker = config.circuit_breaker.clone();
+        let filter = config.response_filter.clone();
+        let backend = Self::create_backend(config)?;
+        let mut provider: Arc<dyn LLMProvider> =
+            Arc::new(LoggingProvider::new(backend, PayloadLogging::from_env()));
+        if let Some(filter) = filter {
+            provider = Arc::new(ResponseFilterProvider::new(provider, filter));
+        }
+        Ok(match breaker {
+            Some(breaker) => Arc::new(CircuitBreakerProvider::new(provider, breaker)),
+            None => provider,
+        })
+    }
+
+    fn create_backend(config: ModelConfig) -> Result<Arc<dyn LLMProvider>> {
+        match config.provider.as_str() {
+            "ollama" => {
+                let ollama_config = config.ollama.unwrap_or_default();
+                Ok(Arc::new(OllamaProvider::from_config(config.model, ollama_config)))
+            }
+            "openai_compatible" => {
+                let api_config = config
+                    .openai_compatible
+                    .ok_or_else(|| crate::error::DirSoulError::Config(format!("Missing openai_compatible configuration")))?;
+                let mut provider = OpenAICompatibleProvider::new(
+                    api_config.base_url,
+                    api_config.api_key,
+                    config.model,
+                );
+                if let Some(prefix) = api_config.api_path_prefix {
+                    provider = provider.with_api_path_prefix(prefix);
+                }
+                Ok(Arc::new(provider))
+            }
+            "azure_openai" => {
+                let azure_config = config
+                    .azure_openai
+                    .ok_or_else(|| crate::error::DirSoulError::Config("Missing azure_openai configuration".to_string()))?;
+                Ok(Arc::new(AzureOpenAIProvider::new(azure_config)))
+            }
+            _ => Err(crate::error::DirSoulError::Config(format!("Unknown provider: {}", config.provider))),
+        }
+    }
+
+    /// Create a provider that routes chat to `inference` and embeddings to `embedding`
+    pub fn create_dual_provider(config: ModelsConfig) -> Result<Arc<DualModelProvider>> {
+        let chat = Self::create_provider(config.inference)?;
+        let embedding = Self::create_provider(config.embedding)?;
+        Ok(Arc::new(DualModelProvider::new(chat, embedding)))
+    }
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self {
+            host: default_ollama_host(),
+            embed_concurrency: None,
+            keep_alive: None,
+        }
+    }
+}
+
+// ============================================================================
+// Test Support
+// ============================================================================
+
+/// Configurable in-memory provider shared by the unit tests
+#[cfg(test)]
+pub(crate) mod mock {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::sync::Notify;
+
+    /// One `chat` request seen by the mock (`generate` arrives as a chat)
+    #[derive(Debug, Clone)]
+    pub(crate) struct MockCall {
+        pub messages: Vec<ChatMessage>,
+        pub options: GenerateOptions,
+    }
+
+    impl MockCall {
+        /// Message contents joined by newlines
+        pub(crate) fn prompt(&self) -> String {
+            self.messages.iter().map(|m| m.content.as_str()).collect::<Vec<_>>().join("\n")
+        }
+    }
+
+    /// Provider that answers with a fixed reply and records what it is sent
+    ///
+    /// `generate` keeps the trait default, so every text request is recorded
+    /// as a chat call. Failure, stalling, gating and latency can be switched
+    /// on to exercise the wrappers and callers around a provider.
+    pub(crate) struct MockProvider {
+        model: String,
+        reply: String,
+        embedding: Vec<f32>,
+        /// Reported `embed_batch_fan_out`; 0 and 1 act as a batch endpoint
+        fan_out: usize,
+        latency: Duration,
+        /// Every request waits for the test to release it
+        gate: Option<(Arc<Notify>, Arc<Notify>)>,
+        healthy: AtomicBool,
+        /// Requests and health checks never answer
+        stalled: AtomicBool,
+        requests: AtomicUsize,
+        current: AtomicUsize,
+        peak: AtomicUsize,
+        calls: Mutex<Vec<MockCall>>,
+        embedded: Mutex<Vec<String>>,
+    }
+
+    impl Default for MockProvider {
+        fn default() -> Self {
+            Self {
+                model: "mock".to_string(),
+                reply: "Mock response".to_string(),
+                embedding: vec![0.0; 512],
+                fan_out: 1,
+                latency: Duration::ZERO,
+                gate: None,
+                healthy: AtomicBool::new(true),
+                stalled: AtomicBool::new(false),
+                requests: AtomicUsize::new(0),
+                current: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+                calls: Mutex::new(Vec::new()),
+                embedded: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl MockProvider {
+        pub(crate) fn new() -> Self {
+            Self::default()
+        }
+
+        pub(crate) fn with_model(mut self, model: &str) -> Self {
+            self.model = model.to_string();
+            self
+        }
+
+        pub(crate) fn with_reply(mut self, reply: &str) -> Self {
+            self.reply = reply.to_string();
+            self
+        }
+
+        pub(crate) fn with_embedding(mut self, embedding: Vec<f32>) -> Self {
+            self.embedding = embedding;
+            self
+        }
+
+        /// Answer `embed_batch` with this many concurrent single requests
+        pub(crate) fn with_fan_out(mut self, fan_out: usize) -> Self {
+            self.fan_out = fan_out;
+            self
+        }
+
+        /// Hold each request open for `latency`
+        pub(crate) fn with_latency(mut self, latency: Duration) -> Self {
+            self.latency = latency;
+            self
+        }
+
+        /// Notify `entered` when a request starts and wait for `release`
+        pub(crate) fn with_gate(mut self, entered: Arc<Notify>, release: Arc<Notify>) -> Self {
+            self.gate = Some((entered, release));
+            self
+        }
+
+        /// Start with every request failing and health checks reporting down
+        pub(crate) fn failing(self) -> Self {
+            self.set_healthy(false);
+            self
+        }
+
+        /// Start with every request and health check hanging
+        pub(crate) fn stalled(self) -> Self {
+            self.set_stalled(true);
+            self
+        }
+
+        pub(crate) fn set_healthy(&self, healthy: bool) {
+            self.healthy.store(healthy, Ordering::SeqCst);
+        }
+
+        pub(crate) fn set_stalled(&self, stalled: bool) {
+            self.stalled.store(stalled, Ordering::SeqCst);
+        }
+
+        /// Requests that reached the backend, failed ones included
+        pub(crate) fn requests(&self) -> usize {
+            self.requests.load(Ordering::SeqCst)
+        }
+
+        /// Most requests that were in flight at once
+        pub(crate) fn peak(&self) -> usize {
+            self.peak.load(Ordering::SeqCst)
+        }
+
+        pub(crate) fn calls(&self) -> Vec<MockCall> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        /// Texts passed to `embed` and `embed_batch`, in order
+        pub(crate) fn embedded(&self) -> Vec<String> {
+            self.embedded.lock().unwrap().clone()
+        }
+
+        /// One simulated backend request
+        pub(crate) async fn call(&self) -> Result<()> {
+            if self.stalled.load(Ordering::SeqCst) {
+                std::future::pending::<()>().await;
+            }
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            let now = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            if let Some((entered, release)) = &self.gate {
+                entered.notify_one();
+                release.notified().await;
+            }
+            if !self.latency.is_zero() {
+                tokio::time::sleep(self.latency).await;
+            }
+            self.current.fetch_sub(1, Ordering::SeqCst);
+
+            if self.healthy.load(Ordering::SeqCst) {
+                Ok(())
+            } else {
+                Err(crate::error::DirSoulError::ExternalError("backend down".to_string()))
+            }
+        }
+    }
+
+    #[async_trait]
+    impl LLMProvider for MockProvider {
+        async fn chat(
+            &self,
+            messages: Vec<ChatMessage>,
+            temperature: Option<f32>,
+            max_tokens: Option<u32>,
+        ) -> Result<ChatResponse> {
+            self.calls.lock().unwrap().push(MockCall {
+                messages,
+                options: GenerateOptions { temperature, max_tokens },
+            });
+            self.call().await?;
+            Ok(ChatResponse::Ollama(OllamaChatResponse {
+                response: self.reply.clone(),
+                done: true,
+                prompt_eval_count: None,
+                eval_count: None,
+            }))
+        }
+
+        /// Streams the reply in chunks of three characters
+        async fn stream_chat(
+            &self,
+            _messages: Vec<ChatMessage>,
+            _temperature: Option<f32>,
+            _max_tokens: Option<u32>,
+        ) -> Result<tokio::sync::mpsc::Receiver<StreamChunk>> {
+            self.call().await?;
+            let chars: Vec<char> = self.reply.chars().collect();
+            let (tx, rx) = tokio::sync::mpsc::channel(chars.len() / 3 + 2);
+            for piece in chars.chunks(3) {
+                let _ = tx.send(StreamChunk { content: piece.iter().collect(), done: false }).await;
+            }
+            let _ = tx.send(StreamChunk { content: String::new(), done: true }).await;
+            Ok(rx)
+        }
+
+        async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+            self.embedded.lock().unwrap().push(text.to_string());
+            self.call().await?;
+            Ok(self.embedding.clone())
+        }
+
+        async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            self.embedded.lock().unwrap().extend(texts.iter().cloned());
+            if self.fan_out <= 1 {
+                self.call().await?;
+            } else {
+                // Like Ollama: concurrent single requests outside any governor
+                let calls = texts.iter().map(|_| self.call());
+                for result in futures_util::future::join_all(calls).await {
+                    result?;
                 }
             }
+            Ok(texts.iter().map(|_| self.embedding.clone()).collect())
         }
-        
-        Ok(())
+
+        fn embed_batch_fan_out(&self) -> usize {
+            self.fan_out.max(1)
+        }
+
+        fn model_name(&self) -> String {
+            self.model.clone()
+        }
+
+        async fn health_check(&self) -> Result<bool> {
+            if self.stalled.load(Ordering::SeqCst) {
+                std::future::pending::<()>().await;
+            }
+            Ok(self.healthy.load(Ordering::SeqCst))
+        }
+    }
+}
+
+// ============================================================================
+// Unit Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::mock::MockProvider;
+
+    #[test]
+    fn test_chat_message_constructors() {
+        let user_msg = ChatMessage::user("Hello");
+        assert_eq!(user_msg.role, "user");
+        assert_eq!(user_msg.content, "Hello");
+
+        let system_msg = ChatMessage::system("You are helpful");
+        assert_eq!(system_msg.role, "system");
+        assert_eq!(system_msg.content, "You are helpful");
+
+        let assistant_msg = ChatMessage::assistant("Hi there");
+        assert_eq!(assistant_msg.role, "assistant");
+        assert_eq!(assistant_msg.content, "Hi there");
+    }
+
+    #[test]
+    fn test_extract_response_text() {
+        let ollama_response = ChatResponse::Ollama(OllamaChatResponse {
+            response: "Ollama text".to_string(),
+            done: true,
+            prompt_eval_count: None,
+            eval_count: None,
+        });
+        assert_eq!(extract_response_text(&ollama_response), "Ollama text");
+
+        let openai_response = ChatResponse::OpenAI(OpenAIChatResponse {
+            id: None,
+            object: None,
+            created: None,
+            model: None,
+            choices: vec![Choice {
+                index: 0,
+                message: ChatMessageContent {
+                    role: "assistant".to_string(),
+                    content: "OpenAI text".to_string(),
+                },
+                finish_reason: None,
+            }],
+            usage: None,
+        });
+        assert_eq!(extract_response_text(&openai_response), "OpenAI text");
+    }
+
+    #[tokio::test]
+    async fn test_mock_provider() {
+        let provider = MockProvider::new().with_model("mock-model");
+
+        // Test chat
+        let messages = vec![ChatMessage::user("Test")];
+        let response = provider.chat(messages, None, None).await.unwrap();
+        let text = extract_response_text(&response);
+        assert_eq!(text, "Mock response");
+
+        // Test embed
+        let embedding = provider.embed("test text").await.unwrap();
+        assert_eq!(embedding.len(), 512);
+
+        // Test embed_batch
+        let texts = vec!["a".to_string(), "b".to_string()];
+        let embeddings = provider.embed_batch(&texts).await.unwrap();
+        assert_eq!(embeddings.len(), 2);
+        assert_eq!(embeddings[0].len(), 512);
+
+        // Test model_name
+        assert_eq!(provider.model_name(), "mock-model");
+
+        // Test health_check
+        assert!(provider.health_check().await.unwrap());
+    }
+
+    #[test]
+    fn test_model_config_default() {
+        let config = OllamaConfig::default();
+        assert_eq!(config.host, "http://127.0.0.1:11434");
+    }
+
+    #[test]
+    fn test_openai_url_default_prefix() {
+        let provider = OpenAICompatibleProvider::new("https://api.deepseek.com/", "key", "deepseek-chat");
+        assert_eq!(provider.url("chat/completions"), "https://api.deepseek.com/v1/chat/completions");
+        assert_eq!(provider.url("/embeddings"), "https://api.deepseek.com/v1/embeddings");
+    }
+
+    #[test]
+    fn test_openai_url_custom_prefix() {
+        let provider = OpenAICompatibleProvider::new("https://gateway.local", "key", "m")
+            .with_api_path_prefix("/proxy/openai/v2/");
+        assert_eq!(provider.url("models"), "https://gateway.local/proxy/openai/v2/models");
+
+        let provider = OpenAICompatibleProvider::new("https://gateway.local/api", "key", "m")
+            .with_api_path_prefix("");
+        assert_eq!(provider.url("chat/completions"), "https://gateway.local/api/chat/completions");
+    }
+
+    #[test]
+    fn test_openai_config_prefix_deserialization() {
+        let config: OpenAICompatibleConfig = toml::from_str(
+            "base_url = \"https://api.example.com\"\napi_key = \"k\"",
+        )
+        .unwrap();
+        assert!(config.api_path_prefix.is_none());
+
+        let config: OpenAICompatibleConfig = toml::from_str(
+            "base_url = \"https://api.example.com\"\napi_key = \"k\"\napi_path_prefix = \"openai\"",
+        )
+        .unwrap();
+        assert_eq!(config.api_path_prefix.as_deref(), Some("openai"));
+    }
+
+    #[test]
+    fn test_azure_deployment_url() {
+        let provider = AzureOpenAIProvider::new(AzureConfig {
+            endpoint: "https://res.openai.azure.com/".to_string(),
+            api_key: "k".to_string(),
+            deployment: "gpt-4o".to_string(),
+            api_version: "2024-02-01".to_string(),
+        });
+        assert_eq!(
+            provider.deployment_url("chat/completions"),
+            "https://res.openai.azure.com/openai/deployments/gpt-4o/chat/completions?api-version=2024-02-01"
+        );
+        assert_eq!(provider.model_name(), "gpt-4o");
+    }
+
+    fn collect_ollama_chunks(chunks: &[&[u8]]) -> Vec<StreamChunk> {
+        let mut framer = NdjsonFramer::default();
+        let mut out = Vec::new();
+        for chunk in chunks {
+            for line in framer.push(chunk) {
+                out.extend(parse_ollama_stream_line(&line));
+            }
+        }
+        out.extend(framer.finish().and_then(|line| parse_ollama_stream_line(&line)));
+        out
+    }
+
+    #[test]
+    fn test_ndjson_framing_split_mid_object() {
+        let chunks: [&[u8]; 3] = [
+            br#"{"response":"Hel"#,
+            br#"lo","done":false}"#,
+            b"\n{\"response\":\"\",\"done\":true}\n",
+        ];
+        let out = collect_ollama_chunks(&chunks);
+
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].content, "Hello");
+        assert!(!out[0].done);
+        assert!(out[1].done);
+    }
+
+    #[test]
+    fn test_ndjson_framing_multiple_objects_per_chunk() {
+        let chunk = b"{\"response\":\"a\",\"done\":false}\n{\"response\":\"b\",\"done\":false}\n{\"response\":\"c\",\"do";
+        let mut framer = NdjsonFramer::default();
+
+        let lines = framer.push(chunk);
+        assert_eq!(lines.len(), 2);
+
+        let rest = framer.push(b"ne\":true}\n");
+        assert_eq!(rest.len(), 1);
+        let last = parse_ollama_stream_line(&rest[0]).unwrap();
+        assert_eq!(last.content, "c");
+        assert!(last.done);
+        assert!(framer.finish().is_none());
+    }
+
+    #[test]
+    fn test_ndjson_framing_split_utf8_and_trailing_line() {
+        let text = "{\"response\":\"你好\",\"done\":false}\n{\"response\":\"\",\"done\":true}";
+        let bytes = text.as_bytes();
+        // Split inside the 3-byte encoding of '你'
+        let split = text.find('你').unwrap() + 1;
+        let out = collect_ollama_chunks(&[&bytes[..split], &bytes[split..]]);
+
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].content, "你好");
+        assert!(out[1].done);
+    }
+
+    #[test]
+    fn test_ndjson_framing_skips_malformed_line() {
+        let out = collect_ollama_chunks(&[&b"not json\n{\"response\":\"ok\",\"done\":false}\n"[..]]);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].content, "ok");
+    }
+
+    #[test]
+    fn test_parse_azure_sse_lines() {
+        // Azure sends a content-filter preamble with empty choices
+        assert!(parse_openai_sse_line(r#"data: {"choices":[],"prompt_filter_results":[]}"#).is_none());
+
+        let chunk = parse_openai_sse_line(
+            r#"data: {"choices":[{"index":0,"delta":{"content":"你好"},"finish_reason":null}]}"#,
+        )
+        .unwrap();
+        assert_eq!(chunk.content, "你好");
+        assert!(!chunk.done);
+
+        let chunk = parse_openai_sse_line(
+            r#"data: {"choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}"#,
+        )
+        .unwrap();
+        assert!(chunk.done);
+
+        assert!(parse_openai_sse_line("data: [DONE]").unwrap().done);
+        assert!(parse_openai_sse_line("").is_none());
+        assert!(parse_openai_sse_line(": keep-alive").is_none());
+    }
+
+    #[test]
+    fn test_factory_azure_requires_config() {
+        let config = ModelConfig {
+            provider: "azure_openai".to_string(),
+            model: "gpt-4o".to_string(),
+            ollama: None,
+            openai_compatible: None,
+            azure_openai: None,
+            circuit_breaker: None,
+            response_filter: None,
+        };
+        assert!(ModelProviderFactory::create_provider(config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_azure_provider_against_mock_server() {
+        use warp::Filter;
+
+        let chat = warp::post()
+            .and(warp::path!("openai" / "deployments" / "my-deploy" / "chat" / "completions"))
+            .and(warp::query::raw())
+            .and(warp::header::exact("api-key", "secret"))
+            .map(|query: String| {
+                assert_eq!(query, "api-version=2024-02-01");
+                warp::reply::json(&serde_json::json!({
+                    "id": "chatcmpl-1",
+                    "object": "chat.completion",
+                    "created": 1,
+                    "model": "gpt-4o",
+                    "choices": [{
+                        "index": 0,
+                        "message": {"role": "assistant", "content": "Azure reply"},
+                        "finish_reason": "stop"
+                    }],
+                    "usage": {"prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5}
+                }))
+            });
+
+        let embeddings = warp::post()
+            .and(warp::path!("openai" / "deployments" / "my-deploy" / "embeddings"))
+            .and(warp::header::exact("api-key", "secret"))
+            .map(|| {
+                warp::reply::json(&serde_json::json!({
+                    "object": "list",
+                    "data": [{"object": "embedding", "embedding": [0.1, 0.2, 0.3], "index": 0}],
+                    "model": "text-embedding-3-small",
+                    "usage": {"prompt_tokens": 2, "total_tokens": 2}
+                }))
+            });
+
+        let (addr, server) = warp::serve(chat.or(embeddings)).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let provider = AzureOpenAIProvider::new(AzureConfig {
+            endpoint: format!("http://{}", addr),
+            api_key: "secret".to_string(),
+            deployment: "my-deploy".to_string(),
+            api_version: "2024-02-01".to_string(),
+        });
+
+        let response = provider
+            .chat(vec![ChatMessage::user("hi")], Some(0.2), Some(16))
+            .await
+            .unwrap();
+        assert_eq!(extract_response_text(&response), "Azure reply");
+
+        let embedding = provider.embed("hello").await.unwrap();
+        assert_eq!(embedding, vec![0.1, 0.2, 0.3]);
+
+        // Wrong key is rejected by the mock server
+        let unauthorized = AzureOpenAIProvider::new(AzureConfig {
+            endpoint: format!("http://{}", addr),
+            api_key: "wrong".to_string(),
+            deployment: "my-deploy".to_string(),
+            api_version: "2024-02-01".to_string(),
+        });
+        assert!(unauthorized.embed("hello").await.is_err());
+    }
+
+    #[test]
+    fn test_models_config_from_toml() {
+        let config = ModelsConfig::from_toml_str(
+            r#"
+            [embedding]
+            provider = "ollama"
+            model = "nomic-embed-text"
+
+            [inference]
+            provider = "ollama"
+            model = "phi4-mini"
+
+            [inference.ollama]
+            host = "http://127.0.0.1:11434"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.embedding.model, "nomic-embed-text");
+        assert_eq!(config.inference.model, "phi4-mini");
+        assert!(config.embedding.ollama.is_none());
+        assert!(config.governor().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_models_config_concurrency_cap() {
+        let parse = |cap: &str| {
+            ModelsConfig::from_toml_str(&format!(
+                r#"
+                max_concurrent_requests = {}
+
+                [embedding]
+                provider = "ollama"
+                model = "nomic-embed-text"
+
+                [inference]
+                provider = "ollama"
+                model = "phi4-mini"
+                "#,
+                cap
+            ))
+            .unwrap()
+        };
+
+        let governor = parse("3").governor().unwrap().unwrap();
+        assert_eq!(governor.max_in_flight(), 3);
+        assert_eq!(governor.in_flight(), 0);
+        assert!(parse("0").governor().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_governor_caps_calls_across_providers() {
+        let probe = Arc::new(MockProvider::new().with_latency(std::time::Duration::from_millis(20)));
+        let governor = LlmGovernor::new(3).unwrap();
+
+        // Chat and embedding go through separate wrappers sharing one governor
+        let chat = governor.govern(probe.clone());
+        let embedding = governor.govern(probe.clone());
+
+        let mut calls = Vec::new();
+        for i in 0..12 {
+            let (chat, embedding) = (Arc::clone(&chat), Arc::clone(&embedding));
+            calls.push(tokio::spawn(async move {
+                if i % 2 == 0 {
+                    chat.chat(vec![ChatMessage::user("hi")], None, None).await.map(|_| ())
+                } else {
+                    embedding.embed("hi").await.map(|_| ())
+                }
+            }));
+        }
+        // A background job taking permits directly, as the extractors do
+        for _ in 0..4 {
+            let (probe, governor) = (Arc::clone(&probe), governor.clone());
+            calls.push(tokio::spawn(async move {
+                let _permit = governor.acquire().await.unwrap();
+                probe.call().await
+            }));
+        }
+        for call in calls {
+            call.await.unwrap().unwrap();
+        }
+
+        assert_eq!(probe.peak(), 3);
+        assert_eq!(governor.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_governed_embed_batch_counts_each_backend_request() {
+        let probe = Arc::new(
+            MockProvider::new()
+                .with_fan_out(4)
+                .with_embedding(vec![0.0; 4])
+                .with_latency(std::time::Duration::from_millis(20)),
+        );
+        let governor = LlmGovernor::new(2).unwrap();
+        let provider = governor.govern(probe.clone());
+        assert_eq!(provider.embed_batch_fan_out(), 4);
+
+        let texts: Vec<String> = (0..8).map(|i| format!("text {}", i)).collect();
+        let embeddings = provider.embed_batch(&texts).await.unwrap();
+
+        assert_eq!(embeddings.len(), 8);
+        assert!(embeddings.iter().all(|embedding| embedding.len() == 4));
+        assert_eq!(probe.peak(), 2);
+        assert_eq!(governor.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_governed_stream_holds_permit_until_consumed() {
+        let governor = LlmGovernor::new(1).unwrap();
+        let provider =
+            GovernedProvider::new(Arc::new(MockProvider::new().with_reply("")), governor.clone());
+
+        let mut rx = provider.stream_chat(vec![ChatMessage::user("hi")], None, None).await.unwrap();
+        assert_eq!(governor.in_flight(), 1);
+
+        assert!(rx.recv().await.unwrap().done);
+        // The forwarding task releases the permit after the final chunk
+        tokio::time::timeout(std::time::Duration::from_secs(1), async {
+            while governor.in_flight() != 0 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(LlmGovernor::new(0).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dual_provider_routes_to_distinct_models() {
+        use std::sync::Mutex;
+        use warp::Filter;
+
+        let seen: Arc<Mutex<Vec<(String, String)>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let chat_seen = Arc::clone(&seen);
+        let chat = warp::post()
+            .and(warp::path!("api" / "chat"))
+            .and(warp::body::json())
+            .map(move |body: serde_json::Value| {
+                let model = body["model"].as_str().unwrap_or_default().to_string();
+                chat_seen.lock().unwrap().push(("chat".to_string(), model.clone()));
+                warp::reply::json(&serde_json::json!({"response": model, "done": true}))
+            });
+
+        let embed_seen = Arc::clone(&seen);
+        let embed = warp::post()
+            .and(warp::path!("api" / "embed"))
+            .and(warp::body::json())
+            .map(move |body: serde_json::Value| {
+                let model = body["model"].as_str().unwrap_or_default().to_string();
+                embed_seen.lock().unwrap().push(("embed".to_string(), model));
+                warp::reply::json(&serde_json::json!({"embedding": [1.0, 0.0]}))
+            });
+
+        let (addr, server) = warp::serve(chat.or(embed)).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let host = format!("http://{}", addr);
+        let ollama = |model: &str| ModelConfig {
+            provider: "ollama".to_string(),
+            model: model.to_string(),
+            ollama: Some(OllamaConfig {
+                host: host.clone(),
+                embed_concurrency: None,
+                keep_alive: None,
+            }),
+            openai_compatible: None,
+            azure_openai: None,
+            circuit_breaker: None,
+            response_filter: None,
+        };
+
+        let provider = ModelProviderFactory::create_dual_provider(ModelsConfig {
+            max_concurrent_requests: None,
+            embedding: ollama("nomic-embed-text"),
+            inference: ollama("phi4-mini"),
+        })
+        .unwrap();
+
+        assert_eq!(provider.model_name(), "phi4-mini");
+        assert_eq!(provider.embedding_provider().model_name(), "nomic-embed-text");
+
+        let response = provider.chat(vec![ChatMessage::user("hi")], None, None).await.unwrap();
+        assert_eq!(extract_response_text(&response), "phi4-mini");
+
+        let embedding = provider.embed("hello").await.unwrap();
+        assert_eq!(embedding, vec![1.0, 0.0]);
+
+        let seen = seen.lock().unwrap().clone();
+        assert_eq!(
+            seen,
+            vec![
+                ("chat".to_string(), "phi4-mini".to_string()),
+                ("embed".to_string(), "nomic-embed-text".to_string()),
+            ]
+        );
+    }
+
+    /// Mock Ollama `/api/embed` that tracks peak concurrency
+    ///
+    /// Texts look like "text-N" and embed to `[N]`; "bad" returns 500.
+    fn spawn_embed_server() -> (std::net::SocketAddr, Arc<std::sync::atomic::AtomicUsize>) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use warp::Filter;
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let peak_out = Arc::clone(&peak);
+
+        let embed = warp::post()
+            .and(warp::path!("api" / "embed"))
+            .and(warp::body::json())
+            .and_then(move |body: serde_json::Value| {
+                let in_flight = Arc::clone(&in_flight);
+                let peak = Arc::clone(&peak);
+                async move {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+
+                    let prompt = body["prompt"].as_str().unwrap_or_default().to_string();
+                    // Later texts finish first to exercise reordering
+                    let n: u64 = prompt.trim_start_matches("text-").parse().unwrap_or(0);
+                    tokio::time::sleep(std::time::Duration::from_millis(60 - n.min(5) * 10)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                    let reply: Box<dyn warp::Reply> = if prompt == "bad" {
+                        Box::new(warp::reply::with_status(
+                            "boom",
+                            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+                        ))
+                    } else {
+                        Box::new(warp::reply::json(&serde_json::json!({"embedding": [n as f32]})))
+                    };
+                    Ok::<_, warp::Rejection>(reply)
+                }
+            });
+
+        let (addr, server) = warp::serve(embed).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        (addr, peak_out)
+    }
+
+    #[tokio::test]
+    async fn test_ollama_embed_batch_preserves_order_and_bounds_concurrency() {
+        use std::sync::atomic::Ordering;
+
+        let (addr, peak) = spawn_embed_server();
+        let provider = OllamaProvider::new(format!("http://{}", addr), "nomic-embed-text")
+            .with_embed_concurrency(2);
+
+        let texts: Vec<String> = (0..6).map(|i| format!("text-{}", i)).collect();
+        let embeddings = provider.embed_batch(&texts).await.unwrap();
+
+        let firsts: Vec<f32> = embeddings.iter().map(|e| e[0]).collect();
+        assert_eq!(firsts, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        let peak = peak.load(Ordering::SeqCst);
+        assert!(peak <= 2, "peak concurrency {}", peak);
+        assert!(peak >= 1);
+    }
+
+    #[tokio::test]
+    async fn test_ollama_embed_batch_reports_failing_index() {
+        let (addr, _) = spawn_embed_server();
+        let provider = OllamaProvider::new(format!("http://{}", addr), "nomic-embed-text")
+            .with_embed_concurrency(3);
+
+        let texts = vec!["text-0".to_string(), "text-1".to_string(), "bad".to_string()];
+        let err = provider.embed_batch(&texts).await.unwrap_err();
+        assert!(err.to_string().contains("batch index 2"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_chat_messages_normalizes_roles() {
+        let messages = vec![
+            ChatMessage { role: " System ".to_string(), content: "be brief".to_string() },
+            ChatMessage { role: "USER".to_string(), content: "hi".to_string() },
+        ];
+        let validated = validate_chat_messages(messages).unwrap();
+        assert_eq!(validated[0].role, "system");
+        assert_eq!(validated[1].role, "user");
+    }
+
+    #[test]
+    fn test_sanitize_sampling_clamps_and_drops() {
+        assert_eq!(sanitize_sampling(Some(5.0), Some(100), OPENAI_MAX_TEMPERATURE), (Some(2.0), Some(100)));
+        assert_eq!(sanitize_sampling(Some(-1.0), None, OLLAMA_MAX_TEMPERATURE), (Some(0.0), None));
+        assert_eq!(sanitize_sampling(Some(0.7), Some(0), OLLAMA_MAX_TEMPERATURE), (Some(0.7), None));
+        assert_eq!(sanitize_sampling(Some(f32::NAN), Some(1), OLLAMA_MAX_TEMPERATURE), (None, Some(1)));
+        assert_eq!(sanitize_sampling(None, None, OLLAMA_MAX_TEMPERATURE), (None, None));
+    }
+
+    /// Mock backend recording every chat request body, for both API shapes
+    fn spawn_chat_body_server() -> (std::net::SocketAddr, Arc<std::sync::Mutex<Vec<serde_json::Value>>>) {
+        use warp::Filter;
+
+        let bodies = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let ollama_bodies = Arc::clone(&bodies);
+        let ollama = warp::post()
+            .and(warp::path!("api" / "chat"))
+            .and(warp::body::json())
+            .map(move |body: serde_json::Value| {
+                ollama_bodies.lock().unwrap().push(body);
+                warp::reply::json(&serde_json::json!({"response": "ok", "done": true}))
+            });
+
+        let openai_bodies = Arc::clone(&bodies);
+        let openai = warp::post()
+            .and(warp::path!("v1" / "chat" / "completions"))
+            .and(warp::body::json())
+            .map(move |body: serde_json::Value| {
+                openai_bodies.lock().unwrap().push(body);
+                warp::reply::json(&serde_json::json!({
+                    "choices": [{"index": 0, "message": {"role": "assistant", "content": "ok"}, "finish_reason": "stop"}]
+                }))
+            });
+
+        let (addr, server) = warp::serve(ollama.or(openai)).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        (addr, bodies)
+    }
+
+    #[tokio::test]
+    async fn test_providers_send_sanitized_sampling() {
+        let (addr, bodies) = spawn_chat_body_server();
+        let host = format!("http://{}", addr);
+
+        let ollama = OllamaProvider::new(host.clone(), "phi4-mini");
+        ollama.chat(vec![ChatMessage::user("hi")], Some(5.0), Some(0)).await.unwrap();
+
+        let openai = OpenAICompatibleProvider::new(host, "sk-test", "deepseek-chat");
+        openai.chat(vec![ChatMessage::user("hi")], Some(-3.0), Some(0)).await.unwrap();
+
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies.len(), 2);
+
+        let options = &bodies[0]["options"];
+        assert_eq!(options["temperature"].as_f64(), Some(OLLAMA_MAX_TEMPERATURE as f64));
+        assert!(options.get("num_predict").is_none());
+
+        assert_eq!(bodies[1]["temperature"].as_f64(), Some(MIN_TEMPERATURE as f64));
+        assert!(bodies[1].get("max_tokens").is_none());
     }
-}
 
-// ============================================================================
-// Model Provider Factory
-// ============================================================================
+    /// Mock Ollama recording `(endpoint, body)` for chat, generate and embed
+    fn spawn_ollama_recorder() -> (std::net::SocketAddr, Arc<std::sync::Mutex<Vec<(String, serde_json::Value)>>>) {
+        use warp::Filter;
 
-/// Factory for creating LLM providers from configuration
-///
-/// # Example
-/// See `OllamaProvider` and `OpenAICompatibleProvider` for direct usage examples.
-pub struct ModelProviderFactory;
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&requests);
+        let api = warp::post()
+            .and(warp::path!("api" / String))
+            .and(warp::body::json())
+            .map(move |endpoint: String, body: serde_json::Value| {
+                let reply = match endpoint.as_str() {
+                    "embed" => serde_json::json!({"embedding": [1.0]}),
+                    _ => serde_json::json!({"response": "", "done": true}),
+                };
+                recorded.lock().unwrap().push((endpoint, body));
+                warp::reply::json(&reply)
+            });
 
-impl ModelProviderFactory {
-    /// Create an LLM provider from configuration
-    pub fn create_provider(config: ModelConfig) -> Result<Arc<dyn LLMProvider>> {
-        match config.provider.as_str() {
-            "ollama" => {
-                let ollama_config = config.ollama.unwrap_or_default();
-                let provider = OllamaProvider::new(ollama_config.host, config.model);
-                Ok(Arc::new(provider))
-            }
-            "openai_compatible" => {
-                let api_config = config
-                    .openai_compatible
-                    .ok_or_else(|| crate::error::DirSoulError::Config(format!("Missing openai_compatible configuration")))?;
-                let provider = OpenAICompatibleProvider::new(
-                    api_config.base_url,
-                    api_config.api_key,
-                    config.model,
-                );
-                Ok(Arc::new(provider))
-            }
-            _ => Err(crate::error::DirSoulError::Config(format!("Unknown provider: {}", config.provider))),
-        }
+        let (addr, server) = warp::serve(api).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        (addr, requests)
     }
-}
 
-impl Default for OllamaConfig {
-    fn default() -> Self {
-        Self {
-            host: default_ollama_host(),
+    #[tokio::test]
+    async fn test_ollama_sends_keep_alive() {
+        let (addr, requests) = spawn_ollama_recorder();
+        let host = format!("http://{}", addr);
+
+        let provider = OllamaProvider::new(host.clone(), "phi4-mini").with_keep_alive("30m");
+        provider.chat(vec![ChatMessage::user("hi")], None, None).await.unwrap();
+        provider.generate("hi", GenerateOptions::default()).await.unwrap();
+        provider.embed("hi").await.unwrap();
+
+        // Without the option the field is left to Ollama's default
+        OllamaProvider::new(host, "phi4-mini").embed("hi").await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        let endpoints: Vec<&str> = requests.iter().map(|(e, _)| e.as_str()).collect();
+        assert_eq!(endpoints, vec!["chat", "generate", "embed", "embed"]);
+        for (_, body) in &requests[..3] {
+            assert_eq!(body["keep_alive"], "30m");
         }
+        assert!(requests[3].1.get("keep_alive").is_none());
     }
-}
 
-// ============================================================================
-// Unit Tests
-// ============================================================================
+    #[tokio::test]
+    async fn test_ollama_preload_issues_warm_up_requests() {
+        let (addr, requests) = spawn_ollama_recorder();
+        let config = OllamaConfig {
+            host: format!("http://{}", addr),
+            keep_alive: Some("-1m".to_string()),
+            ..Default::default()
+        };
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        OllamaProvider::from_config("phi4-mini", config.clone()).preload().await.unwrap();
+        OllamaProvider::from_config("nomic-embed-text", config)
+            .preload_embedding()
+            .await
+            .unwrap();
 
-    /// Mock provider for testing
-    struct MockProvider {
-        model: String,
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+
+        let (endpoint, body) = &requests[0];
+        assert_eq!(endpoint, "generate");
+        assert_eq!(body["model"], "phi4-mini");
+        assert_eq!(body["keep_alive"], "-1m");
+        assert!(body.get("prompt").is_none());
+
+        let (endpoint, body) = &requests[1];
+        assert_eq!(endpoint, "embed");
+        assert_eq!(body["model"], "nomic-embed-text");
+        assert_eq!(body["input"], serde_json::json!([]));
     }
 
-    #[async_trait]
-    impl LLMProvider for MockProvider {
-        async fn chat(
-            &self,
-            _messages: Vec<ChatMessage>,
-            _temperature: Option<f32>,
-            _max_tokens: Option<u32>,
-        ) -> Result<ChatResponse> {
-            Ok(ChatResponse::Ollama(OllamaChatResponse {
-                response: "Mock response".to_string(),
-                done: true,
-                prompt_eval_count: None,
-                eval_count: None,
-            }))
-        }
+    #[tokio::test]
+    async fn test_ollama_preload_reports_unreachable_host() {
+        let provider = OllamaProvider::new("http://127.0.0.1:9", "phi4-mini");
+        assert!(provider.preload().await.is_err());
+    }
 
-        async fn stream_chat(
-            &self,
-            _messages: Vec<ChatMessage>,
-            _temperature: Option<f32>,
-            _max_tokens: Option<u32>,
-        ) -> Result<tokio::sync::mpsc::Receiver<StreamChunk>> {
-            let (tx, rx) = tokio::sync::mpsc::channel(10);
-            let _ = tx.send(StreamChunk {
-                content: "Mock stream".to_string(),
-                done: true,
-            })
+    #[test]
+    fn test_validate_chat_messages_rejects_invalid_role() {
+        let messages = vec![
+            ChatMessage::user("hi"),
+            ChatMessage { role: "tool".to_string(), content: "{}".to_string() },
+        ];
+        let err = validate_chat_messages(messages).unwrap_err();
+        assert!(matches!(err, crate::error::DirSoulError::Config(_)));
+        assert!(err.to_string().contains("'tool' at message 1"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_chat_messages_rejects_all_system() {
+        let messages = vec![ChatMessage::system("a"), ChatMessage::system("b")];
+        let err = validate_chat_messages(messages).unwrap_err();
+        assert!(matches!(err, crate::error::DirSoulError::Config(_)));
+        assert!(validate_chat_messages(Vec::new()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_provider_rejects_invalid_messages_before_request() {
+        // Unroutable host: a network attempt would surface as a non-Config error
+        let provider = OllamaProvider::new("http://127.0.0.1:9", "phi4-mini");
+        let err = provider
+            .chat(vec![ChatMessage::system("only system")], None, None)
             .await
-            .map_err(|e| crate::error::DirSoulError::ExternalError(format!("Send error: {}", e)))?;
-            Ok(rx)
+            .unwrap_err();
+        assert!(matches!(err, crate::error::DirSoulError::Config(_)));
+
+        let err = provider
+            .stream_chat(
+                vec![ChatMessage { role: "bot".to_string(), content: "x".to_string() }],
+                None,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, crate::error::DirSoulError::Config(_)));
+    }
+
+    #[test]
+    fn test_parse_json_array_lenient_plain() {
+        let values = parse_json_array_lenient(r#"[{"a": 1}, {"a": 2}]"#);
+        assert_eq!(values.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_json_array_lenient_fenced() {
+        let text = "```json\n[{\"source\": \"苹果\", \"target\": \"水果\"}]\n```";
+        let values = parse_json_array_lenient(text);
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0]["source"], "苹果");
+    }
+
+    #[test]
+    fn test_parse_json_array_lenient_with_prose() {
+        let text = "好的，以下是关系 [注意]：\n[{\"source\": \"a]b\", \"target\": \"c\"}]\n希望有帮助。";
+        let values = parse_json_array_lenient(text);
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0]["source"], "a]b");
+    }
+
+    #[test]
+    fn test_parse_json_array_lenient_garbage() {
+        assert!(parse_json_array_lenient("").is_empty());
+        assert!(parse_json_array_lenient("no json here").is_empty());
+        assert!(parse_json_array_lenient("[unclosed {").is_empty());
+        assert!(parse_json_array_lenient("{\"not\": \"an array\"}").is_empty());
+    }
+
+    #[test]
+    fn test_stream_chunk() {
+        let chunk = StreamChunk {
+            content: "Hello".to_string(),
+            done: false,
+        };
+        assert_eq!(chunk.content, "Hello");
+        assert!(!chunk.done);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_fast_fails_after_threshold() {
+        let inner = Arc::new(MockProvider::new().failing().with_embedding(vec![1.0]));
+        let provider = CircuitBreakerProvider::new(
+            Arc::clone(&inner),
+            CircuitBreakerConfig {
+                failure_threshold: 3,
+                cooldown_sec: 60,
+            },
+        );
+
+        for _ in 0..3 {
+            assert!(provider.embed("hello").await.is_err());
         }
+        assert_eq!(inner.requests(), 3);
+        assert!(provider.is_open());
+
+        // Every call kind now fails without reaching the backend
+        let err = provider.chat(vec![ChatMessage::user("hi")], None, None).await.unwrap_err();
+        assert!(err.to_string().contains("Circuit breaker open"));
+        assert!(provider.embed("hello").await.is_err());
+        assert!(provider.embed_batch(&["a".to_string()]).await.is_err());
+        assert_eq!(inner.requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_half_open_trial_closes_on_success() {
+        let inner = Arc::new(MockProvider::new().failing().with_embedding(vec![1.0]));
+        let provider = CircuitBreakerProvider::new(
+            Arc::clone(&inner),
+            CircuitBreakerConfig {
+                failure_threshold: 1,
+                cooldown_sec: 0,
+            },
+        );
+
+        assert!(provider.embed("hello").await.is_err());
+        assert!(provider.is_open());
+
+        // The breaker counts whole seconds, so wait past a zero-second cooldown
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        inner.set_healthy(true);
+
+        assert_eq!(provider.embed("hello").await.unwrap(), vec![1.0]);
+        assert!(!provider.is_open());
+        assert_eq!(inner.requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_cancelled_trial_releases_half_open() {
+        let inner = Arc::new(MockProvider::new().failing().with_embedding(vec![1.0]));
+        let provider = CircuitBreakerProvider::new(
+            Arc::clone(&inner),
+            CircuitBreakerConfig {
+                failure_threshold: 3,
+                cooldown_sec: 0,
+            },
+        );
 
-        async fn embed(&self, _text: &str) -> Result<Vec<f32>> {
-            Ok(vec![0.0; 512])
+        for _ in 0..3 {
+            assert!(provider.embed("hello").await.is_err());
         }
+        assert!(provider.is_open());
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
 
-        async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
-            Ok(texts.iter().map(|_| vec![0.0; 512]).collect())
+        // The trial hangs and its caller gives up before it answers
+        inner.set_stalled(true);
+        let trial = tokio::time::timeout(std::time::Duration::from_millis(50), provider.embed("hello")).await;
+        assert!(trial.is_err());
+        assert!(!provider.is_open());
+
+        // The next call reaches the backend as the new trial, so one failure reopens
+        inner.set_stalled(false);
+        assert!(!provider.embed("hello").await.unwrap_err().to_string().contains("Circuit breaker open"));
+        assert!(provider.is_open());
+        assert_eq!(inner.requests(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_ignores_invalid_input_errors() {
+        let provider = CircuitBreakerProvider::new(
+            Arc::new(OllamaProvider::new("http://127.0.0.1:1".to_string(), "phi4-mini".to_string())),
+            CircuitBreakerConfig {
+                failure_threshold: 1,
+                cooldown_sec: 60,
+            },
+        );
+
+        // Rejected by message validation before any request is sent
+        assert!(provider.chat(vec![ChatMessage::system("only system")], None, None).await.is_err());
+        assert!(!provider.is_open());
+    }
+
+    fn wrapper_tags() -> Vec<String> {
+        ResponseFilterConfig::default().tags
+    }
+
+    #[test]
+    fn test_strip_wrapper_tags() {
+        let tags = wrapper_tags();
+        assert_eq!(
+            strip_wrapper_tags("<think>\n用户在问年龄\n</think>\n\n明年26岁。", &tags),
+            "明年26岁。"
+        );
+        assert_eq!(
+            strip_wrapper_tags("A <reasoning>because</reasoning>B", &tags),
+            "A B"
+        );
+        // Truncated reasoning is dropped to the end
+        assert_eq!(strip_wrapper_tags("Answer<think>still thinking", &tags), "Answer");
+
+        // Normal text, including stray brackets, passes through unchanged
+        for text in ["明年26岁。", "1 < 2 and <b>bold</b>", "ends with <thi", "<tool>x</tool>"] {
+            assert_eq!(strip_wrapper_tags(text, &tags), text);
         }
+        assert_eq!(strip_wrapper_tags("<think>x</think>y", &[]), "<think>x</think>y");
+    }
 
-        fn model_name(&self) -> String {
-            self.model.clone()
+    #[test]
+    fn test_tag_stripper_never_leaks_partial_tags() {
+        let tags = wrapper_tags();
+        let text = "<think>plan the answer</think>\n\nHello <reasoning>r</reasoning>world";
+
+        // Split at every byte boundary: the output is identical and never
+        // contains a fragment of a tag
+        for split in 1..text.len() {
+            let mut stripper = TagStripper::new(&tags);
+            let first = stripper.push(&text[..split]);
+            let second = stripper.push(&text[split..]);
+            let last = stripper.finish();
+            assert!(!first.contains('<') && !second.contains('<'), "split at {}", split);
+            assert_eq!(format!("{}{}{}", first, second, last), "Hello world", "split at {}", split);
         }
 
-        async fn health_check(&self) -> Result<bool> {
-            Ok(true)
+        // Character by character
+        let mut stripper = TagStripper::new(&tags);
+        let mut out: String = text.chars().map(|c| stripper.push(&c.to_string())).collect();
+        out.push_str(&stripper.finish());
+        assert_eq!(out, "Hello world");
+    }
+
+    #[tokio::test]
+    async fn test_response_filter_provider_strips_think_blocks() {
+        let inner = Arc::new(MockProvider::new().with_reply("<think>The user wants a greeting.</think>\n你好！"));
+        let provider = ResponseFilterProvider::new(inner.clone(), ResponseFilterConfig::default());
+
+        let response = provider.chat(vec![ChatMessage::user("hi")], None, None).await.unwrap();
+        assert_eq!(extract_response_text(&response), "你好！");
+
+        let mut rx = provider.stream_chat(vec![ChatMessage::user("hi")], None, None).await.unwrap();
+        let mut streamed = String::new();
+        while let Some(chunk) = rx.recv().await {
+            assert!(!chunk.content.contains('<'));
+            streamed.push_str(&chunk.content);
+            if chunk.done {
+                break;
+            }
         }
+        assert_eq!(streamed, "你好！");
+
+        // Without the wrapper the block is kept (default behavior)
+        let response = inner.chat(vec![ChatMessage::user("hi")], None, None).await.unwrap();
+        assert!(extract_response_text(&response).starts_with("<think>"));
+
+        // Plain replies pass through unchanged
+        let plain = ResponseFilterProvider::new(
+            Arc::new(MockProvider::new().with_reply("明年26岁。")),
+            ResponseFilterConfig::default(),
+        );
+        let response = plain.chat(vec![ChatMessage::user("hi")], None, None).await.unwrap();
+        assert_eq!(extract_response_text(&response), "明年26岁。");
     }
 
     #[test]
-    fn test_chat_message_constructors() {
-        let user_msg = ChatMessage::user("Hello");
-        assert_eq!(user_msg.role, "user");
-        assert_eq!(user_msg.content, "Hello");
+    fn test_factory_response_filter_config() {
+        let config: ModelConfig = toml::from_str(
+            r#"
+            provider = "ollama"
+            model = "deepseek-r1"
 
-        let system_msg = ChatMessage::system("You are helpful");
-        assert_eq!(system_msg.role, "system");
-        assert_eq!(system_msg.content, "You are helpful");
+            [response_filter]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.response_filter.clone().unwrap().tags, wrapper_tags());
+        let provider = ModelProviderFactory::create_provider(config).unwrap();
+        assert_eq!(provider.model_name(), "deepseek-r1");
 
-        let assistant_msg = ChatMessage::assistant("Hi there");
-        assert_eq!(assistant_msg.role, "assistant");
-        assert_eq!(assistant_msg.content, "Hi there");
+        let config: ModelConfig = toml::from_str(
+            r#"
+            provider = "ollama"
+            model = "phi4-mini"
+            "#,
+        )
+        .unwrap();
+        assert!(config.response_filter.is_none());
     }
 
     #[test]
-    fn test_extract_response_text() {
-        let ollama_response = ChatResponse::Ollama(OllamaChatResponse {
-            response: "Ollama text".to_string(),
-            done: true,
-            prompt_eval_count: None,
-            eval_count: None,
-        });
-        assert_eq!(extract_response_text(&ollama_response), "Ollama text");
+    fn test_factory_wraps_provider_with_circuit_breaker() {
+        let config: ModelConfig = toml::from_str(
+            r#"
+            provider = "ollama"
+            model = "phi4-mini"
 
-        let openai_response = ChatResponse::OpenAI(OpenAIChatResponse {
-            id: None,
-            object: None,
-            created: None,
-            model: None,
-            choices: vec![Choice {
-                index: 0,
-                message: ChatMessageContent {
-                    role: "assistant".to_string(),
-                    content: "OpenAI text".to_string(),
-                },
-                finish_reason: None,
-            }],
-            usage: None,
-        });
-        assert_eq!(extract_response_text(&openai_response), "OpenAI text");
+            [circuit_breaker]
+            failure_threshold = 2
+            "#,
+        )
+        .unwrap();
+        let breaker = config.circuit_breaker.clone().unwrap();
+        assert_eq!(breaker.failure_threshold, 2);
+        assert_eq!(breaker.cooldown_sec, DEFAULT_BREAKER_COOLDOWN_SEC);
+
+        let provider = ModelProviderFactory::create_provider(config).unwrap();
+        assert_eq!(provider.model_name(), "phi4-mini");
     }
 
     #[tokio::test]
-    async fn test_mock_provider() {
-        let provider = MockProvider {
-            model: "mock-model".to_string(),
-        };
+    async fn test_default_generate_wraps_chat() {
+        let mock = MockProvider::new();
+        assert_eq!(mock.generate("hi", GenerateOptions::default()).await.unwrap(), "Mock response");
 
-        // Test chat
-        let messages = vec![ChatMessage::user("Test")];
-        let response = provider.chat(messages, None, None).await.unwrap();
-        let text = extract_response_text(&response);
-        assert_eq!(text, "Mock response");
+        let provider = MockProvider::new().with_reply("七");
+        let text = provider.generate("三加四等于", GenerateOptions::new(0.3, 64)).await.unwrap();
+        assert_eq!(text, "七");
 
-        // Test embed
-        let embedding = provider.embed("test text").await.unwrap();
-        assert_eq!(embedding.len(), 512);
+        let calls = provider.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].messages.len(), 1);
+        assert_eq!(calls[0].messages[0].role, "user");
+        assert_eq!(calls[0].prompt(), "三加四等于");
+        assert_eq!(calls[0].options, GenerateOptions::new(0.3, 64));
+    }
 
-        // Test embed_batch
-        let texts = vec!["a".to_string(), "b".to_string()];
-        let embeddings = provider.embed_batch(&texts).await.unwrap();
-        assert_eq!(embeddings.len(), 2);
-        assert_eq!(embeddings[0].len(), 512);
+    #[tokio::test]
+    async fn test_ollama_generate_uses_native_endpoint() {
+        use warp::Filter;
 
-        // Test model_name
-        assert_eq!(provider.model_name(), "mock-model");
+        let bodies = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = Arc::clone(&bodies);
+        let generate = warp::post()
+            .and(warp::path!("api" / "generate"))
+            .and(warp::body::json())
+            .map(move |body: serde_json::Value| {
+                seen.lock().unwrap().push(body);
+                warp::reply::json(&serde_json::json!({"response": "生成的文本", "done": true}))
+            });
+        let (addr, server) = warp::serve(generate).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
 
-        // Test health_check
-        assert!(provider.health_check().await.unwrap());
+        let provider = OllamaProvider::new(format!("http://{}/", addr), "phi4-mini");
+        let text = provider.generate("提示词", GenerateOptions::new(9.0, 128)).await.unwrap();
+        assert_eq!(text, "生成的文本");
+
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies.len(), 1);
+        assert_eq!(bodies[0]["model"], "phi4-mini");
+        assert_eq!(bodies[0]["prompt"], "提示词");
+        assert_eq!(bodies[0]["stream"], false);
+        assert_eq!(bodies[0]["options"]["num_predict"], 128);
+        assert_eq!(bodies[0]["options"]["temperature"].as_f64(), Some(OLLAMA_MAX_TEMPERATURE as f64));
+    }
+
+    #[tokio::test]
+    async fn test_wrappers_forward_generate() {
+        let thinking: Arc<dyn LLMProvider> = Arc::new(MockProvider::new().with_reply("<think>推理过程</think>\n\n答案"));
+
+        let filtered = ResponseFilterProvider::new(Arc::clone(&thinking), ResponseFilterConfig::default());
+        assert_eq!(filtered.generate("问题", GenerateOptions::default()).await.unwrap(), "答案");
+
+        let dual = DualModelProvider::new(
+            Arc::new(filtered),
+            Arc::new(MockProvider::new().with_model("embed")),
+        );
+        let breaker = CircuitBreakerProvider::new(Arc::new(dual), CircuitBreakerConfig::default());
+        assert_eq!(breaker.generate("问题", GenerateOptions::default()).await.unwrap(), "答案");
     }
 
     #[test]
-    fn test_model_config_default() {
-        let config = OllamaConfig::default();
-        assert_eq!(config.host, "http://127.0.0.1:11434");
+    fn test_redaction_modes() {
+        assert_eq!(Redaction::parse("hash").unwrap(), Redaction::Hash);
+        assert_eq!(Redaction::parse(" Truncate ").unwrap(), Redaction::Truncate(DEFAULT_REDACTION_TRUNCATE_CHARS));
+        assert_eq!(Redaction::parse("truncate:4").unwrap(), Redaction::Truncate(4));
+        assert_eq!(Redaction::parse("none").unwrap(), Redaction::None);
+        assert!(Redaction::parse("truncate:many").is_err());
+
+        let hashed = Redaction::Hash.apply("我住在上海");
+        assert!(hashed.starts_with("[sha256:") && hashed.ends_with(" len=5]"), "{}", hashed);
+        assert_eq!(hashed, Redaction::Hash.apply("我住在上海"));
+        assert_ne!(hashed, Redaction::Hash.apply("我住在北京"));
+
+        assert_eq!(Redaction::Truncate(2).apply("我住在上海"), "我住…[+3 chars]");
+        assert_eq!(Redaction::Truncate(10).apply("我住在上海"), "我住在上海");
+        assert_eq!(Redaction::None.apply("我住在上海"), "我住在上海");
     }
 
     #[test]
-    fn test_stream_chunk() {
-        let chunk = StreamChunk {
-            content: "Hello".to_string(),
-            done: false,
-        };
-        assert_eq!(chunk.content, "Hello");
-        assert!(!chunk.done);
+    fn test_redact_payload_keeps_structure() {
+        let mut payload = serde_json::json!({
+            "messages": [
+                { "role": "system", "content": "你是助手" },
+                { "role": "user", "content": "我的手机号是13800000000" },
+            ],
+            "temperature": 0.7,
+            "input": ["第一段", "第二段"],
+        });
+        Redaction::Hash.redact_payload(&mut payload);
+
+        let logged = payload.to_string();
+        assert!(!logged.contains("13800000000"));
+        assert!(!logged.contains("第一段"));
+        assert_eq!(payload["messages"][1]["role"], "user");
+        assert_eq!(payload["messages"].as_array().unwrap().len(), 2);
+        assert_eq!(payload["temperature"], 0.7);
+        assert_eq!(payload["input"].as_array().unwrap().len(), 2);
+        assert!(payload["messages"][1]["content"].as_str().unwrap().starts_with("[sha256:"));
+    }
+
+    #[test]
+    fn test_payload_logging_from_env_values() {
+        assert_eq!(PayloadLogging::from_values(None, None), PayloadLogging::default());
+        assert!(!PayloadLogging::default().log_payloads);
+
+        let on = PayloadLogging::from_values(Some("true"), Some("truncate:8"));
+        assert!(on.log_payloads);
+        assert_eq!(on.redaction, Redaction::Truncate(8));
+
+        // An unknown mode never falls back to raw text
+        let fallback = PayloadLogging::from_values(Some("1"), Some("plain"));
+        assert_eq!(fallback.redaction, Redaction::Hash);
+        assert!(!PayloadLogging::from_values(Some("0"), None).log_payloads);
+    }
+
+    /// Log output captured by a test subscriber
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl CapturedLogs {
+        fn text(&self) -> String {
+            String::from_utf8_lossy(&self.0.lock().unwrap()).to_string()
+        }
+    }
+
+    fn capture_logs(level: tracing::Level) -> (CapturedLogs, tracing::subscriber::DefaultGuard) {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(level)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        (logs, tracing::subscriber::set_default(subscriber))
+    }
+
+    #[tokio::test]
+    async fn test_logging_provider_redacts_payloads() {
+        let inner = Arc::new(
+            MockProvider::new()
+                .with_model("deepseek-r1")
+                .with_reply("你住在上海")
+                .with_embedding(vec![0.0]),
+        );
+        let provider = LoggingProvider::new(
+            inner,
+            PayloadLogging {
+                log_payloads: true,
+                redaction: Redaction::Truncate(2),
+            },
+        );
+
+        let (logs, _guard) = capture_logs(tracing::Level::TRACE);
+        let response = provider
+            .chat(vec![ChatMessage::user("我住在上海浦东新区")], Some(0.3), Some(64))
+            .await
+            .unwrap();
+        // The caller still gets the unredacted response
+        assert_eq!(extract_response_text(&response), "你住在上海");
+        provider.embed("我住在上海浦东新区").await.unwrap();
+
+        let output = logs.text();
+        assert!(!output.contains("上海"), "{}", output);
+        assert!(output.contains(r#""role":"user""#), "{}", output);
+        assert!(output.contains(r#""content":"我住…[+7 chars]""#), "{}", output);
+        assert!(output.contains(r#""response":"你住…[+3 chars]""#), "{}", output);
+        assert!(output.contains(r#""temperature":0.3"#), "{}", output);
+        assert!(output.contains(r#""dimensions":1"#), "{}", output);
+        assert!(output.contains("deepseek-r1 chat: ok in"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn test_logging_provider_omits_payloads_by_default() {
+        let provider = LoggingProvider::new(
+            Arc::new(MockProvider::new().with_model("phi4-mini")),
+            PayloadLogging::default(),
+        );
+
+        let (logs, _guard) = capture_logs(tracing::Level::TRACE);
+        provider.chat(vec![ChatMessage::user("我的密码是hunter2")], None, None).await.unwrap();
+
+        let output = logs.text();
+        assert!(!output.contains("hunter2"), "{}", output);
+        assert!(!output.contains("request"), "{}", output);
+        assert!(output.contains("phi4-mini chat: ok in"), "{}", output);
+        assert!(output.contains("prompt_tokens=-"), "{}", output);
     }
 }
@@ -1,6 +1,90 @@
-use dirsoul::Result;
-use dirsoul::http_api::HttpServer;
-use tracing::info;
+use std::sync::Arc;
+
+use diesel::{Connection, PgConnection};
+use dirsoul::{DirSoulError, Result};
+use dirsoul::agents::MemoryPermission;
+use dirsoul::audit::ThreadSafeAuditLogger;
+use dirsoul::built_in_plugins::{DecisionPlugin, PsychologyPlugin};
+use dirsoul::cognitive::{spawn_revalidation_loop, RevalidationConfig};
+use dirsoul::data_lifecycle::{DataLifecycleManager, TieringConfig};
+use dirsoul::embedding::{spawn_embedding_backfill_loop, EmbeddingGenerator};
+use dirsoul::entity_relation_extractor::{
+    spawn_strength_recompute_loop, EntityRelationExtractor, RelationExtractorConfig,
+};
+use dirsoul::event_extractor::SlmExtractor;
+use dirsoul::event_storage::{spawn_extraction_retry_loop, ExtractionRetryConfig};
+use dirsoul::http_api::{ApiTokens, HttpServer, SearchConfig, SemanticChatRetriever};
+use dirsoul::llm_provider::{
+    LLMProvider, LlmGovernor, ModelConfig, ModelProviderFactory, ModelsConfig, OllamaProvider,
+};
+use dirsoul::pattern_detector::{users_with_memories, PatternDetectionScheduler};
+use dirsoul::plugin::PluginManager;
+use dirsoul::prompt_manager::PromptManager;
+use dirsoul::resource_manager::{ResourceAwareScheduler, ResourceManager, ResourceManagerConfig};
+use dirsoul::webhook::{WebhookConfig, WebhookNotifier};
+use tracing::{info, warn};
+
+/// 后台预热 Ollama 模型（尽力而为，失败只记录日志）
+///
+/// 预热请求同样占用全局 LLM 并发许可。
+fn spawn_model_preload(config: ModelsConfig, governor: Option<LlmGovernor>) {
+    fn ollama(config: ModelConfig) -> Option<OllamaProvider> {
+        if config.provider != "ollama" {
+            info!("跳过模型预热: {} 使用 {} 提供方", config.model, config.provider);
+            return None;
+        }
+        Some(OllamaProvider::from_config(config.model, config.ollama.unwrap_or_default()))
+    }
+
+    let chat = ollama(config.inference);
+    let embedding = ollama(config.embedding);
+
+    tokio::spawn(async move {
+        if let Some(provider) = chat {
+            let _permit = match &governor {
+                Some(governor) => governor.acquire().await.ok(),
+                None => None,
+            };
+            match provider.preload().await {
+                Ok(()) => info!("🔥 对话模型已预热: {}", provider.model_name()),
+                Err(e) => warn!("对话模型预热失败 ({}): {}", provider.model_name(), e),
+            }
+        }
+        if let Some(provider) = embedding {
+            let _permit = match &governor {
+                Some(governor) => governor.acquire().await.ok(),
+                None => None,
+            };
+            match provider.preload_embedding().await {
+                Ok(()) => info!("🔥 嵌入模型已预热: {}", provider.model_name()),
+                Err(e) => warn!("嵌入模型预热失败 ({}): {}", provider.model_name(), e),
+            }
+        }
+    });
+}
+
+/// 安装内置的决策与心理分析插件
+///
+/// 插件不属于任何用户，所有用户共享；每次调用的用户由请求上下文决定。
+/// DeepTalk 在实例内保存对话历史，共享会混淆不同用户的对话，因此不在此安装。
+/// 运行时的插件权限调整写入审计日志。
+async fn builtin_plugins(
+    inference: ModelConfig,
+    governor: Option<LlmGovernor>,
+    audit_logger: ThreadSafeAuditLogger,
+) -> Result<PluginManager> {
+    let mut llm = ModelProviderFactory::create_provider(inference)?;
+    if let Some(governor) = &governor {
+        llm = governor.govern(llm);
+    }
+
+    let manager = PluginManager::new().with_audit_logger(audit_logger);
+    let decision = DecisionPlugin::new(llm.clone(), PromptManager::new()?, String::new())?;
+    manager.install(Arc::new(decision), MemoryPermission::ReadWriteDerived).await?;
+    let psychology = PsychologyPlugin::new(llm, PromptManager::new()?, String::new())?;
+    manager.install(Arc::new(psychology), MemoryPermission::ReadWriteDerived).await?;
+    Ok(manager)
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -24,9 +108,184 @@ async fn main() -> Result<()> {
     let bind_address = std::env::var("DIRSOUL_BIND_ADDRESS")
         .unwrap_or_else(|_| "0.0.0.0:8080".to_string());
 
+    // 预热配置中的模型，避免首个请求的冷启动延迟
+    let models_path = std::env::var("DIRSOUL_MODELS_CONFIG")
+        .unwrap_or_else(|_| "config/models.toml".to_string());
+    let (governor, slm_model, inference, embedder) = match ModelsConfig::from_file(&models_path) {
+        Ok(config) => {
+            // 全局 LLM 并发上限只创建一次，由所有调用模型的子系统共享
+            let governor = config.governor()?;
+            // 事件抽取使用 Ollama 推理模型；其他提供方时使用抽取器默认模型
+            let slm_model = (config.inference.provider == "ollama").then(|| {
+                let host = config.inference.ollama.clone().map(|ollama| ollama.host);
+                (host, config.inference.model.clone())
+            });
+            let inference = config.inference.clone();
+            // 嵌入使用 [embedding] 段的模型，与对话模型无关
+            let embedder = match EmbeddingGenerator::from_models_config(&config) {
+                Ok(embedder) => {
+                    let embedder = match &governor {
+                        Some(governor) => embedder.with_governor(governor.clone()),
+                        None => embedder,
+                    };
+                    Some(Arc::new(embedder))
+                }
+                Err(e) => {
+                    warn!("嵌入模型 {} 初始化失败，语义功能不可用: {}", config.embedding.model, e);
+                    None
+                }
+            };
+            spawn_model_preload(config, governor.clone());
+            (governor, slm_model, Some(inference), embedder)
+        }
+        Err(e) => {
+            warn!("未加载模型配置 {}，跳过预热: {}", models_path, e);
+            (None, None, None, None)
+        }
+    };
+
+    // 实体关系计数强度的 k 读取 DIRSOUL_RELATION_STRENGTH_K（未设置时用默认值），无效则拒绝启动
+    let relation_config = RelationExtractorConfig::from_env()?;
+
+    // 按配置的 k 重新推导实体关系的计数强度（k 改变或旧数据仍保存原始计数时）
+    let relations = EntityRelationExtractor::with_config(relation_config.clone());
+    let sync_url = database_url.clone();
+    tokio::task::spawn_blocking(move || {
+        let synced = PgConnection::establish(&sync_url)
+            .map_err(DirSoulError::from)
+            .and_then(|mut conn| relations.sync_count_strengths(&mut conn));
+        match synced {
+            Ok(0) => {}
+            Ok(rewritten) => info!("🔗 已按当前配置更新 {} 条实体关系强度", rewritten),
+            Err(e) => warn!("实体关系强度同步失败: {}", e),
+        }
+    });
+
+    // 每天按近期事件为每个用户重算实体关系的共现度；近期未被提及的关系保持不变，默认不删除关系
+    spawn_strength_recompute_loop(
+        database_url.clone(),
+        std::time::Duration::from_secs(24 * 3600),
+        EntityRelationExtractor::with_config(relation_config),
+    );
+
+    // 定时硬删除超过保留期的软删除记忆
+    DataLifecycleManager::new(TieringConfig::default(), database_url.clone()).spawn_purge_task();
+
+    // 定时重试失败的事件抽取
+    let (slm_host, slm_name) = slm_model.map_or((None, None), |(host, model)| (host, Some(model)));
+    match SlmExtractor::new(slm_host, slm_name).await {
+        Ok(mut extractor) => {
+            if let Some(governor) = &governor {
+                extractor = extractor.with_governor(governor.clone());
+            }
+            spawn_extraction_retry_loop(
+                database_url.clone(),
+                Arc::new(extractor),
+                std::time::Duration::from_secs(60),
+                ExtractionRetryConfig::default(),
+            );
+        }
+        Err(e) => warn!("事件抽取器初始化失败，跳过抽取重试: {}", e),
+    }
+
+    // Webhook 配置读取 DIRSOUL_WEBHOOK_CONFIG 指向的 TOML 文件（未设置时不发送），无效则拒绝启动
+    let webhooks = match std::env::var("DIRSOUL_WEBHOOK_CONFIG") {
+        Ok(path) => Some(WebhookNotifier::new(WebhookConfig::from_file(&path)?)),
+        Err(_) => None,
+    };
+
+    // 定时用新事件重新验证活跃的认知视图，并通知 Webhook 订阅方
+    spawn_revalidation_loop(
+        database_url.clone(),
+        std::time::Duration::from_secs(3600),
+        RevalidationConfig::default(),
+        webhooks.clone(),
+    );
+
+    // 每天为有记忆的用户检测行为模式并保存为认知视图；内存紧张时跳过当次
+    let detection_url = database_url.clone();
+    PatternDetectionScheduler::new().spawn(
+        database_url.clone(),
+        std::time::Duration::from_secs(24 * 3600),
+        move || {
+            PgConnection::establish(&detection_url)
+                .map_err(DirSoulError::from)
+                .and_then(|mut conn| users_with_memories(&mut conn))
+                .unwrap_or_else(|e| {
+                    warn!("模式检测用户列表加载失败: {}", e);
+                    Vec::new()
+                })
+        },
+        ResourceAwareScheduler::new(ResourceManager::new(ResourceManagerConfig::default())),
+    );
+
     // 创建并启动 HTTP 服务器
     info!("📡 启动 API 服务器: {}", bind_address);
-    let server = HttpServer::new(bind_address, database_url)?;
+    let mut server = HttpServer::new(bind_address, database_url.clone())?;
+    if let Some(governor) = &governor {
+        info!("🚦 LLM 并发上限: {}", governor.max_in_flight());
+        server = server.with_llm_governor(governor.clone());
+    }
+
+    // 检索配置读取 DIRSOUL_SEARCH_CONFIG 指向的 TOML 文件（未设置时用默认值），无效则拒绝启动
+    let search_config = match std::env::var("DIRSOUL_SEARCH_CONFIG") {
+        Ok(path) => SearchConfig::from_file(&path)?,
+        Err(_) => SearchConfig::default(),
+    };
+    server = server.with_search_config(search_config.clone());
+
+    // /api/search 用同一个嵌入器做语义检索，/api/chat 用它召回相关记忆并返回召回解释；
+    // 未配置嵌入模型时检索退回关键词，对话不附带召回
+    if let Some(embedder) = &embedder {
+        let retriever = SemanticChatRetriever::new(database_url.clone(), embedder.clone())
+            .with_min_similarity(search_config.min_similarity)
+            .with_keyword_index(search_config.keyword_index);
+        server = server
+            .with_embedder(embedder.clone())
+            .with_chat_retriever(Arc::new(retriever));
+
+        // 检测一次 pgvector：缺失时嵌入器停用，检索、召回与回填一并退回或跳过
+        server.check_vector_support().await;
+
+        // 定时为尚无向量的记忆补写嵌入
+        spawn_embedding_backfill_loop(
+            database_url.clone(),
+            embedder.clone(),
+            std::time::Duration::from_secs(300),
+            32,
+        );
+    }
+
+    if let Some(webhooks) = webhooks {
+        server = server.with_webhooks(webhooks);
+    }
+
+    // 插件、评审与设置接口要求 Bearer 令牌，每个令牌只能代表绑定的用户：
+    // DIRSOUL_API_TOKENS="alice=令牌1,bob=令牌2"；未设置时这些接口拒绝所有请求
+    match std::env::var("DIRSOUL_API_TOKENS") {
+        Ok(spec) => server = server.with_api_tokens(ApiTokens::parse(&spec)?),
+        Err(_) => warn!("未设置 DIRSOUL_API_TOKENS，插件、评审与设置接口将拒绝所有请求"),
+    }
+
+    // /api/chat 使用 models.toml 的推理模型（即预热的模型），而非内置默认模型
+    if let Some(inference) = &inference {
+        match ModelProviderFactory::create_provider(inference.clone()) {
+            Ok(provider) => {
+                info!("💬 对话模型: {}", inference.model);
+                server = server.with_chat_provider(provider);
+            }
+            Err(e) => warn!("对话模型 {} 初始化失败，使用默认模型: {}", inference.model, e),
+        }
+    }
+
+    // 安装内置插件，供 /api/command 调用
+    if let Some(inference) = inference {
+        let audit_logger = ThreadSafeAuditLogger::new(database_url.clone());
+        match builtin_plugins(inference, governor.clone(), audit_logger).await {
+            Ok(plugins) => server = server.with_plugin_manager(Arc::new(plugins)),
+            Err(e) => warn!("内置插件安装失败，/api/command 不可用: {}", e),
+        }
+    }
 
     // 启动服务器（阻塞运行）
     server.start().await?;
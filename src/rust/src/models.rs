@@ -68,6 +68,9 @@ impl From<ContentType> for &'static str {
     }
 }
 
+/// SQL type `RawMemory::embedding` is selected as (`REAL[]`, always NULL)
+type EmbeddingSqlType = diesel::sql_types::Nullable<diesel::sql_types::Array<diesel::sql_types::Float4>>;
+
 /// Raw memory representation - Layer 1 of the memory hierarchy
 ///
 /// This is the append-only storage layer for all user inputs.
@@ -77,7 +80,12 @@ impl From<ContentType> for &'static str {
 /// - `encrypted` field uses `Vec<u8>` for BYTEA from PostgreSQL
 /// - No circular references - uses plain ownership
 /// - Optimized for 8GB memory environment
-#[derive(Debug, Clone, Queryable, Identifiable, Serialize, Deserialize)]
+///
+/// # Vectors
+/// `raw_memories.embedding` only exists when pgvector is installed, so
+/// `RawMemory::as_select()` never reads it and leaves `embedding` as `None`;
+/// read vectors through `EmbeddingGenerator`.
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable, Serialize, Deserialize)]
 #[diesel(table_name = raw_memories)]
 #[diesel(primary_key(memory_id))]
 pub struct RawMemory {
@@ -95,9 +103,17 @@ pub struct RawMemory {
     pub encrypted: Option<Vec<u8>>,
     /// Flexible metadata stored as JSONB
     pub metadata: Option<serde_json::Value>,
-    /// Vector embedding for semantic search (768 dimensions)
-    /// Stored as Vec<f32> representing the vector
+    /// Vector embedding for semantic search; not loaded from the database
+    #[diesel(
+        select_expression = diesel::dsl::sql::<EmbeddingSqlType>("NULL::REAL[]"),
+        select_expression_type = diesel::expression::SqlLiteral<EmbeddingSqlType>
+    )]
+    #[serde(default)]
     pub embedding: Option<Vec<f32>>,
+    /// Soft-delete tombstone (NULL for live memories)
+    pub deleted_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Embedding model that produced `embedding` (NULL for untracked vectors)
+    pub embedding_model: Option<String>,
 }
 
 impl RawMemory {
@@ -106,6 +122,26 @@ impl RawMemory {
         self.encrypted.is_some()
     }
 
+    /// Check if this memory has been soft-deleted
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    /// Time after which a soft-deleted memory is hard-deleted
+    ///
+    /// Returns `None` for live memories.
+    pub fn purge_after(&self, retention_days: i64) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.deleted_at
+            .map(|deleted_at| deleted_at + chrono::Duration::days(retention_days))
+    }
+
+    /// Check if a soft-deleted memory can still be restored at `now`
+    pub fn can_restore_at(&self, now: chrono::DateTime<chrono::Utc>, retention_days: i64) -> bool {
+        self.purge_after(retention_days)
+            .map(|cutoff| now < cutoff)
+            .unwrap_or(false)
+    }
+
     /// Get the effective size in bytes
     ///
     /// Useful for memory tracking in 8GB environment
@@ -262,6 +298,8 @@ mod tests {
             encrypted: None,
             metadata: Some(serde_json::json!({})),
             embedding: None,
+            deleted_at: None,
+            embedding_model: None,
         };
 
         let size = memory.size_bytes();
@@ -280,6 +318,8 @@ mod tests {
             encrypted: None,
             metadata: None,
             embedding: None,
+            deleted_at: None,
+            embedding_model: None,
         };
 
         assert!(!plaintext.is_encrypted());
@@ -292,6 +332,38 @@ mod tests {
 
         assert!(encrypted.is_encrypted());
     }
+
+    #[test]
+    fn test_raw_memory_restore_window() {
+        let deleted_at = chrono::Utc::now() - chrono::Duration::days(10);
+        let live = RawMemory {
+            memory_id: Uuid::new_v4(),
+            user_id: "user123".to_string(),
+            created_at: deleted_at - chrono::Duration::days(1),
+            content_type: "text".to_string(),
+            content: Some("hello".to_string()),
+            encrypted: None,
+            metadata: None,
+            embedding: None,
+            deleted_at: None,
+            embedding_model: None,
+        };
+        assert!(!live.is_deleted());
+        assert!(live.purge_after(30).is_none());
+        assert!(!live.can_restore_at(chrono::Utc::now(), 30));
+
+        let deleted = RawMemory {
+            deleted_at: Some(deleted_at),
+            ..live
+        };
+        assert!(deleted.is_deleted());
+        assert_eq!(deleted.purge_after(30), Some(deleted_at + chrono::Duration::days(30)));
+
+        // Still inside the 30-day window
+        assert!(deleted.can_restore_at(chrono::Utc::now(), 30));
+        // A 7-day window has already closed
+        assert!(!deleted.can_restore_at(chrono::Utc::now(), 7));
+    }
 }
 
 /// Event memory representation - Layer 2 of the memory hierarchy
@@ -335,14 +407,54 @@ pub struct EventMemory {
     pub confidence: f64,
     /// Version of extractor that created this event
     pub extractor_version: Option<String>,
+    /// Extra extractor signals, e.g. `EVENT_NEGATED_KEY`
+    pub metadata: Option<serde_json::Value>,
 }
 
+/// Event metadata key: the source text negates the event ("没吃苹果")
+pub const EVENT_NEGATED_KEY: &str = "negated";
+
+/// Event metadata key: sentiment of the source text ("positive", "negative", "neutral")
+pub const EVENT_SENTIMENT_KEY: &str = "sentiment";
+
+/// Event metadata key: `{"start": .., "end": ..}` character offsets of the
+/// event's source span in the raw memory
+pub const EVENT_RAW_SPAN_KEY: &str = "raw_span";
+
 impl EventMemory {
+    /// Look up one metadata entry
+    pub fn metadata_value(&self, key: &str) -> Option<&serde_json::Value> {
+        self.metadata.as_ref()?.get(key)
+    }
+
+    /// Check if the extractor flagged the event as negated
+    pub fn is_negated(&self) -> bool {
+        self.metadata_value(EVENT_NEGATED_KEY)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    }
+
     /// Check if this event has a quantity
     pub fn has_quantity(&self) -> bool {
         self.quantity.is_some()
     }
 
+    /// Check if the quantity lies in `[min, max]` and is measured in `unit`
+    ///
+    /// `None` conditions don't restrict, but once any condition is given an
+    /// event without a quantity never matches.
+    pub fn quantity_matches(&self, min: Option<f64>, max: Option<f64>, unit: Option<&str>) -> bool {
+        if min.is_none() && max.is_none() && unit.is_none() {
+            return true;
+        }
+        let Some(quantity) = self.quantity else {
+            return false;
+        };
+        min.map_or(true, |min| quantity >= min)
+            && max.map_or(true, |max| quantity <= max)
+            && unit.map_or(true, |unit| self.unit.as_deref() == Some(unit))
+    }
+
     /// Check if this event is high-confidence
     ///
     /// Used in promotion gate to determine if view should be promoted.
@@ -402,6 +514,80 @@ impl EventMemory {
     }
 }
 
+/// Registry of event extractors and their current versions
+///
+/// The single source of truth for `event_memories.extractor_version`: events
+/// are stamped with `tag()` (`"<name>@<version>"`), so operators can count
+/// and re-extract data produced by an older extractor. Bump an extractor's
+/// `version()` when its output changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtractorVersion {
+    /// Events created directly through the API (no extraction)
+    Manual,
+    /// Regex rule extractor (`RuleExtractor`)
+    Rule,
+    /// SLM extractor (`SlmExtractor`)
+    Slm,
+    /// Plugin interactions logged by the command router
+    CommandRouter,
+}
+
+impl ExtractorVersion {
+    /// Every registered extractor
+    pub const ALL: [ExtractorVersion; 4] = [
+        ExtractorVersion::Manual,
+        ExtractorVersion::Rule,
+        ExtractorVersion::Slm,
+        ExtractorVersion::CommandRouter,
+    ];
+
+    /// Extractor name used in tags
+    pub fn name(self) -> &'static str {
+        match self {
+            ExtractorVersion::Manual => "manual",
+            ExtractorVersion::Rule => "rule",
+            ExtractorVersion::Slm => "slm",
+            ExtractorVersion::CommandRouter => "command_router",
+        }
+    }
+
+    /// Current version of this extractor
+    pub fn version(self) -> &'static str {
+        match self {
+            ExtractorVersion::Manual => "0.1.0",
+            ExtractorVersion::Rule => "0.1.0",
+            ExtractorVersion::Slm => env!("CARGO_PKG_VERSION"),
+            ExtractorVersion::CommandRouter => "0.1.0",
+        }
+    }
+
+    /// Tag stored in `extractor_version`, e.g. `"slm@0.1.0"`
+    pub fn tag(self) -> String {
+        format!("{}@{}", self.name(), self.version())
+    }
+
+    /// Extractor for an `ExtractedEvent::method` ("slm" or "rule")
+    pub fn from_method(method: &str) -> Option<Self> {
+        match method {
+            "slm" => Some(ExtractorVersion::Slm),
+            "rule" => Some(ExtractorVersion::Rule),
+            _ => None,
+        }
+    }
+
+    /// Extractor that produced a stored tag; `None` for legacy or unknown tags
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let (name, _) = tag.split_once('@')?;
+        Self::ALL.into_iter().find(|extractor| extractor.name() == name)
+    }
+
+    /// Whether a stored tag was produced by the current version of its extractor
+    pub fn is_current(tag: &str) -> bool {
+        Self::from_tag(tag).map_or(false, |extractor| extractor.tag() == tag)
+    }
+}
+
 /// New event memory for insertion
 ///
 /// Used when creating new events from extracted information.
@@ -418,6 +604,7 @@ pub struct NewEventMemory {
     pub unit: Option<String>,
     pub confidence: f64,
     pub extractor_version: Option<String>,
+    pub metadata: Option<serde_json::Value>,
 }
 
 impl NewEventMemory {
@@ -446,7 +633,8 @@ impl NewEventMemory {
             quantity: None,
             unit: None,
             confidence: 0.5, // Default confidence
-            extractor_version: Some("0.1.0".to_string()),
+            extractor_version: Some(ExtractorVersion::Manual.tag()),
+            metadata: None,
         }
     }
 
@@ -476,6 +664,17 @@ impl NewEventMemory {
         self.extractor_version = Some(version);
         self
     }
+
+    /// Stamp the event with a registered extractor's current tag
+    pub fn with_extractor(self, extractor: ExtractorVersion) -> Self {
+        self.with_extractor_version(extractor.tag())
+    }
+
+    /// Set the extractor metadata
+    pub fn with_metadata(mut self, metadata: serde_json::Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
 }
 
 #[cfg(test)]
@@ -500,6 +699,40 @@ mod event_tests {
         assert_eq!(event.confidence, 0.5);
         assert!(event.actor.is_none());
         assert!(event.quantity.is_none());
+        assert_eq!(event.extractor_version, Some(ExtractorVersion::Manual.tag()));
+    }
+
+    #[test]
+    fn test_event_stamped_with_registry_version() {
+        let event = NewEventMemory::new(
+            Uuid::new_v4(),
+            "user123".to_string(),
+            chrono::Utc::now(),
+            "eat".to_string(),
+            "apple".to_string(),
+        )
+        .with_extractor(ExtractorVersion::Slm);
+
+        let tag = event.extractor_version.unwrap();
+        assert_eq!(tag, format!("slm@{}", env!("CARGO_PKG_VERSION")));
+        assert_eq!(ExtractorVersion::from_tag(&tag), Some(ExtractorVersion::Slm));
+        assert!(ExtractorVersion::is_current(&tag));
+    }
+
+    #[test]
+    fn test_extractor_version_tags() {
+        for extractor in ExtractorVersion::ALL {
+            assert_eq!(ExtractorVersion::from_tag(&extractor.tag()), Some(extractor));
+        }
+
+        assert_eq!(ExtractorVersion::from_method("rule"), Some(ExtractorVersion::Rule));
+        assert_eq!(ExtractorVersion::from_method("test"), None);
+
+        // Older version of a known extractor, and legacy untagged values
+        assert_eq!(ExtractorVersion::from_tag("rule@0.0.1"), Some(ExtractorVersion::Rule));
+        assert!(!ExtractorVersion::is_current("rule@0.0.1"));
+        assert_eq!(ExtractorVersion::from_tag("0.1.0"), None);
+        assert_eq!(ExtractorVersion::from_tag("unknown@1.0"), None);
     }
 
     #[test]
@@ -533,6 +766,42 @@ mod event_tests {
         assert_eq!(event.unit, Some("个".to_string()));
     }
 
+    #[test]
+    fn test_event_metadata_flags() {
+        let new_event = NewEventMemory::new(
+            Uuid::new_v4(),
+            "user123".to_string(),
+            chrono::Utc::now(),
+            "吃".to_string(),
+            "苹果".to_string(),
+        );
+        assert_eq!(new_event.metadata, None);
+        let new_event = new_event.with_metadata(serde_json::json!({"negated": true}));
+
+        let event = EventMemory {
+            event_id: Uuid::new_v4(),
+            memory_id: new_event.memory_id,
+            user_id: new_event.user_id,
+            timestamp: new_event.timestamp,
+            actor: None,
+            action: new_event.action,
+            target: new_event.target,
+            quantity: None,
+            unit: None,
+            confidence: new_event.confidence,
+            extractor_version: None,
+            metadata: new_event.metadata,
+        };
+        assert!(event.is_negated());
+        assert_eq!(event.metadata_value(EVENT_SENTIMENT_KEY), None);
+
+        let plain = EventMemory {
+            metadata: None,
+            ..event
+        };
+        assert!(!plain.is_negated());
+    }
+
     #[test]
     fn test_event_with_confidence() {
         let memory_id = Uuid::new_v4();
@@ -562,6 +831,7 @@ mod event_tests {
             unit: Some("个".to_string()),
             confidence: 0.9,
             extractor_version: Some("0.1.0".to_string()),
+            metadata: None,
         };
 
         let desc = event.description();
@@ -582,6 +852,7 @@ mod event_tests {
             unit: Some("个".to_string()),
             confidence: 0.9,
             extractor_version: Some("0.1.0".to_string()),
+            metadata: None,
         };
 
         let desc = event.description();
@@ -602,6 +873,7 @@ mod event_tests {
             unit: None,
             confidence: 0.7,
             extractor_version: Some("0.1.0".to_string()),
+            metadata: None,
         };
 
         let desc = event.description();
@@ -622,6 +894,7 @@ mod event_tests {
             unit: None,
             confidence: 0.8,
             extractor_version: Some("0.1.0".to_string()),
+            metadata: None,
         };
 
         assert!(event.validate().is_ok());
@@ -641,6 +914,7 @@ mod event_tests {
             unit: None,
             confidence: 1.5,
             extractor_version: Some("0.1.0".to_string()),
+            metadata: None,
         };
 
         assert!(event.validate().is_err());
@@ -660,6 +934,7 @@ mod event_tests {
             unit: None,
             confidence: -0.1,
             extractor_version: Some("0.1.0".to_string()),
+            metadata: None,
         };
 
         assert!(event.validate().is_err());
@@ -679,6 +954,7 @@ mod event_tests {
             unit: None,
             confidence: 0.8,
             extractor_version: Some("0.1.0".to_string()),
+            metadata: None,
         };
 
         assert!(event.validate().is_err());
@@ -698,6 +974,7 @@ mod event_tests {
             unit: None,
             confidence: 0.85,
             extractor_version: Some("0.1.0".to_string()),
+            metadata: None,
         };
 
         assert!(event.is_high_confidence(0.8));
@@ -718,6 +995,7 @@ mod event_tests {
             unit: Some("个".to_string()),
             confidence: 0.9,
             extractor_version: Some("0.1.0".to_string()),
+            metadata: None,
         };
 
         let event_no_qty = EventMemory {
@@ -733,8 +1011,11 @@ mod event_tests {
 
 /// Entity type enumeration
 ///
-/// Defines the type of entity extracted from events.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Defines the type of entity extracted from events. Types outside the
+/// built-in taxonomy (e.g. "medication", "project") are kept as `Custom`
+/// rather than collapsed to `Object`. Serialized as its stored name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum EntityType {
     /// Person (人名、角色)
     Person,
@@ -748,46 +1029,157 @@ pub enum EntityType {
     Organization,
     /// Event (事件名称)
     Event,
+    /// Domain-specific type, stored trimmed and lowercase
+    Custom(String),
+}
+
+impl EntityType {
+    /// Stored name of the type
+    pub fn as_str(&self) -> &str {
+        match self {
+            EntityType::Person => "person",
+            EntityType::Place => "place",
+            EntityType::Object => "object",
+            EntityType::Concept => "concept",
+            EntityType::Organization => "organization",
+            EntityType::Event => "event",
+            EntityType::Custom(name) => name,
+        }
+    }
+
+    /// Check if the type is outside the built-in taxonomy
+    pub fn is_custom(&self) -> bool {
+        matches!(self, EntityType::Custom(_))
+    }
 }
 
 impl From<String> for EntityType {
     fn from(s: String) -> Self {
-        match s.to_lowercase().as_str() {
+        match s.trim().to_lowercase().as_str() {
             "person" => EntityType::Person,
             "place" => EntityType::Place,
             "object" => EntityType::Object,
             "concept" => EntityType::Concept,
             "organization" => EntityType::Organization,
             "event" => EntityType::Event,
-            _ => EntityType::Object, // Default fallback
+            "" => EntityType::Object, // Untyped entities
+            other => EntityType::Custom(other.to_string()),
         }
     }
 }
 
+impl From<&str> for EntityType {
+    fn from(s: &str) -> Self {
+        s.to_string().into()
+    }
+}
+
 impl From<EntityType> for String {
     fn from(et: EntityType) -> Self {
         match et {
-            EntityType::Person => "person".to_string(),
-            EntityType::Place => "place".to_string(),
-            EntityType::Object => "object".to_string(),
-            EntityType::Concept => "concept".to_string(),
-            EntityType::Organization => "organization".to_string(),
-            EntityType::Event => "event".to_string(),
+            EntityType::Custom(name) => name,
+            builtin => builtin.as_str().to_string(),
         }
     }
 }
 
-impl From<EntityType> for &'static str {
-    fn from(et: EntityType) -> Self {
-        match et {
-            EntityType::Person => "person",
-            EntityType::Place => "place",
-            EntityType::Object => "object",
-            EntityType::Concept => "concept",
-            EntityType::Organization => "organization",
-            EntityType::Event => "event",
+impl std::fmt::Display for EntityType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Attributes key holding the original spellings an entity was mentioned by
+pub const SURFACE_FORMS_KEY: &str = "surface_forms";
+
+/// English honorifics stripped from the front of person names
+const PERSON_HONORIFIC_PREFIXES: &[&str] = &["mr", "mrs", "ms", "miss", "dr", "prof"];
+
+/// Chinese honorifics stripped from the end of person names
+const PERSON_HONORIFIC_SUFFIXES: &[&str] = &["先生", "女士", "小姐", "太太"];
+
+/// Normalize an entity name to its canonical form
+///
+/// Deterministic, so every spelling of the same name maps to the same row:
+/// - Full-width ASCII and the ideographic space become half-width
+/// - Whitespace is trimmed and inner runs collapse to a single space
+/// - `Person` names lose honorifics ("Mr. Smith" → "Smith", "王先生" → "王")
+/// - Latin letters are case-folded, then the first letter is capitalized
+///   ("APPLE" → "Apple"); CJK text is unaffected
+pub fn canonicalize_name(raw: &str, entity_type: EntityType) -> String {
+    let half_width: String = raw
+        .chars()
+        .map(|c| match c {
+            '\u{3000}' => ' ',
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        })
+        .collect();
+    let mut name = half_width.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if entity_type == EntityType::Person {
+        name = strip_person_honorifics(&name);
+    }
+
+    let folded = name.to_lowercase();
+    let mut chars = folded.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => folded,
+    }
+}
+
+/// Remove one leading English and one trailing Chinese honorific, never
+/// leaving the name empty
+fn strip_person_honorifics(name: &str) -> String {
+    let mut name = name;
+
+    if let Some((first, rest)) = name.split_once(' ') {
+        let word = first.trim_end_matches('.').to_lowercase();
+        if PERSON_HONORIFIC_PREFIXES.contains(&word.as_str()) && !rest.is_empty() {
+            name = rest;
         }
     }
+    for suffix in PERSON_HONORIFIC_SUFFIXES {
+        if let Some(stripped) = name.strip_suffix(suffix) {
+            if !stripped.trim().is_empty() {
+                name = stripped.trim_end();
+                break;
+            }
+        }
+    }
+
+    name.to_string()
+}
+
+/// Record `surface_form` under `attributes.surface_forms`
+///
+/// Forms identical to `canonical_name` (after trimming) are not recorded,
+/// and each form is kept once.
+pub fn add_surface_form(
+    attributes: Option<serde_json::Value>,
+    surface_form: &str,
+    canonical_name: &str,
+) -> Option<serde_json::Value> {
+    let form = surface_form.trim();
+    if form.is_empty() || form == canonical_name {
+        return attributes;
+    }
+
+    let mut attributes = match attributes {
+        Some(serde_json::Value::Object(map)) => map,
+        Some(other) => return Some(other),
+        None => serde_json::Map::new(),
+    };
+    let forms = attributes
+        .entry(SURFACE_FORMS_KEY)
+        .or_insert_with(|| serde_json::json!([]));
+    if let Some(list) = forms.as_array_mut() {
+        if !list.iter().any(|existing| existing.as_str() == Some(form)) {
+            list.push(serde_json::Value::String(form.to_string()));
+        }
+    }
+    Some(serde_json::Value::Object(attributes))
 }
 
 /// Entity representation - Layer 2 of Structured Memory
@@ -798,7 +1190,7 @@ impl From<EntityType> for &'static str {
 /// # Memory Safety Notes
 /// - Uses JSONB for attributes (flexible schema)
 /// - Occurrence count tracks entity importance
-#[derive(Debug, Clone, Queryable, Identifiable, Serialize, Deserialize)]
+#[derive(Debug, Clone, Queryable, QueryableByName, Identifiable, Serialize, Deserialize)]
 #[diesel(table_name = entities)]
 #[diesel(primary_key(entity_id))]
 pub struct Entity {
@@ -808,7 +1200,7 @@ pub struct Entity {
     pub user_id: String,
     /// Standard/canonical name (e.g., "Apple" for "苹果")
     pub canonical_name: String,
-    /// Entity type (person/place/object/concept/organization)
+    /// Entity type (person/place/object/concept/organization or a custom type)
     pub entity_type: String,
     /// Dynamic attributes stored as JSONB
     pub attributes: Option<serde_json::Value>,
@@ -823,6 +1215,11 @@ pub struct Entity {
 }
 
 impl Entity {
+    /// Parsed entity type; unknown names are `Custom`
+    pub fn get_type(&self) -> EntityType {
+        self.entity_type.as_str().into()
+    }
+
     /// Check if this is a high-confidence entity
     pub fn is_high_confidence(&self, threshold: f64) -> bool {
         self.confidence >= threshold
@@ -837,6 +1234,93 @@ impl Entity {
     }
 }
 
+/// Weights for `entity_importance`
+///
+/// Importance is `ln(1 + occurrence_count)^frequency_weight ×
+/// recency^recency_weight`, where recency halves every `half_life_days`
+/// since `last_seen`. A weight of 0 ignores that factor.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntityImportanceConfig {
+    /// Exponent of the frequency factor (default: 1.0)
+    pub frequency_weight: f64,
+    /// Exponent of the recency factor (default: 1.0)
+    pub recency_weight: f64,
+    /// Days after which recency halves (default: 30)
+    pub half_life_days: f64,
+}
+
+impl Default for EntityImportanceConfig {
+    fn default() -> Self {
+        Self {
+            frequency_weight: 1.0,
+            recency_weight: 1.0,
+            half_life_days: 30.0,
+        }
+    }
+}
+
+impl EntityImportanceConfig {
+    /// Validate the weights
+    pub fn validate(&self) -> crate::error::Result<()> {
+        let weights = [
+            ("frequency_weight", self.frequency_weight),
+            ("recency_weight", self.recency_weight),
+        ];
+        for (name, weight) in weights {
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(crate::error::DirSoulError::Config(format!(
+                    "{} must be a non-negative number, got {}",
+                    name, weight
+                )));
+            }
+        }
+        if !self.half_life_days.is_finite() || self.half_life_days <= 0.0 {
+            return Err(crate::error::DirSoulError::Config(format!(
+                "half_life_days must be positive, got {}",
+                self.half_life_days
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Importance of an entity at `now`, combining frequency and recency
+///
+/// Used to pick which entities to show, summarize or link first; see
+/// `EntityImportanceConfig` for the formula. Entities seen after `now`
+/// count as seen at `now`.
+pub fn entity_importance(
+    entity: &Entity,
+    now: chrono::DateTime<chrono::Utc>,
+    config: &EntityImportanceConfig,
+) -> f64 {
+    let frequency = (1.0 + entity.occurrence_count.max(0) as f64).ln();
+    let age_days = (now - entity.last_seen).num_seconds().max(0) as f64 / 86_400.0;
+    let recency = 0.5f64.powf(age_days / config.half_life_days);
+    frequency.powf(config.frequency_weight) * recency.powf(config.recency_weight)
+}
+
+/// Sort entities by descending `entity_importance`, ties by name
+pub fn rank_entities_by_importance(
+    entities: Vec<Entity>,
+    now: chrono::DateTime<chrono::Utc>,
+    config: &EntityImportanceConfig,
+) -> Vec<(Entity, f64)> {
+    let mut ranked: Vec<(Entity, f64)> = entities
+        .into_iter()
+        .map(|entity| {
+            let importance = entity_importance(&entity, now, config);
+            (entity, importance)
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.1.total_cmp(&a.1)
+            .then_with(|| a.0.canonical_name.cmp(&b.0.canonical_name))
+    });
+    ranked
+}
+
 /// New entity for insertion
 ///
 /// Used when creating new entities from event extraction.
@@ -856,9 +1340,12 @@ pub struct NewEntity {
 impl NewEntity {
     /// Create a new entity
     ///
+    /// The name is passed through `canonicalize_name`; if that changes it,
+    /// the name as given is kept in `attributes.surface_forms`.
+    ///
     /// # Arguments
     /// * `user_id` - Owner of the entity
-    /// * `canonical_name` - Standard name
+    /// * `canonical_name` - Name as mentioned
     /// * `entity_type` - Type of entity
     pub fn new(
         user_id: String,
@@ -866,11 +1353,12 @@ impl NewEntity {
         entity_type: EntityType,
     ) -> Self {
         let now = chrono::Utc::now();
+        let canonical = canonicalize_name(&canonical_name, entity_type.clone());
         Self {
             user_id,
-            canonical_name,
+            attributes: add_surface_form(Some(serde_json::json!({})), &canonical_name, &canonical),
+            canonical_name: canonical,
             entity_type: String::from(entity_type),
-            attributes: Some(serde_json::json!({})),
             first_seen: now,
             last_seen: now,
             occurrence_count: 1,
@@ -879,11 +1367,28 @@ impl NewEntity {
     }
 
     /// Set attributes for the entity
-    pub fn with_attributes(mut self, attributes: serde_json::Value) -> Self {
+    ///
+    /// Surface forms recorded so far are kept unless `attributes` sets its own.
+    pub fn with_attributes(mut self, mut attributes: serde_json::Value) -> Self {
+        let surface_forms = self
+            .attributes
+            .as_ref()
+            .and_then(|attrs| attrs.get(SURFACE_FORMS_KEY))
+            .cloned();
+        if let (Some(forms), Some(map)) = (surface_forms, attributes.as_object_mut()) {
+            map.entry(SURFACE_FORMS_KEY).or_insert(forms);
+        }
         self.attributes = Some(attributes);
         self
     }
 
+    /// Record another spelling this entity was mentioned by
+    pub fn with_surface_form(mut self, surface_form: &str) -> Self {
+        self.attributes =
+            add_surface_form(self.attributes.take(), surface_form, &self.canonical_name);
+        self
+    }
+
     /// Set confidence level
     pub fn with_confidence(mut self, confidence: f64) -> Self {
         self.confidence = confidence;
@@ -915,8 +1420,15 @@ pub struct EntityRelation {
     pub first_seen: chrono::DateTime<chrono::Utc>,
     /// Most recent time this relationship was observed
     pub last_seen: chrono::DateTime<chrono::Utc>,
-    /// Strength of relationship (based on co-occurrence frequency)
+    /// Count-based weight `count / (count + k)` in [0, 1), comparable
+    /// across entity pairs (see `RelationExtractorConfig::count_strength`)
     pub strength: f64,
+    /// Times this relationship was extracted
+    pub observation_count: i32,
+    /// Co-occurrence coefficient of the two entities in recent events, 0-1
+    /// (maintained by `recompute_all_strengths`)
+    #[serde(default)]
+    pub co_occurrence: f64,
 }
 
 /// New entity relation for insertion
@@ -931,6 +1443,8 @@ pub struct NewEntityRelation {
     pub first_seen: chrono::DateTime<chrono::Utc>,
     pub last_seen: chrono::DateTime<chrono::Utc>,
     pub strength: f64,
+    pub observation_count: i32,
+    pub co_occurrence: f64,
 }
 
 impl NewEntityRelation {
@@ -957,6 +1471,8 @@ impl NewEntityRelation {
             first_seen: now,
             last_seen: now,
             strength: 1.0,
+            observation_count: 1,
+            co_occurrence: 0.0,
         }
     }
 
@@ -987,6 +1503,37 @@ mod entity_tests {
         assert_eq!(et2, EntityType::Object);
     }
 
+    #[test]
+    fn test_custom_entity_type_round_trip() {
+        let medication: EntityType = "Medication".to_string().into();
+        assert_eq!(medication, EntityType::Custom("medication".to_string()));
+        assert_ne!(medication, EntityType::Object);
+        assert!(medication.is_custom());
+
+        let s: String = medication.clone().into();
+        assert_eq!(s, "medication");
+        assert_eq!(EntityType::from(s), medication);
+
+        // Built-in names still parse to their variants
+        assert_eq!(EntityType::from(" Person "), EntityType::Person);
+        assert_eq!(EntityType::from(""), EntityType::Object);
+
+        let json = serde_json::to_string(&EntityType::Custom("project".to_string())).unwrap();
+        assert_eq!(json, "\"project\"");
+        let parsed: EntityType = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, EntityType::Custom("project".to_string()));
+    }
+
+    #[test]
+    fn test_new_entity_keeps_custom_type() {
+        let entity = NewEntity::new(
+            "user123".to_string(),
+            "Aspirin".to_string(),
+            EntityType::from("Medication"),
+        );
+        assert_eq!(entity.entity_type, "medication");
+    }
+
     #[test]
     fn test_new_entity() {
         let entity = NewEntity::new(
@@ -1016,6 +1563,68 @@ mod entity_tests {
         assert_eq!(entity.attributes, Some(serde_json::json!({"color": "red", "category": "fruit"})));
     }
 
+    #[test]
+    fn test_canonicalize_name_whitespace_and_width() {
+        assert_eq!(canonicalize_name("苹果 ", EntityType::Object), "苹果");
+        assert_eq!(canonicalize_name("\u{3000}苹果\u{3000}", EntityType::Object), "苹果");
+        assert_eq!(canonicalize_name("ＩＰｈｏｎｅ　１５", EntityType::Object), "Iphone 15");
+        assert_eq!(canonicalize_name("new   york", EntityType::Place), "New york");
+    }
+
+    #[test]
+    fn test_canonicalize_name_case_folding() {
+        for raw in ["APPLE", "apple", "aPPle", " Apple "] {
+            assert_eq!(canonicalize_name(raw, EntityType::Object), "Apple");
+        }
+        assert_eq!(canonicalize_name("Café", EntityType::Place), "Café");
+        assert_eq!(canonicalize_name("CAFÉ", EntityType::Place), "Café");
+    }
+
+    #[test]
+    fn test_canonicalize_name_person_honorifics() {
+        assert_eq!(canonicalize_name("王先生", EntityType::Person), "王");
+        assert_eq!(canonicalize_name("李 女士", EntityType::Person), "李");
+        assert_eq!(canonicalize_name("Mr. Smith", EntityType::Person), "Smith");
+        assert_eq!(canonicalize_name("dr smith", EntityType::Person), "Smith");
+        // Never strip the whole name, and only for people
+        assert_eq!(canonicalize_name("先生", EntityType::Person), "先生");
+        assert_eq!(canonicalize_name("Mr. Smith", EntityType::Object), "Mr. smith");
+    }
+
+    #[test]
+    fn test_canonicalize_name_is_stable() {
+        for (raw, entity_type) in [
+            ("  ＡＰＰＬＥ ", EntityType::Object),
+            ("Mrs. Brown", EntityType::Person),
+            ("张小姐", EntityType::Person),
+        ] {
+            let once = canonicalize_name(raw, entity_type);
+            assert_eq!(canonicalize_name(&once, entity_type), once);
+        }
+    }
+
+    #[test]
+    fn test_new_entity_keeps_surface_form() {
+        let entity = NewEntity::new(
+            "user123".to_string(),
+            "王先生".to_string(),
+            EntityType::Person,
+        )
+        .with_attributes(serde_json::json!({"job": "医生"}))
+        .with_surface_form("王 先生");
+
+        assert_eq!(entity.canonical_name, "王");
+        assert_eq!(
+            entity.attributes,
+            Some(serde_json::json!({"job": "医生", "surface_forms": ["王先生", "王 先生"]}))
+        );
+
+        // Already canonical names record nothing
+        let plain = NewEntity::new("user123".to_string(), "苹果 ".to_string(), EntityType::Object);
+        assert_eq!(plain.canonical_name, "苹果");
+        assert_eq!(plain.attributes, Some(serde_json::json!({})));
+    }
+
     #[test]
     fn test_entity_is_high_confidence() {
         let entity = Entity {
@@ -1034,6 +1643,58 @@ mod entity_tests {
         assert!(!entity.is_high_confidence(0.9));
     }
 
+    fn seen(name: &str, occurrence_count: i32, days_ago: i64) -> Entity {
+        let now = chrono::Utc::now();
+        Entity {
+            entity_id: Uuid::new_v4(),
+            user_id: "user123".to_string(),
+            canonical_name: name.to_string(),
+            entity_type: "object".to_string(),
+            attributes: None,
+            first_seen: now - chrono::Duration::days(400),
+            last_seen: now - chrono::Duration::days(days_ago),
+            occurrence_count,
+            confidence: 0.8,
+        }
+    }
+
+    #[test]
+    fn test_recent_entity_outranks_old_frequent_one() {
+        let now = chrono::Utc::now();
+        let recent = seen("咖啡", 10, 1);
+        let old = seen("奶茶", 200, 180);
+
+        let config = EntityImportanceConfig::default();
+        assert!(entity_importance(&recent, now, &config) > entity_importance(&old, now, &config));
+        let ranked = rank_entities_by_importance(vec![old.clone(), recent.clone()], now, &config);
+        assert_eq!(ranked[0].0.canonical_name, "咖啡");
+
+        // Ignoring recency falls back to frequency order
+        let frequency_only = EntityImportanceConfig {
+            recency_weight: 0.0,
+            ..Default::default()
+        };
+        let ranked = rank_entities_by_importance(vec![recent, old], now, &frequency_only);
+        assert_eq!(ranked[0].0.canonical_name, "奶茶");
+    }
+
+    #[test]
+    fn test_entity_importance_decay() {
+        let now = chrono::Utc::now();
+        let config = EntityImportanceConfig {
+            half_life_days: 10.0,
+            ..Default::default()
+        };
+        let fresh = entity_importance(&seen("a", 5, 0), now, &config);
+        let halved = entity_importance(&seen("a", 5, 10), now, &config);
+        assert!((halved / fresh - 0.5).abs() < 1e-3);
+        assert_eq!(entity_importance(&seen("a", 0, 0), now, &config), 0.0);
+
+        assert!(config.validate().is_ok());
+        assert!(EntityImportanceConfig { half_life_days: 0.0, ..config }.validate().is_err());
+        assert!(EntityImportanceConfig { recency_weight: -1.0, ..config }.validate().is_err());
+    }
+
     #[test]
     fn test_new_entity_relation() {
         let source_id = Uuid::new_v4();
@@ -7,20 +7,30 @@
 //! - **V1 Positioning**: Simple statistics (SQL) + vector similarity
 //! - **V2 Extension**: NetworkX plugin for complex patterns
 //! - **Scheduled Tasks**: Daily runs to detect emerging patterns
+//!   (`PatternDetectionScheduler::spawn` drives them on a Tokio interval)
 //!
 //! # Pattern Types
 //! - **High-frequency behavior**: Repeated actions (e.g., daily coffee)
 //! - **Trend analysis**: Changes over time (e.g., increased exercise)
 //! - **Anomaly detection**: Deviations from baseline (e.g., skipping breakfast)
 
-use crate::error::Result;
-use chrono::{Datelike, Duration, Utc};
+use crate::cognitive::ViewStatus;
+use crate::data_lifecycle::live_memory_ids;
+use crate::error::{DirSoulError, Result};
+use crate::event_extractor::ActionNormalizer;
+use crate::resource_manager::{
+    MemoryUsage, ResourceAwareScheduler, ResourceManager, ScheduledTask, TaskPriority,
+};
+use crate::view_generator::ViewGenerator;
+use crate::user_settings::UserSettings;
+use chrono::{Datelike, Duration, Timelike, Utc};
 use crate::models::EventMemory;
 use crate::schema::event_memories;
 use diesel::prelude::*;
 use diesel::pg::PgConnection;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
 /// Pattern type classification
@@ -118,6 +128,110 @@ pub struct PatternDetectionResult {
     pub time_range_start: chrono::DateTime<Utc>,
     pub time_range_end: chrono::DateTime<Utc>,
     pub detection_timestamp: chrono::DateTime<Utc>,
+    /// Why detection was skipped without looking for patterns, if it was
+    #[serde(default)]
+    pub skipped_reason: Option<String>,
+}
+
+/// Strategy used to assign `pattern_id` to detected patterns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PatternIdStrategy {
+    /// Random UUID v4 per detection run (legacy behavior)
+    #[default]
+    Random,
+    /// Stable UUID v5 derived from user_id + pattern_type + action + target,
+    /// so the same logical pattern keeps its id across runs (upsert-friendly)
+    Deterministic,
+}
+
+/// Language of pattern descriptions and the day-of-week names in them
+///
+/// Only the human-readable `description` is localized; structured fields
+/// such as the temporal `period` key stay in English.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DayNameLocale {
+    /// "Mon", "Tue", ...
+    #[default]
+    English,
+    /// "周一", "周二", ...
+    Chinese,
+}
+
+impl DayNameLocale {
+    /// Short day name for a weekday index (0 = Monday)
+    pub fn day_name(&self, dow: u32) -> &'static str {
+        const EN: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+        const ZH: [&str; 7] = ["周一", "周二", "周三", "周四", "周五", "周六", "周日"];
+        let idx = (dow % 7) as usize;
+        match self {
+            DayNameLocale::English => EN[idx],
+            DayNameLocale::Chinese => ZH[idx],
+        }
+    }
+}
+
+/// Granularity of the `typical_times` reported for high-frequency patterns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TimeBucketing {
+    /// Hour slots labeled "08:00"
+    #[default]
+    Hourly,
+    /// Half-hour slots labeled "08:00" / "08:30"
+    HalfHourly,
+    /// "night" (0-5), "morning" (5-12), "afternoon" (12-17), "evening" (17-21), "night" (21-24)
+    PartOfDay,
+}
+
+impl TimeBucketing {
+    /// Bucket label for a time of day
+    pub fn label(&self, hour: u32, minute: u32) -> String {
+        match self {
+            TimeBucketing::Hourly => format!("{:02}:00", hour),
+            TimeBucketing::HalfHourly => format!("{:02}:{:02}", hour, if minute < 30 { 0 } else { 30 }),
+            TimeBucketing::PartOfDay => match hour {
+                5..=11 => "morning",
+                12..=16 => "afternoon",
+                17..=20 => "evening",
+                _ => "night",
+            }
+            .to_string(),
+        }
+    }
+}
+
+/// Minimum share of a pattern's events a bucket needs to count as typical
+const TYPICAL_TIME_MIN_SHARE: f64 = 0.25;
+
+/// Period used by the periodic consistency metric
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConsistencyPeriod {
+    /// Each day is compared by hour-of-day slots
+    Daily,
+    /// Each week is compared by weekday slots
+    Weekly,
+}
+
+/// How `calculate_consistency` scores the regularity of a behavior
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ConsistencyMetric {
+    /// 1 - coefficient of variation of inter-event gaps (legacy behavior)
+    #[default]
+    GapVariation,
+    /// Similarity of each period's occupied slots to the habitual slots,
+    /// so clustered-but-regular habits (e.g. weekdays only) score high
+    Periodic(ConsistencyPeriod),
+}
+
+/// How `detect_anomalies` derives expected frequencies from the baseline window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AnomalyBaseline {
+    /// Average daily frequency over the whole baseline window (legacy behavior)
+    #[default]
+    FixedWindow,
+    /// Per-weekday frequencies over the baseline window, weighted by the
+    /// weekdays in the detection range, so weekday-specific habits are
+    /// compared with the same weekday of prior weeks
+    SameWeekday,
 }
 
 /// Configuration for pattern detection
@@ -133,6 +247,35 @@ pub struct PatternDetectorConfig {
     pub min_anomaly_deviation: f64,
     /// Baseline window for anomaly detection (days)
     pub anomaly_baseline_days: i32,
+    /// How expected frequencies are derived from the baseline window
+    pub anomaly_baseline: AnomalyBaseline,
+    /// How pattern ids are generated
+    pub id_strategy: PatternIdStrategy,
+    /// Minimum occurrences of an action/target before weekly patterns are checked
+    pub temporal_min_occurrences: usize,
+    /// Fraction of observed weeks a weekday must appear in to count as a weekly pattern
+    pub temporal_week_ratio: f64,
+    /// Language of day names used in temporal pattern descriptions
+    pub day_name_locale: DayNameLocale,
+    /// Metric used to score pattern consistency
+    pub consistency_metric: ConsistencyMetric,
+    /// Granularity of high-frequency `typical_times`
+    pub time_bucketing: TimeBucketing,
+    /// Minimum baseline frequency (per day) for a behavior to be reported as stopped
+    pub stopped_min_baseline_freq: f64,
+    /// Minimum relative drop from baseline for a behavior to be reported as stopped
+    pub stopped_deviation: f64,
+    /// Days a behavior must be absent before it is reported as stopped
+    pub stopped_suppression_days: i64,
+    /// Maps surface verbs to canonical actions before grouping (None keeps raw actions)
+    pub action_normalizer: Option<ActionNormalizer>,
+    /// Fewer events than this in the window skips detection entirely
+    pub min_events_for_detection: usize,
+    /// Events extracted with lower confidence are left out of detection and
+    /// pattern evidence (0 keeps every event)
+    pub min_event_confidence: f64,
+    /// Days of events analyzed by scheduled detection
+    pub detection_window_days: i64,
 }
 
 impl Default for PatternDetectorConfig {
@@ -143,7 +286,34 @@ impl Default for PatternDetectorConfig {
             min_trend_days: 7,             // 1 week minimum
             min_anomaly_deviation: 0.5,     // 50% deviation
             anomaly_baseline_days: 30,      // 30-day baseline
+            anomaly_baseline: AnomalyBaseline::FixedWindow,
+            id_strategy: PatternIdStrategy::Random,
+            temporal_min_occurrences: 4,
+            temporal_week_ratio: 0.6,       // 60% of weeks
+            day_name_locale: DayNameLocale::English,
+            consistency_metric: ConsistencyMetric::GapVariation,
+            time_bucketing: TimeBucketing::Hourly,
+            stopped_min_baseline_freq: 0.5,  // Same bar as high-frequency behavior
+            stopped_deviation: 0.5,          // 50% drop
+            stopped_suppression_days: 3,     // Absent for 3+ days
+            action_normalizer: None,
+            min_events_for_detection: 5,
+            min_event_confidence: 0.0,
+            detection_window_days: 30,
+        }
+    }
+}
+
+impl PatternDetectorConfig {
+    /// Check that `min_event_confidence` is a confidence in [0, 1]
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.min_event_confidence) {
+            return Err(DirSoulError::Config(format!(
+                "min_event_confidence must be between 0 and 1, got {}",
+                self.min_event_confidence
+            )));
         }
+        Ok(())
     }
 }
 
@@ -165,6 +335,77 @@ impl PatternDetector {
         Self { config }
     }
 
+    /// Set the language of pattern descriptions
+    pub fn with_locale(mut self, locale: DayNameLocale) -> Self {
+        self.config.day_name_locale = locale;
+        self
+    }
+
+    /// Group actions by canonical verb, so "吃了" and "eat" count as one behavior
+    pub fn with_action_normalizer(mut self, normalizer: ActionNormalizer) -> Self {
+        self.config.action_normalizer = Some(normalizer);
+        self
+    }
+
+    /// Only use events extracted with at least `confidence`
+    pub fn with_min_event_confidence(mut self, confidence: f64) -> Self {
+        self.config.min_event_confidence = confidence;
+        self
+    }
+
+    /// Drop events below `min_event_confidence`, borrowing when none are
+    fn reliable_events<'a>(&self, events: &'a [EventMemory]) -> Cow<'a, [EventMemory]> {
+        let floor = self.config.min_event_confidence;
+        if events.iter().all(|event| event.confidence >= floor) {
+            return Cow::Borrowed(events);
+        }
+        Cow::Owned(events.iter().filter(|event| event.confidence >= floor).cloned().collect())
+    }
+
+    /// Apply the configured action normalizer, borrowing when there is none
+    fn normalize_actions<'a>(&self, events: &'a [EventMemory]) -> Cow<'a, [EventMemory]> {
+        match &self.config.action_normalizer {
+            Some(normalizer) => Cow::Owned(
+                events
+                    .iter()
+                    .map(|event| EventMemory {
+                        action: normalizer.normalize(&event.action),
+                        ..event.clone()
+                    })
+                    .collect(),
+            ),
+            None => Cow::Borrowed(events),
+        }
+    }
+
+    /// Generate a pattern id according to the configured strategy
+    ///
+    /// `qualifier` distinguishes several patterns of the same type for the
+    /// same action/target (e.g. different weekdays of a weekly pattern).
+    fn generate_pattern_id(
+        &self,
+        user_id: &str,
+        pattern_type: PatternType,
+        action: &str,
+        target: &str,
+        qualifier: Option<&str>,
+    ) -> Uuid {
+        match self.config.id_strategy {
+            PatternIdStrategy::Random => Uuid::new_v4(),
+            PatternIdStrategy::Deterministic => {
+                let type_name: String = pattern_type.into();
+                // Length-prefix each field so no two field tuples share a key
+                let mut key = String::new();
+                for field in [user_id, type_name.as_str(), action, target].into_iter().chain(qualifier) {
+                    key.push_str(&field.len().to_string());
+                    key.push(':');
+                    key.push_str(field);
+                }
+                Uuid::new_v5(&Uuid::NAMESPACE_OID, key.as_bytes())
+            }
+        }
+    }
+
     /// Detect all patterns for a user within a time range
     pub fn detect_patterns(
         &self,
@@ -172,42 +413,67 @@ impl PatternDetector {
         user_id: &str,
         time_range: DetectionTimeRange,
     ) -> Result<PatternDetectionResult> {
+        self.config.validate()?;
         let events = self.fetch_events(conn, user_id, &time_range)?;
+        let baseline_events = self.fetch_baseline_events(conn, user_id, &time_range)?;
+
+        self.detect_patterns_in_events(user_id, &events, &baseline_events, time_range)
+    }
+
+    /// Detect all patterns in already-loaded events
+    ///
+    /// `events` covers `time_range`; `baseline_events` covers the
+    /// `anomaly_baseline_days` before it and is only used for anomalies.
+    /// Events below `min_event_confidence` are ignored. With fewer than
+    /// `min_events_for_detection` remaining events the detectors that only
+    /// look at the range (frequency, trend, temporal) are skipped and the
+    /// result carries a `skipped_reason`; anomaly and stopped-behavior
+    /// detection still run, since a quiet range is what they look for.
+    pub fn detect_patterns_in_events(
+        &self,
+        user_id: &str,
+        events: &[EventMemory],
+        baseline_events: &[EventMemory],
+        time_range: DetectionTimeRange,
+    ) -> Result<PatternDetectionResult> {
+        self.config.validate()?;
+        let events = &*self.reliable_events(events);
+        let baseline_events = &*self.reliable_events(baseline_events);
         let events_analyzed = events.len() as i32;
+        let skipped_reason = (events.len() < self.config.min_events_for_detection).then(|| {
+            format!(
+                "Only {} events in range; at least {} needed to detect habits, \
+                 trends and routines",
+                events.len(),
+                self.config.min_events_for_detection
+            )
+        });
+
+        let events = &*self.normalize_actions(events);
+        let baseline_events = &*self.normalize_actions(baseline_events);
 
         let mut patterns = Vec::new();
 
-        // Detect high-frequency patterns
-        patterns.extend(self.detect_high_frequency_patterns(
-            conn,
-            user_id,
-            &events,
-            &time_range,
-        )?);
+        if skipped_reason.is_none() {
+            // Detect high-frequency patterns
+            patterns.extend(self.detect_high_frequency_patterns(user_id, events, &time_range)?);
 
-        // Detect trends
-        patterns.extend(self.detect_trends(
-            conn,
-            user_id,
-            &events,
-            &time_range,
-        )?);
+            // Detect trends
+            patterns.extend(self.detect_trends(user_id, events, &time_range)?);
+        }
 
         // Detect anomalies
         patterns.extend(self.detect_anomalies(
-            conn,
             user_id,
-            &events,
+            events,
+            baseline_events,
             &time_range,
         )?);
 
         // Detect temporal patterns
-        patterns.extend(self.detect_temporal_patterns(
-            conn,
-            user_id,
-            &events,
-            &time_range,
-        )?);
+        if skipped_reason.is_none() {
+            patterns.extend(self.find_temporal_patterns(user_id, events, &time_range));
+        }
 
         Ok(PatternDetectionResult {
             patterns,
@@ -215,10 +481,11 @@ impl PatternDetector {
             time_range_start: time_range.start,
             time_range_end: time_range.end,
             detection_timestamp: Utc::now(),
+            skipped_reason,
         })
     }
 
-    /// Fetch events within time range
+    /// Fetch live events within time range at or above `min_event_confidence`
     fn fetch_events(
         &self,
         conn: &mut PgConnection,
@@ -227,18 +494,39 @@ impl PatternDetector {
     ) -> Result<Vec<EventMemory>> {
         let events = event_memories::table
             .filter(event_memories::user_id.eq(user_id))
+            .filter(event_memories::memory_id.eq_any(live_memory_ids(user_id)))
             .filter(event_memories::timestamp.ge(time_range.start))
             .filter(event_memories::timestamp.le(time_range.end))
+            .filter(event_memories::confidence.ge(self.config.min_event_confidence))
             .order(event_memories::timestamp.asc())
             .load::<EventMemory>(conn)?;
 
         Ok(events)
     }
 
+    /// Fetch the anomaly baseline window preceding the time range
+    fn fetch_baseline_events(
+        &self,
+        conn: &mut PgConnection,
+        user_id: &str,
+        time_range: &DetectionTimeRange,
+    ) -> Result<Vec<EventMemory>> {
+        let baseline_start = time_range.start - Duration::days(self.config.anomaly_baseline_days as i64);
+
+        let events = event_memories::table
+            .filter(event_memories::user_id.eq(user_id))
+            .filter(event_memories::memory_id.eq_any(live_memory_ids(user_id)))
+            .filter(event_memories::timestamp.ge(baseline_start))
+            .filter(event_memories::timestamp.lt(time_range.start))
+            .filter(event_memories::confidence.ge(self.config.min_event_confidence))
+            .load::<EventMemory>(conn)?;
+
+        Ok(events)
+    }
+
     /// Detect high-frequency patterns
     fn detect_high_frequency_patterns(
         &self,
-        conn: &mut PgConnection,
         user_id: &str,
         events: &[EventMemory],
         time_range: &DetectionTimeRange,
@@ -270,11 +558,15 @@ impl PatternDetector {
                 {
                     let pattern = DetectedPattern {
                         pattern_type: PatternType::HighFrequency,
-                        pattern_id: Uuid::new_v4(),
+                        pattern_id: self.generate_pattern_id(
+                            user_id,
+                            PatternType::HighFrequency,
+                            &action,
+                            &target,
+                            None,
+                        ),
                         user_id: user_id.to_string(),
-                        description: format!("Frequently {} {} ({} times/day)",
-                                          action, target,
-                                          format!("{:.2}", frequency_per_day)),
+                        description: self.high_frequency_description(&action, &target, frequency_per_day),
                         action,
                         target,
                         confidence: consistency_score,
@@ -283,7 +575,7 @@ impl PatternDetector {
                         metadata: PatternMetadata::HighFrequency {
                             average_frequency_per_day: frequency_per_day,
                             consistency_score,
-                            typical_times: vec![], // TODO: Extract typical times
+                            typical_times: self.typical_times(&event_list),
                         },
                         detected_at: Utc::now(),
                     };
@@ -295,8 +587,42 @@ impl PatternDetector {
         Ok(patterns)
     }
 
+    /// Time buckets holding at least `TYPICAL_TIME_MIN_SHARE` of the events
+    ///
+    /// Most frequent first; ties are ordered by label.
+    fn typical_times(&self, events: &[&EventMemory]) -> Vec<String> {
+        if events.is_empty() {
+            return Vec::new();
+        }
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for event in events {
+            let label = self
+                .config
+                .time_bucketing
+                .label(event.timestamp.hour(), event.timestamp.minute());
+            *counts.entry(label).or_default() += 1;
+        }
+
+        let min_count = events.len() as f64 * TYPICAL_TIME_MIN_SHARE;
+        let mut typical: Vec<(String, usize)> = counts
+            .into_iter()
+            .filter(|(_, count)| *count as f64 >= min_count)
+            .collect();
+        typical.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        typical.into_iter().map(|(label, _)| label).collect()
+    }
+
     /// Calculate consistency score based on regularity
-    fn calculate_consistency(&self, events: &[&EventMemory], time_span_days: f64) -> f64 {
+    fn calculate_consistency(&self, events: &[&EventMemory], _time_span_days: f64) -> f64 {
+        match self.config.consistency_metric {
+            ConsistencyMetric::GapVariation => Self::gap_variation_consistency(events),
+            ConsistencyMetric::Periodic(period) => Self::periodic_consistency(events, period),
+        }
+    }
+
+    /// Consistency from the coefficient of variation of inter-event gaps
+    fn gap_variation_consistency(events: &[&EventMemory]) -> f64 {
         if events.len() < 2 {
             return 0.0;
         }
@@ -325,10 +651,75 @@ impl PatternDetector {
         consistency
     }
 
+    /// Consistency against an expected period
+    ///
+    /// Each period (day or week) between the first and last event is reduced
+    /// to the set of slots it occupies (hours or weekdays). Slots occupied in
+    /// at least half of the periods form the habitual signature; the score is
+    /// the mean Jaccard similarity of every period to that signature.
+    fn periodic_consistency(events: &[&EventMemory], period: ConsistencyPeriod) -> f64 {
+        if events.len() < 2 {
+            return 0.0;
+        }
+
+        let first = match events.iter().map(|e| e.timestamp).min() {
+            Some(ts) => ts.date_naive(),
+            None => return 0.0,
+        };
+        let origin = match period {
+            ConsistencyPeriod::Daily => first,
+            ConsistencyPeriod::Weekly => {
+                first - Duration::days(first.weekday().num_days_from_monday() as i64)
+            }
+        };
+
+        let mut periods: HashMap<i64, HashSet<u32>> = HashMap::new();
+        for event in events {
+            let date = event.timestamp.date_naive();
+            let days = (date - origin).num_days();
+            let (index, slot) = match period {
+                ConsistencyPeriod::Daily => (days, event.timestamp.hour()),
+                ConsistencyPeriod::Weekly => (days / 7, date.weekday().num_days_from_monday()),
+            };
+            periods.entry(index).or_default().insert(slot);
+        }
+
+        // Empty periods in between count against consistency
+        let period_count = periods.keys().max().copied().unwrap_or(0) + 1;
+
+        let mut slot_counts: HashMap<u32, i64> = HashMap::new();
+        for slots in periods.values() {
+            for &slot in slots {
+                *slot_counts.entry(slot).or_insert(0) += 1;
+            }
+        }
+        let signature: HashSet<u32> = slot_counts
+            .into_iter()
+            .filter(|&(_, count)| count * 2 >= period_count)
+            .map(|(slot, _)| slot)
+            .collect();
+
+        if signature.is_empty() {
+            return 0.0;
+        }
+
+        let total_similarity: f64 = (0..period_count)
+            .map(|index| match periods.get(&index) {
+                Some(slots) => {
+                    let intersection = slots.intersection(&signature).count() as f64;
+                    let union = slots.union(&signature).count() as f64;
+                    intersection / union
+                }
+                None => 0.0,
+            })
+            .sum();
+
+        total_similarity / period_count as f64
+    }
+
     /// Detect trends (increasing/decreasing patterns)
     fn detect_trends(
         &self,
-        conn: &mut PgConnection,
         user_id: &str,
         events: &[EventMemory],
         time_range: &DetectionTimeRange,
@@ -385,11 +776,15 @@ impl PatternDetector {
             if is_significant {
                 let pattern = DetectedPattern {
                     pattern_type: PatternType::Trend,
-                    pattern_id: Uuid::new_v4(),
+                    pattern_id: self.generate_pattern_id(
+                        user_id,
+                        PatternType::Trend,
+                        &action,
+                        &target,
+                        None,
+                    ),
                     user_id: user_id.to_string(),
-                    description: format!("{} {} is {:?} ({:.0}% change)",
-                                      action, target, direction,
-                                      change_pct.abs() * 100.0),
+                    description: self.trend_description(&action, &target, direction, change_pct),
                     action: action.clone(),
                     target,
                     confidence: change_pct.abs().min(1.0),
@@ -423,37 +818,15 @@ impl PatternDetector {
     /// Detect anomalies (deviations from baseline)
     fn detect_anomalies(
         &self,
-        conn: &mut PgConnection,
         user_id: &str,
         events: &[EventMemory],
+        baseline_events: &[EventMemory],
         time_range: &DetectionTimeRange,
     ) -> Result<Vec<DetectedPattern>> {
         let mut patterns = Vec::new();
 
-        // Need baseline period
-        let baseline_start = time_range.start - Duration::days(self.config.anomaly_baseline_days as i64);
-        let baseline_end = time_range.start;
-
-        // Fetch baseline events
-        let baseline_events: Vec<EventMemory> = event_memories::table
-            .filter(event_memories::user_id.eq(user_id))
-            .filter(event_memories::timestamp.ge(baseline_start))
-            .filter(event_memories::timestamp.lt(baseline_end))
-            .load(conn)?;
-
         // Calculate baseline frequencies
-        let mut baseline_freqs: HashMap<(String, String), f64> = HashMap::new();
-        let baseline_duration = (baseline_end - baseline_start).num_days().max(1) as f64;
-
-        for event in &baseline_events {
-            let key = (event.action.clone(), event.target.clone());
-            *baseline_freqs.entry(key).or_insert(0.0) += 1.0;
-        }
-
-        // Normalize by duration
-        for freq in baseline_freqs.values_mut() {
-            *freq /= baseline_duration;
-        }
+        let baseline_freqs = self.baseline_frequencies(baseline_events, time_range);
 
         // Calculate current frequencies
         let mut current_freqs: HashMap<(String, String), f64> = HashMap::new();
@@ -488,12 +861,15 @@ impl PatternDetector {
             if deviation.abs() >= self.config.min_anomaly_deviation {
                 let pattern = DetectedPattern {
                     pattern_type: PatternType::Anomaly,
-                    pattern_id: Uuid::new_v4(),
+                    pattern_id: self.generate_pattern_id(
+                        user_id,
+                        PatternType::Anomaly,
+                        action,
+                        target,
+                        Some("deviation"),
+                    ),
                     user_id: user_id.to_string(),
-                    description: format!("Anomaly: {} {} is {:.0}% {} expected",
-                                      action, target,
-                                      deviation.abs() * 100.0,
-                                      if deviation > 0.0 { "higher than" } else { "lower than" }),
+                    description: self.anomaly_description(action, target, deviation),
                     action: action.clone(),
                     target: target.clone(),
                     confidence: deviation.abs().min(1.0),
@@ -514,48 +890,172 @@ impl PatternDetector {
         }
 
         // Check for missing patterns (things that stopped happening)
-        for ((action, target), &expected_freq) in &baseline_freqs {
-            if expected_freq >= self.config.min_frequency_threshold {
-                let current_freq = current_freqs.get(&(action.clone(), target.clone())).unwrap_or(&0.0);
+        patterns.extend(self.find_stopped_behaviors(
+            user_id,
+            &baseline_freqs,
+            &current_freqs,
+            baseline_events,
+            events,
+            time_range,
+        ));
 
-                if *current_freq < expected_freq * (1.0 - self.config.min_anomaly_deviation) {
-                    let deviation = (current_freq - expected_freq) / expected_freq;
+        Ok(patterns)
+    }
 
-                    let pattern = DetectedPattern {
-                        pattern_type: PatternType::Anomaly,
-                        pattern_id: Uuid::new_v4(),
-                        user_id: user_id.to_string(),
-                        description: format!("Anomaly: {} {} stopped (was {:.2}/day, now {:.2}/day)",
-                                          action, target, expected_freq, current_freq),
-                        action: action.clone(),
-                        target: target.clone(),
-                        confidence: deviation.abs().min(1.0),
-                        evidence_count: 0, // No occurrences in current period
-                        time_span_days: current_duration as i32,
-                        metadata: PatternMetadata::Anomaly {
-                            expected_value: expected_freq,
-                            actual_value: *current_freq,
-                            deviation_percentage: deviation,
-                            baseline_window_days: self.config.anomaly_baseline_days,
-                        },
-                        detected_at: Utc::now(),
-                    };
-                    patterns.push(pattern);
+    /// Expected daily frequency of each action/target from the baseline window
+    ///
+    /// `FixedWindow` averages over every baseline day. `SameWeekday` first
+    /// computes a per-weekday rate (occurrences on that weekday / number of
+    /// that weekday in the window) and then averages those rates over the
+    /// days of the detection range.
+    fn baseline_frequencies(
+        &self,
+        baseline_events: &[EventMemory],
+        time_range: &DetectionTimeRange,
+    ) -> HashMap<(String, String), f64> {
+        let baseline_days = (self.config.anomaly_baseline_days as i64).max(1);
+        let baseline_start = time_range.start - Duration::days(baseline_days);
+
+        match self.config.anomaly_baseline {
+            AnomalyBaseline::FixedWindow => {
+                let mut freqs: HashMap<(String, String), f64> = HashMap::new();
+                for event in baseline_events {
+                    let key = (event.action.clone(), event.target.clone());
+                    *freqs.entry(key).or_insert(0.0) += 1.0;
                 }
+
+                // Normalize by duration
+                for freq in freqs.values_mut() {
+                    *freq /= baseline_days as f64;
+                }
+                freqs
+            }
+            AnomalyBaseline::SameWeekday => {
+                // Number of each weekday in the baseline window
+                let mut weekdays_in_baseline = [0u32; 7];
+                for day in 0..baseline_days {
+                    let dow = (baseline_start + Duration::days(day)).weekday().num_days_from_monday();
+                    weekdays_in_baseline[dow as usize] += 1;
+                }
+
+                // Share of the detection range falling on each weekday
+                let current_days = (time_range.end - time_range.start).num_days().max(1);
+                let mut weekday_weights = [0.0f64; 7];
+                for day in 0..current_days {
+                    let dow = (time_range.start + Duration::days(day)).weekday().num_days_from_monday();
+                    weekday_weights[dow as usize] += 1.0 / current_days as f64;
+                }
+
+                let mut counts: HashMap<(String, String), [u32; 7]> = HashMap::new();
+                for event in baseline_events {
+                    let key = (event.action.clone(), event.target.clone());
+                    let dow = event.timestamp.weekday().num_days_from_monday();
+                    counts.entry(key).or_insert([0; 7])[dow as usize] += 1;
+                }
+
+                counts
+                    .into_iter()
+                    .map(|(key, by_weekday)| {
+                        let freq = (0..7)
+                            .filter(|&dow| weekdays_in_baseline[dow] > 0)
+                            .map(|dow| {
+                                weekday_weights[dow] * by_weekday[dow] as f64
+                                    / weekdays_in_baseline[dow] as f64
+                            })
+                            .sum();
+                        (key, freq)
+                    })
+                    .collect()
             }
         }
+    }
 
-        Ok(patterns)
+    /// Find behaviors that were regular in the baseline but have stopped
+    ///
+    /// A behavior qualifies when its baseline frequency is at least
+    /// `stopped_min_baseline_freq`, its current frequency dropped by at least
+    /// `stopped_deviation`, and its last occurrence is at least
+    /// `stopped_suppression_days` before the end of the range, so behaviors
+    /// that merely taper or skipped a day are not reported.
+    fn find_stopped_behaviors(
+        &self,
+        user_id: &str,
+        baseline_freqs: &HashMap<(String, String), f64>,
+        current_freqs: &HashMap<(String, String), f64>,
+        baseline_events: &[EventMemory],
+        events: &[EventMemory],
+        time_range: &DetectionTimeRange,
+    ) -> Vec<DetectedPattern> {
+        let mut patterns = Vec::new();
+        let current_duration = (time_range.end - time_range.start).num_days().max(1) as f64;
+
+        let mut last_seen: HashMap<(&str, &str), chrono::DateTime<Utc>> = HashMap::new();
+        for event in baseline_events.iter().chain(events) {
+            let seen = last_seen
+                .entry((event.action.as_str(), event.target.as_str()))
+                .or_insert(event.timestamp);
+            *seen = (*seen).max(event.timestamp);
+        }
+
+        for ((action, target), &expected_freq) in baseline_freqs {
+            if expected_freq < self.config.stopped_min_baseline_freq {
+                continue;
+            }
+
+            let current_freq = current_freqs.get(&(action.clone(), target.clone())).unwrap_or(&0.0);
+            if *current_freq >= expected_freq * (1.0 - self.config.stopped_deviation) {
+                continue;
+            }
+
+            let absent_days = last_seen
+                .get(&(action.as_str(), target.as_str()))
+                .map(|seen| (time_range.end - *seen).num_days())
+                .unwrap_or(i64::MAX);
+            if absent_days < self.config.stopped_suppression_days {
+                continue;
+            }
+
+            let deviation = (current_freq - expected_freq) / expected_freq;
+
+            let pattern = DetectedPattern {
+                pattern_type: PatternType::Anomaly,
+                pattern_id: self.generate_pattern_id(
+                    user_id,
+                    PatternType::Anomaly,
+                    action,
+                    target,
+                    Some("stopped"),
+                ),
+                user_id: user_id.to_string(),
+                description: self.stopped_description(action, target, expected_freq, *current_freq),
+                action: action.clone(),
+                target: target.clone(),
+                confidence: deviation.abs().min(1.0),
+                evidence_count: events.iter()
+                    .filter(|e| &e.action == action && &e.target == target)
+                    .count() as i32,
+                time_span_days: current_duration as i32,
+                metadata: PatternMetadata::Anomaly {
+                    expected_value: expected_freq,
+                    actual_value: *current_freq,
+                    deviation_percentage: deviation,
+                    baseline_window_days: self.config.anomaly_baseline_days,
+                },
+                detected_at: Utc::now(),
+            };
+            patterns.push(pattern);
+        }
+
+        patterns
     }
 
-    /// Detect temporal patterns (daily, weekly, monthly)
-    fn detect_temporal_patterns(
+    /// Find weekly patterns in already-loaded events
+    fn find_temporal_patterns(
         &self,
-        conn: &mut PgConnection,
         user_id: &str,
         events: &[EventMemory],
         time_range: &DetectionTimeRange,
-    ) -> Result<Vec<DetectedPattern>> {
+    ) -> Vec<DetectedPattern> {
         let mut patterns = Vec::new();
 
         // Group by action + target
@@ -567,8 +1067,8 @@ impl PatternDetector {
 
         // Check for weekly patterns (same day of week)
         for ((action, target), event_list) in action_groups {
-            if event_list.len() < 4 {
-                continue; // Need at least 4 occurrences
+            if event_list.len() < self.config.temporal_min_occurrences {
+                continue;
             }
 
             // Group by day of week
@@ -584,23 +1084,27 @@ impl PatternDetector {
             for (dow, occurrence_indices) in dow_counts {
                 let frequency = occurrence_indices.len() as f64 / time_span_weeks;
 
-                // If happens on this day more than 60% of weeks
-                if frequency >= 0.6 {
-                    let day_names = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+                if frequency >= self.config.temporal_week_ratio {
+                    // Period keys stay English so they are locale-independent
+                    let period = format!("weekly_{}", DayNameLocale::English.day_name(dow));
                     let pattern = DetectedPattern {
                         pattern_type: PatternType::Temporal,
-                        pattern_id: Uuid::new_v4(),
+                        pattern_id: self.generate_pattern_id(
+                            user_id,
+                            PatternType::Temporal,
+                            &action,
+                            &target,
+                            Some(&period),
+                        ),
                         user_id: user_id.to_string(),
-                        description: format!("Weekly pattern: {} {} on {}s ({:.0}% of weeks)",
-                                          action, target, day_names[dow as usize],
-                                          frequency * 100.0),
+                        description: self.weekly_description(&action, &target, dow, frequency),
                         action: action.clone(),
                         target: target.clone(),
                         confidence: frequency,
                         evidence_count: occurrence_indices.len() as i32,
                         time_span_days: (time_range.end - time_range.start).num_days() as i32,
                         metadata: PatternMetadata::Temporal {
-                            period: format!("weekly_{}", day_names[dow as usize]),
+                            period,
                             occurrences_at_period: occurrence_indices.len() as i32,
                             total_periods_observed: time_span_weeks.ceil() as i32,
                         },
@@ -611,7 +1115,88 @@ impl PatternDetector {
             }
         }
 
-        Ok(patterns)
+        patterns
+    }
+
+    /// Human-readable description of a high-frequency pattern
+    fn high_frequency_description(&self, action: &str, target: &str, frequency_per_day: f64) -> String {
+        match self.config.day_name_locale {
+            DayNameLocale::English => format!(
+                "Frequently {} {} ({:.2} times/day)",
+                action, target, frequency_per_day
+            ),
+            DayNameLocale::Chinese => format!(
+                "高频行为: {}{} (每天 {:.2} 次)",
+                action, target, frequency_per_day
+            ),
+        }
+    }
+
+    /// Human-readable description of a trend
+    fn trend_description(&self, action: &str, target: &str, direction: TrendDirection, change_pct: f64) -> String {
+        match self.config.day_name_locale {
+            DayNameLocale::English => format!(
+                "{} {} is {:?} ({:.0}% change)",
+                action, target, direction, change_pct.abs() * 100.0
+            ),
+            DayNameLocale::Chinese => {
+                let direction = match direction {
+                    TrendDirection::Increasing => "上升",
+                    TrendDirection::Decreasing => "下降",
+                    TrendDirection::Stable => "平稳",
+                };
+                format!(
+                    "{}{}呈{}趋势 (变化 {:.0}%)",
+                    action, target, direction, change_pct.abs() * 100.0
+                )
+            }
+        }
+    }
+
+    /// Human-readable description of a deviation from the baseline
+    fn anomaly_description(&self, action: &str, target: &str, deviation: f64) -> String {
+        match self.config.day_name_locale {
+            DayNameLocale::English => format!(
+                "Anomaly: {} {} is {:.0}% {} expected",
+                action, target, deviation.abs() * 100.0,
+                if deviation > 0.0 { "higher than" } else { "lower than" }
+            ),
+            DayNameLocale::Chinese => format!(
+                "异常: {}{}比预期{} {:.0}%",
+                action, target,
+                if deviation > 0.0 { "高" } else { "低" },
+                deviation.abs() * 100.0
+            ),
+        }
+    }
+
+    /// Human-readable description of a stopped behavior
+    fn stopped_description(&self, action: &str, target: &str, expected_freq: f64, current_freq: f64) -> String {
+        match self.config.day_name_locale {
+            DayNameLocale::English => format!(
+                "Anomaly: {} {} stopped (was {:.2}/day, now {:.2}/day)",
+                action, target, expected_freq, current_freq
+            ),
+            DayNameLocale::Chinese => format!(
+                "异常: {}{}已停止 (此前每天 {:.2} 次, 现在每天 {:.2} 次)",
+                action, target, expected_freq, current_freq
+            ),
+        }
+    }
+
+    /// Human-readable description of a weekly pattern in the configured locale
+    fn weekly_description(&self, action: &str, target: &str, dow: u32, frequency: f64) -> String {
+        let locale = self.config.day_name_locale;
+        match locale {
+            DayNameLocale::English => format!(
+                "Weekly pattern: {} {} on {}s ({:.0}% of weeks)",
+                action, target, locale.day_name(dow), frequency * 100.0
+            ),
+            DayNameLocale::Chinese => format!(
+                "每周规律: 每{}{}{} ({:.0}% 的周)",
+                locale.day_name(dow), action, target, frequency * 100.0
+            ),
+        }
     }
 }
 
@@ -641,9 +1226,36 @@ impl DetectionTimeRange {
     }
 }
 
+/// Pooled Postgres connections shared by parallel detection workers
+pub type PgPool = diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<PgConnection>>;
+
+/// Default number of users analyzed concurrently by `run_daily_detection`
+pub const DEFAULT_DETECTION_WORKERS: usize = 4;
+
+/// Outcome of a multi-user detection run
+#[derive(Debug)]
+pub struct DailyDetectionReport<T = PatternDetectionResult> {
+    /// Results keyed by user id
+    pub results: HashMap<String, T>,
+    /// Error message for every user whose detection failed
+    pub failures: HashMap<String, String>,
+}
+
+impl<T> Default for DailyDetectionReport<T> {
+    fn default() -> Self {
+        Self {
+            results: HashMap::new(),
+            failures: HashMap::new(),
+        }
+    }
+}
+
 /// Scheduled task runner for daily pattern detection
 pub struct PatternDetectionScheduler {
     detector: PatternDetector,
+    view_generator: ViewGenerator,
+    max_workers: usize,
+    resource_manager: Option<ResourceManager>,
 }
 
 impl PatternDetectionScheduler {
@@ -651,35 +1263,306 @@ impl PatternDetectionScheduler {
     pub fn new() -> Self {
         Self {
             detector: PatternDetector::new(),
+            view_generator: ViewGenerator::new(),
+            max_workers: DEFAULT_DETECTION_WORKERS,
+            resource_manager: None,
         }
     }
 
+    /// Set the maximum number of users analyzed concurrently (at least 1)
+    pub fn with_max_workers(mut self, max_workers: usize) -> Self {
+        self.max_workers = max_workers.max(1);
+        self
+    }
+
+    /// Detect with `detector`, e.g. one with a `min_event_confidence` floor,
+    /// so only reliable events back the generated views
+    pub fn with_detector(mut self, detector: PatternDetector) -> Self {
+        self.detector = detector;
+        self
+    }
+
+    /// Generate (and deduplicate) persisted views with `view_generator`
+    pub fn with_view_generator(mut self, view_generator: ViewGenerator) -> Self {
+        self.view_generator = view_generator;
+        self
+    }
+
+    /// Throttle parallelism according to the memory reported by `manager`
+    pub fn with_resource_manager(mut self, manager: ResourceManager) -> Self {
+        self.resource_manager = Some(manager);
+        self
+    }
+
     /// Run pattern detection for all users
+    ///
+    /// Users are analyzed in parallel, each on its own pooled connection,
+    /// with their own locale setting applied.
+    /// A failing user is recorded in `failures` and does not stop the
+    /// others. With a resource manager attached, memory is re-checked before
+    /// every user and the number of active workers shrinks under pressure.
     pub fn run_daily_detection(
         &self,
-        conn: &mut PgConnection,
+        pool: &PgPool,
         user_ids: &[String],
-    ) -> Result<HashMap<String, PatternDetectionResult>> {
-        let mut results = HashMap::new();
+    ) -> Result<DailyDetectionReport> {
+        let window_days = self.detector.config.detection_window_days;
+        let time_range = DetectionTimeRange::last_n_days(window_days);
+
+        let report = detect_users_parallel(
+            user_ids,
+            self.max_workers,
+            || self.allowed_workers(),
+            |user_id| {
+                let mut conn = pool.get().map_err(|e| {
+                    DirSoulError::DatabaseConnection(diesel::ConnectionError::BadConnection(
+                        e.to_string(),
+                    ))
+                })?;
+                let settings = UserSettings::get(&mut conn, user_id)?;
+                PatternDetector::with_config(settings.pattern_config(&self.detector.config))
+                    .detect_patterns(&mut conn, user_id, time_range.clone())
+            },
+        );
+
+        for (user_id, error) in &report.failures {
+            tracing::warn!("Failed to detect patterns for user {}: {}", user_id, error);
+        }
 
-        // Analyze last 30 days for patterns
-        let time_range = DetectionTimeRange::last_n_days(30);
+        Ok(report)
+    }
 
-        for user_id in user_ids {
-            match self.detector.detect_patterns(conn, user_id, time_range.clone()) {
-                Ok(result) => {
-                    results.insert(user_id.clone(), result);
-                }
-                Err(e) => {
-                    eprintln!("Failed to detect patterns for user {}: {}", user_id, e);
-                }
+    /// Worker count allowed by the current memory usage
+    fn allowed_workers(&self) -> usize {
+        let Some(manager) = &self.resource_manager else {
+            return self.max_workers;
+        };
+        match manager.get_memory_usage() {
+            Ok(usage) => throttled_workers(self.max_workers, &usage),
+            Err(e) => {
+                tracing::warn!("Memory check failed, running pattern detection serially: {}", e);
+                1
             }
         }
+    }
+
+    /// Store views for a detection result, skipping duplicates of stored views
+    ///
+    /// Views come from `generator`, which also decides (by action/target or
+    /// hypothesis similarity) which of them duplicate an active or promoted
+    /// view. Returns the number of views inserted.
+    pub fn persist_result(
+        conn: &mut PgConnection,
+        user_id: &str,
+        result: &PatternDetectionResult,
+        generator: &ViewGenerator,
+    ) -> Result<usize> {
+        use crate::cognitive::CognitiveView;
+        use crate::schema::cognitive_views;
+
+        let views = generator.generate_views_from_result(result, user_id)?;
+        let existing: Vec<CognitiveView> = cognitive_views::table
+            .filter(cognitive_views::user_id.eq(user_id))
+            .filter(cognitive_views::status.eq_any([
+                String::from(ViewStatus::Active),
+                String::from(ViewStatus::Promoted),
+            ]))
+            .load(conn)?;
+
+        let fresh = generator.drop_existing(views, &existing);
+        if fresh.is_empty() {
+            return Ok(0);
+        }
+
+        Ok(diesel::insert_into(cognitive_views::table)
+            .values(&fresh)
+            .execute(conn)?)
+    }
+
+    /// Run detection for every user on a Tokio interval in the background
+    ///
+    /// Each tick asks `user_ids_provider` for the users to analyze (e.g.
+    /// `users_with_memories`), detects patterns over the last
+    /// `detection_window_days` and persists them as cognitive views.
+    /// Ticks are skipped while `resource_scheduler` reports memory pressure.
+    pub fn spawn<P>(
+        self,
+        database_url: String,
+        interval: std::time::Duration,
+        user_ids_provider: P,
+        mut resource_scheduler: ResourceAwareScheduler,
+    ) -> tokio::task::JoinHandle<()>
+    where
+        P: Fn() -> Vec<String> + Send + Sync + 'static,
+    {
+        let task = ScheduledTask::new(
+            "pattern_detection".to_string(),
+            TaskPriority::Low,
+            PATTERN_DETECTION_MEMORY_MB,
+            "Daily pattern detection".to_string(),
+        );
+        let should_run = move || match resource_scheduler.should_schedule(&task) {
+            Ok(allowed) => allowed,
+            Err(e) => {
+                tracing::warn!("Memory check failed, running pattern detection anyway: {}", e);
+                true
+            }
+        };
 
-        Ok(results)
+        let detect = move |user_id: &str| -> Result<usize> {
+            let mut conn = PgConnection::establish(&database_url)?;
+            let result = self.detector.detect_patterns(
+                &mut conn,
+                user_id,
+                DetectionTimeRange::last_n_days(self.detector.config.detection_window_days),
+            )?;
+            Self::persist_result(&mut conn, user_id, &result, &self.view_generator)
+        };
+
+        spawn_detection_loop(interval, user_ids_provider, detect, should_run)
     }
 }
 
+/// Estimated peak memory of one detection pass, used for resource gating
+const PATTERN_DETECTION_MEMORY_MB: u64 = 256;
+
+/// Number of detection workers that fit in the current memory budget
+///
+/// Falls back to a single worker when memory is under pressure; otherwise
+/// each worker is budgeted `PATTERN_DETECTION_MEMORY_MB` of available memory.
+pub fn throttled_workers(max_workers: usize, usage: &MemoryUsage) -> usize {
+    if usage.is_under_pressure() {
+        return 1;
+    }
+    let fits = (usage.available_mb / PATTERN_DETECTION_MEMORY_MB) as usize;
+    fits.clamp(1, max_workers.max(1))
+}
+
+/// Run `detect` for every user on up to `max_workers` threads
+///
+/// Users are handed out from a shared queue. Before taking the next user,
+/// a worker whose index is not below `allowed_workers()` retires, so the
+/// run narrows as memory tightens; the first worker always keeps going and
+/// the run completes. Errors and panics are recorded per user in
+/// `failures` without affecting other users.
+pub fn detect_users_parallel<T, D, W>(
+    user_ids: &[String],
+    max_workers: usize,
+    allowed_workers: W,
+    detect: D,
+) -> DailyDetectionReport<T>
+where
+    T: Send,
+    D: Fn(&str) -> Result<T> + Sync,
+    W: Fn() -> usize + Sync,
+{
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    let next = AtomicUsize::new(0);
+    let report = Mutex::new(DailyDetectionReport::default());
+    let workers = max_workers.clamp(1, user_ids.len().max(1));
+
+    std::thread::scope(|scope| {
+        for worker in 0..workers {
+            let (next, report, detect, allowed_workers) = (&next, &report, &detect, &allowed_workers);
+            scope.spawn(move || loop {
+                if worker > 0 && worker >= allowed_workers() {
+                    break;
+                }
+                let Some(user_id) = user_ids.get(next.fetch_add(1, Ordering::SeqCst)) else {
+                    break;
+                };
+
+                let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    detect(user_id)
+                }));
+                let mut report = report.lock().unwrap_or_else(|e| e.into_inner());
+                match outcome {
+                    Ok(Ok(result)) => {
+                        report.results.insert(user_id.clone(), result);
+                    }
+                    Ok(Err(e)) => {
+                        report.failures.insert(user_id.clone(), e.to_string());
+                    }
+                    Err(_) => {
+                        report
+                            .failures
+                            .insert(user_id.clone(), "pattern detection panicked".to_string());
+                    }
+                }
+            });
+        }
+    });
+
+    report.into_inner().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Users owning at least one memory that is not soft-deleted
+pub fn users_with_memories(conn: &mut PgConnection) -> Result<Vec<String>> {
+    use crate::schema::raw_memories;
+
+    Ok(raw_memories::table
+        .filter(raw_memories::deleted_at.is_null())
+        .select(raw_memories::user_id)
+        .distinct()
+        .load(conn)?)
+}
+
+/// Drive a detection closure on a Tokio interval
+///
+/// The first tick fires immediately. `should_run` is checked before every
+/// tick; `user_ids_provider` and then `detect` per user run on the blocking
+/// pool, and a failing user is logged without stopping the others or later
+/// ticks.
+pub fn spawn_detection_loop<P, D, G>(
+    interval: std::time::Duration,
+    user_ids_provider: P,
+    detect: D,
+    mut should_run: G,
+) -> tokio::task::JoinHandle<()>
+where
+    P: Fn() -> Vec<String> + Send + Sync + 'static,
+    D: Fn(&str) -> Result<usize> + Send + Sync + 'static,
+    G: FnMut() -> bool + Send + 'static,
+{
+    let user_ids_provider = std::sync::Arc::new(user_ids_provider);
+    let detect = std::sync::Arc::new(detect);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            ticker.tick().await;
+
+            if !should_run() {
+                tracing::info!("Skipping pattern detection: memory pressure");
+                continue;
+            }
+
+            let user_ids_provider = std::sync::Arc::clone(&user_ids_provider);
+            let detect = std::sync::Arc::clone(&detect);
+            let run = tokio::task::spawn_blocking(move || {
+                for user_id in &user_ids_provider() {
+                    match detect(user_id) {
+                        Ok(views) => {
+                            tracing::info!("Pattern detection for {}: {} new views", user_id, views)
+                        }
+                        Err(e) => {
+                            tracing::warn!("Pattern detection failed for {}: {}", user_id, e)
+                        }
+                    }
+                }
+            });
+
+            if let Err(e) = run.await {
+                tracing::warn!("Pattern detection tick aborted: {}", e);
+            }
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -719,6 +1602,7 @@ mod tests {
             unit: None,
             confidence: 1.0,
             extractor_version: None,
+            metadata: None,
         };
 
         let event2 = EventMemory {
@@ -733,6 +1617,7 @@ mod tests {
             unit: None,
             confidence: 1.0,
             extractor_version: None,
+            metadata: None,
         };
 
         let event3 = EventMemory {
@@ -747,6 +1632,7 @@ mod tests {
             unit: None,
             confidence: 1.0,
             extractor_version: None,
+            metadata: None,
         };
 
         let events = vec![&event1, &event2, &event3];
@@ -764,6 +1650,296 @@ mod tests {
         assert_eq!(config.min_trend_days, 7);
         assert_eq!(config.min_anomaly_deviation, 0.5);
         assert_eq!(config.anomaly_baseline_days, 30);
+        assert_eq!(config.id_strategy, PatternIdStrategy::Random);
+        assert_eq!(config.temporal_min_occurrences, 4);
+        assert_eq!(config.temporal_week_ratio, 0.6);
+        assert_eq!(config.day_name_locale, DayNameLocale::English);
+        assert_eq!(config.time_bucketing, TimeBucketing::Hourly);
+    }
+
+    /// Two weeks of daily coffee between 7:40 and 9:10, mostly around 8
+    fn morning_coffee_fixture() -> (Vec<EventMemory>, DetectionTimeRange) {
+        use chrono::TimeZone;
+        let start = Utc.with_ymd_and_hms(2026, 1, 5, 0, 0, 0).unwrap();
+        let minutes = [460, 485, 495, 510, 530, 550, 490, 500, 515, 520, 505, 480, 495, 525];
+        let events = minutes
+            .iter()
+            .enumerate()
+            .map(|(day, &minute)| {
+                make_event(start + Duration::days(day as i64) + Duration::minutes(minute), "喝", "咖啡")
+            })
+            .collect();
+        (events, DetectionTimeRange::new(start, start + Duration::days(14)))
+    }
+
+    fn typical_times_with(bucketing: TimeBucketing) -> Vec<String> {
+        let (events, range) = morning_coffee_fixture();
+        let detector = PatternDetector::with_config(PatternDetectorConfig {
+            time_bucketing: bucketing,
+            ..Default::default()
+        });
+        let patterns = detector.detect_high_frequency_patterns("test", &events, &range).unwrap();
+        assert_eq!(patterns.len(), 1);
+        match &patterns[0].metadata {
+            PatternMetadata::HighFrequency { typical_times, .. } => typical_times.clone(),
+            other => panic!("unexpected metadata: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_typical_times_hourly_by_default() {
+        assert_eq!(typical_times_with(TimeBucketing::default()), vec!["08:00"]);
+    }
+
+    #[test]
+    fn test_typical_times_part_of_day() {
+        assert_eq!(typical_times_with(TimeBucketing::PartOfDay), vec!["morning"]);
+    }
+
+    #[test]
+    fn test_typical_times_half_hourly() {
+        assert_eq!(typical_times_with(TimeBucketing::HalfHourly), vec!["08:00", "08:30"]);
+    }
+
+    #[test]
+    fn test_time_bucketing_labels() {
+        assert_eq!(TimeBucketing::Hourly.label(7, 45), "07:00");
+        assert_eq!(TimeBucketing::HalfHourly.label(7, 45), "07:30");
+        assert_eq!(TimeBucketing::PartOfDay.label(4, 59), "night");
+        assert_eq!(TimeBucketing::PartOfDay.label(13, 0), "afternoon");
+        assert_eq!(TimeBucketing::PartOfDay.label(18, 0), "evening");
+        assert_eq!(TimeBucketing::PartOfDay.label(22, 0), "night");
+    }
+
+    fn make_event(timestamp: chrono::DateTime<Utc>, action: &str, target: &str) -> EventMemory {
+        EventMemory {
+            event_id: Uuid::new_v4(),
+            memory_id: Uuid::new_v4(),
+            user_id: "test".to_string(),
+            timestamp,
+            actor: None,
+            action: action.to_string(),
+            target: target.to_string(),
+            quantity: None,
+            unit: None,
+            confidence: 1.0,
+            extractor_version: None,
+            metadata: None,
+        }
+    }
+
+    /// Four-week window with three Monday runs and one Thursday run
+    fn weekly_fixture() -> (Vec<EventMemory>, DetectionTimeRange) {
+        use chrono::TimeZone;
+        // 2026-01-05 is a Monday
+        let start = Utc.with_ymd_and_hms(2026, 1, 5, 0, 0, 0).unwrap();
+        let events = vec![
+            make_event(start + Duration::hours(8), "run", "park"),
+            make_event(start + Duration::days(7) + Duration::hours(8), "run", "park"),
+            make_event(start + Duration::days(10) + Duration::hours(8), "run", "park"),
+            make_event(start + Duration::days(14) + Duration::hours(8), "run", "park"),
+        ];
+        (events, DetectionTimeRange::new(start, start + Duration::days(28)))
+    }
+
+    /// Four weeks of Monday-Friday runs at 07:00, nothing on weekends
+    fn weekday_only_events() -> Vec<EventMemory> {
+        use chrono::TimeZone;
+        // 2026-01-05 is a Monday
+        let start = Utc.with_ymd_and_hms(2026, 1, 5, 7, 0, 0).unwrap();
+        (0..28)
+            .filter(|day| day % 7 < 5)
+            .map(|day| make_event(start + Duration::days(day), "run", "park"))
+            .collect()
+    }
+
+    #[test]
+    fn test_weekday_habit_consistency_by_metric() {
+        let events = weekday_only_events();
+        let refs: Vec<&EventMemory> = events.iter().collect();
+
+        // Weekend gaps make the gap variance look irregular
+        let cv_detector = PatternDetector::new();
+        let cv_score = cv_detector.calculate_consistency(&refs, 28.0);
+        assert!(cv_score < 0.5, "cv score {}", cv_score);
+
+        // Against a weekly period every week matches the habit exactly
+        let periodic_detector = PatternDetector::with_config(PatternDetectorConfig {
+            consistency_metric: ConsistencyMetric::Periodic(ConsistencyPeriod::Weekly),
+            ..Default::default()
+        });
+        let periodic_score = periodic_detector.calculate_consistency(&refs, 28.0);
+        assert!(periodic_score > 0.95, "periodic score {}", periodic_score);
+    }
+
+    #[test]
+    fn test_periodic_consistency_penalizes_missed_periods() {
+        let mut events = weekday_only_events();
+        // Drop the whole third week
+        events.retain(|e| !(14..21).contains(&(e.timestamp.ordinal() as i64 - 5)));
+        let refs: Vec<&EventMemory> = events.iter().collect();
+
+        let score = PatternDetector::periodic_consistency(&refs, ConsistencyPeriod::Weekly);
+        assert!((score - 0.75).abs() < 1e-9, "score {}", score);
+    }
+
+    #[test]
+    fn test_periodic_consistency_daily_hours() {
+        use chrono::TimeZone;
+        let start = Utc.with_ymd_and_hms(2026, 1, 5, 8, 0, 0).unwrap();
+        let events: Vec<EventMemory> = (0..7)
+            .map(|day| make_event(start + Duration::days(day), "drink", "coffee"))
+            .collect();
+        let refs: Vec<&EventMemory> = events.iter().collect();
+
+        let score = PatternDetector::periodic_consistency(&refs, ConsistencyPeriod::Daily);
+        assert!((score - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_temporal_week_ratio_boundary() {
+        let (events, range) = weekly_fixture();
+
+        // 3 of 4 weeks = 0.75: exactly at threshold is a pattern
+        let detector = PatternDetector::with_config(PatternDetectorConfig {
+            temporal_week_ratio: 0.75,
+            ..Default::default()
+        });
+        let patterns = detector.find_temporal_patterns("test", &events, &range);
+        assert_eq!(patterns.len(), 1);
+        assert!(matches!(
+            &patterns[0].metadata,
+            PatternMetadata::Temporal { period, .. } if period == "weekly_Mon"
+        ));
+
+        // Just above the observed ratio, the pattern disappears
+        let detector = PatternDetector::with_config(PatternDetectorConfig {
+            temporal_week_ratio: 0.76,
+            ..Default::default()
+        });
+        assert!(detector.find_temporal_patterns("test", &events, &range).is_empty());
+    }
+
+    #[test]
+    fn test_temporal_min_occurrences_boundary() {
+        let (events, range) = weekly_fixture();
+
+        let detector = PatternDetector::with_config(PatternDetectorConfig {
+            temporal_min_occurrences: 4,
+            ..Default::default()
+        });
+        assert_eq!(detector.find_temporal_patterns("test", &events, &range).len(), 1);
+
+        let detector = PatternDetector::with_config(PatternDetectorConfig {
+            temporal_min_occurrences: 5,
+            ..Default::default()
+        });
+        assert!(detector.find_temporal_patterns("test", &events, &range).is_empty());
+    }
+
+    #[test]
+    fn test_temporal_description_locale() {
+        let (events, range) = weekly_fixture();
+
+        let detector = PatternDetector::with_config(PatternDetectorConfig {
+            day_name_locale: DayNameLocale::Chinese,
+            ..Default::default()
+        });
+        let patterns = detector.find_temporal_patterns("test", &events, &range);
+        assert_eq!(patterns.len(), 1);
+        assert!(patterns[0].description.contains("周一"));
+        // Period key is locale-independent
+        assert!(matches!(
+            &patterns[0].metadata,
+            PatternMetadata::Temporal { period, .. } if period == "weekly_Mon"
+        ));
+    }
+
+    #[test]
+    fn test_temporal_description_default_english() {
+        let (events, range) = weekly_fixture();
+
+        let patterns = PatternDetector::new().find_temporal_patterns("test", &events, &range);
+        assert_eq!(patterns.len(), 1);
+        assert_eq!(patterns[0].description, "Weekly pattern: run park on Mons (75% of weeks)");
+    }
+
+    #[test]
+    fn test_descriptions_follow_locale() {
+        let range = DetectionTimeRange::new(
+            Utc::now() - Duration::days(14),
+            Utc::now(),
+        );
+        let events = daily_events("喝", "咖啡", range.start + Duration::hours(8), 14);
+
+        let describe = |locale: DayNameLocale| {
+            PatternDetector::new()
+                .with_locale(locale)
+                .detect_patterns_in_events("test", &events, &[], range.clone())
+                .unwrap()
+                .patterns
+                .into_iter()
+                .find(|p| p.pattern_type == PatternType::HighFrequency)
+                .unwrap()
+                .description
+        };
+
+        assert_eq!(describe(DayNameLocale::English), "Frequently 喝 咖啡 (1.00 times/day)");
+        assert_eq!(describe(DayNameLocale::Chinese), "高频行为: 喝咖啡 (每天 1.00 次)");
+
+        let zh = PatternDetector::new().with_locale(DayNameLocale::Chinese);
+        assert_eq!(zh.anomaly_description("喝", "咖啡", -0.6), "异常: 喝咖啡比预期低 60%");
+        assert_eq!(
+            zh.trend_description("喝", "咖啡", TrendDirection::Increasing, 0.5),
+            "喝咖啡呈上升趋势 (变化 50%)"
+        );
+    }
+
+    #[test]
+    fn test_deterministic_pattern_ids_are_stable() {
+        let config = PatternDetectorConfig {
+            id_strategy: PatternIdStrategy::Deterministic,
+            ..Default::default()
+        };
+        let detector = PatternDetector::with_config(config);
+
+        let id1 = detector.generate_pattern_id("u1", PatternType::HighFrequency, "eat", "apple", None);
+        let id2 = detector.generate_pattern_id("u1", PatternType::HighFrequency, "eat", "apple", None);
+        assert_eq!(id1, id2);
+
+        // Another detector instance yields the same id
+        let other = PatternDetector::with_config(PatternDetectorConfig {
+            id_strategy: PatternIdStrategy::Deterministic,
+            ..Default::default()
+        });
+        assert_eq!(id1, other.generate_pattern_id("u1", PatternType::HighFrequency, "eat", "apple", None));
+
+        // Any differing component changes the id
+        assert_ne!(id1, detector.generate_pattern_id("u2", PatternType::HighFrequency, "eat", "apple", None));
+        assert_ne!(id1, detector.generate_pattern_id("u1", PatternType::Trend, "eat", "apple", None));
+        assert_ne!(id1, detector.generate_pattern_id("u1", PatternType::HighFrequency, "eat", "banana", None));
+        assert_ne!(
+            detector.generate_pattern_id("u1", PatternType::Temporal, "run", "park", Some("weekly_Mon")),
+            detector.generate_pattern_id("u1", PatternType::Temporal, "run", "park", Some("weekly_Tue")),
+        );
+
+        // A separator inside a field cannot shift it into its neighbor
+        assert_ne!(
+            detector.generate_pattern_id("u1", PatternType::HighFrequency, "eat|apple", "pie", None),
+            detector.generate_pattern_id("u1", PatternType::HighFrequency, "eat", "apple|pie", None),
+        );
+        assert_ne!(
+            detector.generate_pattern_id("u1", PatternType::HighFrequency, "eat", "apple", Some("")),
+            detector.generate_pattern_id("u1", PatternType::HighFrequency, "eat", "apple", None),
+        );
+    }
+
+    #[test]
+    fn test_random_pattern_ids_differ() {
+        let detector = PatternDetector::new();
+        let id1 = detector.generate_pattern_id("u1", PatternType::HighFrequency, "eat", "apple", None);
+        let id2 = detector.generate_pattern_id("u1", PatternType::HighFrequency, "eat", "apple", None);
+        assert_ne!(id1, id2);
     }
 
     #[test]
@@ -783,6 +1959,7 @@ mod tests {
             unit: None,
             confidence: 1.0,
             extractor_version: None,
+            metadata: None,
         };
 
         let event2 = EventMemory {
@@ -797,6 +1974,7 @@ mod tests {
             unit: None,
             confidence: 1.0,
             extractor_version: None,
+            metadata: None,
         };
 
         let events = vec![&event1, &event2];
@@ -810,5 +1988,459 @@ mod tests {
         let scheduler = PatternDetectionScheduler::new();
         // Should not panic
         assert_eq!(scheduler.detector.config.min_frequency_threshold, 0.5);
+        assert_eq!(scheduler.max_workers, DEFAULT_DETECTION_WORKERS);
+
+        let scheduler = PatternDetectionScheduler::new().with_max_workers(0);
+        assert_eq!(scheduler.max_workers, 1);
+        assert_eq!(scheduler.allowed_workers(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_detection_loop_fires_repeatedly_and_survives_failures() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let detect_calls = Arc::clone(&calls);
+        let detect = move |user_id: &str| -> Result<usize> {
+            detect_calls.fetch_add(1, Ordering::SeqCst);
+            if user_id == "broken" {
+                Err(crate::error::DirSoulError::Config("boom".to_string()))
+            } else {
+                Ok(1)
+            }
+        };
+
+        let handle = spawn_detection_loop(
+            std::time::Duration::from_millis(10),
+            || vec!["broken".to_string(), "alice".to_string()],
+            detect,
+            || true,
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        handle.abort();
+
+        // Both users run on every tick, even though one always fails
+        let calls = calls.load(Ordering::SeqCst);
+        assert!(calls >= 6, "only {} detection calls", calls);
+    }
+
+    #[tokio::test]
+    async fn test_detection_loop_skips_ticks_under_pressure() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let detect_calls = Arc::clone(&calls);
+
+        let handle = spawn_detection_loop(
+            std::time::Duration::from_millis(10),
+            || vec!["alice".to_string()],
+            move |_: &str| -> Result<usize> {
+                detect_calls.fetch_add(1, Ordering::SeqCst);
+                Ok(0)
+            },
+            || false,
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(60)).await;
+        handle.abort();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    fn users(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("user_{}", i)).collect()
+    }
+
+    /// Track how many detections run at once
+    struct InFlight {
+        current: std::sync::atomic::AtomicUsize,
+        peak: std::sync::atomic::AtomicUsize,
+    }
+
+    impl InFlight {
+        fn new() -> Self {
+            Self {
+                current: std::sync::atomic::AtomicUsize::new(0),
+                peak: std::sync::atomic::AtomicUsize::new(0),
+            }
+        }
+
+        fn run<T>(&self, work: impl FnOnce() -> T) -> T {
+            use std::sync::atomic::Ordering;
+            let now = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            let result = work();
+            self.current.fetch_sub(1, Ordering::SeqCst);
+            result
+        }
+    }
+
+    #[test]
+    fn test_parallel_detection_collects_every_user_and_isolates_failures() {
+        let user_ids = users(12);
+        let in_flight = InFlight::new();
+
+        let report = detect_users_parallel(&user_ids, 4, || 4, |user_id| {
+            in_flight.run(|| match user_id {
+                "user_3" => Err(crate::error::DirSoulError::Config("boom".to_string())),
+                "user_7" => panic!("detector bug"),
+                _ => Ok(user_id.len()),
+            })
+        });
+
+        assert_eq!(report.results.len(), 10);
+        assert_eq!(report.failures.len(), 2);
+        assert!(report.failures["user_3"].contains("boom"));
+        assert!(report.failures.contains_key("user_7"));
+        for user_id in user_ids.iter().filter(|u| *u != "user_3" && *u != "user_7") {
+            assert_eq!(report.results[user_id], user_id.len());
+        }
+
+        let peak = in_flight.peak.load(std::sync::atomic::Ordering::SeqCst);
+        assert!(peak > 1 && peak <= 4, "peak concurrency {}", peak);
+    }
+
+    #[test]
+    fn test_parallel_detection_narrows_under_memory_pressure() {
+        let user_ids = users(8);
+        let in_flight = InFlight::new();
+
+        let report = detect_users_parallel(&user_ids, 4, || 1, |user_id| {
+            in_flight.run(|| Ok(user_id.to_string()))
+        });
+
+        // Only the first worker keeps taking users, and all are still analyzed
+        assert_eq!(report.results.len(), 8);
+        assert!(report.failures.is_empty());
+        assert_eq!(in_flight.peak.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let empty: DailyDetectionReport<usize> = detect_users_parallel(&[], 4, || 4, |_| Ok(0));
+        assert!(empty.results.is_empty() && empty.failures.is_empty());
+    }
+
+    #[test]
+    fn test_throttled_workers_follows_memory_usage() {
+        let usage = |total_mb: u64, available_mb: u64| MemoryUsage {
+            total_mb,
+            used_mb: total_mb - available_mb,
+            available_mb,
+            used_percent: (total_mb - available_mb) as f64 / total_mb as f64 * 100.0,
+            timestamp: Utc::now(),
+        };
+
+        assert_eq!(throttled_workers(4, &usage(8192, 4096)), 4);
+        // Three passes fit in 800MB
+        assert_eq!(throttled_workers(4, &usage(4096, 800)), 3);
+        // Not under pressure, but not even one pass fits
+        assert_eq!(throttled_workers(4, &usage(1024, 200)), 1);
+        // Above 85% used
+        assert_eq!(throttled_workers(4, &usage(8192, 1000)), 1);
+        assert_eq!(throttled_workers(0, &usage(8192, 4096)), 1);
+    }
+
+    #[test]
+    fn test_action_normalizer_merges_surface_verbs() {
+        use chrono::TimeZone;
+        let start = Utc.with_ymd_and_hms(2026, 1, 5, 0, 0, 0).unwrap();
+        let time_range = DetectionTimeRange::new(start, start + Duration::days(14));
+        let events: Vec<EventMemory> = (0..14)
+            .map(|day| {
+                let action = if day % 2 == 0 { "吃了" } else { "eat" };
+                make_event(start + Duration::days(day) + Duration::hours(8), action, "apple")
+            })
+            .collect();
+
+        let high_frequency = |detector: &PatternDetector| -> Vec<DetectedPattern> {
+            detector
+                .detect_patterns_in_events("test", &events, &[], time_range.clone())
+                .unwrap()
+                .patterns
+                .into_iter()
+                .filter(|p| p.pattern_type == PatternType::HighFrequency)
+                .collect()
+        };
+
+        // Raw actions split the habit in two
+        let mut raw: Vec<String> = high_frequency(&PatternDetector::new())
+            .into_iter()
+            .map(|p| p.action)
+            .collect();
+        raw.sort();
+        assert_eq!(raw, vec!["eat", "吃了"]);
+
+        let merged = high_frequency(&PatternDetector::new().with_action_normalizer(ActionNormalizer::new()));
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].action, "eat");
+        assert_eq!(merged[0].evidence_count, 14);
+    }
+
+    fn daily_events(action: &str, target: &str, from: chrono::DateTime<Utc>, days: i64) -> Vec<EventMemory> {
+        (0..days)
+            .map(|day| EventMemory {
+                event_id: Uuid::new_v4(),
+                memory_id: Uuid::new_v4(),
+                user_id: "test".to_string(),
+                timestamp: from + Duration::days(day),
+                actor: None,
+                action: action.to_string(),
+                target: target.to_string(),
+                quantity: None,
+                unit: None,
+                confidence: 1.0,
+                extractor_version: None,
+                metadata: None,
+            })
+            .collect()
+    }
+
+    /// Monday-only runs in the four weeks before a Monday detection range
+    fn monday_fixture() -> (Vec<EventMemory>, DetectionTimeRange) {
+        use chrono::TimeZone;
+        // 2026-02-02 is a Monday; the 30-day baseline holds four Mondays
+        let start = Utc.with_ymd_and_hms(2026, 2, 2, 0, 0, 0).unwrap();
+        let baseline = (1..=4)
+            .map(|week| make_event(start - Duration::weeks(week) + Duration::hours(8), "run", "park"))
+            .collect();
+        (baseline, DetectionTimeRange::new(start, start + Duration::days(1)))
+    }
+
+    #[test]
+    fn test_weekday_behavior_flagged_against_fixed_baseline() {
+        let (baseline, time_range) = monday_fixture();
+        let events = vec![make_event(time_range.start + Duration::hours(8), "run", "park")];
+
+        let detector = PatternDetector::new();
+        let anomalies = detector
+            .detect_anomalies("test", &events, &baseline, &time_range)
+            .unwrap();
+
+        // 4 runs / 30 days looks rare next to one run today
+        assert_eq!(anomalies.len(), 1);
+        match &anomalies[0].metadata {
+            PatternMetadata::Anomaly { expected_value, actual_value, .. } => {
+                assert!((expected_value - 4.0 / 30.0).abs() < 1e-9);
+                assert_eq!(*actual_value, 1.0);
+            }
+            other => panic!("unexpected metadata: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_weekday_behavior_not_flagged_against_same_weekday_baseline() {
+        let (baseline, time_range) = monday_fixture();
+        let detector = PatternDetector::with_config(PatternDetectorConfig {
+            anomaly_baseline: AnomalyBaseline::SameWeekday,
+            ..Default::default()
+        });
+
+        let events = vec![make_event(time_range.start + Duration::hours(8), "run", "park")];
+        let anomalies = detector
+            .detect_anomalies("test", &events, &baseline, &time_range)
+            .unwrap();
+        assert!(anomalies.is_empty());
+
+        // A real deviation on a Monday is still reported
+        let doubled = vec![
+            make_event(time_range.start + Duration::hours(8), "run", "park"),
+            make_event(time_range.start + Duration::hours(18), "run", "park"),
+        ];
+        let anomalies = detector
+            .detect_anomalies("test", &doubled, &baseline, &time_range)
+            .unwrap();
+        assert_eq!(anomalies.len(), 1);
+        match &anomalies[0].metadata {
+            PatternMetadata::Anomaly { expected_value, deviation_percentage, .. } => {
+                assert!((expected_value - 1.0).abs() < 1e-9);
+                assert!((deviation_percentage - 1.0).abs() < 1e-9);
+            }
+            other => panic!("unexpected metadata: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_same_weekday_baseline_weights_range_weekdays() {
+        let (baseline, monday) = monday_fixture();
+        let detector = PatternDetector::with_config(PatternDetectorConfig {
+            anomaly_baseline: AnomalyBaseline::SameWeekday,
+            ..Default::default()
+        });
+
+        // Over a full week the Monday habit averages out to once a week
+        let week = DetectionTimeRange::new(monday.start, monday.start + Duration::days(7));
+        let freqs = detector.baseline_frequencies(&baseline, &week);
+        let key = ("run".to_string(), "park".to_string());
+        assert!((freqs[&key] - 1.0 / 7.0).abs() < 1e-9);
+
+        // On a Tuesday nothing is expected
+        let tuesday = DetectionTimeRange::new(monday.end, monday.end + Duration::days(1));
+        let freqs = detector.baseline_frequencies(&baseline, &tuesday);
+        assert_eq!(freqs[&key], 0.0);
+    }
+
+    #[test]
+    fn test_stopped_behavior_respects_suppression_window() {
+        let detector = PatternDetector::new();
+        let end = Utc::now();
+        let time_range = DetectionTimeRange::new(end - Duration::days(7), end);
+        let baseline = daily_events("run", "park", time_range.start - Duration::days(30), 30);
+
+        let key = ("run".to_string(), "park".to_string());
+        let baseline_freqs = HashMap::from([(key.clone(), 1.0)]);
+        // One run in the current week: well below baseline either way
+        let current_freqs = HashMap::from([(key, 1.0 / 7.0)]);
+
+        // Last run was yesterday: tapering, not stopped
+        let recent = daily_events("run", "park", end - Duration::days(1), 1);
+        let stopped = detector.find_stopped_behaviors(
+            "test", &baseline_freqs, &current_freqs, &baseline, &recent, &time_range,
+        );
+        assert!(stopped.is_empty());
+
+        // Last run was 5 days ago: beyond the 3-day window
+        let stale = daily_events("run", "park", end - Duration::days(5), 1);
+        let stopped = detector.find_stopped_behaviors(
+            "test", &baseline_freqs, &current_freqs, &baseline, &stale, &time_range,
+        );
+        assert_eq!(stopped.len(), 1);
+        assert_eq!(stopped[0].evidence_count, 1);
+        assert!(stopped[0].description.contains("stopped"));
+    }
+
+    #[test]
+    fn test_stopped_behavior_thresholds_are_configurable() {
+        let end = Utc::now();
+        let time_range = DetectionTimeRange::new(end - Duration::days(7), end);
+        let key = ("read".to_string(), "book".to_string());
+        let baseline_freqs = HashMap::from([(key, 0.3)]);
+
+        // 0.3/day is below the default baseline bar
+        let detector = PatternDetector::new();
+        let stopped = detector.find_stopped_behaviors(
+            "test", &baseline_freqs, &HashMap::new(), &[], &[], &time_range,
+        );
+        assert!(stopped.is_empty());
+
+        let detector = PatternDetector::with_config(PatternDetectorConfig {
+            stopped_min_baseline_freq: 0.2,
+            ..Default::default()
+        });
+        let stopped = detector.find_stopped_behaviors(
+            "test", &baseline_freqs, &HashMap::new(), &[], &[], &time_range,
+        );
+        assert_eq!(stopped.len(), 1);
+    }
+
+    #[test]
+    fn test_detection_skipped_below_min_events() {
+        let range = DetectionTimeRange::new(Utc::now() - Duration::days(7), Utc::now());
+        // Three coffees on three consecutive days would otherwise read as a daily habit
+        let sparse = daily_events("喝", "咖啡", range.start + Duration::days(4), 3);
+
+        let result = PatternDetector::with_config(PatternDetectorConfig {
+            min_frequency_threshold: 0.1,
+            ..Default::default()
+        })
+        .detect_patterns_in_events("test", &sparse, &[], range.clone())
+        .unwrap();
+        assert!(result.patterns.is_empty());
+        assert_eq!(result.events_analyzed, 3);
+        let reason = result.skipped_reason.unwrap();
+        assert!(reason.contains("at least 5"), "{}", reason);
+
+        // Lowering the threshold lets the same events through
+        let result = PatternDetector::with_config(PatternDetectorConfig {
+            min_frequency_threshold: 0.1,
+            min_events_for_detection: 3,
+            ..Default::default()
+        })
+        .detect_patterns_in_events("test", &sparse, &[], range)
+        .unwrap();
+        assert!(result.skipped_reason.is_none());
+        assert!(result.patterns.iter().any(|p| p.pattern_type == PatternType::HighFrequency));
+    }
+
+    #[test]
+    fn test_sparse_range_still_reports_stopped_behavior() {
+        let range = DetectionTimeRange::new(Utc::now() - Duration::days(7), Utc::now());
+        // A daily coffee habit through the baseline, then nothing at all
+        let baseline = daily_events("喝", "咖啡", range.start - Duration::days(30), 30);
+
+        let result = PatternDetector::new()
+            .detect_patterns_in_events("test", &[], &baseline, range)
+            .unwrap();
+        assert_eq!(result.events_analyzed, 0);
+        assert!(result.skipped_reason.is_some());
+        assert!(result
+            .patterns
+            .iter()
+            .any(|p| p.pattern_type == PatternType::Anomaly && p.target == "咖啡"));
+        assert!(result.patterns.iter().all(|p| p.pattern_type == PatternType::Anomaly));
+    }
+
+    #[test]
+    fn test_min_event_confidence_filters_noisy_events() {
+        let range = DetectionTimeRange::new(Utc::now() - Duration::days(7), Utc::now());
+        let mut events = daily_events("喝", "咖啡", range.start + Duration::hours(8), 7);
+        let mut noisy = daily_events("吃", "零食", range.start + Duration::hours(20), 7);
+        for event in &mut noisy {
+            event.confidence = 0.3;
+        }
+        events.extend(noisy);
+
+        // The default floor keeps every event
+        let result = PatternDetector::new()
+            .detect_patterns_in_events("test", &events, &[], range.clone())
+            .unwrap();
+        assert_eq!(result.events_analyzed, 14);
+        assert!(result.patterns.iter().any(|p| p.target == "零食"));
+
+        let result = PatternDetector::new()
+            .with_min_event_confidence(0.5)
+            .detect_patterns_in_events("test", &events, &[], range.clone())
+            .unwrap();
+        assert_eq!(result.events_analyzed, 7);
+        assert!(!result.patterns.is_empty());
+        assert!(result.patterns.iter().all(|p| p.target == "咖啡"));
+        let coffee = result
+            .patterns
+            .iter()
+            .find(|p| p.pattern_type == PatternType::HighFrequency)
+            .unwrap();
+        assert_eq!(coffee.evidence_count, 7);
+
+        // A floor above every event leaves too few to detect anything
+        for event in &mut events {
+            event.confidence = event.confidence.min(0.9);
+        }
+        let result = PatternDetector::new()
+            .with_min_event_confidence(1.0)
+            .detect_patterns_in_events("test", &events, &[], range.clone())
+            .unwrap();
+        assert_eq!(result.events_analyzed, 0);
+        assert!(result.skipped_reason.is_some());
+
+        // A floor outside [0, 1] is a configuration error
+        for floor in [1.1, -0.1, f64::NAN] {
+            let err = PatternDetector::new()
+                .with_min_event_confidence(floor)
+                .detect_patterns_in_events("test", &events, &[], range.clone())
+                .unwrap_err();
+            assert!(matches!(err, DirSoulError::Config(_)), "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn test_detection_runs_at_min_events() {
+        let range = DetectionTimeRange::new(Utc::now() - Duration::days(7), Utc::now());
+        let events = daily_events("喝", "咖啡", range.start + Duration::hours(8), 7);
+
+        let result = PatternDetector::new()
+            .detect_patterns_in_events("test", &events, &[], range)
+            .unwrap();
+        assert!(result.skipped_reason.is_none());
+        assert_eq!(result.events_analyzed, 7);
+        assert!(result.patterns.iter().any(|p| p.pattern_type == PatternType::HighFrequency));
     }
 }
@@ -15,7 +15,7 @@ use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{Mutex, RwLock};
@@ -23,9 +23,12 @@ use uuid::Uuid;
 
 use crate::agents::MemoryPermission;
 use crate::actor_agent::EventNotification;
+use crate::audit::ThreadSafeAuditLogger;
 use crate::cognitive::{CognitiveView, NewCognitiveView};
 use crate::error::{DirSoulError, Result};
-use crate::models::{Entity, EventMemory, NewEventMemory};
+use crate::models::{
+    Entity, EntityType, EventMemory, ExtractorVersion, NewEventMemory, NewRawMemory, RawMemory,
+};
 
 /// Event subscription filter for plugins
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -116,6 +119,10 @@ pub enum PluginOutput {
 ///
 /// This trait provides controlled access to the memory system based on
 /// the plugin's permission level.
+///
+/// Events reference the raw memory they were extracted from, so a plugin
+/// that records a new event must first call `create_raw_memory` and pass the
+/// returned `memory_id` to `create_event`.
 #[async_trait]
 pub trait PluginMemoryInterface: Send + Sync {
     /// Query events (requires ReadWriteEvents permission)
@@ -128,7 +135,12 @@ pub trait PluginMemoryInterface: Send + Sync {
     /// Create cognitive view (requires ReadWriteDerived permission)
     async fn create_view(&self, user_id: &str, view: NewCognitiveView) -> Result<CognitiveView>;
 
+    /// Create raw memory (requires ReadWriteEvents permission)
+    async fn create_raw_memory(&self, user_id: &str, memory: NewRawMemory) -> Result<RawMemory>;
+
     /// Create event (requires ReadWriteEvents permission)
+    ///
+    /// `event.memory_id` must name an existing raw memory of the user.
     async fn create_event(&self, user_id: &str, event: NewEventMemory) -> Result<EventMemory>;
 
     /// Query statistics (available to all permission levels)
@@ -142,13 +154,61 @@ pub trait PluginMemoryInterface: Send + Sync {
 }
 
 /// Event filter for querying
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Quantity bounds are inclusive; once `min_quantity`, `max_quantity` or
+/// `unit` is set, events without a quantity are excluded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct EventFilter {
     pub start_time: Option<DateTime<Utc>>,
     pub end_time: Option<DateTime<Utc>>,
     pub actions: Option<Vec<String>>,
     pub targets: Option<Vec<String>>,
     pub limit: Option<usize>,
+    #[serde(default)]
+    pub min_quantity: Option<f64>,
+    #[serde(default)]
+    pub max_quantity: Option<f64>,
+    /// Unit the quantity must be measured in, matched exactly
+    #[serde(default)]
+    pub unit: Option<String>,
+}
+
+impl EventFilter {
+    /// Reject an inverted or non-finite quantity range
+    pub fn validate(&self) -> Result<()> {
+        validate_quantity_range(self.min_quantity, self.max_quantity)
+    }
+
+    /// Check whether `event` passes every condition
+    ///
+    /// `limit` is applied by the caller.
+    pub fn matches(&self, event: &EventMemory) -> bool {
+        if self.start_time.map_or(false, |start| event.timestamp < start)
+            || self.end_time.map_or(false, |end| event.timestamp > end)
+        {
+            return false;
+        }
+        if self.actions.as_ref().map_or(false, |actions| !actions.contains(&event.action))
+            || self.targets.as_ref().map_or(false, |targets| !targets.contains(&event.target))
+        {
+            return false;
+        }
+        event.quantity_matches(self.min_quantity, self.max_quantity, self.unit.as_deref())
+    }
+}
+
+/// Reject quantity bounds that are not finite or where `min > max`
+pub fn validate_quantity_range(min: Option<f64>, max: Option<f64>) -> Result<()> {
+    if min.into_iter().chain(max).any(|bound| !bound.is_finite()) {
+        return Err(DirSoulError::Config("Quantity bounds must be finite numbers".to_string()));
+    }
+    match (min, max) {
+        (Some(min), Some(max)) if min > max => Err(DirSoulError::Config(format!(
+            "min_quantity {} is greater than max_quantity {}",
+            min, max
+        ))),
+        _ => Ok(()),
+    }
 }
 
 /// Time range for statistics (plugin-specific)
@@ -170,11 +230,27 @@ pub struct Statistics {
 /// Entity filter for querying
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EntityFilter {
+    /// Built-in or custom type names, matched case-insensitively
     pub entity_types: Option<Vec<String>>,
     pub min_confidence: Option<f64>,
     pub limit: Option<usize>,
 }
 
+impl EntityFilter {
+    /// Check whether `entity` passes the type and confidence conditions
+    ///
+    /// `limit` is applied by the caller.
+    pub fn matches(&self, entity: &Entity) -> bool {
+        if let Some(types) = &self.entity_types {
+            let entity_type = entity.get_type();
+            if !types.iter().any(|t| EntityType::from(t.as_str()) == entity_type) {
+                return false;
+            }
+        }
+        self.min_confidence.map_or(true, |min| entity.confidence >= min)
+    }
+}
+
 /// Base User Plugin trait
 ///
 /// All plugins must implement this trait to interact with DirSoul.
@@ -254,6 +330,7 @@ impl PluginContext {
                 "Plugin does not have permission to query events".to_string(),
             ));
         }
+        filter.validate()?;
 
         self.memory_interface.query_events(&self.user_id, filter).await
     }
@@ -269,13 +346,41 @@ impl PluginContext {
         self.memory_interface.create_view(&self.user_id, view).await
     }
 
+    /// Create raw memory with permission check
+    ///
+    /// Call this before `create_event` and use the returned `memory_id` as the
+    /// event's source.
+    pub async fn create_raw_memory(&self, memory: NewRawMemory) -> Result<RawMemory> {
+        if !self.permission.can_create_events() {
+            return Err(DirSoulError::Config(
+                "Plugin does not have permission to create raw memories".to_string(),
+            ));
+        }
+        if memory.user_id != self.user_id {
+            return Err(DirSoulError::Config(format!(
+                "Plugin cannot create memories for user {}",
+                memory.user_id
+            )));
+        }
+
+        self.memory_interface.create_raw_memory(&self.user_id, memory).await
+    }
+
     /// Create event with permission check
+    ///
+    /// The event must reference a raw memory created earlier with
+    /// `create_raw_memory`; a nil `memory_id` is rejected up front.
     pub async fn create_event(&self, event: NewEventMemory) -> Result<EventMemory> {
         if !self.permission.can_create_events() {
             return Err(DirSoulError::Config(
                 "Plugin does not have permission to create events".to_string(),
             ));
         }
+        if event.memory_id.is_nil() {
+            return Err(DirSoulError::Config(
+                "Event has no source raw memory; call create_raw_memory first".to_string(),
+            ));
+        }
 
         self.memory_interface.create_event(&self.user_id, event).await
     }
@@ -313,6 +418,9 @@ pub struct PluginSpec {
     pub author: String,
     pub executable: Option<String>,  // For external process plugins
     pub is_builtin: bool,
+    /// Per-plugin query/event timeout in milliseconds (None uses the manager default)
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 impl PluginSpec {
@@ -326,8 +434,14 @@ impl PluginSpec {
             author: metadata.author.clone(),
             executable: None,
             is_builtin: metadata.is_builtin,
+            timeout_ms: None,
         }
     }
+
+    /// Timeout override, if one is set
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_ms.map(Duration::from_millis)
+    }
 }
 
 /// Plugin execution timeout configuration
@@ -353,6 +467,29 @@ impl Default for PluginTimeoutConfig {
     }
 }
 
+/// Limits on how many plugins a `PluginManager` holds
+///
+/// Keeps plugin sprawl from exhausting memory on the 8GB target.
+/// Reinstalling a plugin under an id that is already installed replaces it
+/// and does not count against the limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginLimits {
+    /// Maximum installed plugins overall, built-ins included
+    pub max_plugins: usize,
+
+    /// Maximum plugins installed on behalf of a single user
+    pub max_plugins_per_user: usize,
+}
+
+impl Default for PluginLimits {
+    fn default() -> Self {
+        Self {
+            max_plugins: 64,
+            max_plugins_per_user: 16,
+        }
+    }
+}
+
 /// Isolated plugin instance with health tracking
 ///
 /// This wrapper provides thread-safe isolation and monitoring for plugins.
@@ -363,9 +500,12 @@ pub struct IsolatedPlugin {
     /// Plugin metadata
     metadata: PluginMetadata,
 
-    /// Permission level
+    /// Effective permission level
     permission: MemoryPermission,
 
+    /// Permission granted at install time; the ceiling for `permission`
+    granted_permission: MemoryPermission,
+
     /// Health status
     is_healthy: Arc<RwLock<bool>>,
 
@@ -377,6 +517,12 @@ pub struct IsolatedPlugin {
 
     /// Max restarts allowed
     max_restarts: usize,
+
+    /// Timeout for query and event handlers
+    timeout: Duration,
+
+    /// User the plugin was installed for (`None` for system-wide plugins)
+    owner: Option<String>,
 }
 
 impl IsolatedPlugin {
@@ -392,13 +538,38 @@ impl IsolatedPlugin {
             plugin,
             metadata,
             permission,
+            granted_permission: permission,
             is_healthy: Arc::new(RwLock::new(true)),
             last_health_check: Arc::new(RwLock::new(None)),
             restart_count: Arc::new(Mutex::new(0)),
             max_restarts,
+            timeout: PluginTimeoutConfig::default().default_timeout,
+            owner: None,
         }
     }
 
+    /// Set the timeout for query and event handlers
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Timeout applied to query and event handlers
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Set the user the plugin is installed for
+    pub fn with_owner(mut self, owner: impl Into<String>) -> Self {
+        self.owner = Some(owner.into());
+        self
+    }
+
+    /// User the plugin is installed for, if any
+    pub fn owner(&self) -> Option<&str> {
+        self.owner.as_deref()
+    }
+
     /// Get plugin metadata
     pub fn metadata(&self) -> &PluginMetadata {
         &self.metadata
@@ -409,6 +580,11 @@ impl IsolatedPlugin {
         self.permission
     }
 
+    /// Get the permission level granted at install time
+    pub fn granted_permission(&self) -> MemoryPermission {
+        self.granted_permission
+    }
+
     /// Check if plugin is healthy
     pub async fn is_healthy(&self) -> bool {
         *self.is_healthy.read().await
@@ -439,18 +615,17 @@ impl IsolatedPlugin {
         *self.restart_count.lock().await < self.max_restarts
     }
 
-    /// Execute plugin event handler with timeout
+    /// Execute plugin event handler with the plugin's timeout
     pub async fn on_event(
         &self,
         event: &EventNotification,
         context: &PluginContext,
-        timeout: Duration,
     ) -> Result<PluginOutput> {
         let plugin = self.plugin.clone();
         let event = event.clone();
         let context = context.clone();
 
-        tokio::time::timeout(timeout, async move {
+        tokio::time::timeout(self.timeout, async move {
             plugin.on_event(&event, &context).await
         })
         .await
@@ -460,18 +635,17 @@ impl IsolatedPlugin {
         )))?
     }
 
-    /// Execute plugin query handler with timeout
+    /// Execute plugin query handler with the plugin's timeout
     pub async fn on_query(
         &self,
         query: &str,
         context: &PluginContext,
-        timeout: Duration,
     ) -> Result<PluginResponse> {
         let plugin = self.plugin.clone();
         let query = query.to_string();
         let context = context.clone();
 
-        tokio::time::timeout(timeout, async move {
+        tokio::time::timeout(self.timeout, async move {
             plugin.on_query(&query, &context).await
         })
         .await
@@ -505,6 +679,15 @@ pub struct PluginManager {
 
     /// Restart backoff base duration
     restart_backoff: Duration,
+
+    /// Most recent runtime permission changes, at most `MAX_PERMISSION_CHANGES`
+    permission_changes: Arc<RwLock<VecDeque<PermissionChange>>>,
+
+    /// Durable record of runtime permission changes
+    audit_logger: Option<ThreadSafeAuditLogger>,
+
+    /// Installed plugin limits
+    limits: PluginLimits,
 }
 
 impl PluginManager {
@@ -525,9 +708,29 @@ impl PluginManager {
             timeout_config,
             max_restarts,
             restart_backoff,
+            permission_changes: Arc::new(RwLock::new(VecDeque::new())),
+            audit_logger: None,
+            limits: PluginLimits::default(),
         }
     }
 
+    /// Record runtime permission changes in the audit log
+    pub fn with_audit_logger(mut self, audit_logger: ThreadSafeAuditLogger) -> Self {
+        self.audit_logger = Some(audit_logger);
+        self
+    }
+
+    /// Set the installed plugin limits
+    pub fn with_limits(mut self, limits: PluginLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Installed plugin limits
+    pub fn limits(&self) -> PluginLimits {
+        self.limits
+    }
+
     /// Register a plugin specification
     pub async fn register_spec(&self, spec: PluginSpec) -> Result<()> {
         let mut specs = self.plugin_specs.write().await;
@@ -537,6 +740,9 @@ impl PluginManager {
 
     /// Install and start a plugin
     ///
+    /// The handler timeout comes from a previously registered `PluginSpec`
+    /// with `timeout_ms`, else `PluginTimeoutConfig::default_timeout`.
+    ///
     /// # Arguments
     /// - `plugin`: Plugin instance to install
     /// - `permission`: Permission level to grant
@@ -547,9 +753,54 @@ impl PluginManager {
         &self,
         plugin: Arc<dyn UserPlugin>,
         permission: MemoryPermission,
+    ) -> Result<PluginMetadata> {
+        self.install_plugin(plugin, permission, None, None).await
+    }
+
+    /// Install a plugin on behalf of a user
+    ///
+    /// Counts against `PluginLimits::max_plugins_per_user` for `user_id` as
+    /// well as the overall limit.
+    pub async fn install_for_user(
+        &self,
+        user_id: &str,
+        plugin: Arc<dyn UserPlugin>,
+        permission: MemoryPermission,
+    ) -> Result<PluginMetadata> {
+        self.install_plugin(plugin, permission, None, Some(user_id)).await
+    }
+
+    /// Install a plugin with its own query/event timeout
+    ///
+    /// The override is kept in the plugin's `PluginSpec`.
+    pub async fn install_with_timeout(
+        &self,
+        plugin: Arc<dyn UserPlugin>,
+        permission: MemoryPermission,
+        timeout: Duration,
+    ) -> Result<PluginMetadata> {
+        self.install_plugin(plugin, permission, Some(timeout), None).await
+    }
+
+    async fn install_plugin(
+        &self,
+        plugin: Arc<dyn UserPlugin>,
+        permission: MemoryPermission,
+        timeout: Option<Duration>,
+        owner: Option<&str>,
     ) -> Result<PluginMetadata> {
         let metadata = plugin.metadata().clone();
 
+        // Fail before running any plugin code
+        self.check_limits(&*self.plugins.read().await, &metadata.id, owner)?;
+
+        if timeout.map_or(false, |timeout| timeout.is_zero()) {
+            return Err(DirSoulError::Config(format!(
+                "Plugin {} timeout must be greater than zero",
+                metadata.id
+            )));
+        }
+
         // Validate requested permission doesn't exceed required
         if permission.as_i32() < metadata.required_permission.as_i32() {
             return Err(DirSoulError::PermissionDenied(format!(
@@ -558,8 +809,15 @@ impl PluginManager {
             )));
         }
 
-        // Register spec
-        let spec = PluginSpec::from_metadata(&metadata);
+        // Register spec, keeping a timeout override registered beforehand
+        let mut spec = PluginSpec::from_metadata(&metadata);
+        spec.timeout_ms = match timeout {
+            Some(timeout) => Some(timeout.as_millis() as u64),
+            None => self.plugin_specs.read().await
+                .get(&metadata.id)
+                .and_then(|registered| registered.timeout_ms),
+        };
+        let timeout = spec.timeout().unwrap_or(self.timeout_config.default_timeout);
         self.register_spec(spec).await?;
 
         // Initialize plugin
@@ -568,15 +826,74 @@ impl PluginManager {
         })?;
 
         // Create isolated instance
-        let isolated = IsolatedPlugin::new(plugin, permission, self.max_restarts);
+        let mut isolated = IsolatedPlugin::new(plugin.clone(), permission, self.max_restarts)
+            .with_timeout(timeout);
+        if let Some(owner) = owner {
+            isolated = isolated.with_owner(owner);
+        }
 
-        // Store plugin
+        // Store plugin, re-checking limits against concurrent installs
         let mut plugins = self.plugins.write().await;
+        if let Err(e) = self.check_limits(&plugins, &metadata.id, owner) {
+            drop(plugins);
+            let _ = plugin.cleanup().await;
+            return Err(e);
+        }
+
+        // A reinstall must not lift a sandbox set with `set_permission`
+        if let Some(existing) = plugins.get(&metadata.id) {
+            if existing.permission < existing.granted_permission {
+                isolated.permission = isolated.permission.min(existing.permission);
+            }
+        }
         plugins.insert(metadata.id.clone(), isolated);
 
         Ok(metadata)
     }
 
+    /// Refuse an install that would exceed the overall or per-user limit
+    ///
+    /// Plugin ids are global, so an installed plugin may only be replaced by
+    /// its own owner; anyone else gets `PermissionDenied`.
+    fn check_limits(
+        &self,
+        plugins: &HashMap<String, IsolatedPlugin>,
+        plugin_id: &str,
+        owner: Option<&str>,
+    ) -> Result<()> {
+        // Replacing an installed plugin doesn't grow the registry
+        if let Some(existing) = plugins.get(plugin_id) {
+            if existing.owner() != owner {
+                return Err(DirSoulError::PermissionDenied(format!(
+                    "Plugin {} is installed by another owner",
+                    plugin_id
+                )));
+            }
+            return Ok(());
+        }
+
+        if plugins.len() >= self.limits.max_plugins {
+            return Err(DirSoulError::Plugin(format!(
+                "Cannot install plugin {}: plugin limit reached ({} of {} installed)",
+                plugin_id,
+                plugins.len(),
+                self.limits.max_plugins
+            )));
+        }
+
+        if let Some(owner) = owner {
+            let owned = plugins.values().filter(|p| p.owner() == Some(owner)).count();
+            if owned >= self.limits.max_plugins_per_user {
+                return Err(DirSoulError::Plugin(format!(
+                    "Cannot install plugin {} for user {}: per-user plugin limit reached ({} of {} installed)",
+                    plugin_id, owner, owned, self.limits.max_plugins_per_user
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Uninstall a plugin
     ///
     /// Built-in plugins cannot be uninstalled.
@@ -627,6 +944,74 @@ impl PluginManager {
         Ok(plugin.permission() >= permission)
     }
 
+    /// Change a plugin's effective permission at runtime
+    ///
+    /// The new level may be anything up to the permission granted at install
+    /// time, so operators can sandbox a suspicious plugin (even below its
+    /// declared requirement, making its memory calls fail) and later restore
+    /// it. Raising above the install-time grant returns `PermissionDenied`.
+    /// Every change is written to the audit logger, if one is set, and kept
+    /// in `permission_changes`.
+    ///
+    /// # Returns
+    /// The previous effective permission
+    pub async fn set_permission(
+        &self,
+        plugin_id: &str,
+        permission: MemoryPermission,
+    ) -> Result<MemoryPermission> {
+        let mut plugins = self.plugins.write().await;
+
+        let plugin = plugins.get_mut(plugin_id)
+            .ok_or_else(|| DirSoulError::PluginNotFound(plugin_id.to_string()))?;
+
+        if permission > plugin.granted_permission {
+            return Err(DirSoulError::PermissionDenied(format!(
+                "Plugin {} was granted {:?}, cannot raise to {:?}",
+                plugin_id, plugin.granted_permission, permission
+            )));
+        }
+
+        let previous = plugin.permission;
+        if previous == permission {
+            return Ok(previous);
+        }
+        plugin.permission = permission;
+        let owner = plugin.owner.clone();
+        drop(plugins);
+
+        {
+            let mut changes = self.permission_changes.write().await;
+            if changes.len() >= MAX_PERMISSION_CHANGES {
+                changes.pop_front();
+            }
+            changes.push_back(PermissionChange {
+                plugin_id: plugin_id.to_string(),
+                from: previous,
+                to: permission,
+                changed_at: Utc::now(),
+            });
+        }
+
+        if let Some(logger) = &self.audit_logger {
+            let metadata = serde_json::json!({ "from": previous, "to": permission });
+            let user_id = owner.as_deref().unwrap_or("system");
+            if let Err(e) = logger
+                .log_custom(user_id, "set_permission", plugin_id, true, Some(metadata))
+                .await
+            {
+                tracing::warn!("Failed to audit permission change for plugin {}: {}", plugin_id, e);
+            }
+        }
+
+        Ok(previous)
+    }
+
+    /// Most recent runtime permission changes, oldest first
+    pub async fn permission_changes(&self) -> Vec<PermissionChange> {
+        self.permission_changes.read().await.iter().cloned().collect()
+    }
+
     /// Get plugin by ID
     pub async fn get_plugin(&self, plugin_id: &str) -> Result<IsolatedPlugin> {
         let plugins = self.plugins.read().await;
@@ -716,12 +1101,16 @@ impl PluginManager {
 
         let mut total_restarts = 0;
         let mut healthy_count = 0;
+        let mut plugins_per_user: HashMap<String, usize> = HashMap::new();
 
         for plugin in plugins.values() {
             total_restarts += plugin.restart_count().await;
             if plugin.is_healthy().await {
                 healthy_count += 1;
             }
+            if let Some(owner) = plugin.owner() {
+                *plugins_per_user.entry(owner.to_string()).or_insert(0) += 1;
+            }
         }
 
         PluginManagerStats {
@@ -729,6 +1118,9 @@ impl PluginManager {
             healthy_plugins: healthy_count,
             registered_specs: specs.len(),
             total_restarts,
+            max_plugins: self.limits.max_plugins,
+            plugins_per_user,
+            max_plugins_per_user: self.limits.max_plugins_per_user,
         }
     }
 }
@@ -739,6 +1131,18 @@ impl Default for PluginManager {
     }
 }
 
+/// Number of runtime permission changes `PluginManager` keeps in memory
+pub const MAX_PERMISSION_CHANGES: usize = 100;
+
+/// Audit record for a runtime permission change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PermissionChange {
+    pub plugin_id: String,
+    pub from: MemoryPermission,
+    pub to: MemoryPermission,
+    pub changed_at: DateTime<Utc>,
+}
+
 /// Plugin manager statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginManagerStats {
@@ -746,6 +1150,12 @@ pub struct PluginManagerStats {
     pub healthy_plugins: usize,
     pub registered_specs: usize,
     pub total_restarts: usize,
+    /// Overall limit that `total_plugins` counts against
+    pub max_plugins: usize,
+    /// Installed plugins per owning user
+    pub plugins_per_user: HashMap<String, usize>,
+    /// Limit that each `plugins_per_user` entry counts against
+    pub max_plugins_per_user: usize,
 }
 
 /// Clone helper for IsolatedPlugin
@@ -755,10 +1165,13 @@ impl Clone for IsolatedPlugin {
             plugin: self.plugin.clone(),
             metadata: self.metadata.clone(),
             permission: self.permission,
+            granted_permission: self.granted_permission,
             is_healthy: self.is_healthy.clone(),
             last_health_check: self.last_health_check.clone(),
             restart_count: self.restart_count.clone(),
             max_restarts: self.max_restarts,
+            timeout: self.timeout,
+            owner: self.owner.clone(),
         }
     }
 }
@@ -858,9 +1271,17 @@ impl CommandRouter {
     }
 
     /// Route command to appropriate plugin and execute
+    ///
+    /// Empty or whitespace-only queries (including a bare `@plugin` with no
+    /// text) are answered with `CommandResponse::Error` without calling any
+    /// plugin.
     pub async fn route(&self, input: &str) -> Result<CommandResponse> {
         let command = self.parse_command(input);
 
+        if let Some(message) = Self::empty_query_error(&command) {
+            return Ok(CommandResponse::Error(message));
+        }
+
         match command {
             ParsedCommand::PluginCall { plugin, query } => {
                 self.route_to_plugin(&plugin, &query).await
@@ -872,10 +1293,43 @@ impl CommandRouter {
         }
     }
 
+    /// Error message for a command that carries no query text
+    fn empty_query_error(command: &ParsedCommand) -> Option<String> {
+        match command {
+            ParsedCommand::PluginCall { plugin, query } if query.trim().is_empty() => {
+                Some(format!("empty query for @{}", plugin))
+            }
+            ParsedCommand::PluginCall { .. } => None,
+            ParsedCommand::DefaultQuery { query } => {
+                let query = query.trim();
+                if query.is_empty() {
+                    return Some("empty query".to_string());
+                }
+                // "@plugin" alone doesn't match the command regex and would
+                // otherwise reach the default plugin as literal text
+                query
+                    .strip_prefix('@')
+                    .filter(|name| {
+                        !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+                    })
+                    .map(|name| format!("empty query for @{}", name))
+            }
+        }
+    }
+
     /// Route to specific plugin
+    ///
+    /// A plugin installed for a user (`install_for_user`) only answers that
+    /// user; plugins installed without an owner are shared.
     async fn route_to_plugin(&self, plugin_id: &str, query: &str) -> Result<CommandResponse> {
-        // Check plugin exists and is healthy
+        // Check plugin exists, belongs to this user and is healthy
         let plugin = self.manager.get_plugin(plugin_id).await?;
+        if plugin.owner().map_or(false, |owner| owner != self.user_id) {
+            return Err(DirSoulError::PermissionDenied(format!(
+                "Plugin {} belongs to another user",
+                plugin_id
+            )));
+        }
 
         if !plugin.is_healthy().await {
             return Ok(CommandResponse::Error(format!(
@@ -893,9 +1347,8 @@ impl CommandRouter {
             memory_interface,
         );
 
-        // Execute plugin query with timeout
-        let timeout = Duration::from_secs(30);
-        let response = plugin.on_query(query, &context, timeout).await?;
+        // Execute plugin query with the plugin's configured timeout
+        let response = plugin.on_query(query, &context).await?;
 
         // Log plugin interaction as event
         self.log_plugin_interaction(plugin_id, query, &response).await?;
@@ -925,6 +1378,13 @@ impl CommandRouter {
         query: &str,
         response: &PluginResponse,
     ) -> Result<()> {
+        let metadata = serde_json::json!({
+            "query": query,
+            "response_length": response.content.len(),
+            "sources": response.sources,
+            "confidence": response.confidence,
+        });
+
         // Create event memory for plugin interaction
         let event = NewEventMemory {
             memory_id: Uuid::new_v4(),
@@ -936,23 +1396,14 @@ impl CommandRouter {
             quantity: None,
             unit: None,
             confidence: 1.0,
-            extractor_version: Some("command_router".to_string()),
+            extractor_version: Some(ExtractorVersion::CommandRouter.tag()),
+            metadata: Some(metadata),
         };
 
         // TODO: Store event in database
         // For now, we'll create the event structure
         let _event = event;  // Suppress unused warning
 
-        // Log metadata (in production, store in event metadata)
-        let metadata = serde_json::json!({
-            "query": query,
-            "response_length": response.content.len(),
-            "sources": response.sources,
-            "confidence": response.confidence,
-        });
-
-        let _metadata = metadata;  // Suppress unused warning
-
         Ok(())
     }
 
@@ -965,7 +1416,7 @@ impl CommandRouter {
 }
 
 /// Mock memory interface for testing (TODO: replace with real implementation)
-struct MockMemoryInterface;
+pub(crate) struct MockMemoryInterface;
 
 #[async_trait]
 impl PluginMemoryInterface for MockMemoryInterface {
@@ -977,6 +1428,10 @@ impl PluginMemoryInterface for MockMemoryInterface {
         Err(DirSoulError::Config("Mock memory interface".to_string()))
     }
 
+    async fn create_raw_memory(&self, _user_id: &str, _memory: NewRawMemory) -> Result<RawMemory> {
+        Err(DirSoulError::Config("Mock memory interface".to_string()))
+    }
+
     async fn create_event(&self, _user_id: &str, _event: NewEventMemory) -> Result<EventMemory> {
         Err(DirSoulError::Config("Mock memory interface".to_string()))
     }
@@ -1052,10 +1507,91 @@ mod tests {
             actions: Some(vec!["test".to_string()]),
             targets: Some(vec!["target".to_string()]),
             limit: Some(10),
+            ..Default::default()
         };
 
         let json = serde_json::to_string(&filter).unwrap();
         let _deserialized: EventFilter = serde_json::from_str(&json).unwrap();
+
+        // Filters serialized before quantity bounds existed still parse
+        let legacy: EventFilter = serde_json::from_str(
+            r#"{"start_time":null,"end_time":null,"actions":null,"targets":null,"limit":5}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy.min_quantity, None);
+        assert_eq!(legacy.unit, None);
+    }
+
+    /// Event eating `quantity` apples, measured in `unit`
+    fn apple_event(quantity: Option<f64>, unit: Option<&str>) -> EventMemory {
+        EventMemory {
+            event_id: Uuid::new_v4(),
+            memory_id: Uuid::new_v4(),
+            user_id: "test_user".to_string(),
+            timestamp: Utc::now(),
+            actor: None,
+            action: "吃".to_string(),
+            target: "苹果".to_string(),
+            quantity,
+            unit: unit.map(str::to_string),
+            confidence: 0.9,
+            extractor_version: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_event_filter_quantity_range() {
+        let events = [
+            apple_event(Some(2.0), Some("个")),
+            apple_event(Some(3.0), Some("个")),
+            apple_event(Some(5.0), Some("个")),
+            apple_event(Some(4.0), Some("斤")),
+            apple_event(None, None),
+        ];
+        let quantities = |filter: &EventFilter| -> Vec<Option<f64>> {
+            events.iter().filter(|e| filter.matches(e)).map(|e| e.quantity).collect()
+        };
+
+        // No quantity condition keeps quantity-less events
+        assert_eq!(quantities(&EventFilter::default()).len(), 5);
+
+        let more_than_three = EventFilter {
+            min_quantity: Some(3.5),
+            ..Default::default()
+        };
+        assert_eq!(quantities(&more_than_three), vec![Some(5.0), Some(4.0)]);
+
+        // Bounds are inclusive; the unit narrows further
+        let three_to_five = EventFilter {
+            min_quantity: Some(3.0),
+            max_quantity: Some(5.0),
+            unit: Some("个".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(quantities(&three_to_five), vec![Some(3.0), Some(5.0)]);
+
+        let unit_only = EventFilter {
+            unit: Some("斤".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(quantities(&unit_only), vec![Some(4.0)]);
+    }
+
+    #[test]
+    fn test_event_filter_rejects_invalid_quantity_range() {
+        assert!(EventFilter::default().validate().is_ok());
+        let inverted = EventFilter {
+            min_quantity: Some(5.0),
+            max_quantity: Some(3.0),
+            ..Default::default()
+        };
+        assert!(matches!(inverted.validate(), Err(DirSoulError::Config(_))));
+        let not_a_number = EventFilter {
+            max_quantity: Some(f64::NAN),
+            ..Default::default()
+        };
+        assert!(not_a_number.validate().is_err());
     }
 
     #[test]
@@ -1114,6 +1650,32 @@ mod tests {
         let _deserialized: EntityFilter = serde_json::from_str(&json).unwrap();
     }
 
+    #[test]
+    fn test_entity_filter_matches_custom_types() {
+        let entity = |entity_type: &str, confidence: f64| Entity {
+            entity_id: Uuid::new_v4(),
+            user_id: "user123".to_string(),
+            canonical_name: "Aspirin".to_string(),
+            entity_type: entity_type.to_string(),
+            attributes: None,
+            first_seen: Utc::now(),
+            last_seen: Utc::now(),
+            occurrence_count: 1,
+            confidence,
+        };
+        let filter = EntityFilter {
+            entity_types: Some(vec!["Medication".to_string(), "person".to_string()]),
+            min_confidence: Some(0.5),
+            limit: None,
+        };
+
+        assert!(filter.matches(&entity("medication", 0.9)));
+        assert!(filter.matches(&entity("Person", 0.9)));
+        assert!(!filter.matches(&entity("object", 0.9)));
+        assert!(!filter.matches(&entity("project", 0.9)));
+        assert!(!filter.matches(&entity("medication", 0.2)));
+    }
+
     /// Mock plugin for testing
     struct MockPlugin {
         metadata: PluginMetadata,
@@ -1169,6 +1731,167 @@ mod tests {
         }
     }
 
+    /// In-memory store that enforces the event -> raw memory reference
+    #[derive(Default)]
+    struct InMemoryStore {
+        raw_memories: std::sync::Mutex<Vec<RawMemory>>,
+    }
+
+    #[async_trait]
+    impl PluginMemoryInterface for InMemoryStore {
+        async fn query_events(&self, _user_id: &str, _filter: &EventFilter) -> Result<Vec<EventMemory>> {
+            Ok(vec![])
+        }
+
+        async fn create_view(&self, _user_id: &str, _view: NewCognitiveView) -> Result<CognitiveView> {
+            Err(DirSoulError::Config("Not supported".to_string()))
+        }
+
+        async fn create_raw_memory(&self, user_id: &str, memory: NewRawMemory) -> Result<RawMemory> {
+            let raw = RawMemory {
+                memory_id: Uuid::new_v4(),
+                user_id: user_id.to_string(),
+                created_at: Utc::now(),
+                content_type: memory.content_type,
+                content: memory.content,
+                encrypted: memory.encrypted,
+                metadata: memory.metadata,
+                embedding: None,
+                deleted_at: None,
+                embedding_model: None,
+            };
+            self.raw_memories.lock().unwrap().push(raw.clone());
+            Ok(raw)
+        }
+
+        async fn create_event(&self, user_id: &str, event: NewEventMemory) -> Result<EventMemory> {
+            let known = self
+                .raw_memories
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|raw| raw.memory_id == event.memory_id && raw.user_id == user_id);
+            if !known {
+                return Err(DirSoulError::Config(
+                    "violates foreign key constraint event_memories_memory_id_fkey".to_string(),
+                ));
+            }
+
+            Ok(EventMemory {
+                event_id: Uuid::new_v4(),
+                memory_id: event.memory_id,
+                user_id: user_id.to_string(),
+                timestamp: event.timestamp,
+                actor: event.actor,
+                action: event.action,
+                target: event.target,
+                quantity: event.quantity,
+                unit: event.unit,
+                confidence: event.confidence,
+                extractor_version: event.extractor_version,
+                metadata: event.metadata,
+            })
+        }
+
+        async fn get_statistics(&self, _user_id: &str, _time_range: PluginTimeRange) -> Result<Statistics> {
+            Ok(Statistics {
+                event_count: 0,
+                view_count: 0,
+                concept_count: 0,
+                entity_count: 0,
+            })
+        }
+
+        async fn query_entities(&self, _user_id: &str, _filter: &EntityFilter) -> Result<Vec<Entity>> {
+            Ok(vec![])
+        }
+
+        fn has_permission(&self, _permission: MemoryPermission) -> bool {
+            true
+        }
+    }
+
+    fn store_context(permission: MemoryPermission) -> PluginContext {
+        PluginContext::new(
+            "recorder".to_string(),
+            "test_user".to_string(),
+            permission,
+            Arc::new(InMemoryStore::default()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_plugin_creates_raw_memory_then_event() {
+        use crate::models::ContentType;
+
+        let context = store_context(MemoryPermission::ReadWriteEvents);
+
+        let raw = context
+            .create_raw_memory(NewRawMemory::new_plaintext(
+                "test_user".to_string(),
+                ContentType::Text,
+                "跑步 5 公里".to_string(),
+            ))
+            .await
+            .unwrap();
+
+        let event = context
+            .create_event(NewEventMemory::new(
+                raw.memory_id,
+                "test_user".to_string(),
+                Utc::now(),
+                "run".to_string(),
+                "park".to_string(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(event.memory_id, raw.memory_id);
+
+        // An event pointing at a memory that was never created is refused
+        let orphan = NewEventMemory::new(
+            Uuid::new_v4(),
+            "test_user".to_string(),
+            Utc::now(),
+            "run".to_string(),
+            "park".to_string(),
+        );
+        assert!(context.create_event(orphan).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_event_rejects_nil_memory_id() {
+        let context = store_context(MemoryPermission::ReadWriteEvents);
+        let event = NewEventMemory::new(
+            Uuid::nil(),
+            "test_user".to_string(),
+            Utc::now(),
+            "run".to_string(),
+            "park".to_string(),
+        );
+
+        let err = context.create_event(event).await.unwrap_err();
+        assert!(err.to_string().contains("create_raw_memory"));
+    }
+
+    #[tokio::test]
+    async fn test_create_raw_memory_requires_permission() {
+        use crate::models::ContentType;
+
+        let memory = NewRawMemory::new_plaintext(
+            "test_user".to_string(),
+            ContentType::Text,
+            "hello".to_string(),
+        );
+        let context = store_context(MemoryPermission::ReadWriteDerived);
+        assert!(context.create_raw_memory(memory.clone()).await.is_err());
+
+        // Writing into another user's memory is refused too
+        let context = store_context(MemoryPermission::ReadWriteEvents);
+        let mut foreign = memory;
+        foreign.user_id = "someone_else".to_string();
+        assert!(context.create_raw_memory(foreign).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_plugin_manager_creation() {
         let manager = PluginManager::new();
@@ -1234,6 +1957,181 @@ mod tests {
             .unwrap());
     }
 
+    #[tokio::test]
+    async fn test_set_permission_lowers_and_restores() {
+        let manager = PluginManager::new();
+        let plugin = Arc::new(MockPlugin::new("sandboxed", MemoryPermission::ReadWriteDerived));
+
+        manager
+            .install(plugin, MemoryPermission::ReadWriteDerived)
+            .await
+            .unwrap();
+
+        // Sandbox below the declared requirement
+        let previous = manager
+            .set_permission("sandboxed", MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+        assert_eq!(previous, MemoryPermission::ReadWriteDerived);
+        assert!(!manager
+            .check_permission("sandboxed", MemoryPermission::ReadWriteDerived)
+            .await
+            .unwrap());
+
+        let isolated = manager.get_plugin("sandboxed").await.unwrap();
+        assert_eq!(isolated.permission(), MemoryPermission::ReadOnly);
+        assert_eq!(isolated.granted_permission(), MemoryPermission::ReadWriteDerived);
+
+        // Restore up to the original grant
+        manager
+            .set_permission("sandboxed", MemoryPermission::ReadWriteDerived)
+            .await
+            .unwrap();
+        assert!(manager
+            .check_permission("sandboxed", MemoryPermission::ReadWriteDerived)
+            .await
+            .unwrap());
+
+        let changes = manager.permission_changes().await;
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].plugin_id, "sandboxed");
+        assert_eq!(changes[0].from, MemoryPermission::ReadWriteDerived);
+        assert_eq!(changes[0].to, MemoryPermission::ReadOnly);
+        assert_eq!(changes[1].from, MemoryPermission::ReadOnly);
+        assert_eq!(changes[1].to, MemoryPermission::ReadWriteDerived);
+    }
+
+    #[tokio::test]
+    async fn test_set_permission_never_exceeds_grant() {
+        let manager = PluginManager::new();
+        let plugin = Arc::new(MockPlugin::new("capped", MemoryPermission::ReadOnly));
+
+        manager
+            .install(plugin, MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+
+        let result = manager
+            .set_permission("capped", MemoryPermission::ReadWriteEvents)
+            .await;
+        assert!(matches!(result, Err(DirSoulError::PermissionDenied(_))));
+        assert!(!manager
+            .check_permission("capped", MemoryPermission::ReadWriteDerived)
+            .await
+            .unwrap());
+
+        // Rejected and no-op changes leave no audit record
+        manager
+            .set_permission("capped", MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+        assert!(manager.permission_changes().await.is_empty());
+
+        assert!(matches!(
+            manager.set_permission("missing", MemoryPermission::ReadOnly).await,
+            Err(DirSoulError::PluginNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_set_permission_is_audited_and_bounded() {
+        use crate::audit::{AuditBatchWriter, AuditBufferConfig, NewAuditLog};
+
+        let written = Arc::new(std::sync::Mutex::new(Vec::<NewAuditLog>::new()));
+        let sink = written.clone();
+        let writer: AuditBatchWriter = Arc::new(move |batch: Vec<NewAuditLog>| {
+            let count = batch.len();
+            sink.lock().unwrap().extend(batch);
+            Ok(count)
+        });
+        let logger = ThreadSafeAuditLogger::new("postgresql://localhost/test".to_string())
+            .with_buffer_writer(
+                AuditBufferConfig {
+                    max_entries: 1,
+                    flush_interval: Duration::from_secs(60),
+                },
+                writer,
+            );
+
+        let manager = PluginManager::new().with_audit_logger(logger);
+        let plugin = Arc::new(MockPlugin::new("audited", MemoryPermission::ReadOnly));
+        manager
+            .install_for_user("alice", plugin, MemoryPermission::ReadWriteDerived)
+            .await
+            .unwrap();
+
+        manager
+            .set_permission("audited", MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+        {
+            let written = written.lock().unwrap();
+            assert_eq!(written.len(), 1);
+            assert_eq!(written[0].user_id, "alice");
+            assert_eq!(written[0].action, "set_permission");
+            assert_eq!(written[0].target, "audited");
+            assert_eq!(
+                written[0].metadata,
+                Some(serde_json::json!({ "from": "ReadWriteDerived", "to": "ReadOnly" }))
+            );
+        }
+
+        // Toggling past the cap keeps only the newest changes in memory
+        for i in 0..MAX_PERMISSION_CHANGES {
+            let level = if i % 2 == 0 {
+                MemoryPermission::ReadWriteDerived
+            } else {
+                MemoryPermission::ReadOnly
+            };
+            manager.set_permission("audited", level).await.unwrap();
+        }
+        let changes = manager.permission_changes().await;
+        assert_eq!(changes.len(), MAX_PERMISSION_CHANGES);
+        assert_eq!(changes[0].from, MemoryPermission::ReadOnly);
+        assert_eq!(changes[0].to, MemoryPermission::ReadWriteDerived);
+        assert_eq!(written.lock().unwrap().len(), MAX_PERMISSION_CHANGES + 1);
+    }
+
+    #[tokio::test]
+    async fn test_reinstall_keeps_lowered_permission() {
+        let manager = PluginManager::new();
+        manager
+            .install_for_user(
+                "alice",
+                Arc::new(MockPlugin::new("sandboxed", MemoryPermission::ReadOnly)),
+                MemoryPermission::ReadWriteDerived,
+            )
+            .await
+            .unwrap();
+        manager
+            .set_permission("sandboxed", MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+
+        // The owner reinstalling does not lift the sandbox
+        manager
+            .install_for_user(
+                "alice",
+                Arc::new(MockPlugin::new("sandboxed", MemoryPermission::ReadOnly)),
+                MemoryPermission::ReadWriteDerived,
+            )
+            .await
+            .unwrap();
+        let isolated = manager.get_plugin("sandboxed").await.unwrap();
+        assert_eq!(isolated.permission(), MemoryPermission::ReadOnly);
+        assert_eq!(isolated.granted_permission(), MemoryPermission::ReadWriteDerived);
+
+        // It can still be restored explicitly
+        manager
+            .set_permission("sandboxed", MemoryPermission::ReadWriteDerived)
+            .await
+            .unwrap();
+        assert!(manager
+            .check_permission("sandboxed", MemoryPermission::ReadWriteDerived)
+            .await
+            .unwrap());
+    }
+
     #[tokio::test]
     async fn test_plugin_manager_list_plugins() {
         let manager = PluginManager::new();
@@ -1332,6 +2230,7 @@ mod tests {
             author: "Test".to_string(),
             executable: None,
             is_builtin: false,
+            timeout_ms: None,
         };
 
         manager.register_spec(spec).await.unwrap();
@@ -1340,6 +2239,215 @@ mod tests {
         assert_eq!(stats.registered_specs, 1);
     }
 
+    #[tokio::test]
+    async fn test_install_enforces_overall_limit() {
+        let manager = PluginManager::new().with_limits(PluginLimits {
+            max_plugins: 2,
+            ..Default::default()
+        });
+
+        for id in ["limit1", "limit2"] {
+            manager
+                .install(Arc::new(MockPlugin::new(id, MemoryPermission::ReadOnly)), MemoryPermission::ReadOnly)
+                .await
+                .unwrap();
+        }
+
+        match manager
+            .install(Arc::new(MockPlugin::new("limit3", MemoryPermission::ReadOnly)), MemoryPermission::ReadOnly)
+            .await
+        {
+            Err(DirSoulError::Plugin(message)) => {
+                assert!(message.contains("limit3"));
+                assert!(message.contains("2 of 2"));
+            }
+            other => panic!("expected limit error, got {:?}", other.map(|m| m.id)),
+        }
+        assert!(manager.get_plugin("limit3").await.is_err());
+
+        // Reinstalling an existing id replaces it
+        manager
+            .install(Arc::new(MockPlugin::new("limit1", MemoryPermission::ReadOnly)), MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+
+        let stats = manager.get_stats().await;
+        assert_eq!(stats.total_plugins, 2);
+        assert_eq!(stats.max_plugins, 2);
+    }
+
+    #[tokio::test]
+    async fn test_install_enforces_per_user_limit() {
+        let manager = PluginManager::new().with_limits(PluginLimits {
+            max_plugins: 10,
+            max_plugins_per_user: 2,
+        });
+
+        for id in ["alice1", "alice2"] {
+            manager
+                .install_for_user("alice", Arc::new(MockPlugin::new(id, MemoryPermission::ReadOnly)), MemoryPermission::ReadOnly)
+                .await
+                .unwrap();
+        }
+
+        let err = manager
+            .install_for_user("alice", Arc::new(MockPlugin::new("alice3", MemoryPermission::ReadOnly)), MemoryPermission::ReadOnly)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("per-user plugin limit reached (2 of 2 installed)"));
+
+        // Other users and system-wide installs are unaffected
+        manager
+            .install_for_user("bob", Arc::new(MockPlugin::new("bob1", MemoryPermission::ReadOnly)), MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+        manager
+            .install(Arc::new(MockPlugin::new("shared", MemoryPermission::ReadOnly)), MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+
+        let stats = manager.get_stats().await;
+        assert_eq!(stats.total_plugins, 4);
+        assert_eq!(stats.plugins_per_user.get("alice"), Some(&2));
+        assert_eq!(stats.plugins_per_user.get("bob"), Some(&1));
+        assert_eq!(stats.max_plugins_per_user, 2);
+        assert_eq!(manager.get_plugin("alice1").await.unwrap().owner(), Some("alice"));
+    }
+
+    #[tokio::test]
+    async fn test_only_the_owner_replaces_a_plugin() {
+        let manager = PluginManager::new().with_limits(PluginLimits {
+            max_plugins: 10,
+            max_plugins_per_user: 1,
+        });
+        let plugin = |id: &str| Arc::new(MockPlugin::new(id, MemoryPermission::ReadOnly));
+
+        manager
+            .install_for_user("alice", plugin("notes"), MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+        manager
+            .install_for_user("bob", plugin("bob1"), MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+
+        // Bob can neither take over Alice's id nor dodge his own limit with it
+        let err = manager
+            .install_for_user("bob", plugin("notes"), MemoryPermission::ReadOnly)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DirSoulError::PermissionDenied(_)), "{:?}", err);
+        let err = manager
+            .install(plugin("notes"), MemoryPermission::ReadOnly)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DirSoulError::PermissionDenied(_)), "{:?}", err);
+        assert_eq!(manager.get_plugin("notes").await.unwrap().owner(), Some("alice"));
+
+        // The owner may still replace it at their limit
+        manager
+            .install_for_user("alice", plugin("notes"), MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+        assert_eq!(manager.get_stats().await.plugins_per_user.get("alice"), Some(&1));
+    }
+
+    /// Plugin whose query handler takes `delay` to answer
+    fn slow_plugin(id: &str, delay: Duration) -> Arc<dyn UserPlugin> {
+        crate::plugin_builder::PluginBuilder::new(id, "Slow")
+            .on_query(move |query, _context| async move {
+                tokio::time::sleep(delay).await;
+                Ok(PluginResponse {
+                    content: query,
+                    sources: vec![],
+                    confidence: 1.0,
+                    metadata: serde_json::json!({}),
+                    timestamp: Utc::now(),
+                })
+            })
+            .on_event(move |_event, _context| async move {
+                tokio::time::sleep(delay).await;
+                Ok(PluginOutput::AnalysisComplete)
+            })
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_per_plugin_timeout_overrides() {
+        let manager = Arc::new(PluginManager::new());
+        let delay = Duration::from_millis(200);
+
+        manager
+            .install_with_timeout(slow_plugin("snappy", delay), MemoryPermission::ReadOnly, Duration::from_millis(20))
+            .await
+            .unwrap();
+        manager
+            .install_with_timeout(slow_plugin("deep", delay), MemoryPermission::ReadOnly, Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        let router = CommandRouter::new(manager.clone(), "user".to_string());
+        assert!(matches!(
+            router.route("@snappy analyse this").await,
+            Err(DirSoulError::PluginTimeout(_))
+        ));
+        match router.route("@deep analyse this").await.unwrap() {
+            CommandResponse::Plugin(response) => assert_eq!(response.content, "analyse this"),
+            other => panic!("unexpected response: {:?}", other),
+        }
+
+        // Event handlers use the same override
+        let context = PluginContext::new(
+            "snappy".to_string(),
+            "user".to_string(),
+            MemoryPermission::ReadOnly,
+            Arc::new(MockMemoryInterface),
+        );
+        let event = EventNotification {
+            event_id: Uuid::new_v4(),
+            user_id: "user".to_string(),
+            action: "eat".to_string(),
+            target: "apple".to_string(),
+            timestamp: Utc::now(),
+        };
+        let snappy = manager.get_plugin("snappy").await.unwrap();
+        assert_eq!(snappy.timeout(), Duration::from_millis(20));
+        assert!(snappy.on_event(&event, &context).await.is_err());
+        let deep = manager.get_plugin("deep").await.unwrap();
+        assert!(deep.on_event(&event, &context).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_plugin_timeout_from_spec_and_default() {
+        let manager = PluginManager::new();
+
+        let mut spec = PluginSpec::from_metadata(slow_plugin("specced", Duration::ZERO).metadata());
+        spec.timeout_ms = Some(1500);
+        manager.register_spec(spec).await.unwrap();
+
+        manager
+            .install(slow_plugin("specced", Duration::ZERO), MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+        manager
+            .install(slow_plugin("plain", Duration::ZERO), MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+
+        let specced = manager.get_plugin("specced").await.unwrap();
+        assert_eq!(specced.timeout(), Duration::from_millis(1500));
+        let plain = manager.get_plugin("plain").await.unwrap();
+        assert_eq!(plain.timeout(), PluginTimeoutConfig::default().default_timeout);
+
+        assert!(matches!(
+            manager
+                .install_with_timeout(slow_plugin("zero", Duration::ZERO), MemoryPermission::ReadOnly, Duration::ZERO)
+                .await,
+            Err(DirSoulError::Config(_))
+        ));
+    }
+
     // ========== CommandRouter Tests ==========
 
     #[test]
@@ -1420,6 +2528,79 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_route_rejects_empty_query() {
+        let manager = Arc::new(PluginManager::new());
+        let plugin = Arc::new(MockPlugin::new("test_plugin", MemoryPermission::ReadOnly));
+        manager.install(plugin, MemoryPermission::ReadOnly).await.unwrap();
+
+        let mut router = CommandRouter::new(manager, "user123".to_string());
+        router.set_default_plugin("test_plugin".to_string());
+
+        for input in ["", "   ", "\t\n \u{3000}"] {
+            match router.route(input).await.unwrap() {
+                CommandResponse::Error(msg) => assert_eq!(msg, "empty query"),
+                other => panic!("expected error for {:?}, got {:?}", input, other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_route_only_reaches_own_or_shared_plugins() {
+        let manager = Arc::new(PluginManager::new());
+        let plugin = |id: &str| Arc::new(MockPlugin::new(id, MemoryPermission::ReadOnly));
+        manager
+            .install_for_user("alice", plugin("notes"), MemoryPermission::ReadOnly)
+            .await
+            .unwrap();
+        manager.install(plugin("shared"), MemoryPermission::ReadOnly).await.unwrap();
+
+        let alice = CommandRouter::new(manager.clone(), "alice".to_string());
+        assert!(matches!(
+            alice.route("@notes hello").await.unwrap(),
+            CommandResponse::Plugin(_)
+        ));
+
+        let mut bob = CommandRouter::new(manager, "bob".to_string());
+        let err = bob.route("@notes hello").await.unwrap_err();
+        assert!(matches!(err, DirSoulError::PermissionDenied(_)), "{:?}", err);
+        // Naming it as the default plugin doesn't get around the check
+        bob.set_default_plugin("notes".to_string());
+        let err = bob.route("hello").await.unwrap_err();
+        assert!(matches!(err, DirSoulError::PermissionDenied(_)), "{:?}", err);
+        assert!(matches!(
+            bob.route("@shared hello").await.unwrap(),
+            CommandResponse::Plugin(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_route_rejects_plugin_call_without_query() {
+        let manager = PluginManager::new();
+        // No default plugin: reaching dispatch would be an Err, not a response
+        let router = CommandRouter::new(Arc::new(manager), "user123".to_string());
+
+        for input in ["@decision", "  @decision   "] {
+            match router.route(input).await.unwrap() {
+                CommandResponse::Error(msg) => assert_eq!(msg, "empty query for @decision"),
+                other => panic!("expected error for {:?}, got {:?}", input, other),
+            }
+        }
+
+        let whitespace_query = ParsedCommand::PluginCall {
+            plugin: "decision".to_string(),
+            query: " \u{3000} ".to_string(),
+        };
+        assert_eq!(
+            CommandRouter::empty_query_error(&whitespace_query),
+            Some("empty query for @decision".to_string())
+        );
+
+        // An email-like address is ordinary text, not a bare command
+        let email = ParsedCommand::DefaultQuery { query: "me@example.com".to_string() };
+        assert_eq!(CommandRouter::empty_query_error(&email), None);
+    }
+
     #[test]
     fn test_parsed_command_equality() {
         let cmd1 = ParsedCommand::PluginCall {
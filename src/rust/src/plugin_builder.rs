@@ -0,0 +1,360 @@
+//! Closure-based Plugin Builder
+//!
+//! Simple plugins don't need a full struct implementing `UserPlugin`:
+//! `PluginBuilder` assembles the metadata and wraps async closures into an
+//! `Arc<dyn UserPlugin>` ready for `PluginManager::install`.
+//!
+//! # Example
+//! ```no_run
+//! use dirsoul::plugin::{PluginManager, PluginOutput, PluginResponse};
+//! use dirsoul::plugin_builder::PluginBuilder;
+//! use dirsoul::MemoryPermission;
+//!
+//! # async fn example(manager: &PluginManager) -> dirsoul::Result<()> {
+//! let plugin = PluginBuilder::new("echo", "Echo")
+//!     .version("1.0.0")
+//!     .permission(MemoryPermission::ReadOnly)
+//!     .on_query(|query, _context| async move {
+//!         Ok(PluginResponse {
+//!             content: query,
+//!             sources: vec![],
+//!             confidence: 1.0,
+//!             metadata: serde_json::json!({}),
+//!             timestamp: chrono::Utc::now(),
+//!         })
+//!     })
+//!     .build()?;
+//!
+//! manager.install(plugin, MemoryPermission::ReadOnly).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use async_trait::async_trait;
+use futures_util::future::BoxFuture;
+use std::future::Future;
+use std::sync::Arc;
+
+use crate::actor_agent::EventNotification;
+use crate::agents::MemoryPermission;
+use crate::error::{DirSoulError, Result};
+use crate::plugin::{
+    EventSubscription, PluginContext, PluginMetadata, PluginOutput, PluginResponse, UserPlugin,
+};
+
+/// Version used when the builder is not given one
+pub const DEFAULT_PLUGIN_VERSION: &str = "0.1.0";
+
+type EventHandler = Arc<
+    dyn Fn(EventNotification, PluginContext) -> BoxFuture<'static, Result<PluginOutput>> + Send + Sync,
+>;
+type QueryHandler =
+    Arc<dyn Fn(String, PluginContext) -> BoxFuture<'static, Result<PluginResponse>> + Send + Sync>;
+
+/// Builder for closure-based plugins
+///
+/// Defaults: version `0.1.0`, `ReadOnly` permission, no cleanup work and an
+/// always-healthy health check. Without explicit subscriptions a plugin with
+/// an event handler subscribes to `EventSubscription::All`.
+pub struct PluginBuilder {
+    id: String,
+    name: String,
+    version: String,
+    description: String,
+    author: String,
+    permission: MemoryPermission,
+    subscriptions: Vec<EventSubscription>,
+    on_event: Option<EventHandler>,
+    on_query: Option<QueryHandler>,
+}
+
+impl PluginBuilder {
+    /// Start a plugin with the given id and display name
+    pub fn new(id: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            version: DEFAULT_PLUGIN_VERSION.to_string(),
+            description: String::new(),
+            author: String::new(),
+            permission: MemoryPermission::ReadOnly,
+            subscriptions: Vec::new(),
+            on_event: None,
+            on_query: None,
+        }
+    }
+
+    /// Set the version string
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Set the human-readable description
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Set the author
+    pub fn author(mut self, author: impl Into<String>) -> Self {
+        self.author = author.into();
+        self
+    }
+
+    /// Set the required permission level
+    pub fn permission(mut self, permission: MemoryPermission) -> Self {
+        self.permission = permission;
+        self
+    }
+
+    /// Add an event subscription
+    pub fn subscribe(mut self, subscription: EventSubscription) -> Self {
+        self.subscriptions.push(subscription);
+        self
+    }
+
+    /// Handle incoming events
+    pub fn on_event<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(EventNotification, PluginContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<PluginOutput>> + Send + 'static,
+    {
+        self.on_event = Some(Arc::new(
+            move |event: EventNotification, context: PluginContext| -> BoxFuture<'static, Result<PluginOutput>> {
+                Box::pin(handler(event, context))
+            },
+        ));
+        self
+    }
+
+    /// Handle user queries (`@id query`)
+    pub fn on_query<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(String, PluginContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<PluginResponse>> + Send + 'static,
+    {
+        self.on_query = Some(Arc::new(
+            move |query: String, context: PluginContext| -> BoxFuture<'static, Result<PluginResponse>> {
+                Box::pin(handler(query, context))
+            },
+        ));
+        self
+    }
+
+    /// Validate the fields and produce the plugin
+    ///
+    /// Returns `DirSoulError::Plugin` when the id is empty or not routable
+    /// (letters, digits and `_` only), the name or version is blank, or no
+    /// handler was given.
+    pub fn build(self) -> Result<Arc<dyn UserPlugin>> {
+        if self.id.is_empty() || !self.id.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(DirSoulError::Plugin(format!(
+                "Invalid plugin id '{}': use letters, digits and '_'",
+                self.id
+            )));
+        }
+        if self.name.trim().is_empty() {
+            return Err(DirSoulError::Plugin(format!("Plugin {} has no name", self.id)));
+        }
+        if self.version.trim().is_empty() {
+            return Err(DirSoulError::Plugin(format!("Plugin {} has no version", self.id)));
+        }
+        if self.on_event.is_none() && self.on_query.is_none() {
+            return Err(DirSoulError::Plugin(format!(
+                "Plugin {} needs an on_event or on_query handler",
+                self.id
+            )));
+        }
+
+        let subscriptions = if self.subscriptions.is_empty() && self.on_event.is_some() {
+            vec![EventSubscription::All]
+        } else {
+            self.subscriptions
+        };
+        let supported_events = subscriptions
+            .iter()
+            .filter_map(|subscription| match subscription {
+                EventSubscription::Action(action) => Some(action.clone()),
+                _ => None,
+            })
+            .collect();
+
+        Ok(Arc::new(ClosurePlugin {
+            metadata: PluginMetadata {
+                id: self.id,
+                name: self.name,
+                version: self.version,
+                description: self.description,
+                required_permission: self.permission,
+                author: self.author,
+                supported_events,
+                is_builtin: false,
+            },
+            subscriptions,
+            on_event: self.on_event,
+            on_query: self.on_query,
+        }))
+    }
+}
+
+/// Plugin produced by `PluginBuilder`
+struct ClosurePlugin {
+    metadata: PluginMetadata,
+    subscriptions: Vec<EventSubscription>,
+    on_event: Option<EventHandler>,
+    on_query: Option<QueryHandler>,
+}
+
+#[async_trait]
+impl UserPlugin for ClosurePlugin {
+    fn metadata(&self) -> &PluginMetadata {
+        &self.metadata
+    }
+
+    async fn on_event(
+        &self,
+        event: &EventNotification,
+        context: &PluginContext,
+    ) -> Result<PluginOutput> {
+        match &self.on_event {
+            Some(handler) => handler(event.clone(), context.clone()).await,
+            None => Ok(PluginOutput::AnalysisComplete),
+        }
+    }
+
+    async fn on_query(&self, query: &str, context: &PluginContext) -> Result<PluginResponse> {
+        match &self.on_query {
+            Some(handler) => handler(query.to_string(), context.clone()).await,
+            None => Err(DirSoulError::Plugin(format!(
+                "Plugin {} does not handle queries",
+                self.metadata.id
+            ))),
+        }
+    }
+
+    fn subscriptions(&self) -> &[EventSubscription] {
+        &self.subscriptions
+    }
+
+    async fn cleanup(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::{CommandResponse, CommandRouter, PluginManager};
+    use chrono::Utc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use uuid::Uuid;
+
+    fn echo_response(content: String) -> PluginResponse {
+        PluginResponse {
+            content,
+            sources: vec![],
+            confidence: 1.0,
+            metadata: serde_json::json!({}),
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_closure_plugin_installs_and_answers_queries() {
+        let plugin = PluginBuilder::new("echo", "Echo")
+            .version("1.2.0")
+            .author("Test")
+            .permission(MemoryPermission::ReadOnly)
+            .on_query(|query, context| async move {
+                Ok(echo_response(format!("{}: {}", context.plugin_id, query)))
+            })
+            .build()
+            .unwrap();
+
+        let manager = Arc::new(PluginManager::new());
+        let metadata = manager.install(plugin, MemoryPermission::ReadOnly).await.unwrap();
+        assert_eq!(metadata.id, "echo");
+        assert_eq!(metadata.version, "1.2.0");
+        assert!(!metadata.is_builtin);
+
+        let router = CommandRouter::new(manager.clone(), "user".to_string());
+        match router.route("@echo hello").await.unwrap() {
+            CommandResponse::Plugin(response) => assert_eq!(response.content, "echo: hello"),
+            other => panic!("unexpected response: {:?}", other),
+        }
+
+        let plugin = manager.get_plugin("echo").await.unwrap();
+        assert!(plugin.health_check().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_closure_plugin_event_handler_and_defaults() {
+        let seen = Arc::new(AtomicUsize::new(0));
+        let counter = seen.clone();
+        let plugin = PluginBuilder::new("counter", "Counter")
+            .on_event(move |event, _context| {
+                let counter = counter.clone();
+                async move {
+                    assert_eq!(event.action, "eat");
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Ok(PluginOutput::AnalysisComplete)
+                }
+            })
+            .build()
+            .unwrap();
+
+        assert_eq!(plugin.metadata().version, DEFAULT_PLUGIN_VERSION);
+        assert_eq!(plugin.metadata().required_permission, MemoryPermission::ReadOnly);
+        assert_eq!(plugin.subscriptions(), &[EventSubscription::All]);
+        assert!(plugin.cleanup().await.is_ok());
+
+        let context = PluginContext::new(
+            "counter".to_string(),
+            "user".to_string(),
+            MemoryPermission::ReadOnly,
+            Arc::new(crate::plugin::MockMemoryInterface),
+        );
+        let event = EventNotification {
+            event_id: Uuid::new_v4(),
+            user_id: "user".to_string(),
+            action: "eat".to_string(),
+            target: "apple".to_string(),
+            timestamp: Utc::now(),
+        };
+        plugin.on_event(&event, &context).await.unwrap();
+        plugin.on_event(&event, &context).await.unwrap();
+        assert_eq!(seen.load(Ordering::SeqCst), 2);
+
+        // No query handler registered
+        assert!(plugin.on_query("hi", &context).await.is_err());
+    }
+
+    #[test]
+    fn test_build_validates_required_fields() {
+        let handler = |query: String, _context: PluginContext| async move { Ok(echo_response(query)) };
+
+        assert!(PluginBuilder::new("ok", "Ok").on_query(handler).build().is_ok());
+        assert!(PluginBuilder::new("", "Empty").on_query(handler).build().is_err());
+        assert!(PluginBuilder::new("has space", "Bad").on_query(handler).build().is_err());
+        assert!(PluginBuilder::new("noname", " ").on_query(handler).build().is_err());
+        assert!(PluginBuilder::new("nover", "No Version").version("").on_query(handler).build().is_err());
+        assert!(matches!(
+            PluginBuilder::new("nohandler", "No Handler").build(),
+            Err(DirSoulError::Plugin(_))
+        ));
+    }
+
+    #[test]
+    fn test_action_subscriptions_fill_supported_events() {
+        let plugin = PluginBuilder::new("meals", "Meals")
+            .subscribe(EventSubscription::Action("eat".to_string()))
+            .subscribe(EventSubscription::TargetPattern("coffee".to_string()))
+            .on_event(|_event, _context| async { Ok(PluginOutput::AnalysisComplete) })
+            .build()
+            .unwrap();
+
+        assert_eq!(plugin.metadata().supported_events, vec!["eat".to_string()]);
+        assert_eq!(plugin.subscriptions().len(), 2);
+    }
+}
@@ -7,6 +7,8 @@
 //! - **Prompt Externalization**: Avoid hardcoding prompts in source code
 //! - **User Customization**: Users can modify prompts without recompiling
 //! - **Template Variables**: Support for variable substitution ({{variable}})
+//! - **System Prompts**: A deployment-wide system prompt, overridable per task,
+//!   is prepended to every LLM request of that task (see [`SystemPrompts`])
 //!
 //! # Example
 //! ```no_run
@@ -20,7 +22,9 @@
 //! # Ok::<(), dirsoul::DirSoulError>(())
 //! ```
 
+use crate::llm_provider::ChatMessage;
 use crate::Result;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -28,6 +32,73 @@ use std::path::{Path, PathBuf};
 /// Default prompts directory
 const DEFAULT_PROMPTS_DIR: &str = "prompts";
 
+/// LLM task a system prompt applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptTask {
+    /// Event extraction (`SlmExtractor`)
+    EventExtraction,
+    /// Relation extraction (`EntityRelationExtractor`)
+    RelationExtraction,
+    /// Conversational replies (`/api/chat`, DeepTalk)
+    Chat,
+}
+
+/// System prompts prepended to LLM requests
+///
+/// `base` applies to every task; an entry in `overrides` replaces it for
+/// one task. A blank prompt (including an empty override) sends no system
+/// prompt for that task.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SystemPrompts {
+    /// Prompt for tasks without an override
+    pub base: String,
+    /// Per-task replacements for `base`
+    pub overrides: HashMap<PromptTask, String>,
+}
+
+impl SystemPrompts {
+    /// Use `base` for every task
+    pub fn new(base: impl Into<String>) -> Self {
+        Self {
+            base: base.into(),
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Replace the base prompt for `task`; an empty prompt disables it
+    pub fn with_override(mut self, task: PromptTask, prompt: impl Into<String>) -> Self {
+        self.overrides.insert(task, prompt.into());
+        self
+    }
+
+    /// The system prompt for `task`, if it is not blank
+    pub fn for_task(&self, task: PromptTask) -> Option<&str> {
+        let prompt = self.overrides.get(&task).unwrap_or(&self.base);
+        Some(prompt.as_str()).filter(|p| !p.trim().is_empty())
+    }
+
+    /// Chat messages for `task`: the system prompt (if any), then `content`
+    pub fn compose_messages(&self, task: PromptTask, content: &str) -> Vec<ChatMessage> {
+        self.for_task(task)
+            .map(ChatMessage::system)
+            .into_iter()
+            .chain(std::iter::once(ChatMessage::user(content)))
+            .collect()
+    }
+
+    /// `prompt` with the system prompt for `task` prepended
+    ///
+    /// For raw-completion requests, which have no separate system role.
+    pub fn compose_prompt(&self, task: PromptTask, prompt: &str) -> String {
+        match self.for_task(task) {
+            Some(system) => format!("{}\n\n{}", system.trim_end(), prompt),
+            None => prompt.to_string(),
+        }
+    }
+}
+
 /// Prompt Manager - loads and renders prompt templates from files
 ///
 /// Templates use double-brace syntax for variables: `{{variable_name}}`
@@ -35,6 +106,8 @@ pub struct PromptManager {
     prompts_dir: PathBuf,
     /// Cache for loaded prompts to avoid repeated disk I/O
     cache: HashMap<String, String>,
+    /// System prompts prepended per task
+    system_prompts: SystemPrompts,
 }
 
 impl PromptManager {
@@ -57,9 +130,31 @@ impl PromptManager {
         Ok(Self {
             prompts_dir,
             cache: HashMap::new(),
+            system_prompts: SystemPrompts::default(),
         })
     }
 
+    /// Set the system prompts prepended per task
+    pub fn with_system_prompts(mut self, system_prompts: SystemPrompts) -> Self {
+        self.system_prompts = system_prompts;
+        self
+    }
+
+    /// Get the system prompts prepended per task
+    pub fn system_prompts(&self) -> &SystemPrompts {
+        &self.system_prompts
+    }
+
+    /// Chat messages for `task` with its system prompt prepended
+    pub fn compose_messages(&self, task: PromptTask, content: &str) -> Vec<ChatMessage> {
+        self.system_prompts.compose_messages(task, content)
+    }
+
+    /// Raw prompt for `task` with its system prompt prepended
+    pub fn compose_prompt(&self, task: PromptTask, prompt: &str) -> String {
+        self.system_prompts.compose_prompt(task, prompt)
+    }
+
     /// Load a prompt template by name
     ///
     /// This loads the template from `{prompts_dir}/{name}.txt`
@@ -433,4 +528,45 @@ mod tests {
         let manager = PromptManager::default();
         assert_eq!(manager.prompts_dir(), Path::new(DEFAULT_PROMPTS_DIR));
     }
+
+    #[test]
+    fn test_system_prompt_in_composed_messages() {
+        let temp_dir = setup_test_prompts();
+        let manager = PromptManager::with_dir(temp_dir.path().join("prompts"))
+            .unwrap()
+            .with_system_prompts(
+                SystemPrompts::new("You assist a nutrition app.")
+                    .with_override(PromptTask::Chat, "Answer briefly."),
+            );
+
+        let messages = manager.compose_messages(PromptTask::EventExtraction, "今天吃了3个苹果");
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, "system");
+        assert_eq!(messages[0].content, "You assist a nutrition app.");
+        assert_eq!(messages[1].role, "user");
+        assert_eq!(messages[1].content, "今天吃了3个苹果");
+
+        let messages = manager.compose_messages(PromptTask::Chat, "你好");
+        assert_eq!(messages[0].content, "Answer briefly.");
+
+        assert_eq!(
+            manager.compose_prompt(PromptTask::RelationExtraction, "Text: 苹果"),
+            "You assist a nutrition app.\n\nText: 苹果"
+        );
+    }
+
+    #[test]
+    fn test_system_prompt_overridable_to_empty() {
+        let prompts = SystemPrompts::new("Domain instructions")
+            .with_override(PromptTask::RelationExtraction, "");
+        assert_eq!(prompts.for_task(PromptTask::RelationExtraction), None);
+        assert_eq!(prompts.compose_prompt(PromptTask::RelationExtraction, "Text"), "Text");
+
+        let messages = prompts.compose_messages(PromptTask::RelationExtraction, "Text");
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].role, "user");
+
+        // No system prompt is configured by default
+        assert_eq!(SystemPrompts::default().for_task(PromptTask::Chat), None);
+    }
 }
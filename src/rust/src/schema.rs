@@ -66,6 +66,16 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    emotional_trends (user_id, day) {
+        user_id -> Text,
+        day -> Date,
+        score -> Float8,
+        sample_count -> Int4,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     entities (entity_id) {
         entity_id -> Uuid,
@@ -91,6 +101,17 @@ diesel::table! {
         first_seen -> Timestamptz,
         last_seen -> Timestamptz,
         strength -> Float8,
+        observation_count -> Int4,
+        co_occurrence -> Float8,
+    }
+}
+
+diesel::table! {
+    event_entity_links (event_id, entity_id) {
+        event_id -> Uuid,
+        entity_id -> Uuid,
+        user_id -> Text,
+        created_at -> Timestamptz,
     }
 }
 
@@ -107,6 +128,21 @@ diesel::table! {
         unit -> Nullable<Text>,
         confidence -> Float8,
         extractor_version -> Nullable<Text>,
+        metadata -> Nullable<Jsonb>,
+    }
+}
+
+diesel::table! {
+    extraction_failures (failure_id) {
+        failure_id -> Uuid,
+        memory_id -> Uuid,
+        user_id -> Text,
+        attempts -> Int4,
+        last_error -> Text,
+        status -> Text,
+        next_retry_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
@@ -123,6 +159,8 @@ diesel::table! {
         encrypted -> Nullable<Bytea>,
         metadata -> Nullable<Jsonb>,
         embedding -> Nullable<Vector>,
+        deleted_at -> Nullable<Timestamptz>,
+        embedding_model -> Nullable<Text>,
     }
 }
 
@@ -152,16 +190,37 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    user_settings (user_id) {
+        user_id -> Text,
+        timezone -> Nullable<Text>,
+        locale -> Nullable<Text>,
+        default_plugin -> Nullable<Text>,
+        promotion_min_confidence -> Nullable<Float8>,
+        promotion_min_validation_count -> Nullable<Int4>,
+        promotion_min_time_span_days -> Nullable<Int8>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::joinable!(cognitive_views -> stable_concepts (promoted_to));
+diesel::joinable!(event_entity_links -> entities (entity_id));
+diesel::joinable!(event_entity_links -> event_memories (event_id));
 diesel::joinable!(event_memories -> raw_memories (memory_id));
+diesel::joinable!(extraction_failures -> raw_memories (memory_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     agents,
     audit_logs,
     cognitive_views,
+    emotional_trends,
     entities,
     entity_relations,
+    event_entity_links,
     event_memories,
+    extraction_failures,
     raw_memories,
     stable_concepts,
+    user_settings,
 );
@@ -24,10 +24,13 @@ use tempfile::TempDir;
 
 use crate::agents::AgentPermissions;
 use crate::audit::{AuditLog, AuditLogger};
-use crate::crypto::EncryptionManager;
+use crate::crypto::{token_nonce, EncryptionManager};
 use crate::error::{DirSoulError, Result};
 use crate::schema::audit_logs;
 
+/// Messages encrypted by the nonce uniqueness test
+const NONCE_AUDIT_MESSAGES: usize = 2000;
+
 /// Security test result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityTestResult {
@@ -179,6 +182,7 @@ impl SecurityTestSuite {
         // Additional security tests
         results.push(self.test_encryption_key_uniqueness()?);
         results.push(self.test_data_integrity_checksum()?);
+        results.push(self.test_nonce_uniqueness()?);
 
         let total_duration_ms = (Utc::now() - start_time).num_milliseconds() as u64;
         let total_tests = results.len();
@@ -594,6 +598,40 @@ impl SecurityTestSuite {
             ))
         }
     }
+
+    /// Test 13: Every token gets a fresh IV that decryption reads back
+    fn test_nonce_uniqueness(&self) -> Result<SecurityTestResult> {
+        let start = std::time::Instant::now();
+        let test_name = "nonce_uniqueness".to_string();
+
+        let mut seen = std::collections::HashSet::with_capacity(NONCE_AUDIT_MESSAGES);
+        let mut failure = None;
+        for i in 0..NONCE_AUDIT_MESSAGES {
+            // Identical plaintexts must still get distinct IVs
+            let plaintext = b"Same message every time";
+            let encrypted = self.encryption.encrypt(plaintext)?;
+
+            if !seen.insert(token_nonce(&encrypted)?) {
+                failure = Some(format!("IV repeated at message {}", i));
+                break;
+            }
+            if self.encryption.decrypt(&encrypted)? != plaintext {
+                failure = Some(format!("Message {} didn't decrypt to its plaintext", i));
+                break;
+            }
+        }
+
+        let duration_ms = start.elapsed().as_millis() as u64;
+        let mut result = match failure {
+            None => SecurityTestResult::success(test_name, duration_ms),
+            Some(error) => SecurityTestResult::failure(test_name, duration_ms, error),
+        };
+        result.metadata = Some(serde_json::json!({
+            "messages": NONCE_AUDIT_MESSAGES,
+            "unique_nonces": seen.len(),
+        }));
+        Ok(result)
+    }
 }
 
 /// Security benchmark results
@@ -671,6 +709,17 @@ mod tests {
         assert_eq!(results.pass_rate(), 66.66666666666666);
     }
 
+    #[test]
+    fn test_nonce_uniqueness_case_passes() {
+        // The nonce case needs no database
+        let suite = SecurityTestSuite::new("postgresql://localhost/unused".to_string()).unwrap();
+        let result = suite.test_nonce_uniqueness().unwrap();
+
+        assert!(result.passed, "{:?}", result.error_message);
+        let metadata = result.metadata.unwrap();
+        assert_eq!(metadata["unique_nonces"], NONCE_AUDIT_MESSAGES);
+    }
+
     #[test]
     fn test_security_test_suite_results_summary() {
         let results = SecurityTestSuiteResults {
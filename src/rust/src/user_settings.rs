@@ -0,0 +1,285 @@
+//! Per-User Settings
+//!
+//! Stores the preferences a user may override: time zone, locale, default
+//! plugin and the Promotion Gate thresholds. Every field is optional; an
+//! unset field (or a user without a row) falls back to the global config.
+//!
+//! # Consumers
+//! - Timeline grouping: `timezone` when the request names no zone
+//! - Promotion candidates: `promotion_*` on top of the server's gate
+//! - `/api/command`: `default_plugin` for input without an `@plugin`
+//! - Pattern detection: `locale` picks the language of descriptions
+//!
+//! # Example
+//! ```ignore
+//! let mut settings = UserSettings::get(&mut conn, "user123")?;
+//! settings.timezone = Some("Asia/Shanghai".to_string());
+//! UserSettings::update(&mut conn, &settings)?;
+//! ```
+
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::cognitive::PromotionGateConfig;
+use crate::data_changes::notify_user_data_changed;
+use crate::error::{DirSoulError, Result};
+use crate::http_api::TimelineZone;
+use crate::pattern_detector::{DayNameLocale, PatternDetectorConfig};
+use crate::schema::user_settings;
+
+/// A user's overrides of the global configuration
+///
+/// `None` means "use the global value". Saving replaces every field, so an
+/// omitted field clears its override.
+#[derive(
+    Debug, Clone, Default, PartialEq, Queryable, Selectable, Insertable, AsChangeset, Serialize,
+    Deserialize,
+)]
+#[diesel(table_name = user_settings, primary_key(user_id), treat_none_as_null = true)]
+pub struct UserSettings {
+    /// Owner of the settings
+    pub user_id: String,
+
+    /// Time zone for timeline grouping: an IANA name or a UTC offset
+    #[serde(default)]
+    pub timezone: Option<String>,
+
+    /// Language tag such as "zh-CN" or "en"
+    #[serde(default)]
+    pub locale: Option<String>,
+
+    /// Plugin answering `/api/command` input without an `@plugin` command
+    #[serde(default)]
+    pub default_plugin: Option<String>,
+
+    /// Promotion Gate `min_confidence` override
+    #[serde(default)]
+    pub promotion_min_confidence: Option<f64>,
+
+    /// Promotion Gate `min_validation_count` override
+    #[serde(default)]
+    pub promotion_min_validation_count: Option<i32>,
+
+    /// Promotion Gate `min_time_span_days` override
+    #[serde(default)]
+    pub promotion_min_time_span_days: Option<i64>,
+}
+
+impl UserSettings {
+    /// Settings without any override
+    pub fn new(user_id: impl Into<String>) -> Self {
+        Self {
+            user_id: user_id.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Load a user's settings; a user without a row gets no overrides
+    pub fn get(conn: &mut PgConnection, user_id: &str) -> Result<Self> {
+        let stored = user_settings::table
+            .find(user_id)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()?;
+        Ok(stored.unwrap_or_else(|| Self::new(user_id)))
+    }
+
+    /// Validate and save the settings, replacing any stored overrides
+    ///
+    /// Cached timelines were grouped in the old time zone, so the change is
+    /// announced through `notify_user_data_changed`.
+    pub fn update(conn: &mut PgConnection, settings: &Self) -> Result<Self> {
+        settings.validate()?;
+
+        let saved = diesel::insert_into(user_settings::table)
+            .values(settings)
+            .on_conflict(user_settings::user_id)
+            .do_update()
+            .set((settings, user_settings::updated_at.eq(diesel::dsl::now)))
+            .returning(Self::as_returning())
+            .get_result(conn)?;
+        notify_user_data_changed(&settings.user_id);
+        Ok(saved)
+    }
+
+    /// Reject values the consumers could not use
+    pub fn validate(&self) -> Result<()> {
+        if self.user_id.trim().is_empty() {
+            return Err(DirSoulError::Config("user_id must not be empty".to_string()));
+        }
+        if let Some(timezone) = &self.timezone {
+            TimelineZone::parse(Some(timezone))?;
+        }
+        if let Some(locale) = &self.locale {
+            let valid = !locale.is_empty()
+                && locale.len() <= 35
+                && locale.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(DirSoulError::Config(format!("Invalid locale: {:?}", locale)));
+            }
+        }
+        if matches!(&self.default_plugin, Some(plugin) if plugin.trim().is_empty()) {
+            return Err(DirSoulError::Config("default_plugin must not be blank".to_string()));
+        }
+        if let Some(confidence) = self.promotion_min_confidence {
+            if !(0.0..=1.0).contains(&confidence) {
+                return Err(DirSoulError::Config(format!(
+                    "promotion_min_confidence must be between 0 and 1, got {}",
+                    confidence
+                )));
+            }
+        }
+        if matches!(self.promotion_min_validation_count, Some(count) if count < 0) {
+            return Err(DirSoulError::Config(
+                "promotion_min_validation_count must not be negative".to_string(),
+            ));
+        }
+        if matches!(self.promotion_min_time_span_days, Some(days) if days < 0) {
+            return Err(DirSoulError::Config(
+                "promotion_min_time_span_days must not be negative".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Zone for timeline grouping
+    ///
+    /// A zone named by the request wins, then the user's zone, then UTC.
+    pub fn timeline_zone(&self, requested: Option<&str>) -> Result<TimelineZone> {
+        match requested.map(str::trim) {
+            Some(requested) if !requested.is_empty() => TimelineZone::parse(Some(requested)),
+            _ => TimelineZone::parse(self.timezone.as_deref()),
+        }
+    }
+
+    /// `base` with the user's Promotion Gate overrides applied
+    pub fn promotion_gate(&self, base: &PromotionGateConfig) -> PromotionGateConfig {
+        PromotionGateConfig {
+            min_confidence: self.promotion_min_confidence.unwrap_or(base.min_confidence),
+            min_validation_count: self
+                .promotion_min_validation_count
+                .unwrap_or(base.min_validation_count),
+            min_time_span_days: self
+                .promotion_min_time_span_days
+                .unwrap_or(base.min_time_span_days),
+            ..base.clone()
+        }
+    }
+
+    /// The user's default plugin, else `fallback`
+    pub fn default_plugin_or(&self, fallback: Option<String>) -> Option<String> {
+        self.default_plugin.clone().or(fallback)
+    }
+
+    /// Language of pattern descriptions implied by the locale
+    ///
+    /// "zh", "zh-CN", "zh_TW" map to Chinese; any other locale to English.
+    pub fn day_name_locale(&self) -> Option<DayNameLocale> {
+        let locale = self.locale.as_deref()?.to_ascii_lowercase();
+        if locale == "zh" || locale.starts_with("zh-") || locale.starts_with("zh_") {
+            Some(DayNameLocale::Chinese)
+        } else {
+            Some(DayNameLocale::English)
+        }
+    }
+
+    /// `base` with the user's locale applied
+    pub fn pattern_config(&self, base: &PatternDetectorConfig) -> PatternDetectorConfig {
+        let mut config = base.clone();
+        if let Some(locale) = self.day_name_locale() {
+            config.day_name_locale = locale;
+        }
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_fall_back_to_global_config() {
+        let settings = UserSettings::new("user");
+
+        assert_eq!(settings.timeline_zone(None).unwrap(), TimelineZone::default());
+        let gate = PromotionGateConfig::default();
+        assert_eq!(settings.promotion_gate(&gate), gate);
+        let fallback = Some("deeptalk".to_string());
+        assert_eq!(settings.default_plugin_or(fallback).as_deref(), Some("deeptalk"));
+        assert_eq!(settings.day_name_locale(), None);
+        let mut config = PatternDetectorConfig::default();
+        config.day_name_locale = DayNameLocale::Chinese;
+        assert_eq!(settings.pattern_config(&config).day_name_locale, DayNameLocale::Chinese);
+    }
+
+    #[test]
+    fn test_overrides_replace_global_values() {
+        let settings = UserSettings {
+            timezone: Some("+08:00".to_string()),
+            locale: Some("zh-CN".to_string()),
+            default_plugin: Some("decision".to_string()),
+            promotion_min_confidence: Some(0.7),
+            promotion_min_time_span_days: Some(7),
+            ..UserSettings::new("user")
+        };
+
+        let plus_eight = TimelineZone::Offset(chrono::FixedOffset::east_opt(8 * 3600).unwrap());
+        assert_eq!(settings.timeline_zone(None).unwrap(), plus_eight);
+        assert_eq!(settings.timeline_zone(Some(" ")).unwrap(), plus_eight);
+        // A zone named by the request wins over the stored one
+        assert_eq!(settings.timeline_zone(Some("UTC")).unwrap(), TimelineZone::default());
+
+        let gate = settings.promotion_gate(&PromotionGateConfig::default());
+        assert_eq!(gate.min_confidence, 0.7);
+        assert_eq!(gate.min_time_span_days, 7);
+        assert_eq!(
+            gate.min_validation_count,
+            PromotionGateConfig::default().min_validation_count
+        );
+
+        let fallback = Some("deeptalk".to_string());
+        assert_eq!(settings.default_plugin_or(fallback).as_deref(), Some("decision"));
+        let config = settings.pattern_config(&PatternDetectorConfig::default());
+        assert_eq!(config.day_name_locale, DayNameLocale::Chinese);
+    }
+
+    #[test]
+    fn test_day_name_locale() {
+        let with_locale = |locale: &str| UserSettings {
+            locale: Some(locale.to_string()),
+            ..UserSettings::new("user")
+        };
+        assert_eq!(with_locale("zh").day_name_locale(), Some(DayNameLocale::Chinese));
+        assert_eq!(with_locale("ZH_tw").day_name_locale(), Some(DayNameLocale::Chinese));
+        assert_eq!(with_locale("en-US").day_name_locale(), Some(DayNameLocale::English));
+        assert_eq!(with_locale("zhx").day_name_locale(), Some(DayNameLocale::English));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(UserSettings::new("user").validate().is_ok());
+        assert!(UserSettings::new(" ").validate().is_err());
+
+        let invalid = [
+            UserSettings { timezone: Some("Mars/Base".to_string()), ..UserSettings::new("user") },
+            UserSettings { locale: Some("zh CN".to_string()), ..UserSettings::new("user") },
+            UserSettings { default_plugin: Some(" ".to_string()), ..UserSettings::new("user") },
+            UserSettings { promotion_min_confidence: Some(1.5), ..UserSettings::new("user") },
+            UserSettings { promotion_min_validation_count: Some(-1), ..UserSettings::new("user") },
+            UserSettings { promotion_min_time_span_days: Some(-1), ..UserSettings::new("user") },
+        ];
+        for settings in invalid {
+            let err = settings.validate().unwrap_err();
+            assert!(matches!(err, DirSoulError::Config(_)), "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn test_omitted_fields_deserialize_as_unset() {
+        let settings: UserSettings =
+            serde_json::from_str(r#"{"user_id": "user", "timezone": "Asia/Shanghai"}"#).unwrap();
+        assert_eq!(settings.timezone.as_deref(), Some("Asia/Shanghai"));
+        assert_eq!(settings.locale, None);
+        assert_eq!(settings.promotion_min_confidence, None);
+    }
+}
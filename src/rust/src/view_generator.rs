@@ -8,13 +8,169 @@
 //! - **慢抽象原则**: Generate Derived Views first (discardable)
 //! - **Promotion Gate 把关**: Views must pass validation before becoming concepts
 //! - **避免 LLM 幻觉放大**: Isolate AI judgments from system structure
-
-use crate::cognitive::{NewCognitiveView, ViewStatus};
+//!
+//! # Deduplication
+//! Batch generation drops views whose hypothesis is similar to one already
+//! kept for the same view type. Similarity comes from the `SimilarityFn` in
+//! `ViewGeneratorConfig`: string-based by default (fast, exact wording
+//! matters), or embedding-based for better recall on paraphrases.
+//! `drop_existing` applies the same test against stored views, where a view
+//! generated for the same action/target is a duplicate regardless of wording.
+
+use crate::cognitive::{CognitiveView, NewCognitiveView, ViewDefaults, ViewStatus, ViewType};
+use crate::embedding::EmbeddingGenerator;
 use crate::error::Result;
 use crate::pattern_detector::{DetectedPattern, PatternMetadata, PatternType};
-use chrono::{Duration, Utc};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Arc;
 use uuid::Uuid;
 
+/// Similarity of two hypotheses, 0.0 (unrelated) to 1.0 (same)
+#[derive(Clone)]
+pub struct SimilarityFn {
+    name: String,
+    score: Arc<dyn Fn(&str, &str) -> f64 + Send + Sync>,
+}
+
+impl SimilarityFn {
+    /// Wrap a custom similarity function
+    pub fn new<F>(name: impl Into<String>, score: F) -> Self
+    where
+        F: Fn(&str, &str) -> f64 + Send + Sync + 'static,
+    {
+        Self {
+            name: name.into(),
+            score: Arc::new(score),
+        }
+    }
+
+    /// Dice coefficient over character bigrams of the normalized text
+    ///
+    /// Case and whitespace are ignored; works for Chinese text without
+    /// word segmentation.
+    pub fn text() -> Self {
+        Self::new("text", text_similarity)
+    }
+
+    /// Cosine similarity of hypothesis embeddings
+    ///
+    /// `lookup` returns a precomputed embedding (e.g. from
+    /// `EmbeddingGenerator::generate_batch`); pairs missing an embedding fall
+    /// back to `text` similarity.
+    pub fn embedding<F>(lookup: F) -> Self
+    where
+        F: Fn(&str) -> Option<Vec<f32>> + Send + Sync + 'static,
+    {
+        Self::new("embedding", move |a, b| match (lookup(a), lookup(b)) {
+            (Some(ea), Some(eb)) => EmbeddingGenerator::cosine_similarity(&ea, &eb) as f64,
+            _ => text_similarity(a, b),
+        })
+    }
+
+    /// Name used in debug output
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Score two hypotheses
+    pub fn score(&self, a: &str, b: &str) -> f64 {
+        (self.score)(a, b)
+    }
+}
+
+impl Default for SimilarityFn {
+    fn default() -> Self {
+        Self::text()
+    }
+}
+
+impl std::fmt::Debug for SimilarityFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SimilarityFn").field(&self.name).finish()
+    }
+}
+
+/// View metadata naming the action/target a pattern was detected for
+fn pattern_subject(pattern: &DetectedPattern) -> serde_json::Value {
+    serde_json::json!({ "action": pattern.action, "target": pattern.target })
+}
+
+/// Both views were generated for the same action/target
+fn same_subject(a: &Option<serde_json::Value>, b: &Option<serde_json::Value>) -> bool {
+    let subject = |metadata: &Option<serde_json::Value>| {
+        let metadata = metadata.as_ref()?;
+        Some((metadata.get("action")?.as_str()?, metadata.get("target")?.as_str()?))
+    };
+    matches!((subject(a), subject(b)), (Some(a), Some(b)) if a == b)
+}
+
+/// Character-bigram Dice coefficient of lowercased, whitespace-free text
+fn text_similarity(a: &str, b: &str) -> f64 {
+    fn bigrams(text: &str) -> Vec<(char, char)> {
+        let chars: Vec<char> = text
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_lowercase)
+            .collect();
+        if chars.len() == 1 {
+            return vec![(chars[0], '\0')];
+        }
+        chars.windows(2).map(|w| (w[0], w[1])).collect()
+    }
+
+    let (a, b) = (bigrams(a), bigrams(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let mut remaining: HashMap<(char, char), usize> = HashMap::new();
+    for gram in &b {
+        *remaining.entry(*gram).or_insert(0) += 1;
+    }
+    let mut shared = 0;
+    for gram in &a {
+        if let Some(count) = remaining.get_mut(gram) {
+            if *count > 0 {
+                *count -= 1;
+                shared += 1;
+            }
+        }
+    }
+    2.0 * shared as f64 / (a.len() + b.len()) as f64
+}
+
+/// Similarities computed during one batch, keyed by unordered hypothesis pair
+struct SimilarityCache<'a> {
+    similarity: &'a SimilarityFn,
+    scores: HashMap<(String, String), f64>,
+}
+
+impl<'a> SimilarityCache<'a> {
+    fn new(similarity: &'a SimilarityFn) -> Self {
+        Self {
+            similarity,
+            scores: HashMap::new(),
+        }
+    }
+
+    fn score(&mut self, a: &str, b: &str) -> f64 {
+        let key = if a <= b {
+            (a.to_string(), b.to_string())
+        } else {
+            (b.to_string(), a.to_string())
+        };
+        let similarity = self.similarity;
+        *self
+            .scores
+            .entry(key)
+            .or_insert_with(|| similarity.score(a, b))
+    }
+}
+
 /// Configuration for view generation
 #[derive(Debug, Clone)]
 pub struct ViewGeneratorConfig {
@@ -30,6 +186,10 @@ pub struct ViewGeneratorConfig {
     pub temporal_confidence_multiplier: f64,
     /// Minimum confidence threshold for view creation
     pub min_confidence_threshold: f64,
+    /// How hypothesis similarity is measured for deduplication
+    pub similarity: SimilarityFn,
+    /// Views of the same type at or above this similarity are duplicates
+    pub dedup_similarity_threshold: f64,
 }
 
 impl Default for ViewGeneratorConfig {
@@ -41,6 +201,8 @@ impl Default for ViewGeneratorConfig {
             anomaly_confidence_multiplier: 0.8,  // Anomalies are less certain
             temporal_confidence_multiplier: 1.1,  // Temporal patterns are reliable
             min_confidence_threshold: 0.5,
+            similarity: SimilarityFn::text(),
+            dedup_similarity_threshold: 0.95,
         }
     }
 }
@@ -93,23 +255,19 @@ impl ViewGenerator {
         // Extract event IDs from pattern evidence
         let derived_from = self.extract_event_ids(pattern);
 
-        // Calculate expiration time
-        let expires_at = self.calculate_expiration(pattern);
-
         // Determine view type
         let view_type = self.determine_view_type(pattern);
 
-        // Create the view
-        let view = NewCognitiveView::new(
+        // Create the view, seeded from the pattern's strength
+        let mut view = NewCognitiveView::new_with_defaults(
             user_id.to_string(),
             pattern.description.clone(),
             view_type,
             derived_from,
+            &self.view_defaults(pattern),
         )
-        .with_confidence(confidence)
-        .with_expiration(expires_at)
-        .with_description(&pattern.description)
-        .with_source("pattern_detector");
+        .with_description(&pattern.description);
+        view.metadata = Some(pattern_subject(pattern));
 
         Ok(view)
     }
@@ -139,7 +297,62 @@ impl ViewGenerator {
             }
         }
 
-        Ok(views)
+        Ok(self.dedup_views(views))
+    }
+
+    /// Drop views whose hypothesis duplicates another view of the same type
+    ///
+    /// Two views are duplicates when `config.similarity` scores their
+    /// hypotheses at or above `dedup_similarity_threshold`; the one with the
+    /// higher confidence is kept, in the position of the first. Scores are
+    /// cached for the duration of the call.
+    pub fn dedup_views(&self, views: Vec<NewCognitiveView>) -> Vec<NewCognitiveView> {
+        let mut cache = SimilarityCache::new(&self.config.similarity);
+        let mut kept: Vec<NewCognitiveView> = Vec::with_capacity(views.len());
+
+        for view in views {
+            let duplicate = kept.iter().position(|existing| {
+                existing.get_view_type() == view.get_view_type()
+                    && cache.score(&existing.hypothesis, &view.hypothesis)
+                        >= self.config.dedup_similarity_threshold
+            });
+
+            match duplicate {
+                Some(index) => {
+                    if view.confidence > kept[index].confidence {
+                        kept[index] = view;
+                    }
+                }
+                None => kept.push(view),
+            }
+        }
+
+        kept
+    }
+
+    /// Drop views that duplicate a stored view of the same type
+    ///
+    /// A view duplicates a stored one when both were generated for the same
+    /// action/target, or when `config.similarity` scores their hypotheses at
+    /// or above `dedup_similarity_threshold`.
+    pub fn drop_existing(
+        &self,
+        views: Vec<NewCognitiveView>,
+        existing: &[CognitiveView],
+    ) -> Vec<NewCognitiveView> {
+        let mut cache = SimilarityCache::new(&self.config.similarity);
+
+        views
+            .into_iter()
+            .filter(|view| {
+                !existing.iter().any(|stored| {
+                    stored.view_type == view.view_type
+                        && (same_subject(&stored.metadata, &view.metadata)
+                            || cache.score(&stored.hypothesis, &view.hypothesis)
+                                >= self.config.dedup_similarity_threshold)
+                })
+            })
+            .collect()
     }
 
     /// Calculate confidence based on pattern type and metadata
@@ -193,8 +406,8 @@ impl ViewGenerator {
             .collect()
     }
 
-    /// Calculate expiration time based on pattern characteristics
-    fn calculate_expiration(&self, pattern: &DetectedPattern) -> chrono::DateTime<Utc> {
+    /// Calculate lifetime in days based on pattern characteristics
+    fn calculate_expiration_days(&self, pattern: &DetectedPattern) -> i64 {
         let base_days = self.config.default_expiration_days;
 
         // Adjust expiration based on confidence
@@ -203,18 +416,25 @@ impl ViewGenerator {
         let adjusted_days = (base_days as f64 * confidence_multiplier) as i64;
 
         // Range: [15 days, 60 days]
-        let clamped_days = adjusted_days.max(15).min(60);
+        adjusted_days.max(15).min(60)
+    }
 
-        Utc::now() + Duration::days(clamped_days)
+    /// View defaults for a pattern: confidence from pattern strength,
+    /// lifetime from `calculate_expiration_days`
+    pub fn view_defaults(&self, pattern: &DetectedPattern) -> ViewDefaults {
+        ViewDefaults::default()
+            .with_confidence(self.calculate_confidence(pattern))
+            .with_expiration_days(self.calculate_expiration_days(pattern))
+            .with_source("pattern_detector")
     }
 
-    /// Determine view type string from pattern type
-    fn determine_view_type(&self, pattern: &DetectedPattern) -> String {
+    /// Determine view type from pattern type
+    fn determine_view_type(&self, pattern: &DetectedPattern) -> ViewType {
         match pattern.pattern_type {
-            PatternType::HighFrequency => "habit".to_string(),
-            PatternType::Trend => "trend".to_string(),
-            PatternType::Anomaly => "anomaly".to_string(),
-            PatternType::Temporal => "routine".to_string(),
+            PatternType::HighFrequency => ViewType::Habit,
+            PatternType::Trend => ViewType::Trend,
+            PatternType::Anomaly => ViewType::Anomaly,
+            PatternType::Temporal => ViewType::Routine,
         }
     }
 
@@ -237,16 +457,16 @@ impl ViewGenerator {
         let derived_from = self.extract_event_ids(pattern);
         let view_type = self.determine_view_type(pattern);
 
-        let view = NewCognitiveView::new(
+        let mut view = NewCognitiveView::new_with_defaults(
             user_id.to_string(),
             pattern.description.clone(),
             view_type,
             derived_from,
+            &self.view_defaults(pattern),
         )
-        .with_confidence(confidence)
         .with_expiration(expires_at)
-        .with_description(&pattern.description)
-        .with_source("pattern_detector");
+        .with_description(&pattern.description);
+        view.metadata = Some(pattern_subject(pattern));
 
         Ok(view)
     }
@@ -270,7 +490,7 @@ impl ViewGenerator {
             }
         }
 
-        Ok(views)
+        Ok(self.dedup_views(views))
     }
 }
 
@@ -322,6 +542,16 @@ impl ViewGeneratorBuilder {
         self
     }
 
+    pub fn with_similarity(mut self, similarity: SimilarityFn) -> Self {
+        self.config.similarity = similarity;
+        self
+    }
+
+    pub fn with_dedup_threshold(mut self, threshold: f64) -> Self {
+        self.config.dedup_similarity_threshold = threshold;
+        self
+    }
+
     pub fn build(self) -> ViewGenerator {
         ViewGenerator::with_config(self.config)
     }
@@ -433,10 +663,10 @@ mod tests {
         let anomaly_pattern = create_test_pattern(PatternType::Anomaly, 0.7);
         let temporal_pattern = create_test_pattern(PatternType::Temporal, 0.7);
 
-        assert_eq!(generator.determine_view_type(&hf_pattern), "habit");
-        assert_eq!(generator.determine_view_type(&trend_pattern), "trend");
-        assert_eq!(generator.determine_view_type(&anomaly_pattern), "anomaly");
-        assert_eq!(generator.determine_view_type(&temporal_pattern), "routine");
+        assert_eq!(generator.determine_view_type(&hf_pattern), ViewType::Habit);
+        assert_eq!(generator.determine_view_type(&trend_pattern), ViewType::Trend);
+        assert_eq!(generator.determine_view_type(&anomaly_pattern), ViewType::Anomaly);
+        assert_eq!(generator.determine_view_type(&temporal_pattern), ViewType::Routine);
     }
 
     #[test]
@@ -444,15 +674,27 @@ mod tests {
         let generator = ViewGenerator::new();
         let pattern = create_test_pattern(PatternType::HighFrequency, 0.7);
 
-        let expires_at = generator.calculate_expiration(&pattern);
-        let now = Utc::now();
-        let days_until_expiration = (expires_at - now).num_days();
+        let days_until_expiration = generator.calculate_expiration_days(&pattern);
 
         // Should be around 21 days (30 * 0.7)
         assert!(days_until_expiration >= 15);
         assert!(days_until_expiration <= 60);
     }
 
+    #[test]
+    fn test_generated_view_seeded_from_pattern() {
+        let generator = ViewGeneratorBuilder::new().with_expiration_days(60).build();
+        let pattern = create_test_pattern(PatternType::HighFrequency, 0.8);
+
+        let defaults = generator.view_defaults(&pattern);
+        assert_eq!(defaults.expiration_days, 48);
+        assert_eq!(defaults.confidence, generator.calculate_confidence(&pattern));
+
+        let view = generator.generate_view(&pattern, "test_user").unwrap();
+        assert_eq!(view.confidence, defaults.confidence);
+        assert_eq!(view.expires_at - view.created_at, chrono::Duration::days(48));
+    }
+
     #[test]
     fn test_generate_view_success() {
         let generator = ViewGenerator::new();
@@ -464,6 +706,7 @@ mod tests {
         let view = result.unwrap();
         assert_eq!(view.user_id, "test_user");
         assert_eq!(view.view_type, "habit");
+        assert_eq!(view.get_view_type(), ViewType::Habit);
         assert!(view.confidence >= 0.8);
     }
 
@@ -507,6 +750,160 @@ mod tests {
         assert_eq!(views.len(), 2);
     }
 
+    fn pattern_with(pattern_type: PatternType, description: &str, confidence: f64) -> DetectedPattern {
+        DetectedPattern {
+            description: description.to_string(),
+            ..create_test_pattern(pattern_type, confidence)
+        }
+    }
+
+    #[test]
+    fn test_text_similarity() {
+        assert_eq!(text_similarity("经常喝咖啡", "经常 喝咖啡"), 1.0);
+        assert_eq!(text_similarity("Drink Coffee", "drink coffee"), 1.0);
+        assert_eq!(text_similarity("", ""), 1.0);
+        assert_eq!(text_similarity("咖啡", ""), 0.0);
+        assert!(text_similarity("经常喝咖啡", "经常喝绿茶") < 0.7);
+        assert!(text_similarity("每天早上喝咖啡", "每天早上都喝咖啡") > 0.7);
+    }
+
+    #[test]
+    fn test_dedup_follows_injected_similarity() {
+        // Stub: hypotheses sharing their first character are the same
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+        let generator = ViewGeneratorBuilder::new()
+            .with_similarity(SimilarityFn::new("first_char", move |a, b| {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                if a.chars().next() == b.chars().next() { 1.0 } else { 0.0 }
+            }))
+            .with_dedup_threshold(0.9)
+            .build();
+
+        let patterns = vec![
+            pattern_with(PatternType::HighFrequency, "咖啡每天两杯", 0.7),
+            pattern_with(PatternType::HighFrequency, "咖啡经常喝", 0.9),
+            pattern_with(PatternType::HighFrequency, "跑步每周三次", 0.8),
+            // Same first character but another view type: not a duplicate
+            pattern_with(PatternType::Temporal, "咖啡周一早上", 0.8),
+        ];
+
+        let views = generator.generate_views_filtered(&patterns, "test_user", 0.5).unwrap();
+        let hypotheses: Vec<&str> = views.iter().map(|v| v.hypothesis.as_str()).collect();
+        // The higher-confidence duplicate replaces the first one in place
+        assert_eq!(hypotheses, vec!["咖啡经常喝", "跑步每周三次", "咖啡周一早上"]);
+        assert!(calls.load(std::sync::atomic::Ordering::SeqCst) > 0);
+
+        // The default text similarity keeps all three distinct hypotheses
+        let views = ViewGenerator::new().generate_views_filtered(&patterns, "test_user", 0.5).unwrap();
+        assert_eq!(views.len(), 4);
+    }
+
+    #[test]
+    fn test_similarities_are_cached_within_a_run() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+        let generator = ViewGeneratorBuilder::new()
+            .with_similarity(SimilarityFn::new("never", move |_, _| {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                0.0
+            }))
+            .build();
+
+        let views: Vec<NewCognitiveView> = ["甲", "乙", "甲", "乙"]
+            .iter()
+            .map(|h| {
+                generator
+                    .generate_view(&pattern_with(PatternType::HighFrequency, h, 0.8), "test_user")
+                    .unwrap()
+            })
+            .collect();
+
+        assert_eq!(generator.dedup_views(views.clone()).len(), 4);
+        // Only the distinct pairs (甲,乙), (甲,甲), (乙,乙) were scored
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        // A new run starts with an empty cache
+        generator.dedup_views(views);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 6);
+    }
+
+    #[test]
+    fn test_drop_existing_matches_subject_or_similar_hypothesis() {
+        let generator = ViewGenerator::new();
+        let stored_view = |pattern: &DetectedPattern| {
+            let view = generator.generate_view(pattern, "test_user").unwrap();
+            let now = Utc::now();
+            CognitiveView {
+                view_id: Uuid::new_v4(),
+                user_id: view.user_id,
+                hypothesis: view.hypothesis,
+                view_type: view.view_type,
+                description: view.description,
+                derived_from: view.derived_from,
+                evidence_count: view.evidence_count,
+                confidence: view.confidence,
+                validation_count: 0,
+                last_validated_at: None,
+                status: String::from(ViewStatus::Active),
+                created_at: now,
+                updated_at: now,
+                expires_at: view.expires_at,
+                promoted_to: None,
+                source: view.source,
+                tags: None,
+                metadata: view.metadata,
+                counter_evidence: serde_json::json!([]),
+                counter_evidence_count: 0,
+            }
+        };
+        let existing = vec![stored_view(&pattern_with(
+            PatternType::HighFrequency,
+            "每天吃苹果 1.9 次",
+            0.8,
+        ))];
+
+        let fresh = vec![
+            // Same action/target, reworded: a duplicate
+            pattern_with(PatternType::HighFrequency, "每天吃苹果 2.1 次", 0.8),
+            // Same action/target but another view type
+            pattern_with(PatternType::Temporal, "每周一吃苹果", 0.8),
+            // Other subject with a different hypothesis
+            DetectedPattern {
+                action: "run".to_string(),
+                target: "park".to_string(),
+                ..pattern_with(PatternType::HighFrequency, "每周跑步三次", 0.8)
+            },
+        ];
+        let views = fresh
+            .iter()
+            .map(|p| generator.generate_view(p, "test_user").unwrap())
+            .collect();
+
+        let kept = generator.drop_existing(views, &existing);
+        let hypotheses: Vec<&str> = kept.iter().map(|v| v.hypothesis.as_str()).collect();
+        assert_eq!(hypotheses, vec!["每周一吃苹果", "每周跑步三次"]);
+
+        // Without a recorded subject only similarity counts
+        let mut legacy = existing.clone();
+        legacy[0].metadata = None;
+        let views = vec![generator.generate_view(&fresh[0], "test_user").unwrap()];
+        assert_eq!(generator.drop_existing(views, &legacy).len(), 1);
+    }
+
+    #[test]
+    fn test_embedding_similarity_falls_back_to_text() {
+        let similarity = SimilarityFn::embedding(|text| match text {
+            "喝咖啡" => Some(vec![1.0, 0.0]),
+            "来杯拿铁" => Some(vec![0.9, 0.1]),
+            _ => None,
+        });
+        assert_eq!(similarity.name(), "embedding");
+        assert!(similarity.score("喝咖啡", "来杯拿铁") > 0.95);
+        assert_eq!(similarity.score("喝咖啡", "喝咖啡 "), 1.0);
+        assert_eq!(similarity.score("跑步", "游泳"), 0.0);
+    }
+
     #[test]
     fn test_view_generator_config_default() {
         let config = ViewGeneratorConfig::default();
@@ -516,5 +913,7 @@ mod tests {
         assert_eq!(config.anomaly_confidence_multiplier, 0.8);
         assert_eq!(config.temporal_confidence_multiplier, 1.1);
         assert_eq!(config.min_confidence_threshold, 0.5);
+        assert_eq!(config.similarity.name(), "text");
+        assert_eq!(config.dedup_similarity_threshold, 0.95);
     }
 }
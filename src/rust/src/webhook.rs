@@ -0,0 +1,651 @@
+//! Webhook Notifications
+//!
+//! Lets integrators react to DirSoul events (a concept promoted, an anomaly
+//! detected, a view rejected) by POSTing a JSON payload to registered URLs.
+//!
+//! # Delivery
+//! - Each endpoint subscribes to a set of event types (empty = all)
+//! - `notify` spawns delivery in the background and never blocks the caller;
+//!   outside a Tokio runtime the event is logged and dropped
+//! - Network errors, 429 and 5xx responses are retried with exponential backoff
+//! - Failures are logged, not propagated to the main flow
+//!
+//! # Authenticity
+//! Each attempt carries its Unix time in `X-DirSoul-Timestamp` and the
+//! HMAC-SHA256 of `<timestamp>.<raw body>` under the endpoint's secret in
+//! `X-DirSoul-Signature: sha256=<hex>`. Receivers recompute the signature
+//! and refuse stale timestamps (see `verify_delivery`), so a captured
+//! delivery cannot be replayed later.
+//!
+//! # Example
+//! ```no_run
+//! use dirsoul::webhook::{WebhookConfig, WebhookEvent, WebhookNotifier};
+//!
+//! # async fn run(concept: dirsoul::cognitive::StableConcept) -> dirsoul::Result<()> {
+//! let config = WebhookConfig::from_toml_str(r#"
+//!     [[endpoints]]
+//!     url = "https://example.com/hooks/dirsoul"
+//!     secret = "change-me"
+//!     events = ["concept_promoted"]
+//! "#)?;
+//! let notifier = WebhookNotifier::new(config);
+//! notifier.notify(WebhookEvent::concept_promoted(&concept));
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::cognitive::{CognitiveView, StableConcept};
+use crate::error::{DirSoulError, Result};
+use crate::pattern_detector::DetectedPattern;
+
+/// Header carrying the body signature
+pub const SIGNATURE_HEADER: &str = "X-DirSoul-Signature";
+
+/// Header carrying the event type
+pub const EVENT_HEADER: &str = "X-DirSoul-Event";
+
+/// Header carrying the signed Unix timestamp of the attempt
+pub const TIMESTAMP_HEADER: &str = "X-DirSoul-Timestamp";
+
+/// Event types a webhook can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventType {
+    /// A cognitive view was promoted to a stable concept
+    ConceptPromoted,
+    /// Pattern detection found a significant anomaly
+    AnomalyDetected,
+    /// A cognitive view was rejected
+    ViewRejected,
+}
+
+impl WebhookEventType {
+    /// Wire name, as used in payloads and configuration
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventType::ConceptPromoted => "concept_promoted",
+            WebhookEventType::AnomalyDetected => "anomaly_detected",
+            WebhookEventType::ViewRejected => "view_rejected",
+        }
+    }
+}
+
+/// JSON payload POSTed to subscribers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    /// Unique delivery id (stable across retries)
+    pub id: Uuid,
+    /// Event type
+    pub event: WebhookEventType,
+    /// User the event belongs to
+    pub user_id: String,
+    /// When the event happened
+    pub occurred_at: DateTime<Utc>,
+    /// Event-specific details
+    pub data: serde_json::Value,
+}
+
+impl WebhookEvent {
+    /// Create an event with the given details
+    pub fn new(event: WebhookEventType, user_id: String, data: serde_json::Value) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            event,
+            user_id,
+            occurred_at: Utc::now(),
+            data,
+        }
+    }
+
+    /// A view was promoted into `concept`
+    pub fn concept_promoted(concept: &StableConcept) -> Self {
+        Self::new(
+            WebhookEventType::ConceptPromoted,
+            concept.user_id.clone(),
+            serde_json::json!({
+                "concept_id": concept.concept_id,
+                "canonical_name": concept.canonical_name,
+                "display_name": concept.display_name,
+                "concept_type": concept.concept_type,
+                "version": concept.version,
+                "promoted_from": concept.promoted_from,
+                "promotion_confidence": concept.promotion_confidence,
+            }),
+        )
+    }
+
+    /// Pattern detection reported an anomaly
+    pub fn anomaly_detected(pattern: &DetectedPattern) -> Self {
+        Self::new(
+            WebhookEventType::AnomalyDetected,
+            pattern.user_id.clone(),
+            serde_json::json!({
+                "pattern_id": pattern.pattern_id,
+                "description": pattern.description,
+                "action": pattern.action,
+                "target": pattern.target,
+                "confidence": pattern.confidence,
+                "metadata": pattern.metadata,
+            }),
+        )
+    }
+
+    /// `view` was rejected
+    pub fn view_rejected(view: &CognitiveView) -> Self {
+        Self::new(
+            WebhookEventType::ViewRejected,
+            view.user_id.clone(),
+            serde_json::json!({
+                "view_id": view.view_id,
+                "hypothesis": view.hypothesis,
+                "view_type": view.view_type,
+                "confidence": view.confidence,
+            }),
+        )
+    }
+}
+
+/// A registered webhook receiver
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    /// URL the payload is POSTed to
+    pub url: String,
+    /// Shared secret for HMAC signing
+    pub secret: String,
+    /// Subscribed event types; empty subscribes to all
+    #[serde(default)]
+    pub events: Vec<WebhookEventType>,
+}
+
+impl WebhookEndpoint {
+    /// Create an endpoint subscribed to all events
+    pub fn new(url: impl Into<String>, secret: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            secret: secret.into(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Restrict the endpoint to the given event types
+    pub fn with_events(mut self, events: Vec<WebhookEventType>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Whether the endpoint wants this event type
+    pub fn subscribes_to(&self, event: WebhookEventType) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+impl std::fmt::Debug for WebhookEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookEndpoint")
+            .field("url", &self.url)
+            .field("secret", &"<redacted>")
+            .field("events", &self.events)
+            .finish()
+    }
+}
+
+/// Webhook configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    /// Registered receivers
+    pub endpoints: Vec<WebhookEndpoint>,
+    /// Delivery attempts per endpoint, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles after each failure (milliseconds)
+    pub initial_backoff_ms: u64,
+    /// Per-request timeout (seconds)
+    pub timeout_secs: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            max_attempts: 3,
+            initial_backoff_ms: 500,
+            timeout_secs: 10,
+        }
+    }
+}
+
+impl WebhookConfig {
+    /// Load from a TOML file
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        let content = std::fs::read_to_string(path.as_ref()).map_err(DirSoulError::Io)?;
+        Self::from_toml_str(&content)
+    }
+
+    /// Parse from TOML
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        let config: Self = toml::from_str(content)
+            .map_err(|e| DirSoulError::Config(format!("Invalid webhook config: {}", e)))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Register another endpoint
+    pub fn with_endpoint(mut self, endpoint: WebhookEndpoint) -> Self {
+        self.endpoints.push(endpoint);
+        self
+    }
+
+    /// Validate the configuration
+    pub fn validate(&self) -> Result<()> {
+        if self.max_attempts == 0 {
+            return Err(DirSoulError::Config("max_attempts must be at least 1".to_string()));
+        }
+        for endpoint in &self.endpoints {
+            if !endpoint.url.starts_with("http://") && !endpoint.url.starts_with("https://") {
+                return Err(DirSoulError::Config(format!(
+                    "Webhook URL must be http(s): {}",
+                    endpoint.url
+                )));
+            }
+            if endpoint.secret.is_empty() {
+                return Err(DirSoulError::Config(format!(
+                    "Webhook secret must not be empty: {}",
+                    endpoint.url
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sign `body` with HMAC-SHA256, formatted as `sha256=<hex>`
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    let digest: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("sha256={}", digest)
+}
+
+/// Signature of one delivery attempt: `sign_payload` over `<timestamp>.<body>`
+pub fn sign_delivery(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut signed = format!("{}.", timestamp).into_bytes();
+    signed.extend_from_slice(body);
+    sign_payload(secret, &signed)
+}
+
+/// Check a delivery as a receiver would
+///
+/// `timestamp` and `signature` are the header values. Deliveries signed more
+/// than `tolerance_secs` away from `now` are refused even when the signature
+/// matches.
+pub fn verify_delivery(
+    secret: &str,
+    timestamp: &str,
+    body: &[u8],
+    signature: &str,
+    tolerance_secs: i64,
+    now: DateTime<Utc>,
+) -> bool {
+    let Ok(timestamp) = timestamp.trim().parse::<i64>() else {
+        return false;
+    };
+    if (now.timestamp() - timestamp).abs() > tolerance_secs {
+        return false;
+    }
+    let expected = sign_delivery(secret, timestamp, body);
+    expected.len() == signature.len()
+        && expected
+            .bytes()
+            .zip(signature.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Sends webhook events to subscribed endpoints
+#[derive(Clone)]
+pub struct WebhookNotifier {
+    config: WebhookConfig,
+    client: Client,
+}
+
+impl WebhookNotifier {
+    /// Create a notifier for the configured endpoints
+    pub fn new(config: WebhookConfig) -> Self {
+        let client = Client::builder()
+            .timeout(std::time::Duration::from_secs(config.timeout_secs))
+            .build()
+            .unwrap_or_else(|_| Client::new());
+
+        Self { config, client }
+    }
+
+    /// Endpoints subscribed to `event`
+    pub fn subscribers(&self, event: WebhookEventType) -> Vec<&WebhookEndpoint> {
+        self.config
+            .endpoints
+            .iter()
+            .filter(|endpoint| endpoint.subscribes_to(event))
+            .collect()
+    }
+
+    /// Deliver `event` to every subscriber in the background
+    ///
+    /// Returns immediately; the handles can be awaited when the caller wants
+    /// to know the outcome (e.g. in tests or on shutdown). Called outside a
+    /// Tokio runtime, the event is logged and dropped.
+    pub fn notify(&self, event: WebhookEvent) -> Vec<tokio::task::JoinHandle<Result<()>>> {
+        let subscribers = self.subscribers(event.event);
+        if subscribers.is_empty() {
+            return Vec::new();
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!(
+                "Webhook {} {} dropped: no Tokio runtime to deliver it",
+                event.event.as_str(),
+                event.id
+            );
+            return Vec::new();
+        };
+
+        subscribers
+            .into_iter()
+            .cloned()
+            .map(|endpoint| {
+                let notifier = self.clone();
+                let event = event.clone();
+                runtime.spawn(async move {
+                    let result = notifier.deliver(&endpoint, &event).await;
+                    if let Err(e) = &result {
+                        tracing::warn!(
+                            "Webhook {} for {} dropped: {}",
+                            event.event.as_str(),
+                            endpoint.url,
+                            e
+                        );
+                    }
+                    result
+                })
+            })
+            .collect()
+    }
+
+    /// POST `event` to one endpoint, retrying transient failures with backoff
+    ///
+    /// Every attempt is signed with its own timestamp, so retries stay within
+    /// the receiver's tolerance window.
+    pub async fn deliver(&self, endpoint: &WebhookEndpoint, event: &WebhookEvent) -> Result<()> {
+        let body = serde_json::to_vec(event)?;
+        let mut backoff = std::time::Duration::from_millis(self.config.initial_backoff_ms);
+        let mut attempt = 1;
+
+        loop {
+            let timestamp = Utc::now().timestamp();
+            let signature = sign_delivery(&endpoint.secret, timestamp, &body);
+            let result = self.post(endpoint, event.event, &body, timestamp, &signature).await;
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) if e.retryable() && attempt < self.config.max_attempts => {
+                    tracing::warn!(
+                        "Webhook delivery to {} failed (attempt {}/{}), retrying in {:?}: {}",
+                        endpoint.url,
+                        attempt,
+                        self.config.max_attempts,
+                        backoff,
+                        e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Single delivery attempt
+    async fn post(
+        &self,
+        endpoint: &WebhookEndpoint,
+        event: WebhookEventType,
+        body: &[u8],
+        timestamp: i64,
+        signature: &str,
+    ) -> Result<()> {
+        let response = self
+            .client
+            .post(&endpoint.url)
+            .header("Content-Type", "application/json")
+            .header(EVENT_HEADER, event.as_str())
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, signature)
+            .body(body.to_vec())
+            .send()
+            .await
+            .map_err(|e| DirSoulError::ExternalError(format!("Webhook request failed: {}", e)))?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            Err(DirSoulError::ExternalError(format!("Webhook receiver returned {}", status)))
+        } else {
+            Err(DirSoulError::Config(format!("Webhook receiver rejected payload: {}", status)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// Captured request: event header, timestamp header, signature header, raw body
+    type Captured = Arc<Mutex<Vec<(String, String, String, Vec<u8>)>>>;
+
+    /// Mock receiver that fails the first `failures` requests with 503
+    fn spawn_receiver(failures: usize) -> (std::net::SocketAddr, Captured, Arc<AtomicUsize>) {
+        use warp::Filter;
+
+        let captured: Captured = Arc::new(Mutex::new(Vec::new()));
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let (captured_out, attempts_out) = (Arc::clone(&captured), Arc::clone(&attempts));
+
+        let hook = warp::path("hook")
+            .and(warp::post())
+            .and(warp::header::<String>(EVENT_HEADER))
+            .and(warp::header::<String>(TIMESTAMP_HEADER))
+            .and(warp::header::<String>(SIGNATURE_HEADER))
+            .and(warp::body::bytes())
+            .map(
+                move |event: String,
+                      timestamp: String,
+                      signature: String,
+                      body: warp::hyper::body::Bytes| {
+                    let n = attempts.fetch_add(1, Ordering::SeqCst);
+                    captured.lock().unwrap().push((event, timestamp, signature, body.to_vec()));
+                    let status = if n < failures {
+                        warp::http::StatusCode::SERVICE_UNAVAILABLE
+                    } else {
+                        warp::http::StatusCode::OK
+                    };
+                    warp::reply::with_status("", status)
+                },
+            );
+
+        let (addr, server) = warp::serve(hook).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        (addr, captured_out, attempts_out)
+    }
+
+    fn test_config(addr: std::net::SocketAddr) -> WebhookConfig {
+        WebhookConfig {
+            initial_backoff_ms: 10,
+            ..Default::default()
+        }
+        .with_endpoint(WebhookEndpoint::new(format!("http://{}/hook", addr), "s3cret"))
+    }
+
+    fn anomaly_event() -> WebhookEvent {
+        WebhookEvent::new(
+            WebhookEventType::AnomalyDetected,
+            "test_user".to_string(),
+            serde_json::json!({"description": "coffee spike"}),
+        )
+    }
+
+    #[test]
+    fn test_sign_payload_matches_rfc4231() {
+        assert_eq!(
+            sign_payload("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_verify_delivery_refuses_replays() {
+        let now = Utc::now();
+        let body = br#"{"event":"view_rejected"}"#;
+        let signature = sign_delivery("s3cret", now.timestamp(), body);
+        let timestamp = now.timestamp().to_string();
+
+        assert!(verify_delivery("s3cret", &timestamp, body, &signature, 300, now));
+        // Same delivery replayed an hour later
+        let later = now + chrono::Duration::hours(1);
+        assert!(!verify_delivery("s3cret", &timestamp, body, &signature, 300, later));
+        // Timestamp swapped for a fresh one
+        let fresh = later.timestamp().to_string();
+        assert!(!verify_delivery("s3cret", &fresh, body, &signature, 300, later));
+        assert!(!verify_delivery("s3cret", "soon", body, &signature, 300, now));
+    }
+
+    #[test]
+    fn test_notify_outside_runtime_drops_event() {
+        let notifier = WebhookNotifier::new(
+            WebhookConfig::default()
+                .with_endpoint(WebhookEndpoint::new("http://127.0.0.1:9/hook", "s3cret")),
+        );
+        assert!(notifier.notify(anomaly_event()).is_empty());
+    }
+
+    #[test]
+    fn test_endpoint_debug_redacts_secret() {
+        let config = WebhookConfig::default()
+            .with_endpoint(WebhookEndpoint::new("https://example.com/hook", "s3cret"));
+        let printed = format!("{:?}", config);
+        assert!(printed.contains("https://example.com/hook"));
+        assert!(!printed.contains("s3cret"));
+    }
+
+    #[tokio::test]
+    async fn test_notify_posts_signed_payload() {
+        let (addr, captured, _) = spawn_receiver(0);
+        let notifier = WebhookNotifier::new(test_config(addr));
+
+        let event = anomaly_event();
+        for handle in notifier.notify(event.clone()) {
+            handle.await.unwrap().unwrap();
+        }
+
+        let captured = captured.lock().unwrap();
+        assert_eq!(captured.len(), 1);
+        let (event_header, timestamp, signature, body) = &captured[0];
+        assert_eq!(event_header, "anomaly_detected");
+        assert!(verify_delivery("s3cret", timestamp, body, signature, 300, Utc::now()));
+        assert!(!verify_delivery("wrong", timestamp, body, signature, 300, Utc::now()));
+
+        let payload: serde_json::Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(payload["event"], "anomaly_detected");
+        assert_eq!(payload["user_id"], "test_user");
+        assert_eq!(payload["id"], event.id.to_string());
+        assert_eq!(payload["data"]["description"], "coffee spike");
+        assert!(payload["occurred_at"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_delivery_retries_with_backoff() {
+        let (addr, captured, attempts) = spawn_receiver(2);
+        let notifier = WebhookNotifier::new(test_config(addr));
+
+        let handles = notifier.notify(anomaly_event());
+        for handle in handles {
+            handle.await.unwrap().unwrap();
+        }
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        // Every attempt carries the same body, signed with its own timestamp
+        let captured = captured.lock().unwrap();
+        for (_, timestamp, signature, body) in captured.iter() {
+            assert_eq!(body, &captured[0].3);
+            assert!(verify_delivery("s3cret", timestamp, body, signature, 300, Utc::now()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delivery_gives_up_after_max_attempts() {
+        let (addr, _, attempts) = spawn_receiver(usize::MAX);
+        let notifier = WebhookNotifier::new(test_config(addr));
+
+        let handles = notifier.notify(anomaly_event());
+        assert_eq!(handles.len(), 1);
+        for handle in handles {
+            assert!(handle.await.unwrap().is_err());
+        }
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_notify_only_reaches_subscribers() {
+        let (addr, captured, _) = spawn_receiver(0);
+        let config = WebhookConfig::default().with_endpoint(
+            WebhookEndpoint::new(format!("http://{}/hook", addr), "s3cret")
+                .with_events(vec![WebhookEventType::ConceptPromoted]),
+        );
+        let notifier = WebhookNotifier::new(config);
+
+        assert!(notifier.notify(anomaly_event()).is_empty());
+        assert_eq!(notifier.subscribers(WebhookEventType::ConceptPromoted).len(), 1);
+        assert!(captured.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_config_from_toml() {
+        let config = WebhookConfig::from_toml_str(
+            r#"
+            max_attempts = 5
+
+            [[endpoints]]
+            url = "https://example.com/a"
+            secret = "one"
+            events = ["concept_promoted", "view_rejected"]
+
+            [[endpoints]]
+            url = "https://example.com/b"
+            secret = "two"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.max_attempts, 5);
+        assert_eq!(config.initial_backoff_ms, 500);
+        assert!(config.endpoints[0].subscribes_to(WebhookEventType::ViewRejected));
+        assert!(!config.endpoints[0].subscribes_to(WebhookEventType::AnomalyDetected));
+        assert!(config.endpoints[1].subscribes_to(WebhookEventType::AnomalyDetected));
+
+        assert!(WebhookConfig::from_toml_str("[[endpoints]]\nurl = \"ftp://x\"\nsecret = \"s\"").is_err());
+        assert!(WebhookConfig::from_toml_str("max_attempts = 0").is_err());
+    }
+}
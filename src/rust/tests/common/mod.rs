@@ -0,0 +1,30 @@
+//! Helpers shared by the database-backed integration tests
+//!
+//! Those tests are marked `#[ignore = "requires DATABASE_URL"]`, so a plain
+//! `cargo test` reports them as ignored rather than passing without checking
+//! anything. Run them against a migrated database with
+//! `DATABASE_URL=... cargo test -- --ignored`.
+
+#![allow(dead_code)]
+
+use diesel::prelude::*;
+use dirsoul::embedding::check_vector_capability;
+
+/// URL of the migrated test database
+pub fn database_url() -> String {
+    std::env::var("DATABASE_URL").expect("DATABASE_URL must point at a migrated database")
+}
+
+/// Connect to the test database
+pub fn connect() -> PgConnection {
+    PgConnection::establish(&database_url()).expect("DATABASE_URL is set but unreachable")
+}
+
+/// Connect to a test database that has pgvector and the embedding column
+pub fn connect_with_pgvector() -> PgConnection {
+    let mut conn = connect();
+    if let Some(reason) = check_vector_capability(&mut conn).unwrap().missing_reason() {
+        panic!("The test database lacks vector support: {}", reason);
+    }
+    conn
+}
@@ -0,0 +1,89 @@
+//! Concept Promotion Integration Tests
+//!
+//! Checks that `promote_concept` keeps one concept chain per canonical
+//! name: a repeated promotion becomes the next version, and a promotion
+//! that conflicts with the existing chain is rejected. Requires a migrated
+//! database in `DATABASE_URL`; the tests only run with `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::cognitive::{
+    find_concept_by_canonical_name, promote_concept, promote_concept_with, DuplicateConceptPolicy,
+    NewStableConcept, StableConcept,
+};
+use dirsoul::error::DirSoulError;
+use dirsoul::schema::stable_concepts;
+use uuid::Uuid;
+
+fn promoted(user_id: &str, concept_type: &str) -> NewStableConcept {
+    NewStableConcept::from_view(
+        user_id.to_string(),
+        "likes_fruit".to_string(),
+        "喜欢吃水果".to_string(),
+        concept_type.to_string(),
+        Uuid::new_v4(),
+        0.9,
+    )
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_repeated_promotion_creates_version() {
+    let mut conn = common::connect();
+
+    let user_id = format!("promotion_test_{}", Uuid::new_v4());
+    let first = promote_concept(&mut conn, promoted(&user_id, "preference")).unwrap();
+    assert_eq!(first.version, 1);
+    assert_eq!(first.parent_concept_id, None);
+
+    let second = promote_concept(&mut conn, promoted(&user_id, "preference")).unwrap();
+    assert_eq!(second.version, 2);
+    assert_eq!(second.parent_concept_id, Some(first.concept_id));
+
+    let stored: StableConcept = stable_concepts::table
+        .filter(stable_concepts::concept_id.eq(first.concept_id))
+        .first(&mut conn)
+        .unwrap();
+    assert!(stored.is_deprecated);
+    assert!(stored.deprecated_at.is_some());
+
+    let head = find_concept_by_canonical_name(&mut conn, &user_id, "likes_fruit").unwrap();
+    assert_eq!(head.map(|c| c.concept_id), Some(second.concept_id));
+
+    diesel::delete(stable_concepts::table.filter(stable_concepts::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_conflicting_promotion_is_rejected() {
+    let mut conn = common::connect();
+
+    let user_id = format!("promotion_test_{}", Uuid::new_v4());
+    let first = promote_concept(&mut conn, promoted(&user_id, "preference")).unwrap();
+
+    let conflicting = promote_concept(&mut conn, promoted(&user_id, "habit"));
+    assert!(matches!(conflicting, Err(DirSoulError::Config(_))));
+
+    let refused = promote_concept_with(
+        &mut conn,
+        promoted(&user_id, "preference"),
+        DuplicateConceptPolicy::Reject,
+    );
+    assert!(matches!(refused, Err(DirSoulError::Config(_))));
+
+    // Rejected promotions leave the chain untouched
+    let chain: Vec<StableConcept> = stable_concepts::table
+        .filter(stable_concepts::user_id.eq(&user_id))
+        .load(&mut conn)
+        .unwrap();
+    assert_eq!(chain.len(), 1);
+    assert_eq!(chain[0].concept_id, first.concept_id);
+    assert!(!chain[0].is_deprecated);
+
+    diesel::delete(stable_concepts::table.filter(stable_concepts::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
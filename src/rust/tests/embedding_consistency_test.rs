@@ -0,0 +1,141 @@
+//! Embedding Model Consistency Integration Tests
+//!
+//! Checks that vectors stored through `EmbeddingGenerator::store_embedding`
+//! are grouped by the model that produced them, that searches reuse the
+//! consistency report until the generator stores again, and that a user
+//! cannot store vectors on another user's memory. Requires a migrated
+//! database with pgvector in `DATABASE_URL`; the tests only run with
+//! `--ignored`.
+
+mod common;
+
+use std::sync::Arc;
+
+use diesel::prelude::*;
+use dirsoul::embedding::{
+    check_embedding_consistency, EmbeddingConfig, EmbeddingGenerator, ModelMismatchPolicy,
+    EMBEDDING_DIM,
+};
+use dirsoul::error::DirSoulError;
+use dirsoul::llm_provider::OllamaProvider;
+use dirsoul::models::*;
+use dirsoul::schema::raw_memories;
+use uuid::Uuid;
+
+/// Generator for `model` that is never asked to embed text
+fn generator(model: &str, on_model_mismatch: ModelMismatchPolicy) -> EmbeddingGenerator {
+    EmbeddingGenerator::with_provider(
+        Arc::new(OllamaProvider::new("http://127.0.0.1:1", model)),
+        EmbeddingConfig {
+            on_model_mismatch,
+            ..Default::default()
+        },
+    )
+}
+
+fn vector() -> Vec<f32> {
+    vec![0.1; EMBEDDING_DIM]
+}
+
+fn seed_memory(conn: &mut PgConnection, user_id: &str) -> Uuid {
+    diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.to_string(),
+            ContentType::Text,
+            "今天跑步五公里".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(conn)
+        .unwrap()
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL with pgvector"]
+fn test_mixed_model_vectors_are_flagged() {
+    let mut conn = common::connect_with_pgvector();
+
+    let user_id = format!("embedding_test_{}", Uuid::new_v4());
+    let nomic = generator("nomic-embed-text:v1.5", ModelMismatchPolicy::Warn);
+    let bge = generator("bge-m3", ModelMismatchPolicy::Warn);
+    for model in [&nomic, &nomic, &bge] {
+        let memory_id = seed_memory(&mut conn, &user_id);
+        model.store_embedding(&mut conn, &user_id, memory_id, &vector()).unwrap();
+    }
+    // Vectors stored before the model was recorded
+    let legacy = seed_memory(&mut conn, &user_id);
+    diesel::sql_query("UPDATE raw_memories SET embedding = $2::vector WHERE memory_id = $1")
+        .bind::<diesel::sql_types::Uuid, _>(legacy)
+        .bind::<diesel::sql_types::Text, _>(format!("[{}]", ["0.1"; EMBEDDING_DIM].join(",")))
+        .execute(&mut conn)
+        .unwrap();
+    // Memories without a vector are not counted
+    seed_memory(&mut conn, &user_id);
+
+    let report = check_embedding_consistency(&mut conn, &user_id).unwrap();
+    assert!(report.is_mixed());
+    assert_eq!(report.by_model.get("nomic-embed-text:v1.5"), Some(&2));
+    assert_eq!(report.by_model.get("bge-m3"), Some(&1));
+    assert_eq!(report.untracked, 1);
+    assert_eq!(report.total(), 4);
+    assert_eq!(report.mismatched("nomic-embed-text:v1.5"), 1);
+
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL with pgvector"]
+fn test_search_reuses_report_until_generator_stores() {
+    let mut conn = common::connect_with_pgvector();
+
+    let user_id = format!("embedding_test_{}", Uuid::new_v4());
+    let nomic = generator("nomic-embed-text:v1.5", ModelMismatchPolicy::Refuse);
+    let bge = generator("bge-m3", ModelMismatchPolicy::Warn);
+
+    let first = seed_memory(&mut conn, &user_id);
+    nomic.store_embedding(&mut conn, &user_id, first, &vector()).unwrap();
+    assert_eq!(nomic.search_raw_memories(&mut conn, &user_id, &vector(), 5).unwrap().len(), 1);
+
+    // Another writer's vector is not seen while the report is cached
+    let foreign = seed_memory(&mut conn, &user_id);
+    bge.store_embedding(&mut conn, &user_id, foreign, &vector()).unwrap();
+    assert!(nomic.search_raw_memories(&mut conn, &user_id, &vector(), 5).is_ok());
+
+    // The generator's own write drops the report, exposing the drift
+    let second = seed_memory(&mut conn, &user_id);
+    nomic.store_embedding(&mut conn, &user_id, second, &vector()).unwrap();
+    let err = nomic.search_raw_memories(&mut conn, &user_id, &vector(), 5).unwrap_err();
+    assert!(matches!(err, DirSoulError::Config(_)), "{:?}", err);
+
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL with pgvector"]
+fn test_store_embedding_is_scoped_to_the_owner() {
+    let mut conn = common::connect_with_pgvector();
+
+    let owner = format!("embedding_test_{}", Uuid::new_v4());
+    let memory_id = seed_memory(&mut conn, &owner);
+    let nomic = generator("nomic-embed-text:v1.5", ModelMismatchPolicy::Warn);
+
+    let err = nomic.store_embedding(&mut conn, "someone_else", memory_id, &vector()).unwrap_err();
+    assert!(matches!(err, DirSoulError::NotFound(_)), "{:?}", err);
+    let model: Option<String> = raw_memories::table
+        .find(memory_id)
+        .select(raw_memories::embedding_model)
+        .first(&mut conn)
+        .unwrap();
+    assert_eq!(model, None);
+
+    nomic.store_embedding(&mut conn, &owner, memory_id, &vector()).unwrap();
+    let report = check_embedding_consistency(&mut conn, &owner).unwrap();
+    assert_eq!(report.by_model.get("nomic-embed-text:v1.5"), Some(&1));
+
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(&owner)))
+        .execute(&mut conn)
+        .unwrap();
+}
@@ -0,0 +1,75 @@
+//! Emotional Trend History Integration Tests
+//!
+//! Checks that `EmotionalTrendStore` keeps one row per user per day and
+//! returns the series in day order. Requires a migrated database in
+//! `DATABASE_URL`; the tests only run with `--ignored`.
+
+mod common;
+
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use dirsoul::deeptalk::{EmotionalTrend, EmotionalTrendStore};
+use dirsoul::schema::emotional_trends;
+use uuid::Uuid;
+
+fn day(d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 2, d).unwrap()
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_series_is_ordered_by_day() {
+    let mut conn = common::connect();
+
+    let user_id = format!("emotion_test_{}", Uuid::new_v4());
+
+    // Written out of order, with one score outside the normalized range
+    for (d, score) in [(3, -0.4), (1, 0.6), (4, 2.5), (2, 0.0)] {
+        EmotionalTrendStore::record(&mut conn, &user_id, day(d), score).unwrap();
+    }
+
+    let series = EmotionalTrendStore::series(&mut conn, &user_id, day(1), day(4)).unwrap();
+    let days: Vec<NaiveDate> = series.iter().map(|p| p.day).collect();
+    assert_eq!(days, vec![day(1), day(2), day(3), day(4)]);
+    let scores: Vec<f64> = series.iter().map(|p| p.score).collect();
+    assert_eq!(scores, vec![0.6, 0.0, -0.4, 1.0]);
+
+    // The range is inclusive on both ends
+    let middle = EmotionalTrendStore::series(&mut conn, &user_id, day(2), day(3)).unwrap();
+    assert_eq!(middle.len(), 2);
+
+    assert!(EmotionalTrendStore::series(&mut conn, &user_id, day(4), day(1)).is_err());
+
+    diesel::delete(emotional_trends::table.filter(emotional_trends::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_same_day_scores_share_one_row() {
+    let mut conn = common::connect();
+
+    let user_id = format!("emotion_test_{}", Uuid::new_v4());
+
+    EmotionalTrendStore::record_trend(&mut conn, &user_id, day(10), EmotionalTrend::Positive).unwrap();
+    EmotionalTrendStore::record(&mut conn, &user_id, day(10), 0.0).unwrap();
+    let point = EmotionalTrendStore::record(&mut conn, &user_id, day(10), 0.5).unwrap();
+
+    assert_eq!(point.sample_count, 3);
+    assert!((point.score - 0.5).abs() < 1e-9);
+
+    let rows: i64 = emotional_trends::table
+        .filter(emotional_trends::user_id.eq(&user_id))
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    assert_eq!(rows, 1);
+
+    let series = EmotionalTrendStore::series(&mut conn, &user_id, day(1), day(28)).unwrap();
+    assert_eq!(EmotionalTrend::from_series(&series), EmotionalTrend::Positive);
+
+    diesel::delete(emotional_trends::table.filter(emotional_trends::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
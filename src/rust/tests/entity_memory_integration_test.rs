@@ -96,6 +96,7 @@ fn test_event_confidence_validation() {
         unit: Some("个".to_string()),
         confidence: 0.95,
         extractor_version: Some("1.0".to_string()),
+        metadata: None,
     };
 
     // Valid confidence range is [0.0, 1.0]
@@ -0,0 +1,333 @@
+//! Entity Relation Graph Integration Tests
+//!
+//! Checks that `find_related_entities` and `get_relation_stats` agree on a
+//! seeded graph, honor `min_strength` and stay within one user's relations,
+//! and that `GET /api/entities/{id}/relations` serves that graph only to the
+//! user's own token. The strength recompute must keep relations with no
+//! recent mentions. Requires a migrated database in `DATABASE_URL`; the
+//! tests only run with `--ignored`.
+
+mod common;
+
+use std::sync::Arc;
+
+use diesel::prelude::*;
+use dirsoul::entity_relation_extractor::{
+    users_with_relations, ConfidenceBlending, EntityRelationExtractor, RelationExtractorConfig,
+    RelationType,
+};
+use dirsoul::http_api::HttpServer;
+use dirsoul::models::*;
+use dirsoul::schema::{entities, entity_relations, event_memories, raw_memories};
+use uuid::Uuid;
+
+fn insert_entity(conn: &mut PgConnection, user_id: &str, name: &str, entity_type: EntityType) -> Uuid {
+    diesel::insert_into(entities::table)
+        .values(&NewEntity::new(user_id.to_string(), name.to_string(), entity_type))
+        .returning(entities::entity_id)
+        .get_result(conn)
+        .unwrap()
+}
+
+fn insert_relation(
+    conn: &mut PgConnection,
+    user_id: &str,
+    source: Uuid,
+    target: Uuid,
+    relation_type: &str,
+    strength: f64,
+) {
+    diesel::insert_into(entity_relations::table)
+        .values(
+            &NewEntityRelation::new(user_id.to_string(), source, target, relation_type.to_string())
+                .with_strength(strength),
+        )
+        .execute(conn)
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_related_entities_and_stats_respect_min_strength() {
+    let mut conn = common::connect();
+
+    let user_id = format!("graph_test_{}", Uuid::new_v4());
+    let other_user = format!("graph_test_{}", Uuid::new_v4());
+
+    let alice = insert_entity(&mut conn, &user_id, "Alice", EntityType::Person);
+    let acme = insert_entity(&mut conn, &user_id, "Acme", EntityType::Organization);
+    let beijing = insert_entity(&mut conn, &user_id, "北京", EntityType::Place);
+    let bob = insert_entity(&mut conn, &user_id, "Bob", EntityType::Person);
+
+    insert_relation(&mut conn, &user_id, alice, acme, "works_at", 0.9);
+    insert_relation(&mut conn, &user_id, alice, beijing, "located_at", 0.4);
+    insert_relation(&mut conn, &user_id, bob, alice, "friends_with", 0.7);
+    // Another user's relation pointing at the same entity id is never counted
+    insert_relation(&mut conn, &other_user, bob, alice, "friends_with", 0.9);
+
+    // Both users are visited by the scheduled strength recompute
+    let users = users_with_relations(&mut conn).unwrap();
+    assert!(users.contains(&user_id) && users.contains(&other_user));
+
+    let extractor = EntityRelationExtractor::new();
+
+    let related = extractor
+        .find_related_entities(&mut conn, &user_id, alice, None, None)
+        .unwrap();
+    let mut names: Vec<String> = related.iter().map(|(e, _, _)| e.canonical_name.clone()).collect();
+    names.sort();
+    assert_eq!(names, vec!["Acme", "Bob", "北京"]);
+
+    let stats = extractor.get_relation_stats(&mut conn, &user_id, alice, None).unwrap();
+    assert_eq!(stats.values().sum::<i64>(), 3);
+    assert_eq!(stats.get("friends_with"), Some(&1));
+
+    let strong = extractor
+        .find_related_entities(&mut conn, &user_id, alice, Some(0.5), None)
+        .unwrap();
+    assert_eq!(strong.len(), 2);
+
+    // The limit keeps the strongest relations across both directions
+    let top = extractor
+        .find_related_entities(&mut conn, &user_id, alice, None, Some(2))
+        .unwrap();
+    let top_names: Vec<&str> = top.iter().map(|(e, _, _)| e.canonical_name.as_str()).collect();
+    assert_eq!(top_names, vec!["Acme", "Bob"]);
+    let strong_stats = extractor.get_relation_stats(&mut conn, &user_id, alice, Some(0.5)).unwrap();
+    assert_eq!(strong_stats.get("located_at"), None);
+    assert_eq!(strong_stats.values().sum::<i64>(), 2);
+
+    for uid in [&user_id, &other_user] {
+        diesel::delete(entity_relations::table.filter(entity_relations::user_id.eq(uid)))
+            .execute(&mut conn)
+            .unwrap();
+    }
+    diesel::delete(entities::table.filter(entities::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_save_relations_counts_observations_apart_from_strength() {
+    let mut conn = common::connect();
+
+    let user_id = format!("graph_test_{}", Uuid::new_v4());
+    let alice = insert_entity(&mut conn, &user_id, "Alice", EntityType::Person);
+    let acme = insert_entity(&mut conn, &user_id, "Acme", EntityType::Organization);
+
+    let config = RelationExtractorConfig {
+        confidence_blending: ConfidenceBlending::Ema { alpha: 0.5 },
+        ..Default::default()
+    };
+    let extractor = EntityRelationExtractor::with_config(config.clone());
+
+    let first = extractor
+        .save_relations(&mut conn, &user_id, alice, acme, RelationType::WorksAt, 0.4)
+        .unwrap();
+    assert_eq!(first.observation_count, 1);
+    assert_eq!(first.strength, config.count_strength(1));
+
+    let second = extractor
+        .save_relations(&mut conn, &user_id, alice, acme, RelationType::WorksAt, 0.8)
+        .unwrap();
+    assert_eq!(second.relation_id, first.relation_id);
+    assert_eq!(second.observation_count, 2);
+    assert!((second.confidence - 0.6).abs() < 1e-9);
+    // The raw count stays in observation_count; strength is its normalized value
+    assert_eq!(second.strength, config.count_strength(2));
+    assert!(second.strength > first.strength && second.strength < 1.0);
+
+    // Recomputing co-occurrence leaves the count-based strength alone
+    let memory_id: Uuid = diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.clone(),
+            ContentType::Text,
+            "Alice 在 Acme 上班".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(&mut conn)
+        .unwrap();
+    diesel::insert_into(event_memories::table)
+        .values(&NewEventMemory::new(
+            memory_id,
+            user_id.clone(),
+            chrono::Utc::now(),
+            "work".to_string(),
+            "Alice at Acme".to_string(),
+        ))
+        .execute(&mut conn)
+        .unwrap();
+    let summary = extractor.recompute_all_strengths(&mut conn, &user_id).unwrap();
+    assert_eq!(summary.relations_updated, 1);
+    let stored: EntityRelation = entity_relations::table
+        .find(first.relation_id)
+        .first(&mut conn)
+        .unwrap();
+    assert_eq!(stored.co_occurrence, 1.0);
+    assert_eq!(stored.strength, config.count_strength(2));
+
+    // A different k from config re-derives the strength from the count
+    let slower = RelationExtractorConfig {
+        strength_saturation: 10.0,
+        ..config.clone()
+    };
+    EntityRelationExtractor::with_config(slower.clone())
+        .recompute_all_strengths(&mut conn, &user_id)
+        .unwrap();
+    let stored: EntityRelation = entity_relations::table
+        .find(first.relation_id)
+        .first(&mut conn)
+        .unwrap();
+    assert_eq!(stored.strength, slower.count_strength(2));
+    assert_eq!(stored.co_occurrence, 1.0);
+
+    diesel::delete(entity_relations::table.filter(entity_relations::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+    diesel::delete(entities::table.filter(entities::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_recompute_keeps_relations_without_recent_mentions() {
+    let mut conn = common::connect();
+
+    let user_id = format!("graph_test_{}", Uuid::new_v4());
+    let alice = insert_entity(&mut conn, &user_id, "Alice", EntityType::Person);
+    let acme = insert_entity(&mut conn, &user_id, "Acme", EntityType::Organization);
+    insert_relation(&mut conn, &user_id, alice, acme, "works_at", 0.9);
+    diesel::update(entity_relations::table.filter(entity_relations::user_id.eq(&user_id)))
+        .set(entity_relations::co_occurrence.eq(0.6))
+        .execute(&mut conn)
+        .unwrap();
+
+    // A recent event that mentions neither entity
+    let memory_id: Uuid = diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.clone(),
+            ContentType::Text,
+            "喝了咖啡".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(&mut conn)
+        .unwrap();
+    diesel::insert_into(event_memories::table)
+        .values(&NewEventMemory::new(
+            memory_id,
+            user_id.clone(),
+            chrono::Utc::now(),
+            "drink".to_string(),
+            "咖啡".to_string(),
+        ))
+        .execute(&mut conn)
+        .unwrap();
+
+    // Even with pruning on, no evidence in the window is not weak evidence
+    let extractor = EntityRelationExtractor::with_config(RelationExtractorConfig {
+        prune_weak_relations: true,
+        prune_min_samples: 1,
+        ..Default::default()
+    });
+    let summary = extractor.recompute_all_strengths(&mut conn, &user_id).unwrap();
+    assert_eq!(summary.relations_pruned, 0);
+    assert_eq!(summary.relations_unchanged, 1);
+
+    let stored: EntityRelation = entity_relations::table
+        .filter(entity_relations::user_id.eq(&user_id))
+        .first(&mut conn)
+        .unwrap();
+    assert_eq!(stored.co_occurrence, 0.6);
+
+    diesel::delete(entity_relations::table.filter(entity_relations::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+    diesel::delete(entities::table.filter(entities::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires DATABASE_URL"]
+async fn test_relations_endpoint_serves_seeded_graph_to_its_owner() {
+    let mut conn = common::connect();
+
+    let user_id = format!("graph_test_{}", Uuid::new_v4());
+    let other_user = format!("graph_test_{}", Uuid::new_v4());
+
+    let alice = insert_entity(&mut conn, &user_id, "Alice", EntityType::Person);
+    let acme = insert_entity(&mut conn, &user_id, "Acme", EntityType::Organization);
+    let beijing = insert_entity(&mut conn, &user_id, "北京", EntityType::Place);
+    let bob = insert_entity(&mut conn, &user_id, "Bob", EntityType::Person);
+
+    insert_relation(&mut conn, &user_id, alice, acme, "works_at", 0.9);
+    insert_relation(&mut conn, &user_id, alice, beijing, "located_at", 0.4);
+    insert_relation(&mut conn, &user_id, bob, alice, "friends_with", 0.7);
+
+    let server = HttpServer::new("127.0.0.1:0".to_string(), common::database_url())
+        .unwrap()
+        .with_api_token(user_id.clone(), "graph-owner-token")
+        .with_api_token(other_user.clone(), "graph-other-token");
+    let routes = Arc::new(server).routes();
+    let path = format!("/api/entities/{}/relations?user_id={}&min_strength=0.5", alice, user_id);
+
+    // Without a token, or with another user's token, the graph stays hidden
+    for (authorization, status) in [
+        (None, 401),
+        (Some("Bearer wrong-token"), 401),
+        (Some("Bearer graph-other-token"), 403),
+    ] {
+        let mut request = warp::test::request().method("GET").path(&path);
+        if let Some(authorization) = authorization {
+            request = request.header("authorization", authorization);
+        }
+        let response = request.reply(&routes).await;
+        assert_eq!(response.status(), status, "{:?}", authorization);
+    }
+
+    let response = warp::test::request()
+        .method("GET")
+        .path(&path)
+        .header("authorization", "Bearer graph-owner-token")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(body["entity_id"], alice.to_string());
+    let related: Vec<(&str, &str)> = body["related"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            (
+                r["entity"]["canonical_name"].as_str().unwrap(),
+                r["direction"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(related, vec![("Acme", "outgoing"), ("Bob", "incoming")]);
+
+    // Another user asking about this entity with their own token sees nothing
+    let response = warp::test::request()
+        .method("GET")
+        .path(&format!("/api/entities/{}/relations?user_id={}", alice, other_user))
+        .header("authorization", "Bearer graph-other-token")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 404);
+
+    diesel::delete(entity_relations::table.filter(entity_relations::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+    diesel::delete(entities::table.filter(entities::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
@@ -0,0 +1,89 @@
+//! Entity Upsert Integration Tests
+//!
+//! Checks that repeated and concurrent mentions fold into one entity row.
+//! Requires a migrated database in `DATABASE_URL`; the tests only run with
+//! `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::entity_linker::upsert_entity;
+use dirsoul::models::{EntityType, NewEntity};
+use dirsoul::schema::entities;
+use uuid::Uuid;
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_repeated_mention_increments_occurrence_count() {
+    let mut conn = common::connect();
+
+    let user_id = format!("upsert_test_{}", Uuid::new_v4());
+    let mention = |attributes: serde_json::Value| {
+        NewEntity::new(user_id.clone(), "Apple".to_string(), EntityType::Object)
+            .with_attributes(attributes)
+    };
+
+    let first = upsert_entity(&mut conn, &mention(serde_json::json!({"color": "red"}))).unwrap();
+    let second = upsert_entity(&mut conn, &mention(serde_json::json!({"taste": "sweet"}))).unwrap();
+
+    assert_eq!(first.entity_id, second.entity_id);
+    assert_eq!(second.occurrence_count, 2);
+    assert!(second.last_seen >= first.last_seen);
+
+    let rows: Vec<(i32, Option<serde_json::Value>)> = entities::table
+        .filter(entities::user_id.eq(&user_id))
+        .select((entities::occurrence_count, entities::attributes))
+        .load(&mut conn)
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].0, 2);
+    assert_eq!(
+        rows[0].1,
+        Some(serde_json::json!({"color": "red", "taste": "sweet"}))
+    );
+
+    // Same name, different type conflicts with the (user_id, canonical_name) key
+    let conflicting = NewEntity::new(user_id.clone(), "Apple".to_string(), EntityType::Organization);
+    assert!(upsert_entity(&mut conn, &conflicting).is_err());
+
+    diesel::delete(entities::table.filter(entities::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_concurrent_first_mentions_fold_into_one_row() {
+    let mut conn = common::connect();
+
+    let user_id = format!("upsert_race_test_{}", Uuid::new_v4());
+    let barrier = std::sync::Arc::new(std::sync::Barrier::new(4));
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let barrier = barrier.clone();
+            let user_id = user_id.clone();
+            std::thread::spawn(move || {
+                let mut conn = connect().unwrap();
+                let mention = NewEntity::new(user_id, "Banana".to_string(), EntityType::Object);
+                barrier.wait();
+                upsert_entity(&mut conn, &mention).map(|entity| entity.entity_id)
+            })
+        })
+        .collect();
+    let ids: Vec<Uuid> = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap().unwrap())
+        .collect();
+
+    assert!(ids.iter().all(|id| *id == ids[0]));
+    let count: i32 = entities::table
+        .filter(entities::user_id.eq(&user_id))
+        .select(entities::occurrence_count)
+        .first(&mut conn)
+        .unwrap();
+    assert_eq!(count, 4);
+
+    diesel::delete(entities::table.filter(entities::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
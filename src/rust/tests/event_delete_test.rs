@@ -0,0 +1,88 @@
+//! Event Bulk Delete Integration Tests
+//!
+//! Checks that `EventStorage::delete_events` removes only the matching events
+//! and records an audit entry. Requires a migrated database in `DATABASE_URL`;
+//! the test only runs with `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::event_storage::EventStorage;
+use dirsoul::models::*;
+use dirsoul::plugin::EventFilter;
+use dirsoul::schema::*;
+use uuid::Uuid;
+
+fn empty_filter() -> EventFilter {
+    EventFilter::default()
+}
+
+fn seed_events(conn: &mut PgConnection, user_id: &str) {
+    let memory_id: Uuid = diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.to_string(),
+            ContentType::Text,
+            "今天吃了苹果，喝了咖啡".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(conn)
+        .unwrap();
+
+    for (action, target) in [("eat", "苹果"), ("eat", "香蕉"), ("drink", "咖啡")] {
+        diesel::insert_into(event_memories::table)
+            .values(&NewEventMemory::new(
+                memory_id,
+                user_id.to_string(),
+                chrono::Utc::now(),
+                action.to_string(),
+                target.to_string(),
+            ))
+            .execute(conn)
+            .unwrap();
+    }
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_delete_events_by_action() {
+    let mut conn = common::connect();
+
+    let user_id = format!("delete_test_{}", Uuid::new_v4());
+    seed_events(&mut conn, &user_id);
+
+    // An unguarded empty filter is refused and deletes nothing
+    assert!(EventStorage::delete_events(&mut conn, &user_id, empty_filter(), false).is_err());
+
+    let filter = EventFilter {
+        actions: Some(vec!["eat".to_string()]),
+        ..empty_filter()
+    };
+    let deleted = EventStorage::delete_events(&mut conn, &user_id, filter, false).unwrap();
+    assert_eq!(deleted, 2);
+
+    let remaining: Vec<String> = event_memories::table
+        .filter(event_memories::user_id.eq(&user_id))
+        .select(event_memories::action)
+        .load(&mut conn)
+        .unwrap();
+    assert_eq!(remaining, vec!["drink".to_string()]);
+
+    let audit_counts: Vec<Option<i32>> = audit_logs::table
+        .filter(audit_logs::user_id.eq(&user_id))
+        .filter(audit_logs::action.eq("delete"))
+        .select(audit_logs::result_count)
+        .load(&mut conn)
+        .unwrap();
+    assert_eq!(audit_counts, vec![Some(2)]);
+
+    // With the explicit flag the remaining events go too
+    let deleted = EventStorage::delete_events(&mut conn, &user_id, empty_filter(), true).unwrap();
+    assert_eq!(deleted, 1);
+
+    diesel::delete(audit_logs::table.filter(audit_logs::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
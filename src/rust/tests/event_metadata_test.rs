@@ -0,0 +1,97 @@
+//! Event Metadata Integration Tests
+//!
+//! Checks that extractor signals stored in `event_memories.metadata`
+//! round-trip through the database and can be queried.
+//! Requires a migrated database in `DATABASE_URL`; the test only runs with
+//! `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::event_extractor::RuleExtractor;
+use dirsoul::models::*;
+use dirsoul::schema::{event_memories, raw_memories};
+use uuid::Uuid;
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_event_metadata_round_trip() {
+    let mut conn = common::connect();
+
+    let user_id = format!("event_metadata_test_{}", Uuid::new_v4());
+    let memory_id: Uuid = diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.clone(),
+            ContentType::Text,
+            "今天没吃苹果".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(&mut conn)
+        .unwrap();
+
+    // Signals from the rule extractor are stored with the event
+    let extracted = RuleExtractor::new().extract("今天没吃苹果").unwrap().remove(0);
+    let new_event = NewEventMemory::new(
+        memory_id,
+        user_id.clone(),
+        chrono::Utc::now(),
+        extracted.action,
+        extracted.target,
+    )
+    .with_metadata(extracted.metadata.unwrap());
+    let stored: EventMemory = diesel::insert_into(event_memories::table)
+        .values(&new_event)
+        .get_result(&mut conn)
+        .unwrap();
+    assert!(stored.is_negated());
+    assert_eq!(
+        stored.metadata_value(EVENT_RAW_SPAN_KEY),
+        Some(&serde_json::json!({"start": 3, "end": 6}))
+    );
+
+    // Arbitrary metadata survives unchanged; events without it stay NULL
+    let metadata = serde_json::json!({"sentiment": "positive", "source": {"offset": [0, 4]}});
+    let tagged = NewEventMemory::new(
+        memory_id,
+        user_id.clone(),
+        chrono::Utc::now(),
+        "喝".to_string(),
+        "咖啡".to_string(),
+    )
+    .with_metadata(metadata.clone());
+    let plain = NewEventMemory::new(
+        memory_id,
+        user_id.clone(),
+        chrono::Utc::now(),
+        "喝".to_string(),
+        "茶".to_string(),
+    );
+    diesel::insert_into(event_memories::table)
+        .values(&vec![tagged, plain])
+        .execute(&mut conn)
+        .unwrap();
+
+    let loaded: Vec<EventMemory> = event_memories::table
+        .filter(event_memories::user_id.eq(&user_id))
+        .filter(event_memories::action.eq("喝"))
+        .order(event_memories::target.asc())
+        .load(&mut conn)
+        .unwrap();
+    let by_target: Vec<(&str, Option<&serde_json::Value>)> =
+        loaded.iter().map(|e| (e.target.as_str(), e.metadata.as_ref())).collect();
+    assert!(by_target.contains(&("咖啡", Some(&metadata))));
+    assert!(by_target.contains(&("茶", None)));
+
+    // The JSONB column can be filtered on
+    let negated: i64 = event_memories::table
+        .filter(event_memories::user_id.eq(&user_id))
+        .filter(event_memories::metadata.contains(serde_json::json!({"negated": true})))
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    assert_eq!(negated, 1);
+
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
@@ -0,0 +1,106 @@
+//! Event Quantity Query Integration Tests
+//!
+//! Checks that `EventStorage::query_events` applies quantity bounds in SQL
+//! and leaves out events without a quantity. Requires a migrated database in
+//! `DATABASE_URL`; the test only runs with `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::event_storage::EventStorage;
+use dirsoul::models::*;
+use dirsoul::plugin::EventFilter;
+use dirsoul::schema::*;
+use uuid::Uuid;
+
+fn seed_events(conn: &mut PgConnection, user_id: &str) {
+    let memory_id: Uuid = diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.to_string(),
+            ContentType::Text,
+            "这周吃了不少苹果".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(conn)
+        .unwrap();
+
+    let start = chrono::Utc::now() - chrono::Duration::days(4);
+    let seeded = [
+        Some((2.0, "个")),
+        Some((4.0, "个")),
+        Some((6.0, "个")),
+        Some((5.0, "斤")),
+        None,
+    ];
+    for (day, quantity) in seeded.into_iter().enumerate() {
+        let mut event = NewEventMemory::new(
+            memory_id,
+            user_id.to_string(),
+            start + chrono::Duration::days(day as i64),
+            "eat".to_string(),
+            "苹果".to_string(),
+        );
+        if let Some((quantity, unit)) = quantity {
+            event = event.with_quantity(quantity, unit.to_string());
+        }
+        diesel::insert_into(event_memories::table)
+            .values(&event)
+            .execute(conn)
+            .unwrap();
+    }
+}
+
+fn quantities(conn: &mut PgConnection, user_id: &str, filter: &EventFilter) -> Vec<Option<f64>> {
+    EventStorage::query_events(conn, user_id, filter)
+        .unwrap()
+        .into_iter()
+        .map(|event| event.quantity)
+        .collect()
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_query_events_by_quantity_range() {
+    let mut conn = common::connect();
+
+    let user_id = format!("quantity_test_{}", Uuid::new_v4());
+    seed_events(&mut conn, &user_id);
+
+    // Without a quantity condition the quantity-less event is returned too
+    assert_eq!(
+        quantities(&mut conn, &user_id, &EventFilter::default()).len(),
+        5
+    );
+
+    // "More than 3 apples": the event without a quantity is excluded
+    let more_than_three = EventFilter {
+        min_quantity: Some(3.5),
+        unit: Some("个".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        quantities(&mut conn, &user_id, &more_than_three),
+        vec![Some(4.0), Some(6.0)]
+    );
+
+    let range = EventFilter {
+        min_quantity: Some(2.0),
+        max_quantity: Some(5.0),
+        ..Default::default()
+    };
+    assert_eq!(
+        quantities(&mut conn, &user_id, &range),
+        vec![Some(2.0), Some(4.0), Some(5.0)]
+    );
+
+    let inverted = EventFilter {
+        min_quantity: Some(5.0),
+        max_quantity: Some(2.0),
+        ..Default::default()
+    };
+    assert!(EventStorage::query_events(&mut conn, &user_id, &inverted).is_err());
+
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
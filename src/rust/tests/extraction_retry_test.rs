@@ -0,0 +1,168 @@
+//! Extraction Retry Integration Tests
+//!
+//! Checks that `retry_failed_extractions` re-extracts failed memories with
+//! backoff and dead-letters the ones that keep failing. Requires a migrated
+//! database in `DATABASE_URL`; the tests only run with `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::error::DirSoulError;
+use dirsoul::event_extractor::ExtractedEvent;
+use dirsoul::event_storage::{
+    list_dead_letter_extractions, record_extraction_failure, retry_failed_extractions,
+    users_with_due_extractions, ExtractionFailure, ExtractionFailureStatus, ExtractionRetryConfig,
+};
+use dirsoul::models::*;
+use dirsoul::schema::{event_memories, extraction_failures, raw_memories};
+use uuid::Uuid;
+
+fn seed_memory(conn: &mut PgConnection, user_id: &str) -> Uuid {
+    diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.to_string(),
+            ContentType::Text,
+            "今天吃了一个苹果".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(conn)
+        .unwrap()
+}
+
+/// Retries are due immediately so the test does not wait on backoff
+fn immediate_retry(max_attempts: i32) -> ExtractionRetryConfig {
+    ExtractionRetryConfig {
+        max_attempts,
+        base_delay: std::time::Duration::ZERO,
+        ..Default::default()
+    }
+}
+
+fn cleanup(conn: &mut PgConnection, user_id: &str) {
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(user_id)))
+        .execute(conn)
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires DATABASE_URL"]
+async fn test_transient_failure_succeeds_on_retry() {
+    let mut conn = common::connect();
+
+    let user_id = format!("extraction_retry_test_{}", Uuid::new_v4());
+    let memory_id = seed_memory(&mut conn, &user_id);
+    let config = immediate_retry(5);
+    record_extraction_failure(&mut conn, memory_id, &user_id, "LLM timeout", &config).unwrap();
+
+    // The backend is still down on the first retry, then recovers
+    let mut calls = 0;
+    let mut flaky = |_text: String| {
+        calls += 1;
+        let result = if calls == 1 {
+            Err(DirSoulError::ExternalError("LLM timeout".to_string()))
+        } else {
+            Ok(vec![ExtractedEvent::new("吃".to_string(), "苹果".to_string())])
+        };
+        async move { result }
+    };
+
+    let report = retry_failed_extractions(&mut conn, &user_id, &config, &mut flaky)
+        .await
+        .unwrap();
+    assert_eq!(report.attempted, 1);
+    assert_eq!(report.rescheduled, 1);
+    let failure: ExtractionFailure = extraction_failures::table
+        .filter(extraction_failures::memory_id.eq(memory_id))
+        .first(&mut conn)
+        .unwrap();
+    assert_eq!(failure.attempts, 2);
+    assert_eq!(failure.get_status(), ExtractionFailureStatus::Pending);
+
+    let report = retry_failed_extractions(&mut conn, &user_id, &config, &mut flaky)
+        .await
+        .unwrap();
+    assert_eq!(report.succeeded, vec![memory_id]);
+    assert_eq!(report.events_inserted, 1);
+
+    let events: i64 = event_memories::table
+        .filter(event_memories::memory_id.eq(memory_id))
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    assert_eq!(events, 1);
+    let remaining: i64 = extraction_failures::table
+        .filter(extraction_failures::memory_id.eq(memory_id))
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    assert_eq!(remaining, 0);
+
+    cleanup(&mut conn, &user_id);
+}
+
+#[tokio::test]
+#[ignore = "requires DATABASE_URL"]
+async fn test_persistent_failure_lands_in_dead_letter() {
+    let mut conn = common::connect();
+
+    let user_id = format!("extraction_retry_test_{}", Uuid::new_v4());
+    let memory_id = seed_memory(&mut conn, &user_id);
+    let config = immediate_retry(3);
+    record_extraction_failure(&mut conn, memory_id, &user_id, "LLM timeout", &config).unwrap();
+
+    let mut broken = |_text: String| async {
+        Err::<Vec<ExtractedEvent>, _>(DirSoulError::ExternalError("backend down".to_string()))
+    };
+
+    let report = retry_failed_extractions(&mut conn, &user_id, &config, &mut broken)
+        .await
+        .unwrap();
+    assert_eq!(report.rescheduled, 1);
+    let report = retry_failed_extractions(&mut conn, &user_id, &config, &mut broken)
+        .await
+        .unwrap();
+    assert_eq!(report.dead_lettered, vec![memory_id]);
+
+    // Dead letters are left for manual review, not retried
+    let report = retry_failed_extractions(&mut conn, &user_id, &config, &mut broken)
+        .await
+        .unwrap();
+    assert_eq!(report.attempted, 0);
+
+    let dead = list_dead_letter_extractions(&mut conn, &user_id).unwrap();
+    assert_eq!(dead.len(), 1);
+    assert_eq!(dead[0].memory_id, memory_id);
+    assert_eq!(dead[0].attempts, 3);
+    assert_eq!(dead[0].next_retry_at, None);
+    assert!(dead[0].last_error.contains("backend down"));
+
+    cleanup(&mut conn, &user_id);
+}
+
+#[tokio::test]
+#[ignore = "requires DATABASE_URL"]
+async fn test_claimed_failures_are_not_due_for_other_workers() {
+    let mut conn = common::connect();
+    let mut other = connect().unwrap();
+
+    let user_id = format!("extraction_retry_test_{}", Uuid::new_v4());
+    let memory_id = seed_memory(&mut conn, &user_id);
+    let config = immediate_retry(5);
+    record_extraction_failure(&mut conn, memory_id, &user_id, "LLM timeout", &config).unwrap();
+    assert!(users_with_due_extractions(&mut other).unwrap().contains(&user_id));
+
+    // While the extraction runs the claimed record is leased away
+    let mut due_elsewhere = None;
+    let mut observe = |_text: String| {
+        due_elsewhere = Some(users_with_due_extractions(&mut other).unwrap().contains(&user_id));
+        let events = vec![ExtractedEvent::new("吃".to_string(), "苹果".to_string())];
+        async move { dirsoul::Result::Ok(events) }
+    };
+    let report = retry_failed_extractions(&mut conn, &user_id, &config, &mut observe)
+        .await
+        .unwrap();
+    assert_eq!(report.succeeded, vec![memory_id]);
+    assert_eq!(due_elsewhere, Some(false));
+
+    cleanup(&mut conn, &user_id);
+}
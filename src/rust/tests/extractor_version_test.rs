@@ -0,0 +1,86 @@
+//! Extractor Version Integration Tests
+//!
+//! Checks that `EventStorage::count_events_by_extractor_version` groups a
+//! user's events by the registry tag they were stamped with. Requires a
+//! migrated database in `DATABASE_URL`; the test only runs with `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::event_storage::EventStorage;
+use dirsoul::models::*;
+use dirsoul::schema::*;
+use uuid::Uuid;
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_count_events_by_extractor_version() {
+    let mut conn = common::connect();
+
+    let user_id = format!("extractor_test_{}", Uuid::new_v4());
+    let memory_id: Uuid = diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.to_string(),
+            ContentType::Text,
+            "今天吃了苹果，喝了咖啡".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(&mut conn)
+        .unwrap();
+
+    let new_event = |target: &str| {
+        NewEventMemory::new(
+            memory_id,
+            user_id.clone(),
+            chrono::Utc::now(),
+            "eat".to_string(),
+            target.to_string(),
+        )
+    };
+    let events = vec![
+        new_event("苹果").with_extractor(ExtractorVersion::Slm),
+        new_event("香蕉").with_extractor(ExtractorVersion::Slm),
+        new_event("米饭").with_extractor(ExtractorVersion::Slm),
+        new_event("面包").with_extractor(ExtractorVersion::Rule),
+        // Stamped by an older rule extractor
+        new_event("牛奶").with_extractor_version("rule@0.0.1".to_string()),
+        NewEventMemory {
+            extractor_version: None,
+            ..new_event("咖啡")
+        },
+    ];
+    diesel::insert_into(event_memories::table)
+        .values(&events)
+        .execute(&mut conn)
+        .unwrap();
+
+    let counts = EventStorage::count_events_by_extractor_version(&mut conn, &user_id).unwrap();
+    // Largest group first, ties ordered by tag with untagged events first
+    assert_eq!(
+        counts,
+        vec![
+            (Some(ExtractorVersion::Slm.tag()), 3),
+            (None, 1),
+            (Some("rule@0.0.1".to_string()), 1),
+            (Some(ExtractorVersion::Rule.tag()), 1),
+        ]
+    );
+
+    let stale: i64 = counts
+        .iter()
+        .filter(|(tag, _)| !tag.as_deref().map_or(false, ExtractorVersion::is_current))
+        .map(|(_, count)| count)
+        .sum();
+    assert_eq!(stale, 2);
+
+    // Other users' events are not counted
+    let other = EventStorage::count_events_by_extractor_version(&mut conn, "no_such_user").unwrap();
+    assert!(other.is_empty());
+
+    diesel::delete(event_memories::table.filter(event_memories::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
@@ -0,0 +1,97 @@
+//! Import Dry-Run Integration Tests
+//!
+//! Checks that `DataImporter::import` with `dry_run` reports the same summary
+//! as a real import without writing anything. Requires a migrated database in
+//! `DATABASE_URL`; the test only runs with `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::cognitive::{NewCognitiveView, NewStableConcept};
+use dirsoul::export::{DataExporter, DataImporter};
+use dirsoul::schema::*;
+use uuid::Uuid;
+
+fn count_cognitive_rows(conn: &mut PgConnection, user_id: &str) -> (i64, i64) {
+    let views = cognitive_views::table
+        .filter(cognitive_views::user_id.eq(user_id))
+        .count()
+        .get_result(conn)
+        .unwrap();
+    let concepts = stable_concepts::table
+        .filter(stable_concepts::user_id.eq(user_id))
+        .count()
+        .get_result(conn)
+        .unwrap();
+    (views, concepts)
+}
+
+fn delete_cognitive_rows(conn: &mut PgConnection, user_id: &str) {
+    // Views may point at concepts via promoted_to
+    diesel::delete(cognitive_views::table.filter(cognitive_views::user_id.eq(user_id)))
+        .execute(conn)
+        .unwrap();
+    diesel::delete(stable_concepts::table.filter(stable_concepts::user_id.eq(user_id)))
+        .execute(conn)
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_dry_run_matches_import_without_writing() {
+    let url = common::database_url();
+    let mut conn = common::connect();
+
+    let user_id = format!("import_test_{}", Uuid::new_v4());
+    let view_id: Uuid = diesel::insert_into(cognitive_views::table)
+        .values(&NewCognitiveView::new(
+            user_id.clone(),
+            "用户喜欢喝咖啡".to_string(),
+            "preference".to_string(),
+            vec![Uuid::new_v4()],
+        ))
+        .returning(cognitive_views::view_id)
+        .get_result(&mut conn)
+        .unwrap();
+    diesel::insert_into(stable_concepts::table)
+        .values(&NewStableConcept::from_view(
+            user_id.clone(),
+            "likes_coffee".to_string(),
+            "喜欢喝咖啡".to_string(),
+            "preference".to_string(),
+            view_id,
+            0.9,
+        ))
+        .execute(&mut conn)
+        .unwrap();
+
+    // Back up, then clear the user so the backup can be restored
+    let export = DataExporter::new(url.clone()).export_user_data(&user_id).unwrap();
+    delete_cognitive_rows(&mut conn, &user_id);
+
+    let importer = DataImporter::new(url);
+    let preview = importer.import(&export, true).unwrap();
+    assert!(preview.dry_run);
+    assert_eq!(preview.cognitive_views_imported, 1);
+    assert_eq!(preview.stable_concepts_imported, 1);
+    assert_eq!(count_cognitive_rows(&mut conn, &user_id), (0, 0));
+
+    let summary = importer.import(&export, false).unwrap();
+    assert!(!summary.dry_run);
+    assert_eq!(summary.cognitive_views_imported, preview.cognitive_views_imported);
+    assert_eq!(summary.stable_concepts_imported, preview.stable_concepts_imported);
+    assert_eq!(summary.raw_memories_imported, preview.raw_memories_imported);
+    assert_eq!(summary.event_memories_imported, preview.event_memories_imported);
+    assert_eq!(summary.entities_imported, preview.entities_imported);
+    assert_eq!(summary.rows_skipped, preview.rows_skipped);
+    assert_eq!(count_cognitive_rows(&mut conn, &user_id), (1, 1));
+
+    // Importing the same backup again skips every row
+    let rerun = importer.import(&export, false).unwrap();
+    assert_eq!(rerun.cognitive_views_imported, 0);
+    assert_eq!(rerun.stable_concepts_imported, 0);
+    assert_eq!(rerun.rows_skipped, 2);
+    assert_eq!(count_cognitive_rows(&mut conn, &user_id), (1, 1));
+
+    delete_cognitive_rows(&mut conn, &user_id);
+}
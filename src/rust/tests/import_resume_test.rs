@@ -0,0 +1,128 @@
+//! Batched Import Integration Tests
+//!
+//! Checks that `DataImporter::import_batched` keeps the batches committed
+//! before an interruption and that resuming from the reported watermark
+//! imports every remaining row exactly once. Requires a migrated database
+//! in `DATABASE_URL`; the test only runs with `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::cognitive::{NewCognitiveView, NewStableConcept};
+use dirsoul::error::DirSoulError;
+use dirsoul::export::{DataExporter, DataImporter, ImportOptions};
+use dirsoul::schema::*;
+use uuid::Uuid;
+
+fn count_cognitive_rows(conn: &mut PgConnection, user_id: &str) -> (i64, i64) {
+    let views = cognitive_views::table
+        .filter(cognitive_views::user_id.eq(user_id))
+        .count()
+        .get_result(conn)
+        .unwrap();
+    let concepts = stable_concepts::table
+        .filter(stable_concepts::user_id.eq(user_id))
+        .count()
+        .get_result(conn)
+        .unwrap();
+    (views, concepts)
+}
+
+fn delete_cognitive_rows(conn: &mut PgConnection, user_id: &str) {
+    // Views may point at concepts via promoted_to
+    diesel::delete(cognitive_views::table.filter(cognitive_views::user_id.eq(user_id)))
+        .execute(conn)
+        .unwrap();
+    diesel::delete(stable_concepts::table.filter(stable_concepts::user_id.eq(user_id)))
+        .execute(conn)
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_interrupted_import_resumes_exactly_once() {
+    let url = common::database_url();
+    let mut conn = common::connect();
+
+    let user_id = format!("import_resume_test_{}", Uuid::new_v4());
+    for i in 0..3 {
+        let view_id: Uuid = diesel::insert_into(cognitive_views::table)
+            .values(&NewCognitiveView::new(
+                user_id.clone(),
+                format!("用户喜欢第{}种水果", i),
+                "preference".to_string(),
+                vec![Uuid::new_v4()],
+            ))
+            .returning(cognitive_views::view_id)
+            .get_result(&mut conn)
+            .unwrap();
+        diesel::insert_into(stable_concepts::table)
+            .values(&NewStableConcept::from_view(
+                user_id.clone(),
+                format!("likes_fruit_{}", i),
+                format!("喜欢第{}种水果", i),
+                "preference".to_string(),
+                view_id,
+                0.9,
+            ))
+            .execute(&mut conn)
+            .unwrap();
+    }
+
+    // Back up, then clear the user so the backup can be restored
+    let export = DataExporter::new(url.clone()).export_user_data(&user_id).unwrap();
+    delete_cognitive_rows(&mut conn, &user_id);
+
+    // The import is interrupted once two batches of two rows are committed
+    let importer = DataImporter::new(url);
+    let options = ImportOptions {
+        batch_size: 2,
+        ..Default::default()
+    };
+    let mut progress = Vec::new();
+    let interrupted = importer
+        .import_batched(&export, &options, |p| {
+            progress.push(p.rows_processed);
+            if p.rows_processed == 4 {
+                return Err(DirSoulError::ExternalError("connection reset".to_string()));
+            }
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(progress, vec![2, 4]);
+    assert!(interrupted.failure.unwrap().contains("connection reset"));
+    let watermark = interrupted.watermark.unwrap();
+    assert_eq!(watermark.rows_committed, 4);
+    assert_eq!(watermark.total_rows, 6);
+    assert!(!watermark.is_complete());
+    assert_eq!(count_cognitive_rows(&mut conn, &user_id), (1, 3));
+
+    let mut progress = Vec::new();
+    let resumed = importer
+        .import_batched(&export, &options.with_resume_from(watermark), |p| {
+            progress.push((p.rows_processed, p.total_rows));
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(progress, vec![(6, 6)]);
+    assert_eq!(resumed.failure, None);
+    assert!(resumed.watermark.unwrap().is_complete());
+    assert_eq!(resumed.stable_concepts_imported, 0);
+    assert_eq!(resumed.cognitive_views_imported, 2);
+    assert_eq!(count_cognitive_rows(&mut conn, &user_id), (3, 3));
+
+    // Concepts committed before the interruption link to the views
+    // imported after it
+    let linked: i64 = stable_concepts::table
+        .inner_join(
+            cognitive_views::table
+                .on(stable_concepts::promoted_from.eq(cognitive_views::view_id.nullable())),
+        )
+        .filter(stable_concepts::user_id.eq(&user_id))
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    assert_eq!(linked, 3);
+
+    delete_cognitive_rows(&mut conn, &user_id);
+}
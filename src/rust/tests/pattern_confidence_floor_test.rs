@@ -0,0 +1,92 @@
+//! Pattern Confidence Floor Integration Tests
+//!
+//! Checks that `PatternDetector::detect_patterns` only loads events at or
+//! above `min_event_confidence`, so low-confidence extractions stay out of
+//! patterns and their evidence.
+//! Requires a migrated database in `DATABASE_URL`; the test only runs with
+//! `--ignored`.
+
+mod common;
+
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use dirsoul::models::*;
+use dirsoul::pattern_detector::{DetectionTimeRange, PatternDetector, PatternType};
+use dirsoul::schema::{event_memories, raw_memories};
+use uuid::Uuid;
+
+/// One event a day for the last week, extracted with `confidence`
+fn seed_daily_events(
+    conn: &mut PgConnection,
+    user_id: &str,
+    memory_id: Uuid,
+    target: &str,
+    confidence: f64,
+) {
+    let start = Utc::now() - Duration::days(7);
+    for day in 0..7 {
+        diesel::insert_into(event_memories::table)
+            .values(
+                &NewEventMemory::new(
+                    memory_id,
+                    user_id.to_string(),
+                    start + Duration::days(day) + Duration::hours(2),
+                    "吃".to_string(),
+                    target.to_string(),
+                )
+                .with_confidence(confidence),
+            )
+            .execute(conn)
+            .unwrap();
+    }
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_confidence_floor_restricts_pattern_evidence() {
+    let mut conn = common::connect();
+
+    let user_id = format!("pattern_confidence_test_{}", Uuid::new_v4());
+    let memory_id: Uuid = diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.clone(),
+            ContentType::Text,
+            "这周的饮食记录".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(&mut conn)
+        .unwrap();
+    seed_daily_events(&mut conn, &user_id, memory_id, "水果", 0.9);
+    // Extractions the model was unsure about
+    seed_daily_events(&mut conn, &user_id, memory_id, "零食", 0.3);
+
+    let high_frequency_targets = |detector: PatternDetector, conn: &mut PgConnection| {
+        let result = detector
+            .detect_patterns(conn, &user_id, DetectionTimeRange::last_n_days(8))
+            .unwrap();
+        let mut targets: Vec<String> = result
+            .patterns
+            .into_iter()
+            .filter(|p| p.pattern_type == PatternType::HighFrequency)
+            .map(|p| p.target)
+            .collect();
+        targets.sort();
+        (result.events_analyzed, targets)
+    };
+
+    // A low floor includes the noisy events
+    let (analyzed, targets) =
+        high_frequency_targets(PatternDetector::new().with_min_event_confidence(0.2), &mut conn);
+    assert_eq!(analyzed, 14);
+    assert_eq!(targets, vec!["水果".to_string(), "零食".to_string()]);
+
+    // A higher floor keeps only the reliable evidence
+    let (analyzed, targets) =
+        high_frequency_targets(PatternDetector::new().with_min_event_confidence(0.8), &mut conn);
+    assert_eq!(analyzed, 7);
+    assert_eq!(targets, vec!["水果".to_string()]);
+
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
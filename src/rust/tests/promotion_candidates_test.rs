@@ -0,0 +1,119 @@
+//! Promotion Candidate Integration Tests
+//!
+//! Checks that `list_promotion_candidates` returns the active views that pass
+//! the Promotion Gate, plus the near misses within the configured margin,
+//! and pages through them.
+//! Requires a migrated database in `DATABASE_URL`; the test only runs with
+//! `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::cognitive::{
+    list_promotion_candidates, CandidateReadiness, CognitiveView, GateCriterion,
+    NewCognitiveView, PromotionCandidateConfig, ViewDefaults, ViewStatus,
+};
+use dirsoul::schema::cognitive_views;
+use uuid::Uuid;
+
+/// Insert a view observed for 35 of its 40 days
+fn seed_view(
+    conn: &mut PgConnection,
+    user_id: &str,
+    confidence: f64,
+    validation_count: i32,
+    counter_evidence_count: i32,
+) -> CognitiveView {
+    let mut new_view = NewCognitiveView::new_with_defaults(
+        user_id.to_string(),
+        format!("用户喜欢吃水果 ({})", confidence),
+        "preference".to_string(),
+        (0..20).map(|_| Uuid::new_v4()).collect(),
+        &ViewDefaults::default().with_confidence(confidence),
+    );
+    new_view.validation_count = validation_count;
+    new_view.counter_evidence_count = counter_evidence_count;
+    new_view.created_at = chrono::Utc::now() - chrono::Duration::days(35);
+    new_view.expires_at = new_view.created_at + chrono::Duration::days(40);
+
+    diesel::insert_into(cognitive_views::table)
+        .values(&new_view)
+        .get_result(conn)
+        .unwrap()
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_only_qualifying_and_near_miss_views_are_candidates() {
+    let mut conn = common::connect();
+
+    let user_id = format!("promotion_candidates_test_{}", Uuid::new_v4());
+    let ready = seed_view(&mut conn, &user_id, 0.95, 4, 0);
+    let ready_lower = seed_view(&mut conn, &user_id, 0.9, 3, 1);
+    // Confidence just under the gate
+    let near_confidence = seed_view(&mut conn, &user_id, 0.8, 3, 0);
+    // Counter-evidence ratio 3/20, at the gate's exclusive 0.15 bound
+    let near_counter = seed_view(&mut conn, &user_id, 0.88, 5, 3);
+    // Far from the gate
+    seed_view(&mut conn, &user_id, 0.5, 3, 0);
+    seed_view(&mut conn, &user_id, 0.95, 0, 0);
+    // Counter-evidence ratio 8/20, auto-rejected
+    seed_view(&mut conn, &user_id, 0.95, 5, 8);
+    // Passes the gate but is no longer active
+    let promoted = seed_view(&mut conn, &user_id, 0.99, 5, 0);
+    diesel::update(cognitive_views::table.filter(cognitive_views::view_id.eq(promoted.view_id)))
+        .set(cognitive_views::status.eq(String::from(ViewStatus::Promoted)))
+        .execute(&mut conn)
+        .unwrap();
+
+    let config = PromotionCandidateConfig::default();
+    let page = list_promotion_candidates(&mut conn, &user_id, &config, 0, 10).unwrap();
+    assert_eq!(page.total, 4);
+    assert_eq!(page.next_offset, None);
+    let listed: Vec<(Uuid, CandidateReadiness)> =
+        page.candidates.iter().map(|c| (c.view.view_id, c.readiness)).collect();
+    assert_eq!(
+        listed,
+        vec![
+            (ready.view_id, CandidateReadiness::Ready),
+            (ready_lower.view_id, CandidateReadiness::Ready),
+            (near_counter.view_id, CandidateReadiness::NearMiss),
+            (near_confidence.view_id, CandidateReadiness::NearMiss),
+        ]
+    );
+
+    // Each candidate carries its criterion breakdown
+    let near = &page.candidates[3];
+    assert_eq!(near.criteria.len(), 4);
+    let failing: Vec<GateCriterion> =
+        near.criteria.iter().filter(|c| !c.passed).map(|c| c.criterion).collect();
+    assert_eq!(failing, vec![GateCriterion::Confidence]);
+    assert!(near.criteria[0].near_miss);
+
+    // Pages follow `next_offset`
+    let first = list_promotion_candidates(&mut conn, &user_id, &config, 0, 3).unwrap();
+    assert_eq!(first.candidates.len(), 3);
+    assert_eq!(first.next_offset, Some(3));
+    let second = list_promotion_candidates(&mut conn, &user_id, &config, 3, 3).unwrap();
+    assert_eq!(second.candidates.len(), 1);
+    assert_eq!(second.candidates[0].view.view_id, near_confidence.view_id);
+    assert_eq!(second.next_offset, None);
+
+    // Without a margin only the views passing the gate are listed
+    let strict = PromotionCandidateConfig {
+        near_miss_margin: 0.0,
+        ..Default::default()
+    };
+    let page = list_promotion_candidates(&mut conn, &user_id, &strict, 0, 10).unwrap();
+    let listed: Vec<Uuid> = page.candidates.iter().map(|c| c.view.view_id).collect();
+    assert_eq!(listed, vec![ready.view_id, ready_lower.view_id]);
+
+    // Other users see none of them
+    let other_user = format!("promotion_candidates_test_{}", Uuid::new_v4());
+    let page = list_promotion_candidates(&mut conn, &other_user, &config, 0, 10).unwrap();
+    assert_eq!(page.total, 0);
+
+    diesel::delete(cognitive_views::table.filter(cognitive_views::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
@@ -0,0 +1,141 @@
+//! Selective Export Integration Tests
+//!
+//! Checks that `DataExporter::with_options` leaves out the sections that are
+//! not selected, that such a partial export still imports, and that a rerun
+//! of the import writes nothing twice. Requires a migrated database in
+//! `DATABASE_URL`; the test only runs with `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::cognitive::NewCognitiveView;
+use dirsoul::export::{DataExporter, DataImporter, ExportOptions};
+use dirsoul::models::*;
+use dirsoul::schema::*;
+use uuid::Uuid;
+
+fn delete_events(conn: &mut PgConnection, user_id: &str) {
+    diesel::delete(event_memories::table.filter(event_memories::user_id.eq(user_id)))
+        .execute(conn)
+        .unwrap();
+}
+
+fn count_events(conn: &mut PgConnection, user_id: &str) -> i64 {
+    event_memories::table
+        .filter(event_memories::user_id.eq(user_id))
+        .count()
+        .get_result(conn)
+        .unwrap()
+}
+
+fn delete_user_rows(conn: &mut PgConnection, user_id: &str) {
+    diesel::delete(cognitive_views::table.filter(cognitive_views::user_id.eq(user_id)))
+        .execute(conn)
+        .unwrap();
+    delete_events(conn, user_id);
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(user_id)))
+        .execute(conn)
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_events_only_export_omits_other_sections_and_imports() {
+    let url = common::database_url();
+    let mut conn = common::connect();
+
+    let user_id = format!("selective_export_{}", Uuid::new_v4());
+    let memory_id: Uuid = diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.clone(),
+            ContentType::Text,
+            "早上喝了咖啡，中午吃了面".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(&mut conn)
+        .unwrap();
+    let events: Vec<NewEventMemory> = [("喝", "咖啡"), ("吃", "面")]
+        .iter()
+        .map(|(action, target)| {
+            NewEventMemory::new(
+                memory_id,
+                user_id.clone(),
+                chrono::Utc::now(),
+                action.to_string(),
+                target.to_string(),
+            )
+        })
+        .collect();
+    diesel::insert_into(event_memories::table)
+        .values(&events)
+        .execute(&mut conn)
+        .unwrap();
+    diesel::insert_into(cognitive_views::table)
+        .values(&NewCognitiveView::new(
+            user_id.clone(),
+            "用户每天喝咖啡".to_string(),
+            "habit".to_string(),
+            vec![Uuid::new_v4()],
+        ))
+        .execute(&mut conn)
+        .unwrap();
+
+    let events_only = ExportOptions {
+        include_events: true,
+        ..ExportOptions::none()
+    };
+    let export = DataExporter::new(url.clone())
+        .with_options(events_only)
+        .export_user_data(&user_id)
+        .unwrap();
+
+    assert_eq!(export.sections, events_only);
+    assert_eq!(export.event_memories.len(), 2);
+    assert!(export.raw_memories.is_empty());
+    assert!(export.cognitive_views.is_empty());
+    assert!(export.audit_logs.is_empty());
+    assert_eq!(export.metadata.event_memory_count, 2);
+    assert_eq!(export.metadata.cognitive_view_count, 0);
+
+    // The omitted sections are absent from the serialized backup
+    let json = serde_json::to_value(&export).unwrap();
+    assert!(json.get("raw_memories").is_none());
+    assert!(json.get("cognitive_views").is_none());
+
+    // Restore the events; they link to the memory still in the account
+    delete_events(&mut conn, &user_id);
+    let restored = serde_json::from_value(json).unwrap();
+    let importer = DataImporter::new(url);
+    let preview = importer.import(&restored, true).unwrap();
+    assert!(preview.dry_run);
+    assert_eq!(preview.event_memories_imported, 2);
+    assert_eq!(count_events(&mut conn, &user_id), 0);
+    let summary = importer.import(&restored, false).unwrap();
+    assert_eq!(summary.event_memories_imported, 2);
+    assert_eq!(summary.raw_memories_imported, 0);
+    assert_eq!(summary.cognitive_views_imported, 0);
+    assert_eq!(summary.stable_concepts_imported, 0);
+    assert_eq!(summary.rows_skipped, 0);
+    let linked: i64 = event_memories::table
+        .filter(event_memories::user_id.eq(&user_id))
+        .filter(event_memories::memory_id.eq(memory_id))
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    assert_eq!(linked, 2);
+
+    // Rerunning the import writes nothing twice
+    let rerun = importer.import(&restored, false).unwrap();
+    assert_eq!(rerun.event_memories_imported, 0);
+    assert_eq!(rerun.rows_skipped, 2);
+    assert_eq!(count_events(&mut conn, &user_id), 2);
+
+    // Without their memory the events are skipped, not reported as imported
+    delete_user_rows(&mut conn, &user_id);
+    let orphaned = importer.import(&restored, false).unwrap();
+    assert_eq!(orphaned.event_memories_imported, 0);
+    assert_eq!(orphaned.rows_skipped, 2);
+    assert_eq!(count_events(&mut conn, &user_id), 0);
+
+    delete_user_rows(&mut conn, &user_id);
+}
@@ -0,0 +1,80 @@
+//! Soft Delete Integration Tests
+//!
+//! Checks that the events of a soft-deleted memory disappear from event
+//! queries until the memory is restored, and that only its owner can
+//! restore it. Requires a migrated database in `DATABASE_URL`; the test only
+//! runs with `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::data_changes::{subscribe_user_data_changes, UserDataListener};
+use dirsoul::data_lifecycle::{restore_memory, soft_delete_memory};
+use dirsoul::error::DirSoulError;
+use dirsoul::event_storage::EventStorage;
+use dirsoul::models::*;
+use dirsoul::plugin::EventFilter;
+use dirsoul::schema::*;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_soft_deleted_events_hidden_until_restored_by_owner() {
+    let mut conn = common::connect();
+
+    let user_id = format!("soft_delete_test_{}", Uuid::new_v4());
+    let memory_id: Uuid = diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.clone(),
+            ContentType::Text,
+            "今天吃了苹果".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(&mut conn)
+        .unwrap();
+    diesel::insert_into(event_memories::table)
+        .values(&NewEventMemory::new(
+            memory_id,
+            user_id.clone(),
+            chrono::Utc::now(),
+            "eat".to_string(),
+            "苹果".to_string(),
+        ))
+        .execute(&mut conn)
+        .unwrap();
+
+    let visible = |conn: &mut PgConnection| {
+        EventStorage::query_events(conn, &user_id, &EventFilter::default())
+            .unwrap()
+            .len()
+    };
+    assert_eq!(visible(&mut conn), 1);
+
+    // Cached query responses are invalidated by both writes
+    let notified = Arc::new(Mutex::new(0));
+    let counter = notified.clone();
+    let watched = user_id.clone();
+    let listener: Arc<UserDataListener> = Arc::new(move |changed: &str| {
+        if changed == watched {
+            *counter.lock().unwrap() += 1;
+        }
+    });
+    subscribe_user_data_changes(&listener);
+
+    soft_delete_memory(&mut conn, &user_id, memory_id).unwrap();
+    assert_eq!(visible(&mut conn), 0);
+
+    // Another user cannot restore the memory
+    let err = restore_memory(&mut conn, "someone_else", memory_id, 30).unwrap_err();
+    assert!(matches!(err, DirSoulError::NotFound(_)), "{:?}", err);
+    assert_eq!(visible(&mut conn), 0);
+
+    restore_memory(&mut conn, &user_id, memory_id, 30).unwrap();
+    assert_eq!(visible(&mut conn), 1);
+    assert_eq!(*notified.lock().unwrap(), 2);
+
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
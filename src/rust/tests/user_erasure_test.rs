@@ -0,0 +1,151 @@
+//! User Erasure Integration Tests
+//!
+//! Seeds one user's data across every table and checks that `erase_user`
+//! removes it. Requires a migrated database in `DATABASE_URL`; the test only
+//! runs with `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::agents::{AgentPermissions, NewAgent};
+use dirsoul::audit::NewAuditLog;
+use dirsoul::cognitive::{NewCognitiveView, NewStableConcept};
+use dirsoul::deeptalk::{EmotionalTrend, EmotionalTrendStore};
+use dirsoul::export::{erase_user, erasure_confirmation_token};
+use dirsoul::models::*;
+use dirsoul::schema::*;
+use uuid::Uuid;
+
+fn seed_user(conn: &mut PgConnection, user_id: &str) {
+    let memory_id: Uuid = diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.to_string(),
+            ContentType::Text,
+            "今天吃了一个苹果".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(conn)
+        .unwrap();
+
+    diesel::insert_into(event_memories::table)
+        .values(&NewEventMemory::new(
+            memory_id,
+            user_id.to_string(),
+            chrono::Utc::now(),
+            "eat".to_string(),
+            "苹果".to_string(),
+        ))
+        .execute(conn)
+        .unwrap();
+
+    let apple: Uuid = diesel::insert_into(entities::table)
+        .values(&NewEntity::new(user_id.to_string(), "苹果".to_string(), EntityType::Object))
+        .returning(entities::entity_id)
+        .get_result(conn)
+        .unwrap();
+    let fruit: Uuid = diesel::insert_into(entities::table)
+        .values(&NewEntity::new(user_id.to_string(), "水果".to_string(), EntityType::Concept))
+        .returning(entities::entity_id)
+        .get_result(conn)
+        .unwrap();
+
+    diesel::insert_into(entity_relations::table)
+        .values(&NewEntityRelation::new(
+            user_id.to_string(),
+            apple,
+            fruit,
+            "belongs_to".to_string(),
+        ))
+        .execute(conn)
+        .unwrap();
+
+    let view_id: Uuid = diesel::insert_into(cognitive_views::table)
+        .values(&NewCognitiveView::new(
+            user_id.to_string(),
+            "用户喜欢吃水果".to_string(),
+            "preference".to_string(),
+            vec![memory_id],
+        ))
+        .returning(cognitive_views::view_id)
+        .get_result(conn)
+        .unwrap();
+
+    diesel::insert_into(stable_concepts::table)
+        .values(&NewStableConcept::from_view(
+            user_id.to_string(),
+            "likes_fruit".to_string(),
+            "喜欢吃水果".to_string(),
+            "preference".to_string(),
+            view_id,
+            0.9,
+        ))
+        .execute(conn)
+        .unwrap();
+
+    let today = chrono::Utc::now().date_naive();
+    EmotionalTrendStore::record_trend(conn, user_id, today, EmotionalTrend::Positive).unwrap();
+
+    diesel::insert_into(agents::table)
+        .values(
+            &NewAgent::new_plugin(user_id, "Journal", "custom", AgentPermissions::default(), user_id)
+                .unwrap(),
+        )
+        .execute(conn)
+        .unwrap();
+
+    diesel::insert_into(audit_logs::table)
+        .values(&NewAuditLog::new(user_id.to_string(), "query".to_string(), "events".to_string()))
+        .execute(conn)
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_erase_user_removes_all_rows() {
+    let mut conn = common::connect();
+
+    let user_id = format!("erase_test_{}", Uuid::new_v4());
+    seed_user(&mut conn, &user_id);
+
+    // Wrong token leaves everything in place
+    assert!(erase_user(&mut conn, &user_id, "ERASE:someone_else").is_err());
+    let raw_left: i64 = raw_memories::table
+        .filter(raw_memories::user_id.eq(&user_id))
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    assert_eq!(raw_left, 1);
+
+    let report = erase_user(&mut conn, &user_id, &erasure_confirmation_token(&user_id)).unwrap();
+    assert_eq!(report.raw_memories_deleted, 1);
+    assert_eq!(report.event_memories_deleted, 1);
+    assert_eq!(report.entities_deleted, 2);
+    assert_eq!(report.entity_relations_deleted, 1);
+    assert_eq!(report.cognitive_views_deleted, 1);
+    assert_eq!(report.stable_concepts_deleted, 1);
+    assert_eq!(report.emotional_trends_deleted, 1);
+    assert_eq!(report.agents_deleted, 1);
+    assert_eq!(report.audit_logs_anonymized, 1);
+
+    let remaining: Vec<i64> = vec![
+        raw_memories::table.filter(raw_memories::user_id.eq(&user_id)).count().get_result(&mut conn).unwrap(),
+        event_memories::table.filter(event_memories::user_id.eq(&user_id)).count().get_result(&mut conn).unwrap(),
+        entities::table.filter(entities::user_id.eq(&user_id)).count().get_result(&mut conn).unwrap(),
+        entity_relations::table.filter(entity_relations::user_id.eq(&user_id)).count().get_result(&mut conn).unwrap(),
+        cognitive_views::table.filter(cognitive_views::user_id.eq(&user_id)).count().get_result(&mut conn).unwrap(),
+        stable_concepts::table.filter(stable_concepts::user_id.eq(&user_id)).count().get_result(&mut conn).unwrap(),
+        emotional_trends::table.filter(emotional_trends::user_id.eq(&user_id)).count().get_result(&mut conn).unwrap(),
+        agents::table.filter(agents::user_id.eq(&user_id)).count().get_result(&mut conn).unwrap(),
+        audit_logs::table.filter(audit_logs::user_id.eq(&user_id)).count().get_result(&mut conn).unwrap(),
+    ];
+    assert!(remaining.iter().all(|&count| count == 0), "rows left: {:?}", remaining);
+
+    // Anonymized history plus the erasure record itself
+    let pseudonymous: Vec<String> = audit_logs::table
+        .filter(audit_logs::user_id.eq(&report.audit_pseudonym))
+        .select(audit_logs::action)
+        .load(&mut conn)
+        .unwrap();
+    assert_eq!(pseudonymous.len(), 2);
+    assert!(pseudonymous.contains(&"erase".to_string()));
+}
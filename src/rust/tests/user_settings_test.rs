@@ -0,0 +1,93 @@
+//! Per-User Settings Integration Tests
+//!
+//! Checks that settings round-trip through `user_settings`, that saving
+//! replaces earlier overrides, and that one user's time zone doesn't change
+//! how another user's timeline is bucketed. Requires a migrated database in
+//! `DATABASE_URL`; the test only runs with `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::cognitive::PromotionGateConfig;
+use dirsoul::http_api::TimelineZone;
+use dirsoul::schema::user_settings;
+use dirsoul::user_settings::UserSettings;
+use uuid::Uuid;
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_timezone_override_buckets_only_that_user() {
+    let mut conn = common::connect();
+
+    let shanghai_user = format!("settings_test_{}", Uuid::new_v4());
+    let default_user = format!("settings_test_{}", Uuid::new_v4());
+
+    // Without a row the global defaults apply
+    let settings = UserSettings::get(&mut conn, &shanghai_user).unwrap();
+    assert_eq!(settings, UserSettings::new(shanghai_user.as_str()));
+
+    let saved = UserSettings::update(
+        &mut conn,
+        &UserSettings {
+            timezone: Some("Asia/Shanghai".to_string()),
+            promotion_min_confidence: Some(0.7),
+            ..UserSettings::new(shanghai_user.as_str())
+        },
+    )
+    .unwrap();
+    assert_eq!(UserSettings::get(&mut conn, &shanghai_user).unwrap(), saved);
+
+    let event_time = chrono::DateTime::parse_from_rfc3339("2026-01-31T23:30:00Z")
+        .unwrap()
+        .with_timezone(&chrono::Utc);
+    let local_date = |conn: &mut PgConnection, user_id: &str| {
+        let settings = UserSettings::get(conn, user_id).unwrap();
+        settings
+            .timeline_zone(None)
+            .unwrap()
+            .local_date(event_time)
+            .to_string()
+    };
+    assert_eq!(local_date(&mut conn, &shanghai_user), "2026-02-01");
+    assert_eq!(local_date(&mut conn, &default_user), "2026-01-31");
+    assert_eq!(
+        TimelineZone::parse(None)
+            .unwrap()
+            .local_date(event_time)
+            .to_string(),
+        "2026-01-31"
+    );
+
+    // Saving again replaces every override; omitted ones fall back
+    UserSettings::update(
+        &mut conn,
+        &UserSettings {
+            locale: Some("zh-CN".to_string()),
+            ..UserSettings::new(shanghai_user.as_str())
+        },
+    )
+    .unwrap();
+    let settings = UserSettings::get(&mut conn, &shanghai_user).unwrap();
+    assert_eq!(settings.timezone, None);
+    assert_eq!(settings.locale.as_deref(), Some("zh-CN"));
+    assert_eq!(
+        settings.promotion_gate(&PromotionGateConfig::default()),
+        PromotionGateConfig::default()
+    );
+    assert_eq!(local_date(&mut conn, &shanghai_user), "2026-01-31");
+
+    // Invalid settings never reach the table
+    let invalid = UserSettings {
+        timezone: Some("Mars/Base".to_string()),
+        ..UserSettings::new(default_user.as_str())
+    };
+    assert!(UserSettings::update(&mut conn, &invalid).is_err());
+    assert_eq!(
+        UserSettings::get(&mut conn, &default_user).unwrap(),
+        UserSettings::new(default_user.as_str())
+    );
+
+    diesel::delete(user_settings::table.filter(user_settings::user_id.eq(&shanghai_user)))
+        .execute(&mut conn)
+        .unwrap();
+}
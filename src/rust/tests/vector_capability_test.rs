@@ -0,0 +1,112 @@
+//! Vector Capability Integration Tests
+//!
+//! Checks that the schema only needs pgvector for the vector column, and
+//! that an embedding generator disabled by a missing pgvector capability
+//! skips storage and semantic search instead of failing.
+//! Requires a migrated database in `DATABASE_URL`; the tests only run with
+//! `--ignored`.
+
+mod common;
+
+use std::sync::Arc;
+
+use diesel::prelude::*;
+use dirsoul::embedding::{
+    check_vector_capability, EmbeddingConfig, EmbeddingGenerator, VectorCapability,
+};
+use dirsoul::llm_provider::OllamaProvider;
+use dirsoul::models::*;
+use dirsoul::schema::raw_memories;
+use uuid::Uuid;
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_migrated_database_reports_capability() {
+    let mut conn = common::connect();
+
+    // The migrations create the extension and the embedding column only
+    // when the server offers pgvector
+    let pgvector_available: bool = diesel::select(diesel::dsl::sql::<diesel::sql_types::Bool>(
+        "EXISTS (SELECT 1 FROM pg_available_extensions WHERE name = 'vector')",
+    ))
+    .get_result(&mut conn)
+    .unwrap();
+    let capability = check_vector_capability(&mut conn).unwrap();
+    assert_eq!(
+        capability.is_available(),
+        pgvector_available,
+        "{:?}",
+        capability.missing_reason()
+    );
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_raw_memories_load_without_vector_column() {
+    let mut conn = common::connect();
+
+    // `RawMemory` never selects `embedding`, so it loads with or without pgvector
+    let user_id = format!("vector_capability_test_{}", Uuid::new_v4());
+    let memory_id: Uuid = diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.clone(),
+            ContentType::Text,
+            "今天吃了一个苹果".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(&mut conn)
+        .unwrap();
+    let memory = raw_memories::table
+        .find(memory_id)
+        .select(RawMemory::as_select())
+        .first(&mut conn)
+        .unwrap();
+    assert_eq!(memory.user_id, user_id);
+    assert!(memory.embedding.is_none());
+
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_disabled_generator_skips_vector_queries() {
+    let mut conn = common::connect();
+
+    let generator = EmbeddingGenerator::with_provider(
+        Arc::new(OllamaProvider::new("http://127.0.0.1:1", "nomic-embed-text")),
+        EmbeddingConfig::default(),
+    );
+    generator.apply_capability(&VectorCapability {
+        extension_installed: false,
+        embedding_column: false,
+    });
+    assert!(!generator.is_enabled());
+
+    let user_id = format!("vector_capability_test_{}", Uuid::new_v4());
+    let memory_id: Uuid = diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.clone(),
+            ContentType::Text,
+            "今天吃了一个苹果".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(&mut conn)
+        .unwrap();
+
+    generator.store_embedding(&mut conn, &user_id, memory_id, &[0.1, 0.2]).unwrap();
+    let model: Option<String> = raw_memories::table
+        .filter(raw_memories::memory_id.eq(memory_id))
+        .select(raw_memories::embedding_model)
+        .first(&mut conn)
+        .unwrap();
+    assert_eq!(model, None);
+
+    let matches = generator.search_raw_memories(&mut conn, &user_id, &[0.1, 0.2], 5).unwrap();
+    assert!(matches.is_empty());
+
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+}
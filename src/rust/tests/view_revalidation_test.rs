@@ -0,0 +1,316 @@
+//! View Re-validation Integration Tests
+//!
+//! Checks that `revalidate_active_views` scores active views against newly
+//! stored events, promoting (or flagging for approval) and rejecting them
+//! per the Promotion Gate.
+//! Requires a migrated database in `DATABASE_URL`; the tests only run with
+//! `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::cognitive::{
+    approve_view_promotion, list_views_ready_for_promotion, revalidate_active_views,
+    CognitiveView, NewCognitiveView, RevalidationConfig, ViewDefaults, ViewStatus,
+};
+use dirsoul::error::DirSoulError;
+use dirsoul::models::*;
+use dirsoul::schema::{
+    audit_logs, cognitive_views, event_memories, raw_memories, stable_concepts, user_settings,
+};
+use dirsoul::user_settings::UserSettings;
+use uuid::Uuid;
+
+fn seed_view(conn: &mut PgConnection, user_id: &str, confidence: f64) -> CognitiveView {
+    let mut new_view = NewCognitiveView::new_with_defaults(
+        user_id.to_string(),
+        "用户喜欢吃水果".to_string(),
+        "preference".to_string(),
+        (0..5).map(|_| Uuid::new_v4()).collect(),
+        &ViewDefaults::default().with_confidence(confidence).with_expiration_days(40),
+    );
+    // Observed for long enough to satisfy the gate's time span
+    new_view.created_at = chrono::Utc::now() - chrono::Duration::days(35);
+    new_view.expires_at = new_view.created_at + chrono::Duration::days(40);
+
+    diesel::insert_into(cognitive_views::table)
+        .values(&new_view)
+        .get_result(conn)
+        .unwrap()
+}
+
+fn seed_events(conn: &mut PgConnection, user_id: &str, events: &[(&str, &str)]) {
+    let memory_id: Uuid = diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.to_string(),
+            ContentType::Text,
+            "最近的饮食记录".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(conn)
+        .unwrap();
+
+    for (action, target) in events {
+        diesel::insert_into(event_memories::table)
+            .values(&NewEventMemory::new(
+                memory_id,
+                user_id.to_string(),
+                chrono::Utc::now(),
+                action.to_string(),
+                target.to_string(),
+            ))
+            .execute(conn)
+            .unwrap();
+    }
+}
+
+/// Audit modes ("auto"/"manual") recorded for the user's promotions
+fn promotion_audits(conn: &mut PgConnection, user_id: &str) -> Vec<String> {
+    let metadata: Vec<Option<serde_json::Value>> = audit_logs::table
+        .filter(audit_logs::user_id.eq(user_id))
+        .filter(audit_logs::action.eq("promote"))
+        .select(audit_logs::metadata)
+        .load(conn)
+        .unwrap();
+    metadata
+        .into_iter()
+        .filter_map(|m| m?.get("mode")?.as_str().map(str::to_string))
+        .collect()
+}
+
+fn cleanup(conn: &mut PgConnection, user_id: &str) {
+    diesel::delete(user_settings::table.filter(user_settings::user_id.eq(user_id)))
+        .execute(conn)
+        .unwrap();
+    diesel::delete(audit_logs::table.filter(audit_logs::user_id.eq(user_id)))
+        .execute(conn)
+        .unwrap();
+    diesel::delete(cognitive_views::table.filter(cognitive_views::user_id.eq(user_id)))
+        .execute(conn)
+        .unwrap();
+    diesel::delete(stable_concepts::table.filter(stable_concepts::user_id.eq(user_id)))
+        .execute(conn)
+        .unwrap();
+    diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(user_id)))
+        .execute(conn)
+        .unwrap();
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_supporting_events_promote_view() {
+    let mut conn = common::connect();
+
+    let user_id = format!("revalidation_test_{}", Uuid::new_v4());
+    let view = seed_view(&mut conn, &user_id, 0.7);
+    seed_events(&mut conn, &user_id, &[("吃", "水果"), ("吃", "水果"), ("吃", "水果"), ("吃", "蔬菜")]);
+
+    // Events below the confidence floor are skipped and stay unscanned
+    let floored = RevalidationConfig {
+        min_event_confidence: 0.9,
+        ..Default::default()
+    };
+    let report = revalidate_active_views(&mut conn, &user_id, &floored).unwrap();
+    assert_eq!(report.views_checked, 1);
+    assert_eq!(report.events_scanned, 0);
+    assert_eq!(report.supporting_events, 0);
+
+    let config = RevalidationConfig {
+        auto_promote: true,
+        ..Default::default()
+    };
+    let report = revalidate_active_views(&mut conn, &user_id, &config).unwrap();
+    assert_eq!(report.views_checked, 1);
+    assert_eq!(report.events_scanned, 4);
+    assert_eq!(report.supporting_events, 3);
+    assert_eq!(report.ready_for_promotion, vec![view.view_id]);
+    assert_eq!(report.promoted, vec![view.view_id]);
+
+    let stored: CognitiveView = cognitive_views::table
+        .filter(cognitive_views::view_id.eq(view.view_id))
+        .first(&mut conn)
+        .unwrap();
+    assert_eq!(stored.get_status(), ViewStatus::Promoted);
+    assert_eq!(stored.validation_count, 3);
+    let concept_id = stored.promoted_to.expect("promoted view links its concept");
+    let promoted_from: Option<Uuid> = stable_concepts::table
+        .filter(stable_concepts::concept_id.eq(concept_id))
+        .select(stable_concepts::promoted_from)
+        .first(&mut conn)
+        .unwrap();
+    assert_eq!(promoted_from, Some(view.view_id));
+    assert!(!stored.is_flagged_for_promotion());
+    assert_eq!(promotion_audits(&mut conn, &user_id), vec!["auto"]);
+
+    cleanup(&mut conn, &user_id);
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_manual_mode_only_flags_view() {
+    let mut conn = common::connect();
+
+    let user_id = format!("revalidation_test_{}", Uuid::new_v4());
+    let view = seed_view(&mut conn, &user_id, 0.7);
+    seed_events(&mut conn, &user_id, &[("吃", "水果"), ("吃", "水果"), ("吃", "水果")]);
+
+    let config = RevalidationConfig {
+        auto_promote: false,
+        ..Default::default()
+    };
+    let report = revalidate_active_views(&mut conn, &user_id, &config).unwrap();
+    assert_eq!(report.ready_for_promotion, vec![view.view_id]);
+    assert!(report.promoted.is_empty());
+
+    let ready = list_views_ready_for_promotion(&mut conn, &user_id).unwrap();
+    assert_eq!(ready.len(), 1);
+    assert_eq!(ready[0].view_id, view.view_id);
+    assert_eq!(ready[0].get_status(), ViewStatus::Active);
+    assert_eq!(ready[0].promoted_to, None);
+    let concepts: i64 = stable_concepts::table
+        .filter(stable_concepts::user_id.eq(&user_id))
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    assert_eq!(concepts, 0);
+    assert!(promotion_audits(&mut conn, &user_id).is_empty());
+
+    // Another user cannot approve it
+    let foreign = approve_view_promotion(&mut conn, "someone_else", view.view_id, &config.gate);
+    assert!(matches!(foreign, Err(DirSoulError::NotFound(_))));
+
+    // The user's own gate applies at approval time too
+    let strict = UserSettings {
+        promotion_min_validation_count: Some(10),
+        ..UserSettings::new(user_id.as_str())
+    };
+    UserSettings::update(&mut conn, &strict).unwrap();
+    let blocked = approve_view_promotion(&mut conn, &user_id, view.view_id, &config.gate);
+    assert!(matches!(blocked, Err(DirSoulError::Config(_))), "{:?}", blocked);
+    UserSettings::update(&mut conn, &UserSettings::new(user_id.as_str())).unwrap();
+
+    let promoted = approve_view_promotion(&mut conn, &user_id, view.view_id, &config.gate).unwrap();
+    assert_eq!(promoted.get_status(), ViewStatus::Promoted);
+    assert!(promoted.promoted_to.is_some());
+    assert!(!promoted.is_flagged_for_promotion());
+    assert!(list_views_ready_for_promotion(&mut conn, &user_id).unwrap().is_empty());
+    assert_eq!(promotion_audits(&mut conn, &user_id), vec!["manual"]);
+
+    // A view can only be approved once
+    assert!(approve_view_promotion(&mut conn, &user_id, view.view_id, &config.gate).is_err());
+
+    cleanup(&mut conn, &user_id);
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_contradicting_events_reject_view() {
+    let mut conn = common::connect();
+
+    let user_id = format!("revalidation_test_{}", Uuid::new_v4());
+    let view = seed_view(&mut conn, &user_id, 0.9);
+    seed_events(&mut conn, &user_id, &[("吃", "水果")]);
+
+    // The first run only records support
+    let config = RevalidationConfig::default();
+    let report = revalidate_active_views(&mut conn, &user_id, &config).unwrap();
+    assert_eq!(report.supporting_events, 1);
+    assert!(report.rejected.is_empty());
+
+    // Events already scanned are not read again
+    let report = revalidate_active_views(&mut conn, &user_id, &config).unwrap();
+    assert_eq!(report.events_scanned, 0);
+
+    seed_events(&mut conn, &user_id, &[("讨厌", "水果"), ("不吃", "水果")]);
+    let report = revalidate_active_views(&mut conn, &user_id, &config).unwrap();
+    assert_eq!(report.contradicting_events, 2);
+    assert_eq!(report.rejected, vec![view.view_id]);
+
+    let stored: CognitiveView = cognitive_views::table
+        .filter(cognitive_views::view_id.eq(view.view_id))
+        .first(&mut conn)
+        .unwrap();
+    assert_eq!(stored.get_status(), ViewStatus::Rejected);
+    assert_eq!(stored.counter_evidence_count, 2);
+
+    // Rejected views are no longer re-scored
+    let report = revalidate_active_views(&mut conn, &user_id, &config).unwrap();
+    assert_eq!(report.views_checked, 0);
+
+    cleanup(&mut conn, &user_id);
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_scan_follows_ingestion_order_across_pages() {
+    let mut conn = common::connect();
+
+    let user_id = format!("revalidation_test_{}", Uuid::new_v4());
+    seed_view(&mut conn, &user_id, 0.5);
+
+    // Backdated events from one memory share an ingestion time
+    let memory_id: Uuid = diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.clone(),
+            ContentType::Text,
+            "上个月的饮食记录".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(&mut conn)
+        .unwrap();
+    let backdated = chrono::Utc::now() - chrono::Duration::days(60);
+    for _ in 0..3 {
+        diesel::insert_into(event_memories::table)
+            .values(&NewEventMemory::new(
+                memory_id,
+                user_id.clone(),
+                backdated,
+                "吃".to_string(),
+                "水果".to_string(),
+            ))
+            .execute(&mut conn)
+            .unwrap();
+    }
+
+    let config = RevalidationConfig {
+        max_events_per_view: 1,
+        ..RevalidationConfig::default()
+    };
+    let mut supporting = 0;
+    for _ in 0..3 {
+        let report = revalidate_active_views(&mut conn, &user_id, &config).unwrap();
+        assert_eq!(report.events_scanned, 1);
+        supporting += report.supporting_events;
+    }
+    assert_eq!(supporting, 3);
+
+    let report = revalidate_active_views(&mut conn, &user_id, &config).unwrap();
+    assert_eq!(report.events_scanned, 0);
+
+    cleanup(&mut conn, &user_id);
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_user_gate_overrides_apply_to_revalidation() {
+    let mut conn = common::connect();
+
+    let user_id = format!("revalidation_test_{}", Uuid::new_v4());
+    seed_view(&mut conn, &user_id, 0.7);
+    seed_events(&mut conn, &user_id, &[("吃", "水果"), ("吃", "水果"), ("吃", "水果")]);
+    UserSettings::update(
+        &mut conn,
+        &UserSettings {
+            promotion_min_validation_count: Some(10),
+            ..UserSettings::new(user_id.as_str())
+        },
+    )
+    .unwrap();
+
+    // Three validations pass the global gate but not this user's
+    let report = revalidate_active_views(&mut conn, &user_id, &RevalidationConfig::default()).unwrap();
+    assert_eq!(report.supporting_events, 3);
+    assert!(report.ready_for_promotion.is_empty());
+
+    cleanup(&mut conn, &user_id);
+}
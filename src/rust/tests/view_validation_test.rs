@@ -0,0 +1,132 @@
+//! View Validation Integration Tests
+//!
+//! Checks that `validate_view` records the user's supporting events on
+//! their stored view until it passes the Promotion Gate, and refuses other
+//! users' views and events as well as events below the confidence floor.
+//! Requires a migrated database in `DATABASE_URL`; the test only runs with
+//! `--ignored`.
+
+mod common;
+
+use diesel::prelude::*;
+use dirsoul::cognitive::{
+    validate_view, validate_view_with, CognitiveView, ConfidenceBump, NewCognitiveView,
+    PromotionGateConfig, ViewDecision, ViewDefaults, ViewStatus,
+};
+use dirsoul::error::DirSoulError;
+use dirsoul::models::*;
+use dirsoul::schema::{cognitive_views, event_memories, raw_memories};
+use uuid::Uuid;
+
+/// Store a "喝 咖啡" event of `user_id` and return its id
+fn seed_event(conn: &mut PgConnection, user_id: &str) -> Uuid {
+    let memory_id: Uuid = diesel::insert_into(raw_memories::table)
+        .values(&NewRawMemory::new_plaintext(
+            user_id.to_string(),
+            ContentType::Text,
+            "今天喝了咖啡".to_string(),
+        ))
+        .returning(raw_memories::memory_id)
+        .get_result(conn)
+        .unwrap();
+    diesel::insert_into(event_memories::table)
+        .values(&NewEventMemory::new(
+            memory_id,
+            user_id.to_string(),
+            chrono::Utc::now(),
+            "喝".to_string(),
+            "咖啡".to_string(),
+        ))
+        .returning(event_memories::event_id)
+        .get_result(conn)
+        .unwrap()
+}
+
+#[test]
+#[ignore = "requires DATABASE_URL"]
+fn test_three_validations_reach_promotion_gate() {
+    let mut conn = common::connect();
+
+    let user_id = format!("validation_test_{}", Uuid::new_v4());
+    let mut new_view = NewCognitiveView::new_with_defaults(
+        user_id.clone(),
+        "用户每天喝咖啡".to_string(),
+        "habit".to_string(),
+        (0..5).map(|_| Uuid::new_v4()).collect(),
+        &ViewDefaults::default().with_confidence(0.7).with_expiration_days(40),
+    );
+    // Observed for long enough to satisfy the gate's time span
+    new_view.created_at = chrono::Utc::now() - chrono::Duration::days(35);
+    new_view.expires_at = new_view.created_at + chrono::Duration::days(40);
+
+    let view: CognitiveView = diesel::insert_into(cognitive_views::table)
+        .values(&new_view)
+        .get_result(&mut conn)
+        .unwrap();
+
+    let config = PromotionGateConfig::default();
+    let mut current = view.clone();
+    for _ in 0..3 {
+        assert_ne!(current.evaluate(&config), ViewDecision::Promote);
+        let event_id = seed_event(&mut conn, &user_id);
+        current = validate_view(&mut conn, &user_id, view.view_id, event_id).unwrap();
+    }
+
+    let stored: CognitiveView = cognitive_views::table
+        .filter(cognitive_views::view_id.eq(view.view_id))
+        .first(&mut conn)
+        .unwrap();
+    assert_eq!(stored.validation_count, 3);
+    assert_eq!(stored.evidence_count, 8);
+    assert!(stored.last_validated_at.is_some());
+    assert_eq!(stored.evaluate(&config), ViewDecision::Promote);
+
+    // Another user can neither validate the view nor lend it their events
+    let other_user = format!("validation_test_{}", Uuid::new_v4());
+    let own_event = seed_event(&mut conn, &user_id);
+    let foreign_event = seed_event(&mut conn, &other_user);
+    for (user, event_id) in [
+        (other_user.as_str(), own_event),
+        (user_id.as_str(), foreign_event),
+        (user_id.as_str(), Uuid::new_v4()),
+    ] {
+        let err = validate_view(&mut conn, user, view.view_id, event_id).unwrap_err();
+        assert!(matches!(err, DirSoulError::NotFound(_)), "{:?}", err);
+    }
+
+    // Events below the confidence floor are not evidence
+    let bump = ConfidenceBump::default();
+    let err = validate_view_with(&mut conn, &user_id, view.view_id, own_event, bump, 0.9)
+        .unwrap_err();
+    assert!(matches!(err, DirSoulError::Config(_)), "{:?}", err);
+
+    // Unreadable evidence fails instead of being wiped
+    diesel::update(cognitive_views::table.filter(cognitive_views::view_id.eq(view.view_id)))
+        .set(cognitive_views::derived_from.eq(serde_json::json!({"not": "a list"})))
+        .execute(&mut conn)
+        .unwrap();
+    assert!(validate_view(&mut conn, &user_id, view.view_id, own_event).is_err());
+    let derived_from: serde_json::Value = cognitive_views::table
+        .filter(cognitive_views::view_id.eq(view.view_id))
+        .select(cognitive_views::derived_from)
+        .first(&mut conn)
+        .unwrap();
+    assert_eq!(derived_from, serde_json::json!({"not": "a list"}));
+
+    // Views that are no longer active cannot be validated
+    diesel::update(cognitive_views::table.filter(cognitive_views::view_id.eq(view.view_id)))
+        .set(cognitive_views::status.eq(String::from(ViewStatus::Rejected)))
+        .execute(&mut conn)
+        .unwrap();
+    assert!(validate_view(&mut conn, &user_id, view.view_id, own_event).is_err());
+    assert!(validate_view(&mut conn, &user_id, Uuid::new_v4(), own_event).is_err());
+
+    diesel::delete(cognitive_views::table.filter(cognitive_views::user_id.eq(&user_id)))
+        .execute(&mut conn)
+        .unwrap();
+    for user in [&user_id, &other_user] {
+        diesel::delete(raw_memories::table.filter(raw_memories::user_id.eq(user)))
+            .execute(&mut conn)
+            .unwrap();
+    }
+}